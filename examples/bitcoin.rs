@@ -400,6 +400,7 @@ impl<F: Field> RecursiveCircuit<F> for BitcoinHeaderCircuit {
         cs: &mut CS,
         old_payload: &[AllocatedBit],
         new_payload: &[AllocatedBit],
+        _challenges: &[AllocatedNum<F>],
     ) -> Result<(), SynthesisError> {
         // Unpack the payloads for the previous and current blocks
         let (prev_height, prev_chain_work, prev_header_bits) =
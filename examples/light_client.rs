@@ -0,0 +1,726 @@
+//! A template for light clients: a chain of recursive proofs that follows a
+//! chain of headers, each signed by a quorum of the validators the header
+//! before it named, so that the latest proof attests to the latest header
+//! without its verifier replaying any of them.
+//!
+//! A header names its parent, the validators who sign the next header and
+//! the root of some application state. Validators sign the digest of a
+//! header with Schnorr signatures over `Ec1`, whose base field is `Fp`, so
+//! that the steps over `Fp` verify them without foreign field arithmetic.
+//!
+//! The payload carries the digest of the latest header, the digest of the
+//! validators it names and a step counter:
+//!
+//! | bytes  | contents                                    |
+//! |--------|---------------------------------------------|
+//! | 0..32  | the Rescue digest of the latest header      |
+//! | 32..64 | the Rescue digest of the next validators    |
+//! | 64..72 | the number of steps, as a little-endian u64 |
+//!
+//! The counter is the base case rule, so a prover cannot restart the chain
+//! from the genesis header to follow a fork signed by its validators.
+//!
+//! Writing this against the public API turned up the following gaps:
+//!
+//! - There is no signature gadget over the curves of the cycle, so
+//!   `verify_signature` is a minimal one. `ed25519::verify` costs about
+//!   twelve million gates, which no step could afford.
+//! - As in the rollup template, the headers and signatures are hashed with
+//!   Rescue over `Fp`, so only the steps over `Fp` can follow a header. The
+//!   steps over `Fq` carry the payload across, and each header costs two
+//!   proofs.
+//! - `unpack_fe` does not constrain its bits to the canonical encoding, so a
+//!   prover can choose among the few encodings of a challenge below `2^256`.
+//!   This widens a forger's odds by a small factor and no more.
+//! - Each hash here absorbs `LOST` first, for the reason the rollup template
+//!   gives.
+
+use halo::ecdh::alloc_scalar_bits;
+use halo::rescue::Rescue;
+use halo::{
+    dev, unpack_fe, AllocatedBit, AllocatedNum, BaseCaseRule, Basic, Circuit, Coeff, Combination,
+    ConstraintSystem, Curve, CurvePoint, Ec0, Ec1, Field, Fp, Fq, IvcState, Num, Params,
+    RecursiveCircuit, RescueGadget, SynthesisError,
+};
+use std::ops::Range;
+use std::time::Instant;
+
+const VALIDATORS: usize = 3;
+const THRESHOLD: usize = 2;
+
+/// Absorbed where Rescue's sponge loses its input, which is first in each
+/// block of twelve elements.
+const LOST: u64 = 0;
+
+/// The number of bits of a challenge.
+const CHALLENGE_BITS: usize = 128;
+
+const PAYLOAD_BYTES: usize = 72;
+const HEAD_BITS: Range<usize> = 0..256;
+const VALIDATORS_BITS: Range<usize> = 256..512;
+const STEP_BITS: Range<usize> = 512..576;
+
+/// The two field elements a 32-byte digest is absorbed as: its low and high
+/// sixteen bytes, each as a little-endian integer.
+fn halves<F: Field>(bytes: &[u8; 32]) -> [F; 2] {
+    let mut lo = [0; 16];
+    let mut hi = [0; 16];
+    lo.copy_from_slice(&bytes[..16]);
+    hi.copy_from_slice(&bytes[16..]);
+    [
+        F::from_u128(u128::from_le_bytes(lo)),
+        F::from_u128(u128::from_le_bytes(hi)),
+    ]
+}
+
+/// The coordinates of `point`, or (0, 0) for the identity, as `CurvePoint`
+/// represents it.
+fn coordinates(point: Ec1) -> [Fp; 2] {
+    let coords = point.get_xy();
+    if coords.is_some().into() {
+        let (x, y) = coords.unwrap();
+        [x, y]
+    } else {
+        [Fp::zero(), Fp::zero()]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Keypair {
+    sk: Fq,
+    pk: Ec1,
+}
+
+impl Keypair {
+    fn new(sk: u64) -> Self {
+        let sk = Fq::from_u64(sk);
+        Keypair {
+            sk,
+            pk: Ec1::one() * sk,
+        }
+    }
+
+    /// Signs `message` with a nonce derived from the key and the message.
+    fn sign(&self, message: &[u8; 32]) -> Signature {
+        let [lo, hi] = halves::<Fq>(message);
+        let nonce = Rescue::hash_to_field(&[Fq::from_u64(LOST), self.sk, lo, hi]);
+        let r = Ec1::one() * nonce;
+        Signature {
+            r,
+            s: nonce + challenge(r, self.pk, message) * self.sk,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Signature {
+    r: Ec1,
+    s: Fq,
+}
+
+impl Signature {
+    fn verify(&self, pk: Ec1, message: &[u8; 32]) -> bool {
+        Ec1::one() * self.s == self.r + pk * challenge(self.r, pk, message)
+    }
+}
+
+/// The challenge of a signature by `pk` on `message` with the nonce point
+/// `r`: the low `CHALLENGE_BITS` bits of a Rescue hash of all three.
+fn challenge(r: Ec1, pk: Ec1, message: &[u8; 32]) -> Fq {
+    let [rx, ry] = coordinates(r);
+    let [pkx, pky] = coordinates(pk);
+    let [lo, hi] = halves(message);
+    let hash = Rescue::hash_to_field(&[Fp::from_u64(LOST), rx, ry, pkx, pky, lo, hi]);
+    Fq::from_u128(hash.get_lower_128())
+}
+
+/// The digest of a validator set, absorbing `LOST` and then the coordinates
+/// of each key in turn.
+fn validators_digest(keys: &[Ec1; VALIDATORS]) -> [u8; 32] {
+    let mut rescue = Rescue::<Fp>::new();
+    rescue.absorb(Fp::from_u64(LOST));
+    for key in keys {
+        for coordinate in coordinates(*key).iter() {
+            rescue.absorb(*coordinate);
+        }
+    }
+    rescue.squeeze_digest()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Header {
+    parent: [u8; 32],
+    /// The digest of the validators who sign the next header.
+    validators: [u8; 32],
+    state_root: [u8; 32],
+}
+
+impl Header {
+    /// The digest validators sign, absorbing `LOST` and then the halves of
+    /// the parent, the validators and the state root.
+    fn digest(&self) -> [u8; 32] {
+        let mut rescue = Rescue::<Fp>::new();
+        rescue.absorb(Fp::from_u64(LOST));
+        for field in [self.parent, self.validators, self.state_root].iter() {
+            for half in halves(field).iter() {
+                rescue.absorb(*half);
+            }
+        }
+        rescue.squeeze_digest()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct LightClientPayload {
+    head: [u8; 32],
+    validators: [u8; 32],
+    steps: u64,
+}
+
+impl LightClientPayload {
+    /// The base payload of a chain that starts from `genesis`.
+    fn genesis(genesis: &Header) -> Self {
+        LightClientPayload {
+            head: genesis.digest(),
+            validators: genesis.validators,
+            steps: 0,
+        }
+    }
+
+    fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PAYLOAD_BYTES);
+        bytes.extend_from_slice(&self.head);
+        bytes.extend_from_slice(&self.validators);
+        bytes.extend_from_slice(&self.steps.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        assert_eq!(bytes.len(), PAYLOAD_BYTES);
+
+        let mut payload = LightClientPayload {
+            head: [0; 32],
+            validators: [0; 32],
+            steps: 0,
+        };
+        payload.head.copy_from_slice(&bytes[0..32]);
+        payload.validators.copy_from_slice(&bytes[32..64]);
+        let mut steps = [0; 8];
+        steps.copy_from_slice(&bytes[64..72]);
+        payload.steps = u64::from_le_bytes(steps);
+        payload
+    }
+
+    fn to_bits(self) -> Vec<bool> {
+        self.to_bytes()
+            .iter()
+            .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+            .collect()
+    }
+}
+
+/// A header, together with the validators who signed it and their
+/// signatures. A validator who did not sign has `None`.
+#[derive(Clone, Debug)]
+struct SignedHeader {
+    header: Header,
+    keys: [Ec1; VALIDATORS],
+    signatures: [Option<Signature>; VALIDATORS],
+}
+
+impl SignedHeader {
+    fn new(header: Header, signers: &[Keypair; VALIDATORS], signed: [bool; VALIDATORS]) -> Self {
+        let digest = header.digest();
+        let mut keys = [Ec1::zero(); VALIDATORS];
+        let mut signatures = [None; VALIDATORS];
+        for (i, signer) in signers.iter().enumerate() {
+            keys[i] = signer.pk;
+            if signed[i] {
+                signatures[i] = Some(signer.sign(&digest));
+            }
+        }
+        SignedHeader {
+            header,
+            keys,
+            signatures,
+        }
+    }
+
+    /// Whether a quorum of `validators` signed the header.
+    fn is_valid(&self, validators: &[u8; 32]) -> bool {
+        let digest = self.header.digest();
+        let signed = self
+            .keys
+            .iter()
+            .zip(self.signatures.iter())
+            .filter(|(key, signature)| signature.is_some_and(|s| s.verify(**key, &digest)))
+            .count();
+        validators_digest(&self.keys) == *validators && signed >= THRESHOLD
+    }
+}
+
+/// The step circuit of the light client. Steps over `Fp` follow `header`,
+/// which is `None` when only the shape of the circuit is needed, as it is to
+/// verify a proof or to carry the payload across a step over `Fq`.
+struct HeaderStep {
+    genesis: Header,
+    header: Option<SignedHeader>,
+}
+
+/// The little-endian value of `bits`. There may be more bits than the
+/// field holds, in which case the value wraps.
+fn pack<F: Field>(bits: &[AllocatedBit]) -> Combination<F> {
+    let mut acc = Combination::zero();
+    let mut coeff = F::one();
+    for bit in bits {
+        acc = acc + (Coeff::Full(coeff), AllocatedNum::from(bit.clone()));
+        coeff = coeff + coeff;
+    }
+    acc
+}
+
+fn enforce_equal<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: &Combination<F>,
+    b: &Combination<F>,
+) {
+    let difference = a.clone() + b.clone().scale(-F::one());
+    let lc = difference.lc(&mut *cs);
+    cs.enforce_zero(lc);
+}
+
+fn enforce_equal_bits<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: &[AllocatedBit],
+    b: &[AllocatedBit],
+) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        cs.enforce_zero(a.lc() - b.get_variable());
+    }
+}
+
+/// Allocates the 256 bits of `bytes`, ordered as payload bits are.
+fn alloc_digest<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    bytes: Option<[u8; 32]>,
+) -> Result<Vec<AllocatedBit>, SynthesisError> {
+    (0..256)
+        .map(|i| {
+            AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), || {
+                bytes
+                    .map(|bytes| (bytes[i / 8] >> (i % 8)) & 1 == 1)
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })
+        })
+        .collect()
+}
+
+/// Absorbs the 256 bits of a digest as `halves` splits it.
+fn absorb_digest<CS: ConstraintSystem<Fp>>(
+    mut cs: CS,
+    rescue: &mut RescueGadget<Fp>,
+    bits: &[AllocatedBit],
+) -> Result<(), SynthesisError> {
+    assert_eq!(bits.len(), 256);
+    for (i, half) in bits.chunks(128).enumerate() {
+        let half = pack(half).evaluate(cs.namespace(|| format!("pack half {}", i)))?;
+        rescue.absorb(cs.namespace(|| format!("absorb half {}", i)), half)?;
+    }
+    Ok(())
+}
+
+/// Constrains each step to count one more step than the last.
+fn enforce_next_step<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    old_payload: &[AllocatedBit],
+    new_payload: &[AllocatedBit],
+) {
+    let difference =
+        pack(&new_payload[STEP_BITS]) + pack::<F>(&old_payload[STEP_BITS]).scale(-F::one());
+    let lc = difference.lc(&mut *cs);
+    cs.enforce_zero(lc - CS::ONE);
+}
+
+fn lost() -> Num<Fp> {
+    Num::constant(Fp::from_u64(LOST))
+}
+
+/// Constrains `signature` to be a signature by `pk` on the digest `message`
+/// if `signed` is set.
+fn verify_signature<CS: ConstraintSystem<Fp>>(
+    mut cs: CS,
+    signed: &AllocatedBit,
+    pk: &CurvePoint<Ec1>,
+    signature: Option<Signature>,
+    message: &[AllocatedBit],
+) -> Result<(), SynthesisError> {
+    let r = CurvePoint::witness(cs.namespace(|| "r"), || {
+        signature
+            .map(|signature| signature.r)
+            .ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    let s = alloc_scalar_bits::<Ec1, _>(cs.namespace(|| "s"), signature.map(|s| s.s))?;
+
+    let mut rescue = RescueGadget::new(cs.namespace(|| "init challenge"))?;
+    rescue.absorb(cs.namespace(|| "absorb lost"), lost())?;
+    for (name, point) in [("r", &r), ("pk", pk)].iter() {
+        let (x, y) = point.get_xy();
+        rescue.absorb(cs.namespace(|| format!("absorb {} x", name)), x)?;
+        rescue.absorb(cs.namespace(|| format!("absorb {} y", name)), y)?;
+    }
+    absorb_digest(cs.namespace(|| "absorb message"), &mut rescue, message)?;
+    let challenge = rescue.squeeze(cs.namespace(|| "squeeze challenge"))?;
+    let mut challenge = unpack_fe(cs.namespace(|| "unpack challenge"), &challenge.into())?;
+    challenge.truncate(CHALLENGE_BITS);
+
+    let (gx, gy) = Ec1::one().get_xy().unwrap();
+    let lhs = CurvePoint::<Ec1>::constant(gx, gy).multiply(cs.namespace(|| "[s] G"), &s)?;
+    let rhs = pk.multiply(cs.namespace(|| "[c] pk"), &challenge)?;
+    let rhs = r.add(cs.namespace(|| "R + [c] pk"), &rhs)?;
+
+    // Neither side can have a y-coordinate of zero unless it is the identity,
+    // which is represented as (0, 0), so comparing coordinates suffices.
+    let (lhs_x, lhs_y) = lhs.get_xy();
+    let (rhs_x, rhs_y) = rhs.get_xy();
+    for (name, a, b) in [("x", lhs_x, rhs_x), ("y", lhs_y, rhs_y)].iter() {
+        let difference = Combination::from(*a) + (Coeff::NegativeOne, *b);
+        let product = Combination::from(AllocatedNum::from(signed.clone())).mul(
+            cs.namespace(|| format!("{} unless unsigned", name)),
+            &difference,
+        )?;
+        cs.enforce_zero(product.lc());
+    }
+
+    Ok(())
+}
+
+impl RecursiveCircuit<Fp> for HeaderStep {
+    fn base_payload(&self) -> Vec<bool> {
+        LightClientPayload::genesis(&self.genesis).to_bits()
+    }
+
+    fn base_case_rule(&self) -> BaseCaseRule {
+        BaseCaseRule::Counter(STEP_BITS)
+    }
+
+    fn synthesize<CS: ConstraintSystem<Fp>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        _challenges: &[AllocatedNum<Fp>],
+    ) -> Result<(), SynthesisError> {
+        assert_eq!(old_payload.len(), PAYLOAD_BYTES * 8);
+        assert_eq!(new_payload.len(), PAYLOAD_BYTES * 8);
+        enforce_next_step(cs, old_payload, new_payload);
+
+        let signed = self.header.as_ref();
+
+        // The signers must be the validators the parent named.
+        let mut keys = vec![];
+        let mut rescue = RescueGadget::new(cs.namespace(|| "init validators"))?;
+        rescue.absorb(cs.namespace(|| "absorb lost"), lost())?;
+        for i in 0..VALIDATORS {
+            let cs = &mut cs.namespace(|| format!("validator {}", i));
+            let key = CurvePoint::<Ec1>::witness(cs.namespace(|| "key"), || {
+                signed
+                    .map(|signed| signed.keys[i])
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let (x, y) = key.get_xy();
+            rescue.absorb(cs.namespace(|| "absorb x"), x)?;
+            rescue.absorb(cs.namespace(|| "absorb y"), y)?;
+            keys.push(key);
+        }
+        let validators = rescue.squeeze_digest(cs.namespace(|| "squeeze validators"))?;
+        enforce_equal_bits(cs, &validators, &old_payload[VALIDATORS_BITS]);
+
+        // The header must follow the old head, and name the new validators.
+        let state_root = alloc_digest(
+            cs.namespace(|| "state root"),
+            signed.map(|signed| signed.header.state_root),
+        )?;
+        let mut rescue = RescueGadget::new(cs.namespace(|| "init header"))?;
+        rescue.absorb(cs.namespace(|| "absorb lost"), lost())?;
+        absorb_digest(
+            cs.namespace(|| "absorb parent"),
+            &mut rescue,
+            &old_payload[HEAD_BITS],
+        )?;
+        absorb_digest(
+            cs.namespace(|| "absorb validators"),
+            &mut rescue,
+            &new_payload[VALIDATORS_BITS],
+        )?;
+        absorb_digest(
+            cs.namespace(|| "absorb state root"),
+            &mut rescue,
+            &state_root,
+        )?;
+        let head = rescue.squeeze_digest(cs.namespace(|| "squeeze header"))?;
+        enforce_equal_bits(cs, &head, &new_payload[HEAD_BITS]);
+
+        // A quorum of them must have signed it.
+        let mut count = Combination::<Fp>::zero();
+        for (i, key) in keys.iter().enumerate() {
+            let cs = &mut cs.namespace(|| format!("signature {}", i));
+            let signature = signed.map(|signed| signed.signatures[i]);
+            let is_signed = AllocatedBit::alloc(cs.namespace(|| "signed"), || {
+                signature
+                    .map(|signature| signature.is_some())
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+
+            // A missing signature is replaced with a placeholder, whose
+            // equation is synthesized but not enforced.
+            let placeholder = Signature {
+                r: Ec1::zero(),
+                s: Fq::zero(),
+            };
+            verify_signature(
+                cs.namespace(|| "verify"),
+                &is_signed,
+                key,
+                signature.map(|signature| signature.unwrap_or(placeholder)),
+                &new_payload[HEAD_BITS],
+            )?;
+            count += AllocatedNum::from(is_signed);
+        }
+
+        // The count minus the threshold fits in two bits, which it does for
+        // any count from the threshold up to `VALIDATORS`.
+        let surplus = count.get_value().map(|count| count.get_lower_128() as u64);
+        let surplus = surplus.map(|surplus| surplus.wrapping_sub(THRESHOLD as u64));
+        let bits = (0..2)
+            .map(|i| {
+                AllocatedBit::alloc(cs.namespace(|| format!("surplus bit {}", i)), || {
+                    surplus
+                        .map(|surplus| (surplus >> i) & 1 == 1)
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        enforce_equal(
+            cs,
+            &(count + Num::constant(-Fp::from_u64(THRESHOLD as u64))),
+            &pack(&bits),
+        );
+
+        Ok(())
+    }
+}
+
+impl RecursiveCircuit<Fq> for HeaderStep {
+    fn base_payload(&self) -> Vec<bool> {
+        LightClientPayload::genesis(&self.genesis).to_bits()
+    }
+
+    fn base_case_rule(&self) -> BaseCaseRule {
+        BaseCaseRule::Counter(STEP_BITS)
+    }
+
+    fn synthesize<CS: ConstraintSystem<Fq>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        _challenges: &[AllocatedNum<Fq>],
+    ) -> Result<(), SynthesisError> {
+        assert_eq!(old_payload.len(), PAYLOAD_BYTES * 8);
+        assert_eq!(new_payload.len(), PAYLOAD_BYTES * 8);
+        enforce_next_step(cs, old_payload, new_payload);
+        enforce_equal_bits(
+            cs,
+            &old_payload[..STEP_BITS.start],
+            &new_payload[..STEP_BITS.start],
+        );
+
+        Ok(())
+    }
+}
+
+/// The header logic of a step over `Fp` on its own, without the
+/// verification circuit around it, so that it can be checked quickly.
+struct HeaderCheck<'a> {
+    step: &'a HeaderStep,
+    old: LightClientPayload,
+    new: LightClientPayload,
+}
+
+impl<'a> Circuit<Fp> for HeaderCheck<'a> {
+    fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut alloc_payload = |name: &str, payload: &LightClientPayload| {
+            payload
+                .to_bits()
+                .into_iter()
+                .enumerate()
+                .map(|(i, bit)| {
+                    AllocatedBit::alloc(cs.namespace(|| format!("{} bit {}", name, i)), || Ok(bit))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let old = alloc_payload("old", &self.old)?;
+        let new = alloc_payload("new", &self.new)?;
+
+        RecursiveCircuit::<Fp>::synthesize(self.step, cs, &old, &new, &[])
+    }
+}
+
+fn main() {
+    let keypairs = |first: u64| {
+        let mut keypairs = [Keypair::new(first); VALIDATORS];
+        for (i, keypair) in keypairs.iter_mut().enumerate() {
+            *keypair = Keypair::new(first + i as u64);
+        }
+        keypairs
+    };
+    let genesis_set = keypairs(0x1000);
+    // The second header hands over to a set with one validator replaced.
+    let mut next_set = genesis_set;
+    next_set[1] = Keypair::new(0x2000);
+    let digest = |set: &[Keypair; VALIDATORS]| {
+        let mut keys = [Ec1::zero(); VALIDATORS];
+        for (key, keypair) in keys.iter_mut().zip(set.iter()) {
+            *key = keypair.pk;
+        }
+        validators_digest(&keys)
+    };
+
+    let genesis = Header {
+        parent: [0; 32],
+        validators: digest(&genesis_set),
+        state_root: [0; 32],
+    };
+    let outsider = Keypair::new(0x3000);
+
+    println!("checking headers");
+    let start = Instant::now();
+    let mut steps = vec![];
+    let mut old = LightClientPayload::genesis(&genesis);
+    let mut signers = genesis_set;
+    for (i, next) in [genesis_set, next_set, next_set].iter().enumerate() {
+        let header = Header {
+            parent: old.head,
+            validators: digest(next),
+            state_root: [i as u8 + 1; 32],
+        };
+        let signed = [i != 0, true, i != 2];
+        let signed_header = SignedHeader::new(header, &signers, signed);
+        assert!(signed_header.is_valid(&old.validators));
+
+        let new = LightClientPayload {
+            head: header.digest(),
+            validators: header.validators,
+            steps: old.steps + 1,
+        };
+        let check = |signed_header: &SignedHeader, new: LightClientPayload| {
+            let step = HeaderStep {
+                genesis,
+                header: Some(signed_header.clone()),
+            };
+            dev::is_satisfied::<_, _, Basic>(
+                &HeaderCheck {
+                    step: &step,
+                    old,
+                    new,
+                },
+                &[],
+            )
+            .is_ok()
+        };
+        assert!(check(&signed_header, new));
+        assert!(!check(
+            &signed_header,
+            LightClientPayload {
+                head: genesis.digest(),
+                ..new
+            }
+        ));
+        assert!(!check(
+            &signed_header,
+            LightClientPayload {
+                validators: [0; 32],
+                ..new
+            }
+        ));
+        assert!(!check(
+            &signed_header,
+            LightClientPayload { steps: 0, ..new }
+        ));
+
+        // Too few signatures, a signature by a key outside the set, or a
+        // signature on another header.
+        let mut impostors = signers;
+        impostors[0] = outsider;
+        for forged in [
+            SignedHeader::new(header, &signers, [false, true, false]),
+            SignedHeader::new(header, &impostors, [true, true, false]),
+            SignedHeader {
+                header,
+                ..SignedHeader::new(genesis, &signers, [true, true, true])
+            },
+        ]
+        .iter()
+        {
+            assert!(!forged.is_valid(&old.validators));
+            assert!(!check(forged, new));
+        }
+
+        steps.push((
+            HeaderStep {
+                genesis,
+                header: Some(signed_header),
+            },
+            new,
+        ));
+        old = LightClientPayload {
+            steps: new.steps + 1,
+            ..new
+        };
+        signers = *next;
+    }
+    println!("done, took {:?}", start.elapsed());
+
+    println!("making parameters");
+    let start = Instant::now();
+    let params0: Params<Ec0> = Params::new(22);
+    let params1: Params<Ec1> = Params::new(22);
+    println!("done, took {:?}", start.elapsed());
+
+    let shape = HeaderStep {
+        genesis,
+        header: None,
+    };
+    let headers = steps.len();
+    let mut steps = steps.into_iter();
+
+    println!("proving header 0");
+    let start = Instant::now();
+    let (step, payload) = steps.next().unwrap();
+    let mut state: IvcState<Ec0, Ec1> =
+        IvcState::start(&params0, &params1, &step, &payload.to_bytes()).unwrap();
+    assert!(state.proof().verify(&params0, &params1, &shape).unwrap());
+    println!("done, took {:?}", start.elapsed());
+
+    for (i, (step, payload)) in steps.enumerate() {
+        println!("proving header {}", i + 1);
+        let start = Instant::now();
+        let carried = LightClientPayload {
+            steps: payload.steps - 1,
+            ..LightClientPayload::from_bytes(state.payload())
+        };
+        let carrier = state
+            .advance(&params0, &params1, &shape, &carried.to_bytes())
+            .unwrap();
+        state = carrier
+            .advance(&params1, &params0, &step, &payload.to_bytes())
+            .unwrap();
+        assert!(state.proof().verify(&params0, &params1, &shape).unwrap());
+        println!("done, took {:?}", start.elapsed());
+    }
+
+    let latest = LightClientPayload::from_bytes(state.payload());
+    assert_eq!(latest.validators, digest(&next_set));
+    println!("{} headers in {} steps", headers, latest.steps);
+}
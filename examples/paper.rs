@@ -26,6 +26,7 @@ impl<F: Field> RecursiveCircuit<F> for MyCircuit {
         cs: &mut CS,
         old_payload: &[AllocatedBit],
         new_payload: &[AllocatedBit],
+        _challenges: &[AllocatedNum<F>],
     ) -> Result<(), SynthesisError> {
         assert_eq!(old_payload.len(), 8);
         assert_eq!(new_payload.len(), 8);
@@ -0,0 +1,164 @@
+//! Derive macros for the `halo` crate.
+//!
+//! `#[derive(Payload)]` implements `halo::Payload` for a struct with named
+//! fields that are all `halo::PayloadField`s, such as integers, field
+//! elements and byte arrays. Each field becomes a payload channel of the same
+//! name, in declaration order:
+//!
+//! ```ignore
+//! #[derive(Payload)]
+//! struct State {
+//!     counter: u32,
+//!     root: [u8; 32],
+//! }
+//!
+//! // In the inner circuit:
+//! let layout = State::layout();
+//! let counter = PayloadChannels::new(&layout, new_payload).get("counter");
+//! ```
+//!
+//! This crate has no dependencies, so the struct is parsed by hand; generic
+//! structs, tuple structs and enums are rejected.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+#[proc_macro_derive(Payload)]
+pub fn derive_payload(input: TokenStream) -> TokenStream {
+    let generated = match parse_struct(input) {
+        Ok((name, fields)) => expand(&name, &fields),
+        Err(msg) => format!("compile_error!({:?});", msg),
+    };
+    generated.parse().unwrap()
+}
+
+// The name of the struct and the name and type of each field.
+fn parse_struct(input: TokenStream) -> Result<(String, Vec<(String, String)>), String> {
+    let mut tokens = input.into_iter().peekable();
+
+    // Skip attributes and visibility up to the `struct` keyword
+    loop {
+        match tokens.next() {
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "struct" => break,
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "enum" => {
+                return Err("Payload can only be derived for structs".to_string());
+            }
+            Some(_) => (),
+            None => return Err("expected a struct".to_string()),
+        }
+    }
+
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected a struct name".to_string()),
+    };
+
+    let body = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group.stream(),
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            return Err("Payload cannot be derived for generic structs".to_string());
+        }
+        _ => return Err("Payload can only be derived for structs with named fields".to_string()),
+    };
+
+    Ok((name, parse_fields(body)?))
+}
+
+fn parse_fields(body: TokenStream) -> Result<Vec<(String, String)>, String> {
+    // Split the body at top-level commas. Commas within a type's angle
+    // brackets are not in a group, so track their depth.
+    let mut fields = vec![];
+    let mut current: Vec<TokenTree> = vec![];
+    let mut depth = 0;
+    for token in body {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                ',' if depth == 0 => {
+                    fields.push(parse_field(std::mem::take(&mut current))?);
+                    continue;
+                }
+                _ => (),
+            }
+        }
+        current.push(token);
+    }
+    if !current.is_empty() {
+        fields.push(parse_field(current)?);
+    }
+
+    Ok(fields)
+}
+
+// Parses `#[attrs] vis name: Type`.
+fn parse_field(tokens: Vec<TokenTree>) -> Result<(String, String), String> {
+    let colon = tokens
+        .iter()
+        .position(|t| match t {
+            TokenTree::Punct(punct) => punct.as_char() == ':',
+            _ => false,
+        })
+        .ok_or_else(|| "expected `name: Type`".to_string())?;
+
+    let name = match colon.checked_sub(1).map(|i| &tokens[i]) {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected a field name".to_string()),
+    };
+    let ty: TokenStream = tokens[colon + 1..].iter().cloned().collect();
+
+    Ok((name, ty.to_string()))
+}
+
+fn expand(name: &str, fields: &[(String, String)]) -> String {
+    let mut layout = String::new();
+    let mut encode = String::new();
+    let mut decode = String::new();
+    let mut construct = String::new();
+    for (field, ty) in fields {
+        let len = format!("<{} as ::halo::PayloadField>::LEN", ty);
+        layout += &format!(".channel({:?}, {})", field, len);
+        encode += &format!(
+            "::halo::PayloadField::encode_into(&self.{}, &mut out);",
+            field
+        );
+        decode += &format!(
+            "let {field} = <{ty} as ::halo::PayloadField>::decode(&payload[offset..offset + {len}])?;
+             offset += {len};",
+            field = field,
+            ty = ty,
+            len = len,
+        );
+        construct += &format!("{},", field);
+    }
+
+    format!(
+        "impl ::halo::Payload for {name} {{
+            fn layout() -> ::halo::PayloadLayout {{
+                ::halo::PayloadLayout::new(){layout}
+            }}
+
+            fn encode(&self) -> ::std::vec::Vec<u8> {{
+                let mut out = ::std::vec::Vec::new();
+                {encode}
+                out
+            }}
+
+            #[allow(unused_mut, unused_variables, unused_assignments)]
+            fn decode(payload: &[u8]) -> ::std::option::Option<Self> {{
+                if payload.len() != <Self as ::halo::Payload>::layout().len() {{
+                    return ::std::option::Option::None;
+                }}
+                let mut offset = 0;
+                {decode}
+                ::std::option::Option::Some({name} {{ {construct} }})
+            }}
+        }}",
+        name = name,
+        layout = layout,
+        encode = encode,
+        decode = decode,
+        construct = construct,
+    )
+}
@@ -0,0 +1,92 @@
+use halo::*;
+use halo_derive::Payload;
+
+#[derive(Debug, PartialEq, Payload)]
+struct State {
+    counter: u32,
+    /// The root of the state tree
+    pub root: [u8; 4],
+    pub(crate) value: Fp,
+    flags: u8,
+}
+
+#[derive(Debug, PartialEq, Payload)]
+struct Empty {}
+
+fn state() -> State {
+    State {
+        counter: 0x01020304,
+        root: [9, 8, 7, 6],
+        value: Fp::from_u64(5),
+        flags: 0xff,
+    }
+}
+
+#[test]
+fn test_layout() {
+    let layout = State::layout();
+    assert_eq!(
+        layout.names().collect::<Vec<_>>(),
+        vec!["counter", "root", "value", "flags"]
+    );
+    assert_eq!(layout.range("value"), Some(8..40));
+    assert_eq!(layout.len(), 41);
+    assert!(Empty::layout().is_empty());
+}
+
+#[test]
+fn test_encode_decode() {
+    let payload = state().encode();
+    assert_eq!(&payload[..8], &[4, 3, 2, 1, 9, 8, 7, 6]);
+    assert_eq!(
+        State::layout().get(&payload, "value"),
+        &Fp::from_u64(5).to_bytes()[..]
+    );
+    assert_eq!(State::decode(&payload), Some(state()));
+
+    // Wrong lengths and non-canonical field elements are rejected.
+    assert_eq!(State::decode(&payload[1..]), None);
+    let mut bad = payload.clone();
+    State::layout().set(&mut bad, "value", &[0xff; 32]);
+    assert_eq!(State::decode(&bad), None);
+
+    assert_eq!(Empty::decode(&Empty {}.encode()), Some(Empty {}));
+}
+
+#[test]
+fn test_base() {
+    assert_eq!(
+        State::base(),
+        State {
+            counter: 0,
+            root: [0; 4],
+            value: Fp::zero(),
+            flags: 0,
+        }
+    );
+    assert_eq!(Empty::base(), Empty {});
+}
+
+struct AllocCircuit;
+
+impl Circuit<Fp> for AllocCircuit {
+    fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let bits = State::alloc(cs.namespace(|| "state"), Some(&state()))?;
+        let layout = State::layout();
+        let flags = PayloadChannels::new(&layout, &bits).get("flags");
+        assert!(flags.iter().all(|bit| bit.get_value() == Some(true)));
+        let counter = PayloadChannels::new(&layout, &bits).get("counter");
+        assert_eq!(counter[2].get_value(), Some(true));
+        assert_eq!(counter[3].get_value(), Some(false));
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_alloc() {
+    assert_eq!(
+        dev::is_satisfied::<_, _, Basic>(&AllocCircuit, &[]),
+        Ok(true)
+    );
+}
@@ -0,0 +1,272 @@
+//! Circuits assembled at runtime, which is how the bindings build them from
+//! Python.
+//!
+//! A [`Program`] allocates public inputs and witnesses and collects
+//! equalities between [`Expr`]s over them. Synthesizing it allocates every
+//! input and then every witness, in order, and lowers each equality with
+//! `halo::expr::enforce_equal`, so it costs exactly the gates of the same
+//! expressions written in a Rust circuit: subexpressions shared within an
+//! equality are lowered once, but those shared between equalities are
+//! lowered in each of them.
+
+use halo::expr;
+use halo::{AllocatedNum, Circuit, ConstraintSystem, Fq, SynthesisError};
+use std::collections::{HashMap, HashSet};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+static NEXT_PROGRAM: AtomicUsize = AtomicUsize::new(0);
+
+/// An arithmetic expression over the inputs and witnesses of a [`Program`].
+/// Cloning an `Expr` shares it rather than copying it.
+#[derive(Clone, Debug)]
+pub struct Expr(Arc<Node>);
+
+#[derive(Debug)]
+enum Node {
+    Constant(Fq),
+    Input { program: usize, index: usize },
+    Witness { program: usize, index: usize },
+    Add(Expr, Expr),
+    Sub(Expr, Expr),
+    Mul(Expr, Expr),
+    Neg(Expr),
+}
+
+impl Expr {
+    /// A constant.
+    pub fn constant(value: Fq) -> Self {
+        Expr(Arc::new(Node::Constant(value)))
+    }
+
+    fn key(&self) -> *const Node {
+        Arc::as_ptr(&self.0)
+    }
+}
+
+impl Add for Expr {
+    type Output = Expr;
+
+    fn add(self, other: Expr) -> Expr {
+        Expr(Arc::new(Node::Add(self, other)))
+    }
+}
+
+impl Sub for Expr {
+    type Output = Expr;
+
+    fn sub(self, other: Expr) -> Expr {
+        Expr(Arc::new(Node::Sub(self, other)))
+    }
+}
+
+impl Mul for Expr {
+    type Output = Expr;
+
+    fn mul(self, other: Expr) -> Expr {
+        Expr(Arc::new(Node::Mul(self, other)))
+    }
+}
+
+impl Neg for Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        Expr(Arc::new(Node::Neg(self)))
+    }
+}
+
+/// A circuit over the scalar field of `Ec1`, built at runtime. It carries no
+/// values; [`Program::assign`] supplies them for proving.
+#[derive(Debug)]
+pub struct Program {
+    id: usize,
+    inputs: usize,
+    witnesses: usize,
+    equalities: Vec<(Expr, Expr)>,
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Program::new()
+    }
+}
+
+impl Program {
+    /// Creates a program with no variables or constraints.
+    pub fn new() -> Self {
+        Program {
+            id: NEXT_PROGRAM.fetch_add(1, Ordering::Relaxed),
+            inputs: 0,
+            witnesses: 0,
+            equalities: vec![],
+        }
+    }
+
+    /// Allocates the next public input.
+    pub fn input(&mut self) -> Expr {
+        self.inputs += 1;
+        Expr(Arc::new(Node::Input {
+            program: self.id,
+            index: self.inputs - 1,
+        }))
+    }
+
+    /// Allocates the next witness.
+    pub fn witness(&mut self) -> Expr {
+        self.witnesses += 1;
+        Expr(Arc::new(Node::Witness {
+            program: self.id,
+            index: self.witnesses - 1,
+        }))
+    }
+
+    /// The number of public inputs allocated so far.
+    pub fn num_inputs(&self) -> usize {
+        self.inputs
+    }
+
+    /// The number of witnesses allocated so far.
+    pub fn num_witnesses(&self) -> usize {
+        self.witnesses
+    }
+
+    /// Constrains `a` and `b` to be equal. Returns `false`, and constrains
+    /// nothing, if either refers to a variable of another program.
+    pub fn enforce_equal(&mut self, a: Expr, b: Expr) -> bool {
+        if !self.owns(&a) || !self.owns(&b) {
+            return false;
+        }
+        self.equalities.push((a, b));
+        true
+    }
+
+    fn owns(&self, expr: &Expr) -> bool {
+        let mut seen = HashSet::new();
+        let mut stack = vec![expr];
+        while let Some(expr) = stack.pop() {
+            if !seen.insert(expr.key()) {
+                continue;
+            }
+            match &*expr.0 {
+                Node::Constant(_) => {}
+                Node::Input { program, .. } | Node::Witness { program, .. } => {
+                    if *program != self.id {
+                        return false;
+                    }
+                }
+                Node::Add(a, b) | Node::Sub(a, b) | Node::Mul(a, b) => {
+                    stack.push(a);
+                    stack.push(b);
+                }
+                Node::Neg(a) => stack.push(a),
+            }
+        }
+        true
+    }
+
+    /// Assigns values to the program's inputs and witnesses, for proving or
+    /// checking satisfaction. Returns `None` if there are not exactly as many
+    /// of each as the program has allocated.
+    pub fn assign<'a>(&'a self, inputs: &'a [Fq], witnesses: &'a [Fq]) -> Option<Assigned<'a>> {
+        if inputs.len() != self.inputs || witnesses.len() != self.witnesses {
+            return None;
+        }
+
+        Some(Assigned {
+            program: self,
+            inputs,
+            witnesses,
+        })
+    }
+
+    fn synthesize_with<CS: ConstraintSystem<Fq>>(
+        &self,
+        cs: &mut CS,
+        values: Option<(&[Fq], &[Fq])>,
+    ) -> Result<(), SynthesisError> {
+        let mut inputs = Vec::with_capacity(self.inputs);
+        for i in 0..self.inputs {
+            let num = AllocatedNum::alloc_input(cs.namespace(|| format!("input {}", i)), || {
+                values
+                    .map(|(inputs, _)| inputs[i])
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            inputs.push(expr::var(num));
+        }
+
+        let mut witnesses = Vec::with_capacity(self.witnesses);
+        for i in 0..self.witnesses {
+            let num = AllocatedNum::alloc(cs.namespace(|| format!("witness {}", i)), || {
+                values
+                    .map(|(_, witnesses)| witnesses[i])
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            witnesses.push(expr::var(num));
+        }
+
+        let mut lowering = Lowering {
+            inputs,
+            witnesses,
+            lowered: HashMap::new(),
+        };
+        for (i, (a, b)) in self.equalities.iter().enumerate() {
+            let a = lowering.lower(a);
+            let b = lowering.lower(b);
+            expr::enforce_equal(cs.namespace(|| format!("equality {}", i)), &a, &b)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Circuit<Fq> for Program {
+    fn synthesize<CS: ConstraintSystem<Fq>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        self.synthesize_with(cs, None)
+    }
+}
+
+/// A [`Program`] along with values for its inputs and witnesses.
+#[derive(Clone, Copy, Debug)]
+pub struct Assigned<'a> {
+    program: &'a Program,
+    inputs: &'a [Fq],
+    witnesses: &'a [Fq],
+}
+
+impl<'a> Circuit<Fq> for Assigned<'a> {
+    fn synthesize<CS: ConstraintSystem<Fq>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        self.program
+            .synthesize_with(cs, Some((self.inputs, self.witnesses)))
+    }
+}
+
+/// Translates expressions into `halo::expr` expressions over the allocated
+/// variables, preserving which subexpressions are shared.
+struct Lowering {
+    inputs: Vec<expr::Expr<Fq>>,
+    witnesses: Vec<expr::Expr<Fq>>,
+    lowered: HashMap<*const Node, expr::Expr<Fq>>,
+}
+
+impl Lowering {
+    fn lower(&mut self, e: &Expr) -> expr::Expr<Fq> {
+        if let Some(lowered) = self.lowered.get(&e.key()) {
+            return lowered.clone();
+        }
+
+        let lowered = match &*e.0 {
+            Node::Constant(c) => expr::constant(*c),
+            Node::Input { index, .. } => self.inputs[*index].clone(),
+            Node::Witness { index, .. } => self.witnesses[*index].clone(),
+            Node::Add(a, b) => self.lower(a) + self.lower(b),
+            Node::Sub(a, b) => self.lower(a) - self.lower(b),
+            Node::Mul(a, b) => self.lower(a) * self.lower(b),
+            Node::Neg(a) => -self.lower(a),
+        };
+
+        self.lowered.insert(e.key(), lowered.clone());
+        lowered
+    }
+}
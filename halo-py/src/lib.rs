@@ -0,0 +1,311 @@
+//! Python bindings for the `halo` crate, for scripting experiments without
+//! writing a Rust harness for each one.
+//!
+//! The `halo_py` module proves plain, non-recursive statements about circuits
+//! over the scalar field of `Ec1`, which Python builds at runtime from
+//! expressions:
+//!
+//! ```python
+//! import halo_py
+//!
+//! params = halo_py.Params(8)
+//! circuit = halo_py.Circuit()
+//! x = circuit.witness()
+//! y = circuit.input()
+//! circuit.enforce_equal(x * x * x + 5, y)
+//!
+//! proof = halo_py.prove(params, circuit, [32], [3])
+//! assert halo_py.verify(params, circuit, proof, [32])
+//! print(circuit.gates())
+//! ```
+//!
+//! Values are Python integers, reduced modulo `halo_py.MODULUS`. Proofs are
+//! the bytes of their binary encoding. Proving and parameter generation
+//! release the GIL.
+//!
+//! Recursion is not exposed: a `RecursiveCircuit` has to be synthesized over
+//! both fields of the cycle, so its step logic is still written in Rust.
+
+pub mod circuit;
+
+use circuit::{Expr, Program};
+use halo::dev::SatisfactionError;
+use halo::{plain, Basic, Ec1, Field, Fq, Params, Proof, SynthesisError};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyInt};
+
+/// Proves that `program` is satisfied by the given values, returning the
+/// encoding of the proof.
+pub fn prove(
+    params: &Params<Ec1>,
+    program: &Program,
+    inputs: &[Fq],
+    witnesses: &[Fq],
+) -> Result<Vec<u8>, SynthesisError> {
+    let assigned = program
+        .assign(inputs, witnesses)
+        .ok_or(SynthesisError::AssignmentMissing)?;
+    let proof = plain::prove(params, &assigned)?;
+
+    let mut bytes = vec![];
+    proof
+        .write(&mut bytes)
+        .expect("writing to a Vec does not fail");
+    Ok(bytes)
+}
+
+/// Verifies the encoding of a proof created by `prove` against the
+/// program's public `inputs`. Returns `false` if it cannot be decoded.
+pub fn verify(
+    params: &Params<Ec1>,
+    program: &Program,
+    proof: &[u8],
+    inputs: &[Fq],
+) -> Result<bool, SynthesisError> {
+    let mut reader = proof;
+    let proof = match Proof::<Ec1>::read(&mut reader) {
+        Ok(proof) if reader.is_empty() => proof,
+        _ => return Ok(false),
+    };
+    if inputs.len() != program.num_inputs() {
+        return Ok(false);
+    }
+
+    plain::verify(params, program, &proof, inputs)
+}
+
+fn synthesis_error(e: SynthesisError) -> PyErr {
+    PyValueError::new_err(format!("synthesis failed: {:?}", e))
+}
+
+/// The modulus of the field, as a Python integer.
+fn modulus(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+    let minus_one = PyBytes::new(py, &(-Fq::one()).to_bytes());
+    py.get_type::<PyInt>()
+        .call_method1("from_bytes", (minus_one, "little"))?
+        .add(1)
+}
+
+fn to_field(value: &Bound<'_, PyAny>) -> PyResult<Fq> {
+    let reduced = value.rem(modulus(value.py())?)?;
+    let bytes: [u8; 32] = reduced
+        .call_method1("to_bytes", (32, "little"))?
+        .extract()?;
+    Ok(Fq::from_bytes(&bytes).unwrap())
+}
+
+fn to_fields(values: &[Bound<'_, PyAny>]) -> PyResult<Vec<Fq>> {
+    values.iter().map(to_field).collect()
+}
+
+/// Public parameters for proofs over `Ec1`, supporting circuits of up to
+/// 2^(k-2) multiplication gates.
+#[pyclass(name = "Params", frozen)]
+struct PyParams(Params<Ec1>);
+
+#[pymethods]
+impl PyParams {
+    /// Generates parameters, derived from `seed` if one is given so that they
+    /// can be regenerated, or randomly otherwise.
+    #[new]
+    #[pyo3(signature = (k, seed=None))]
+    fn new(py: Python<'_>, k: usize, seed: Option<Vec<u8>>) -> PyResult<Self> {
+        if k < 4 {
+            return Err(PyValueError::new_err("k must be at least 4"));
+        }
+        Ok(PyParams(py.allow_threads(|| match seed {
+            Some(seed) => Params::from_seed(k, &seed),
+            None => Params::new(k),
+        })))
+    }
+
+    #[getter]
+    fn k(&self) -> usize {
+        self.0.k()
+    }
+}
+
+/// An arithmetic expression over the inputs and witnesses of a circuit,
+/// combined with `+`, `-`, `*`, unary `-` and `**` by a non-negative integer,
+/// and with integers as constants.
+#[pyclass(name = "Expr", frozen)]
+#[derive(Clone)]
+struct PyExpr(Expr);
+
+fn to_expr(value: &Bound<'_, PyAny>) -> PyResult<Expr> {
+    match value.downcast::<PyExpr>() {
+        Ok(expr) => Ok(expr.get().0.clone()),
+        Err(_) => Ok(Expr::constant(to_field(value)?)),
+    }
+}
+
+#[pymethods]
+impl PyExpr {
+    fn __add__(&self, other: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(PyExpr(self.0.clone() + to_expr(other)?))
+    }
+
+    fn __radd__(&self, other: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(PyExpr(to_expr(other)? + self.0.clone()))
+    }
+
+    fn __sub__(&self, other: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(PyExpr(self.0.clone() - to_expr(other)?))
+    }
+
+    fn __rsub__(&self, other: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(PyExpr(to_expr(other)? - self.0.clone()))
+    }
+
+    fn __mul__(&self, other: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(PyExpr(self.0.clone() * to_expr(other)?))
+    }
+
+    fn __rmul__(&self, other: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(PyExpr(to_expr(other)? * self.0.clone()))
+    }
+
+    fn __neg__(&self) -> Self {
+        PyExpr(-self.0.clone())
+    }
+
+    /// Raises this expression to `exp` by square-and-multiply, so that it
+    /// costs at most 2 log2(exp) gates.
+    fn __pow__(&self, exp: u64, modulo: Option<&Bound<'_, PyAny>>) -> PyResult<Self> {
+        if modulo.is_some() {
+            return Err(PyValueError::new_err(
+                "modular exponentiation is not supported",
+            ));
+        }
+
+        let mut acc = Expr::constant(Fq::one());
+        for i in (0..64 - exp.leading_zeros()).rev() {
+            acc = acc.clone() * acc;
+            if (exp >> i) & 1 == 1 {
+                acc = acc * self.0.clone();
+            }
+        }
+        Ok(PyExpr(acc))
+    }
+}
+
+/// A circuit under construction. Inputs and witnesses are numbered in the
+/// order they are allocated, which is the order their values are given in.
+#[pyclass(name = "Circuit")]
+struct PyCircuit(Program);
+
+#[pymethods]
+impl PyCircuit {
+    #[new]
+    fn new() -> Self {
+        PyCircuit(Program::new())
+    }
+
+    /// Allocates the next public input.
+    fn input(&mut self) -> PyExpr {
+        PyExpr(self.0.input())
+    }
+
+    /// Allocates the next witness.
+    fn witness(&mut self) -> PyExpr {
+        PyExpr(self.0.witness())
+    }
+
+    /// Constrains `a` and `b`, expressions or integers, to be equal.
+    fn enforce_equal(&mut self, a: &Bound<'_, PyAny>, b: &Bound<'_, PyAny>) -> PyResult<()> {
+        if self.0.enforce_equal(to_expr(a)?, to_expr(b)?) {
+            Ok(())
+        } else {
+            Err(PyValueError::new_err(
+                "expression refers to a variable of another circuit",
+            ))
+        }
+    }
+
+    #[getter]
+    fn num_inputs(&self) -> usize {
+        self.0.num_inputs()
+    }
+
+    #[getter]
+    fn num_witnesses(&self) -> usize {
+        self.0.num_witnesses()
+    }
+
+    /// The number of multiplication gates the circuit costs.
+    fn gates(&self) -> PyResult<usize> {
+        halo::dev::constraint_totals::<_, _, Basic>(&self.0)
+            .map(|counts| counts.total_mults)
+            .map_err(synthesis_error)
+    }
+
+    /// Checks whether the values satisfy the circuit, without proving.
+    fn is_satisfied(
+        &self,
+        inputs: Vec<Bound<'_, PyAny>>,
+        witnesses: Vec<Bound<'_, PyAny>>,
+    ) -> PyResult<bool> {
+        let (inputs, witnesses) = (to_fields(&inputs)?, to_fields(&witnesses)?);
+        let assigned = self
+            .0
+            .assign(&inputs, &witnesses)
+            .ok_or_else(|| PyValueError::new_err("wrong number of values"))?;
+        match halo::dev::is_satisfied::<_, _, Basic>(&assigned, &inputs) {
+            Ok(satisfied) => Ok(satisfied),
+            Err(SatisfactionError::Synthesis(SynthesisError::Unsatisfiable))
+            | Err(SatisfactionError::Multiplication(..))
+            | Err(SatisfactionError::Linear(..)) => Ok(false),
+            Err(e) => Err(PyValueError::new_err(format!("{:?}", e))),
+        }
+    }
+}
+
+/// Proves that `circuit` is satisfied by the values of its public `inputs`
+/// and `witnesses`, returning the encoded proof.
+#[pyfunction]
+#[pyo3(name = "prove")]
+fn py_prove<'py>(
+    py: Python<'py>,
+    params: &PyParams,
+    circuit: &PyCircuit,
+    inputs: Vec<Bound<'py, PyAny>>,
+    witnesses: Vec<Bound<'py, PyAny>>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let (inputs, witnesses) = (to_fields(&inputs)?, to_fields(&witnesses)?);
+    if inputs.len() != circuit.0.num_inputs() || witnesses.len() != circuit.0.num_witnesses() {
+        return Err(PyValueError::new_err("wrong number of values"));
+    }
+
+    let proof = py
+        .allow_threads(|| prove(&params.0, &circuit.0, &inputs, &witnesses))
+        .map_err(synthesis_error)?;
+    Ok(PyBytes::new(py, &proof))
+}
+
+/// Verifies an encoded proof against the values of the circuit's public
+/// `inputs`.
+#[pyfunction]
+#[pyo3(name = "verify")]
+fn py_verify(
+    py: Python<'_>,
+    params: &PyParams,
+    circuit: &PyCircuit,
+    proof: Vec<u8>,
+    inputs: Vec<Bound<'_, PyAny>>,
+) -> PyResult<bool> {
+    let inputs = to_fields(&inputs)?;
+    py.allow_threads(|| verify(&params.0, &circuit.0, &proof, &inputs))
+        .map_err(synthesis_error)
+}
+
+#[pymodule]
+fn halo_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("MODULUS", modulus(m.py())?)?;
+    m.add_class::<PyParams>()?;
+    m.add_class::<PyExpr>()?;
+    m.add_class::<PyCircuit>()?;
+    m.add_function(wrap_pyfunction!(py_prove, m)?)?;
+    m.add_function(wrap_pyfunction!(py_verify, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use halo::*;
+use halo_py::circuit::{Expr, Program};
+
+// y = x^3 + 5, with x a witness and y a public input
+fn cubic() -> Program {
+    let mut program = Program::new();
+    let y = program.input();
+    let x = program.witness();
+    let x3 = x.clone() * x.clone() * x;
+    assert!(program.enforce_equal(x3 + Expr::constant(Fq::from_u64(5)), y));
+    program
+}
+
+#[test]
+fn test_gates() {
+    let program = cubic();
+    assert_eq!(program.num_inputs(), 1);
+    assert_eq!(program.num_witnesses(), 1);
+
+    let counts = dev::constraint_totals::<_, _, Basic>(&program).unwrap();
+    assert_eq!(counts.total_mults, 4);
+
+    let inputs = [Fq::from_u64(32)];
+    let witnesses = [Fq::from_u64(3)];
+    let assigned = program.assign(&inputs, &witnesses).unwrap();
+    assert_eq!(
+        dev::is_satisfied::<_, _, Basic>(&assigned, &inputs),
+        Ok(true)
+    );
+    let witnesses = [Fq::from_u64(4)];
+    let assigned = program.assign(&inputs, &witnesses).unwrap();
+    assert!(matches!(
+        dev::is_satisfied::<_, _, Basic>(&assigned, &inputs),
+        Err(dev::SatisfactionError::Linear(..))
+    ));
+    assert!(program.assign(&inputs, &[]).is_none());
+}
+
+#[test]
+fn test_shared_subexpressions() {
+    let mut program = Program::new();
+    let x = program.witness();
+    let x2 = x.clone() * x.clone();
+    assert!(program.enforce_equal(x2.clone() * x2, x));
+
+    let counts = dev::constraint_totals::<_, _, Basic>(&program).unwrap();
+    assert_eq!(counts.total_mults, 3);
+}
+
+#[test]
+fn test_foreign_variables() {
+    let mut program = Program::new();
+    let x = Program::new().witness();
+    let one = Expr::constant(Fq::one());
+    assert!(!program.enforce_equal(x, one.clone()));
+    assert!(program.enforce_equal(one.clone(), one));
+}
+
+#[test]
+fn test_prove_and_verify() {
+    let params = Params::<Ec1>::from_seed(5, b"halo-py");
+    let program = cubic();
+    let inputs = [Fq::from_u64(32)];
+
+    let proof = halo_py::prove(&params, &program, &inputs, &[Fq::from_u64(3)]).unwrap();
+    assert_eq!(
+        halo_py::verify(&params, &program, &proof, &inputs),
+        Ok(true)
+    );
+    assert_eq!(
+        halo_py::verify(&params, &program, &proof, &[Fq::from_u64(33)]),
+        Ok(false)
+    );
+    assert_eq!(
+        halo_py::verify(&params, &program, &proof[1..], &inputs),
+        Ok(false)
+    );
+    assert_eq!(halo_py::verify(&params, &program, &proof, &[]), Ok(false));
+}
@@ -0,0 +1,560 @@
+//! Proof verification as an accumulation scheme, in the sense of Bünz,
+//! Chiesa, Mishra and Spooner.
+//!
+//! An [`AccumulationScheme`] folds proofs into an accumulator with a cheap
+//! `accumulate` step, and leaves the expensive checks to a single `decide`
+//! on the final accumulator. For [`Halo`], the accumulation prover is
+//! `Proof::new` run against the current leftovers, and the pieces map onto
+//! the rest of the crate as follows:
+//!
+//! - the accumulator is an [`Accumulator`]: the `Leftovers` of the last proof
+//!   accumulated, whose g(X) commitment and s(X, y_new) commitment have yet
+//!   to be checked, and the `Deferred` scalar checks of every proof;
+//! - `accumulate` is the partial verification `Proof::verify` makes, which
+//!   also checks the opening of the previous leftovers' g(X);
+//! - `decide` is `Deferred::verify` for each proof and `Leftovers::verify`
+//!   for the last, the linear-time check.
+//!
+//! Recursive proofs perform `accumulate` in-circuit and discharge the
+//! `Deferred` checks on the other curve, but decide in the same way.
+//!
+//! [`Split`] is an alternative backend whose accumulation verifier reads
+//! only short instances and never checks an inner product argument, with
+//! the polynomials themselves carried natively in the accumulator witness.
+
+use crate::circuits::*;
+use crate::curves::*;
+use crate::fields::Field;
+use crate::proofs::*;
+use crate::rescue::Rescue;
+use crate::synthesis::Basic;
+
+/// An accumulation scheme for proofs of a fixed circuit.
+pub trait AccumulationScheme {
+    /// What proofs are folded into.
+    type Accumulator: Clone;
+
+    /// A proof, together with the public inputs it attests to.
+    type Proof;
+
+    /// The accumulator nothing has been accumulated into yet.
+    fn init(&self) -> Self::Accumulator;
+
+    /// Folds `proofs` into `acc`, in order. Each proof must have been created
+    /// against the accumulator produced by the ones before it.
+    ///
+    /// Returns `SynthesisError::Unsatisfiable` if a proof is rejected.
+    fn accumulate(
+        &self,
+        proofs: &[Self::Proof],
+        acc: &Self::Accumulator,
+    ) -> Result<Self::Accumulator, SynthesisError>;
+
+    /// Whether every proof folded into `acc` verifies.
+    fn decide(&self, acc: &Self::Accumulator) -> bool;
+}
+
+/// The accumulator of the [`Halo`] scheme.
+#[derive(Clone, Debug)]
+pub struct Accumulator<C: Curve> {
+    leftovers: Leftovers<C>,
+    deferred: Vec<Deferred<C::Scalar>>,
+}
+
+impl<C: Curve> Accumulator<C> {
+    /// The leftovers the next proof must be created against.
+    pub fn leftovers(&self) -> &Leftovers<C> {
+        &self.leftovers
+    }
+
+    /// The deferred checks of every proof accumulated so far.
+    pub fn deferred(&self) -> &[Deferred<C::Scalar>] {
+        &self.deferred
+    }
+}
+
+/// The accumulation scheme underlying Halo, for proofs of one circuit made
+/// with `params`.
+pub struct Halo<'a, C: Curve> {
+    params: &'a Params<C>,
+
+    // Positions in k(Y) of the public inputs of the circuit
+    input_map: Vec<usize>,
+
+    s: SparseS<C::Scalar>,
+}
+
+impl<'a, C: Curve> Halo<'a, C> {
+    /// Synthesizes `circuit` once for its shape, which is all the scheme
+    /// needs of it.
+    pub fn new<CS: Circuit<C::Scalar>>(
+        params: &'a Params<C>,
+        circuit: &CS,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Halo {
+            params,
+            input_map: compute_input_map::<_, _, Basic>(circuit)?,
+            s: params.compute_s::<_, Basic>(circuit)?,
+        })
+    }
+}
+
+impl<'a, C: Curve> AccumulationScheme for Halo<'a, C> {
+    type Accumulator = Accumulator<C>;
+    type Proof = (Proof<C>, Vec<C::Scalar>);
+
+    fn init(&self) -> Accumulator<C> {
+        Accumulator {
+            leftovers: Leftovers::dummy(self.params),
+            deferred: vec![],
+        }
+    }
+
+    fn accumulate(
+        &self,
+        proofs: &[(Proof<C>, Vec<C::Scalar>)],
+        acc: &Accumulator<C>,
+    ) -> Result<Accumulator<C>, SynthesisError> {
+        let mut acc = acc.clone();
+        for (proof, inputs) in proofs {
+            if inputs.len() + 1 != self.input_map.len() {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+
+            let (worked, leftovers, deferred, _) = proof.verify_with_input_map(
+                &acc.leftovers,
+                self.params,
+                &self.input_map,
+                inputs,
+                None,
+            );
+            if !worked {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+
+            acc.leftovers = leftovers;
+            acc.deferred.push(deferred);
+        }
+
+        Ok(acc)
+    }
+
+    fn decide(&self, acc: &Accumulator<C>) -> bool {
+        acc.deferred
+            .iter()
+            .all(|deferred| deferred.verify(self.params.k))
+            && acc.leftovers.verify_with_s(self.params, &self.s)
+    }
+}
+
+/// A claim that the polynomial committed to by `commitment` evaluates to
+/// `value` at `point`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpeningClaim<C: Curve> {
+    pub commitment: C,
+    pub point: C::Scalar,
+    pub value: C::Scalar,
+}
+
+impl<C: Curve> OpeningClaim<C> {
+    fn append(&self, transcript: &mut Rescue<C::Base>) {
+        append_point(transcript, &self.commitment);
+        append_scalar::<C>(transcript, &self.point);
+        append_scalar::<C>(transcript, &self.value);
+    }
+}
+
+/// The accumulator of the [`Split`] scheme. Accumulation is verified
+/// against its short instance alone, while the polynomial witnessing the
+/// instance is only needed to accumulate further and to decide.
+#[derive(Clone, Debug)]
+pub struct SplitAccumulator<C: Curve> {
+    instance: OpeningClaim<C>,
+    witness: Vec<C::Scalar>,
+}
+
+impl<C: Curve> SplitAccumulator<C> {
+    pub fn instance(&self) -> &OpeningClaim<C> {
+        &self.instance
+    }
+}
+
+/// The proof that a [`Split`] accumulation step was done honestly: a
+/// commitment to the quotient of each claim folded, the old accumulator
+/// first.
+#[derive(Clone, Debug)]
+pub struct SplitProof<C: Curve> {
+    quotients: Vec<C>,
+}
+
+/// A split accumulation scheme for polynomial opening claims, after Bünz,
+/// Chiesa, Lin, Mishra and Spooner.
+///
+/// Unlike [`Halo`], claims are folded without an inner product argument.
+/// Each claim p(z) = v comes with its polynomial, and the prover commits to
+/// its quotient q(X) = (p(X) - v) / (X - z). At random challenges `alpha` and
+/// `z'`, the claims fold into the single claim that
+///
+///   sum_i alpha^i (p_i(X) - v_i - (z' - z_i) q_i(X))
+///
+/// is zero at `z'`, whose commitment the verifier derives from those of the
+/// claims and quotients. Verifying a step therefore costs a few group
+/// operations per claim, rather than the O(k) of an inner product argument,
+/// at the price of an accumulator witness as long as the polynomials.
+///
+/// Verification circuits still check inner product arguments; this scheme is
+/// an alternative backend for accumulating the opening claims they produce.
+pub struct Split<'a, C: Curve> {
+    params: &'a Params<C>,
+}
+
+impl<'a, C: Curve> Split<'a, C> {
+    pub fn new(params: &'a Params<C>) -> Self {
+        Split { params }
+    }
+
+    /// The accumulation prover: folds `claims`, each with its polynomial,
+    /// into `acc`.
+    pub fn prove(
+        &self,
+        claims: &[(OpeningClaim<C>, Vec<C::Scalar>)],
+        acc: &SplitAccumulator<C>,
+    ) -> (SplitAccumulator<C>, SplitProof<C>) {
+        let claims: Vec<(&OpeningClaim<C>, &[C::Scalar])> = Some((&acc.instance, &acc.witness[..]))
+            .into_iter()
+            .chain(claims.iter().map(|(claim, poly)| (claim, &poly[..])))
+            .collect();
+
+        let quotients: Vec<Vec<C::Scalar>> = claims
+            .iter()
+            .map(|(claim, poly)| quotient(poly, claim.point))
+            .collect();
+        let proof = SplitProof {
+            quotients: quotients
+                .iter()
+                .map(|q| self.params.commit(q, false))
+                .collect(),
+        };
+
+        let instances: Vec<OpeningClaim<C>> =
+            claims.iter().map(|(claim, _)| (*claim).clone()).collect();
+        let (alpha, point) = challenges(self.params, &instances, &proof);
+
+        let len = claims.iter().map(|(_, poly)| poly.len()).max().unwrap_or(0);
+        let mut witness = vec![C::Scalar::zero(); len.max(1)];
+        let mut coeff = C::Scalar::one();
+        for ((claim, poly), q) in claims.iter().zip(quotients.iter()) {
+            let shift = (point - claim.point) * coeff;
+            for (w, p) in witness.iter_mut().zip(poly.iter()) {
+                *w += *p * coeff;
+            }
+            witness[0] -= claim.value * coeff;
+            for (w, q) in witness.iter_mut().zip(q.iter()) {
+                *w -= *q * shift;
+            }
+            coeff *= alpha;
+        }
+
+        let instance = fold(self.params, &instances, &proof, alpha, point);
+        (SplitAccumulator { instance, witness }, proof)
+    }
+
+    /// The accumulation verifier: whether `new` is the result of folding
+    /// `claims` into `old`, reading only instances.
+    pub fn verify(
+        &self,
+        claims: &[OpeningClaim<C>],
+        old: &OpeningClaim<C>,
+        new: &OpeningClaim<C>,
+        proof: &SplitProof<C>,
+    ) -> bool {
+        let instances: Vec<OpeningClaim<C>> = Some(old.clone())
+            .into_iter()
+            .chain(claims.iter().cloned())
+            .collect();
+        if proof.quotients.len() != instances.len() {
+            return false;
+        }
+
+        let (alpha, point) = challenges(self.params, &instances, proof);
+        fold(self.params, &instances, proof, alpha, point) == *new
+    }
+}
+
+impl<'a, C: Curve> AccumulationScheme for Split<'a, C> {
+    type Accumulator = SplitAccumulator<C>;
+    type Proof = (OpeningClaim<C>, Vec<C::Scalar>);
+
+    fn init(&self) -> SplitAccumulator<C> {
+        // The zero polynomial, which is zero everywhere
+        SplitAccumulator {
+            instance: OpeningClaim {
+                commitment: C::zero(),
+                point: C::Scalar::zero(),
+                value: C::Scalar::zero(),
+            },
+            witness: vec![],
+        }
+    }
+
+    fn accumulate(
+        &self,
+        proofs: &[(OpeningClaim<C>, Vec<C::Scalar>)],
+        acc: &SplitAccumulator<C>,
+    ) -> Result<SplitAccumulator<C>, SynthesisError> {
+        if proofs
+            .iter()
+            .any(|(_, poly)| poly.len() > self.params.generators.len())
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let (acc, _) = self.prove(proofs, acc);
+        Ok(acc)
+    }
+
+    fn decide(&self, acc: &SplitAccumulator<C>) -> bool {
+        self.params.commit(&acc.witness, false) == acc.instance.commitment
+            && self
+                .params
+                .compute_opening(&acc.witness, acc.instance.point, false)
+                == acc.instance.value
+    }
+}
+
+/// (p(X) - p(z)) / (X - z), by synthetic division.
+fn quotient<F: Field>(poly: &[F], point: F) -> Vec<F> {
+    let mut q = vec![F::zero(); poly.len().saturating_sub(1)];
+    let mut carry = F::zero();
+    for i in (1..poly.len()).rev() {
+        carry = poly[i] + (carry * point);
+        q[i - 1] = carry;
+    }
+    q
+}
+
+/// The challenges `alpha` and `z'` of a folding step.
+fn challenges<C: Curve>(
+    params: &Params<C>,
+    instances: &[OpeningClaim<C>],
+    proof: &SplitProof<C>,
+) -> (C::Scalar, C::Scalar) {
+    let mut transcript = Rescue::<C::Base>::new();
+    for instance in instances {
+        instance.append(&mut transcript);
+    }
+    for q in &proof.quotients {
+        append_point(&mut transcript, q);
+    }
+    let alpha = get_challenge::<_, C::Scalar>(&mut transcript, params.level);
+    let point = get_challenge::<_, C::Scalar>(&mut transcript, params.level);
+
+    (alpha, point)
+}
+
+/// The claim `instances` fold into.
+fn fold<C: Curve>(
+    params: &Params<C>,
+    instances: &[OpeningClaim<C>],
+    proof: &SplitProof<C>,
+    alpha: C::Scalar,
+    point: C::Scalar,
+) -> OpeningClaim<C> {
+    let one = params.generators[0].to_projective();
+
+    let mut commitment = C::zero();
+    let mut coeff = C::Scalar::one();
+    for (instance, q) in instances.iter().zip(proof.quotients.iter()) {
+        let term = instance.commitment - (one * instance.value) - (*q * (point - instance.point));
+        commitment += term * coeff;
+        coeff *= alpha;
+    }
+
+    OpeningClaim {
+        commitment,
+        point,
+        value: C::Scalar::zero(),
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::fields::*;
+
+    struct CubeCircuit<F: Field> {
+        x: Option<F>,
+    }
+
+    impl<F: Field> Circuit<F> for CubeCircuit<F> {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let (a, b, c) = cs.multiply(
+                || "x^2",
+                || {
+                    let x = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+
+                    Ok((x, x, x.square()))
+                },
+            )?;
+            cs.enforce_zero(LinearCombination::from(a) - b);
+
+            let (d, e, f) = cs.multiply(
+                || "x^3",
+                || {
+                    let x = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+
+                    Ok((x.square(), x, x.square() * x))
+                },
+            )?;
+            cs.enforce_zero(LinearCombination::from(c) - d);
+            cs.enforce_zero(LinearCombination::from(a) - e);
+
+            let x3 = cs.alloc_input(
+                || "x3",
+                || {
+                    let x = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+
+                    Ok(x.square() * x)
+                },
+            )?;
+            cs.enforce_zero(LinearCombination::from(x3) - f);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_accumulate_and_decide() {
+        let params: Params<Ec1> = Params::new(4);
+        let scheme = Halo::new(&params, &CubeCircuit::<Fq> { x: None }).unwrap();
+
+        // Each proof is created against the accumulator of those before it.
+        let mut acc = scheme.init();
+        let mut proofs = vec![];
+        for x in 2..5 {
+            let circuit = CubeCircuit {
+                x: Some(Fq::from(x)),
+            };
+            let (proof, _) = Proof::new::<_, Basic>(&params, &circuit, acc.leftovers()).unwrap();
+            let proof = (proof, vec![Fq::from(x * x * x)]);
+            acc = scheme
+                .accumulate(std::slice::from_ref(&proof), &acc)
+                .unwrap();
+            proofs.push(proof);
+        }
+        assert_eq!(acc.deferred().len(), 3);
+        assert!(scheme.decide(&acc));
+
+        // Accumulating them all at once gives the same accumulator.
+        let batch = scheme.accumulate(&proofs, &scheme.init()).unwrap();
+        assert_eq!(batch.leftovers(), acc.leftovers());
+        assert!(scheme.decide(&batch));
+
+        // Proofs must be accumulated in the order they were created.
+        let mut reordered = proofs.clone();
+        reordered.swap(0, 1);
+        assert!(scheme.accumulate(&reordered, &scheme.init()).is_err());
+
+        // A wrong public input is rejected.
+        let mut wrong = proofs[0].clone();
+        wrong.1[0] = Fq::from(9);
+        assert!(scheme.accumulate(&[wrong], &scheme.init()).is_err());
+
+        // A proof that stopped its inner product argument early can be
+        // accumulated, and proofs created against it after.
+        let mut acc = scheme.init();
+        for (x, tail) in [(2, 2), (3, 0), (4, 1)].iter() {
+            let circuit = CubeCircuit {
+                x: Some(Fq::from(*x)),
+            };
+            let (proof, _) =
+                Proof::new_with_tail::<_, Basic>(&params, &circuit, acc.leftovers(), *tail)
+                    .unwrap();
+            acc = scheme
+                .accumulate(&[(proof, vec![Fq::from(x * x * x)])], &acc)
+                .unwrap();
+        }
+        assert!(scheme.decide(&acc));
+
+        // So is an accumulator whose leftovers were tampered with.
+        let mut tampered = acc.clone();
+        tampered.leftovers.g_new = params.generators[0].to_projective();
+        assert!(!scheme.decide(&tampered));
+    }
+
+    fn claim(params: &Params<Ec1>, poly: &[Fq], point: Fq) -> (OpeningClaim<Ec1>, Vec<Fq>) {
+        let claim = OpeningClaim {
+            commitment: params.commit(poly, false),
+            point,
+            value: params.compute_opening(poly, point, false),
+        };
+        (claim, poly.to_vec())
+    }
+
+    #[test]
+    fn test_quotient() {
+        let poly: Vec<Fq> = (1..6).map(Fq::from).collect();
+        let point = Fq::from(3);
+        let q = quotient(&poly, point);
+
+        let eval = |p: &[Fq], x: Fq| p.iter().rev().fold(Fq::zero(), |acc, c| acc * x + c);
+        let x = Fq::from(11);
+        assert_eq!(
+            eval(&poly, x) - eval(&poly, point),
+            eval(&q, x) * (x - point)
+        );
+    }
+
+    #[test]
+    fn test_split_accumulation() {
+        let params: Params<Ec1> = Params::new(4);
+        let scheme = Split::new(&params);
+
+        let polys: Vec<Vec<Fq>> = (0..4)
+            .map(|i| (0..(4 + 3 * i)).map(|j| Fq::from(7 * i + j + 1)).collect())
+            .collect();
+        let claims: Vec<_> = polys
+            .iter()
+            .enumerate()
+            .map(|(i, poly)| claim(&params, poly, Fq::from(100 + i as u64)))
+            .collect();
+
+        // Fold the claims in two steps, checking each against instances only.
+        let init = scheme.init();
+        assert!(scheme.decide(&init));
+        let (acc1, proof1) = scheme.prove(&claims[..2], &init);
+        let instances: Vec<_> = claims.iter().map(|(claim, _)| claim.clone()).collect();
+        assert!(scheme.verify(&instances[..2], init.instance(), acc1.instance(), &proof1));
+        let (acc2, proof2) = scheme.prove(&claims[2..], &acc1);
+        assert!(scheme.verify(&instances[2..], acc1.instance(), acc2.instance(), &proof2));
+        assert!(!scheme.verify(&instances[2..], init.instance(), acc2.instance(), &proof2));
+        assert!(scheme.decide(&acc2));
+        assert_eq!(
+            scheme
+                .accumulate(&claims, &init)
+                .unwrap()
+                .instance()
+                .commitment,
+            scheme.prove(&claims, &init).0.instance().commitment
+        );
+
+        // A false claim makes the accumulator undecidable.
+        let mut wrong = claims.clone();
+        wrong[1].0.value += Fq::one();
+        let acc = scheme.accumulate(&wrong, &init).unwrap();
+        assert!(!scheme.decide(&acc));
+
+        // As does a polynomial that does not match its commitment.
+        let mut wrong = claims.clone();
+        wrong[2].1[0] += Fq::one();
+        let acc = scheme.accumulate(&wrong, &init).unwrap();
+        assert!(!scheme.decide(&acc));
+
+        // Polynomials longer than the params can commit to are rejected.
+        let long = vec![(
+            claims[0].0.clone(),
+            vec![Fq::one(); params.generators.len() + 1],
+        )];
+        assert!(scheme.accumulate(&long, &init).is_err());
+    }
+}
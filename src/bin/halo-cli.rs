@@ -0,0 +1,429 @@
+//! Command line access to parameters and recursive proofs, for exercising the
+//! system end-to-end and debugging serialized artifacts.
+//!
+//! ```text
+//! halo-cli params gen --k <k> [--seed <seed>] [--hash <hash>] --out <file>
+//! halo-cli params inspect <file>
+//! halo-cli prove-step --params <file> [--prev <proof>] --out <proof>
+//! halo-cli verify --params <file> <proof>
+//! halo-cli proof inspect <proof>
+//! ```
+//!
+//! Parameter files hold the `k`, seed and generator hash (`sha256`, the
+//! default, `sha512` or `rescue`) that `Params::from_seed_with` derives the
+//! parameters of both curves from. Files written before the hash was
+//! recorded end after the seed, and name SHA-256. Files naming any other
+//! hash, such as an application's own with an id above 255, are rejected,
+//! as the CLI cannot derive their generators. Proof files hold a tag
+//! naming the curve the proof is over, followed by the `RecursiveProof`
+//! encoding. Each step proves [`StepCircuit`], which is the only thing to
+//! change in order to drive a different circuit.
+
+extern crate halo;
+
+use halo::*;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::process;
+
+/// The circuit proven at every step.
+type StepCircuit = CounterCircuit;
+
+fn step_circuit() -> StepCircuit {
+    CounterCircuit
+}
+
+/// The payload of a step following one with payload `old`, or the first step
+/// if there is none.
+fn next_payload(old: Option<&[u8]>) -> Vec<u8> {
+    match old {
+        Some(old) => vec![old[0].wrapping_add(1)],
+        None => vec![1],
+    }
+}
+
+/// Counts steps in a one-byte payload, which starts at zero and increases by
+/// one (modulo 256) at every step.
+struct CounterCircuit;
+
+impl<F: Field> RecursiveCircuit<F> for CounterCircuit {
+    fn base_payload(&self) -> Vec<bool> {
+        vec![false; 8]
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        _challenges: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        if old_payload.len() != 8 || new_payload.len() != 8 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut carry = Boolean::constant(true);
+        for (i, (old, new)) in old_payload.iter().zip(new_payload.iter()).enumerate() {
+            let mut cs = cs.namespace(|| format!("bit {}", i));
+            let old = Boolean::from(old.clone());
+            let sum = Boolean::xor(cs.namespace(|| "sum"), &old, &carry)?;
+            Boolean::enforce_equal(cs.namespace(|| "new"), &sum, &Boolean::from(new.clone()))?;
+            carry = Boolean::and(cs.namespace(|| "carry"), &old, &carry)?;
+        }
+
+        Ok(())
+    }
+}
+
+const PARAMS_MAGIC: &[u8; 8] = b"HALOPRMS";
+const PROOF_MAGIC: &[u8; 8] = b"HALOPROF";
+
+// Tags of the curve a proof file's proof is over
+const OVER_EC1: u8 = 1;
+const OVER_EC0: u8 = 0;
+
+struct ParamsFile {
+    k: usize,
+    seed: Vec<u8>,
+    hash: u32,
+}
+
+// The generator hashes a params file may name, by id and name
+const GENERATOR_HASHES: &[(u32, &str)] = &[
+    (Sha256Generators::ID, "sha256"),
+    (Sha512Generators::ID, "sha512"),
+    (RescueGenerators::ID, "rescue"),
+];
+
+fn hash_name(id: u32) -> Option<&'static str> {
+    GENERATOR_HASHES
+        .iter()
+        .find(|(i, _)| *i == id)
+        .map(|(_, name)| *name)
+}
+
+impl ParamsFile {
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(PARAMS_MAGIC)?;
+        writer.write_all(&(self.k as u32).to_le_bytes())?;
+        writer.write_all(&(self.seed.len() as u32).to_le_bytes())?;
+        writer.write_all(&self.seed)?;
+        writer.write_all(&self.hash.to_le_bytes())
+    }
+
+    fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        read_magic(reader, PARAMS_MAGIC)?;
+        let k = read_u32(reader)? as usize;
+        if !(4..=32).contains(&k) {
+            return Err(invalid_data("k out of range"));
+        }
+        let mut seed = vec![0; read_u32(reader)? as usize];
+        reader.read_exact(&mut seed)?;
+        let mut hash = [0; 4];
+        let hash = match reader.read(&mut hash)? {
+            0 => Sha256Generators::ID,
+            len => {
+                reader.read_exact(&mut hash[len..])?;
+                u32::from_le_bytes(hash)
+            }
+        };
+        if hash_name(hash).is_none() {
+            return Err(invalid_data(&format!(
+                "generator hash {} is not one halo-cli can derive",
+                hash
+            )));
+        }
+
+        Ok(ParamsFile { k, seed, hash })
+    }
+
+    fn params<C: Curve>(&self) -> Params<C> {
+        match self.hash {
+            Sha256Generators::ID => Params::from_seed_with(self.k, &self.seed, &Sha256Generators),
+            Sha512Generators::ID => Params::from_seed_with(self.k, &self.seed, &Sha512Generators),
+            RescueGenerators::ID => {
+                Params::from_seed_with(self.k, &self.seed, &RescueGenerators::new())
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+enum ProofFile {
+    OverEc1(RecursiveProof<Ec1, Ec0>),
+    OverEc0(RecursiveProof<Ec0, Ec1>),
+}
+
+impl ProofFile {
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(PROOF_MAGIC)?;
+        match self {
+            ProofFile::OverEc1(proof) => {
+                writer.write_all(&[OVER_EC1])?;
+                proof.write(writer)
+            }
+            ProofFile::OverEc0(proof) => {
+                writer.write_all(&[OVER_EC0])?;
+                proof.write(writer)
+            }
+        }
+    }
+
+    fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        read_magic(reader, PROOF_MAGIC)?;
+        let mut tag = [0];
+        reader.read_exact(&mut tag)?;
+        let proof = match tag[0] {
+            OVER_EC1 => ProofFile::OverEc1(RecursiveProof::read(reader)?),
+            OVER_EC0 => ProofFile::OverEc0(RecursiveProof::read(reader)?),
+            _ => return Err(invalid_data("unknown curve tag")),
+        };
+        if reader.read(&mut [0])? != 0 {
+            return Err(invalid_data("trailing bytes after proof"));
+        }
+
+        Ok(proof)
+    }
+
+    fn curve(&self) -> &'static str {
+        match self {
+            ProofFile::OverEc1(_) => "Ec1",
+            ProofFile::OverEc0(_) => "Ec0",
+        }
+    }
+
+    fn payload(&self) -> &[u8] {
+        match self {
+            ProofFile::OverEc1(proof) => proof.payload(),
+            ProofFile::OverEc0(proof) => proof.payload(),
+        }
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_magic<R: Read>(reader: &mut R, magic: &[u8; 8]) -> io::Result<()> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    if &bytes != magic {
+        return Err(invalid_data("wrong file type"));
+    }
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The command line arguments after the subcommand: `--name value` options
+/// and positional arguments.
+struct Args {
+    options: Vec<(String, String)>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: &[&str]) -> Result<Self, String> {
+        let mut options = vec![];
+        let mut positional = vec![];
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("missing value for {}", arg))?;
+                options.push((name.to_string(), value.to_string()));
+            } else {
+                positional.push(arg.to_string());
+            }
+        }
+
+        Ok(Args {
+            options,
+            positional,
+        })
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| &v[..])
+    }
+
+    fn required(&self, name: &str) -> Result<&str, String> {
+        self.option(name)
+            .ok_or_else(|| format!("missing --{}", name))
+    }
+
+    fn single_positional(&self) -> Result<&str, String> {
+        match &self.positional[..] {
+            [path] => Ok(path),
+            _ => Err("expected a single file argument".to_string()),
+        }
+    }
+}
+
+fn read_file<T, F: FnOnce(&mut BufReader<File>) -> io::Result<T>>(
+    path: &str,
+    read: F,
+) -> Result<T, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    read(&mut BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn write_file<F: FnOnce(&mut BufWriter<File>) -> io::Result<()>>(
+    path: &str,
+    write: F,
+) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)
+        .and_then(|_| writer.flush())
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+fn params_gen(args: &Args) -> Result<(), String> {
+    let k = args
+        .required("k")?
+        .parse()
+        .map_err(|_| "--k must be a number".to_string())?;
+    if !(4..=32).contains(&k) {
+        return Err("--k must be between 4 and 32".to_string());
+    }
+    let hash = args.option("hash").unwrap_or("sha256");
+    let hash = GENERATOR_HASHES
+        .iter()
+        .find(|(_, name)| *name == hash)
+        .map(|(id, _)| *id)
+        .ok_or_else(|| format!("unknown --hash {}", hash))?;
+    let params = ParamsFile {
+        k,
+        hash,
+        seed: args
+            .option("seed")
+            .unwrap_or("halo-cli")
+            .as_bytes()
+            .to_vec(),
+    };
+
+    write_file(args.required("out")?, |w| params.write(w))
+}
+
+fn params_inspect(args: &Args) -> Result<(), String> {
+    let params = read_file(args.single_positional()?, ParamsFile::read)?;
+    let e1params: Params<Ec1> = params.params();
+    let e0params: Params<Ec0> = params.params();
+
+    println!("k = {}", params.k);
+    println!("d = {}", e1params.d());
+    println!("n = {}", e1params.n());
+    println!("seed = {}", to_hex(&params.seed));
+    println!("hash = {}", hash_name(params.hash).unwrap());
+    println!("digest_ec1 = {}", to_hex(&e1params.digest()));
+    println!("digest_ec0 = {}", to_hex(&e0params.digest()));
+
+    Ok(())
+}
+
+fn prove_step(args: &Args) -> Result<(), String> {
+    let params = read_file(args.required("params")?, ParamsFile::read)?;
+    let e1params: Params<Ec1> = params.params();
+    let e0params: Params<Ec0> = params.params();
+    let circuit = step_circuit();
+
+    let prev = match args.option("prev") {
+        Some(path) => Some(read_file(path, ProofFile::read)?),
+        None => None,
+    };
+    let payload = next_payload(prev.as_ref().map(|p| p.payload()));
+
+    // Steps alternate curves, starting over Ec1.
+    let proof = match &prev {
+        None => RecursiveProof::create_proof(&e1params, &e0params, None, &circuit, &payload)
+            .map(ProofFile::OverEc1),
+        Some(ProofFile::OverEc0(prev)) => {
+            RecursiveProof::create_proof(&e1params, &e0params, Some(prev), &circuit, &payload)
+                .map(ProofFile::OverEc1)
+        }
+        Some(ProofFile::OverEc1(prev)) => {
+            RecursiveProof::create_proof(&e0params, &e1params, Some(prev), &circuit, &payload)
+                .map(ProofFile::OverEc0)
+        }
+    }
+    .map_err(|e| format!("proving failed: {:?}", e))?;
+
+    write_file(args.required("out")?, |w| proof.write(w))?;
+    println!("payload = {}", to_hex(&payload));
+
+    Ok(())
+}
+
+fn verify(args: &Args) -> Result<(), String> {
+    let params = read_file(args.required("params")?, ParamsFile::read)?;
+    let e1params: Params<Ec1> = params.params();
+    let e0params: Params<Ec0> = params.params();
+    let circuit = step_circuit();
+
+    let valid = match read_file(args.single_positional()?, ProofFile::read)? {
+        ProofFile::OverEc1(proof) => proof.verify(&e1params, &e0params, &circuit),
+        ProofFile::OverEc0(proof) => proof.verify(&e0params, &e1params, &circuit),
+    }
+    .map_err(|e| format!("verification failed: {:?}", e))?;
+
+    if valid {
+        println!("valid");
+        Ok(())
+    } else {
+        Err("invalid proof".to_string())
+    }
+}
+
+fn proof_inspect(args: &Args) -> Result<(), String> {
+    let path = args.single_positional()?;
+    let proof = read_file(path, ProofFile::read)?;
+    let mut encoded = vec![];
+    proof.write(&mut encoded).unwrap();
+
+    println!("curve = {}", proof.curve());
+    println!("size = {}", encoded.len());
+    println!("payload = {}", to_hex(proof.payload()));
+
+    Ok(())
+}
+
+const USAGE: &str = "usage:
+    halo-cli params gen --k <k> [--seed <seed>] [--hash <hash>] --out <file>
+    halo-cli params inspect <file>
+    halo-cli prove-step --params <file> [--prev <proof>] --out <proof>
+    halo-cli verify --params <file> <proof>
+    halo-cli proof inspect <proof>";
+
+fn run(args: &[&str]) -> Result<(), String> {
+    match args {
+        ["params", "gen", rest @ ..] => params_gen(&Args::parse(rest)?),
+        ["params", "inspect", rest @ ..] => params_inspect(&Args::parse(rest)?),
+        ["prove-step", rest @ ..] => prove_step(&Args::parse(rest)?),
+        ["verify", rest @ ..] => verify(&Args::parse(rest)?),
+        ["proof", "inspect", rest @ ..] => proof_inspect(&Args::parse(rest)?),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|a| &a[..]).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,210 @@
+//! A summary of the steps of a chain, for block explorers and auditors
+//! tracking the history of an IVC application.
+//!
+//! Unlike [`ProofHistory`](crate::ProofHistory), a [`ProofChain`] keeps no
+//! proofs. It records the payload of each step, a digest of the step's proof
+//! and whether the proof was found to verify, so it stays small however long
+//! the chain runs and can be published as JSON.
+
+use crate::circuits::*;
+use crate::curves::*;
+use crate::ivc::IvcState;
+use crate::json::{bytes, enclose, Object};
+use crate::proofs::*;
+use crate::recursion::RecursiveProof;
+use std::collections::BTreeMap;
+
+/// Whether the proof of a recorded step verifies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationStatus {
+    /// The proof has not been checked.
+    Unverified,
+    Valid,
+    Invalid,
+}
+
+impl VerificationStatus {
+    fn name(&self) -> &'static str {
+        match self {
+            VerificationStatus::Unverified => "unverified",
+            VerificationStatus::Valid => "valid",
+            VerificationStatus::Invalid => "invalid",
+        }
+    }
+}
+
+/// A recorded step of a chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainEntry {
+    pub step: u64,
+    pub payload: Vec<u8>,
+    /// The SHA-256 digest of the proof's encoding, as `RecursiveProof::digest`
+    /// computes it.
+    pub proof_digest: [u8; 32],
+    pub status: VerificationStatus,
+}
+
+impl<E1: Curve, E2: Curve> RecursiveProof<E1, E2> {
+    /// Computes the SHA-256 digest of the encoding of this proof, which
+    /// identifies it in a [`ProofChain`].
+    pub fn digest(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut encoding = vec![];
+        self.write(&mut encoding)
+            .expect("writing to a Vec does not fail");
+
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&Sha256::digest(&encoding));
+        digest
+    }
+}
+
+/// The recorded steps of a chain, in order of step. The chain alternates
+/// between the curves of the cycle, so steps are recorded from states over
+/// either.
+#[derive(Clone, Debug, Default)]
+pub struct ProofChain {
+    entries: BTreeMap<u64, ChainEntry>,
+}
+
+impl ProofChain {
+    pub fn new() -> Self {
+        ProofChain::default()
+    }
+
+    /// Records the step of `state` with the given status, replacing any
+    /// earlier record of the same step.
+    pub fn record<E1, E2>(&mut self, state: &IvcState<E1, E2>, status: VerificationStatus)
+    where
+        E1: Curve<Base = <E2 as Curve>::Scalar>,
+        E2: Curve<Base = <E1 as Curve>::Scalar>,
+    {
+        self.entries.insert(
+            state.step(),
+            ChainEntry {
+                step: state.step(),
+                payload: state.payload().to_vec(),
+                proof_digest: state.proof().digest(),
+                status,
+            },
+        );
+    }
+
+    /// Fully verifies the proof of `state` and records its step with the
+    /// outcome, which is returned. `e1params` and `e2params` are the
+    /// parameters `state` was created with.
+    pub fn record_verified<E1, E2, CS>(
+        &mut self,
+        state: &IvcState<E1, E2>,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+        circuit: &CS,
+    ) -> Result<bool, SynthesisError>
+    where
+        E1: Curve<Base = <E2 as Curve>::Scalar>,
+        E2: Curve<Base = <E1 as Curve>::Scalar>,
+        CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>,
+    {
+        let valid = state.proof().verify(e1params, e2params, circuit)?;
+        let status = if valid {
+            VerificationStatus::Valid
+        } else {
+            VerificationStatus::Invalid
+        };
+        self.record(state, status);
+
+        Ok(valid)
+    }
+
+    pub fn get(&self, step: u64) -> Option<&ChainEntry> {
+        self.entries.get(&step)
+    }
+
+    /// The most recently recorded step.
+    pub fn latest(&self) -> Option<&ChainEntry> {
+        self.entries.values().next_back()
+    }
+
+    /// The recorded steps, in increasing order.
+    pub fn entries(&self) -> impl Iterator<Item = &ChainEntry> {
+        self.entries.values()
+    }
+
+    /// Renders the chain as a JSON object with an `entries` array, holding
+    /// for each recorded step its `step`, `payload` and `proof_digest` in
+    /// unprefixed hex and its `status` as one of `"unverified"`, `"valid"` or
+    /// `"invalid"`.
+    pub fn to_json(&self) -> String {
+        let entries = self
+            .entries()
+            .map(|entry| {
+                Object::new()
+                    .field("step", entry.step.to_string())
+                    .field("payload", bytes(&entry.payload))
+                    .field("proof_digest", bytes(&entry.proof_digest))
+                    .field("status", format!("\"{}\"", entry.status.name()))
+                    .finish()
+            })
+            .collect();
+
+        Object::new()
+            .field("entries", enclose('[', entries, ']'))
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::fixtures::phony_state;
+
+    #[test]
+    fn test_chain() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+        let mut chain = ProofChain::new();
+        assert!(chain.latest().is_none());
+
+        let even = phony_state(&e1params, &e2params, 0);
+        let odd = phony_state(&e2params, &e1params, 1);
+        chain.record(&odd, VerificationStatus::Invalid);
+        chain.record(&even, VerificationStatus::Unverified);
+        chain.record(&even, VerificationStatus::Valid);
+
+        let steps: Vec<u64> = chain.entries().map(|entry| entry.step).collect();
+        assert_eq!(steps, vec![0, 1]);
+        assert_eq!(chain.get(0).unwrap().status, VerificationStatus::Valid);
+        assert_eq!(chain.get(0).unwrap().proof_digest, even.proof().digest());
+        assert_ne!(even.proof().digest(), odd.proof().digest());
+        assert_eq!(chain.latest().unwrap().payload, vec![1]);
+        assert!(chain.get(2).is_none());
+
+        let hex =
+            |digest: [u8; 32]| -> String { digest.iter().map(|b| format!("{:02x}", b)).collect() };
+        assert_eq!(
+            chain.to_json(),
+            format!(
+                "{{
+  \"entries\": [
+    {{
+      \"step\": 0,
+      \"payload\": \"00\",
+      \"proof_digest\": \"{}\",
+      \"status\": \"valid\"
+    }},
+    {{
+      \"step\": 1,
+      \"payload\": \"01\",
+      \"proof_digest\": \"{}\",
+      \"status\": \"invalid\"
+    }}
+  ]
+}}",
+                hex(even.proof().digest()),
+                hex(odd.proof().digest())
+            )
+        );
+        assert_eq!(ProofChain::new().to_json(), "{\n  \"entries\": []\n}");
+    }
+}
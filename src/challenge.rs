@@ -0,0 +1,161 @@
+//! Typed values that cross between the two fields of a curve cycle.
+//!
+//! A proof over one curve is partially verified in a circuit over the other
+//! curve's scalar field, so some of its values have to be carried over. The
+//! Fiat-Shamir challenges are squeezed from a transcript over the base field
+//! and truncated to 128 bits, which fit in both fields, while the other
+//! scalars generally do not. These types make those conversions explicit.
+
+use crate::curves::Curve;
+use crate::fields::Field;
+use crate::util::get_challenge_scalar;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A value of `F` that does not fit in 128 bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChallengeOverflow;
+
+impl fmt::Display for ChallengeOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "value does not fit in a 128-bit challenge")
+    }
+}
+
+impl std::error::Error for ChallengeOverflow {}
+
+/// A packed 128-bit challenge, as an element of `F`. The same challenge
+/// is an element of either field of the cycle, and `cast` moves it between
+/// them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Challenge<F: Field> {
+    packed: u128,
+    _field: PhantomData<F>,
+}
+
+impl<F: Field> Challenge<F> {
+    /// The challenge with the given packed bits.
+    pub fn from_u128(packed: u128) -> Self {
+        Challenge {
+            packed,
+            _field: PhantomData,
+        }
+    }
+
+    /// The challenge `value` is the packing of, if it fits in 128 bits.
+    pub fn new(value: F) -> Result<Self, ChallengeOverflow> {
+        if value.to_bytes()[16..].iter().any(|b| *b != 0) {
+            return Err(ChallengeOverflow);
+        }
+
+        Ok(Self::from_u128(value.get_lower_128()))
+    }
+
+    /// The packed bits of the challenge.
+    pub fn to_u128(&self) -> u128 {
+        self.packed
+    }
+
+    /// The packed challenge as an element of `F`.
+    pub fn packed(&self) -> F {
+        F::from_u128(self.packed)
+    }
+
+    /// The scalar the challenge stands for, as the endomorphism-based
+    /// scalar multiplication of the verifier computes it.
+    pub fn scalar(&self) -> F {
+        get_challenge_scalar(self.packed())
+    }
+
+    /// The same challenge as an element of `F2`.
+    pub fn cast<F2: Field>(&self) -> Challenge<F2> {
+        Challenge::from_u128(self.packed)
+    }
+}
+
+/// An element of the scalar field of `E`, kept apart from elements of its
+/// base field, which is the scalar field of the other curve of the cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scalar<E: Curve>(E::Scalar);
+
+impl<E: Curve> Scalar<E> {
+    pub fn new(value: E::Scalar) -> Self {
+        Scalar(value)
+    }
+
+    pub fn get(&self) -> E::Scalar {
+        self.0
+    }
+
+    /// The element of the base field of `E` with the same canonical
+    /// representation, if there is one.
+    pub fn to_base(&self) -> Option<E::Base> {
+        Option::from(E::Base::from_bytes(&self.0.to_bytes()))
+    }
+
+    /// The element of the scalar field of `E` with the same canonical
+    /// representation as `value`, if there is one.
+    pub fn from_base(value: E::Base) -> Option<Self> {
+        Option::<E::Scalar>::from(E::Scalar::from_bytes(&value.to_bytes())).map(Scalar)
+    }
+
+    /// The challenge this scalar is the packing of, if it fits in 128 bits.
+    pub fn to_challenge(&self) -> Result<Challenge<E::Scalar>, ChallengeOverflow> {
+        Challenge::new(self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Ec0, Fp, Fq};
+
+    #[test]
+    fn test_challenge() {
+        let packed = (1 << 127) | 12345;
+        let challenge = Challenge::<Fq>::from_u128(packed);
+        assert_eq!(challenge.packed(), Fq::from_u128(packed));
+        assert_eq!(Challenge::new(challenge.packed()), Ok(challenge));
+        assert_eq!(
+            challenge.scalar(),
+            get_challenge_scalar(Fq::from_u128(packed))
+        );
+
+        // Casting keeps the packed bits, and the scalar is computed in the
+        // new field.
+        let cast = challenge.cast::<Fp>();
+        assert_eq!(cast.to_u128(), packed);
+        assert_eq!(cast.packed(), Fp::from_u128(packed));
+        assert_eq!(cast.cast::<Fq>(), challenge);
+        assert_eq!(cast.scalar(), get_challenge_scalar(Fq::from_u128(packed)));
+
+        assert_eq!(Challenge::new(-Fq::one()), Err(ChallengeOverflow));
+        assert_eq!(
+            Challenge::new(Fq::from_u128(u128::MAX) + Fq::one()),
+            Err(ChallengeOverflow)
+        );
+    }
+
+    #[test]
+    fn test_scalar() {
+        let small = Scalar::<Ec0>::new(Fp::from(42));
+        assert_eq!(small.to_base(), Some(Fq::from(42)));
+        assert_eq!(Scalar::<Ec0>::from_base(Fq::from(42)), Some(small));
+        assert_eq!(small.to_challenge().unwrap().to_u128(), 42);
+
+        // The larger modulus has no counterpart in the smaller field.
+        let (p_minus_one, q_minus_one) = (-Fp::one(), -Fq::one());
+        let big_endian = |mut bytes: [u8; 32]| {
+            bytes.reverse();
+            bytes
+        };
+        if big_endian(p_minus_one.to_bytes()) < big_endian(q_minus_one.to_bytes()) {
+            assert!(Scalar::<Ec0>::from_base(q_minus_one).is_none());
+            assert!(Scalar::<Ec0>::new(p_minus_one).to_base().is_some());
+        } else {
+            assert!(Scalar::<Ec0>::new(p_minus_one).to_base().is_none());
+            assert!(Scalar::<Ec0>::from_base(q_minus_one).is_some());
+        }
+        assert!(Scalar::<Ec0>::new(p_minus_one).to_challenge().is_err());
+    }
+}
@@ -1,8 +1,8 @@
 use crate::fields::Field;
 use std::marker::PhantomData;
-use std::ops::{Add, Mul, Neg, Sub};
+use std::ops::{Add, Mul, Neg, Range, Sub};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Variable {
     A(usize),
     B(usize),
@@ -15,18 +15,83 @@ pub enum SynthesisError {
     DivisionByZero,
     Unsatisfiable,
     Violation,
+    BudgetExceeded,
 }
 
-use crate::AllocatedBit;
+use crate::{AllocatedBit, AllocatedNum};
+
+/// How the recursion decides whether a step is the base case, in which there
+/// is no previous proof. The verification circuit keeps its checks of the
+/// previous proof but only enforces the equalities they lead to unless it is
+/// the base case, so that the base case can be proven without one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum BaseCaseRule {
+    /// The prover chooses. Claiming the base case forces the old payload to
+    /// be the base payload, so this is sound as long as restarting from the
+    /// base payload is harmless for the application.
+    #[default]
+    Prover,
+    /// It is the base case exactly when these bits of the old payload, a
+    /// step counter, are all zero. The base payload must have a zero counter
+    /// and the circuit must never let it return to zero. A prover can still
+    /// restart from the base payload, but the restarted chain's counter then
+    /// shows how many steps it really covers. No rule on a flag can do that:
+    /// the only old payload a restart needs is the base payload, which sets
+    /// the flag anyway.
+    Counter(Range<usize>),
+}
 
 pub trait RecursiveCircuit<F: Field> {
+    /// The payload of the base case. Circuits whose payload is a `Payload`
+    /// struct can implement `StateCircuit` instead, which derives this from
+    /// a typed base state.
     fn base_payload(&self) -> Vec<bool>;
 
+    /// How base-case-ness is derived. Defaults to letting the prover choose.
+    fn base_case_rule(&self) -> BaseCaseRule {
+        BaseCaseRule::Prover
+    }
+
+    /// How many challenges `synthesize` is given. Defaults to none.
+    fn num_challenges(&self) -> usize {
+        0
+    }
+
+    /// How many auxiliary commitments each step binds besides its payload,
+    /// such as a commitment to data made available elsewhere. Each is 32
+    /// bytes, which follow the payload in the public inputs of a proof and
+    /// in `RecursiveProof::payload`. Defaults to none.
+    fn num_aux_commitments(&self) -> usize {
+        0
+    }
+
+    /// Enforces that the auxiliary commitments of the new step, given as the
+    /// little-endian bits of each, open to values this circuit witnesses and
+    /// relates to `new_payload`. Only called if `num_aux_commitments` is not
+    /// zero. Circuits that bind any must implement it, as the default is
+    /// unsatisfiable.
+    fn synthesize_aux<CS: ConstraintSystem<F>>(
+        &self,
+        _cs: &mut CS,
+        _new_payload: &[AllocatedBit],
+        _commitments: &[&[AllocatedBit]],
+    ) -> Result<(), SynthesisError> {
+        Err(SynthesisError::Unsatisfiable)
+    }
+
+    /// Enforces the transition from `old_payload` to `new_payload`.
+    ///
+    /// `challenges` holds `num_challenges()` values squeezed from a Rescue
+    /// transcript over both payloads, for arguments such as permutation or
+    /// lookup checks that need verifier randomness. They are only random
+    /// with respect to what the payloads fix, so such an argument must be
+    /// over values the payloads commit to.
     fn synthesize<CS: ConstraintSystem<F>>(
         &self,
         cs: &mut CS,
         old_payload: &[AllocatedBit],
         new_payload: &[AllocatedBit],
+        challenges: &[AllocatedNum<F>],
     ) -> Result<(), SynthesisError>;
 }
 
@@ -95,7 +160,17 @@ pub trait ConstraintSystem<FF: Field> {
     /// Not intended for downstream use; use `namespace` instead.
     fn get_root(&mut self) -> &mut Self::Root;
 
-    /// Begin a namespace for this constraint system.
+    /// Fails with `BudgetExceeded` if the namespace `name`, a child of the
+    /// current namespace that has already been left, used more than
+    /// `max_mults` multiplication gates. Only the counting backends of `dev`
+    /// check budgets; proving ignores them.
+    fn assert_budget(&mut self, _name: &str, _max_mults: usize) -> Result<(), SynthesisError> {
+        Ok(())
+    }
+
+    /// Begin a namespace for this constraint system. As with annotations,
+    /// `name_fn` is only invoked by backends that keep names, such as those
+    /// of `dev`, so formatting a name per bit costs nothing when proving.
     fn namespace<'a, NR, N>(&'a mut self, name_fn: N) -> Namespace<'a, FF, Self::Root>
     where
         NR: Into<String>,
@@ -170,6 +245,10 @@ impl<'cs, FF: Field, CS: ConstraintSystem<FF>> ConstraintSystem<FF> for Namespac
     fn get_root(&mut self) -> &mut Self::Root {
         self.0.get_root()
     }
+
+    fn assert_budget(&mut self, name: &str, max_mults: usize) -> Result<(), SynthesisError> {
+        self.0.assert_budget(name, max_mults)
+    }
 }
 
 impl<'a, FF: Field, CS: ConstraintSystem<FF>> Drop for Namespace<'a, FF, CS> {
@@ -262,6 +341,10 @@ impl<'cs, FF: Field, CS: ConstraintSystem<FF>> ConstraintSystem<FF> for &'cs mut
     fn get_root(&mut self) -> &mut Self::Root {
         (**self).get_root()
     }
+
+    fn assert_budget(&mut self, name: &str, max_mults: usize) -> Result<(), SynthesisError> {
+        (**self).assert_budget(name, max_mults)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
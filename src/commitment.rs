@@ -0,0 +1,390 @@
+//! Commitment keys derived from the generators of [`Params`].
+//!
+//! Proofs commit to vectors with the generators of their `Params` directly.
+//! Protocols built on the same commitments sometimes need related keys: a
+//! key shifted along the generators commits to a polynomial multiplied by a
+//! power of X, which is how a degree bound is enforced, and a key whose
+//! generators are scaled by powers of a scalar commits to a polynomial
+//! evaluated at a multiple of X. A [`CommitmentKey`] is such a key.
+//!
+//! A [`BoundedCommitment`] uses a shifted key to commit to a polynomial
+//! together with a bound on its degree, which opening it enforces.
+//!
+//! Commitments made with `Params::commit` are binding only: anyone can tell
+//! whether they commit to a given vector. `Params::commit_blinded` commits
+//! with a [`Blinding`] of the caller's choice, either binding only or hiding
+//! behind a random multiple of `Params::blinding_generator`. Hiding costs
+//! the prover a scalar multiplication per commitment, and the blinding
+//! factor is revealed to the verifier when the commitment is opened.
+
+use crate::curves::*;
+use crate::fields::Field;
+use crate::proofs::{Params, PolynomialOpening};
+use crate::util;
+
+/// Whether a commitment hides the vector it commits to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blinding<F: Field> {
+    /// The commitment is determined by the vector.
+    Binding,
+    /// The commitment adds this multiple of the blinding generator.
+    Hiding(F),
+}
+
+impl<F: Field> Blinding<F> {
+    /// A hiding blinding with a random factor.
+    #[cfg(feature = "prover")]
+    pub fn random() -> Self {
+        use rand_core::{OsRng, RngCore};
+
+        let mut bytes = [0u8; 32];
+        loop {
+            OsRng.fill_bytes(&mut bytes);
+            // The moduli of the Tweedle fields are just above 2^254.
+            bytes[31] &= 0x3f;
+            let factor = F::from_bytes(&bytes);
+            if bool::from(factor.is_some()) {
+                return Blinding::Hiding(factor.unwrap());
+            }
+        }
+    }
+
+    /// The blinding factor, which is zero for a binding-only commitment.
+    pub fn factor(&self) -> F {
+        match self {
+            Blinding::Binding => F::zero(),
+            Blinding::Hiding(factor) => *factor,
+        }
+    }
+}
+
+/// A sequence of generators that vectors of scalars are committed to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitmentKey<C: Curve> {
+    pub generators: Vec<C::Affine>,
+}
+
+impl<C: Curve> Params<C> {
+    /// The commitment key of these parameters, under which `commit` is the
+    /// same as `Params::commit` at the left edge.
+    pub fn commitment_key(&self) -> CommitmentKey<C> {
+        CommitmentKey {
+            generators: self.generators.clone(),
+        }
+    }
+
+    /// Commits to `v`, the coefficients of a polynomial p(X) of degree less
+    /// than `bound`, with the bound enforced when the commitment is opened.
+    pub fn commit_bounded(&self, v: &[C::Scalar], bound: usize) -> BoundedCommitment<C> {
+        assert!(v.len() <= bound && bound <= self.generators.len());
+        let shift = self.generators.len() - bound;
+        BoundedCommitment {
+            commitment: util::multiexp_affine(v, &self.generators[shift..shift + v.len()]),
+            bound,
+        }
+    }
+
+    /// The generator blinding factors multiply: the first valid point
+    /// encoding among SHA-256("halo blinding generator" || counter) for
+    /// successive little-endian 64-bit counters. It is the same for all
+    /// parameters, and nobody knows its discrete log relative to theirs.
+    #[cfg(feature = "verify")]
+    pub fn blinding_generator(&self) -> C {
+        use sha2::{Digest, Sha256};
+
+        let mut attempt = [0u8; 32];
+        for counter in 0u64.. {
+            let mut hasher = Sha256::new();
+            hasher.input(b"halo blinding generator");
+            hasher.input(counter.to_le_bytes());
+            attempt.copy_from_slice(&hasher.result());
+
+            let point =
+                C::from_bytes(&attempt).and_then(|p| subtle::CtOption::new(p, p.is_torsion_free()));
+            if bool::from(point.is_some()) && !bool::from(point.unwrap().is_zero()) {
+                return point.unwrap();
+            }
+        }
+        unreachable!()
+    }
+
+    /// Commits to `v` as `Params::commit` does, plus the blinding factor of
+    /// `blinding` times the blinding generator.
+    #[cfg(feature = "verify")]
+    pub fn commit_blinded(
+        &self,
+        v: &[C::Scalar],
+        right_edge: bool,
+        blinding: Blinding<C::Scalar>,
+    ) -> C {
+        let commitment = self.commit(v, right_edge);
+        match blinding {
+            Blinding::Binding => commitment,
+            Blinding::Hiding(factor) => commitment + self.blinding_generator() * factor,
+        }
+    }
+
+    /// The claim that `commitment`, made by `commit_blinded` with
+    /// `blinding`, opens to `opening` at `point`. The blinding is removed
+    /// from the commitment, so the verifier must be told its factor.
+    #[cfg(feature = "verify")]
+    pub fn blinded_opening(
+        &self,
+        commitment: C,
+        blinding: Blinding<C::Scalar>,
+        opening: C::Scalar,
+        point: C::Scalar,
+        right_edge: bool,
+    ) -> PolynomialOpening<C> {
+        let commitment = match blinding {
+            Blinding::Binding => commitment,
+            Blinding::Hiding(factor) => commitment - self.blinding_generator() * factor,
+        };
+        PolynomialOpening::new(commitment, opening, point, right_edge)
+    }
+}
+
+impl<C: Curve> CommitmentKey<C> {
+    /// The number of scalars a vector committed under this key may have.
+    pub fn len(&self) -> usize {
+        self.generators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.generators.is_empty()
+    }
+
+    /// Commits to `v` with the first `v.len()` generators.
+    pub fn commit(&self, v: &[C::Scalar]) -> C {
+        assert!(self.generators.len() >= v.len());
+        util::multiexp_affine(v, &self.generators[0..v.len()])
+    }
+
+    /// The key with the first `shift` generators dropped. Committing to `v`
+    /// under it commits to `v` preceded by `shift` zeroes under this key, or
+    /// for the coefficients of p(X), to X^shift p(X).
+    pub fn shift(&self, shift: usize) -> Self {
+        assert!(shift <= self.generators.len());
+        CommitmentKey {
+            generators: self.generators[shift..].to_vec(),
+        }
+    }
+
+    /// The key whose generators are `factor` times these. Commitments under
+    /// it are `factor` times commitments under this key.
+    pub fn scale(&self, factor: C::Scalar) -> Self {
+        self.map(|gen| gen * factor)
+    }
+
+    /// The key whose `i`th generator is `factor^i` times this key's. For
+    /// the coefficients of p(X), committing under it commits to p(factor X)
+    /// under this key.
+    pub fn scale_powers(&self, factor: C::Scalar) -> Self {
+        let mut power = C::Scalar::one();
+        self.map(|gen| {
+            let scaled = gen * power;
+            power *= factor;
+            scaled
+        })
+    }
+
+    fn map<F: FnMut(C::Affine) -> C>(&self, mut f: F) -> Self {
+        let projective: Vec<C> = self.generators.iter().map(|gen| f(*gen)).collect();
+        let mut generators = vec![C::Affine::zero(); projective.len()];
+        C::batch_normalize(&projective, &mut generators);
+        CommitmentKey { generators }
+    }
+}
+
+/// A commitment to a polynomial p(X) of degree less than `bound`, made as
+/// a commitment to X^(d - bound) p(X) where d is the number of generators.
+/// Coefficients of p(X) at or above the bound would need generators past
+/// the last, so a valid opening shows that the bound holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundedCommitment<C: Curve> {
+    pub commitment: C,
+    pub bound: usize,
+}
+
+impl<C: Curve> BoundedCommitment<C> {
+    /// The claim, for the inner product argument, that the committed
+    /// polynomial is `value` at `point`.
+    pub fn opening(
+        &self,
+        params: &Params<C>,
+        point: C::Scalar,
+        value: C::Scalar,
+    ) -> PolynomialOpening<C> {
+        let shift = (params.generators.len() - self.bound) as u64;
+        PolynomialOpening::new(
+            self.commitment,
+            value * point.pow(&[shift, 0, 0, 0]),
+            point,
+            true,
+        )
+    }
+
+    /// The coefficients `MultiPolynomialOpening::new_proof` proves an
+    /// `opening` of this commitment with: `v` padded with zeroes up to the
+    /// bound, so that it ends at the last generator.
+    pub fn coefficients(&self, v: &[C::Scalar]) -> Vec<C::Scalar> {
+        assert!(v.len() <= self.bound);
+        let mut coefficients = v.to_vec();
+        coefficients.resize(self.bound, C::Scalar::zero());
+        coefficients
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::fields::Fq;
+    use crate::proofs::MultiPolynomialOpening;
+    use crate::rescue::Rescue;
+    use crate::SecurityLevel;
+
+    #[test]
+    fn test_commitment_key() {
+        let params = Params::<Ec1>::from_seed(4, b"commitment key");
+        let key = params.commitment_key();
+        assert_eq!(key.len(), params.d);
+
+        let v: Vec<_> = (0..5).map(|i| Fq::from_u64(i * 7 + 3)).collect();
+        assert_eq!(key.commit(&v), params.commit(&v, false));
+
+        // Shifting to the right edge.
+        let shifted = key.shift(params.d - v.len());
+        assert_eq!(shifted.commit(&v), params.commit(&v, true));
+        let mut padded = vec![Fq::zero(); 3];
+        padded.extend_from_slice(&v);
+        assert_eq!(key.shift(3).commit(&v), key.commit(&padded));
+        assert!(key.shift(params.d).is_empty());
+
+        let factor = Fq::from_u64(11);
+        assert_eq!(key.scale(factor).commit(&v), key.commit(&v) * factor);
+
+        let mut power = Fq::one();
+        let scaled: Vec<_> = v
+            .iter()
+            .map(|c| {
+                let scaled = *c * power;
+                power *= factor;
+                scaled
+            })
+            .collect();
+        assert_eq!(key.scale_powers(factor).commit(&v), key.commit(&scaled));
+    }
+
+    fn evaluate(v: &[Fq], point: Fq) -> Fq {
+        v.iter().rev().fold(Fq::zero(), |acc, c| acc * point + c)
+    }
+
+    #[test]
+    fn test_bounded_commitment() {
+        let params = Params::<Ec1>::from_seed(4, b"bounded commitment");
+        let v: Vec<_> = (0..5).map(|i| Fq::from_u64(i * 3 + 1)).collect();
+        let point = Fq::from_u64(9);
+        let value = evaluate(&v, point);
+
+        let bounded = params.commit_bounded(&v, 8);
+        assert_eq!(
+            bounded.commitment,
+            params.commitment_key().shift(params.d - 8).commit(&v)
+        );
+        // The whole key is a bound of d, at the right edge.
+        assert_eq!(
+            params.commit_bounded(&v, params.d).commitment,
+            params.commitment_key().commit(&v)
+        );
+
+        let coefficients = bounded.coefficients(&v);
+        let verify = |opening: PolynomialOpening<Ec1>| {
+            let (proof, _, _) = MultiPolynomialOpening::new_proof(
+                &mut Rescue::new(),
+                &[(bounded.opening(&params, point, value), &coefficients)],
+                &params.generators,
+                params.k,
+                0,
+                SecurityLevel::default(),
+            );
+            proof
+                .verify_proof(
+                    &mut Rescue::new(),
+                    &[opening],
+                    &params.generators,
+                    params.k,
+                    SecurityLevel::default(),
+                )
+                .0
+        };
+        assert!(verify(bounded.opening(&params, point, value)));
+        assert!(!verify(bounded.opening(&params, point, value + Fq::one())));
+
+        // A tighter bound than the commitment was made with does not hold.
+        let tighter = BoundedCommitment {
+            bound: 6,
+            ..bounded
+        };
+        assert!(!verify(tighter.opening(&params, point, value)));
+    }
+
+    #[test]
+    fn test_blinding() {
+        let params = Params::<Ec1>::from_seed(4, b"blinding");
+        let v: Vec<_> = (0..5).map(|i| Fq::from_u64(i * 5 + 2)).collect();
+        let point = Fq::from_u64(3);
+        let value = evaluate(&v, point);
+
+        let h = params.blinding_generator();
+        assert!(!params.generators.iter().any(|g| g.to_projective() == h));
+        assert_eq!(
+            params.commit_blinded(&v, false, Blinding::Binding),
+            params.commit(&v, false)
+        );
+        let blinding = Blinding::random();
+        let hiding = params.commit_blinded(&v, false, blinding);
+        assert_ne!(hiding, params.commit(&v, false));
+        assert_eq!(hiding, params.commit(&v, false) + h * blinding.factor());
+        assert_ne!(Blinding::<Fq>::random(), blinding);
+
+        let verify = |opening: PolynomialOpening<Ec1>| {
+            let (proof, _, _) = MultiPolynomialOpening::new_proof(
+                &mut Rescue::new(),
+                &[(
+                    PolynomialOpening::new(params.commit(&v, false), value, point, false),
+                    &v[..],
+                )],
+                &params.generators,
+                params.k,
+                0,
+                SecurityLevel::default(),
+            );
+            proof
+                .verify_proof(
+                    &mut Rescue::new(),
+                    &[opening],
+                    &params.generators,
+                    params.k,
+                    SecurityLevel::default(),
+                )
+                .0
+        };
+        assert!(verify(
+            params.blinded_opening(hiding, blinding, value, point, false)
+        ));
+        assert!(!verify(params.blinded_opening(
+            hiding,
+            Blinding::Binding,
+            value,
+            point,
+            false
+        )));
+        assert!(verify(params.blinded_opening(
+            params.commit(&v, false),
+            Blinding::Binding,
+            value,
+            point,
+            false
+        )));
+    }
+}
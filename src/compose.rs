@@ -0,0 +1,470 @@
+//! Combinators that assemble a `RecursiveCircuit` from smaller ones, routing
+//! the payload between them.
+//!
+//! - `Sequence(a, b)` runs both circuits, each over its own part of the
+//!   payload: `a`'s bits come first, followed by `b`'s.
+//! - `Conditional(flag, a, b)` runs `a` if bit `flag` of the new payload is
+//!   set and `b` otherwise, over the same payload. The constraints of the
+//!   branch not taken are switched off, which costs a multiplication gate per
+//!   linear constraint of either branch.
+//! - `Repeat(a, n)` runs `a` for `n` transitions per step. The intermediate
+//!   payloads have to be witnessed somewhere, so the payload is `n` copies of
+//!   `a`'s, holding the state after each transition; the last copy is the one
+//!   the next step starts from.
+//!
+//! The split of a payload is determined by the lengths of the inner
+//! circuits' base payloads.
+
+use crate::circuits::*;
+use crate::fields::*;
+use crate::gadgets::{AllocatedBit, AllocatedNum};
+use std::collections::HashMap;
+
+/// Runs `A` over the first part of the payload and `B` over the rest.
+pub struct Sequence<A, B>(pub A, pub B);
+
+impl<F: Field, A: RecursiveCircuit<F>, B: RecursiveCircuit<F>> RecursiveCircuit<F>
+    for Sequence<A, B>
+{
+    fn base_payload(&self) -> Vec<bool> {
+        let mut payload = self.0.base_payload();
+        payload.extend(self.1.base_payload());
+        payload
+    }
+
+    fn num_challenges(&self) -> usize {
+        self.0.num_challenges().max(self.1.num_challenges())
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        challenges: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        let split = self.0.base_payload().len();
+        let len = split + self.1.base_payload().len();
+        if old_payload.len() != len || new_payload.len() != len {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        self.0.synthesize(
+            &mut cs.namespace(|| "first"),
+            &old_payload[..split],
+            &new_payload[..split],
+            &challenges[..self.0.num_challenges()],
+        )?;
+        self.1.synthesize(
+            &mut cs.namespace(|| "second"),
+            &old_payload[split..],
+            &new_payload[split..],
+            &challenges[..self.1.num_challenges()],
+        )
+    }
+}
+
+/// Runs `A` if bit `flag` of the new payload is set and `B` otherwise. Both
+/// circuits see the whole payload and must have the same base payload.
+pub struct Conditional<A, B>(pub usize, pub A, pub B);
+
+impl<F: Field, A: RecursiveCircuit<F>, B: RecursiveCircuit<F>> RecursiveCircuit<F>
+    for Conditional<A, B>
+{
+    fn base_payload(&self) -> Vec<bool> {
+        let payload = self.1.base_payload();
+        assert_eq!(payload, self.2.base_payload());
+        assert!(self.0 < payload.len());
+        payload
+    }
+
+    fn num_challenges(&self) -> usize {
+        self.1.num_challenges().max(self.2.num_challenges())
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        challenges: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        let flag = new_payload
+            .get(self.0)
+            .ok_or(SynthesisError::Unsatisfiable)?;
+        let flag_value = flag
+            .get_value()
+            .map(|b| if b { F::one() } else { F::zero() });
+
+        let known = known_values::<F, CS>(old_payload, new_payload, challenges);
+
+        let mut taken = Gated::new(
+            cs.namespace(|| "if"),
+            LinearCombination::from(flag.get_variable()),
+            flag_value,
+            known.clone(),
+        );
+        self.1.synthesize(
+            &mut taken,
+            old_payload,
+            new_payload,
+            &challenges[..self.1.num_challenges()],
+        )?;
+        taken.finish()?;
+
+        let mut not_taken = Gated::new(
+            cs.namespace(|| "else"),
+            LinearCombination::from(CS::ONE) - flag.get_variable(),
+            flag_value.map(|v| F::one() - v),
+            known,
+        );
+        self.2.synthesize(
+            &mut not_taken,
+            old_payload,
+            new_payload,
+            &challenges[..self.2.num_challenges()],
+        )?;
+        not_taken.finish()
+    }
+}
+
+/// Runs `A` for a fixed number of transitions per step, with a payload
+/// holding the state after each of them.
+pub struct Repeat<A>(pub A, pub usize);
+
+impl<F: Field, A: RecursiveCircuit<F>> RecursiveCircuit<F> for Repeat<A> {
+    fn base_payload(&self) -> Vec<bool> {
+        assert!(self.1 > 0);
+        self.0.base_payload().repeat(self.1)
+    }
+
+    fn num_challenges(&self) -> usize {
+        self.0.num_challenges()
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        challenges: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        let len = self.0.base_payload().len();
+        if self.1 == 0 || old_payload.len() != len * self.1 || new_payload.len() != len * self.1 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut state = &old_payload[(len * (self.1 - 1))..];
+        for i in 0..self.1 {
+            let next = &new_payload[(len * i)..(len * (i + 1))];
+            self.0.synthesize(
+                &mut cs.namespace(|| format!("transition {}", i)),
+                state,
+                next,
+                challenges,
+            )?;
+            state = next;
+        }
+        Ok(())
+    }
+}
+
+/// The values of the variables that a circuit run under `Gated` can refer to
+/// without having allocated them: the payloads, the challenges and `CS::ONE`.
+pub(crate) fn known_values<F: Field, CS: ConstraintSystem<F>>(
+    old_payload: &[AllocatedBit],
+    new_payload: &[AllocatedBit],
+    challenges: &[AllocatedNum<F>],
+) -> HashMap<Variable, Option<F>> {
+    let mut known = HashMap::new();
+    known.insert(CS::ONE, Some(F::one()));
+    for bit in old_payload.iter().chain(new_payload.iter()) {
+        let value = bit
+            .get_value()
+            .map(|b| if b { F::one() } else { F::zero() });
+        known.insert(bit.get_variable(), value);
+    }
+    for challenge in challenges {
+        known.insert(challenge.get_variable(), challenge.get_value());
+    }
+    known
+}
+
+/// A constraint system whose constraints only need to hold when `condition`,
+/// which must be boolean, is one. Multiplication gates are left as they are,
+/// as the prover can always satisfy them, and each linear constraint `lc = 0`
+/// becomes `condition * lc = 0`. When the condition is zero every allocation
+/// is assigned zero, so that the switched-off circuit does not need a
+/// meaningful witness.
+pub(crate) struct Gated<F: Field, CS: ConstraintSystem<F>> {
+    cs: CS,
+    condition: LinearCombination<F>,
+    condition_value: Option<F>,
+    // The value of each variable an enforced linear combination can refer
+    // to, or None if it is not known.
+    values: HashMap<Variable, Option<F>>,
+    gates: usize,
+    // The first error from allocating a gate in `enforce_zero`, which
+    // cannot return it.
+    error: Option<SynthesisError>,
+}
+
+impl<F: Field, CS: ConstraintSystem<F>> Gated<F, CS> {
+    pub(crate) fn new(
+        cs: CS,
+        condition: LinearCombination<F>,
+        condition_value: Option<F>,
+        values: HashMap<Variable, Option<F>>,
+    ) -> Self {
+        Gated {
+            cs,
+            condition,
+            condition_value,
+            values,
+            gates: 0,
+            error: None,
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<(), SynthesisError> {
+        self.error.map_or(Ok(()), Err)
+    }
+
+    fn switched_off(&self) -> bool {
+        self.condition_value == Some(F::zero())
+    }
+
+    fn evaluate(&self, lc: &LinearCombination<F>) -> Option<F> {
+        let mut sum = F::zero();
+        for (var, coeff) in lc.as_ref() {
+            let mut value = (*self.values.get(var)?)?;
+            coeff.multiply(&mut value);
+            sum += value;
+        }
+        Some(sum)
+    }
+}
+
+impl<FF: Field, CS: ConstraintSystem<FF>> ConstraintSystem<FF> for Gated<FF, CS> {
+    type Root = Self;
+
+    const ONE: Variable = CS::ONE;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, value: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<FF, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let switched_off = self.switched_off();
+        let mut assigned = None;
+        let var = self.cs.alloc(annotation, || {
+            let value = if switched_off { FF::zero() } else { value()? };
+            assigned = Some(value);
+            Ok(value)
+        })?;
+        self.values.insert(var, assigned);
+        Ok(var)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, value: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<FF, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Public inputs are fixed by the verifier, so they are passed
+        // through whether or not the condition holds.
+        let mut assigned = None;
+        let var = self.cs.alloc_input(annotation, || {
+            let value = value()?;
+            assigned = Some(value);
+            Ok(value)
+        })?;
+        self.values.insert(var, assigned);
+        Ok(var)
+    }
+
+    fn enforce_zero(&mut self, lc: LinearCombination<FF>) {
+        let condition = self.condition_value;
+        let value = self.evaluate(&lc);
+
+        self.gates += 1;
+        let gate = self.gates;
+        let gated = self.cs.multiply(
+            || format!("condition * lc {} = 0", gate),
+            || {
+                let condition = condition.ok_or(SynthesisError::AssignmentMissing)?;
+                let value = value.ok_or(SynthesisError::AssignmentMissing)?;
+                Ok((condition, value, condition * value))
+            },
+        );
+        let (a, b, c) = match gated {
+            Ok(vars) => vars,
+            Err(e) => {
+                self.error.get_or_insert(e);
+                return;
+            }
+        };
+
+        self.cs
+            .enforce_zero(LinearCombination::from(a) - &self.condition);
+        self.cs.enforce_zero(LinearCombination::from(b) - &lc);
+        self.cs.enforce_zero(LinearCombination::from(c));
+    }
+
+    fn multiply<F, A, AR>(
+        &mut self,
+        annotation: A,
+        values: F,
+    ) -> Result<(Variable, Variable, Variable), SynthesisError>
+    where
+        F: FnOnce() -> Result<(FF, FF, FF), SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let switched_off = self.switched_off();
+        let mut assigned = None;
+        let (a, b, c) = self.cs.multiply(annotation, || {
+            let values = if switched_off {
+                (FF::zero(), FF::zero(), FF::zero())
+            } else {
+                values()?
+            };
+            assigned = Some(values);
+            Ok(values)
+        })?;
+        self.values.insert(a, assigned.map(|v| v.0));
+        self.values.insert(b, assigned.map(|v| v.1));
+        self.values.insert(c, assigned.map(|v| v.2));
+        Ok((a, b, c))
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.cs.get_root().push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.cs.get_root().pop_namespace(gadget_name)
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+
+    fn assert_budget(&mut self, name: &str, max_mults: usize) -> Result<(), SynthesisError> {
+        self.cs.get_root().assert_budget(name, max_mults)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{dev::is_satisfied, Basic, Fp};
+
+    // Adds a constant to the first byte of a two-byte payload, leaving the
+    // second byte unconstrained.
+    struct Add(u64);
+
+    impl<F: Field> RecursiveCircuit<F> for Add {
+        fn base_payload(&self) -> Vec<bool> {
+            vec![false; 16]
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            cs: &mut CS,
+            old_payload: &[AllocatedBit],
+            new_payload: &[AllocatedBit],
+            _challenges: &[AllocatedNum<F>],
+        ) -> Result<(), SynthesisError> {
+            let pack = |bits: &[AllocatedBit]| {
+                let mut lc = LinearCombination::zero();
+                let mut coeff = F::one();
+                for bit in &bits[..8] {
+                    lc = lc + (Coeff::Full(coeff), bit.get_variable());
+                    coeff = coeff + coeff;
+                }
+                lc
+            };
+
+            let old = pack(old_payload);
+            let new = pack(new_payload);
+            cs.enforce_zero(new - &old - (Coeff::Full(F::from_u64(self.0)), CS::ONE));
+
+            Ok(())
+        }
+    }
+
+    struct TestCircuit<'a, C> {
+        circuit: &'a C,
+        old: &'a [u8],
+        new: &'a [u8],
+    }
+
+    impl<'a, C: RecursiveCircuit<Fp>> Circuit<Fp> for TestCircuit<'a, C> {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut alloc = |bytes: &[u8]| {
+                let mut bits = vec![];
+                for byte in bytes {
+                    for i in 0..8 {
+                        bits.push(AllocatedBit::alloc(&mut *cs, || Ok((byte >> i) & 1 == 1))?);
+                    }
+                }
+                Ok(bits)
+            };
+            let old = alloc(self.old)?;
+            let new = alloc(self.new)?;
+
+            self.circuit.synthesize(cs, &old, &new, &[])
+        }
+    }
+
+    fn satisfied<C: RecursiveCircuit<Fp>>(circuit: &C, old: &[u8], new: &[u8]) -> bool {
+        is_satisfied::<_, _, Basic>(&TestCircuit { circuit, old, new }, &[]) == Ok(true)
+    }
+
+    #[test]
+    fn test_sequence() {
+        let circuit = Sequence(Add(1), Add(2));
+        assert_eq!(RecursiveCircuit::<Fp>::base_payload(&circuit).len(), 32);
+
+        assert!(satisfied(&circuit, &[5, 0, 7, 0], &[6, 0, 9, 0]));
+        assert!(!satisfied(&circuit, &[5, 0, 7, 0], &[6, 0, 8, 0]));
+        assert!(!satisfied(&circuit, &[5, 0, 7, 0], &[7, 0, 9, 0]));
+        assert!(!satisfied(&circuit, &[5, 0], &[6, 0]));
+    }
+
+    #[test]
+    fn test_conditional() {
+        // The first bit of the second byte selects the branch.
+        let circuit = Conditional(8, Add(1), Add(2));
+
+        assert!(satisfied(&circuit, &[5, 0], &[6, 1]));
+        assert!(satisfied(&circuit, &[5, 0], &[7, 0]));
+        assert!(!satisfied(&circuit, &[5, 0], &[7, 1]));
+        assert!(!satisfied(&circuit, &[5, 0], &[6, 0]));
+
+        // Nested conditionals switch off each other's gates.
+        let circuit = Conditional(9, Conditional(8, Add(1), Add(2)), Add(3));
+        assert!(satisfied(&circuit, &[5, 0], &[6, 3]));
+        assert!(satisfied(&circuit, &[5, 0], &[7, 2]));
+        assert!(satisfied(&circuit, &[5, 0], &[8, 1]));
+        assert!(!satisfied(&circuit, &[5, 0], &[8, 3]));
+    }
+
+    #[test]
+    fn test_repeat() {
+        let circuit = Repeat(Add(3), 2);
+        assert_eq!(RecursiveCircuit::<Fp>::base_payload(&circuit).len(), 32);
+
+        // Each step starts from the last state of the previous one.
+        assert!(satisfied(&circuit, &[0, 0, 4, 0], &[7, 0, 10, 0]));
+        assert!(!satisfied(&circuit, &[4, 0, 0, 0], &[7, 0, 10, 0]));
+        assert!(!satisfied(&circuit, &[0, 0, 4, 0], &[7, 0, 11, 0]));
+    }
+}
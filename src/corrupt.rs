@@ -0,0 +1,72 @@
+//! Deliberate corruption of proofs, so that applications can check that their
+//! handling of invalid proofs is actually exercised.
+//!
+//! Only available with the `test-utils` feature.
+
+use crate::curves::*;
+use crate::fields::*;
+use crate::recursion::RecursiveProof;
+
+/// A part of a [`RecursiveProof`] for [`RecursiveProof::corrupt`] to alter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofComponent {
+    /// The lowest bit of the payload byte at this index.
+    Payload(usize),
+    /// b(x) in the deferred checks, which `Deferred::verify` recomputes.
+    Deferred,
+    /// The first L of the inner product round at this index.
+    InnerProductRound(usize),
+}
+
+impl<E1: Curve, E2: Curve> RecursiveProof<E1, E2> {
+    /// Returns a copy of this proof with `component` altered, such that
+    /// verification fails. Panics if the component does not exist in this
+    /// proof.
+    pub fn corrupt(&self, component: ProofComponent) -> Self {
+        let mut proof = self.clone();
+
+        match component {
+            ProofComponent::Payload(i) => proof.payload[i] ^= 1,
+            ProofComponent::Deferred => {
+                proof.deferred.b_x += E2::Scalar::one();
+            }
+            ProofComponent::InnerProductRound(i) => {
+                let round = &mut proof.proof.inner_product.rounds[i];
+                round.L[0] += E1::one();
+            }
+        }
+
+        proof
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::fixtures::{phony_proof, TrivialCircuit};
+    use crate::proofs::*;
+
+    #[test]
+    fn test_corrupt() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+
+        // A plain proof wrapped with the base-case leftovers, so that each
+        // component can be checked on its own.
+        let proof = phony_proof(&e1params, &e2params, vec![0xab; 4]);
+        let inputs = [Fq::one()];
+        assert!(
+            crate::plain::verify(&e1params, &TrivialCircuit, &proof.proof, &inputs).unwrap()
+        );
+        assert!(proof.deferred.verify(e2params.k));
+
+        let bad = proof.corrupt(ProofComponent::Payload(2));
+        assert_eq!(bad.payload(), &[0xab, 0xab, 0xaa, 0xab][..]);
+
+        let bad = proof.corrupt(ProofComponent::Deferred);
+        assert!(!bad.deferred.verify(e2params.k));
+
+        let bad = proof.corrupt(ProofComponent::InnerProductRound(1));
+        assert!(!crate::plain::verify(&e1params, &TrivialCircuit, &bad.proof, &inputs).unwrap());
+    }
+}
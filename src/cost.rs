@@ -0,0 +1,280 @@
+//! Worst-case costs of verifying a recursive proof.
+//!
+//! `RecursiveProof::validate` turns away every proof whose shape differs
+//! from that of a proof of the circuit under the params, so the work of
+//! `RecursiveProof::verify` that follows depends only on `k` and the length
+//! of the payload. [`VerificationCost::estimate`] counts that work, so that
+//! a node can tell what verifying a proof will cost before it accepts one
+//! from the network.
+
+use crate::util::msm_window;
+
+// What evaluating s(X, y) for a verification circuit costs: a field
+// multiplication per linear constraint, and one per coefficient that is
+// neither one nor minus one. The verification circuit has a fixed part, a
+// part per inner product round and per challenge of the leftovers and
+// deferred checks, a check per 250-bit chunk of the old challenges it
+// compares, and parts per payload byte and per public input bit it commits
+// to in k(Y).
+const SX_MULS: u64 = 403_420;
+const SX_MULS_PER_K: u64 = 63_238;
+const SX_MULS_PER_CHALLENGE_CHUNK: u64 = 3;
+const SX_MULS_PER_PAYLOAD_BYTE: u64 = 72;
+const SX_MULS_PER_INPUT_BIT: u64 = 17;
+
+/// The operations `RecursiveProof::verify` performs once a proof has passed
+/// `validate`, in the worst case.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerificationCost {
+    /// Field multiplications in evaluating s(X, y) for the verification
+    /// circuits and in computing the coefficients of g(X). Those of the
+    /// transcript and of the challenges, which grow with `k` rather than
+    /// `2^k`, are not counted.
+    pub field_muls: u64,
+    /// Point additions and doublings, both in multiexps, counted as for one
+    /// thread and the default window, and in the scalar multiplications and
+    /// sums of commitments outside them.
+    pub point_adds: u64,
+    /// The number of bases of each multiexp.
+    pub msms: Vec<usize>,
+}
+
+impl VerificationCost {
+    /// The cost of verifying a recursive proof with `payload_len` bytes of
+    /// payload, including any auxiliary commitments, under params of size
+    /// `2^k` on both curves. The inner circuit is taken to have no
+    /// constraints of its own, the default base case rule and no
+    /// challenges; each of its linear constraints, and of its coefficients
+    /// that are neither one nor minus one, adds three field
+    /// multiplications.
+    ///
+    /// This is the cost of `RecursiveProof::verify`. A `VerifierCache`
+    /// evaluates s(X, y) from a sparse s(X, Y) it keeps instead.
+    pub fn estimate(k: usize, payload_len: usize) -> Self {
+        let d = 1 << k;
+        let n = d / 4;
+
+        // `decide_pair` checks the leftovers over each curve once, each with
+        // s(X, y_new) and g(X) committed to over every generator.
+        let checked_leftovers = 2;
+        let mut msms = vec![];
+        for _ in 0..checked_leftovers {
+            msms.push(3 * n + 1);
+            msms.push(d);
+        }
+        let field_muls = checked_leftovers * (sx_muls(k, payload_len) + (d as u64 - 1));
+
+        // Every inner product round folds L and R of five openings into
+        // their commitments, after which each is checked against the final
+        // g. Before that, seven commitments are combined into p and q.
+        let scalar_muls = 10 * k as u64 + 5 + 7;
+        let sums = 10 * k as u64 + 7;
+        // The commitment to k(Y) sums a precomputed point per nibble of the
+        // public inputs.
+        let k_commitment = 2 * input_bytes(k, payload_len) as u64;
+        let point_adds = msms.iter().map(|&m| msm_point_adds(m)).sum::<u64>()
+            + scalar_muls * SCALAR_MUL_POINT_ADDS
+            + sums
+            + k_commitment;
+
+        VerificationCost {
+            field_muls,
+            point_adds,
+            msms,
+        }
+    }
+
+    /// The number of bases of every multiexp together.
+    pub fn msm_bases(&self) -> usize {
+        self.msms.iter().sum()
+    }
+}
+
+// Point multiplication doubles and adds for each bit of the scalar after
+// the leading one.
+const SCALAR_MUL_POINT_ADDS: u64 = 2 * 255;
+
+/// The number of bytes of public input of a verification circuit: the
+/// payload, two leftovers and a deferred.
+fn input_bytes(k: usize, payload_len: usize) -> usize {
+    // s_new_commitment and g_new as coordinates, y_new and the challenges
+    // as 16 bytes each
+    let leftovers = 2 * 64 + 16 + 16 * k;
+    // x, y_old, y_cur and y_new, then 12 openings and the old and new
+    // challenges
+    let deferred = 4 * 16 + 12 * 32 + 2 * 16 * k;
+
+    payload_len + 2 * leftovers + deferred
+}
+
+/// The field multiplications of evaluating s(X, y) for a verification
+/// circuit with a trivial inner circuit.
+fn sx_muls(k: usize, payload_len: usize) -> u64 {
+    // Only as many input bits are committed to as there are generators for.
+    let input_bits = std::cmp::min(8 * input_bytes(k, payload_len), (1 << k) - 2);
+    let challenge_chunks = (128 * k).div_ceil(250);
+
+    SX_MULS
+        + SX_MULS_PER_K * k as u64
+        + SX_MULS_PER_CHALLENGE_CHUNK * challenge_chunks as u64
+        + SX_MULS_PER_PAYLOAD_BYTE * payload_len as u64
+        + SX_MULS_PER_INPUT_BIT * input_bits as u64
+}
+
+/// The point additions and doublings of a multiexp of `len` bases, if every
+/// window of every scalar is nonzero.
+fn msm_point_adds(len: usize) -> u64 {
+    let c = msm_window(len);
+    let segments = 256 / c + 1;
+    let buckets = (1 << c) - 1;
+
+    (segments * (c + len + 2 * buckets)) as u64
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::circuits::*;
+    use crate::curves::*;
+    use crate::fields::*;
+    use crate::gadgets::{AllocatedBit, AllocatedNum};
+    use crate::metrics::{clear_recorder, set_recorder, Metrics, TEST_RECORDER};
+    use crate::proofs::*;
+    use crate::recursion::VerificationCircuit;
+    use crate::synthesis::{Backend, Basic, SynthesisDriver};
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, ThreadId};
+
+    struct Bytes(usize);
+
+    impl<F: Field> RecursiveCircuit<F> for Bytes {
+        fn base_payload(&self) -> Vec<bool> {
+            vec![false; 8 * self.0]
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            _: &mut CS,
+            _: &[AllocatedBit],
+            _: &[AllocatedBit],
+            _: &[AllocatedNum<F>],
+        ) -> Result<(), SynthesisError> {
+            Ok(())
+        }
+    }
+
+    /// Counts the field multiplications `SxEval` makes.
+    #[derive(Default)]
+    struct SxMuls(u64);
+
+    impl<'a, F: Field> Backend<F> for &'a mut SxMuls {
+        type LinearConstraintIndex = ();
+
+        fn new_linear_constraint<A, AR>(&mut self, _: A)
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.0 += 1;
+        }
+
+        fn insert_coefficient(&mut self, _: Variable, coeff: Coeff<F>, _: &()) {
+            if let Coeff::Full(_) = coeff {
+                self.0 += 1;
+            }
+        }
+
+        fn get_for_q(&self, _: usize) {}
+    }
+
+    fn count_sx_muls<E1, E2>(k: usize, payload_len: usize) -> u64
+    where
+        E1: Curve<Base = <E2 as Curve>::Scalar>,
+        E2: Curve<Base = <E1 as Curve>::Scalar>,
+    {
+        let params: Params<E2> = Params::new(k);
+        let inner = Bytes(payload_len);
+        let payload = vec![0; payload_len];
+        let circuit = VerificationCircuit::<E1, E2, _>::new(k, &params, &inner, &payload);
+        let mut muls = SxMuls::default();
+        Basic::synthesize(&mut muls, &circuit).unwrap();
+        muls.0
+    }
+
+    #[test]
+    fn test_sx_muls() {
+        for &(k, payload_len) in &[(4, 1), (5, 40), (6, 3)] {
+            assert_eq!(
+                count_sx_muls::<Ec1, Ec0>(k, payload_len),
+                sx_muls(k, payload_len)
+            );
+            assert_eq!(
+                count_sx_muls::<Ec0, Ec1>(k, payload_len),
+                sx_muls(k, payload_len)
+            );
+        }
+        // With enough generators for every input bit.
+        assert_eq!(count_sx_muls::<Ec1, Ec0>(14, 2), sx_muls(14, 2));
+    }
+
+    #[test]
+    fn test_input_bytes() {
+        let k = 5;
+        let params: Params<Ec1> = Params::new(k);
+        let leftovers = Leftovers::dummy(&params).to_bytes().len();
+        let deferred = Deferred::<Fp>::dummy(k).to_bytes().len();
+        assert_eq!(input_bytes(k, 3), 3 + 2 * leftovers + deferred);
+    }
+
+    /// Records the multiexps computed on one thread.
+    struct Recorder(ThreadId, Mutex<Vec<usize>>);
+
+    impl Metrics for Recorder {
+        fn msm_size(&self, size: usize) {
+            if thread::current().id() == self.0 {
+                self.1.lock().unwrap().push(size);
+            }
+        }
+    }
+
+    struct Empty;
+
+    impl<F: Field> Circuit<F> for Empty {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, _: &mut CS) -> Result<(), SynthesisError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_leftovers_msms() {
+        let k = 4;
+        let params: Params<Ec1> = Params::new(k);
+        let leftovers = Leftovers::dummy(&params);
+
+        // Each check of leftovers is a pair of the multiexps.
+        let _lock = TEST_RECORDER.lock().unwrap();
+        let recorder = Arc::new(Recorder(thread::current().id(), Mutex::default()));
+        set_recorder(recorder.clone());
+        leftovers.verify::<_, Basic>(&params, &Empty).unwrap();
+        clear_recorder();
+
+        let cost = VerificationCost::estimate(k, 1);
+        assert_eq!(*recorder.1.lock().unwrap(), cost.msms[..2].to_vec());
+        assert_eq!(cost.msms.len(), 4);
+        assert_eq!(cost.msm_bases(), 2 * (3 * params.n + 1 + params.d));
+    }
+
+    #[test]
+    fn test_estimate() {
+        let small = VerificationCost::estimate(20, 1);
+        let large = VerificationCost::estimate(20, 1000);
+        assert!(large.field_muls > small.field_muls);
+        assert!(large.point_adds > small.point_adds);
+        assert_eq!(large.msms, small.msms);
+
+        // The multiexps over every generator dominate.
+        let wider = VerificationCost::estimate(21, 1);
+        assert!(wider.point_adds > 3 * small.point_adds / 2);
+        assert_eq!(wider.msm_bases(), 2 * small.msm_bases() - 2);
+    }
+}
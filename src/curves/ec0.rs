@@ -1,4 +1,4 @@
-use crate::{Curve, Field, Fp, Fq};
+use crate::{Curve, CurveAffine, Field, Fp, Fq};
 use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
 
@@ -44,10 +44,14 @@ impl ConditionallySelectable for Ec0 {
 impl Curve for Ec0 {
     type Scalar = Fp;
     type Base = Fq;
+    type Affine = Ec0Affine;
 
     const BETA_SCALAR: Self::Scalar = Fp::BETA;
     const BETA_BASE: Self::Base = Fq::BETA;
 
+    // The Tweedle curves have prime order.
+    const COFACTOR: u64 = 1;
+
     fn b() -> Self::Base {
         B
     }
@@ -126,6 +130,29 @@ impl Curve for Ec0 {
         })
     }
 
+    fn to_affine(&self) -> Ec0Affine {
+        let zinv = self.z.invert().unwrap_or(Fq::zero());
+        Ec0Affine {
+            x: self.x * zinv,
+            y: self.y * zinv,
+        }
+    }
+
+    fn batch_normalize(p: &[Self], q: &mut [Ec0Affine]) {
+        assert_eq!(p.len(), q.len());
+
+        // The identity has z = 0, which batch inversion leaves alone, so it
+        // is mapped to (0, 0).
+        let mut zinvs: Vec<_> = p.iter().map(|p| p.z).collect();
+        Fq::batch_invert(&mut zinvs);
+        for ((p, q), zinv) in p.iter().zip(q.iter_mut()).zip(zinvs) {
+            *q = Ec0Affine {
+                x: p.x * zinv,
+                y: p.y * zinv,
+            };
+        }
+    }
+
     fn get_xyz(&self) -> (Self::Base, Self::Base, Self::Base) {
         (self.x, self.y, self.z)
     }
@@ -320,6 +347,190 @@ impl<'a, 'b> Mul<&'b Fp> for &'a Ec0 {
 impl_binops_additive!(Ec0, Ec0);
 impl_binops_multiplicative!(Ec0, Fp);
 
+/// A point of `Ec0` in affine coordinates, with the identity represented
+/// as (0, 0) (which is not on the curve).
+#[derive(Default, Eq, Debug, Copy, Clone)]
+pub struct Ec0Affine {
+    x: Fq,
+    y: Fq,
+}
+
+impl ConstantTimeEq for Ec0Affine {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.x.ct_eq(&other.x) & self.y.ct_eq(&other.y)
+    }
+}
+
+impl PartialEq for Ec0Affine {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).unwrap_u8() == 1
+    }
+}
+
+impl ConditionallySelectable for Ec0Affine {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Ec0Affine {
+            x: Fq::conditional_select(&a.x, &b.x, choice),
+            y: Fq::conditional_select(&a.y, &b.y, choice),
+        }
+    }
+}
+
+impl CurveAffine for Ec0Affine {
+    type Projective = Ec0;
+    type Scalar = Fp;
+    type Base = Fq;
+
+    fn zero() -> Self {
+        Ec0Affine {
+            x: Fq::zero(),
+            y: Fq::zero(),
+        }
+    }
+
+    fn one() -> Self {
+        Ec0::one().to_affine()
+    }
+
+    fn is_zero(&self) -> Choice {
+        self.x.is_zero() & self.y.is_zero()
+    }
+
+    fn to_projective(&self) -> Ec0 {
+        Ec0::conditional_select(
+            &Ec0 {
+                x: self.x,
+                y: self.y,
+                z: Fq::one(),
+            },
+            &Ec0::zero(),
+            self.is_zero(),
+        )
+    }
+
+    fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
+        // Decompression always produces z = 1 for points other than the
+        // identity, so no inversion is needed.
+        Ec0::from_bytes(bytes).map(|p| {
+            Ec0Affine::conditional_select(&Ec0Affine { x: p.x, y: p.y }, &Self::zero(), p.is_zero())
+        })
+    }
+
+    fn to_bytes(&self) -> [u8; 32] {
+        self.to_projective().to_bytes()
+    }
+
+    fn get_xy(&self) -> CtOption<(Fq, Fq)> {
+        CtOption::new((self.x, self.y), !self.is_zero())
+    }
+
+    fn from_xy(x: Fq, y: Fq) -> CtOption<Self> {
+        Ec0::from_xy(x, y).map(|p| p.to_affine())
+    }
+}
+
+impl From<Ec0Affine> for Ec0 {
+    fn from(p: Ec0Affine) -> Ec0 {
+        p.to_projective()
+    }
+}
+
+impl From<&Ec0Affine> for Ec0 {
+    fn from(p: &Ec0Affine) -> Ec0 {
+        p.to_projective()
+    }
+}
+
+impl Neg for &Ec0Affine {
+    type Output = Ec0Affine;
+
+    #[inline]
+    fn neg(self) -> Ec0Affine {
+        Ec0Affine {
+            x: self.x,
+            y: -self.y,
+        }
+    }
+}
+
+impl Neg for Ec0Affine {
+    type Output = Ec0Affine;
+
+    #[inline]
+    fn neg(self) -> Ec0Affine {
+        -&self
+    }
+}
+
+impl Add<&Ec0Affine> for &Ec0 {
+    type Output = Ec0;
+
+    #[inline]
+    fn add(self, rhs: &Ec0Affine) -> Ec0 {
+        // Algorithm 8, https://eprint.iacr.org/2015/1060.pdf
+
+        let t0 = self.x * rhs.x;
+        let t1 = self.y * rhs.y;
+        let t3 = rhs.x + rhs.y;
+        let t4 = self.x + self.y;
+        let t3 = t3 * t4;
+        let t4 = t0 + t1;
+        let t3 = t3 - t4;
+        let t4 = rhs.y * self.z;
+        let t4 = t4 + self.y;
+        let y3 = rhs.x * self.z;
+        let y3 = y3 + self.x;
+        let x3 = t0 + t0;
+        let t0 = x3 + t0;
+        let t2 = mul_by_3b(self.z);
+        let z3 = t1 + t2;
+        let t1 = t1 - t2;
+        let y3 = mul_by_3b(y3);
+        let x3 = t4 * y3;
+        let t2 = t3 * t1;
+        let x3 = t2 - x3;
+        let y3 = y3 * t0;
+        let t1 = t1 * z3;
+        let y3 = t1 + y3;
+        let t0 = t0 * t3;
+        let z3 = z3 * t4;
+        let z3 = z3 + t0;
+
+        // The formulas assume that rhs is not the identity.
+        Ec0::conditional_select(
+            &Ec0 {
+                x: x3,
+                y: y3,
+                z: z3,
+            },
+            self,
+            rhs.is_zero(),
+        )
+    }
+}
+
+impl Sub<&Ec0Affine> for &Ec0 {
+    type Output = Ec0;
+
+    #[inline]
+    fn sub(self, rhs: &Ec0Affine) -> Ec0 {
+        self + (-rhs)
+    }
+}
+
+impl Mul<&Fp> for &Ec0Affine {
+    type Output = Ec0;
+
+    #[inline]
+    fn mul(self, rhs: &Fp) -> Ec0 {
+        self.to_projective() * rhs
+    }
+}
+
+impl_binops_additive!(Ec0, Ec0Affine);
+impl_binops_multiplicative_mixed!(Ec0Affine, Fp, Ec0);
+
 #[test]
 fn test_curve() {
     let a = Ec0::one();
@@ -375,3 +586,30 @@ fn test_endo() {
     let x = x * Ec0::BETA_BASE;
     assert_eq!(g * Ec0::BETA_SCALAR, Ec0::from_xy_unchecked(x, y));
 }
+
+#[test]
+fn test_affine() {
+    let g = Ec0::one();
+    let points = [Ec0::zero(), g, g.double(), g * Fp::from_u64(1000), -g];
+
+    let mut affine = [Ec0Affine::zero(); 5];
+    Ec0::batch_normalize(&points, &mut affine);
+    for (p, a) in points.iter().zip(affine.iter()) {
+        assert_eq!(p.to_affine(), *a);
+        assert_eq!(a.to_projective(), *p);
+        assert_eq!(Ec0Affine::from_bytes(&a.to_bytes()).unwrap(), *a);
+    }
+    assert!(bool::from(affine[0].is_zero()));
+    assert!(bool::from(affine[0].get_xy().is_none()));
+    assert_eq!(affine[1], Ec0Affine::one());
+
+    // Mixed addition agrees with projective addition, including doubling
+    // and sums involving the identity on either side.
+    for p in &points {
+        for a in &affine {
+            assert_eq!(*p + *a, *p + a.to_projective());
+            assert_eq!(*p - *a, *p - a.to_projective());
+        }
+    }
+    assert_eq!(affine[3] * Fp::from_u64(3), points[3] * Fp::from_u64(3));
+}
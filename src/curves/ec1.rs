@@ -1,4 +1,4 @@
-use crate::{Curve, Field, Fp, Fq};
+use crate::{Curve, CurveAffine, Field, Fp, Fq};
 use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
 
@@ -44,10 +44,14 @@ impl ConditionallySelectable for Ec1 {
 impl Curve for Ec1 {
     type Scalar = Fq;
     type Base = Fp;
+    type Affine = Ec1Affine;
 
     const BETA_SCALAR: Self::Scalar = Fq::BETA;
     const BETA_BASE: Self::Base = Fp::BETA;
 
+    // The Tweedle curves have prime order.
+    const COFACTOR: u64 = 1;
+
     fn b() -> Self::Base {
         B
     }
@@ -126,6 +130,29 @@ impl Curve for Ec1 {
         })
     }
 
+    fn to_affine(&self) -> Ec1Affine {
+        let zinv = self.z.invert().unwrap_or(Fp::zero());
+        Ec1Affine {
+            x: self.x * zinv,
+            y: self.y * zinv,
+        }
+    }
+
+    fn batch_normalize(p: &[Self], q: &mut [Ec1Affine]) {
+        assert_eq!(p.len(), q.len());
+
+        // The identity has z = 0, which batch inversion leaves alone, so it
+        // is mapped to (0, 0).
+        let mut zinvs: Vec<_> = p.iter().map(|p| p.z).collect();
+        Fp::batch_invert(&mut zinvs);
+        for ((p, q), zinv) in p.iter().zip(q.iter_mut()).zip(zinvs) {
+            *q = Ec1Affine {
+                x: p.x * zinv,
+                y: p.y * zinv,
+            };
+        }
+    }
+
     fn get_xyz(&self) -> (Self::Base, Self::Base, Self::Base) {
         (self.x, self.y, self.z)
     }
@@ -320,6 +347,190 @@ impl<'a, 'b> Mul<&'b Fq> for &'a Ec1 {
 impl_binops_additive!(Ec1, Ec1);
 impl_binops_multiplicative!(Ec1, Fq);
 
+/// A point of `Ec1` in affine coordinates, with the identity represented
+/// as (0, 0) (which is not on the curve).
+#[derive(Default, Eq, Debug, Copy, Clone)]
+pub struct Ec1Affine {
+    x: Fp,
+    y: Fp,
+}
+
+impl ConstantTimeEq for Ec1Affine {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.x.ct_eq(&other.x) & self.y.ct_eq(&other.y)
+    }
+}
+
+impl PartialEq for Ec1Affine {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).unwrap_u8() == 1
+    }
+}
+
+impl ConditionallySelectable for Ec1Affine {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Ec1Affine {
+            x: Fp::conditional_select(&a.x, &b.x, choice),
+            y: Fp::conditional_select(&a.y, &b.y, choice),
+        }
+    }
+}
+
+impl CurveAffine for Ec1Affine {
+    type Projective = Ec1;
+    type Scalar = Fq;
+    type Base = Fp;
+
+    fn zero() -> Self {
+        Ec1Affine {
+            x: Fp::zero(),
+            y: Fp::zero(),
+        }
+    }
+
+    fn one() -> Self {
+        Ec1::one().to_affine()
+    }
+
+    fn is_zero(&self) -> Choice {
+        self.x.is_zero() & self.y.is_zero()
+    }
+
+    fn to_projective(&self) -> Ec1 {
+        Ec1::conditional_select(
+            &Ec1 {
+                x: self.x,
+                y: self.y,
+                z: Fp::one(),
+            },
+            &Ec1::zero(),
+            self.is_zero(),
+        )
+    }
+
+    fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
+        // Decompression always produces z = 1 for points other than the
+        // identity, so no inversion is needed.
+        Ec1::from_bytes(bytes).map(|p| {
+            Ec1Affine::conditional_select(&Ec1Affine { x: p.x, y: p.y }, &Self::zero(), p.is_zero())
+        })
+    }
+
+    fn to_bytes(&self) -> [u8; 32] {
+        self.to_projective().to_bytes()
+    }
+
+    fn get_xy(&self) -> CtOption<(Fp, Fp)> {
+        CtOption::new((self.x, self.y), !self.is_zero())
+    }
+
+    fn from_xy(x: Fp, y: Fp) -> CtOption<Self> {
+        Ec1::from_xy(x, y).map(|p| p.to_affine())
+    }
+}
+
+impl From<Ec1Affine> for Ec1 {
+    fn from(p: Ec1Affine) -> Ec1 {
+        p.to_projective()
+    }
+}
+
+impl From<&Ec1Affine> for Ec1 {
+    fn from(p: &Ec1Affine) -> Ec1 {
+        p.to_projective()
+    }
+}
+
+impl Neg for &Ec1Affine {
+    type Output = Ec1Affine;
+
+    #[inline]
+    fn neg(self) -> Ec1Affine {
+        Ec1Affine {
+            x: self.x,
+            y: -self.y,
+        }
+    }
+}
+
+impl Neg for Ec1Affine {
+    type Output = Ec1Affine;
+
+    #[inline]
+    fn neg(self) -> Ec1Affine {
+        -&self
+    }
+}
+
+impl Add<&Ec1Affine> for &Ec1 {
+    type Output = Ec1;
+
+    #[inline]
+    fn add(self, rhs: &Ec1Affine) -> Ec1 {
+        // Algorithm 8, https://eprint.iacr.org/2015/1060.pdf
+
+        let t0 = self.x * rhs.x;
+        let t1 = self.y * rhs.y;
+        let t3 = rhs.x + rhs.y;
+        let t4 = self.x + self.y;
+        let t3 = t3 * t4;
+        let t4 = t0 + t1;
+        let t3 = t3 - t4;
+        let t4 = rhs.y * self.z;
+        let t4 = t4 + self.y;
+        let y3 = rhs.x * self.z;
+        let y3 = y3 + self.x;
+        let x3 = t0 + t0;
+        let t0 = x3 + t0;
+        let t2 = mul_by_3b(self.z);
+        let z3 = t1 + t2;
+        let t1 = t1 - t2;
+        let y3 = mul_by_3b(y3);
+        let x3 = t4 * y3;
+        let t2 = t3 * t1;
+        let x3 = t2 - x3;
+        let y3 = y3 * t0;
+        let t1 = t1 * z3;
+        let y3 = t1 + y3;
+        let t0 = t0 * t3;
+        let z3 = z3 * t4;
+        let z3 = z3 + t0;
+
+        // The formulas assume that rhs is not the identity.
+        Ec1::conditional_select(
+            &Ec1 {
+                x: x3,
+                y: y3,
+                z: z3,
+            },
+            self,
+            rhs.is_zero(),
+        )
+    }
+}
+
+impl Sub<&Ec1Affine> for &Ec1 {
+    type Output = Ec1;
+
+    #[inline]
+    fn sub(self, rhs: &Ec1Affine) -> Ec1 {
+        self + (-rhs)
+    }
+}
+
+impl Mul<&Fq> for &Ec1Affine {
+    type Output = Ec1;
+
+    #[inline]
+    fn mul(self, rhs: &Fq) -> Ec1 {
+        self.to_projective() * rhs
+    }
+}
+
+impl_binops_additive!(Ec1, Ec1Affine);
+impl_binops_multiplicative_mixed!(Ec1Affine, Fq, Ec1);
+
 #[test]
 fn test_curve() {
     let a = Ec1::one();
@@ -375,3 +586,30 @@ fn test_endo() {
     let x = x * Ec1::BETA_BASE;
     assert_eq!(g * Ec1::BETA_SCALAR, Ec1::from_xy_unchecked(x, y));
 }
+
+#[test]
+fn test_affine() {
+    let g = Ec1::one();
+    let points = [Ec1::zero(), g, g.double(), g * Fq::from_u64(1000), -g];
+
+    let mut affine = [Ec1Affine::zero(); 5];
+    Ec1::batch_normalize(&points, &mut affine);
+    for (p, a) in points.iter().zip(affine.iter()) {
+        assert_eq!(p.to_affine(), *a);
+        assert_eq!(a.to_projective(), *p);
+        assert_eq!(Ec1Affine::from_bytes(&a.to_bytes()).unwrap(), *a);
+    }
+    assert!(bool::from(affine[0].is_zero()));
+    assert!(bool::from(affine[0].get_xy().is_none()));
+    assert_eq!(affine[1], Ec1Affine::one());
+
+    // Mixed addition agrees with projective addition, including doubling
+    // and sums involving the identity on either side.
+    for p in &points {
+        for a in &affine {
+            assert_eq!(*p + *a, *p + a.to_projective());
+            assert_eq!(*p - *a, *p - a.to_projective());
+        }
+    }
+    assert_eq!(affine[3] * Fq::from_u64(3), points[3] * Fq::from_u64(3));
+}
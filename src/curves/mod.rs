@@ -1,5 +1,6 @@
 use crate::Field;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
+use std::io;
 use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
 
@@ -29,26 +30,101 @@ pub trait Curve:
     + Eq
     + ConditionallySelectable
     + ConstantTimeEq
+    + From<<Self as Curve>::Affine>
+    + Add<<Self as Curve>::Affine, Output = Self>
+    + Sub<<Self as Curve>::Affine, Output = Self>
+    + for<'a> Add<&'a <Self as Curve>::Affine, Output = Self>
+    + for<'a> Sub<&'a <Self as Curve>::Affine, Output = Self>
+    + AddAssign<<Self as Curve>::Affine>
+    + SubAssign<<Self as Curve>::Affine>
+    + for<'a> AddAssign<&'a <Self as Curve>::Affine>
+    + for<'a> SubAssign<&'a <Self as Curve>::Affine>
 {
     type Scalar: Field;
     type Base: Field;
 
+    /// The affine representation of points of this curve, which mixed
+    /// addition with this type accepts on the right-hand side.
+    type Affine: CurveAffine<Projective = Self, Scalar = Self::Scalar, Base = Self::Base>;
+
     const BETA_SCALAR: Self::Scalar;
     const BETA_BASE: Self::Base;
 
+    /// The number of points on the curve divided by the modulus of `Scalar`.
+    /// Proofs only use points of the subgroup of order that modulus, which
+    /// is the whole group when this is 1.
+    const COFACTOR: u64;
+
     fn zero() -> Self;
     fn one() -> Self;
 
     fn is_zero(&self) -> Choice;
 
+    /// Whether this point is in the subgroup of order the modulus of
+    /// `Scalar`. The identity always is.
+    fn is_torsion_free(&self) -> Choice {
+        if Self::COFACTOR == 1 {
+            return Choice::from(1);
+        }
+
+        // Scalar multiplication uses the canonical bits of the scalar, so
+        // this is [r - 1] P + P = [r] P.
+        (*self * -Self::Scalar::one() + self).is_zero()
+    }
+
+    /// Maps this point into the subgroup by multiplying it by the cofactor.
+    fn clear_cofactor(&self) -> Self {
+        let mut acc = Self::zero();
+        for i in (0..64).rev() {
+            acc = acc.double();
+            if (Self::COFACTOR >> i) & 1 == 1 {
+                acc += self;
+            }
+        }
+        acc
+    }
+
+    /// Decodes a point from its canonical 32-byte compressed encoding: the
+    /// little-endian x-coordinate, with the most significant bit of the
+    /// last byte set when the canonical encoding of y is odd. The identity
+    /// is encoded as all zeroes. Every other input is rejected, so each
+    /// point has exactly one encoding; see `decode` for why.
+    ///
+    /// Points outside the subgroup are not rejected; `decode` rejects them.
     fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self>;
+
+    /// Encodes this point as described in `from_bytes`.
     fn to_bytes(&self) -> [u8; 32];
 
+    /// Decodes a point as `from_bytes` does, additionally rejecting points
+    /// outside the subgroup, and reports why an encoding was rejected.
+    /// Every point read from outside the crate is decoded with this.
+    fn decode(bytes: &[u8; 32]) -> Result<Self, PointEncodingError> {
+        check_x_canonical::<Self::Base>(bytes)?;
+        let point = Self::from_bytes(bytes);
+        if bool::from(point.is_none()) {
+            return Err(PointEncodingError::NotOnCurve);
+        }
+        let point = point.unwrap();
+        if !bool::from(point.is_torsion_free()) {
+            return Err(PointEncodingError::NotInSubgroup);
+        }
+        Ok(point)
+    }
+
     /// Returns None if this is the identity (for which [`is_zero`] returns
     /// true), and a valid curve point (for which [`is_on_curve`] returns true)
     /// otherwise.
     fn get_xy(&self) -> CtOption<(Self::Base, Self::Base)>;
 
+    /// Converts this point to affine coordinates, at the cost of an
+    /// inversion.
+    fn to_affine(&self) -> Self::Affine;
+
+    /// Converts every point in `p` to affine coordinates, writing them to
+    /// `q`, using a single inversion. Panics if the lengths differ.
+    fn batch_normalize(p: &[Self], q: &mut [Self::Affine]);
+
     fn get_xyz(&self) -> (Self::Base, Self::Base, Self::Base);
     fn from_xy(x: Self::Base, y: Self::Base) -> CtOption<Self>;
     fn from_xy_unchecked(x: Self::Base, y: Self::Base) -> Self;
@@ -61,8 +137,167 @@ pub trait Curve:
     fn is_on_curve(&self) -> bool;
 }
 
+/// A curve point in affine coordinates. These take less space than
+/// `Curve` points and make for cheaper additions, but every operation other
+/// than negation produces a projective point.
+pub trait CurveAffine:
+    Sized
+    + Default
+    + Copy
+    + Clone
+    + Send
+    + Sync
+    + 'static
+    + Debug
+    + Neg<Output = Self>
+    + Mul<<Self as CurveAffine>::Scalar, Output = <Self as CurveAffine>::Projective>
+    + for<'a> Mul<&'a <Self as CurveAffine>::Scalar, Output = <Self as CurveAffine>::Projective>
+    + PartialEq
+    + Eq
+    + ConditionallySelectable
+    + ConstantTimeEq
+{
+    type Projective: Curve<Affine = Self, Scalar = Self::Scalar, Base = Self::Base>;
+    type Scalar: Field;
+    type Base: Field;
+
+    fn zero() -> Self;
+    fn one() -> Self;
+
+    fn is_zero(&self) -> Choice;
+
+    fn to_projective(&self) -> Self::Projective;
+
+    /// Whether this point is in the subgroup, as `Curve::is_torsion_free`.
+    fn is_torsion_free(&self) -> Choice {
+        self.to_projective().is_torsion_free()
+    }
+
+    /// Uses the same encoding as `Curve::from_bytes`, and likewise does not
+    /// reject points outside the subgroup.
+    fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self>;
+    fn to_bytes(&self) -> [u8; 32];
+
+    /// Decodes a point as `Curve::decode` does.
+    fn decode(bytes: &[u8; 32]) -> Result<Self, PointEncodingError> {
+        check_x_canonical::<Self::Base>(bytes)?;
+        let point = Self::from_bytes(bytes);
+        if bool::from(point.is_none()) {
+            return Err(PointEncodingError::NotOnCurve);
+        }
+        let point = point.unwrap();
+        if !bool::from(point.is_torsion_free()) {
+            return Err(PointEncodingError::NotInSubgroup);
+        }
+        Ok(point)
+    }
+
+    /// Returns None if this is the identity.
+    fn get_xy(&self) -> CtOption<(Self::Base, Self::Base)>;
+    fn from_xy(x: Self::Base, y: Self::Base) -> CtOption<Self>;
+}
+
+/// Why a point encoding was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointEncodingError {
+    /// The x-coordinate is not a canonical field element.
+    NonCanonical,
+    /// There is no point with this x-coordinate and y parity. This includes
+    /// an all-zero x-coordinate with the sign bit set, which is not a valid
+    /// encoding of the identity.
+    NotOnCurve,
+    /// The point is on the curve but outside the subgroup proofs use.
+    NotInSubgroup,
+}
+
+impl fmt::Display for PointEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PointEncodingError::NonCanonical => write!(f, "non-canonical point encoding"),
+            PointEncodingError::NotOnCurve => write!(f, "invalid curve point"),
+            PointEncodingError::NotInSubgroup => write!(f, "curve point outside the subgroup"),
+        }
+    }
+}
+
+impl std::error::Error for PointEncodingError {}
+
+impl From<PointEncodingError> for io::Error {
+    fn from(e: PointEncodingError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+fn check_x_canonical<F: Field>(bytes: &[u8; 32]) -> Result<(), PointEncodingError> {
+    let mut x = *bytes;
+    x[31] &= 0b0111_1111;
+    if bool::from(F::from_bytes(&x).is_none()) {
+        return Err(PointEncodingError::NonCanonical);
+    }
+    Ok(())
+}
+
 mod ec0;
 mod ec1;
+#[cfg(all(test, feature = "prover"))]
+pub(crate) mod reference;
 
 pub use ec0::*;
 pub use ec1::*;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check_encoding<C: Curve>() {
+        assert_eq!(C::decode(&[0; 32]), Ok(C::zero()));
+        let g = C::one() * C::Scalar::from_u64(1234);
+        assert_eq!(C::decode(&g.to_bytes()), Ok(g));
+        assert_eq!(C::Affine::decode(&g.to_bytes()), Ok(g.to_affine()));
+
+        // The identity has no signed form.
+        let mut signed_identity = [0; 32];
+        signed_identity[31] = 0b1000_0000;
+        assert_eq!(C::decode(&signed_identity), Err(PointEncodingError::NotOnCurve));
+
+        // An x-coordinate equal to the modulus.
+        let mut modulus = (-C::Base::one()).to_bytes();
+        modulus[0] += 1;
+        assert_eq!(C::decode(&modulus), Err(PointEncodingError::NonCanonical));
+
+        // An x-coordinate with no point on the curve.
+        let missing = (2..)
+            .map(|x| C::Base::from_u64(x).to_bytes())
+            .find(|bytes| bool::from(C::from_bytes(bytes).is_none()))
+            .unwrap();
+        assert_eq!(C::decode(&missing), Err(PointEncodingError::NotOnCurve));
+    }
+
+    #[test]
+    fn test_point_encoding() {
+        check_encoding::<Ec0>();
+        check_encoding::<Ec1>();
+    }
+
+    fn check_subgroup<C: Curve>() {
+        assert_eq!(C::COFACTOR, 1);
+        let g = C::one() * C::Scalar::from_u64(1234);
+        for p in &[C::zero(), C::one(), g] {
+            assert!(bool::from(p.is_torsion_free()));
+            assert!(bool::from(p.to_affine().is_torsion_free()));
+            assert_eq!(p.clear_cofactor(), *p);
+            // What the check for curves with a cofactor computes.
+            assert!(bool::from((*p * -C::Scalar::one() + p).is_zero()));
+        }
+    }
+
+    #[test]
+    fn test_subgroup() {
+        check_subgroup::<Ec0>();
+        check_subgroup::<Ec1>();
+        assert_eq!(
+            PointEncodingError::NotInSubgroup.to_string(),
+            "curve point outside the subgroup"
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! A slow implementation of the group law, written from the textbook
+//! formulas so that it is easy to check by eye, to test the curve arithmetic
+//! and the curve gadgets against.
+
+use super::Curve;
+use crate::fields::Field;
+
+/// A point of y^2 = x^3 + b in affine coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RefPoint<F: Field> {
+    Identity,
+    Affine(F, F),
+}
+
+impl<F: Field> RefPoint<F> {
+    pub(crate) fn from_curve<C: Curve<Base = F>>(p: &C) -> Self {
+        let xy = p.get_xy();
+        if bool::from(xy.is_some()) {
+            let (x, y) = xy.unwrap();
+            RefPoint::Affine(x, y)
+        } else {
+            RefPoint::Identity
+        }
+    }
+
+    pub(crate) fn to_curve<C: Curve<Base = F>>(self) -> C {
+        match self {
+            RefPoint::Identity => C::zero(),
+            RefPoint::Affine(x, y) => C::from_xy(x, y).unwrap(),
+        }
+    }
+
+    /// The coordinates the curve gadgets represent this point with, which
+    /// are (0, 0) for the identity.
+    pub(crate) fn xy(self) -> (F, F) {
+        match self {
+            RefPoint::Identity => (F::zero(), F::zero()),
+            RefPoint::Affine(x, y) => (x, y),
+        }
+    }
+
+    pub(crate) fn is_on_curve(self, b: F) -> bool {
+        match self {
+            RefPoint::Identity => true,
+            RefPoint::Affine(x, y) => y.square() == x.square() * x + b,
+        }
+    }
+
+    pub(crate) fn neg(self) -> Self {
+        match self {
+            RefPoint::Identity => RefPoint::Identity,
+            RefPoint::Affine(x, y) => RefPoint::Affine(x, -y),
+        }
+    }
+
+    pub(crate) fn add(self, other: Self) -> Self {
+        match (self, other) {
+            (RefPoint::Identity, q) => q,
+            (p, RefPoint::Identity) => p,
+            (RefPoint::Affine(x1, y1), RefPoint::Affine(x2, y2)) => {
+                if x1 == x2 {
+                    // Either the same point, or one is the negation of the
+                    // other.
+                    return if y1 == y2 {
+                        self.double()
+                    } else {
+                        RefPoint::Identity
+                    };
+                }
+                let lambda = (y2 - y1) * (x2 - x1).invert().unwrap();
+                let x3 = lambda.square() - x1 - x2;
+                let y3 = lambda * (x1 - x3) - y1;
+                RefPoint::Affine(x3, y3)
+            }
+        }
+    }
+
+    pub(crate) fn double(self) -> Self {
+        match self {
+            RefPoint::Identity => RefPoint::Identity,
+            // A point of order two, which the tangent meets at infinity.
+            RefPoint::Affine(_, y) if y == F::zero() => RefPoint::Identity,
+            RefPoint::Affine(x, y) => {
+                let three = F::from_u64(3);
+                let lambda = three * x.square() * y.double().invert().unwrap();
+                let x3 = lambda.square() - x.double();
+                let y3 = lambda * (x - x3) - y;
+                RefPoint::Affine(x3, y3)
+            }
+        }
+    }
+
+    /// Multiplies by the integer whose little-endian bits are `bits`, by
+    /// double-and-add from the most significant bit.
+    pub(crate) fn multiply(self, bits: &[bool]) -> Self {
+        let mut acc = RefPoint::Identity;
+        for bit in bits.iter().rev() {
+            acc = acc.double();
+            if *bit {
+                acc = acc.add(self);
+            }
+        }
+        acc
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RefPoint;
+    use crate::curves::{Curve, CurveAffine, Ec0, Ec1};
+    use crate::fields::Field;
+    use crate::util;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn random_scalar<F: Field>(rng: &mut XorShiftRng) -> F {
+        loop {
+            let mut bytes = [0u8; 32];
+            rng.fill_bytes(&mut bytes);
+            bytes[31] &= 0x3f;
+            let scalar = F::from_bytes(&bytes);
+            if bool::from(scalar.is_some()) {
+                return scalar.unwrap();
+            }
+        }
+    }
+
+    /// Scalars that tend to hit the exceptional cases of the group law.
+    fn edge_scalars<F: Field>() -> Vec<F> {
+        vec![
+            F::zero(),
+            F::one(),
+            F::from_u64(2),
+            F::from_u64(3),
+            -F::one(),
+            -F::from_u64(2),
+            F::from_u128(1 << 127),
+        ]
+    }
+
+    fn check_group_law<C: Curve>(rng: &mut XorShiftRng) {
+        let reference = |p: &C| RefPoint::from_curve(p);
+
+        let mut points = vec![C::zero(), C::one(), -C::one(), C::one().double()];
+        for _ in 0..20 {
+            points.push(C::one() * random_scalar::<C::Scalar>(rng));
+        }
+        // Sums with a point itself, its negation and the identity.
+        let mut pairs = vec![];
+        for p in &points {
+            pairs.push((*p, *p));
+            pairs.push((*p, -*p));
+            pairs.push((*p, C::zero()));
+            pairs.push((C::zero(), *p));
+        }
+        for w in points.windows(2) {
+            pairs.push((w[0], w[1]));
+        }
+
+        for p in &points {
+            assert!(p.is_on_curve());
+            assert!(reference(p).is_on_curve(C::b()));
+            assert_eq!(reference(&-*p), reference(p).neg());
+            assert_eq!(reference(&p.double()), reference(p).double());
+            assert_eq!(reference(p).to_curve::<C>(), *p);
+        }
+
+        for (p, q) in &pairs {
+            let expected = reference(p).add(reference(q));
+            assert_eq!(reference(&(*p + *q)), expected);
+            assert_eq!(reference(&(*p + q.to_affine())), expected);
+            assert_eq!(reference(&(*p - *q)), reference(p).add(reference(q).neg()));
+            assert_eq!(
+                reference(&(*p - q.to_affine())),
+                reference(p).add(reference(q).neg())
+            );
+
+            let mut assigned = *p;
+            assigned += q;
+            assert_eq!(reference(&assigned), expected);
+        }
+
+        let mut scalars = edge_scalars::<C::Scalar>();
+        for _ in 0..10 {
+            scalars.push(random_scalar(rng));
+        }
+        for p in points.iter().take(8) {
+            for s in &scalars {
+                let expected = reference(p).multiply(&s.to_le_bits());
+                assert_eq!(reference(&(*p * *s)), expected);
+                assert_eq!(reference(&(p.to_affine() * *s)), expected);
+            }
+        }
+
+        let mut affine = vec![C::Affine::zero(); points.len()];
+        C::batch_normalize(&points, &mut affine);
+        for (p, a) in points.iter().zip(affine.iter()) {
+            assert_eq!(RefPoint::from_curve(&a.to_projective()), reference(p));
+        }
+
+        let coeffs: Vec<_> = (0..points.len())
+            .map(|i| {
+                if i % 5 == 0 {
+                    C::Scalar::zero()
+                } else {
+                    random_scalar(rng)
+                }
+            })
+            .collect();
+        let mut expected = RefPoint::Identity;
+        for (p, s) in points.iter().zip(coeffs.iter()) {
+            expected = expected.add(reference(p).multiply(&s.to_le_bits()));
+        }
+        assert_eq!(reference(&util::multiexp(&coeffs, &points)), expected);
+    }
+
+    #[test]
+    fn test_group_law() {
+        let mut rng = XorShiftRng::from_seed([
+            0x3a, 0x91, 0x0c, 0x5e, 0xd2, 0x47, 0x18, 0xb6, 0x6f, 0x23, 0xe4, 0x80, 0x9d, 0x15,
+            0xc7, 0x52,
+        ]);
+        check_group_law::<Ec0>(&mut rng);
+        check_group_law::<Ec1>(&mut rng);
+    }
+}
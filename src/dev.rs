@@ -8,12 +8,15 @@ use crate::{
     curves::Curve,
     fields::Field,
     proofs::{Deferred, Leftovers, Params},
-    recursion::{RecursiveProof, VerificationCircuit},
+    recursion::{
+        RecursiveProof, VerificationCircuit, VerificationWitness, VerifierVerificationCircuit,
+    },
     synthesis::{Backend, SynthesisDriver},
 };
 use std::collections::BTreeMap;
 use std::fmt;
-use std::marker::PhantomData;
+
+pub use crate::registry::{circuit_shape_digest, recursive_circuit_shape_digest};
 use std::ops::AddAssign;
 
 impl Variable {
@@ -73,6 +76,10 @@ pub fn is_satisfied<F: Field, C: Circuit<F>, S: SynthesisDriver>(
 
     struct Assignment<F: Field> {
         current_namespace: Vec<String>,
+        // For each open namespace, the gates before it was entered and the
+        // gates of each child it has left, for budgets.
+        namespace_start: Vec<usize>,
+        left: Vec<Vec<(String, usize)>>,
         n: usize,
         q: usize,
         a: Vec<F>,
@@ -216,15 +223,37 @@ pub fn is_satisfied<F: Field, C: Circuit<F>, S: SynthesisDriver>(
         {
             let name = name_fn().into();
             self.current_namespace.push(name);
+            self.namespace_start.push(self.n);
+            self.left.push(vec![]);
         }
 
         fn pop_namespace(&mut self, _gadget_name: Option<String>) {
-            assert!(self.current_namespace.pop().is_some());
+            let name = self.current_namespace.pop().unwrap();
+            let start = self.namespace_start.pop().unwrap();
+            self.left.pop();
+            self.left.last_mut().unwrap().push((name, self.n - start));
+        }
+
+        fn assert_budget(&mut self, name: &str, max_mults: usize) -> Result<(), SynthesisError> {
+            let (_, mults) = self
+                .left
+                .last()
+                .unwrap()
+                .iter()
+                .rev()
+                .find(|(left, _)| left == name)
+                .expect("budgeted namespace should have been left");
+            if *mults > max_mults {
+                return Err(SynthesisError::BudgetExceeded);
+            }
+            Ok(())
         }
     }
 
     let mut assignment = Assignment::<F> {
         current_namespace: vec![],
+        namespace_start: vec![],
+        left: vec![vec![]],
         n: 0,
         q: 0,
         a: vec![],
@@ -321,25 +350,19 @@ where
         ),
     };
 
-    let mut circuit = VerificationCircuit::<E1, E2, _> {
-        _marker: PhantomData,
-        params: e2params,
-        base_case: None,
-        proof: None,
-        inner_circuit: circuit,
+    let circuit = VerificationCircuit::with_witness(
+        e1params.k,
+        e2params,
+        circuit,
         new_payload,
-        forkvalues: Some(&forkvalues[..]),
-        old_leftovers: Some(old_leftovers.clone()),
-        new_leftovers: Some(new_leftovers.clone()),
-        deferred: Some(newdeferred.clone()),
-    };
-
-    if old_proof.is_some() {
-        circuit.base_case = Some(false);
-        circuit.proof = old_proof;
-    } else {
-        circuit.base_case = Some(true);
-    }
+        VerificationWitness {
+            proof: old_proof,
+            forkvalues: &forkvalues,
+            old_leftovers: old_leftovers.clone(),
+            new_leftovers: new_leftovers.clone(),
+            deferred: newdeferred.clone(),
+        },
+    );
 
     let mut inputs = vec![];
     inputs.extend(new_payload.iter().cloned());
@@ -599,6 +622,18 @@ impl fmt::Display for ConstraintCounts {
     }
 }
 
+impl ConstraintCounts {
+    /// The smallest `k` whose parameters can prove a circuit with this many
+    /// multiplication gates, which must be fewer than `2^(k - 2)`.
+    pub fn min_k(&self) -> usize {
+        let mut k = 2;
+        while self.total_mults >= 1 << (k - 2) {
+            k += 1;
+        }
+        k
+    }
+}
+
 impl AddAssign<ConstraintCounts> for ConstraintCounts {
     fn add_assign(&mut self, other: ConstraintCounts) {
         self.allocations += other.allocations;
@@ -700,6 +735,18 @@ pub fn constraint_count<F: Field, C: Circuit<F>, S: SynthesisDriver>(
             // Accumulate counts from this node into its parent.
             self.current_counts += self.count_stack.pop().unwrap_or_default();
         }
+
+        fn assert_budget(&mut self, name: &str, max_mults: usize) -> Result<(), SynthesisError> {
+            let path = compute_path(&self.current_namespace, name.to_string());
+            let (counts, _) = self
+                .counts
+                .get(&path)
+                .expect("budgeted namespace should have been left");
+            if counts.total_mults > max_mults {
+                return Err(SynthesisError::BudgetExceeded);
+            }
+            Ok(())
+        }
     }
 
     let mut assignment = Assignment {
@@ -719,6 +766,159 @@ pub fn constraint_count<F: Field, C: Circuit<F>, S: SynthesisDriver>(
     Ok(assignment.counts)
 }
 
+/// Counts the constraints of a circuit in total.
+///
+/// Unlike [`constraint_count`], this keeps nothing per namespace, so it can
+/// count circuits far too large to synthesize any other way in memory.
+pub fn constraint_totals<F: Field, C: Circuit<F>, S: SynthesisDriver>(
+    circuit: &C,
+) -> Result<ConstraintCounts, SynthesisError> {
+    struct Assignment {
+        counts: ConstraintCounts,
+    }
+
+    impl<F: Field> Backend<F> for &mut Assignment {
+        type LinearConstraintIndex = usize;
+
+        fn new_multiplication_gate<A, AR>(&mut self, annotation: Option<A>)
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.counts.total_mults += 1;
+            if annotation.is_some() {
+                self.counts.mult_constraints += 1;
+            }
+        }
+
+        fn set_var<FF, A, AR>(
+            &mut self,
+            annotation: Option<A>,
+            _var: Variable,
+            _value: FF,
+        ) -> Result<(), SynthesisError>
+        where
+            FF: FnOnce() -> Result<F, SynthesisError>,
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            if annotation.is_some() {
+                self.counts.allocations += 1;
+            }
+
+            Ok(())
+        }
+
+        fn new_linear_constraint<A, AR>(&mut self, _annotation: A) -> Self::LinearConstraintIndex
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.counts.total_lcs += 1;
+            self.counts.total_lcs
+        }
+
+        fn get_for_q(&self, q: usize) -> Self::LinearConstraintIndex {
+            q
+        }
+    }
+
+    let mut assignment = Assignment {
+        counts: ConstraintCounts::default(),
+    };
+
+    S::synthesize(&mut assignment, circuit)?;
+
+    Ok(assignment.counts)
+}
+
+/// Profiles the multiplication gates of a circuit as folded stacks, one line
+/// per namespace path with the gates synthesized directly in it, such as
+/// `verify proof;inner product;round 0 1234`.
+///
+/// This is the input format of flamegraph tools such as `flamegraph.pl` and
+/// `inferno-flamegraph`. Gates of namespaces entered more than once under the
+/// same path are summed, and paths without gates of their own are left out.
+pub fn folded_profile<F: Field, C: Circuit<F>, S: SynthesisDriver>(
+    circuit: &C,
+) -> Result<String, SynthesisError> {
+    struct Assignment {
+        stacks: BTreeMap<String, usize>,
+        current_namespace: Vec<String>,
+        count_stack: Vec<usize>,
+        current_count: usize,
+    }
+
+    impl<F: Field> Backend<F> for &mut Assignment {
+        type LinearConstraintIndex = usize;
+
+        fn new_multiplication_gate<A, AR>(&mut self, _annotation: Option<A>)
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.current_count += 1;
+        }
+
+        fn new_linear_constraint<A, AR>(&mut self, _annotation: A) -> Self::LinearConstraintIndex
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            0
+        }
+
+        fn get_for_q(&self, q: usize) -> Self::LinearConstraintIndex {
+            q
+        }
+
+        fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where
+            NR: Into<String>,
+            N: FnOnce() -> NR,
+        {
+            // Semicolons separate frames and newlines separate stacks.
+            let name = name_fn().into().replace([';', '\n'], " ");
+            self.current_namespace.push(name);
+            self.count_stack.push(self.current_count);
+            self.current_count = 0;
+        }
+
+        fn pop_namespace(&mut self, _gadget_name: Option<String>) {
+            if self.current_count > 0 {
+                *self
+                    .stacks
+                    .entry(self.current_namespace.join(";"))
+                    .or_default() += self.current_count;
+            }
+            self.current_namespace
+                .pop()
+                .expect("Should be leaving a namespace we entered");
+            self.current_count = self.count_stack.pop().unwrap_or_default();
+        }
+    }
+
+    let mut assignment = Assignment {
+        stacks: BTreeMap::default(),
+        current_namespace: vec![],
+        count_stack: vec![],
+        current_count: 0,
+    };
+
+    S::synthesize(&mut assignment, circuit)?;
+
+    // Gates outside of any namespace are attributed to the circuit itself.
+    let mut profile = String::new();
+    if assignment.current_count > 0 {
+        profile += &format!("circuit {}\n", assignment.current_count);
+    }
+    for (stack, count) in assignment.stacks {
+        profile += &format!("circuit;{} {}\n", stack, count);
+    }
+
+    Ok(profile)
+}
+
 /// Counts the constraints within each namespace of a recursive circuit.
 ///
 /// Returns a map of namespace paths, containing the number of multiplication
@@ -731,6 +931,7 @@ pub fn recursive_constraint_count<
     C: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>,
     S: SynthesisDriver,
 >(
+    e1params: &Params<E1>,
     e2params: &Params<E2>,
     circuit: &C,
     new_payload: &[u8],
@@ -739,18 +940,605 @@ where
     E1: Curve<Base = <E2 as Curve>::Scalar>,
     E2: Curve<Base = <E1 as Curve>::Scalar>,
 {
-    let circuit = VerificationCircuit::<E1, E2, _> {
-        _marker: PhantomData,
-        params: e2params,
-        base_case: None,
-        proof: None,
-        inner_circuit: circuit,
-        new_payload,
-        forkvalues: None,
-        old_leftovers: None,
-        new_leftovers: None,
-        deferred: None,
-    };
+    let circuit =
+        VerifierVerificationCircuit::<E1, E2, _>::new(e1params.k, e2params, circuit, new_payload);
 
     constraint_count::<_, _, S>(&circuit)
 }
+
+/// Profiles the multiplication gates of the verification circuit of a
+/// recursive circuit as folded stacks, as [`folded_profile`] does.
+pub fn recursive_folded_profile<
+    E1,
+    E2,
+    C: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>,
+    S: SynthesisDriver,
+>(
+    e1params: &Params<E1>,
+    e2params: &Params<E2>,
+    circuit: &C,
+    new_payload: &[u8],
+) -> Result<String, SynthesisError>
+where
+    E1: Curve<Base = <E2 as Curve>::Scalar>,
+    E2: Curve<Base = <E1 as Curve>::Scalar>,
+{
+    let circuit =
+        VerifierVerificationCircuit::<E1, E2, _>::new(e1params.k, e2params, circuit, new_payload);
+
+    folded_profile::<_, _, S>(&circuit)
+}
+
+/// The ways in which a gadget can fail a [`GadgetCheck`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum GadgetCheckError<F: Field> {
+    /// The honest witness does not satisfy the circuit.
+    Honest(SatisfactionError<F>),
+    /// The named malformed witness satisfies the circuit.
+    Malformed(String),
+    /// The circuit could not be synthesized to count its constraints.
+    Synthesis(SynthesisError),
+    /// The circuit does not use the recorded number of multiplication gates
+    /// and linear constraints.
+    Budget {
+        expected: (usize, usize),
+        actual: (usize, usize),
+    },
+}
+
+/// A soundness and cost check for a gadget, exercised through circuits that
+/// differ only in their witnesses.
+///
+/// The check passes if the honest circuit is satisfied, every malformed
+/// circuit is not, and the honest circuit uses exactly the recorded budget
+/// of multiplication gates and linear constraints. Recording the budget
+/// exactly means any change to a gadget's constraints is noticed, so that a
+/// saving can be recorded and a regression questioned.
+pub struct GadgetCheck<F: Field, C: Circuit<F>> {
+    honest: (C, Vec<F>),
+    malformed: Vec<(String, C, Vec<F>)>,
+    budget: Option<(usize, usize)>,
+}
+
+impl<F: Field, C: Circuit<F>> GadgetCheck<F, C> {
+    pub fn new(honest: C, inputs: &[F]) -> Self {
+        GadgetCheck {
+            honest: (honest, inputs.to_vec()),
+            malformed: vec![],
+            budget: None,
+        }
+    }
+
+    /// Adds a class of malformed witness that the gadget must reject.
+    pub fn malformed<N: Into<String>>(mut self, name: N, circuit: C, inputs: &[F]) -> Self {
+        self.malformed.push((name.into(), circuit, inputs.to_vec()));
+        self
+    }
+
+    /// Records the number of multiplication gates and linear constraints the
+    /// honest circuit uses.
+    pub fn budget(mut self, mults: usize, linear: usize) -> Self {
+        self.budget = Some((mults, linear));
+        self
+    }
+
+    pub fn check<S: SynthesisDriver>(&self) -> Result<(), GadgetCheckError<F>> {
+        match is_satisfied::<_, _, S>(&self.honest.0, &self.honest.1) {
+            Ok(_) => (),
+            Err(e) => return Err(GadgetCheckError::Honest(e)),
+        }
+
+        for (name, circuit, inputs) in &self.malformed {
+            if is_satisfied::<_, _, S>(circuit, inputs).is_ok() {
+                return Err(GadgetCheckError::Malformed(name.clone()));
+            }
+        }
+
+        if let Some(expected) = self.budget {
+            let counts = constraint_count::<_, _, S>(&self.honest.0)
+                .map_err(GadgetCheckError::Synthesis)?;
+            let root = &counts[""].0;
+            let actual = (root.total_mults, root.total_lcs);
+            if actual != expected {
+                return Err(GadgetCheckError::Budget { expected, actual });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        circuit_shape_digest, constraint_count, folded_profile, is_satisfied,
+        recursive_circuit_shape_digest, recursive_constraint_count, recursive_folded_profile,
+        recursive_is_satisfied, SatisfactionError,
+    };
+    use crate::{
+        circuits::{
+            BaseCaseRule, Circuit, Coeff, ConstraintSystem, RecursiveCircuit, SynthesisError,
+        },
+        curves::{Ec0, Ec1},
+        fields::{Field, Fp, Fq},
+        fixtures::seeded_params,
+        gadgets::{unpack_fe, AllocatedBit, AllocatedNum, Num, RescueCommitment},
+        proofs::{Deferred, Leftovers, Params},
+        recursion::{derive_base_case, ProverVerificationCircuit, VerificationWitness},
+        Basic,
+    };
+
+    struct ByteCircuit;
+
+    impl<F: Field> RecursiveCircuit<F> for ByteCircuit {
+        fn base_payload(&self) -> Vec<bool> {
+            vec![false; 8]
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            _cs: &mut CS,
+            _old_payload: &[AllocatedBit],
+            _new_payload: &[AllocatedBit],
+            _challenges: &[AllocatedNum<F>],
+        ) -> Result<(), SynthesisError> {
+            Ok(())
+        }
+    }
+
+    /// Like `ByteCircuit`, but with its base case derived from the payload.
+    struct RuleCircuit(BaseCaseRule);
+
+    impl<F: Field> RecursiveCircuit<F> for RuleCircuit {
+        fn base_payload(&self) -> Vec<bool> {
+            vec![false; 8]
+        }
+
+        fn base_case_rule(&self) -> BaseCaseRule {
+            self.0.clone()
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            _cs: &mut CS,
+            _old_payload: &[AllocatedBit],
+            _new_payload: &[AllocatedBit],
+            _challenges: &[AllocatedNum<F>],
+        ) -> Result<(), SynthesisError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_base_case_rule() {
+        let params0: Params<Ec0> = seeded_params(4);
+        let params1: Params<Ec1> = seeded_params(4);
+
+        for rule in &[BaseCaseRule::Prover, BaseCaseRule::Counter(2..6)] {
+            let satisfied = recursive_is_satisfied::<Ec1, Ec0, _, Basic>(
+                &params1,
+                &params0,
+                None,
+                &RuleCircuit(rule.clone()),
+                &[0],
+            );
+            assert_eq!(satisfied, Ok(true), "{:?}", rule);
+        }
+
+        // Deriving the base case adds constraints, leaving the default
+        // circuit alone.
+        let digest = |rule: BaseCaseRule| {
+            recursive_circuit_shape_digest::<Ec1, Ec0, _, Basic>(
+                &params1,
+                &params0,
+                &RuleCircuit(rule),
+                &[0],
+            )
+            .unwrap()
+        };
+        let default = recursive_circuit_shape_digest::<Ec1, Ec0, _, Basic>(
+            &params1,
+            &params0,
+            &ByteCircuit,
+            &[0],
+        )
+        .unwrap();
+        assert_eq!(digest(BaseCaseRule::Prover), default);
+        assert!(digest(BaseCaseRule::Counter(2..6)) != default);
+
+        // The derived base case must match the one the prover claims, so
+        // a later step cannot pass for the base case or the other way
+        // round. Bits outside the counter do not matter.
+        let derive = |rule: BaseCaseRule, old_payload: u8, base_case: bool| {
+            is_satisfied::<Fp, _, Basic>(
+                &DeriveCircuit {
+                    rule,
+                    old_payload,
+                    base_case,
+                },
+                &[],
+            )
+        };
+        let counter = BaseCaseRule::Counter(2..6);
+        assert_eq!(derive(counter.clone(), 0b1000_0011, true), Ok(true));
+        assert_eq!(derive(counter.clone(), 0b0000_0100, false), Ok(true));
+        assert!(matches!(
+            derive(counter.clone(), 0b1000_0011, false),
+            Err(SatisfactionError::Linear(..))
+        ));
+        // Restarting claims the base case, which a nonzero counter rules
+        // out, so a restarted chain counts its steps from zero again.
+        assert!(matches!(
+            derive(counter, 0b0010_0000, true),
+            Err(SatisfactionError::Linear(..))
+        ));
+        // The prover's claim is taken as it is.
+        assert_eq!(derive(BaseCaseRule::Prover, 0, false), Ok(true));
+        assert_eq!(derive(BaseCaseRule::Prover, 0xff, true), Ok(true));
+    }
+
+    /// An old payload and a claimed base case, tied together as the
+    /// verification circuit ties them.
+    struct DeriveCircuit {
+        rule: BaseCaseRule,
+        old_payload: u8,
+        base_case: bool,
+    }
+
+    impl<F: Field> Circuit<F> for DeriveCircuit {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let old_payload = (0..8)
+                .map(|i| {
+                    AllocatedBit::alloc(cs.namespace(|| format!("old payload bit {}", i)), || {
+                        Ok((self.old_payload >> i) & 1 == 1)
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let base_case =
+                AllocatedBit::alloc(cs.namespace(|| "base case"), || Ok(self.base_case))?;
+
+            derive_base_case(
+                cs.namespace(|| "derive base case"),
+                self.rule.clone(),
+                &[false; 8],
+                &old_payload,
+                &base_case,
+            )
+        }
+    }
+
+    /// Like `ByteCircuit`, but asks for challenges and records their values.
+    struct ChallengeCircuit(std::cell::RefCell<Vec<[u8; 32]>>);
+
+    impl<F: Field> RecursiveCircuit<F> for ChallengeCircuit {
+        fn base_payload(&self) -> Vec<bool> {
+            vec![false; 8]
+        }
+
+        fn num_challenges(&self) -> usize {
+            2
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            cs: &mut CS,
+            _old_payload: &[AllocatedBit],
+            _new_payload: &[AllocatedBit],
+            challenges: &[AllocatedNum<F>],
+        ) -> Result<(), SynthesisError> {
+            assert_eq!(challenges.len(), 2);
+            challenges[0].mul(cs.namespace(|| "use challenges"), &challenges[1])?;
+            self.0.borrow_mut().extend(
+                challenges
+                    .iter()
+                    .filter_map(|c| c.get_value().map(|v| v.to_bytes())),
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_inner_challenges() {
+        let params0: Params<Ec0> = seeded_params(4);
+        let params1: Params<Ec1> = seeded_params(4);
+
+        let challenges = |payload: u8| {
+            let circuit = ChallengeCircuit(Default::default());
+            let satisfied = recursive_is_satisfied::<Ec1, Ec0, _, Basic>(
+                &params1,
+                &params0,
+                None,
+                &circuit,
+                &[payload],
+            );
+            assert_eq!(satisfied, Ok(true));
+            circuit.0.into_inner()
+        };
+
+        // The challenges are distinct and depend on the payloads.
+        let zero = challenges(0);
+        assert_eq!(zero.len(), 2);
+        assert!(zero[0] != zero[1]);
+        assert_eq!(challenges(0), zero);
+        assert!(challenges(1) != zero);
+    }
+
+    /// Like `ByteCircuit`, but binds a commitment, under the randomness it
+    /// holds, to the byte of each payload.
+    struct AuxCircuit(u64);
+
+    impl<F: Field> RecursiveCircuit<F> for AuxCircuit {
+        fn base_payload(&self) -> Vec<bool> {
+            vec![false; 8]
+        }
+
+        fn num_aux_commitments(&self) -> usize {
+            1
+        }
+
+        fn synthesize_aux<CS: ConstraintSystem<F>>(
+            &self,
+            cs: &mut CS,
+            new_payload: &[AllocatedBit],
+            commitments: &[&[AllocatedBit]],
+        ) -> Result<(), SynthesisError> {
+            assert_eq!(new_payload.len(), 8);
+            assert_eq!(commitments.len(), 1);
+
+            let value = AllocatedNum::alloc(cs.namespace(|| "value"), || {
+                let mut byte = 0;
+                for (i, bit) in new_payload.iter().enumerate() {
+                    let bit = bit.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+                    byte |= (bit as u64) << i;
+                }
+                Ok(F::from_u64(byte))
+            })?;
+            let mut lc = value.lc();
+            for (i, bit) in new_payload.iter().enumerate() {
+                lc = lc - (Coeff::Full(F::from_u64(1 << i)), bit.get_variable());
+            }
+            cs.enforce_zero(lc);
+
+            let commitment = RescueCommitment::commit(
+                cs.namespace(|| "commit"),
+                &[value.into()],
+                Num::constant(F::from_u64(self.0)),
+            )?;
+            let bits = unpack_fe(cs.namespace(|| "unpack"), &commitment.num().into())?;
+            for (a, b) in bits.iter().zip(commitments[0].iter()) {
+                cs.enforce_zero(a.lc() - &b.lc());
+            }
+
+            Ok(())
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            _cs: &mut CS,
+            _old_payload: &[AllocatedBit],
+            _new_payload: &[AllocatedBit],
+            _challenges: &[AllocatedNum<F>],
+        ) -> Result<(), SynthesisError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_aux_commitments() {
+        let params0: Params<Ec0> = seeded_params(4);
+        let params1: Params<Ec1> = seeded_params(4);
+        let circuit = AuxCircuit(7);
+
+        let satisfied = |payload: &[u8]| {
+            recursive_is_satisfied::<Ec1, Ec0, _, Basic>(
+                &params1, &params0, None, &circuit, payload,
+            )
+        };
+        let payload = |byte: u8, commitment: Fq| {
+            let mut payload = vec![byte];
+            payload.extend_from_slice(&commitment.to_bytes());
+            payload
+        };
+        let commit = |byte: u8, randomness: u64| {
+            RescueCommitment::commit_native(&[Fq::from_u64(byte as u64)], Fq::from_u64(randomness))
+        };
+
+        assert_eq!(satisfied(&payload(42, commit(42, 7))), Ok(true));
+        // The commitment must be to this payload, under the circuit's
+        // randomness.
+        assert!(satisfied(&payload(43, commit(42, 7))) != Ok(true));
+        assert!(satisfied(&payload(42, commit(42, 8))) != Ok(true));
+        // Without the commitment, the payload is malformed.
+        assert_eq!(
+            satisfied(&[42]),
+            Err(SatisfactionError::Synthesis(SynthesisError::Unsatisfiable))
+        );
+    }
+
+    #[test]
+    fn test_shape_digest_golden() {
+        let params0: Params<Ec0> = seeded_params(4);
+        let params1: Params<Ec1> = seeded_params(4);
+
+        let digest1 = recursive_circuit_shape_digest::<Ec1, Ec0, _, Basic>(
+            &params1,
+            &params0,
+            &ByteCircuit,
+            &[0],
+        )
+        .unwrap();
+        let digest0 = recursive_circuit_shape_digest::<Ec0, Ec1, _, Basic>(
+            &params0,
+            &params1,
+            &ByteCircuit,
+            &[0],
+        )
+        .unwrap();
+
+        // If these change, proofs created before the change will no longer
+        // verify. Update them only when that is intended.
+        assert_eq!(
+            digest1,
+            hex!("ee806c9e3057b398d42aa86202676b33e4e460ee88bbf9c406a704bf69b1fb51"),
+            "Ec1 verification circuit shape changed"
+        );
+        assert_eq!(
+            digest0,
+            hex!("22ad3da28c2910ade158165fc99f99e9bdbf5e7947da9ed6b007fef0054aad7c"),
+            "Ec0 verification circuit shape changed"
+        );
+    }
+
+    /// The shape digest of the base case verification circuit as the prover
+    /// sees it, with witnesses filled in.
+    fn base_case_prover_digest(
+        params1: &Params<Ec1>,
+        params0: &Params<Ec0>,
+        new_payload: &[u8],
+    ) -> [u8; 32] {
+        let deferred = Deferred::dummy(params0.k);
+        let new_leftovers = Leftovers::dummy(params0);
+        let old_leftovers = Leftovers::dummy(params1);
+        let forkvalues = vec![0; params0.k];
+        let prover = ProverVerificationCircuit::<Ec1, Ec0, _>::with_witness(
+            params1.k,
+            params0,
+            &ByteCircuit,
+            new_payload,
+            VerificationWitness {
+                proof: None,
+                forkvalues: &forkvalues,
+                old_leftovers,
+                new_leftovers,
+                deferred,
+            },
+        );
+        circuit_shape_digest::<_, _, Basic>(&prover).unwrap()
+    }
+
+    #[test]
+    fn test_shape_digest_independent_of_witness() {
+        let params0: Params<Ec0> = seeded_params(4);
+        let params1: Params<Ec1> = seeded_params(4);
+
+        let verifier = recursive_circuit_shape_digest::<Ec1, Ec0, _, Basic>(
+            &params1,
+            &params0,
+            &ByteCircuit,
+            &[7],
+        )
+        .unwrap();
+
+        // The base case prover sees the same circuit with witnesses filled in.
+        assert_eq!(verifier, base_case_prover_digest(&params1, &params0, &[7]));
+
+        // A different payload length is a different circuit.
+        let longer = recursive_circuit_shape_digest::<Ec1, Ec0, _, Basic>(
+            &params1,
+            &params0,
+            &ByteCircuit,
+            &[7, 7],
+        );
+        assert!(longer.unwrap() != verifier);
+    }
+
+    #[test]
+    fn test_shape_digest_mixed_k() {
+        // A circuit proven at a larger k than the proofs it verifies.
+        let params0: Params<Ec0> = seeded_params(4);
+        let params1: Params<Ec1> = seeded_params(5);
+
+        let verifier = recursive_circuit_shape_digest::<Ec1, Ec0, _, Basic>(
+            &params1,
+            &params0,
+            &ByteCircuit,
+            &[7],
+        )
+        .unwrap();
+        assert_eq!(verifier, base_case_prover_digest(&params1, &params0, &[7]));
+
+        // Both values of k are part of the circuit.
+        let same_k = recursive_circuit_shape_digest::<Ec1, Ec0, _, Basic>(
+            &seeded_params(4),
+            &params0,
+            &ByteCircuit,
+            &[7],
+        )
+        .unwrap();
+        assert!(same_k != verifier);
+    }
+
+    /// Squares a number `n` times in a namespace budgeted for `budget`
+    /// multiplication gates.
+    struct BudgetCircuit {
+        n: usize,
+        budget: usize,
+    }
+
+    impl Circuit<Fp> for BudgetCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            {
+                let mut cs = cs.namespace(|| "squares");
+                let mut num = AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(Fp::from(3)))?;
+                for i in 0..self.n {
+                    num = num.mul(cs.namespace(|| format!("square {}", i)), &num)?;
+                }
+            }
+            cs.assert_budget("squares", self.budget)
+        }
+    }
+
+    #[test]
+    fn test_assert_budget() {
+        // Each square adds a gate.
+        let within = BudgetCircuit { n: 4, budget: 4 };
+        assert_eq!(is_satisfied::<_, _, Basic>(&within, &[]), Ok(true));
+        assert!(constraint_count::<_, _, Basic>(&within).is_ok());
+
+        let over = BudgetCircuit { n: 5, budget: 4 };
+        assert_eq!(
+            is_satisfied::<_, _, Basic>(&over, &[]),
+            Err(SatisfactionError::Synthesis(SynthesisError::BudgetExceeded))
+        );
+        assert_eq!(
+            constraint_count::<_, _, Basic>(&over).err(),
+            Some(SynthesisError::BudgetExceeded)
+        );
+    }
+
+    #[test]
+    fn test_folded_profile() {
+        // The gate holding the implicit input one is outside any namespace.
+        assert_eq!(
+            folded_profile::<_, _, Basic>(&BudgetCircuit { n: 3, budget: 3 }).unwrap(),
+            "circuit 1\n\
+             circuit;squares;square 0 1\n\
+             circuit;squares;square 1 1\n\
+             circuit;squares;square 2 1\n"
+        );
+
+        let params0: Params<Ec0> = seeded_params(4);
+        let params1: Params<Ec1> = seeded_params(4);
+        let profile =
+            recursive_folded_profile::<Ec1, Ec0, _, Basic>(&params1, &params0, &ByteCircuit, &[0])
+                .unwrap();
+        let counts = recursive_constraint_count::<Ec1, Ec0, _, Basic>(
+            &params1,
+            &params0,
+            &ByteCircuit,
+            &[0],
+        )
+        .unwrap();
+
+        // Every gate is in exactly one stack.
+        let total: usize = profile
+            .lines()
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(total, counts[""].0.total_mults);
+        assert!(profile
+            .lines()
+            .any(|line| line.starts_with("circuit;verify proof;inner product;round 0;")));
+    }
+}
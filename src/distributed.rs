@@ -0,0 +1,577 @@
+//! Splits the largest prover workloads (multiexps and polynomial
+//! multiplications) across worker processes or machines.
+//!
+//! A `Coordinator` holds one `Channel` per worker. Each job is cut into one
+//! piece per worker, the pieces are sent out before any result is awaited so
+//! that the workers run concurrently, and the partial results are merged
+//! locally. Workers run `serve` on their end of the channel.
+//!
+//! `Proof::new_distributed` proves with a `Coordinator`, which then computes
+//! every commitment of the proof and the t(X) product. The inner product
+//! argument and the work shared with `Proof::create_batch` are not
+//! distributed.
+
+use crate::curves::*;
+use crate::fields::*;
+use crate::proofs::Params;
+use crate::{fft, util, DecodeLimits, SynthesisError};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc;
+
+const JOB_MULTIEXP: u8 = 0;
+const JOB_MULTIPLY_POLYNOMIALS: u8 = 1;
+const JOB_SHUTDOWN: u8 = 2;
+
+/// A bidirectional, message-oriented link between the coordinator and a
+/// worker.
+pub trait Channel {
+    fn send(&mut self, msg: &[u8]) -> io::Result<()>;
+    fn recv(&mut self) -> io::Result<Vec<u8>>;
+}
+
+/// A channel over TCP. Messages are framed with a little-endian `u64`
+/// length prefix, and frames longer than any job or result for params within
+/// the given `DecodeLimits` are refused before anything is allocated for
+/// them.
+pub struct TcpChannel {
+    stream: TcpStream,
+    max_frame: usize,
+}
+
+impl TcpChannel {
+    /// Wraps `stream`, accepting frames for params up to the default
+    /// `DecodeLimits::max_k`.
+    pub fn new(stream: TcpStream) -> Self {
+        Self::with_limits(stream, &DecodeLimits::default())
+    }
+
+    pub fn with_limits(stream: TcpStream, limits: &DecodeLimits) -> Self {
+        TcpChannel {
+            stream,
+            max_frame: max_frame(limits),
+        }
+    }
+
+    pub fn into_inner(self) -> TcpStream {
+        self.stream
+    }
+}
+
+// The largest message for params of size 2^max_k: a multiexp over all of
+// the generators, a tag and two length prefixes. Polynomial multiplications
+// and their products are smaller.
+fn max_frame(limits: &DecodeLimits) -> usize {
+    u32::try_from(limits.max_k)
+        .ok()
+        .and_then(|k| 1usize.checked_shl(k))
+        .and_then(|n| n.checked_mul(64))
+        .and_then(|n| n.checked_add(17))
+        .unwrap_or(usize::MAX)
+}
+
+impl Channel for TcpChannel {
+    fn send(&mut self, msg: &[u8]) -> io::Result<()> {
+        self.stream.write_all(&(msg.len() as u64).to_le_bytes())?;
+        self.stream.write_all(msg)?;
+        self.stream.flush()
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        let mut len = [0u8; 8];
+        self.stream.read_exact(&mut len)?;
+        let len = match usize::try_from(u64::from_le_bytes(len)) {
+            Ok(len) if len <= self.max_frame => len,
+            _ => return Err(invalid_data("message too long")),
+        };
+        // The buffer grows with the bytes that actually arrive, so a peer
+        // that announces a long frame must also send it.
+        let mut msg = vec![];
+        (&mut self.stream).take(len as u64).read_to_end(&mut msg)?;
+        if msg.len() != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated message",
+            ));
+        }
+        Ok(msg)
+    }
+}
+
+/// One end of an in-process channel, for running workers on threads.
+pub struct LocalChannel {
+    tx: mpsc::Sender<Vec<u8>>,
+    rx: mpsc::Receiver<Vec<u8>>,
+}
+
+impl LocalChannel {
+    /// Creates a connected pair of channels.
+    pub fn pair() -> (LocalChannel, LocalChannel) {
+        let (tx_a, rx_b) = mpsc::channel();
+        let (tx_b, rx_a) = mpsc::channel();
+
+        (
+            LocalChannel { tx: tx_a, rx: rx_a },
+            LocalChannel { tx: tx_b, rx: rx_b },
+        )
+    }
+}
+
+impl Channel for LocalChannel {
+    fn send(&mut self, msg: &[u8]) -> io::Result<()> {
+        self.tx
+            .send(msg.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "channel closed"))
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        self.rx
+            .recv()
+            .map_err(|_| io::Error::new(io::ErrorKind::UnexpectedEof, "channel closed"))
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_len(buf: &mut Vec<u8>, len: usize) {
+    buf.extend_from_slice(&(len as u64).to_le_bytes());
+}
+
+fn write_scalars<F: Field>(buf: &mut Vec<u8>, v: &[F]) {
+    write_len(buf, v.len());
+    for v in v {
+        buf.extend_from_slice(&v.to_bytes());
+    }
+}
+
+fn write_points<C: Curve>(buf: &mut Vec<u8>, v: &[C]) {
+    write_len(buf, v.len());
+    for v in v {
+        buf.extend_from_slice(&v.to_bytes());
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.buf.len() < n {
+            return Err(invalid_data("truncated message"));
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn read_u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_32(&mut self) -> io::Result<[u8; 32]> {
+        let mut repr = [0u8; 32];
+        repr.copy_from_slice(self.take(32)?);
+        Ok(repr)
+    }
+
+    fn read_len(&mut self) -> io::Result<usize> {
+        let mut len = [0u8; 8];
+        len.copy_from_slice(self.take(8)?);
+        // Every element is 32 bytes, so the length can be checked up front
+        // rather than trusting it for an allocation. A length that does not
+        // fit in a `usize` cannot be checked after truncating it.
+        match usize::try_from(u64::from_le_bytes(len)) {
+            Ok(len) if len <= self.buf.len() / 32 => Ok(len),
+            _ => Err(invalid_data("truncated message")),
+        }
+    }
+
+    fn read_scalar<F: Field>(&mut self) -> io::Result<F> {
+        let v = F::from_bytes(&self.read_32()?);
+        if bool::from(v.is_none()) {
+            return Err(invalid_data("invalid field element"));
+        }
+        Ok(v.unwrap())
+    }
+
+    fn read_point<C: Curve>(&mut self) -> io::Result<C> {
+        Ok(C::decode(&self.read_32()?)?)
+    }
+
+    fn read_scalars<F: Field>(&mut self) -> io::Result<Vec<F>> {
+        let len = self.read_len()?;
+        (0..len).map(|_| self.read_scalar()).collect()
+    }
+
+    fn read_points<C: Curve>(&mut self) -> io::Result<Vec<C>> {
+        let len = self.read_len()?;
+        (0..len).map(|_| self.read_point()).collect()
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid_data("trailing bytes in message"))
+        }
+    }
+}
+
+/// Handles jobs arriving on `channel` until the coordinator shuts the worker
+/// down. Multiexps are performed over `C` and polynomial multiplications over
+/// its scalar field.
+pub fn serve<C: Curve, Ch: Channel>(channel: &mut Ch) -> io::Result<()> {
+    loop {
+        let msg = channel.recv()?;
+        let mut reader = Reader { buf: &msg };
+        let mut response = vec![];
+
+        match reader.read_u8()? {
+            JOB_MULTIEXP => {
+                let coeffs: Vec<C::Scalar> = reader.read_scalars()?;
+                let bases: Vec<C> = reader.read_points()?;
+                reader.finish()?;
+                if coeffs.len() != bases.len() {
+                    return Err(invalid_data("multiexp length mismatch"));
+                }
+                response.extend_from_slice(&util::multiexp(&coeffs, &bases).to_bytes());
+            }
+            JOB_MULTIPLY_POLYNOMIALS => {
+                let a: Vec<C::Scalar> = reader.read_scalars()?;
+                let b: Vec<C::Scalar> = reader.read_scalars()?;
+                reader.finish()?;
+                if a.is_empty() || b.is_empty() {
+                    return Err(invalid_data("empty polynomial"));
+                }
+                write_scalars(&mut response, &fft::multiply_polynomials(a, b));
+            }
+            JOB_SHUTDOWN => {
+                reader.finish()?;
+                return Ok(());
+            }
+            _ => return Err(invalid_data("unknown job")),
+        }
+
+        channel.send(&response)?;
+    }
+}
+
+/// Distributes jobs across a set of workers.
+pub struct Coordinator<Ch: Channel> {
+    workers: Vec<Ch>,
+}
+
+impl<Ch: Channel> Coordinator<Ch> {
+    pub fn new(workers: Vec<Ch>) -> Self {
+        assert!(!workers.is_empty());
+        Coordinator { workers }
+    }
+
+    fn chunk_size(&self, len: usize) -> usize {
+        let workers = self.workers.len();
+        std::cmp::max(1, len.div_ceil(workers))
+    }
+
+    /// Computes the same result as `util::multiexp`, with each worker
+    /// handling a contiguous slice of the terms.
+    pub fn multiexp<C: Curve>(&mut self, coeffs: &[C::Scalar], bases: &[C]) -> io::Result<C> {
+        assert_eq!(coeffs.len(), bases.len());
+
+        let chunk = self.chunk_size(coeffs.len());
+        let mut busy = 0;
+        for ((coeffs, bases), worker) in coeffs
+            .chunks(chunk)
+            .zip(bases.chunks(chunk))
+            .zip(self.workers.iter_mut())
+        {
+            let mut msg = vec![JOB_MULTIEXP];
+            write_scalars(&mut msg, coeffs);
+            write_points(&mut msg, bases);
+            worker.send(&msg)?;
+            busy += 1;
+        }
+
+        let mut acc = C::zero();
+        for worker in self.workers.iter_mut().take(busy) {
+            let msg = worker.recv()?;
+            let mut reader = Reader { buf: &msg };
+            acc += reader.read_point::<C>()?;
+            reader.finish()?;
+        }
+
+        Ok(acc)
+    }
+
+    /// Computes the same result as `fft::multiply_polynomials`. `a` is
+    /// split into pieces, each worker multiplies its piece by `b`, and the
+    /// products are added back together at their offsets.
+    pub fn multiply_polynomials<F: Field>(&mut self, a: &[F], b: &[F]) -> io::Result<Vec<F>> {
+        assert!(!a.is_empty() && !b.is_empty());
+
+        let chunk = self.chunk_size(a.len());
+        let mut busy = 0;
+        for (a, worker) in a.chunks(chunk).zip(self.workers.iter_mut()) {
+            let mut msg = vec![JOB_MULTIPLY_POLYNOMIALS];
+            write_scalars(&mut msg, a);
+            write_scalars(&mut msg, b);
+            worker.send(&msg)?;
+            busy += 1;
+        }
+
+        let mut result = vec![F::zero(); a.len() + b.len() - 1];
+        for (i, worker) in self.workers.iter_mut().take(busy).enumerate() {
+            let msg = worker.recv()?;
+            let mut reader = Reader { buf: &msg };
+            let partial: Vec<F> = reader.read_scalars()?;
+            reader.finish()?;
+
+            let offset = i * chunk;
+            if offset + partial.len() > result.len() {
+                return Err(invalid_data("worker returned an oversized product"));
+            }
+            for (acc, v) in result[offset..].iter_mut().zip(partial.iter()) {
+                *acc += v;
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Asks every worker to exit and returns their channels.
+    pub fn shutdown(mut self) -> io::Result<Vec<Ch>> {
+        for worker in self.workers.iter_mut() {
+            worker.send(&[JOB_SHUTDOWN])?;
+        }
+
+        Ok(self.workers)
+    }
+}
+
+/// Why `Proof::new_distributed` failed.
+#[derive(Debug)]
+pub enum ProvingError {
+    Synthesis(SynthesisError),
+    /// A worker could not be reached or sent back a malformed result.
+    Io(io::Error),
+}
+
+impl From<SynthesisError> for ProvingError {
+    fn from(e: SynthesisError) -> Self {
+        ProvingError::Synthesis(e)
+    }
+}
+
+impl From<io::Error> for ProvingError {
+    fn from(e: io::Error) -> Self {
+        ProvingError::Io(e)
+    }
+}
+
+/// Where the prover sends the commitments and the t(X) product of each
+/// proof: either computed in place or handed to a `Coordinator`.
+pub(crate) trait Offload<C: Curve> {
+    fn commit(&mut self, params: &Params<C>, v: &[C::Scalar], right_edge: bool) -> io::Result<C>;
+
+    fn multiply_polynomials(
+        &mut self,
+        a: Vec<C::Scalar>,
+        b: Vec<C::Scalar>,
+    ) -> io::Result<Vec<C::Scalar>>;
+}
+
+/// Computes everything on the calling thread, as `Proof::new` does.
+pub(crate) struct Local;
+
+impl<C: Curve> Offload<C> for Local {
+    fn commit(&mut self, params: &Params<C>, v: &[C::Scalar], right_edge: bool) -> io::Result<C> {
+        Ok(params.commit(v, right_edge))
+    }
+
+    fn multiply_polynomials(
+        &mut self,
+        a: Vec<C::Scalar>,
+        b: Vec<C::Scalar>,
+    ) -> io::Result<Vec<C::Scalar>> {
+        Ok(fft::multiply_polynomials(a, b))
+    }
+}
+
+impl<C: Curve, Ch: Channel> Offload<C> for Coordinator<Ch> {
+    fn commit(&mut self, params: &Params<C>, v: &[C::Scalar], right_edge: bool) -> io::Result<C> {
+        assert!(params.generators.len() >= v.len());
+        let generators = if right_edge {
+            &params.generators[(params.generators.len() - v.len())..]
+        } else {
+            &params.generators[0..v.len()]
+        };
+        let bases: Vec<C> = generators.iter().map(|g| g.to_projective()).collect();
+        self.multiexp(v, &bases)
+    }
+
+    fn multiply_polynomials(
+        &mut self,
+        a: Vec<C::Scalar>,
+        b: Vec<C::Scalar>,
+    ) -> io::Result<Vec<C::Scalar>> {
+        Coordinator::multiply_polynomials(self, &a, &b)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::proofs::{Leftovers, Proof};
+    use crate::{AllocatedNum, Basic, Circuit, ConstraintSystem};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn points(n: u64) -> Vec<Ec1> {
+        (0..n)
+            .map(|i| Ec1::one() * Fq::from_u64(i * 3 + 5))
+            .collect()
+    }
+
+    fn scalars(n: u64) -> Vec<Fq> {
+        (0..n).map(|i| Fq::from_u64(i * i + 11)).collect()
+    }
+
+    #[test]
+    fn test_local_workers() {
+        let mut workers = vec![];
+        let mut handles = vec![];
+        for _ in 0..3 {
+            let (coordinator_end, mut worker_end) = LocalChannel::pair();
+            workers.push(coordinator_end);
+            handles.push(thread::spawn(move || serve::<Ec1, _>(&mut worker_end)));
+        }
+        let mut coordinator = Coordinator::new(workers);
+
+        for &n in &[1, 2, 10, 33] {
+            let coeffs = scalars(n);
+            let bases = points(n);
+            assert_eq!(
+                coordinator.multiexp(&coeffs, &bases).unwrap(),
+                util::multiexp(&coeffs, &bases)
+            );
+
+            let b = scalars(n / 2 + 1);
+            assert_eq!(
+                coordinator.multiply_polynomials(&coeffs, &b).unwrap(),
+                fft::multiply_polynomials(coeffs.clone(), b.clone())
+            );
+        }
+
+        coordinator.shutdown().unwrap();
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_tcp_worker() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve::<Ec1, _>(&mut TcpChannel::new(stream))
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut coordinator = Coordinator::new(vec![TcpChannel::new(stream)]);
+        let coeffs = scalars(17);
+        let bases = points(17);
+        assert_eq!(
+            coordinator.multiexp(&coeffs, &bases).unwrap(),
+            util::multiexp(&coeffs, &bases)
+        );
+
+        coordinator.shutdown().unwrap();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_malformed_job() {
+        let (mut coordinator_end, mut worker_end) = LocalChannel::pair();
+        coordinator_end
+            .send(&[JOB_MULTIEXP, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff])
+            .unwrap();
+        assert!(serve::<Ec1, _>(&mut worker_end).is_err());
+    }
+
+    #[test]
+    fn test_distributed_proof() {
+        struct CubeCircuit {
+            x: Fq,
+        }
+
+        impl Circuit<Fq> for CubeCircuit {
+            fn synthesize<CS: ConstraintSystem<Fq>>(
+                &self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(self.x))?;
+                let x2 = x.mul(cs.namespace(|| "x^2"), &x)?;
+                let x3 = x2.mul(cs.namespace(|| "x^3"), &x)?;
+                x3.inputize(cs.namespace(|| "output"))?;
+
+                Ok(())
+            }
+        }
+
+        let mut workers = vec![];
+        let mut handles = vec![];
+        for _ in 0..2 {
+            let (coordinator_end, mut worker_end) = LocalChannel::pair();
+            workers.push(coordinator_end);
+            handles.push(thread::spawn(move || serve::<Ec1, _>(&mut worker_end)));
+        }
+        let mut coordinator = Coordinator::new(workers);
+
+        let params: Params<Ec1> = Params::new(5);
+        let circuit = CubeCircuit { x: Fq::from(3) };
+        let leftovers = Leftovers::dummy(&params);
+        let (local, local_leftovers) =
+            Proof::new::<_, Basic>(&params, &circuit, &leftovers).unwrap();
+        let (distributed, distributed_leftovers) =
+            Proof::new_distributed::<_, Basic, _>(&params, &circuit, &leftovers, &mut coordinator)
+                .unwrap();
+
+        let (mut a, mut b) = (vec![], vec![]);
+        local.write(&mut a).unwrap();
+        distributed.write(&mut b).unwrap();
+        assert!(a == b);
+        assert_eq!(local_leftovers, distributed_leftovers);
+
+        coordinator.shutdown().unwrap();
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_oversized_frame() {
+        let limits = DecodeLimits {
+            max_k: 4,
+            ..DecodeLimits::default()
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve::<Ec1, _>(&mut TcpChannel::with_limits(stream, &limits))
+        });
+
+        // The length prefix alone must be refused; the frame never follows.
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let len = max_frame(&limits) as u64 + 1;
+        stream.write_all(&len.to_le_bytes()).unwrap();
+        let err = handle.join().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        assert_eq!(max_frame(&DecodeLimits::unlimited()), usize::MAX);
+    }
+}
@@ -0,0 +1,257 @@
+//! A C interface for verifying recursive proofs, for platforms that only
+//! check proofs, such as mobile wallets checking the tip of a proof chain.
+//! Kotlin (through JNI or JNA) and Swift can call it directly, and binding
+//! generators can wrap it.
+//!
+//! Verifying synthesizes the verification circuit around the application's
+//! `RecursiveCircuit`, so the crate that defines the circuit exports the
+//! verify function with `export_verifier!`. It needs only the `verify`
+//! feature. The parameters it takes are made and freed by the functions
+//! here:
+//!
+//! ```c
+//! halo_params *params = halo_params_new(k, seed, seed_len);
+//! int32_t status = my_chain_verify(params, 1, proof, proof_len);
+//! halo_params_free(params);
+//! ```
+
+use crate::circuits::RecursiveCircuit;
+use crate::curves::*;
+use crate::encoding::DecodeLimits;
+use crate::fields::*;
+use crate::proofs::Params;
+use crate::recursion::RecursiveProof;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+/// The outcome of verifying a proof through this interface. Exported
+/// functions return it as an `i32`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum VerifyStatus {
+    Valid = 0,
+    /// The proof decoded but is not a valid proof of the circuit.
+    Invalid = 1,
+    /// The bytes are not the encoding of a recursive proof within the
+    /// parameters' limits.
+    Malformed = 2,
+    /// A null pointer, an unknown curve, or a failure to synthesize the
+    /// verification circuit, including params too small to hold it.
+    Error = 3,
+}
+
+/// The parameters of both curves of the cycle, derived with
+/// `Params::from_seed` from the same seed, as `halo_params_new` makes them.
+pub struct VerifierParams {
+    ec0: Params<Ec0>,
+    ec1: Params<Ec1>,
+    limits: DecodeLimits,
+}
+
+impl VerifierParams {
+    pub fn from_seed(k: usize, seed: &[u8]) -> Self {
+        VerifierParams {
+            ec0: Params::from_seed(k, seed),
+            ec1: Params::from_seed(k, seed),
+            limits: DecodeLimits {
+                max_k: k,
+                ..DecodeLimits::default()
+            },
+        }
+    }
+
+    /// Verifies the encoding of a `RecursiveProof` of `circuit`. `curve` is
+    /// the curve the proof was created over: 0 for `RecursiveProof<Ec0, Ec1>`
+    /// and 1 for `RecursiveProof<Ec1, Ec0>`, which alternate along a chain.
+    pub fn verify<CS>(&self, curve: u32, proof: &[u8], circuit: &CS) -> VerifyStatus
+    where
+        CS: RecursiveCircuit<Fp> + RecursiveCircuit<Fq>,
+    {
+        match curve {
+            0 => verify_bytes(&self.ec0, &self.ec1, &self.limits, proof, circuit),
+            1 => verify_bytes(&self.ec1, &self.ec0, &self.limits, proof, circuit),
+            _ => VerifyStatus::Error,
+        }
+    }
+}
+
+fn verify_bytes<E1, E2, CS>(
+    e1params: &Params<E1>,
+    e2params: &Params<E2>,
+    limits: &DecodeLimits,
+    proof: &[u8],
+    circuit: &CS,
+) -> VerifyStatus
+where
+    E1: Curve<Base = <E2 as Curve>::Scalar>,
+    E2: Curve<Base = <E1 as Curve>::Scalar>,
+    CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>,
+{
+    let mut reader = proof;
+    let proof = match RecursiveProof::<E1, E2>::read_with_limits(&mut reader, limits) {
+        Ok(proof) if reader.is_empty() => proof,
+        _ => return VerifyStatus::Malformed,
+    };
+
+    match proof.verify(e1params, e2params, circuit) {
+        Ok(true) => VerifyStatus::Valid,
+        Ok(false) => VerifyStatus::Invalid,
+        Err(_) => VerifyStatus::Error,
+    }
+}
+
+/// Derives the parameters for `2^k` generators from the `seed_len` bytes at
+/// `seed`. Returns null if `k` is less than 4 or larger than the default
+/// `DecodeLimits::max_k`, or if `seed` is null and `seed_len` is not zero.
+///
+/// # Safety
+///
+/// `seed` must point to `seed_len` readable bytes unless `seed_len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn halo_params_new(
+    k: u32,
+    seed: *const u8,
+    seed_len: usize,
+) -> *mut VerifierParams {
+    let k = k as usize;
+    if k < 4 || k > DecodeLimits::default().max_k || (seed.is_null() && seed_len != 0) {
+        return std::ptr::null_mut();
+    }
+    let seed = if seed_len == 0 {
+        &[][..]
+    } else {
+        slice::from_raw_parts(seed, seed_len)
+    };
+
+    match panic::catch_unwind(|| VerifierParams::from_seed(k, seed)) {
+        Ok(params) => Box::into_raw(Box::new(params)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Frees parameters made by `halo_params_new`. Does nothing if `params` is
+/// null.
+///
+/// # Safety
+///
+/// `params` must be null or returned by `halo_params_new` and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn halo_params_free(params: *mut VerifierParams) {
+    if !params.is_null() {
+        drop(Box::from_raw(params));
+    }
+}
+
+/// The body of the functions `export_verifier!` defines.
+///
+/// # Safety
+///
+/// As for those functions.
+#[doc(hidden)]
+pub unsafe fn verify_raw<CS, F>(
+    params: *const VerifierParams,
+    curve: u32,
+    proof: *const u8,
+    proof_len: usize,
+    circuit: F,
+) -> i32
+where
+    CS: RecursiveCircuit<Fp> + RecursiveCircuit<Fq>,
+    F: FnOnce() -> CS,
+{
+    if params.is_null() || proof.is_null() {
+        return VerifyStatus::Error as i32;
+    }
+    let params = &*params;
+    let proof = slice::from_raw_parts(proof, proof_len);
+
+    // Unwinding into the caller's frames is undefined behaviour.
+    panic::catch_unwind(AssertUnwindSafe(|| params.verify(curve, proof, &circuit())))
+        .unwrap_or(VerifyStatus::Error) as i32
+}
+
+/// Exports a C function named `$name` that verifies recursive proofs of the
+/// circuit `$circuit` evaluates to:
+///
+/// ```c
+/// int32_t $name(const halo_params *params, uint32_t curve,
+///               const uint8_t *proof, size_t proof_len);
+/// ```
+///
+/// It returns a `VerifyStatus`, with `curve` as `VerifierParams::verify`
+/// takes it. `params` must come from `halo_params_new` and `proof` must point
+/// to `proof_len` readable bytes.
+///
+/// ```ignore
+/// halo::export_verifier!(my_chain_verify, MyCircuit::default());
+/// ```
+#[macro_export]
+macro_rules! export_verifier {
+    ($name:ident, $circuit:expr) => {
+        /// # Safety
+        ///
+        /// `params` must come from `halo_params_new` and not have been
+        /// freed, and `proof` must point to `proof_len` readable bytes.
+        #[no_mangle]
+        pub unsafe extern "C" fn $name(
+            params: *const $crate::ffi::VerifierParams,
+            curve: u32,
+            proof: *const u8,
+            proof_len: usize,
+        ) -> i32 {
+            $crate::ffi::verify_raw(params, curve, proof, proof_len, || $circuit)
+        }
+    };
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::fixtures::{phony_proof, TrivialCircuit};
+
+    export_verifier!(halo_test_verify, TrivialCircuit);
+
+    #[test]
+    fn test_verify_status() {
+        let seed = b"ffi";
+        let params = unsafe { halo_params_new(4, seed.as_ptr(), seed.len()) };
+        assert!(!params.is_null());
+
+        // The phony proof decodes, and its payload is one byte longer than
+        // the circuit's, so `validate` rejects it before any synthesis.
+        let (ec0, ec1) = unsafe { (&(*params).ec0, &(*params).ec1) };
+        let mut bytes = vec![];
+        phony_proof(ec1, ec0, vec![0; 5]).write(&mut bytes).unwrap();
+        let verify = |curve, bytes: &[u8]| unsafe {
+            halo_test_verify(params, curve, bytes.as_ptr(), bytes.len())
+        };
+
+        assert_eq!(verify(1, &bytes), VerifyStatus::Invalid as i32);
+        assert_eq!(verify(2, &bytes), VerifyStatus::Error as i32);
+        assert_eq!(
+            verify(1, &bytes[..bytes.len() - 1]),
+            VerifyStatus::Malformed as i32
+        );
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert_eq!(verify(1, &extended), VerifyStatus::Malformed as i32);
+        assert_eq!(
+            unsafe { halo_test_verify(std::ptr::null(), 1, bytes.as_ptr(), bytes.len()) },
+            VerifyStatus::Error as i32
+        );
+
+        // A proof for larger params is turned away while decoding.
+        let e1params: Params<Ec1> = Params::new(5);
+        let e2params: Params<Ec0> = Params::new(5);
+        let mut large = vec![];
+        phony_proof(&e1params, &e2params, vec![0; 5])
+            .write(&mut large)
+            .unwrap();
+        assert_eq!(verify(1, &large), VerifyStatus::Malformed as i32);
+
+        unsafe { halo_params_free(params) };
+        assert!(unsafe { halo_params_new(3, seed.as_ptr(), seed.len()) }.is_null());
+        assert!(unsafe { halo_params_new(4, std::ptr::null(), 1) }.is_null());
+        unsafe { halo_params_free(std::ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,290 @@
+//! Radix-2 FFTs over the 2-adic scalar fields.
+//!
+//! An [`EvaluationDomain`] is the multiplicative subgroup of order `2^k`
+//! generated by `F::root_of_unity(k)`. Moving a polynomial between its
+//! coefficients and its evaluations over the domain costs `O(n log n)`
+//! field operations, which is what makes [`multiply_polynomials`]
+//! quasi-linear rather than quadratic.
+
+use crate::fields::Field;
+use crate::tune::AutoTune;
+use crossbeam_utils::thread;
+
+/// The subgroup of order `2^log_size` of the multiplicative group of `F`.
+#[derive(Clone, Copy, Debug)]
+pub struct EvaluationDomain<F: Field> {
+    log_size: u32,
+    omega: F,
+    omega_inv: F,
+    size_inv: F,
+}
+
+impl<F: Field> EvaluationDomain<F> {
+    /// The smallest domain with at least `size` points, or None if `F` has
+    /// no multiplicative subgroup that large.
+    pub fn new(size: usize) -> Option<Self> {
+        let mut log_size = 0;
+        while (1usize << log_size) < size {
+            log_size += 1;
+            if log_size > F::S {
+                return None;
+            }
+        }
+
+        let omega = F::root_of_unity(log_size);
+        Some(EvaluationDomain {
+            log_size,
+            omega,
+            omega_inv: omega.invert().unwrap(),
+            size_inv: F::from_u64(1 << log_size).invert().unwrap(),
+        })
+    }
+
+    /// The number of points in the domain.
+    pub fn size(&self) -> usize {
+        1 << self.log_size
+    }
+
+    pub fn log_size(&self) -> u32 {
+        self.log_size
+    }
+
+    /// The generator `omega` of the domain. The `i`-th point is `omega^i`.
+    pub fn generator(&self) -> F {
+        self.omega
+    }
+
+    /// Replaces the coefficients `a` of a polynomial of degree less than
+    /// `self.size()` with its evaluations at each point of the domain.
+    /// `a` is padded with zeroes to the size of the domain.
+    pub fn fft(&self, a: &mut Vec<F>) {
+        assert!(a.len() <= self.size());
+        a.resize(self.size(), F::zero());
+        best_fft(a, self.omega, self.log_size);
+    }
+
+    /// The inverse of `fft`: replaces the evaluations `a` over the domain
+    /// with the coefficients of the polynomial they determine.
+    pub fn ifft(&self, a: &mut [F]) {
+        assert_eq!(a.len(), self.size());
+        best_fft(a, self.omega_inv, self.log_size);
+        parallel_map(a, |a| *a *= self.size_inv);
+    }
+
+    /// Multiplies evaluations over the domain pointwise, computing in `a`
+    /// the evaluations of the product polynomial.
+    pub fn mul_assign(&self, a: &mut [F], b: &[F]) {
+        assert_eq!(a.len(), self.size());
+        assert_eq!(b.len(), self.size());
+        parallel_zip(a, b, |a, b| *a *= *b);
+    }
+}
+
+/// Multiplies the polynomials with coefficients `a` and `b`, both of which
+/// must be nonempty.
+pub fn multiply_polynomials<F: Field>(mut a: Vec<F>, mut b: Vec<F>) -> Vec<F> {
+    let degree_of_result = (a.len() - 1) + (b.len() - 1);
+    let coeffs_of_result = degree_of_result + 1;
+
+    // The scalar fields may not be able to support large enough radix-2
+    // evaluation domains.
+    let domain = EvaluationDomain::new(coeffs_of_result).expect("polynomial too large");
+
+    domain.fft(&mut a);
+    domain.fft(&mut b);
+    domain.mul_assign(&mut a, &b);
+    domain.ifft(&mut a);
+
+    a.truncate(coeffs_of_result);
+
+    a
+}
+
+fn parallel_map<F: Field>(a: &mut [F], f: impl Fn(&mut F) + Sync) {
+    if let Some(chunk) = AutoTune::current().chunk_size(a.len()) {
+        let f = &f;
+        thread::scope(|scope| {
+            for a in a.chunks_mut(chunk) {
+                scope.spawn(move |_| a.iter_mut().for_each(f));
+            }
+        })
+        .unwrap();
+    } else {
+        a.iter_mut().for_each(f);
+    }
+}
+
+fn parallel_zip<F: Field>(a: &mut [F], b: &[F], f: impl Fn(&mut F, &F) + Sync) {
+    if let Some(chunk) = AutoTune::current().chunk_size(a.len()) {
+        let f = &f;
+        thread::scope(|scope| {
+            for (a, b) in a.chunks_mut(chunk).zip(b.chunks(chunk)) {
+                scope.spawn(move |_| {
+                    for (a, b) in a.iter_mut().zip(b.iter()) {
+                        f(a, b);
+                    }
+                });
+            }
+        })
+        .unwrap();
+    } else {
+        for (a, b) in a.iter_mut().zip(b.iter()) {
+            f(a, b);
+        }
+    }
+}
+
+fn log2_floor(num: usize) -> u32 {
+    assert!(num > 0);
+
+    let mut pow = 0;
+
+    while (1 << (pow + 1)) <= num {
+        pow += 1;
+    }
+
+    pow
+}
+
+fn best_fft<F: Field>(a: &mut [F], omega: F, log_n: u32) {
+    let log_cpus = log2_floor(AutoTune::current().threads);
+
+    if log_n <= log_cpus {
+        serial_fft(a, omega, log_n);
+    } else {
+        parallel_fft(a, omega, log_n, log_cpus);
+    }
+}
+
+fn serial_fft<F: Field>(a: &mut [F], omega: F, log_n: u32) {
+    fn bitreverse(mut n: u32, l: u32) -> u32 {
+        let mut r = 0;
+        for _ in 0..l {
+            r = (r << 1) | (n & 1);
+            n >>= 1;
+        }
+        r
+    }
+
+    let n = a.len() as u32;
+    assert_eq!(n, 1 << log_n);
+
+    for k in 0..n {
+        let rk = bitreverse(k, log_n);
+        if k < rk {
+            a.swap(rk as usize, k as usize);
+        }
+    }
+
+    let mut m = 1;
+    for _ in 0..log_n {
+        let w_m = omega.pow(&[u64::from(n / (2 * m)), 0, 0, 0]);
+
+        let mut k = 0;
+        while k < n {
+            let mut w = F::one();
+            for j in 0..m {
+                let mut t = a[(k + j + m) as usize];
+                t *= w;
+                a[(k + j + m) as usize] = a[(k + j) as usize] - t;
+                a[(k + j) as usize] += t;
+                w *= w_m;
+            }
+
+            k += 2 * m;
+        }
+
+        m *= 2;
+    }
+}
+
+fn parallel_fft<F: Field>(a: &mut [F], omega: F, log_n: u32, log_cpus: u32) {
+    assert!(log_n >= log_cpus);
+
+    let num_cpus = 1 << log_cpus;
+    let log_new_n = log_n - log_cpus;
+    let mut tmp = vec![vec![F::zero(); 1 << log_new_n]; num_cpus];
+    let new_omega = omega.pow(&[num_cpus as u64, 0, 0, 0]);
+
+    thread::scope(|scope| {
+        let a = &*a;
+
+        for (j, tmp) in tmp.iter_mut().enumerate() {
+            scope.spawn(move |_| {
+                // Shuffle into a sub-FFT
+                let omega_j = omega.pow(&[j as u64, 0, 0, 0]);
+                let omega_step = omega.pow(&[(j as u64) << log_new_n, 0, 0, 0]);
+
+                let mut elt = F::one();
+                for i in 0..(1 << log_new_n) {
+                    for s in 0..num_cpus {
+                        let idx = (i + (s << log_new_n)) % (1 << log_n);
+                        let mut t = a[idx];
+                        t *= elt;
+                        tmp[i] += t;
+                        elt *= omega_step;
+                    }
+                    elt *= omega_j;
+                }
+
+                // Perform sub-FFT
+                serial_fft(tmp, new_omega, log_new_n);
+            });
+        }
+    })
+    .unwrap();
+
+    // Unshuffle
+    let mask = (1 << log_cpus) - 1;
+    for (idx, a) in a.iter_mut().enumerate() {
+        *a = tmp[idx & mask][idx >> log_cpus];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fields::{Fp, Fq};
+    use crate::gadgets::poly::evaluate_native;
+
+    #[test]
+    fn test_fft() {
+        let a = (0..1000).map(Fp::from_u64).collect::<Vec<_>>();
+        let b = (0..1000)
+            .map(|i| Fp::from_u64(i + 1000))
+            .collect::<Vec<_>>();
+
+        let mut naive_product = vec![Fp::zero(); (a.len() + b.len()) - 1];
+        for (i, a) in a.iter().enumerate() {
+            for (j, b) in b.iter().enumerate() {
+                naive_product[i + j] += (*a) * (*b);
+            }
+        }
+        let valid_product = multiply_polynomials(a, b);
+
+        assert_eq!(valid_product, naive_product);
+    }
+
+    #[test]
+    fn test_evaluation_domain() {
+        let domain = EvaluationDomain::<Fq>::new(100).unwrap();
+        assert_eq!(domain.size(), 128);
+        assert_eq!(domain.log_size(), 7);
+
+        let coeffs = (0..100).map(|i| Fq::from_u64(i * i + 7)).collect::<Vec<_>>();
+        let mut evals = coeffs.clone();
+        domain.fft(&mut evals);
+        let mut point = Fq::one();
+        for eval in &evals {
+            assert_eq!(*eval, evaluate_native(&coeffs, point));
+            point *= domain.generator();
+        }
+
+        domain.ifft(&mut evals);
+        assert_eq!(&evals[..100], &coeffs[..]);
+        assert!(evals[100..].iter().all(|c| bool::from(c.is_zero())));
+
+        assert!(EvaluationDomain::<Fq>::new(1 << Fq::S).is_some());
+        assert!(EvaluationDomain::<Fq>::new((1 << Fq::S) + 1).is_none());
+    }
+}
@@ -62,6 +62,11 @@ pub trait Field:
     fn one() -> Self;
     fn square(&self) -> Self;
 
+    /// Doubles this element.
+    fn double(&self) -> Self {
+        *self + *self
+    }
+
     fn to_bytes(&self) -> [u8; 32];
     fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self>;
 
@@ -104,6 +109,52 @@ pub trait Field:
     /// expressed canonically.
     fn get_lower_128(&self) -> u128;
 
+    /// The bits of the canonical encoding of this element, in
+    /// little-endian order. Bits at or above `NUM_BITS` are zero.
+    fn to_le_bits(&self) -> Vec<bool> {
+        let bytes = self.to_bytes();
+        (0..256).map(|i| (bytes[i / 8] >> (i % 8)) & 1 == 1).collect()
+    }
+
+    /// Returns a primitive 2^log_n root of unity, derived from `ALPHA`.
+    /// Panics if `log_n` exceeds `S`.
+    fn root_of_unity(log_n: u32) -> Self {
+        assert!(log_n <= Self::S);
+        let mut root = Self::ALPHA;
+        for _ in log_n..Self::S {
+            root = root.square();
+        }
+        root
+    }
+
+    /// Computes the Legendre symbol of this element, by raising it
+    /// to (p - 1) / 2.
+    fn legendre(&self) -> LegendreSymbol {
+        // (p - 1) / 2, from the canonical encoding of -1 = p - 1
+        let bytes = (-Self::one()).to_bytes();
+        let mut exp = [0u64; 4];
+        for (i, limb) in exp.iter_mut().enumerate() {
+            for j in (0..8).rev() {
+                *limb = (*limb << 8) | u64::from(bytes[i * 8 + j]);
+            }
+        }
+        for i in 0..4 {
+            exp[i] >>= 1;
+            if i < 3 {
+                exp[i] |= exp[i + 1] << 63;
+            }
+        }
+
+        let res = self.pow_vartime(&exp);
+        if bool::from(res.is_zero()) {
+            LegendreSymbol::Zero
+        } else if res == Self::one() {
+            LegendreSymbol::QuadraticResidue
+        } else {
+            LegendreSymbol::QuadraticNonResidue
+        }
+    }
+
     // Performs a batch inversion using Montgomery's trick,
     // returns the product of every inverse. Zero inputs are
     // ignored.
@@ -131,8 +182,81 @@ pub trait Field:
     }
 }
 
+/// The Legendre symbol of a field element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LegendreSymbol {
+    Zero,
+    QuadraticResidue,
+    QuadraticNonResidue,
+}
+
 mod fp;
 mod fq;
 
 pub use fp::*;
 pub use fq::*;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check_field<F: Field>() {
+        let two = F::from_u64(2);
+        assert_eq!(F::one().double(), two);
+        assert_eq!(F::from_u128(1 << 64), F::from_u64(1 << 32).square());
+
+        let bits = F::from_u64(0b1011).to_le_bits();
+        assert_eq!(bits.len(), 256);
+        assert_eq!(&bits[..5], &[true, true, false, true, false]);
+        assert!(bits[5..].iter().all(|b| !b));
+
+        let root = F::root_of_unity(4);
+        assert_eq!(root.pow_vartime(&[1 << 4, 0, 0, 0]), F::one());
+        assert!(root.pow_vartime(&[1 << 3, 0, 0, 0]) != F::one());
+        assert_eq!(F::root_of_unity(F::S), F::ALPHA);
+
+        assert_eq!(F::zero().legendre(), LegendreSymbol::Zero);
+        assert_eq!(two.square().legendre(), LegendreSymbol::QuadraticResidue);
+        // ALPHA generates the 2-Sylow subgroup, so it is never a square.
+        assert_eq!(F::ALPHA.legendre(), LegendreSymbol::QuadraticNonResidue);
+        assert!(bool::from(F::ALPHA.sqrt().is_none()));
+    }
+
+    #[test]
+    fn test_field_helpers() {
+        check_field::<Fp>();
+        check_field::<Fq>();
+    }
+
+    // Encodings and bit orders are fixed rather than following the
+    // platform, so that proofs made on one platform verify on another.
+    fn check_byte_layout<F: Field>() {
+        let v = 0x0f0e_0d0c_0b0a_0908_0706_0504_0302_0100u128;
+        let mut expected = [0u8; 32];
+        for (i, b) in expected[..16].iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let x = F::from_u128(v);
+        assert_eq!(x.to_bytes(), expected);
+        assert_eq!(F::from_bytes(&expected).unwrap(), x);
+        assert_eq!(x.get_lower_128(), v);
+        assert_eq!(
+            F::from_u64(0x0706_0504_0302_0100).to_bytes()[..8],
+            expected[..8]
+        );
+
+        let bits = x.to_le_bits();
+        assert_eq!(bits.len(), 256);
+        // 0x00, 0x01, 0x02: bit 8 is the lowest bit of the second byte.
+        assert!(bits[..8].iter().all(|b| !b));
+        assert!(bits[8] && !bits[9]);
+        assert!(!bits[16] && bits[17]);
+        assert!(bits[128..].iter().all(|b| !b));
+    }
+
+    #[test]
+    fn test_byte_layout() {
+        check_byte_layout::<Fp>();
+        check_byte_layout::<Fq>();
+    }
+}
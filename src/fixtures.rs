@@ -0,0 +1,107 @@
+//! Fixtures shared by the unit tests.
+
+use crate::circuits::*;
+use crate::curves::*;
+use crate::fields::*;
+use crate::gadgets::{AllocatedBit, AllocatedNum};
+use crate::ivc::IvcState;
+use crate::proofs::*;
+use crate::recursion::RecursiveProof;
+use crate::security::SecurityLevel;
+use rand_core::{RngCore, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use std::sync::OnceLock;
+
+/// A circuit whose only constraint is a public input of one. As a recursive
+/// circuit it carries a 32-bit payload and has no step logic.
+pub(crate) struct TrivialCircuit;
+
+impl<F: Field> Circuit<F> for TrivialCircuit {
+    fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        cs.alloc_input(|| "one", || Ok(F::one()))?;
+        Ok(())
+    }
+}
+
+impl<F: Field> RecursiveCircuit<F> for TrivialCircuit {
+    fn base_payload(&self) -> Vec<bool> {
+        vec![false; 32]
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        _: &mut CS,
+        _: &[AllocatedBit],
+        _: &[AllocatedBit],
+        _: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        Ok(())
+    }
+}
+
+/// A recursive proof at test sizes can't be made quickly, so this builds one
+/// out of a plain proof of `TrivialCircuit` and the base-case leftovers. It
+/// decodes and has the right shape, but does not verify.
+pub(crate) fn phony_proof<E1, E2>(
+    e1params: &Params<E1>,
+    e2params: &Params<E2>,
+    payload: Vec<u8>,
+) -> RecursiveProof<E1, E2>
+where
+    E1: Curve<Base = <E2 as Curve>::Scalar>,
+    E2: Curve<Base = <E1 as Curve>::Scalar>,
+{
+    RecursiveProof {
+        proof: crate::plain::prove(e1params, &TrivialCircuit).unwrap(),
+        oldproof1: Leftovers::dummy(e1params),
+        oldproof2: Leftovers::dummy(e2params),
+        deferred: Deferred::dummy(e2params.k),
+        payload,
+    }
+}
+
+/// `phony_proof` as the `step`th proof of a chain, with `step` as its payload.
+pub(crate) fn phony_state<E1, E2>(
+    e1params: &Params<E1>,
+    e2params: &Params<E2>,
+    step: u64,
+) -> IvcState<E1, E2>
+where
+    E1: Curve<Base = <E2 as Curve>::Scalar>,
+    E2: Curve<Base = <E1 as Curve>::Scalar>,
+{
+    let proof = phony_proof(e1params, e2params, vec![step as u8]);
+    IvcState::new(proof, step, e1params, e2params)
+}
+
+/// Parameters with generators drawn from a fixed seed, so that the
+/// constants embedded in the verification circuit are reproducible.
+pub(crate) fn seeded_params<C: Curve>(k: usize) -> Params<C> {
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    let d = 1 << k;
+    let mut generators = Vec::with_capacity(d);
+    let mut attempt = [0u8; 32];
+    while generators.len() < d {
+        rng.fill_bytes(&mut attempt);
+        let p = C::Affine::from_bytes(&attempt);
+        if bool::from(p.is_some()) {
+            generators.push(p.unwrap());
+        }
+    }
+
+    Params {
+        g: C::one(),
+        d,
+        n: d / 4,
+        k,
+        generators,
+        level: SecurityLevel::default(),
+        generator_hash: None,
+        dummy_g_new: OnceLock::new(),
+        k_commitment_table: OnceLock::new(),
+    }
+}
@@ -0,0 +1,168 @@
+//! Binding of the steps of a chain to the rounds of an external randomness
+//! beacon, for protocols such as leader election that prove things about
+//! randomness no prover controls.
+//!
+//! Each step's payload carries a [`BeaconBinding`]: a round and the beacon's
+//! output for it. [`enforce_next_round`] constrains a step to the round after
+//! its predecessor's and seeds a Rescue sponge with the output and round, for
+//! the step to draw its randomness from; [`BeaconBinding::sponge`] computes
+//! the same sponge natively.
+//!
+//! The circuit cannot tell whether the output is really the beacon's. The
+//! payload is a public input, and `k_commitment`, the first thing the
+//! verification transcript absorbs, commits to it, so it is whoever verifies
+//! a step that compares its binding with the published beacon. A proof only
+//! exposes its latest binding, so the bindings of earlier steps must have
+//! been checked when those steps were verified.
+
+use super::bytes::pack;
+use super::{AllocatedBit, Boolean, Combination, Num, RescueGadget};
+use crate::circuits::{ConstraintSystem, SynthesisError};
+use crate::fields::Field;
+use crate::rescue::Rescue;
+
+/// The number of payload bytes a [`BeaconBinding`] occupies.
+pub const BEACON_BYTES: usize = 40;
+
+/// The beacon round a step is bound to, and the beacon's output for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BeaconBinding {
+    pub round: u64,
+    pub output: [u8; 32],
+}
+
+impl BeaconBinding {
+    /// Encodes the binding as payload bytes: the round as a little-endian
+    /// `u64`, followed by the output.
+    pub fn to_bytes(&self) -> [u8; BEACON_BYTES] {
+        let mut bytes = [0u8; BEACON_BYTES];
+        bytes[..8].copy_from_slice(&self.round.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.output);
+        bytes
+    }
+
+    /// Decodes a binding from `to_bytes`, or returns `None` if `bytes` is not
+    /// `BEACON_BYTES` long.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != BEACON_BYTES {
+            return None;
+        }
+
+        let mut round = [0u8; 8];
+        round.copy_from_slice(&bytes[..8]);
+        let mut output = [0u8; 32];
+        output.copy_from_slice(&bytes[8..]);
+
+        Some(BeaconBinding {
+            round: u64::from_le_bytes(round),
+            output,
+        })
+    }
+
+    /// The sponge `enforce_next_round` returns for a step bound to this
+    /// round: resumed from the output as a digest, after which the round is
+    /// absorbed. Steps alternate between the fields of the cycle, so `F` must
+    /// be the field of the step's circuit.
+    pub fn sponge<F: Field>(&self) -> Rescue<F> {
+        let mut rescue = Rescue::from_digest(&self.output);
+        rescue.absorb(F::from_u64(self.round));
+        rescue
+    }
+}
+
+/// Constrains the binding `new`, given as payload bits, to the round after
+/// that of `old`, and returns the sponge `BeaconBinding::sponge` computes
+/// for `new`.
+pub fn enforce_next_round<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    old: &[AllocatedBit],
+    new: &[AllocatedBit],
+) -> Result<RescueGadget<F>, SynthesisError> {
+    assert_eq!(old.len(), BEACON_BYTES * 8);
+    assert_eq!(new.len(), BEACON_BYTES * 8);
+
+    let round = |bits: &[AllocatedBit]| -> Combination<F> {
+        let bits: Vec<Boolean> = bits[..64].iter().cloned().map(Boolean::from).collect();
+        pack(&bits)
+    };
+    let old_round = round(old);
+    let new_round = round(new);
+
+    let difference = new_round.clone() + old_round.scale(-F::one());
+    let lc = difference.lc(&mut cs);
+    cs.enforce_zero(lc - CS::ONE);
+
+    let mut rescue = RescueGadget::from_digest(cs.namespace(|| "resume from output"), &new[64..])?;
+    let new_round: Num<F> = new_round.evaluate(cs.namespace(|| "pack round"))?;
+    rescue.absorb(cs.namespace(|| "absorb round"), new_round)?;
+
+    Ok(rescue)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::circuits::Circuit;
+    use crate::dev::GadgetCheck;
+    use crate::fields::Fp;
+    use crate::Basic;
+
+    struct BeaconCircuit {
+        old: BeaconBinding,
+        new: BeaconBinding,
+        expected: Fp,
+    }
+
+    impl Circuit<Fp> for BeaconCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut alloc_bits = |name: &str, binding: &BeaconBinding| {
+                let bytes = binding.to_bytes();
+                (0..BEACON_BYTES * 8)
+                    .map(|i| {
+                        AllocatedBit::alloc(cs.namespace(|| format!("{} bit {}", name, i)), || {
+                            Ok((bytes[i / 8] >> (i % 8)) & 1 == 1)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            let old = alloc_bits("old", &self.old)?;
+            let new = alloc_bits("new", &self.new)?;
+
+            let mut rescue = enforce_next_round(cs.namespace(|| "beacon"), &old, &new)?;
+            let randomness = rescue.squeeze(cs.namespace(|| "randomness"))?;
+            let expected = cs.alloc_input(|| "expected", || Ok(self.expected))?;
+            cs.enforce_zero(randomness.lc() - expected);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_beacon() {
+        let binding = |round: u64| BeaconBinding {
+            round,
+            output: [round as u8; 32],
+        };
+        let old = binding(6);
+        assert_eq!(BeaconBinding::from_bytes(&old.to_bytes()), Some(old));
+        assert_eq!(BeaconBinding::from_bytes(&[0; 39]), None);
+
+        let randomness = |binding: &BeaconBinding| binding.sponge::<Fp>().squeeze();
+        let new = binding(7);
+        assert!(randomness(&new) != randomness(&BeaconBinding { round: 8, ..new }));
+
+        let circuit = |new: BeaconBinding, expected: Fp| BeaconCircuit { old, new, expected };
+        let expected = randomness(&new);
+        GadgetCheck::new(circuit(new, expected), &[expected])
+            .malformed("skipped round", circuit(binding(8), expected), &[expected])
+            .malformed(
+                "repeated round",
+                circuit(BeaconBinding { round: 6, ..new }, expected),
+                &[expected],
+            )
+            .malformed("wrong randomness", circuit(new, Fp::one()), &[Fp::one()])
+            .budget(1418, 2837)
+            .check::<Basic>()
+            .unwrap();
+    }
+}
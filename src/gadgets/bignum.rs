@@ -0,0 +1,854 @@
+//! Arithmetic on elements of a foreign prime field.
+//!
+//! An element is held as limbs of 52 bits, each range checked, five for a
+//! 256-bit modulus, and need not be reduced: any integer below 2^260, for
+//! five limbs, congruent to the value is a valid representative. Every
+//! operation witnesses its result `r` and a quotient `q`, and checks
+//! `lhs = q * p + r` as an equation over the integers, limb by limb with
+//! witnessed carries. Limb products are far below the native modulus, so
+//! the limb equations cannot wrap around.
+//!
+//! With five limbs, a multiplication costs 25 gates for the limb products
+//! and about 1150 for range checks; additions and subtractions only pay for
+//! the latter.
+
+use super::bytes::{boolean, enforce_false, pack, range_check};
+use super::{AllocatedBit, AllocatedNum, Boolean, Combination, Num};
+use crate::circuits::{ConstraintSystem, SynthesisError};
+use crate::fields::Field;
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+/// The number of bits of each limb.
+pub const LIMB_BITS: usize = 52;
+
+const CARRY_BITS: usize = 64;
+
+/// Fixed-size little-endian 64-bit words.
+pub trait WordArray: Copy + Default + PartialEq + Debug + AsRef<[u64]> + AsMut<[u64]> {}
+
+impl<T: Copy + Default + PartialEq + Debug + AsRef<[u64]> + AsMut<[u64]>> WordArray for T {}
+
+/// A prime modulus.
+pub trait ForeignModulus {
+    /// The words values are given in, such as `[u64; 4]`.
+    type Words: WordArray;
+    /// The modulus, as little-endian 64-bit words.
+    const MODULUS: Self::Words;
+}
+
+/// Non-negative integers as little-endian 64-bit words, for computing
+/// witnesses.
+pub(crate) mod words {
+    use super::*;
+
+    pub fn trim(mut a: Vec<u64>) -> Vec<u64> {
+        while a.last() == Some(&0) {
+            a.pop();
+        }
+        a
+    }
+
+    pub fn cmp(a: &[u64], b: &[u64]) -> Ordering {
+        let len = a.len().max(b.len());
+        for i in (0..len).rev() {
+            let x = a.get(i).cloned().unwrap_or(0);
+            let y = b.get(i).cloned().unwrap_or(0);
+            match x.cmp(&y) {
+                Ordering::Equal => (),
+                ord => return ord,
+            }
+        }
+        Ordering::Equal
+    }
+
+    pub fn add(a: &[u64], b: &[u64]) -> Vec<u64> {
+        let len = a.len().max(b.len());
+        let mut out = Vec::with_capacity(len + 1);
+        let mut carry = 0u128;
+        for i in 0..len {
+            let sum = a.get(i).cloned().unwrap_or(0) as u128
+                + b.get(i).cloned().unwrap_or(0) as u128
+                + carry;
+            out.push(sum as u64);
+            carry = sum >> 64;
+        }
+        out.push(carry as u64);
+        trim(out)
+    }
+
+    /// `a - b`, which must not be negative.
+    pub fn sub(a: &[u64], b: &[u64]) -> Vec<u64> {
+        assert_ne!(cmp(a, b), Ordering::Less);
+        let mut out = Vec::with_capacity(a.len());
+        let mut borrow = 0i128;
+        for (i, &x) in a.iter().enumerate() {
+            let diff = x as i128 - b.get(i).cloned().unwrap_or(0) as i128 - borrow;
+            out.push(diff as u64);
+            borrow = if diff < 0 { 1 } else { 0 };
+        }
+        trim(out)
+    }
+
+    pub fn mul(a: &[u64], b: &[u64]) -> Vec<u64> {
+        let mut out = vec![0u64; a.len() + b.len() + 1];
+        for (i, &x) in a.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &y) in b.iter().enumerate() {
+                let t = out[i + j] as u128 + x as u128 * y as u128 + carry;
+                out[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            let mut k = i + b.len();
+            while carry != 0 {
+                let t = out[k] as u128 + carry;
+                out[k] = t as u64;
+                carry = t >> 64;
+                k += 1;
+            }
+        }
+        trim(out)
+    }
+
+    /// The quotient and remainder of `a` divided by `m`, by schoolbook
+    /// long division (Knuth's algorithm D).
+    pub fn divmod(a: &[u64], m: &[u64]) -> (Vec<u64>, Vec<u64>) {
+        let a = trim(a.to_vec());
+        let m = trim(m.to_vec());
+        assert!(!m.is_empty());
+        if cmp(&a, &m) == Ordering::Less {
+            return (vec![], a);
+        }
+
+        let n = m.len();
+        if n == 1 {
+            let mut q = vec![0; a.len()];
+            let mut r = 0u128;
+            for i in (0..a.len()).rev() {
+                let num = (r << 64) | a[i] as u128;
+                q[i] = (num / m[0] as u128) as u64;
+                r = num % m[0] as u128;
+            }
+            return (trim(q), trim(vec![r as u64]));
+        }
+
+        // Normalize so that the top word of the divisor has its top bit set.
+        let shift = m[n - 1].leading_zeros();
+        let shl = |x: &[u64], len: usize| -> Vec<u64> {
+            (0..len)
+                .map(|i| {
+                    let lo = x.get(i).cloned().unwrap_or(0);
+                    let hi = if i == 0 {
+                        0
+                    } else {
+                        x.get(i - 1).cloned().unwrap_or(0)
+                    };
+                    if shift == 0 {
+                        lo
+                    } else {
+                        (lo << shift) | (hi >> (64 - shift))
+                    }
+                })
+                .collect()
+        };
+        let v = shl(&m, n);
+        let mut u = shl(&a, a.len() + 1);
+
+        let mut q = vec![0; a.len() - n + 1];
+        for j in (0..q.len()).rev() {
+            let num = ((u[j + n] as u128) << 64) | u[j + n - 1] as u128;
+            let mut qhat = num / v[n - 1] as u128;
+            let mut rhat = num % v[n - 1] as u128;
+            while qhat >> 64 != 0 || qhat * v[n - 2] as u128 > ((rhat << 64) | u[j + n - 2] as u128)
+            {
+                qhat -= 1;
+                rhat += v[n - 1] as u128;
+                if rhat >> 64 != 0 {
+                    break;
+                }
+            }
+
+            // u -= qhat * v
+            let mut borrow = 0i128;
+            for i in 0..n {
+                let product = qhat * v[i] as u128;
+                let t = u[i + j] as i128 - borrow - (product as u64) as i128;
+                u[i + j] = t as u64;
+                borrow = (product >> 64) as i128 - (t >> 64);
+            }
+            let t = u[j + n] as i128 - borrow;
+            u[j + n] = t as u64;
+
+            // qhat was one too large: add v back.
+            if t < 0 {
+                qhat -= 1;
+                let mut carry = 0u128;
+                for i in 0..n {
+                    let t = u[i + j] as u128 + v[i] as u128 + carry;
+                    u[i + j] = t as u64;
+                    carry = t >> 64;
+                }
+                u[j + n] = u[j + n].wrapping_add(carry as u64);
+            }
+            q[j] = qhat as u64;
+        }
+
+        let r = (0..n)
+            .map(|i| {
+                if shift == 0 {
+                    u[i]
+                } else {
+                    (u[i] >> shift) | (u[i + 1] << (64 - shift))
+                }
+            })
+            .collect();
+        (trim(q), trim(r))
+    }
+
+    /// `a^e mod m`.
+    pub fn pow_mod(a: &[u64], e: &[u64], m: &[u64]) -> Vec<u64> {
+        let mut acc = vec![1];
+        for i in (0..(e.len() * 64)).rev() {
+            acc = divmod(&mul(&acc, &acc), m).1;
+            if (e[i / 64] >> (i % 64)) & 1 == 1 {
+                acc = divmod(&mul(&acc, a), m).1;
+            }
+        }
+        acc
+    }
+
+    /// `a mod m`, as an array.
+    pub fn reduce<W: WordArray>(a: &[u64], m: &[u64]) -> W {
+        to_array(&divmod(a, m).1)
+    }
+
+    pub fn add_mod<W: WordArray>(a: &W, b: &W, m: &[u64]) -> W {
+        reduce(&add(a.as_ref(), b.as_ref()), m)
+    }
+
+    /// `a - b mod m`, for `b` less than `m`.
+    pub fn sub_mod<W: WordArray>(a: &W, b: &W, m: &[u64]) -> W {
+        reduce(&sub(&add(a.as_ref(), m), b.as_ref()), m)
+    }
+
+    pub fn mul_mod<W: WordArray>(a: &W, b: &W, m: &[u64]) -> W {
+        reduce(&mul(a.as_ref(), b.as_ref()), m)
+    }
+
+    /// The inverse of `a` modulo the prime `m`, or zero if `a` is zero.
+    pub fn invert_mod<W: WordArray>(a: &W, m: &[u64]) -> W {
+        to_array(&pow_mod(a.as_ref(), &sub(m, &[2]), m))
+    }
+
+    /// The number of significant bits of `a`.
+    pub fn bits(a: &[u64]) -> usize {
+        let a = trim(a.to_vec());
+        match a.last() {
+            Some(top) => 64 * a.len() - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    /// The `n` limbs of `LIMB_BITS` bits of `a`.
+    pub fn limbs(a: &[u64], n: usize) -> Vec<u64> {
+        (0..n)
+            .map(|i| {
+                let mut limb = 0;
+                for j in 0..LIMB_BITS {
+                    let bit = i * LIMB_BITS + j;
+                    if bit / 64 < a.len() {
+                        limb |= ((a[bit / 64] >> (bit % 64)) & 1) << j;
+                    }
+                }
+                limb
+            })
+            .collect()
+    }
+
+    pub fn to_array<W: WordArray>(a: &[u64]) -> W {
+        let mut out = W::default();
+        assert!(a.len() <= out.as_ref().len());
+        out.as_mut()[..a.len()].copy_from_slice(a);
+        out
+    }
+}
+
+// The integer a field element holds, which must be less than 2^126 in
+// absolute value.
+fn signed<F: Field>(v: F) -> i128 {
+    let lo = v.get_lower_128();
+    if lo < (1 << 126) && F::from_u128(lo) == v {
+        lo as i128
+    } else {
+        let lo = (-v).get_lower_128();
+        assert!(lo < (1 << 126) && F::from_u128(lo) == -v);
+        -(lo as i128)
+    }
+}
+
+fn from_signed<F: Field>(v: i128) -> F {
+    if v < 0 {
+        -F::from_u128((-v) as u128)
+    } else {
+        F::from_u128(v as u128)
+    }
+}
+
+fn constant<F: Field>(v: u64) -> Combination<F> {
+    Combination::from(Num::constant(F::from_u64(v)))
+}
+
+/// An element of the field of integers modulo `M::MODULUS`.
+pub struct ForeignElement<F: Field, M: ForeignModulus> {
+    limbs: Vec<Combination<F>>,
+    // The canonical value, less than the modulus
+    value: Option<M::Words>,
+    _marker: PhantomData<M>,
+}
+
+impl<F: Field, M: ForeignModulus> Clone for ForeignElement<F, M> {
+    fn clone(&self) -> Self {
+        ForeignElement {
+            limbs: self.limbs.clone(),
+            value: self.value,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: Field, M: ForeignModulus> ForeignElement<F, M> {
+    fn modulus() -> Vec<u64> {
+        words::trim(M::MODULUS.as_ref().to_vec())
+    }
+
+    /// The number of limbs of an element, enough for any integer of as many
+    /// bits as the modulus.
+    pub fn limb_count() -> usize {
+        words::bits(&Self::modulus()).div_ceil(LIMB_BITS)
+    }
+
+    // The number of limbs of a quotient, enough for any product of two
+    // elements divided by the modulus.
+    fn quotient_limb_count() -> usize {
+        let bits = 2 * Self::limb_count() * LIMB_BITS - (words::bits(&Self::modulus()) - 1);
+        bits.div_ceil(LIMB_BITS)
+    }
+
+    fn reduced(value: &[u64]) -> M::Words {
+        words::reduce(value, &Self::modulus())
+    }
+
+    /// The element `value`, which is reduced modulo `M::MODULUS`.
+    pub fn constant(value: M::Words) -> Self {
+        let value = Self::reduced(value.as_ref());
+        ForeignElement {
+            limbs: words::limbs(value.as_ref(), Self::limb_count())
+                .into_iter()
+                .map(constant)
+                .collect(),
+            value: Some(value),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Allocates `value`, which is reduced modulo `M::MODULUS`.
+    pub fn alloc<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        value: Option<M::Words>,
+    ) -> Result<Self, SynthesisError> {
+        let value = value.map(|v| Self::reduced(v.as_ref()));
+        let limbs = Self::alloc_limbs(
+            cs.namespace(|| "limbs"),
+            value.map(|v| words::limbs(v.as_ref(), Self::limb_count())),
+            Self::limb_count(),
+        )?;
+        Ok(ForeignElement {
+            limbs,
+            value,
+            _marker: PhantomData,
+        })
+    }
+
+    fn alloc_limbs<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        values: Option<Vec<u64>>,
+        n: usize,
+    ) -> Result<Vec<Combination<F>>, SynthesisError> {
+        let mut limbs = Vec::with_capacity(n);
+        for i in 0..n {
+            let value = values.as_ref().map(|v| F::from_u64(v[i]));
+            let limb = Combination::from(AllocatedNum::alloc(
+                cs.namespace(|| format!("limb {}", i)),
+                || value.ok_or(SynthesisError::AssignmentMissing),
+            )?);
+            range_check(
+                cs.namespace(|| format!("limb {} range", i)),
+                &limb,
+                LIMB_BITS,
+            )?;
+            limbs.push(limb);
+        }
+        Ok(limbs)
+    }
+
+    /// The value of this element, reduced modulo `M::MODULUS`.
+    pub fn get_value(&self) -> Option<M::Words> {
+        self.value
+    }
+
+    // Constrains the integer with limbs `lhs` to be `q * p + r`, for a
+    // witnessed `q` and either a witnessed `r`, which is returned, or zero.
+    // `value` is the value of `lhs`, which must not be negative.
+    fn reduce<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        lhs: Vec<Combination<F>>,
+        value: Option<Vec<u64>>,
+        remainder: bool,
+    ) -> Result<Self, SynthesisError> {
+        let modulus = Self::modulus();
+        let limb_count = Self::limb_count();
+        let quotient_limb_count = Self::quotient_limb_count();
+        let divided = value.map(|v| words::divmod(&v, &modulus));
+
+        let q = Self::alloc_limbs(
+            cs.namespace(|| "quotient"),
+            divided
+                .as_ref()
+                .map(|(q, _)| words::limbs(q, quotient_limb_count)),
+            quotient_limb_count,
+        )?;
+        let (r, r_value) = if remainder {
+            let r_value = divided
+                .as_ref()
+                .map(|(_, r)| words::to_array::<M::Words>(r));
+            let r = Self::alloc_limbs(
+                cs.namespace(|| "remainder"),
+                r_value.map(|r| words::limbs(r.as_ref(), limb_count)),
+                limb_count,
+            )?;
+            (r, r_value)
+        } else {
+            if let Some((_, r)) = &divided {
+                if !r.is_empty() {
+                    return Err(SynthesisError::Unsatisfiable);
+                }
+            }
+            (
+                (0..limb_count).map(|_| Combination::zero()).collect(),
+                Some(M::Words::default()),
+            )
+        };
+
+        // The limbs of lhs - q * p - r
+        let p = words::limbs(&modulus, limb_count);
+        let positions = lhs.len().max(quotient_limb_count + limb_count - 1);
+        let mut diff: Vec<Combination<F>> = (0..positions)
+            .map(|k| lhs.get(k).cloned().unwrap_or_else(Combination::zero))
+            .collect();
+        for (i, q_i) in q.iter().enumerate() {
+            for (j, &p_j) in p.iter().enumerate() {
+                diff[i + j] = diff[i + j].clone() + q_i.clone().scale(-F::from_u64(p_j));
+            }
+        }
+        for (k, r_k) in r.iter().enumerate() {
+            diff[k] = diff[k].clone() + r_k.clone().scale(-F::one());
+        }
+
+        // Each carry, offset to be non-negative, takes the excess of one
+        // position into the next. The last position has nothing to carry.
+        let shift = F::from_u64(1 << LIMB_BITS);
+        let offset = F::from_u128(1 << (CARRY_BITS - 1));
+        let mut carry_in = Combination::zero();
+        for (k, diff_k) in diff.into_iter().enumerate() {
+            let total = diff_k + carry_in;
+            let carry_value = total.get_value().map(|t| signed::<F>(t) >> LIMB_BITS);
+
+            if k == positions - 1 {
+                let lc = total.lc(&mut cs);
+                cs.enforce_zero(lc);
+                break;
+            }
+
+            let mut cs = cs.namespace(|| format!("carry {}", k));
+            let offset_carry =
+                Combination::from(AllocatedNum::alloc(cs.namespace(|| "carry"), || {
+                    carry_value
+                        .map(|c| from_signed::<F>(c) + offset)
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })?);
+            range_check(cs.namespace(|| "range"), &offset_carry, CARRY_BITS)?;
+            let carry = offset_carry + Num::constant(-offset);
+
+            let lc = total.lc(&mut cs) - &carry.clone().scale(shift).lc(&mut cs);
+            cs.enforce_zero(lc);
+
+            carry_in = carry;
+        }
+
+        Ok(ForeignElement {
+            limbs: r,
+            value: r_value,
+            _marker: PhantomData,
+        })
+    }
+
+    // The limbs and value of this element minus `other`, plus a multiple of
+    // the modulus above any value the limbs can hold, to keep the integer
+    // positive.
+    fn difference(&self, other: &Self) -> (Vec<Combination<F>>, Option<Vec<u64>>) {
+        let limb_count = Self::limb_count();
+        let shift = limb_count * LIMB_BITS + 1 - words::bits(&Self::modulus());
+        let multiple = words::mul(&Self::modulus(), &[1 << shift]);
+        let multiple_limbs = words::limbs(&multiple, limb_count + 1);
+        let lhs = self
+            .limbs
+            .iter()
+            .zip(other.limbs.iter())
+            .zip(multiple_limbs.iter())
+            .map(|((a, b), &m)| a.clone() + b.clone().scale(-F::one()) + constant(m))
+            .chain(std::iter::once(constant(multiple_limbs[limb_count])))
+            .collect();
+        let value = self.words().and_then(|a| {
+            other
+                .words()
+                .map(|b| words::sub(&words::add(&a, &multiple), &b))
+        });
+        (lhs, value)
+    }
+
+    // The value of this element's limbs as an integer.
+    fn words(&self) -> Option<Vec<u64>> {
+        self.value.map(|v| words::trim(v.as_ref().to_vec()))
+    }
+
+    pub fn add<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let lhs = self
+            .limbs
+            .iter()
+            .zip(other.limbs.iter())
+            .map(|(a, b)| a.clone() + b.clone())
+            .collect();
+        let value = self
+            .words()
+            .and_then(|a| other.words().map(|b| words::add(&a, &b)));
+        Self::reduce(cs, lhs, value, true)
+    }
+
+    pub fn sub<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let (lhs, value) = self.difference(other);
+        Self::reduce(cs, lhs, value, true)
+    }
+
+    pub fn mul<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let mut lhs: Vec<Combination<F>> = (0..(2 * Self::limb_count() - 1))
+            .map(|_| Combination::zero())
+            .collect();
+        for (i, a) in self.limbs.iter().enumerate() {
+            for (j, b) in other.limbs.iter().enumerate() {
+                let product = a.mul(cs.namespace(|| format!("limb product {} {}", i, j)), b)?;
+                lhs[i + j] = lhs[i + j].clone() + product;
+            }
+        }
+        let value = self
+            .words()
+            .and_then(|a| other.words().map(|b| words::mul(&a, &b)));
+        Self::reduce(cs.namespace(|| "reduce"), lhs, value, true)
+    }
+
+    /// This element multiplied by a small constant.
+    pub fn scale<CS: ConstraintSystem<F>>(&self, cs: CS, by: u32) -> Result<Self, SynthesisError> {
+        let lhs = self
+            .limbs
+            .iter()
+            .map(|a| a.clone().scale(F::from_u64(by as u64)))
+            .collect();
+        let value = self.words().map(|a| words::mul(&a, &[by as u64]));
+        Self::reduce(cs, lhs, value, true)
+    }
+
+    /// Constrains `a` and `b` to be congruent.
+    pub fn enforce_equal<CS: ConstraintSystem<F>>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<(), SynthesisError> {
+        let (lhs, value) = a.difference(b);
+        Self::reduce(cs, lhs, value, false).map(|_| ())
+    }
+
+    /// Constrains this element to be nonzero.
+    pub fn enforce_nonzero<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+    ) -> Result<(), SynthesisError> {
+        let inverse = self.value.map(|v| words::invert_mod(&v, &Self::modulus()));
+        let inverse = Self::alloc(cs.namespace(|| "inverse"), inverse)?;
+        let product = self.mul(cs.namespace(|| "product"), &inverse)?;
+        Self::enforce_equal(
+            cs.namespace(|| "is one"),
+            &product,
+            &Self::constant(words::to_array(&[1])),
+        )
+    }
+
+    /// `if_true` if `condition` holds, and `if_false` otherwise.
+    pub fn select<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        condition: &Boolean,
+        if_true: &Self,
+        if_false: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let mut limbs = Vec::with_capacity(if_true.limbs.len());
+        for (i, (t, f)) in if_true.limbs.iter().zip(if_false.limbs.iter()).enumerate() {
+            let difference = t.clone() + f.clone().scale(-F::one());
+            let selected: Combination<F> = match condition {
+                Boolean::Constant(true) => difference,
+                Boolean::Constant(false) => Combination::zero(),
+                _ => boolean::<F>(condition)
+                    .mul(cs.namespace(|| format!("limb {}", i)), &difference)?
+                    .into(),
+            };
+            limbs.push(selected + f.clone());
+        }
+        let value = match condition.get_value() {
+            Some(true) => if_true.value,
+            Some(false) => if_false.value,
+            None => None,
+        };
+        Ok(ForeignElement {
+            limbs,
+            value,
+            _marker: PhantomData,
+        })
+    }
+
+    /// The element with little-endian bits `bits`, of which there may be
+    /// about twice as many as the modulus has, for a wide reduction.
+    pub fn from_bits<CS: ConstraintSystem<F>>(
+        cs: CS,
+        bits: &[Boolean],
+    ) -> Result<Self, SynthesisError> {
+        assert!(
+            bits.len() + 1 - words::bits(&Self::modulus())
+                <= Self::quotient_limb_count() * LIMB_BITS
+        );
+
+        let lhs = bits.chunks(LIMB_BITS).map(pack).collect();
+        let mut value = Some(vec![0; bits.len().div_ceil(64)]);
+        for (i, bit) in bits.iter().enumerate() {
+            match (bit.get_value(), value.as_mut()) {
+                (Some(true), Some(v)) => v[i / 64] |= 1 << (i % 64),
+                (Some(false), _) => (),
+                _ => value = None,
+            }
+        }
+        Self::reduce(cs, lhs, value.map(words::trim), true)
+    }
+
+    /// The little-endian bits of the canonical value of this element, 64
+    /// for each word of `M::Words`.
+    pub fn to_bits<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+    ) -> Result<Vec<Boolean>, SynthesisError> {
+        let len = 64 * M::MODULUS.as_ref().len();
+        let mut bits = Vec::with_capacity(len);
+        for i in 0..len {
+            bits.push(Boolean::Is(AllocatedBit::alloc(
+                cs.namespace(|| format!("bit {}", i)),
+                || {
+                    self.value
+                        .map(|v| (v.as_ref()[i / 64] >> (i % 64)) & 1 == 1)
+                        .ok_or(SynthesisError::AssignmentMissing)
+                },
+            )?));
+        }
+        Self::enforce_below_modulus(cs.namespace(|| "canonical"), &bits)?;
+
+        let packed = ForeignElement {
+            limbs: bits.chunks(LIMB_BITS).map(pack).collect(),
+            value: self.value,
+            _marker: PhantomData,
+        };
+        Self::enforce_equal(cs.namespace(|| "equal"), self, &packed)?;
+
+        Ok(bits)
+    }
+
+    /// Constrains the integer with little-endian bits `bits` to be less
+    /// than the modulus.
+    pub fn enforce_below_modulus<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        bits: &[Boolean],
+    ) -> Result<(), SynthesisError> {
+        let max = words::sub(&Self::modulus(), &[1]);
+        // Whether the bits so far, most significant first, are those of the
+        // modulus minus one
+        let mut equal = Boolean::constant(true);
+        for (i, bit) in bits.iter().enumerate().rev() {
+            let max_bit = max.get(i / 64).is_some_and(|w| (w >> (i % 64)) & 1 == 1);
+            if max_bit {
+                equal = Boolean::and(cs.namespace(|| format!("bit {}", i)), &equal, bit)?;
+            } else {
+                let above = Boolean::and(cs.namespace(|| format!("bit {} above", i)), &equal, bit)?;
+                enforce_false(cs.namespace(|| format!("bit {} not above", i)), &above)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{circuits::Circuit, dev::GadgetCheck, fields::Fp, Basic};
+
+    // 2^255 - 19
+    struct Curve25519Base;
+
+    impl ForeignModulus for Curve25519Base {
+        type Words = [u64; 4];
+        const MODULUS: [u64; 4] = [
+            0xffff_ffff_ffff_ffed,
+            0xffff_ffff_ffff_ffff,
+            0xffff_ffff_ffff_ffff,
+            0x7fff_ffff_ffff_ffff,
+        ];
+    }
+
+    type Element = ForeignElement<Fp, Curve25519Base>;
+
+    #[derive(Clone, Copy)]
+    enum Op {
+        Add,
+        Sub,
+        Mul,
+    }
+
+    struct OpCircuit {
+        op: Op,
+        a: [u64; 4],
+        b: [u64; 4],
+        expected: [u64; 4],
+    }
+
+    impl Circuit<Fp> for OpCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let a = Element::alloc(cs.namespace(|| "a"), Some(self.a))?;
+            let b = Element::alloc(cs.namespace(|| "b"), Some(self.b))?;
+            let expected = Element::alloc(cs.namespace(|| "expected"), Some(self.expected))?;
+            let result = match self.op {
+                Op::Add => a.add(cs.namespace(|| "add"), &b)?,
+                Op::Sub => a.sub(cs.namespace(|| "sub"), &b)?,
+                Op::Mul => a.mul(cs.namespace(|| "mul"), &b)?,
+            };
+            // Compare limbs, so that a wrong witness is caught by the
+            // constraints rather than by witness generation.
+            let mut cs = cs.namespace(|| "result");
+            for (r, e) in result.limbs.iter().zip(expected.limbs.iter()) {
+                let lc = r.lc(&mut cs) - &e.lc(&mut cs);
+                cs.enforce_zero(lc);
+            }
+            Ok(())
+        }
+    }
+
+    fn expected(op: Op, a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+        let p = words::trim(Curve25519Base::MODULUS.to_vec());
+        let value = match op {
+            Op::Add => words::add(a, b),
+            Op::Sub => words::sub(&words::add(a, &p), b),
+            Op::Mul => words::mul(a, b),
+        };
+        words::to_array(&words::divmod(&value, &p).1)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = [
+            0x0123_4567_89ab_cdef,
+            0xfedc_ba98_7654_3210,
+            0x0f1e_2d3c_4b5a_6978,
+            0x7fff_ffff_ffff_fff0,
+        ];
+        let b = [
+            0xffff_ffff_ffff_ffee,
+            u64::MAX,
+            u64::MAX,
+            0x7fff_ffff_ffff_ffff,
+        ];
+
+        for &(op, budget) in &[
+            (Op::Add, (1946, 3907)),
+            (Op::Sub, (1946, 3907)),
+            (Op::Mul, (1971, 3957)),
+        ] {
+            let expected = expected(op, &a, &b);
+            let mut wrong = expected;
+            wrong[0] ^= 1;
+            GadgetCheck::new(OpCircuit { op, a, b, expected }, &[])
+                .malformed(
+                    "wrong result",
+                    OpCircuit {
+                        op,
+                        a,
+                        b,
+                        expected: wrong,
+                    },
+                    &[],
+                )
+                .budget(budget.0, budget.1)
+                .check::<Basic>()
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_divmod() {
+        // Compare against repeated subtraction of shifted divisors.
+        let mut state = 0x0123_4567_89ab_cdefu64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..200 {
+            let a_len = (next() % 12) as usize;
+            let m_len = 1 + (next() % 7) as usize;
+            let a: Vec<u64> = (0..a_len).map(|_| next()).collect();
+            let mut m: Vec<u64> = (0..m_len).map(|_| next()).collect();
+            // Exercise both normalized and unnormalized divisors.
+            m[m_len - 1] >>= next() % 64;
+            if words::trim(m.clone()).is_empty() {
+                continue;
+            }
+
+            let (q, r) = words::divmod(&a, &m);
+            assert_eq!(words::cmp(&r, &m), Ordering::Less);
+            assert_eq!(words::add(&words::mul(&q, &m), &r), words::trim(a));
+        }
+    }
+
+    #[test]
+    fn test_words() {
+        let p = words::trim(Curve25519Base::MODULUS.to_vec());
+        let a = [3, 0, 0, 1 << 62];
+        let inverse = words::pow_mod(&a, &words::sub(&p, &[2]), &p);
+        assert_eq!(words::divmod(&words::mul(&a, &inverse), &p).1, vec![1]);
+        assert_eq!(words::limbs(&[u64::MAX], 2), vec![(1 << 52) - 1, 0xfff]);
+    }
+}
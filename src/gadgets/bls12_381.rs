@@ -0,0 +1,1670 @@
+//! Checks of the BLS12-381 pairing, whose base field is foreign to both
+//! fields of the cycle, for verifying BLS signatures and Groth16 proofs made
+//! elsewhere.
+//!
+//! The extension tower `Fp2 = Fp[u] / (u^2 + 1)`, `Fp6 = Fp2[v] / (v^3 - ξ)`
+//! with `ξ = u + 1`, and `Fp12 = Fp6[w] / (w^2 - v)` is written once over
+//! [`BaseField`], which either computes natively or synthesizes non-native
+//! arithmetic, so witnesses and constraints follow the same formulas. The
+//! Miller loop uses affine coordinates with witnessed slopes, and the final
+//! exponentiation raises to three times the usual exponent, which makes no
+//! difference to whether a product of pairings is one.
+//!
+//! Points are not checked to be in the prime-order subgroups: callers must
+//! ensure that of any point the statement does not fix. Some points outside
+//! the subgroups make a check unsatisfiable rather than wrong.
+//!
+//! This is expensive: every base field operation costs a couple of thousand
+//! gates, and a check of two pairs, as for a BLS signature, about 187
+//! million, needing `k = 30`. A Groth16 proof with one 255-bit public input
+//! costs about 216 million. [`pairing_check_cost`], [`verify_bls_cost`] and
+//! [`verify_groth16_cost`] count the gates, and [`ConstraintCounts::min_k`]
+//! the `k` they need.
+
+use super::bignum::{words, ForeignElement, ForeignModulus};
+use super::Boolean;
+use crate::circuits::{ConstraintSystem, SynthesisError};
+use crate::fields::Field;
+#[cfg(feature = "prover")]
+use crate::{
+    circuits::Circuit,
+    dev::{constraint_totals, ConstraintCounts},
+    gadgets::AllocatedBit,
+    Basic,
+};
+use std::marker::PhantomData;
+
+/// The base field of BLS12-381.
+pub struct Bls12Base;
+
+impl ForeignModulus for Bls12Base {
+    type Words = [u64; 6];
+    const MODULUS: [u64; 6] = [
+        0xb9fe_ffff_ffff_aaab,
+        0x1eab_fffe_b153_ffff,
+        0x6730_d2a0_f6b0_f624,
+        0x6477_4b84_f385_12bf,
+        0x4b1b_a7b6_434b_acd7,
+        0x1a01_11ea_397f_e69a,
+    ];
+}
+
+pub type Bls12Element<F> = ForeignElement<F, Bls12Base>;
+
+/// A base field element, as little-endian 64-bit words.
+pub type BaseValue = [u64; 6];
+
+/// The absolute value of the curve parameter `x`, which is negative.
+const X: u64 = 0xd201_0000_0001_0000;
+
+/// The standard generator of G1.
+pub const G1_GENERATOR: G1Affine<BaseValue> = G1Affine {
+    x: [
+        0xfb3a_f00a_db22_c6bb,
+        0x6c55_e83f_f97a_1aef,
+        0xa14e_3a3f_171b_ac58,
+        0xc368_8c4f_9774_b905,
+        0x2695_638c_4fa9_ac0f,
+        0x17f1_d3a7_3197_d794,
+    ],
+    y: [
+        0x0caa_2329_46c5_e7e1,
+        0xd03c_c744_a288_8ae4,
+        0x00db_18cb_2c04_b3ed,
+        0xfcf5_e095_d5d0_0af6,
+        0xa09e_30ed_741d_8ae4,
+        0x08b3_f481_e3aa_a0f1,
+    ],
+};
+
+/// The standard generator of G2.
+pub const G2_GENERATOR: G2Affine<BaseValue> = G2Affine {
+    x: Fp2 {
+        c0: [
+            0xd480_56c8_c121_bdb8,
+            0x0bac_0326_a805_bbef,
+            0xb451_0b64_7ae3_d177,
+            0xc6e4_7ad4_fa40_3b02,
+            0x2608_0527_2dc5_1051,
+            0x024a_a2b2_f08f_0a91,
+        ],
+        c1: [
+            0xe5ac_7d05_5d04_2b7e,
+            0x334c_f112_1394_5d57,
+            0xb5da_61bb_dc7f_5049,
+            0x596b_d0d0_9920_b61a,
+            0x7dac_d3a0_8827_4f65,
+            0x13e0_2b60_5271_9f60,
+        ],
+    },
+    y: Fp2 {
+        c0: [
+            0xe193_5486_08b8_2801,
+            0x923a_c9cc_3bac_a289,
+            0x6d42_9a69_5160_d12c,
+            0xadfd_9baa_8cbd_d3a7,
+            0x8cc9_cdc6_da2e_351a,
+            0x0ce5_d527_727d_6e11,
+        ],
+        c1: [
+            0xaaa9_075f_f05f_79be,
+            0x3f37_0d27_5cec_1da1,
+            0x2674_92ab_572e_99ab,
+            0xcb3e_287e_85a7_63af,
+            0x32ac_d2b0_2bc2_8b99,
+            0x0606_c4a0_2ea7_34cc,
+        ],
+    },
+};
+
+fn small(v: u64) -> BaseValue {
+    words::to_array(&[v])
+}
+
+/// Arithmetic in the base field, either native or in a circuit.
+pub trait BaseField {
+    type Element: Clone;
+
+    fn constant(&mut self, value: BaseValue) -> Self::Element;
+
+    fn add(
+        &mut self,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, SynthesisError>;
+
+    fn sub(
+        &mut self,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, SynthesisError>;
+
+    fn mul(
+        &mut self,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, SynthesisError>;
+
+    /// `a / b`, where `b` must be nonzero.
+    fn div(
+        &mut self,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, SynthesisError>;
+
+    fn enforce_equal(&mut self, a: &Self::Element, b: &Self::Element)
+        -> Result<(), SynthesisError>;
+}
+
+/// Native arithmetic, for computing witnesses and constants. Failed checks
+/// are `Unsatisfiable` errors.
+pub struct Native;
+
+fn p() -> Vec<u64> {
+    Bls12Base::MODULUS.to_vec()
+}
+
+impl BaseField for Native {
+    type Element = BaseValue;
+
+    fn constant(&mut self, value: BaseValue) -> BaseValue {
+        words::reduce(&value, &p())
+    }
+
+    fn add(&mut self, a: &BaseValue, b: &BaseValue) -> Result<BaseValue, SynthesisError> {
+        Ok(words::add_mod(a, b, &p()))
+    }
+
+    fn sub(&mut self, a: &BaseValue, b: &BaseValue) -> Result<BaseValue, SynthesisError> {
+        Ok(words::sub_mod(a, b, &p()))
+    }
+
+    fn mul(&mut self, a: &BaseValue, b: &BaseValue) -> Result<BaseValue, SynthesisError> {
+        Ok(words::mul_mod(a, b, &p()))
+    }
+
+    fn div(&mut self, a: &BaseValue, b: &BaseValue) -> Result<BaseValue, SynthesisError> {
+        if *b == [0; 6] {
+            return Err(SynthesisError::DivisionByZero);
+        }
+        Ok(words::mul_mod(a, &words::invert_mod(b, &p()), &p()))
+    }
+
+    fn enforce_equal(&mut self, a: &BaseValue, b: &BaseValue) -> Result<(), SynthesisError> {
+        if a == b {
+            Ok(())
+        } else {
+            Err(SynthesisError::Unsatisfiable)
+        }
+    }
+}
+
+/// Non-native arithmetic in a circuit, each operation in a namespace of its
+/// own.
+pub struct InCircuit<F: Field, CS: ConstraintSystem<F>> {
+    cs: CS,
+    ops: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field, CS: ConstraintSystem<F>> InCircuit<F, CS> {
+    pub fn new(cs: CS) -> Self {
+        InCircuit {
+            cs,
+            ops: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    fn next(&mut self) -> usize {
+        self.ops += 1;
+        self.ops
+    }
+
+    /// `if_true` if `condition` holds, and `if_false` otherwise.
+    pub fn select(
+        &mut self,
+        condition: &Boolean,
+        if_true: &Bls12Element<F>,
+        if_false: &Bls12Element<F>,
+    ) -> Result<Bls12Element<F>, SynthesisError> {
+        let i = self.next();
+        Bls12Element::select(
+            self.cs.namespace(|| format!("select {}", i)),
+            condition,
+            if_true,
+            if_false,
+        )
+    }
+}
+
+impl<F: Field, CS: ConstraintSystem<F>> BaseField for InCircuit<F, CS> {
+    type Element = Bls12Element<F>;
+
+    fn constant(&mut self, value: BaseValue) -> Bls12Element<F> {
+        Bls12Element::constant(value)
+    }
+
+    fn add(
+        &mut self,
+        a: &Bls12Element<F>,
+        b: &Bls12Element<F>,
+    ) -> Result<Bls12Element<F>, SynthesisError> {
+        let i = self.next();
+        a.add(self.cs.namespace(|| format!("add {}", i)), b)
+    }
+
+    fn sub(
+        &mut self,
+        a: &Bls12Element<F>,
+        b: &Bls12Element<F>,
+    ) -> Result<Bls12Element<F>, SynthesisError> {
+        let i = self.next();
+        a.sub(self.cs.namespace(|| format!("sub {}", i)), b)
+    }
+
+    fn mul(
+        &mut self,
+        a: &Bls12Element<F>,
+        b: &Bls12Element<F>,
+    ) -> Result<Bls12Element<F>, SynthesisError> {
+        let i = self.next();
+        a.mul(self.cs.namespace(|| format!("mul {}", i)), b)
+    }
+
+    fn div(
+        &mut self,
+        a: &Bls12Element<F>,
+        b: &Bls12Element<F>,
+    ) -> Result<Bls12Element<F>, SynthesisError> {
+        let i = self.next();
+        let mut cs = self.cs.namespace(|| format!("div {}", i));
+        let value = match (a.get_value(), b.get_value()) {
+            (Some(a), Some(b)) => Some(Native.div(&a, &b)?),
+            _ => None,
+        };
+        let quotient = Bls12Element::alloc(cs.namespace(|| "quotient"), value)?;
+        let product = quotient.mul(cs.namespace(|| "product"), b)?;
+        Bls12Element::enforce_equal(cs.namespace(|| "product is dividend"), &product, a)?;
+        b.enforce_nonzero(cs.namespace(|| "divisor is nonzero"))?;
+        Ok(quotient)
+    }
+
+    fn enforce_equal(
+        &mut self,
+        a: &Bls12Element<F>,
+        b: &Bls12Element<F>,
+    ) -> Result<(), SynthesisError> {
+        let i = self.next();
+        Bls12Element::enforce_equal(self.cs.namespace(|| format!("equal {}", i)), a, b)
+    }
+}
+
+/// An element `c0 + c1 u` of `Fp2`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fp2<E> {
+    pub c0: E,
+    pub c1: E,
+}
+
+impl Fp2<BaseValue> {
+    /// This value as a constant of `b`.
+    pub fn lift<B: BaseField>(&self, b: &mut B) -> Fp2<B::Element> {
+        Fp2 {
+            c0: b.constant(self.c0),
+            c1: b.constant(self.c1),
+        }
+    }
+
+    fn pow(&self, exponent: &[u64]) -> Self {
+        let b = &mut Native;
+        let mut acc = Fp2::one(b);
+        for i in (0..64 * exponent.len()).rev() {
+            acc = acc.square(b).unwrap();
+            if (exponent[i / 64] >> (i % 64)) & 1 == 1 {
+                acc = acc.mul(b, self).unwrap();
+            }
+        }
+        acc
+    }
+}
+
+impl<E: Clone> Fp2<E> {
+    pub fn zero<B: BaseField<Element = E>>(b: &mut B) -> Self {
+        Fp2 {
+            c0: b.constant([0; 6]),
+            c1: b.constant([0; 6]),
+        }
+    }
+
+    pub fn one<B: BaseField<Element = E>>(b: &mut B) -> Self {
+        Fp2 {
+            c0: b.constant(small(1)),
+            c1: b.constant([0; 6]),
+        }
+    }
+
+    pub fn add<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Fp2 {
+            c0: b.add(&self.c0, &other.c0)?,
+            c1: b.add(&self.c1, &other.c1)?,
+        })
+    }
+
+    pub fn sub<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Fp2 {
+            c0: b.sub(&self.c0, &other.c0)?,
+            c1: b.sub(&self.c1, &other.c1)?,
+        })
+    }
+
+    pub fn neg<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        Fp2::zero(b).sub(b, self)
+    }
+
+    pub fn mul<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        // Additions cost as much as multiplications, so Karatsuba's trick
+        // would not pay.
+        let t0 = b.mul(&self.c0, &other.c0)?;
+        let t1 = b.mul(&self.c1, &other.c1)?;
+        let t2 = b.mul(&self.c0, &other.c1)?;
+        let t3 = b.mul(&self.c1, &other.c0)?;
+        Ok(Fp2 {
+            c0: b.sub(&t0, &t1)?,
+            c1: b.add(&t2, &t3)?,
+        })
+    }
+
+    pub fn square<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        let sum = b.add(&self.c0, &self.c1)?;
+        let difference = b.sub(&self.c0, &self.c1)?;
+        let product = b.mul(&self.c0, &self.c1)?;
+        Ok(Fp2 {
+            c0: b.mul(&sum, &difference)?,
+            c1: b.add(&product, &product)?,
+        })
+    }
+
+    /// This element multiplied by one of the base field.
+    pub fn mul_by_base<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &E,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Fp2 {
+            c0: b.mul(&self.c0, other)?,
+            c1: b.mul(&self.c1, other)?,
+        })
+    }
+
+    /// This element multiplied by `ξ = u + 1`.
+    pub fn mul_by_nonresidue<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Fp2 {
+            c0: b.sub(&self.c0, &self.c1)?,
+            c1: b.add(&self.c0, &self.c1)?,
+        })
+    }
+
+    pub fn conjugate<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        let zero = b.constant([0; 6]);
+        Ok(Fp2 {
+            c0: self.c0.clone(),
+            c1: b.sub(&zero, &self.c1)?,
+        })
+    }
+
+    pub fn invert<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        let c0_squared = b.mul(&self.c0, &self.c0)?;
+        let c1_squared = b.mul(&self.c1, &self.c1)?;
+        let norm = b.add(&c0_squared, &c1_squared)?;
+        let one = b.constant(small(1));
+        let inverse = b.div(&one, &norm)?;
+        self.conjugate(b)?.mul_by_base(b, &inverse)
+    }
+
+    /// This element raised to `p^n`.
+    pub fn frobenius<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        n: usize,
+    ) -> Result<Self, SynthesisError> {
+        if n % 2 == 1 {
+            self.conjugate(b)
+        } else {
+            Ok(self.clone())
+        }
+    }
+
+    pub fn enforce_equal<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<(), SynthesisError> {
+        b.enforce_equal(&self.c0, &other.c0)?;
+        b.enforce_equal(&self.c1, &other.c1)
+    }
+}
+
+/// An element `c0 + c1 v + c2 v^2` of `Fp6`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fp6<E> {
+    pub c0: Fp2<E>,
+    pub c1: Fp2<E>,
+    pub c2: Fp2<E>,
+}
+
+impl Fp6<BaseValue> {
+    /// This value as a constant of `b`.
+    pub fn lift<B: BaseField>(&self, b: &mut B) -> Fp6<B::Element> {
+        Fp6 {
+            c0: self.c0.lift(b),
+            c1: self.c1.lift(b),
+            c2: self.c2.lift(b),
+        }
+    }
+}
+
+impl<E: Clone> Fp6<E> {
+    pub fn zero<B: BaseField<Element = E>>(b: &mut B) -> Self {
+        Fp6 {
+            c0: Fp2::zero(b),
+            c1: Fp2::zero(b),
+            c2: Fp2::zero(b),
+        }
+    }
+
+    pub fn one<B: BaseField<Element = E>>(b: &mut B) -> Self {
+        Fp6 {
+            c0: Fp2::one(b),
+            c1: Fp2::zero(b),
+            c2: Fp2::zero(b),
+        }
+    }
+
+    pub fn add<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Fp6 {
+            c0: self.c0.add(b, &other.c0)?,
+            c1: self.c1.add(b, &other.c1)?,
+            c2: self.c2.add(b, &other.c2)?,
+        })
+    }
+
+    pub fn sub<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Fp6 {
+            c0: self.c0.sub(b, &other.c0)?,
+            c1: self.c1.sub(b, &other.c1)?,
+            c2: self.c2.sub(b, &other.c2)?,
+        })
+    }
+
+    pub fn neg<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        Fp6::zero(b).sub(b, self)
+    }
+
+    pub fn mul<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let a0b0 = self.c0.mul(b, &other.c0)?;
+        let a0b1 = self.c0.mul(b, &other.c1)?;
+        let a0b2 = self.c0.mul(b, &other.c2)?;
+        let a1b0 = self.c1.mul(b, &other.c0)?;
+        let a1b1 = self.c1.mul(b, &other.c1)?;
+        let a1b2 = self.c1.mul(b, &other.c2)?;
+        let a2b0 = self.c2.mul(b, &other.c0)?;
+        let a2b1 = self.c2.mul(b, &other.c1)?;
+        let a2b2 = self.c2.mul(b, &other.c2)?;
+
+        let wrapped = a1b2.add(b, &a2b1)?.mul_by_nonresidue(b)?;
+        let c0 = a0b0.add(b, &wrapped)?;
+        let wrapped = a2b2.mul_by_nonresidue(b)?;
+        let c1 = a0b1.add(b, &a1b0)?.add(b, &wrapped)?;
+        let c2 = a0b2.add(b, &a1b1)?.add(b, &a2b0)?;
+        Ok(Fp6 { c0, c1, c2 })
+    }
+
+    /// This element multiplied by `a0 + a1 v`.
+    pub fn mul_by_01<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        a0: &Fp2<E>,
+        a1: &Fp2<E>,
+    ) -> Result<Self, SynthesisError> {
+        let wrapped = self.c2.mul(b, a1)?.mul_by_nonresidue(b)?;
+        let c0 = self.c0.mul(b, a0)?.add(b, &wrapped)?;
+        let a1b0 = self.c1.mul(b, a0)?;
+        let c1 = self.c0.mul(b, a1)?.add(b, &a1b0)?;
+        let a2b0 = self.c2.mul(b, a0)?;
+        let c2 = self.c1.mul(b, a1)?.add(b, &a2b0)?;
+        Ok(Fp6 { c0, c1, c2 })
+    }
+
+    /// This element multiplied by one of the base field.
+    pub fn mul_by_base<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &E,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Fp6 {
+            c0: self.c0.mul_by_base(b, other)?,
+            c1: self.c1.mul_by_base(b, other)?,
+            c2: self.c2.mul_by_base(b, other)?,
+        })
+    }
+
+    /// This element multiplied by `v`.
+    pub fn mul_by_nonresidue<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Fp6 {
+            c0: self.c2.mul_by_nonresidue(b)?,
+            c1: self.c0.clone(),
+            c2: self.c1.clone(),
+        })
+    }
+
+    pub fn invert<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        let t0 = {
+            let wrapped = self.c1.mul(b, &self.c2)?.mul_by_nonresidue(b)?;
+            self.c0.square(b)?.sub(b, &wrapped)?
+        };
+        let t1 = {
+            let wrapped = self.c2.square(b)?.mul_by_nonresidue(b)?;
+            let product = self.c0.mul(b, &self.c1)?;
+            wrapped.sub(b, &product)?
+        };
+        let t2 = {
+            let product = self.c0.mul(b, &self.c2)?;
+            self.c1.square(b)?.sub(b, &product)?
+        };
+        let norm = {
+            let a1t2 = self.c1.mul(b, &t2)?;
+            let wrapped = self.c2.mul(b, &t1)?.add(b, &a1t2)?.mul_by_nonresidue(b)?;
+            self.c0.mul(b, &t0)?.add(b, &wrapped)?
+        };
+        let inverse = norm.invert(b)?;
+        Ok(Fp6 {
+            c0: t0.mul(b, &inverse)?,
+            c1: t1.mul(b, &inverse)?,
+            c2: t2.mul(b, &inverse)?,
+        })
+    }
+
+    pub fn enforce_equal<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<(), SynthesisError> {
+        self.c0.enforce_equal(b, &other.c0)?;
+        self.c1.enforce_equal(b, &other.c1)?;
+        self.c2.enforce_equal(b, &other.c2)
+    }
+}
+
+/// An element `c0 + c1 w` of `Fp12`, the target group of the pairing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fp12<E> {
+    pub c0: Fp6<E>,
+    pub c1: Fp6<E>,
+}
+
+impl Fp12<BaseValue> {
+    /// This value as a constant of `b`.
+    pub fn lift<B: BaseField>(&self, b: &mut B) -> Fp12<B::Element> {
+        Fp12 {
+            c0: self.c0.lift(b),
+            c1: self.c1.lift(b),
+        }
+    }
+}
+
+// `ξ^(k (p^n - 1) / 6)` for `k` from zero to five, by which the coefficient
+// of `w^k` is multiplied when raising to `p^n`.
+fn frobenius_coefficients(n: usize) -> Vec<Fp2<BaseValue>> {
+    let mut power = vec![1];
+    for _ in 0..n {
+        power = words::mul(&power, &p());
+    }
+    let exponent = words::divmod(&words::sub(&power, &[1]), &[6]).0;
+    let xi = Fp2 {
+        c0: small(1),
+        c1: small(1),
+    };
+    let gamma = xi.pow(&exponent);
+
+    let mut coefficients = vec![Fp2::one(&mut Native)];
+    for _ in 1..6 {
+        let last = coefficients.last().unwrap();
+        coefficients.push(last.mul(&mut Native, &gamma).unwrap());
+    }
+    coefficients
+}
+
+impl<E: Clone> Fp12<E> {
+    pub fn one<B: BaseField<Element = E>>(b: &mut B) -> Self {
+        Fp12 {
+            c0: Fp6::one(b),
+            c1: Fp6::zero(b),
+        }
+    }
+
+    pub fn mul<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let t0 = self.c0.mul(b, &other.c0)?;
+        let t1 = self.c1.mul(b, &other.c1)?;
+        let sum = self.c0.add(b, &self.c1)?;
+        let other_sum = other.c0.add(b, &other.c1)?;
+        let c1 = sum.mul(b, &other_sum)?.sub(b, &t0)?.sub(b, &t1)?;
+        let wrapped = t1.mul_by_nonresidue(b)?;
+        let c0 = t0.add(b, &wrapped)?;
+        Ok(Fp12 { c0, c1 })
+    }
+
+    pub fn square<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        let product = self.c0.mul(b, &self.c1)?;
+        let sum = self.c0.add(b, &self.c1)?;
+        let wrapped = self.c1.mul_by_nonresidue(b)?;
+        let twisted = self.c0.add(b, &wrapped)?;
+        let wrapped = product.mul_by_nonresidue(b)?;
+        let c0 = sum.mul(b, &twisted)?.sub(b, &product)?.sub(b, &wrapped)?;
+        let c1 = product.add(b, &product)?;
+        Ok(Fp12 { c0, c1 })
+    }
+
+    /// This element multiplied by the line `l0 + l2 w^2 + y w^3`.
+    fn mul_by_line<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        l0: &Fp2<E>,
+        l2: &Fp2<E>,
+        y: &E,
+    ) -> Result<Self, SynthesisError> {
+        let t0 = self.c0.mul_by_01(b, l0, l2)?;
+        let t1 = self.c1.mul_by_base(b, y)?.mul_by_nonresidue(b)?;
+        let l2_and_y = Fp2 {
+            c0: b.add(&l2.c0, y)?,
+            c1: l2.c1.clone(),
+        };
+        let sum = self.c0.add(b, &self.c1)?;
+        let c1 = sum.mul_by_01(b, l0, &l2_and_y)?.sub(b, &t0)?.sub(b, &t1)?;
+        let wrapped = t1.mul_by_nonresidue(b)?;
+        let c0 = t0.add(b, &wrapped)?;
+        Ok(Fp12 { c0, c1 })
+    }
+
+    /// The inverse of this element if it is unitary, such as a result of the
+    /// easy part of the final exponentiation.
+    pub fn conjugate<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        Ok(Fp12 {
+            c0: self.c0.clone(),
+            c1: self.c1.neg(b)?,
+        })
+    }
+
+    pub fn invert<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        let c1_squared = self.c1.mul(b, &self.c1)?.mul_by_nonresidue(b)?;
+        let norm = self.c0.mul(b, &self.c0)?.sub(b, &c1_squared)?;
+        let inverse = norm.invert(b)?;
+        Ok(Fp12 {
+            c0: self.c0.mul(b, &inverse)?,
+            c1: self.c1.mul(b, &inverse)?.neg(b)?,
+        })
+    }
+
+    /// This element raised to `p^n`.
+    pub fn frobenius<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        n: usize,
+    ) -> Result<Self, SynthesisError> {
+        let coefficients = frobenius_coefficients(n);
+        // The coefficients of w^0 to w^5
+        let mut c = [
+            &self.c0.c0,
+            &self.c1.c0,
+            &self.c0.c1,
+            &self.c1.c1,
+            &self.c0.c2,
+            &self.c1.c2,
+        ]
+        .iter()
+        .zip(coefficients.iter())
+        .enumerate()
+        .map(|(k, (c, gamma))| {
+            let c = c.frobenius(b, n)?;
+            if k == 0 {
+                Ok(c)
+            } else {
+                let gamma = gamma.lift(b);
+                c.mul(b, &gamma)
+            }
+        })
+        .collect::<Result<Vec<_>, SynthesisError>>()?
+        .into_iter();
+        let mut next = || c.next().unwrap();
+        let (c00, c10, c01, c11, c02, c12) = (next(), next(), next(), next(), next(), next());
+        Ok(Fp12 {
+            c0: Fp6 {
+                c0: c00,
+                c1: c01,
+                c2: c02,
+            },
+            c1: Fp6 {
+                c0: c10,
+                c1: c11,
+                c2: c12,
+            },
+        })
+    }
+
+    pub fn enforce_equal<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<(), SynthesisError> {
+        self.c0.enforce_equal(b, &other.c0)?;
+        self.c1.enforce_equal(b, &other.c1)
+    }
+}
+
+/// A point of G1, on `y^2 = x^3 + 4` over the base field, in affine
+/// coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct G1Affine<E> {
+    pub x: E,
+    pub y: E,
+}
+
+impl G1Affine<BaseValue> {
+    /// This point as a constant of `b`.
+    pub fn lift<B: BaseField>(&self, b: &mut B) -> G1Affine<B::Element> {
+        G1Affine {
+            x: b.constant(self.x),
+            y: b.constant(self.y),
+        }
+    }
+}
+
+impl<E: Clone> G1Affine<E> {
+    pub fn neg<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        let zero = b.constant([0; 6]);
+        Ok(G1Affine {
+            x: self.x.clone(),
+            y: b.sub(&zero, &self.y)?,
+        })
+    }
+
+    // The negation of the third point on the line through this point with
+    // slope `slope`, whose second point has `x` coordinate `other_x`.
+    fn third_point<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        slope: &E,
+        other_x: &E,
+    ) -> Result<Self, SynthesisError> {
+        let x = b.mul(slope, slope)?;
+        let x = b.sub(&x, &self.x)?;
+        let x = b.sub(&x, other_x)?;
+        let y = b.sub(&self.x, &x)?;
+        let y = b.mul(slope, &y)?;
+        let y = b.sub(&y, &self.y)?;
+        Ok(G1Affine { x, y })
+    }
+
+    /// The sum of this point and `other`, which must have distinct `x`
+    /// coordinates.
+    pub fn add<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let dy = b.sub(&other.y, &self.y)?;
+        let dx = b.sub(&other.x, &self.x)?;
+        let slope = b.div(&dy, &dx)?;
+        self.third_point(b, &slope, &other.x)
+    }
+
+    pub fn double<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        let x_squared = b.mul(&self.x, &self.x)?;
+        let numerator = b.add(&x_squared, &x_squared)?;
+        let numerator = b.add(&numerator, &x_squared)?;
+        let denominator = b.add(&self.y, &self.y)?;
+        let slope = b.div(&numerator, &denominator)?;
+        self.third_point(b, &slope, &self.x)
+    }
+
+    pub fn enforce_on_curve<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+    ) -> Result<(), SynthesisError> {
+        let y_squared = b.mul(&self.y, &self.y)?;
+        let x_squared = b.mul(&self.x, &self.x)?;
+        let x_cubed = b.mul(&x_squared, &self.x)?;
+        let four = b.constant(small(4));
+        let rhs = b.add(&x_cubed, &four)?;
+        b.enforce_equal(&y_squared, &rhs)
+    }
+}
+
+impl<F: Field> G1Affine<Bls12Element<F>> {
+    /// Allocates `value`, constrained to be on the curve.
+    pub fn alloc<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        value: Option<&G1Affine<BaseValue>>,
+    ) -> Result<Self, SynthesisError> {
+        let point = G1Affine {
+            x: Bls12Element::alloc(cs.namespace(|| "x"), value.map(|v| v.x))?,
+            y: Bls12Element::alloc(cs.namespace(|| "y"), value.map(|v| v.y))?,
+        };
+        point.enforce_on_curve(&mut InCircuit::new(cs.namespace(|| "on curve")))?;
+        Ok(point)
+    }
+
+    pub fn get_value(&self) -> Option<G1Affine<BaseValue>> {
+        Some(G1Affine {
+            x: self.x.get_value()?,
+            y: self.y.get_value()?,
+        })
+    }
+}
+
+/// A point of G2, on the twist `y^2 = x^3 + 4 ξ` over `Fp2`, in affine
+/// coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct G2Affine<E> {
+    pub x: Fp2<E>,
+    pub y: Fp2<E>,
+}
+
+impl G2Affine<BaseValue> {
+    /// This point as a constant of `b`.
+    pub fn lift<B: BaseField>(&self, b: &mut B) -> G2Affine<B::Element> {
+        G2Affine {
+            x: self.x.lift(b),
+            y: self.y.lift(b),
+        }
+    }
+}
+
+impl<E: Clone> G2Affine<E> {
+    pub fn neg<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        Ok(G2Affine {
+            x: self.x.clone(),
+            y: self.y.neg(b)?,
+        })
+    }
+
+    fn third_point<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        slope: &Fp2<E>,
+        other_x: &Fp2<E>,
+    ) -> Result<Self, SynthesisError> {
+        let x = slope.square(b)?.sub(b, &self.x)?.sub(b, other_x)?;
+        let y = self.x.sub(b, &x)?.mul(b, slope)?.sub(b, &self.y)?;
+        Ok(G2Affine { x, y })
+    }
+
+    fn add_slope<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<Fp2<E>, SynthesisError> {
+        let dy = other.y.sub(b, &self.y)?;
+        let dx = other.x.sub(b, &self.x)?;
+        let inverse = dx.invert(b)?;
+        dy.mul(b, &inverse)
+    }
+
+    fn double_slope<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Fp2<E>, SynthesisError> {
+        let x_squared = self.x.square(b)?;
+        let numerator = x_squared.add(b, &x_squared)?.add(b, &x_squared)?;
+        let denominator = self.y.add(b, &self.y)?;
+        let inverse = denominator.invert(b)?;
+        numerator.mul(b, &inverse)
+    }
+
+    /// The sum of this point and `other`, which must have distinct `x`
+    /// coordinates.
+    pub fn add<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let slope = self.add_slope(b, other)?;
+        self.third_point(b, &slope, &other.x)
+    }
+
+    pub fn double<B: BaseField<Element = E>>(&self, b: &mut B) -> Result<Self, SynthesisError> {
+        let slope = self.double_slope(b)?;
+        self.third_point(b, &slope, &self.x)
+    }
+
+    pub fn enforce_on_curve<B: BaseField<Element = E>>(
+        &self,
+        b: &mut B,
+    ) -> Result<(), SynthesisError> {
+        let y_squared = self.y.square(b)?;
+        let x_cubed = self.x.square(b)?.mul(b, &self.x)?;
+        let four = b.constant(small(4));
+        let rhs = x_cubed.add(
+            b,
+            &Fp2 {
+                c0: four.clone(),
+                c1: four,
+            },
+        )?;
+        y_squared.enforce_equal(b, &rhs)
+    }
+}
+
+impl<F: Field> G2Affine<Bls12Element<F>> {
+    /// Allocates `value`, constrained to be on the curve.
+    pub fn alloc<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        value: Option<&G2Affine<BaseValue>>,
+    ) -> Result<Self, SynthesisError> {
+        let mut coordinate = |name: &'static str, value: Option<&Fp2<BaseValue>>| {
+            Ok::<_, SynthesisError>(Fp2 {
+                c0: Bls12Element::alloc(
+                    cs.namespace(|| format!("{} c0", name)),
+                    value.map(|v| v.c0),
+                )?,
+                c1: Bls12Element::alloc(
+                    cs.namespace(|| format!("{} c1", name)),
+                    value.map(|v| v.c1),
+                )?,
+            })
+        };
+        let point = G2Affine {
+            x: coordinate("x", value.map(|v| &v.x))?,
+            y: coordinate("y", value.map(|v| &v.y))?,
+        };
+        point.enforce_on_curve(&mut InCircuit::new(cs.namespace(|| "on curve")))?;
+        Ok(point)
+    }
+}
+
+/// A point of G1 and one of G2, to be paired.
+pub type Pair<E> = (G1Affine<E>, G2Affine<E>);
+
+// `f` multiplied by the line through `t` with slope `slope`, evaluated at
+// the point with coordinates `-x` and `y`.
+fn line<B: BaseField>(
+    b: &mut B,
+    f: &Fp12<B::Element>,
+    t: &G2Affine<B::Element>,
+    slope: &Fp2<B::Element>,
+    (neg_x, y): (&B::Element, &B::Element),
+) -> Result<Fp12<B::Element>, SynthesisError> {
+    // Untwisted, the line is y - y_T w^-3 - slope w^-1 (x - x_T w^-2);
+    // scaled by w^3 it is (slope x_T - y_T) - slope x w^2 + y w^3.
+    let l0 = slope.mul(b, &t.x)?.sub(b, &t.y)?;
+    let l2 = slope.mul_by_base(b, neg_x)?;
+    f.mul_by_line(b, &l0, &l2, y)
+}
+
+/// The product of the Miller loops of `pairs`.
+pub fn miller_loop<B: BaseField>(
+    b: &mut B,
+    pairs: &[Pair<B::Element>],
+) -> Result<Fp12<B::Element>, SynthesisError> {
+    let zero = b.constant([0; 6]);
+    let evaluations = pairs
+        .iter()
+        .map(|(p, _)| Ok((b.sub(&zero, &p.x)?, p.y.clone())))
+        .collect::<Result<Vec<_>, SynthesisError>>()?;
+    let mut ts: Vec<_> = pairs.iter().map(|(_, q)| q.clone()).collect();
+
+    let mut f = Fp12::one(b);
+    for i in (0..63).rev() {
+        if i != 62 {
+            f = f.square(b)?;
+        }
+        for (t, (neg_x, y)) in ts.iter_mut().zip(evaluations.iter()) {
+            let slope = t.double_slope(b)?;
+            f = line(b, &f, t, &slope, (neg_x, y))?;
+            *t = t.third_point(b, &slope, &t.x)?;
+        }
+        if (X >> i) & 1 == 1 {
+            for ((t, (_, q)), (neg_x, y)) in ts.iter_mut().zip(pairs).zip(evaluations.iter()) {
+                let slope = t.add_slope(b, q)?;
+                f = line(b, &f, t, &slope, (neg_x, y))?;
+                *t = t.third_point(b, &slope, &q.x)?;
+            }
+        }
+    }
+
+    // The loop ran over -x.
+    f.conjugate(b)
+}
+
+// `a^x` for `a` unitary.
+fn exp_by_x<B: BaseField>(
+    b: &mut B,
+    a: &Fp12<B::Element>,
+) -> Result<Fp12<B::Element>, SynthesisError> {
+    let mut acc = a.clone();
+    for i in (0..63).rev() {
+        acc = acc.square(b)?;
+        if (X >> i) & 1 == 1 {
+            acc = acc.mul(b, a)?;
+        }
+    }
+    acc.conjugate(b)
+}
+
+/// `f^(3 (p^12 - 1) / r)`, which is one for the same `f` as the usual final
+/// exponentiation.
+pub fn final_exponentiation<B: BaseField>(
+    b: &mut B,
+    f: &Fp12<B::Element>,
+) -> Result<Fp12<B::Element>, SynthesisError> {
+    // f^((p^6 - 1) (p^2 + 1))
+    let inverse = f.invert(b)?;
+    let m = f.conjugate(b)?.mul(b, &inverse)?;
+    let m = m.frobenius(b, 2)?.mul(b, &m)?;
+
+    // 3 (p^4 - p^2 + 1) / r = λ3 p^3 + λ2 p^2 + λ1 p + λ0, for λ3 = (x - 1)^2,
+    // λ2 = λ3 x, λ1 = λ2 x - λ3 and λ0 = λ1 x + 3.
+    let m_inverse = m.conjugate(b)?;
+    let t = exp_by_x(b, &m)?.mul(b, &m_inverse)?;
+    let t_inverse = t.conjugate(b)?;
+    let l3 = exp_by_x(b, &t)?.mul(b, &t_inverse)?;
+    let l2 = exp_by_x(b, &l3)?;
+    let l3_inverse = l3.conjugate(b)?;
+    let l1 = exp_by_x(b, &l2)?.mul(b, &l3_inverse)?;
+    let m_squared = m.square(b)?;
+    let l0 = exp_by_x(b, &l1)?.mul(b, &m_squared)?.mul(b, &m)?;
+
+    let l1 = l1.frobenius(b, 1)?;
+    let l2 = l2.frobenius(b, 2)?;
+    let l3 = l3.frobenius(b, 3)?;
+    l0.mul(b, &l1)?.mul(b, &l2)?.mul(b, &l3)
+}
+
+/// The cube of the pairing of `p` and `q`, computed natively.
+pub fn pairing(
+    p: &G1Affine<BaseValue>,
+    q: &G2Affine<BaseValue>,
+) -> Result<Fp12<BaseValue>, SynthesisError> {
+    let f = miller_loop(&mut Native, &[(p.clone(), q.clone())])?;
+    final_exponentiation(&mut Native, &f)
+}
+
+// Checks that the product of the pairings of `pairs`, with the Miller loop
+// value `fixed` multiplied in, is one.
+fn product_is_one<B: BaseField>(
+    b: &mut B,
+    pairs: &[Pair<B::Element>],
+    fixed: Option<&Fp12<BaseValue>>,
+) -> Result<(), SynthesisError> {
+    let mut f = miller_loop(b, pairs)?;
+    if let Some(fixed) = fixed {
+        let fixed = fixed.lift(b);
+        f = f.mul(b, &fixed)?;
+    }
+    let f = final_exponentiation(b, &f)?;
+    let one = Fp12::one(b);
+    f.enforce_equal(b, &one)
+}
+
+/// Constrains the product of the pairings of `pairs` to be one.
+pub fn pairing_check<F: Field, CS: ConstraintSystem<F>>(
+    cs: CS,
+    pairs: &[Pair<Bls12Element<F>>],
+) -> Result<(), SynthesisError> {
+    product_is_one(&mut InCircuit::new(cs), pairs, None)
+}
+
+/// Constrains `signature` to be a BLS signature under `public_key` on a
+/// message hashing to `message`, with public keys in G1 and signatures in G2
+/// as in Ethereum. Hashing the message to G2 is left to the caller.
+pub fn verify_bls<F: Field, CS: ConstraintSystem<F>>(
+    cs: CS,
+    public_key: &G1Affine<Bls12Element<F>>,
+    message: &G2Affine<Bls12Element<F>>,
+    signature: &G2Affine<Bls12Element<F>>,
+) -> Result<(), SynthesisError> {
+    let mut b = InCircuit::new(cs);
+    let generator = G1_GENERATOR.lift(&mut b).neg(&mut b)?;
+    product_is_one(
+        &mut b,
+        &[
+            (public_key.clone(), message.clone()),
+            (generator, signature.clone()),
+        ],
+        None,
+    )
+}
+
+/// A Groth16 verifying key.
+#[derive(Clone, Debug)]
+pub struct VerifyingKey {
+    pub alpha_g1: G1Affine<BaseValue>,
+    pub beta_g2: G2Affine<BaseValue>,
+    pub gamma_g2: G2Affine<BaseValue>,
+    pub delta_g2: G2Affine<BaseValue>,
+    /// The bases of the public inputs, the first for the constant one.
+    pub ic: Vec<G1Affine<BaseValue>>,
+}
+
+/// A Groth16 proof.
+#[derive(Clone, Debug)]
+pub struct Proof<E> {
+    pub a: G1Affine<E>,
+    pub b: G2Affine<E>,
+    pub c: G1Affine<E>,
+}
+
+// A point of G1 no one knows the discrete logarithm of, from which the
+// public input combination is accumulated so that no addition is of the
+// identity. It is the point with the smallest positive `x`.
+fn offset_point() -> G1Affine<BaseValue> {
+    let b = &mut Native;
+    // p = 3 (mod 4)
+    let exponent = words::divmod(&words::add(&p(), &[1]), &[4]).0;
+    let mut x = 0;
+    loop {
+        x += 1;
+        let x = small(x);
+        let rhs = words::add_mod(
+            &words::mul_mod(&words::mul_mod(&x, &x, &p()), &x, &p()),
+            &small(4),
+            &p(),
+        );
+        let y = words::to_array(&words::pow_mod(&rhs, &exponent, &p()));
+        let point = G1Affine { x, y };
+        if point.enforce_on_curve(b).is_ok() {
+            return point;
+        }
+    }
+}
+
+// `ic[0] + Σ inputs[i] ic[i + 1]`, accumulated from the offset point.
+fn input_point<F: Field, CS: ConstraintSystem<F>>(
+    b: &mut InCircuit<F, CS>,
+    ic: &[G1Affine<BaseValue>],
+    inputs: &[Vec<Boolean>],
+) -> Result<G1Affine<Bls12Element<F>>, SynthesisError> {
+    let offset = offset_point();
+    let mut acc = offset.lift(b);
+    for (input, base) in inputs.iter().zip(ic[1..].iter()) {
+        let mut power = base.clone();
+        for bit in input {
+            let base = power.lift(b);
+            let sum = acc.add(b, &base)?;
+            acc = G1Affine {
+                x: b.select(bit, &sum.x, &acc.x)?,
+                y: b.select(bit, &sum.y, &acc.y)?,
+            };
+            power = power.double(&mut Native)?;
+        }
+    }
+    let correction = ic[0].add(&mut Native, &offset.neg(&mut Native)?)?;
+    let correction = correction.lift(b);
+    acc.add(b, &correction)
+}
+
+/// Constrains `proof` to be valid under `vk` for the public inputs `inputs`,
+/// each given as little-endian bits, at most as many as the scalar field of
+/// BLS12-381 has.
+pub fn verify_groth16<F: Field, CS: ConstraintSystem<F>>(
+    cs: CS,
+    vk: &VerifyingKey,
+    proof: &Proof<Bls12Element<F>>,
+    inputs: &[Vec<Boolean>],
+) -> Result<(), SynthesisError> {
+    assert_eq!(inputs.len() + 1, vk.ic.len());
+    let mut b = InCircuit::new(cs);
+
+    let inputs = input_point(&mut b, &vk.ic, inputs)?;
+
+    // e(A, B) = e(alpha, beta) e(inputs, gamma) e(C, delta)
+    let fixed = miller_loop(&mut Native, &[(vk.alpha_g1.clone(), vk.beta_g2.clone())])?;
+    let a = proof.a.neg(&mut b)?;
+    let gamma = vk.gamma_g2.lift(&mut b);
+    let delta = vk.delta_g2.lift(&mut b);
+    product_is_one(
+        &mut b,
+        &[
+            (a, proof.b.clone()),
+            (inputs, gamma),
+            (proof.c.clone(), delta),
+        ],
+        Some(&fixed),
+    )
+}
+
+#[cfg(feature = "prover")]
+struct PairingCheckCost {
+    pairs: usize,
+}
+
+#[cfg(feature = "prover")]
+impl<F: Field> Circuit<F> for PairingCheckCost {
+    fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut pairs = vec![];
+        for i in 0..self.pairs {
+            pairs.push((
+                G1Affine::alloc(cs.namespace(|| format!("p {}", i)), None)?,
+                G2Affine::alloc(cs.namespace(|| format!("q {}", i)), None)?,
+            ));
+        }
+        pairing_check(cs.namespace(|| "pairing check"), &pairs)
+    }
+}
+
+#[cfg(feature = "prover")]
+struct VerifyBlsCost;
+
+#[cfg(feature = "prover")]
+impl<F: Field> Circuit<F> for VerifyBlsCost {
+    fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let public_key = G1Affine::alloc(cs.namespace(|| "public key"), None)?;
+        let message = G2Affine::alloc(cs.namespace(|| "message"), None)?;
+        let signature = G2Affine::alloc(cs.namespace(|| "signature"), None)?;
+        verify_bls(cs.namespace(|| "verify"), &public_key, &message, &signature)
+    }
+}
+
+#[cfg(feature = "prover")]
+struct VerifyGroth16Cost {
+    inputs: usize,
+    input_bits: usize,
+}
+
+#[cfg(feature = "prover")]
+impl<F: Field> Circuit<F> for VerifyGroth16Cost {
+    fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        // The cost does not depend on the key, as long as its bases are
+        // distinct.
+        let mut ic = vec![G1_GENERATOR, G1_GENERATOR.double(&mut Native)?];
+        while ic.len() <= self.inputs {
+            let next = ic.last().unwrap().add(&mut Native, &G1_GENERATOR)?;
+            ic.push(next);
+        }
+        ic.truncate(self.inputs + 1);
+        let vk = VerifyingKey {
+            alpha_g1: G1_GENERATOR,
+            beta_g2: G2_GENERATOR,
+            gamma_g2: G2_GENERATOR,
+            delta_g2: G2_GENERATOR,
+            ic,
+        };
+
+        let proof = Proof {
+            a: G1Affine::alloc(cs.namespace(|| "a"), None)?,
+            b: G2Affine::alloc(cs.namespace(|| "b"), None)?,
+            c: G1Affine::alloc(cs.namespace(|| "c"), None)?,
+        };
+        let mut inputs = vec![];
+        for i in 0..self.inputs {
+            let mut bits = vec![];
+            for j in 0..self.input_bits {
+                bits.push(Boolean::Is(AllocatedBit::alloc(
+                    cs.namespace(|| format!("input {} bit {}", i, j)),
+                    || Err(SynthesisError::AssignmentMissing),
+                )?));
+            }
+            inputs.push(bits);
+        }
+        verify_groth16(cs.namespace(|| "verify"), &vk, &proof, &inputs)
+    }
+}
+
+/// The cost of [`pairing_check`] of `pairs` pairs, including allocating the
+/// points.
+#[cfg(feature = "prover")]
+pub fn pairing_check_cost<F: Field>(pairs: usize) -> Result<ConstraintCounts, SynthesisError> {
+    constraint_totals::<F, _, Basic>(&PairingCheckCost { pairs })
+}
+
+/// The cost of [`verify_bls`], including allocating the points.
+#[cfg(feature = "prover")]
+pub fn verify_bls_cost<F: Field>() -> Result<ConstraintCounts, SynthesisError> {
+    constraint_totals::<F, _, Basic>(&VerifyBlsCost)
+}
+
+/// The cost of [`verify_groth16`] with `inputs` public inputs of
+/// `input_bits` bits each, including allocating the proof.
+#[cfg(feature = "prover")]
+pub fn verify_groth16_cost<F: Field>(
+    inputs: usize,
+    input_bits: usize,
+) -> Result<ConstraintCounts, SynthesisError> {
+    constraint_totals::<F, _, Basic>(&VerifyGroth16Cost { inputs, input_bits })
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        dev::{is_satisfied, GadgetCheck},
+        fields::Fp,
+    };
+
+    fn mul_g1(point: &G1Affine<BaseValue>, scalar: u64) -> G1Affine<BaseValue> {
+        let mut acc = point.clone();
+        for i in (0..63 - scalar.leading_zeros()).rev() {
+            acc = acc.double(&mut Native).unwrap();
+            if (scalar >> i) & 1 == 1 {
+                acc = acc.add(&mut Native, point).unwrap();
+            }
+        }
+        acc
+    }
+
+    fn mul_g2(point: &G2Affine<BaseValue>, scalar: u64) -> G2Affine<BaseValue> {
+        let mut acc = point.clone();
+        for i in (0..63 - scalar.leading_zeros()).rev() {
+            acc = acc.double(&mut Native).unwrap();
+            if (scalar >> i) & 1 == 1 {
+                acc = acc.add(&mut Native, point).unwrap();
+            }
+        }
+        acc
+    }
+
+    #[test]
+    fn test_generators() {
+        G1_GENERATOR.enforce_on_curve(&mut Native).unwrap();
+        G2_GENERATOR.enforce_on_curve(&mut Native).unwrap();
+        mul_g1(&G1_GENERATOR, 5)
+            .enforce_on_curve(&mut Native)
+            .unwrap();
+        mul_g2(&G2_GENERATOR, 5)
+            .enforce_on_curve(&mut Native)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_bilinearity() {
+        let e = pairing(&G1_GENERATOR, &G2_GENERATOR).unwrap();
+        assert_ne!(e, Fp12::one(&mut Native));
+
+        let squared = e.square(&mut Native).unwrap();
+        assert_eq!(
+            pairing(&mul_g1(&G1_GENERATOR, 2), &G2_GENERATOR).unwrap(),
+            squared
+        );
+        assert_eq!(
+            pairing(&G1_GENERATOR, &mul_g2(&G2_GENERATOR, 2)).unwrap(),
+            squared
+        );
+    }
+
+    #[test]
+    fn test_product_is_one() {
+        let pairs = |k: u64| {
+            vec![
+                (mul_g1(&G1_GENERATOR, 2), mul_g2(&G2_GENERATOR, 3)),
+                (
+                    mul_g1(&G1_GENERATOR, k).neg(&mut Native).unwrap(),
+                    G2_GENERATOR,
+                ),
+            ]
+        };
+        assert_eq!(product_is_one(&mut Native, &pairs(6), None), Ok(()));
+        assert_eq!(
+            product_is_one(&mut Native, &pairs(5), None),
+            Err(SynthesisError::Unsatisfiable)
+        );
+
+        // A Groth16-shaped equation, with e(alpha, beta) multiplied in:
+        // e(A, B) = e(2 G1, 3 G2) e(7 G1, G2) e(4 G1, G2) for A = 17 G1
+        let fixed = miller_loop(
+            &mut Native,
+            &[(mul_g1(&G1_GENERATOR, 2), mul_g2(&G2_GENERATOR, 3))],
+        )
+        .unwrap();
+        let pairs = [
+            (
+                mul_g1(&G1_GENERATOR, 17).neg(&mut Native).unwrap(),
+                G2_GENERATOR,
+            ),
+            (mul_g1(&G1_GENERATOR, 7), G2_GENERATOR),
+            (mul_g1(&G1_GENERATOR, 4), G2_GENERATOR),
+        ];
+        assert_eq!(product_is_one(&mut Native, &pairs, Some(&fixed)), Ok(()));
+    }
+
+    struct Fp2Circuit {
+        a: Fp2<BaseValue>,
+        b: Fp2<BaseValue>,
+        // a b and 1 / a
+        expected: (Fp2<BaseValue>, Fp2<BaseValue>),
+    }
+
+    impl Circuit<Fp> for Fp2Circuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut alloc = |name: &str, value: &Fp2<BaseValue>| {
+                Ok::<_, SynthesisError>(Fp2 {
+                    c0: Bls12Element::alloc(
+                        cs.namespace(|| format!("{} c0", name)),
+                        Some(value.c0),
+                    )?,
+                    c1: Bls12Element::alloc(
+                        cs.namespace(|| format!("{} c1", name)),
+                        Some(value.c1),
+                    )?,
+                })
+            };
+            let a = alloc("a", &self.a)?;
+            let b = alloc("b", &self.b)?;
+
+            let mut field = InCircuit::new(cs.namespace(|| "ops"));
+            let product = a.mul(&mut field, &b)?;
+            let expected = self.expected.0.lift(&mut field);
+            product.enforce_equal(&mut field, &expected)?;
+            let inverse = a.invert(&mut field)?;
+            let expected = self.expected.1.lift(&mut field);
+            inverse.enforce_equal(&mut field, &expected)
+        }
+    }
+
+    #[test]
+    fn test_fp2() {
+        let a = G2_GENERATOR.x;
+        let b = G2_GENERATOR.y;
+        let product = a.mul(&mut Native, &b).unwrap();
+        let inverse = a.invert(&mut Native).unwrap();
+        assert_eq!(inverse.mul(&mut Native, &a).unwrap(), Fp2::one(&mut Native));
+
+        GadgetCheck::new(
+            Fp2Circuit {
+                a: a.clone(),
+                b: b.clone(),
+                expected: (product.clone(), inverse.clone()),
+            },
+            &[],
+        )
+        .malformed(
+            "wrong product",
+            Fp2Circuit {
+                a: a.clone(),
+                b: b.clone(),
+                expected: (product.conjugate(&mut Native).unwrap(), inverse.clone()),
+            },
+            &[],
+        )
+        .malformed(
+            "wrong inverse",
+            Fp2Circuit {
+                a,
+                b,
+                expected: (product, inverse.conjugate(&mut Native).unwrap()),
+            },
+            &[],
+        )
+        .check::<Basic>()
+        .unwrap();
+    }
+
+    struct PointCircuit {
+        g1: G1Affine<BaseValue>,
+        g2: G2Affine<BaseValue>,
+    }
+
+    impl Circuit<Fp> for PointCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            G1Affine::alloc(cs.namespace(|| "g1"), Some(&self.g1))?;
+            G2Affine::alloc(cs.namespace(|| "g2"), Some(&self.g2))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_on_curve() {
+        let mut off_curve = G1_GENERATOR;
+        off_curve.y[0] ^= 1;
+        let mut twist_off_curve = G2_GENERATOR;
+        twist_off_curve.x.c1[0] ^= 1;
+
+        GadgetCheck::new(
+            PointCircuit {
+                g1: G1_GENERATOR,
+                g2: G2_GENERATOR,
+            },
+            &[],
+        )
+        .malformed(
+            "G1 point off the curve",
+            PointCircuit {
+                g1: off_curve,
+                g2: G2_GENERATOR,
+            },
+            &[],
+        )
+        .malformed(
+            "G2 point off the curve",
+            PointCircuit {
+                g1: G1_GENERATOR,
+                g2: twist_off_curve,
+            },
+            &[],
+        )
+        .check::<Basic>()
+        .unwrap();
+    }
+
+    struct InputCircuit {
+        inputs: Vec<u64>,
+        expected: G1Affine<BaseValue>,
+    }
+
+    impl Circuit<Fp> for InputCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut inputs = vec![];
+            for (i, input) in self.inputs.iter().enumerate() {
+                let mut bits = vec![];
+                for j in 0..2 {
+                    bits.push(Boolean::Is(AllocatedBit::alloc(
+                        cs.namespace(|| format!("input {} bit {}", i, j)),
+                        || Ok((input >> j) & 1 == 1),
+                    )?));
+                }
+                inputs.push(bits);
+            }
+
+            let ic = [
+                G1_GENERATOR,
+                mul_g1(&G1_GENERATOR, 2),
+                mul_g1(&G1_GENERATOR, 5),
+            ];
+            let mut field = InCircuit::new(cs.namespace(|| "ops"));
+            let point = input_point(&mut field, &ic, &inputs)?;
+            let expected = self.expected.lift(&mut field);
+            field.enforce_equal(&point.x, &expected.x)?;
+            field.enforce_equal(&point.y, &expected.y)
+        }
+    }
+
+    #[test]
+    fn test_input_point() {
+        // G + 3 (2 G) + 2 (5 G)
+        let circuit = InputCircuit {
+            inputs: vec![3, 2],
+            expected: mul_g1(&G1_GENERATOR, 17),
+        };
+        assert_eq!(is_satisfied::<_, _, Basic>(&circuit, &[]), Ok(true));
+        // Only the constant base
+        let circuit = InputCircuit {
+            inputs: vec![0, 0],
+            expected: G1_GENERATOR,
+        };
+        assert_eq!(is_satisfied::<_, _, Basic>(&circuit, &[]), Ok(true));
+        let circuit = InputCircuit {
+            inputs: vec![1, 2],
+            expected: mul_g1(&G1_GENERATOR, 17),
+        };
+        assert_ne!(is_satisfied::<_, _, Basic>(&circuit, &[]), Ok(true));
+    }
+
+    #[test]
+    fn test_constraint_totals() {
+        let counts = constraint_totals::<Fp, _, Basic>(&PointCircuit {
+            g1: G1_GENERATOR,
+            g2: G2_GENERATOR,
+        })
+        .unwrap();
+        let root = &crate::dev::constraint_count::<Fp, _, Basic>(&PointCircuit {
+            g1: G1_GENERATOR,
+            g2: G2_GENERATOR,
+        })
+        .unwrap()[""]
+            .0;
+        assert_eq!(
+            (counts.total_mults, counts.total_lcs),
+            (root.total_mults, root.total_lcs)
+        );
+
+        // k = 5 holds up to 7 gates.
+        let counts = |total_mults| ConstraintCounts {
+            total_mults,
+            ..ConstraintCounts::default()
+        };
+        assert_eq!(counts(4).min_k(), 5);
+        assert_eq!(counts(7).min_k(), 5);
+        assert_eq!(counts(8).min_k(), 6);
+    }
+
+    #[test]
+    #[ignore] // Synthesizes hundreds of millions of gates; run in release
+    fn test_costs() {
+        assert_eq!(
+            pairing_check_cost::<Fp>(2).unwrap().total_mults,
+            187_117_133
+        );
+        assert_eq!(verify_bls_cost::<Fp>().unwrap().min_k(), 30);
+        assert_eq!(verify_groth16_cost::<Fp>(1, 255).unwrap().min_k(), 30);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::*;
 
+/// A variable constrained to be 0 or 1, together with its value when it is
+/// known.
 #[derive(Clone, Debug)]
 pub struct AllocatedBit {
     value: Option<bool>,
@@ -7,6 +9,7 @@ pub struct AllocatedBit {
 }
 
 impl AllocatedBit {
+    /// The constant 1, as the constraint system's `ONE` variable.
     pub fn one<F, CS>(_cs: CS) -> Self
     where
         F: Field,
@@ -18,6 +21,13 @@ impl AllocatedBit {
         }
     }
 
+    /// Wraps a variable that the caller has already constrained to be a
+    /// bit, such as the output of another boolean gadget.
+    pub fn from_raw_unchecked(value: Option<bool>, var: Variable) -> Self {
+        AllocatedBit { value, var }
+    }
+
+    /// The value of this bit, or `None` if there is no witness.
     pub fn get_value(&self) -> Option<bool> {
         self.value
     }
@@ -26,8 +36,27 @@ impl AllocatedBit {
         self.var
     }
 
+    /// This bit as a linear combination.
+    pub fn lc<F: Field>(&self) -> LinearCombination<F> {
+        LinearCombination::from(self.var)
+    }
+
+    /// Allocates a public input and constrains it to be a bit.
+    pub fn alloc_input<F: Field, CS: ConstraintSystem<F>, FF>(
+        mut cs: CS,
+        value: FF,
+    ) -> Result<Self, SynthesisError>
+    where
+        FF: FnOnce() -> Result<bool, SynthesisError>,
+    {
+        let bit = Self::alloc_input_unchecked(cs.namespace(|| "alloc"), value)?;
+        bit.check(cs.namespace(|| "check"))?;
+
+        Ok(bit)
+    }
+
     /// Allocates this bit but does not check that it's a bit, call
-    /// `checked()` to do this later. This is a hack to ensure that
+    /// `check()` to do this later. This is a hack to ensure that
     /// the first linear constraints in our proof verification circuits
     /// are always input constraints.
     pub fn alloc_input_unchecked<F: Field, CS: ConstraintSystem<F>, FF>(
@@ -53,6 +82,29 @@ impl AllocatedBit {
         })
     }
 
+    /// Allocates a public input constrained to equal this bit, exposing it
+    /// as an output of the circuit.
+    pub fn inputize<F: Field, CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+    ) -> Result<Self, SynthesisError> {
+        let value = self.value;
+        let var = cs.alloc_input(
+            || "input bit",
+            || {
+                value
+                    .map(|v| v.into())
+                    .ok_or(SynthesisError::AssignmentMissing)
+            },
+        )?;
+
+        cs.enforce_zero(LinearCombination::from(self.var) - var);
+
+        Ok(AllocatedBit { value, var })
+    }
+
+    /// Constrains this bit to be 0 or 1, for bits allocated with
+    /// `alloc_input_unchecked`.
     pub fn check<F: Field, CS: ConstraintSystem<F>>(
         &self,
         mut cs: CS,
@@ -74,6 +126,7 @@ impl AllocatedBit {
         Ok(())
     }
 
+    /// Allocates a private bit, using one multiplication gate.
     pub fn alloc<F: Field, CS: ConstraintSystem<F>, FF>(
         mut cs: CS,
         value: FF,
@@ -278,25 +331,113 @@ impl AllocatedBit {
     }
 }
 
+/// Public input bits allocated with `AllocatedBit::alloc_input_unchecked`,
+/// whose boolean constraints are all added by `finish`. This keeps the first
+/// linear constraints of a circuit the input constraints, without risking a
+/// bit that is never checked.
+#[derive(Debug, Default)]
+pub struct InputBits {
+    groups: Vec<(String, Vec<AllocatedBit>)>,
+}
+
+impl InputBits {
+    pub fn new() -> Self {
+        InputBits::default()
+    }
+
+    /// Allocates a group of public input bits with the given values, in
+    /// namespace `name`.
+    pub fn alloc<F, CS, I>(
+        &mut self,
+        mut cs: CS,
+        name: &str,
+        values: I,
+    ) -> Result<Vec<AllocatedBit>, SynthesisError>
+    where
+        F: Field,
+        CS: ConstraintSystem<F>,
+        I: IntoIterator<Item = bool>,
+    {
+        let mut bits = vec![];
+        {
+            let mut cs = cs.namespace(|| name);
+            for (i, value) in values.into_iter().enumerate() {
+                bits.push(AllocatedBit::alloc_input_unchecked(
+                    cs.namespace(|| format!("bit {}", i)),
+                    || Ok(value),
+                )?);
+            }
+        }
+        self.groups.push((name.to_string(), bits.clone()));
+
+        Ok(bits)
+    }
+
+    /// Like `alloc`, taking each byte as eight bits, least significant first.
+    ///
+    /// This is the bulk allocation of the payload, leftovers and deferred
+    /// blobs. The per-bit names are only formatted by the backends of `dev`,
+    /// and each witness value is a shift of a byte, so neither is worth
+    /// batching or computing in parallel; the cost of a public input bit is
+    /// its gate, its linear constraint and its power of `y` in `k(Y)`.
+    pub fn alloc_bytes<F, CS>(
+        &mut self,
+        cs: CS,
+        name: &str,
+        bytes: &[u8],
+    ) -> Result<Vec<AllocatedBit>, SynthesisError>
+    where
+        F: Field,
+        CS: ConstraintSystem<F>,
+    {
+        let values = bytes
+            .iter()
+            .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1));
+        self.alloc(cs, name, values)
+    }
+
+    /// Constrains every allocated bit to be boolean, group by group. Call it
+    /// once all public inputs have been allocated.
+    pub fn finish<F: Field, CS: ConstraintSystem<F>>(
+        self,
+        mut cs: CS,
+    ) -> Result<(), SynthesisError> {
+        for (name, bits) in self.groups {
+            let mut cs = cs.namespace(|| format!("constrain {}", name));
+            for (i, bit) in bits.iter().enumerate() {
+                bit.check(cs.namespace(|| format!("bit {}", i)))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 pub fn unpack_fe<F: Field, CS: ConstraintSystem<F>>(
+    cs: CS,
+    num: &Num<F>,
+) -> Result<Vec<AllocatedBit>, SynthesisError> {
+    unpack_low_bits(cs, num, 256)
+}
+
+/// Unpacks `num` into 128 little-endian bits, which constrains its high bits
+/// to be zero. Scalars such as `y_new` and the packed challenges occupy 128
+/// bits in the leftovers and deferred layouts, and are checked with this.
+pub fn constrain_to_128_bits<F: Field, CS: ConstraintSystem<F>>(
+    cs: CS,
+    num: &Num<F>,
+) -> Result<Vec<AllocatedBit>, SynthesisError> {
+    unpack_low_bits(cs, num, 128)
+}
+
+fn unpack_low_bits<F: Field, CS: ConstraintSystem<F>>(
     mut cs: CS,
     num: &Num<F>,
+    len: usize,
 ) -> Result<Vec<AllocatedBit>, SynthesisError> {
     let values = match num.value() {
-        Some(value) => {
-            let mut tmp = Vec::with_capacity(256);
-            let bytes = value.to_bytes();
-
-            for byte in &bytes[0..] {
-                for i in 0..8 {
-                    let bit = ((*byte >> i) & 1) == 1;
-                    tmp.push(Some(bit));
-                }
-            }
-
-            tmp
-        }
-        None => vec![None; 256],
+        Some(value) => value.to_le_bits().into_iter().take(len).map(Some).collect(),
+        None => vec![None; len],
     };
 
     let mut bools = vec![];
@@ -738,12 +879,13 @@ impl From<AllocatedBit> for Boolean {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod test {
-    use super::{AllocatedBit, Boolean};
+    use super::{constrain_to_128_bits, AllocatedBit, Boolean, InputBits};
     use crate::{
-        dev::{is_satisfied, SatisfactionError},
-        fields::Fp,
+        dev::{is_satisfied, GadgetCheck, SatisfactionError},
+        fields::{Field, Fp},
+        gadgets::num::{AllocatedNum, Num},
         Basic, Circuit, ConstraintSystem, SynthesisError,
     };
 
@@ -769,6 +911,96 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_input_bit() {
+        struct InputBitCircuit {
+            value: bool,
+        }
+
+        impl Circuit<Fp> for InputBitCircuit {
+            fn synthesize<CS: ConstraintSystem<Fp>>(
+                &self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let bit = AllocatedBit::alloc_input(cs.namespace(|| "bit"), || Ok(self.value))?;
+                assert_eq!(bit.get_value(), Some(self.value));
+
+                Ok(())
+            }
+        }
+
+        // A public input that is checked after the fact.
+        struct CheckedNumCircuit {
+            value: Fp,
+        }
+
+        impl Circuit<Fp> for CheckedNumCircuit {
+            fn synthesize<CS: ConstraintSystem<Fp>>(
+                &self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let num = AllocatedNum::alloc_input(cs.namespace(|| "num"), || Ok(self.value))?;
+                let bit = AllocatedBit::from_raw_unchecked(
+                    Some(self.value == Fp::one()),
+                    num.get_variable(),
+                );
+                bit.check(cs.namespace(|| "check"))?;
+
+                Ok(())
+            }
+        }
+
+        for value in [false, true].iter() {
+            let input = if *value { Fp::one() } else { Fp::zero() };
+            assert_eq!(
+                is_satisfied::<_, _, Basic>(&InputBitCircuit { value: *value }, &[input]),
+                Ok(true)
+            );
+        }
+
+        GadgetCheck::new(CheckedNumCircuit { value: Fp::one() }, &[Fp::one()])
+            .malformed(
+                "non-boolean input",
+                CheckedNumCircuit { value: Fp::from(2) },
+                &[Fp::from(2)],
+            )
+            .check::<Basic>()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_input_bits() {
+        struct InputBitsCircuit;
+
+        impl Circuit<Fp> for InputBitsCircuit {
+            fn synthesize<CS: ConstraintSystem<Fp>>(
+                &self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let mut inputs = InputBits::new();
+                let a = inputs.alloc(&mut *cs, "a", vec![true, false])?;
+                let b = inputs.alloc_bytes(&mut *cs, "b", &[0x81])?;
+                inputs.finish(&mut *cs)?;
+
+                assert_eq!(a.len(), 2);
+                assert_eq!(b.len(), 8);
+                assert_eq!(b[0].get_value(), Some(true));
+                assert_eq!(b[1].get_value(), Some(false));
+                assert_eq!(b[7].get_value(), Some(true));
+
+                Ok(())
+            }
+        }
+
+        let mut inputs = vec![Fp::one(), Fp::zero(), Fp::one()];
+        inputs.extend(vec![Fp::zero(); 6]);
+        inputs.push(Fp::one());
+        GadgetCheck::new(InputBitsCircuit, &inputs)
+            .budget(16, 41)
+            .check::<Basic>()
+            .unwrap();
+    }
+
     #[test]
     fn test_xor() {
         struct TestCircuit {
@@ -1774,4 +2006,95 @@ mod test {
             }
         }
     }
+
+    struct GateCircuit {
+        a: bool,
+        b: bool,
+        xor: bool,
+        and: bool,
+        input: Fp,
+    }
+
+    impl Circuit<Fp> for GateCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let var = cs.alloc_input(|| "input", || Ok(self.input))?;
+            AllocatedBit {
+                value: Some(self.input == Fp::one()),
+                var,
+            }
+            .check(cs.namespace(|| "check input"))?;
+
+            let a = AllocatedBit::alloc(cs.namespace(|| "a"), || Ok(self.a))?;
+            let b = AllocatedBit::alloc(cs.namespace(|| "b"), || Ok(self.b))?;
+            let xor = AllocatedBit::xor(cs.namespace(|| "a xor b"), &a, &b)?;
+            let and = AllocatedBit::and(cs.namespace(|| "a and b"), &a, &b)?;
+
+            Boolean::enforce_equal(
+                cs.namespace(|| "check xor"),
+                &Boolean::from(xor),
+                &Boolean::constant(self.xor),
+            )?;
+            Boolean::enforce_equal(
+                cs.namespace(|| "check and"),
+                &Boolean::from(and),
+                &Boolean::constant(self.and),
+            )
+        }
+    }
+
+    #[test]
+    fn test_gadget_check() {
+        let circuit = |xor, and, input| GateCircuit {
+            a: true,
+            b: false,
+            xor,
+            and,
+            input,
+        };
+
+        GadgetCheck::new(circuit(true, false, Fp::one()), &[Fp::one()])
+            .malformed("wrong xor", circuit(false, false, Fp::one()), &[Fp::one()])
+            .malformed("wrong and", circuit(true, true, Fp::one()), &[Fp::one()])
+            .malformed(
+                "non-boolean input",
+                circuit(true, false, Fp::from(2)),
+                &[Fp::from(2)],
+            )
+            .budget(7, 16)
+            .check::<Basic>()
+            .unwrap();
+    }
+
+    struct PackedCircuit {
+        value: Fp,
+    }
+
+    impl Circuit<Fp> for PackedCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let num = AllocatedNum::alloc_input(cs.namespace(|| "value"), || Ok(self.value))?;
+            let bits = constrain_to_128_bits(cs.namespace(|| "unpack"), &Num::from(num))?;
+            assert_eq!(bits.len(), 128);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_constrain_to_128_bits() {
+        let max = Fp::from_u128(u128::MAX);
+        let overflow = max + Fp::one();
+        GadgetCheck::new(PackedCircuit { value: max }, &[max])
+            .malformed(
+                "value of 129 bits",
+                PackedCircuit { value: overflow },
+                &[overflow],
+            )
+            .malformed(
+                "negative value",
+                PackedCircuit { value: -Fp::one() },
+                &[-Fp::one()],
+            )
+            .check::<Basic>()
+            .unwrap();
+    }
 }
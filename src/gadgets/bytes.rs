@@ -0,0 +1,108 @@
+//! Helpers shared by the decoding gadgets. Witnessed bytes are given as
+//! bits, eight per byte and least significant bit first, which is the order
+//! the recursion layer allocates payload bits in.
+
+use super::{AllocatedBit, AllocatedNum, Boolean, Combination, Num};
+use crate::circuits::{Coeff, ConstraintSystem, SynthesisError};
+use crate::fields::Field;
+
+/// The bits of byte `i` of `bytes`, which is taken to be followed by zeroes.
+pub(crate) fn byte(bytes: &[Boolean], i: usize) -> Vec<Boolean> {
+    assert_eq!(bytes.len() % 8, 0);
+    (0..8)
+        .map(|j| {
+            bytes
+                .get(i * 8 + j)
+                .cloned()
+                .unwrap_or(Boolean::Constant(false))
+        })
+        .collect()
+}
+
+pub(crate) fn boolean<F: Field>(b: &Boolean) -> Combination<F> {
+    match b {
+        Boolean::Constant(c) => Combination::from(Num::constant(F::from(*c))),
+        Boolean::Is(bit) => Combination::from(AllocatedNum::from(bit.clone())),
+        Boolean::Not(bit) => {
+            Combination::from(Num::constant(F::one()))
+                + (Coeff::NegativeOne, AllocatedNum::from(bit.clone()))
+        }
+    }
+}
+
+/// The little-endian value of `bits`.
+pub(crate) fn pack<F: Field>(bits: &[Boolean]) -> Combination<F> {
+    assert!(bits.len() < F::CAPACITY as usize);
+
+    let mut acc = Combination::zero();
+    let mut coeff = F::one();
+    for bit in bits {
+        acc = acc + boolean(bit).scale(coeff);
+        coeff = coeff + coeff;
+    }
+    acc
+}
+
+/// `value` if `condition` holds, and zero otherwise.
+pub(crate) fn select<F: Field, CS: ConstraintSystem<F>>(
+    cs: CS,
+    condition: &Boolean,
+    value: &Combination<F>,
+) -> Result<Combination<F>, SynthesisError> {
+    match condition {
+        Boolean::Constant(true) => Ok(value.clone()),
+        Boolean::Constant(false) => Ok(Combination::zero()),
+        _ => Ok(boolean(condition).mul(cs, value)?.into()),
+    }
+}
+
+pub(crate) fn enforce_false<F: Field, CS: ConstraintSystem<F>>(
+    cs: CS,
+    b: &Boolean,
+) -> Result<(), SynthesisError> {
+    Boolean::enforce_equal(cs, b, &Boolean::constant(false))
+}
+
+/// Constrains `value` to be less than `2^bits`.
+pub(crate) fn range_check<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    value: &Combination<F>,
+    bits: usize,
+) -> Result<(), SynthesisError> {
+    let value_bits = value.get_value().map(|v| v.to_le_bits());
+
+    let mut decomposition = Vec::with_capacity(bits);
+    for i in 0..bits {
+        decomposition.push(Boolean::Is(AllocatedBit::alloc(
+            cs.namespace(|| format!("bit {}", i)),
+            || {
+                value_bits
+                    .as_ref()
+                    .map(|b| b[i])
+                    .ok_or(SynthesisError::AssignmentMissing)
+            },
+        )?));
+    }
+
+    let lc = pack::<F>(&decomposition).lc(&mut cs) - &value.lc(&mut cs);
+    cs.enforce_zero(lc);
+    Ok(())
+}
+
+/// Allocates the bits of `bytes`.
+#[cfg(all(test, feature = "prover"))]
+pub(crate) fn alloc_bytes<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    bytes: &[u8],
+) -> Result<Vec<Boolean>, SynthesisError> {
+    let mut bits = vec![];
+    for (i, byte) in bytes.iter().enumerate() {
+        for j in 0..8 {
+            bits.push(Boolean::Is(AllocatedBit::alloc(
+                cs.namespace(|| format!("byte {} bit {}", i, j)),
+                || Ok((byte >> j) & 1 == 1),
+            )?));
+        }
+    }
+    Ok(bits)
+}
@@ -0,0 +1,299 @@
+use super::boolean::{unpack_fe, AllocatedBit, Boolean};
+use super::ecc::CurvePoint;
+use super::num::{AllocatedNum, Combination, Num};
+use super::rescue::RescueGadget;
+use crate::{
+    circuits::{Coeff, ConstraintSystem, LinearCombination},
+    curves::Curve,
+    fields::Field,
+    Challenge, SecurityLevel, SynthesisError,
+};
+
+/// A packed 128-bit challenge in a circuit, as the little-endian bits of its
+/// packing. This is the circuit counterpart of `Challenge`, and like it can
+/// be used in circuits over either field of the cycle.
+#[derive(Clone, Debug)]
+pub struct AllocatedChallenge {
+    bits: Vec<Boolean>,
+}
+
+impl AllocatedChallenge {
+    /// Squeezes a challenge from `transcript`, taking as many bits as
+    /// `level` allows and setting the top bit, as `Proof::verify` does.
+    pub fn squeeze<F, CS>(
+        mut cs: CS,
+        transcript: &mut RescueGadget<F>,
+        level: SecurityLevel,
+    ) -> Result<Self, SynthesisError>
+    where
+        F: Field,
+        CS: ConstraintSystem<F>,
+    {
+        let num = transcript.squeeze(cs.namespace(|| "squeeze"))?;
+        let mut bits: Vec<_> = unpack_fe(cs.namespace(|| "unpack"), &num.into())?
+            .into_iter()
+            .take(level.challenge_bits())
+            .map(Boolean::from)
+            .collect();
+        // The bits the security level leaves out of the challenge are zero.
+        // As constants they cost nothing in the gadgets the challenge is
+        // used in, which is what makes a lower level cheaper.
+        bits.resize(127, Boolean::constant(false));
+        // The top bit stays the allocated one it has always been, so that
+        // the circuit at the default level keeps its shape.
+        bits.push(AllocatedBit::one(cs).into());
+
+        Ok(AllocatedChallenge { bits })
+    }
+
+    /// Takes the 128 bits of a packed challenge that are already
+    /// constrained, such as those in the deferred values of a proof.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is not 128 bits long.
+    pub fn from_bits(bits: &[AllocatedBit]) -> Self {
+        assert_eq!(bits.len(), 128);
+
+        AllocatedChallenge {
+            bits: bits.iter().cloned().map(Boolean::from).collect(),
+        }
+    }
+
+    /// The little-endian bits of the packed challenge, of which those the
+    /// security level leaves out are constant.
+    pub fn bits(&self) -> &[Boolean] {
+        &self.bits
+    }
+
+    /// The value of the challenge, or `None` if there is no witness.
+    pub fn get_value<F: Field>(&self) -> Option<Challenge<F>> {
+        let mut packed = 0u128;
+        for (i, bit) in self.bits.iter().enumerate() {
+            if bit.get_value()? {
+                packed |= 1 << i;
+            }
+        }
+
+        Some(Challenge::from_u128(packed))
+    }
+
+    /// Multiplies `point` by the scalar the challenge stands for.
+    pub fn multiply<C: Curve, CS: ConstraintSystem<C::Base>>(
+        &self,
+        cs: CS,
+        point: &CurvePoint<C>,
+    ) -> Result<CurvePoint<C>, SynthesisError> {
+        point.multiply_endo(cs, &self.bits)
+    }
+
+    /// Multiplies `point` by the inverse of the scalar the challenge stands
+    /// for.
+    pub fn multiply_inv<C: Curve, CS: ConstraintSystem<C::Base>>(
+        &self,
+        cs: CS,
+        point: &CurvePoint<C>,
+    ) -> Result<CurvePoint<C>, SynthesisError> {
+        point.multiply_inv_endo(cs, &self.bits)
+    }
+
+    /// Allocates the scalar the challenge stands for, as computed by
+    /// `Challenge::scalar`.
+    pub fn scalar<F: Field, CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+    ) -> Result<AllocatedNum<F>, SynthesisError> {
+        let bits = &self.bits;
+        let mut acc = Combination::from(Num::constant(F::from_u64(3)));
+
+        for i in 1..64 {
+            // acc = acc + acc
+            acc = acc.scale(F::from_u128(2));
+            // tmp = 1 - 2b
+            // acc = acc + tmp
+            acc = acc + Combination::from(AllocatedNum::one(&mut cs));
+            acc = acc + combination(&bits[i * 2]).scale(-F::from_u128(2));
+            let should_endo = match &bits[i * 2 + 1] {
+                Boolean::Is(bit) => bit,
+                Boolean::Constant(endo) => {
+                    // Scaling by a constant needs no gate.
+                    if *endo {
+                        acc = acc.scale(F::BETA);
+                    }
+                    continue;
+                }
+                Boolean::Not(_) => unreachable!("challenge bits are never negated"),
+            };
+            // acc = (1 - b') acc + b' acc * beta
+            //     = acc - b' * acc + b' * acc * beta
+            //     = (b' * beta - b') * (acc) + acc
+            let mut outval = None;
+            let (a, b, c) = cs.multiply(
+                || format!("should_endo round {}", i),
+                || {
+                    let acc = acc.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+                    let should_endo = should_endo
+                        .get_value()
+                        .ok_or(SynthesisError::AssignmentMissing)?;
+                    let should_endo = if should_endo { F::one() } else { F::zero() };
+                    let beta = F::BETA;
+
+                    let lhs = should_endo * beta - should_endo;
+                    let rhs = acc;
+                    let out = lhs * rhs;
+                    outval = Some(out);
+
+                    Ok((lhs, rhs, out))
+                },
+            )?;
+            cs.enforce_zero(
+                LinearCombination::from(a) + should_endo.get_variable()
+                    - (Coeff::Full(F::BETA), should_endo.get_variable()),
+            );
+            let acclc = acc.lc(&mut cs);
+            cs.enforce_zero(LinearCombination::from(b) - &acclc);
+
+            acc = acc + Combination::from(Num::from(AllocatedNum::from_raw_unchecked(outval, c)));
+        }
+
+        let newacc = AllocatedNum::alloc(cs.namespace(|| "final acc value"), || {
+            acc.get_value().ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let acclc = acc.lc(&mut cs);
+        cs.enforce_zero(LinearCombination::from(newacc.get_variable()) - &acclc);
+
+        Ok(newacc)
+    }
+}
+
+/// A challenge bit as a combination, which is constant if the bit is.
+fn combination<F: Field>(bit: &Boolean) -> Combination<F> {
+    match bit {
+        Boolean::Is(bit) => Combination::from(AllocatedNum::from(bit.clone())),
+        Boolean::Constant(b) => {
+            Combination::from(Num::constant(if *b { F::one() } else { F::zero() }))
+        }
+        Boolean::Not(_) => unreachable!("challenge bits are never negated"),
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::AllocatedChallenge;
+    use crate::{
+        circuits::{Circuit, Coeff, ConstraintSystem, SynthesisError},
+        curves::{Curve, Ec1},
+        dev::{constraint_count, is_satisfied},
+        fields::{Field, Fp, Fq},
+        gadgets::{AllocatedBit, AllocatedNum, CurvePoint, RescueGadget},
+        rescue::Rescue,
+        Basic, Challenge, SecurityLevel,
+    };
+
+    struct ChallengeCircuit {
+        level: SecurityLevel,
+        // The packed bits to witness, and the scalar to expect from them
+        packed: u128,
+        scalar: Fp,
+    }
+
+    impl Circuit<Fp> for ChallengeCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            // A squeezed challenge agrees with the native transcript.
+            let mut native = Rescue::new();
+            native.absorb(Fp::from(7));
+            let expected = self.level.pack_challenge(native.squeeze().get_lower_128());
+
+            let seven = AllocatedNum::alloc(cs.namespace(|| "seven"), || Ok(Fp::from(7)))?;
+            let mut transcript = RescueGadget::new(cs.namespace(|| "transcript"))?;
+            transcript.absorb(cs.namespace(|| "absorb"), seven.into())?;
+            let squeezed = AllocatedChallenge::squeeze(
+                cs.namespace(|| "squeeze"),
+                &mut transcript,
+                self.level,
+            )?;
+            assert_eq!(squeezed.bits().len(), 128);
+            assert_eq!(
+                squeezed.get_value::<Fp>(),
+                Some(Challenge::from_u128(expected))
+            );
+
+            let mut bits = vec![];
+            for i in 0..128 {
+                bits.push(AllocatedBit::alloc(
+                    cs.namespace(|| format!("bit {}", i)),
+                    || Ok((self.packed >> i) & 1 == 1),
+                )?);
+            }
+            let challenge = AllocatedChallenge::from_bits(&bits);
+            let scalar = challenge.scalar(cs.namespace(|| "scalar"))?;
+            let expected =
+                AllocatedNum::alloc_input(cs.namespace(|| "expected"), || Ok(self.scalar))?;
+            cs.enforce_zero(scalar.lc() - &expected.lc());
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_allocated_challenge() {
+        let packed = (1 << 127) | 0x1234_5678_9abc_def0;
+        let scalar = Challenge::<Fq>::from_u128(packed).cast::<Fp>().scalar();
+        for level in [SecurityLevel::Bits128, SecurityLevel::Bits100] {
+            let circuit = ChallengeCircuit {
+                level,
+                packed,
+                scalar,
+            };
+            assert_eq!(is_satisfied::<_, _, Basic>(&circuit, &[scalar]), Ok(true));
+            assert_ne!(
+                is_satisfied::<_, _, Basic>(&circuit, &[scalar + Fp::one()]),
+                Ok(true)
+            );
+        }
+    }
+
+    struct MultiplyCircuit(SecurityLevel);
+
+    impl Circuit<Fp> for MultiplyCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut native = Rescue::new();
+            native.absorb(Fp::from(7));
+            let packed = self.0.pack_challenge(native.squeeze().get_lower_128());
+            let scalar = Challenge::<Fq>::from_u128(packed).scalar();
+            let (x, y) = (Ec1::one() * scalar).get_xy().unwrap();
+
+            let seven = AllocatedNum::alloc(cs.namespace(|| "seven"), || Ok(Fp::from(7)))?;
+            let mut transcript = RescueGadget::new(cs.namespace(|| "transcript"))?;
+            transcript.absorb(cs.namespace(|| "absorb"), seven.into())?;
+            let challenge =
+                AllocatedChallenge::squeeze(cs.namespace(|| "squeeze"), &mut transcript, self.0)?;
+
+            let point = CurvePoint::<Ec1>::witness(cs.namespace(|| "point"), || Ok(Ec1::one()))?;
+            let product = challenge.multiply(cs.namespace(|| "multiply"), &point)?;
+            let (product_x, product_y) = product.get_xy();
+            let product_x = product_x.lc(&mut *cs);
+            let product_y = product_y.lc(&mut *cs);
+            cs.enforce_zero(product_x - (Coeff::Full(x), CS::ONE));
+            cs.enforce_zero(product_y - (Coeff::Full(y), CS::ONE));
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_lower_level_is_cheaper() {
+        let mults = |level| {
+            let circuit = MultiplyCircuit(level);
+            assert_eq!(is_satisfied::<_, _, Basic>(&circuit, &[]), Ok(true));
+            constraint_count::<_, _, Basic>(&circuit).unwrap()[""]
+                .0
+                .total_mults
+        };
+
+        // The bits the lower level leaves out are constants, which the
+        // multiplication neither negates nor applies the endomorphism by.
+        assert!(mults(SecurityLevel::Bits100) < mults(SecurityLevel::Bits128));
+    }
+}
@@ -0,0 +1,166 @@
+use super::num::{AllocatedNum, Num};
+use super::rescue::RescueGadget;
+use crate::{circuits::ConstraintSystem, fields::Field, rescue::Rescue, SynthesisError};
+
+/// Absorbed ahead of the committed values, so that commitments can't be
+/// confused with other Rescue digests of the same elements.
+const COMMITMENT_DOMAIN: u64 = 0x6861_6c6f_636f_6d6d;
+
+/// A hiding commitment to a vector of field elements, computed as the Rescue
+/// digest of a domain separator, the number of values, the randomness, and
+/// the values themselves. The sponge is at a reduced rate, so that every one
+/// of them affects the commitment.
+pub struct RescueCommitment<F: Field> {
+    value: AllocatedNum<F>,
+}
+
+impl<F: Field> RescueCommitment<F> {
+    /// Computes the commitment outside of the circuit.
+    pub fn commit_native(values: &[F], randomness: F) -> F {
+        let mut rescue = Rescue::reduced_rate();
+        rescue.absorb(F::from_u64(COMMITMENT_DOMAIN));
+        rescue.absorb(F::from_u64(values.len() as u64));
+        rescue.absorb(randomness);
+        for value in values {
+            rescue.absorb(*value);
+        }
+        rescue.squeeze()
+    }
+
+    /// Computes the commitment to `values` under `randomness`.
+    pub fn commit<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        values: &[Num<F>],
+        randomness: Num<F>,
+    ) -> Result<Self, SynthesisError> {
+        let mut rescue = RescueGadget::reduced_rate(cs.namespace(|| "init Rescue"))?;
+        rescue.absorb(
+            cs.namespace(|| "absorb domain"),
+            Num::constant(F::from_u64(COMMITMENT_DOMAIN)),
+        )?;
+        rescue.absorb(
+            cs.namespace(|| "absorb length"),
+            Num::constant(F::from_u64(values.len() as u64)),
+        )?;
+        rescue.absorb(cs.namespace(|| "absorb randomness"), randomness)?;
+        for (i, value) in values.iter().enumerate() {
+            rescue.absorb(cs.namespace(|| format!("absorb value {}", i)), *value)?;
+        }
+
+        Ok(RescueCommitment {
+            value: rescue.squeeze(cs.namespace(|| "squeeze"))?,
+        })
+    }
+
+    /// Enforces that `commitment` opens to `values` under `randomness`.
+    pub fn open<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        commitment: &AllocatedNum<F>,
+        values: &[Num<F>],
+        randomness: Num<F>,
+    ) -> Result<(), SynthesisError> {
+        let recomputed = Self::commit(cs.namespace(|| "recompute"), values, randomness)?;
+        cs.enforce_zero(recomputed.value.lc() - &commitment.lc());
+
+        Ok(())
+    }
+
+    pub fn get_value(&self) -> Option<F> {
+        self.value.get_value()
+    }
+
+    pub fn num(&self) -> AllocatedNum<F> {
+        self.value
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::RescueCommitment;
+    use crate::{
+        circuits::{Circuit, ConstraintSystem, SynthesisError},
+        dev::GadgetCheck,
+        fields::{Field, Fp},
+        gadgets::AllocatedNum,
+        rescue::Rescue,
+        Basic,
+    };
+
+    struct TestCircuit {
+        values: Vec<Fp>,
+        randomness: Fp,
+        commitment: Fp,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut values = vec![];
+            for (i, value) in self.values.iter().enumerate() {
+                values.push(
+                    AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || Ok(*value))?
+                        .into(),
+                );
+            }
+            let randomness =
+                AllocatedNum::alloc(cs.namespace(|| "randomness"), || Ok(self.randomness))?;
+            let commitment =
+                AllocatedNum::alloc_input(cs.namespace(|| "commitment"), || Ok(self.commitment))?;
+
+            RescueCommitment::open(
+                cs.namespace(|| "open"),
+                &commitment,
+                &values,
+                randomness.into(),
+            )
+        }
+    }
+
+    #[test]
+    fn test_rescue_commitment() {
+        let values = vec![Fp::from(1), Fp::from(2), Fp::from(3)];
+        let randomness = Fp::from_u64(0xdead_beef);
+        let commitment = RescueCommitment::commit_native(&values, randomness);
+
+        // Both the randomness and the number of values affect the commitment.
+        assert!(commitment != RescueCommitment::commit_native(&values, Fp::one()));
+        assert!(commitment != RescueCommitment::commit_native(&values[..2], randomness));
+
+        // So does every value, wherever it falls in the blocks of the sponge.
+        let many: Vec<_> = (0..30).map(Fp::from_u64).collect();
+        let many_commitment = RescueCommitment::commit_native(&many, randomness);
+        for i in 0..many.len() {
+            let mut changed = many.clone();
+            changed[i] += Fp::one();
+            assert!(many_commitment != RescueCommitment::commit_native(&changed, randomness));
+        }
+
+        // And the domain separator.
+        let mut undomained = Rescue::reduced_rate();
+        undomained.absorb(Fp::from_u64(values.len() as u64));
+        undomained.absorb(randomness);
+        for value in &values {
+            undomained.absorb(*value);
+        }
+        assert!(commitment != undomained.squeeze());
+
+        let circuit = |values: &[Fp], randomness| TestCircuit {
+            values: values.to_vec(),
+            randomness,
+            commitment,
+        };
+        GadgetCheck::new(circuit(&values, randomness), &[commitment])
+            .malformed(
+                "wrong randomness",
+                circuit(&values, Fp::one()),
+                &[commitment],
+            )
+            .malformed(
+                "wrong value",
+                circuit(&[Fp::from(1), Fp::from(2), Fp::from(4)], randomness),
+                &[commitment],
+            )
+            .budget(772, 1541)
+            .check::<Basic>()
+            .unwrap();
+    }
+}
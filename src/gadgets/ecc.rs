@@ -133,8 +133,9 @@ impl<C: Curve> CurvePoint<C> {
         })
     }
 
-    /// Returns Some(None) if this is the identity, and Some(point) otherwise.
-    fn get_point(&self) -> Option<CtOption<C>> {
+    /// The value of this point, as a `CtOption` that is none if this is the
+    /// identity, or `None` if there is no witness.
+    pub fn get_point(&self) -> Option<CtOption<C>> {
         match (self.x.value(), self.y.value(), self.is_identity.get_value()) {
             (Some(x), Some(y), Some(is_identity)) => Some(if is_identity {
                 CtOption::new(C::zero(), 0.into())
@@ -153,11 +154,31 @@ impl<C: Curve> CurvePoint<C> {
         (self.x, self.y)
     }
 
+    /// Whether this point is the identity.
+    pub fn is_identity(&self) -> &Boolean {
+        &self.is_identity
+    }
+
+    /// Returns the endomorphism of P, (BETA * x, y), if condition is true,
+    /// else returns P.
     pub fn conditional_endo<CS: ConstraintSystem<C::Base>>(
         &self,
         mut cs: CS,
         condition: &Boolean,
     ) -> Result<Self, SynthesisError> {
+        // The endomorphism scales x by a constant, which needs no gate.
+        if let Boolean::Constant(endo) = condition {
+            return Ok(if *endo {
+                CurvePoint {
+                    x: self.x.scale(C::Base::BETA),
+                    y: self.y,
+                    is_identity: self.is_identity.clone(),
+                }
+            } else {
+                self.clone()
+            });
+        }
+
         let x_ret_val = self
             .x
             .value()
@@ -201,6 +222,18 @@ impl<C: Curve> CurvePoint<C> {
         mut cs: CS,
         condition: &Boolean,
     ) -> Result<Self, SynthesisError> {
+        if let Boolean::Constant(negate) = condition {
+            return Ok(if *negate {
+                CurvePoint {
+                    x: self.x,
+                    y: -self.y,
+                    is_identity: self.is_identity.clone(),
+                }
+            } else {
+                self.clone()
+            });
+        }
+
         let y_ret_val = self
             .y
             .value()
@@ -1500,114 +1533,177 @@ impl<C: Curve> CurvePoint<C> {
             let dbl = ret.double(cs.namespace(|| "double"))?;
             let sum = dbl.add(cs.namespace(|| "add"), &self)?;
 
-            let bit_val = bit.get_value();
-
-            let x_out = AllocatedNum::alloc(cs.namespace(|| "x_out"), || {
-                bit_val
-                    .and_then(|b| if b { sum.x.value() } else { dbl.x.value() })
-                    .ok_or(SynthesisError::AssignmentMissing)
-            })?;
-            let y_out = AllocatedNum::alloc(cs.namespace(|| "y_out"), || {
-                bit_val
-                    .and_then(|b| if b { sum.y.value() } else { dbl.y.value() })
-                    .ok_or(SynthesisError::AssignmentMissing)
-            })?;
-            let is_identity_out = AllocatedBit::alloc(cs.namespace(|| "out_is_identity"), || {
-                bit_val
-                    .and_then(|b| {
-                        if b {
-                            sum.is_identity.get_value()
-                        } else {
-                            dbl.is_identity.get_value()
-                        }
+            ret = dbl.select(&mut cs, bit, &sum)?;
+        }
+
+        Ok(ret)
+    }
+
+    /// Multiply by a little-endian scalar, `window` bits at a time.
+    ///
+    /// This tabulates [0] P to [2^window - 1] P, and then for each window
+    /// doubles `window` times, picks the table entry with `2^window - 1`
+    /// selections and adds it. Compared to `multiply`, which adds once per
+    /// bit, this trades the table and the selections for fewer additions.
+    pub fn multiply_windowed<CS: ConstraintSystem<C::Base>>(
+        &self,
+        mut cs: CS,
+        other: &[AllocatedBit],
+        window: usize,
+    ) -> Result<Self, SynthesisError> {
+        assert!((2..=4).contains(&window), "windows are 2 to 4 bits");
+
+        let mut table = vec![CurvePoint::identity(), self.clone()];
+        for i in 2..(1 << window) {
+            let entry = table[i - 1].add(cs.namespace(|| format!("table {}", i)), self)?;
+            table.push(entry);
+        }
+
+        let mut ret = CurvePoint::identity();
+        for (i, bits) in other.chunks(window).enumerate().rev() {
+            let mut cs = cs.namespace(|| format!("window {}", i));
+
+            for j in 0..bits.len() {
+                ret = ret.double(cs.namespace(|| format!("double {}", j)))?;
+            }
+
+            // Each bit halves the candidates, pairing those that differ
+            // only in it.
+            let mut candidates = table[..(1 << bits.len())].to_vec();
+            for (j, bit) in bits.iter().enumerate() {
+                candidates = candidates
+                    .chunks(2)
+                    .enumerate()
+                    .map(|(k, pair)| {
+                        pair[0].select(
+                            cs.namespace(|| format!("select {} {}", j, k)),
+                            bit,
+                            &pair[1],
+                        )
                     })
-                    .ok_or(SynthesisError::AssignmentMissing)
-            })?;
-
-            let (a_var, b_var, c_var) = cs.multiply(
-                || "bit * (x_sum - x_dbl) = (x_out - x_dbl)",
-                || {
-                    let bit = bit_val.ok_or(SynthesisError::AssignmentMissing)?;
-                    let x_dbl = dbl.x.value().ok_or(SynthesisError::AssignmentMissing)?;
-                    let x_sum = sum.x.value().ok_or(SynthesisError::AssignmentMissing)?;
-                    let x_out = x_out.get_value().ok_or(SynthesisError::AssignmentMissing)?;
-
-                    Ok((bit.into(), x_sum - &x_dbl, x_out - &x_dbl))
-                },
-            )?;
-            let x_dbl_lc = dbl.x.lc(&mut cs);
-            let x_sum_lc = sum.x.lc(&mut cs);
-            cs.enforce_zero(LinearCombination::from(bit.get_variable()) - a_var);
-            cs.enforce_zero(x_sum_lc - &x_dbl_lc - b_var);
-            cs.enforce_zero(x_out.lc() - &x_dbl_lc - c_var);
-
-            let (d_var, e_var, f_var) = cs.multiply(
-                || "bit * (y_sum - y_dbl) = (y_out - y_dbl)",
-                || {
-                    let bit = bit_val.ok_or(SynthesisError::AssignmentMissing)?;
-                    let y_dbl = dbl.y.value().ok_or(SynthesisError::AssignmentMissing)?;
-                    let y_sum = sum.y.value().ok_or(SynthesisError::AssignmentMissing)?;
-                    let y_out = y_out.get_value().ok_or(SynthesisError::AssignmentMissing)?;
-
-                    Ok((bit.into(), y_sum - &y_dbl, y_out - &y_dbl))
-                },
-            )?;
-            let y_dbl_lc = dbl.y.lc(&mut cs);
-            let y_sum_lc = sum.y.lc(&mut cs);
-            cs.enforce_zero(LinearCombination::from(bit.get_variable()) - d_var);
-            cs.enforce_zero(y_sum_lc - &y_dbl_lc - e_var);
-            cs.enforce_zero(y_out.lc() - &y_dbl_lc - f_var);
-
-            let (g_var, h_var, i_var) = cs.multiply(
-                || "bit * (is_identity_sum - is_identity_dbl) = (is_identity_out - is_identity_dbl)",
-                || {
-                    let bit = bit_val.ok_or(SynthesisError::AssignmentMissing)?;
-                    let is_identity_dbl = dbl
-                        .is_identity
-                        .get_value()
-                        .ok_or(SynthesisError::AssignmentMissing)?;
-                    let is_identity_sum = sum
-                        .is_identity
-                        .get_value()
-                        .ok_or(SynthesisError::AssignmentMissing)?;
-                    let is_identity_out = is_identity_out
-                        .get_value()
-                        .ok_or(SynthesisError::AssignmentMissing)?;
-
-                    let is_identity_dbl: C::Base = is_identity_dbl.into();
-                    let is_identity_sum: C::Base = is_identity_sum.into();
-                    let is_identity_out: C::Base = is_identity_out.into();
-
-                    Ok((
-                        bit.into(),
-                        is_identity_sum - &is_identity_dbl,
-                        is_identity_out - &is_identity_dbl,
-                    ))
-                },
-            )?;
-            let is_identity_dbl_lc = dbl.is_identity.lc(CS::ONE, Coeff::One);
-            cs.enforce_zero(LinearCombination::from(bit.get_variable()) - g_var);
-            cs.enforce_zero(sum.is_identity.lc(CS::ONE, Coeff::One) - &is_identity_dbl_lc - h_var);
-            cs.enforce_zero(
-                LinearCombination::from(is_identity_out.get_variable())
-                    - &is_identity_dbl_lc
-                    - i_var,
-            );
+                    .collect::<Result<_, _>>()?;
+            }
 
-            ret = CurvePoint {
-                x: x_out.into(),
-                y: y_out.into(),
-                is_identity: is_identity_out.into(),
-            };
+            ret = ret.add(cs.namespace(|| "add"), &candidates[0])?;
         }
 
         Ok(ret)
     }
 
+    /// Returns `other` if `bit` is set, else returns `self`.
+    fn select<CS: ConstraintSystem<C::Base>>(
+        &self,
+        mut cs: CS,
+        bit: &AllocatedBit,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let bit_val = bit.get_value();
+
+        let x_out = AllocatedNum::alloc(cs.namespace(|| "x_out"), || {
+            bit_val
+                .and_then(|b| if b { other.x.value() } else { self.x.value() })
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let y_out = AllocatedNum::alloc(cs.namespace(|| "y_out"), || {
+            bit_val
+                .and_then(|b| if b { other.y.value() } else { self.y.value() })
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let is_identity_out = AllocatedBit::alloc(cs.namespace(|| "out_is_identity"), || {
+            bit_val
+                .and_then(|b| {
+                    if b {
+                        other.is_identity.get_value()
+                    } else {
+                        self.is_identity.get_value()
+                    }
+                })
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let (a_var, b_var, c_var) = cs.multiply(
+            || "bit * (x_other - x_self) = (x_out - x_self)",
+            || {
+                let bit = bit_val.ok_or(SynthesisError::AssignmentMissing)?;
+                let x_self = self.x.value().ok_or(SynthesisError::AssignmentMissing)?;
+                let x_other = other.x.value().ok_or(SynthesisError::AssignmentMissing)?;
+                let x_out = x_out.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+
+                Ok((bit.into(), x_other - x_self, x_out - x_self))
+            },
+        )?;
+        let x_self_lc = self.x.lc(&mut cs);
+        let x_other_lc = other.x.lc(&mut cs);
+        cs.enforce_zero(LinearCombination::from(bit.get_variable()) - a_var);
+        cs.enforce_zero(x_other_lc - &x_self_lc - b_var);
+        cs.enforce_zero(x_out.lc() - &x_self_lc - c_var);
+
+        let (d_var, e_var, f_var) = cs.multiply(
+            || "bit * (y_other - y_self) = (y_out - y_self)",
+            || {
+                let bit = bit_val.ok_or(SynthesisError::AssignmentMissing)?;
+                let y_self = self.y.value().ok_or(SynthesisError::AssignmentMissing)?;
+                let y_other = other.y.value().ok_or(SynthesisError::AssignmentMissing)?;
+                let y_out = y_out.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+
+                Ok((bit.into(), y_other - y_self, y_out - y_self))
+            },
+        )?;
+        let y_self_lc = self.y.lc(&mut cs);
+        let y_other_lc = other.y.lc(&mut cs);
+        cs.enforce_zero(LinearCombination::from(bit.get_variable()) - d_var);
+        cs.enforce_zero(y_other_lc - &y_self_lc - e_var);
+        cs.enforce_zero(y_out.lc() - &y_self_lc - f_var);
+
+        let (g_var, h_var, i_var) = cs.multiply(
+            || "bit * (is_identity_other - is_identity_self) = (is_identity_out - is_identity_self)",
+            || {
+                let bit = bit_val.ok_or(SynthesisError::AssignmentMissing)?;
+                let is_identity_self = self
+                    .is_identity
+                    .get_value()
+                    .ok_or(SynthesisError::AssignmentMissing)?;
+                let is_identity_other = other
+                    .is_identity
+                    .get_value()
+                    .ok_or(SynthesisError::AssignmentMissing)?;
+                let is_identity_out = is_identity_out
+                    .get_value()
+                    .ok_or(SynthesisError::AssignmentMissing)?;
+
+                let is_identity_self: C::Base = is_identity_self.into();
+                let is_identity_other: C::Base = is_identity_other.into();
+                let is_identity_out: C::Base = is_identity_out.into();
+
+                Ok((
+                    bit.into(),
+                    is_identity_other - is_identity_self,
+                    is_identity_out - is_identity_self,
+                ))
+            },
+        )?;
+        let is_identity_self_lc = self.is_identity.lc(CS::ONE, Coeff::One);
+        cs.enforce_zero(LinearCombination::from(bit.get_variable()) - g_var);
+        cs.enforce_zero(other.is_identity.lc(CS::ONE, Coeff::One) - &is_identity_self_lc - h_var);
+        cs.enforce_zero(
+            LinearCombination::from(is_identity_out.get_variable()) - &is_identity_self_lc - i_var,
+        );
+
+        Ok(CurvePoint {
+            x: x_out.into(),
+            y: y_out.into(),
+            is_identity: is_identity_out.into(),
+        })
+    }
+
+    /// Multiplies this point by the challenge scalar that `other`, the bits
+    /// of a packed challenge, stand for, as computed by
+    /// `get_challenge_scalar`. Constant bits cost no gates to negate or
+    /// apply the endomorphism by.
     pub fn multiply_endo<CS: ConstraintSystem<C::Base>>(
         &self,
         mut cs: CS,
-        other: &[AllocatedBit],
+        other: &[Boolean],
     ) -> Result<Self, SynthesisError> {
         // let p = self.get_point();
 
@@ -1641,13 +1737,13 @@ impl<C: Curve> CurvePoint<C> {
 
             let base = self.conditional_neg(
                 cs.namespace(|| format!("conditional negation {}", i)),
-                &Boolean::from(should_negate.clone())
+                should_negate,
             )?;
 
             acc = acc.double_and_add_incomplete(cs.namespace(|| format!("double and add {}", i)), &base)?;
             acc = acc.conditional_endo(
                 cs.namespace(|| format!("conditional endo {}", i)),
-                &Boolean::from(should_endo.clone())
+                should_endo,
             )?;
         }
 
@@ -1720,10 +1816,12 @@ impl<C: Curve> CurvePoint<C> {
         */
     }
 
+    /// Multiplies this point by the inverse of the challenge scalar that
+    /// `other` stands for, as in `multiply_endo`.
     pub fn multiply_inv_endo<CS: ConstraintSystem<C::Base>>(
         &self,
         mut cs: CS,
-        other: &[AllocatedBit],
+        other: &[Boolean],
     ) -> Result<Self, SynthesisError> {
         let res = Self::witness(&mut cs, || {
             let p = self.get_point().ok_or(SynthesisError::AssignmentMissing)?;
@@ -2029,17 +2127,19 @@ impl<C: Curve> CurvePoint<C> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod test {
     use super::CurvePoint;
     use crate::{
         circuits::{Circuit, Coeff, ConstraintSystem, SynthesisError},
-        curves::{Curve, Ec1},
-        dev::is_satisfied,
+        curves::{reference::RefPoint, Curve, Ec1},
+        dev::{constraint_count, is_satisfied, GadgetCheck},
         fields::{Field, Fp, Fq},
         gadgets::boolean::{AllocatedBit, Boolean},
         Basic,
     };
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
 
     #[test]
     fn test_witness() {
@@ -2903,11 +3003,12 @@ mod test {
                 let p = CurvePoint::<Ec1>::witness(&mut cs, || Ok(Ec1::one()))?;
 
                 let mut scalar5 = vec![
-                    AllocatedBit::alloc(cs.namespace(|| "bit"), || Ok(true))?; 128
+                    Boolean::from(AllocatedBit::alloc(cs.namespace(|| "bit"), || Ok(true))?);
+                    128
                 ];
-                scalar5[1] = AllocatedBit::alloc(cs.namespace(|| "bit"), || Ok(false))?;
-                scalar5[2] = AllocatedBit::alloc(cs.namespace(|| "bit"), || Ok(false))?;
-                scalar5[3] = AllocatedBit::alloc(cs.namespace(|| "bit"), || Ok(false))?;
+                scalar5[1] = AllocatedBit::alloc(cs.namespace(|| "bit"), || Ok(false))?.into();
+                scalar5[2] = AllocatedBit::alloc(cs.namespace(|| "bit"), || Ok(false))?.into();
+                scalar5[3] = AllocatedBit::alloc(cs.namespace(|| "bit"), || Ok(false))?.into();
 
                 let pinv5 = p.multiply_endo(cs.namespace(|| "[5^-1] 0"), &scalar5)?;
                 let (pinv5_x, pinv5_y) = pinv5.get_xy();
@@ -2960,4 +3061,266 @@ mod test {
             Ok(true)
         );
     }
+
+    /// Multiplies a witnessed point by a scalar of `bits` bits, per bit or
+    /// with the given window.
+    struct MultiplyCircuit {
+        p: Ec1,
+        scalar: u64,
+        bits: usize,
+        window: Option<usize>,
+    }
+
+    impl Circuit<Fp> for MultiplyCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(
+            &self,
+            mut cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let p = CurvePoint::witness(cs.namespace(|| "p"), || Ok(self.p))?;
+            let mut bits = vec![];
+            for i in 0..self.bits {
+                let bit = i < 64 && (self.scalar >> i) & 1 == 1;
+                bits.push(AllocatedBit::alloc(
+                    cs.namespace(|| format!("bit {}", i)),
+                    || Ok(bit),
+                )?);
+            }
+
+            let r = match self.window {
+                Some(window) => p.multiply_windowed(cs.namespace(|| "multiply"), &bits, window)?,
+                None => p.multiply(cs.namespace(|| "multiply"), &bits)?,
+            };
+
+            let expected = self.p * Fq::from_u64(self.scalar);
+            let (r_x, r_y) = r.get_xy();
+            let xy = expected.get_xy();
+            let (x, y) = if bool::from(xy.is_some()) {
+                xy.unwrap()
+            } else {
+                (Fp::zero(), Fp::zero())
+            };
+            let r_x = r_x.lc(&mut cs);
+            let r_y = r_y.lc(&mut cs);
+            cs.enforce_zero(r_x - (Coeff::Full(x), CS::ONE));
+            cs.enforce_zero(r_y - (Coeff::Full(y), CS::ONE));
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_multiply_windowed() {
+        let p = Ec1::one().double() + Ec1::one();
+        for window in 2..=4 {
+            for (scalar, bits) in &[(0b1011_0110_1101, 12), (0b101, 7), (0, 5), (1, 1)] {
+                let circuit = MultiplyCircuit {
+                    p,
+                    scalar: *scalar,
+                    bits: *bits,
+                    window: Some(window),
+                };
+                assert_eq!(
+                    is_satisfied::<_, _, Basic>(&circuit, &[]),
+                    Ok(true),
+                    "{} bit scalar {} with window {}",
+                    bits,
+                    scalar,
+                    window
+                );
+            }
+        }
+
+        // The identity multiplies to the identity.
+        let circuit = MultiplyCircuit {
+            p: Ec1::zero(),
+            scalar: 0b1101,
+            bits: 4,
+            window: Some(2),
+        };
+        assert_eq!(is_satisfied::<_, _, Basic>(&circuit, &[]), Ok(true));
+    }
+
+    #[test]
+    fn test_multiply_windowed_costs() {
+        let mults = |bits, window| {
+            let circuit = MultiplyCircuit {
+                p: Ec1::one(),
+                scalar: 1,
+                bits,
+                window,
+            };
+            let counts = constraint_count::<_, _, Basic>(&circuit).unwrap();
+            counts["multiply"].0.total_mults
+        };
+
+        // Multiplication gates by scalar length and window, per bit first.
+        // Only 2-bit windows beat adding per bit.
+        assert_eq!(
+            [128, 256]
+                .iter()
+                .map(|bits| {
+                    (
+                        mults(*bits, None),
+                        mults(*bits, Some(2)),
+                        mults(*bits, Some(3)),
+                        mults(*bits, Some(4)),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            vec![(3584, 2850, 3086, 3950), (7168, 5666, 6080, 7662)]
+        );
+    }
+
+    struct AddCircuit {
+        p: Ec1,
+        q: Ec1,
+        sum: Ec1,
+    }
+
+    impl Circuit<Fp> for AddCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(
+            &self,
+            mut cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let p = CurvePoint::witness(cs.namespace(|| "p"), || Ok(self.p))?;
+            let q = CurvePoint::witness(cs.namespace(|| "q"), || Ok(self.q))?;
+            let r = p.add(cs.namespace(|| "p + q"), &q)?;
+
+            let (sum_x, sum_y) = self.sum.get_xy().unwrap();
+            let (r_x, r_y) = r.get_xy();
+            let r_x = r_x.lc(&mut cs);
+            let r_y = r_y.lc(&mut cs);
+            cs.enforce_zero(r_x - (Coeff::Full(sum_x), CS::ONE));
+            cs.enforce_zero(r_y - (Coeff::Full(sum_y), CS::ONE));
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_gadget_check() {
+        let one = Ec1::one();
+        let two = one.double();
+        let circuit = |q, sum| AddCircuit { p: one, q, sum };
+
+        GadgetCheck::new(circuit(two, two + one), &[])
+            .malformed("wrong sum", circuit(two, two), &[])
+            .budget(30, 58)
+            .check::<Basic>()
+            .unwrap();
+
+        // Doubling is an edge case of addition.
+        GadgetCheck::new(circuit(one, two), &[])
+            .malformed("wrong double", circuit(one, one), &[])
+            .check::<Basic>()
+            .unwrap();
+    }
+
+    /// Runs the curve gadgets over witnesses and checks them against the
+    /// reference group law.
+    struct DifferentialCircuit {
+        p: Ec1,
+        q: Ec1,
+        bits: Vec<bool>,
+    }
+
+    fn enforce_point<CS: ConstraintSystem<Fp>>(
+        mut cs: CS,
+        point: &CurvePoint<Ec1>,
+        expected: RefPoint<Fp>,
+    ) {
+        if let Some(is_identity) = point.is_identity().get_value() {
+            assert_eq!(is_identity, expected == RefPoint::Identity);
+        }
+        let (x, y) = expected.xy();
+        let (point_x, point_y) = point.get_xy();
+        let point_x = point_x.lc(&mut cs);
+        let point_y = point_y.lc(&mut cs);
+        cs.enforce_zero(point_x - (Coeff::Full(x), CS::ONE));
+        cs.enforce_zero(point_y - (Coeff::Full(y), CS::ONE));
+    }
+
+    impl Circuit<Fp> for DifferentialCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let p_ref = RefPoint::from_curve(&self.p);
+            let q_ref = RefPoint::from_curve(&self.q);
+
+            let p = CurvePoint::witness(cs.namespace(|| "p"), || Ok(self.p))?;
+            let q = CurvePoint::witness(cs.namespace(|| "q"), || Ok(self.q))?;
+            let bits = self
+                .bits
+                .iter()
+                .enumerate()
+                .map(|(i, b)| AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), || Ok(*b)))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let sum = p.add(cs.namespace(|| "p + q"), &q)?;
+            enforce_point(cs.namespace(|| "sum"), &sum, p_ref.add(q_ref));
+
+            let double = p.double(cs.namespace(|| "[2] p"))?;
+            enforce_point(cs.namespace(|| "double"), &double, p_ref.double());
+
+            let product = p.multiply(cs.namespace(|| "[bits] p"), &bits)?;
+            enforce_point(
+                cs.namespace(|| "product"),
+                &product,
+                p_ref.multiply(&self.bits),
+            );
+
+            let windowed = p.multiply_windowed(cs.namespace(|| "[bits] p windowed"), &bits, 2)?;
+            enforce_point(
+                cs.namespace(|| "windowed"),
+                &windowed,
+                p_ref.multiply(&self.bits),
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_differential() {
+        let mut rng = XorShiftRng::from_seed([
+            0xc4, 0x1d, 0x7e, 0x02, 0x96, 0x3b, 0xaf, 0x58, 0x21, 0xe0, 0x6c, 0x93, 0x4a, 0xf7,
+            0x0d, 0xb2,
+        ]);
+        let mut random_scalar = || {
+            Fq::from_u128(u128::from(rng.next_u64()) << 64 | u128::from(rng.next_u64()))
+                * Fq::from_u128(u128::from(rng.next_u64()) << 64 | u128::from(rng.next_u64()))
+        };
+
+        let p = Ec1::one() * random_scalar();
+        let q = Ec1::one() * random_scalar();
+        let cases = vec![
+            (p, q),
+            (p, p),
+            (p, -p),
+            (p, Ec1::zero()),
+            (Ec1::zero(), p),
+            (Ec1::zero(), Ec1::zero()),
+            (Ec1::one(), Ec1::one().double()),
+        ];
+        // Zero, one, the largest scalar and random ones.
+        let scalars = [
+            Fq::zero(),
+            Fq::one(),
+            -Fq::one(),
+            random_scalar(),
+            random_scalar(),
+        ];
+
+        for (i, (p, q)) in cases.into_iter().enumerate() {
+            let circuit = DifferentialCircuit {
+                p,
+                q,
+                bits: scalars[i % scalars.len()].to_le_bits(),
+            };
+            assert_eq!(
+                is_satisfied::<_, _, Basic>(&circuit, &[]),
+                Ok(true),
+                "case {}",
+                i
+            );
+        }
+    }
 }
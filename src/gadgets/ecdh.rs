@@ -0,0 +1,145 @@
+//! Diffie–Hellman key agreement over a curve whose base field is the field
+//! of the circuit, so that an inner circuit can prove that a note was
+//! encrypted to the holder of a public key.
+
+use super::num::{AllocatedNum, Num};
+use super::rescue::RescueGadget;
+use super::{AllocatedBit, CurvePoint};
+use crate::{circuits::ConstraintSystem, fields::Field, rescue::Rescue, Curve, SynthesisError};
+
+/// Absorbed ahead of the coordinates, separating point encodings from other
+/// Rescue digests. The sponge is at a reduced rate, so that it is not
+/// discarded.
+const POINT_DOMAIN: u64 = 0x6861_6c6f_5f70_7431;
+
+/// Encodes `point` as a single base field element, outside of the circuit.
+/// The identity is encoded as the point (0, 0).
+pub fn point_to_field_native<C: Curve>(point: C) -> C::Base {
+    let coords = point.get_xy();
+    let (x, y) = if coords.is_some().into() {
+        coords.unwrap()
+    } else {
+        (C::Base::zero(), C::Base::zero())
+    };
+
+    let mut rescue = Rescue::reduced_rate();
+    rescue.absorb(C::Base::from_u64(POINT_DOMAIN));
+    rescue.absorb(x);
+    rescue.absorb(y);
+    rescue.squeeze()
+}
+
+/// Encodes `point` as a single base field element.
+pub fn point_to_field<C: Curve, CS: ConstraintSystem<C::Base>>(
+    mut cs: CS,
+    point: &CurvePoint<C>,
+) -> Result<AllocatedNum<C::Base>, SynthesisError> {
+    let (x, y) = point.get_xy();
+
+    let mut rescue = RescueGadget::reduced_rate(cs.namespace(|| "init Rescue"))?;
+    rescue.absorb(
+        cs.namespace(|| "absorb domain"),
+        Num::constant(C::Base::from_u64(POINT_DOMAIN)),
+    )?;
+    rescue.absorb(cs.namespace(|| "absorb x"), x)?;
+    rescue.absorb(cs.namespace(|| "absorb y"), y)?;
+
+    rescue.squeeze(cs.namespace(|| "squeeze"))
+}
+
+/// Witnesses the little-endian bits of a secret scalar, for use with
+/// `shared_point` and `shared_secret`.
+pub fn alloc_scalar_bits<C: Curve, CS: ConstraintSystem<C::Base>>(
+    mut cs: CS,
+    scalar: Option<C::Scalar>,
+) -> Result<Vec<AllocatedBit>, SynthesisError> {
+    let bits = scalar.map(|s| s.to_le_bits());
+
+    (0..(C::Scalar::NUM_BITS as usize))
+        .map(|i| {
+            AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), || {
+                let bits = bits.as_ref().ok_or(SynthesisError::AssignmentMissing)?;
+                Ok(bits[i])
+            })
+        })
+        .collect()
+}
+
+/// The shared point `[sk] pk`, given the little-endian bits of `sk`.
+pub fn shared_point<C: Curve, CS: ConstraintSystem<C::Base>>(
+    cs: CS,
+    sk: &[AllocatedBit],
+    pk: &CurvePoint<C>,
+) -> Result<CurvePoint<C>, SynthesisError> {
+    pk.multiply(cs, sk)
+}
+
+/// The shared secret between `sk` and `pk`, outside of the circuit.
+pub fn shared_secret_native<C: Curve>(sk: C::Scalar, pk: C) -> C::Base {
+    point_to_field_native(pk * sk)
+}
+
+/// The shared secret between `sk` and `pk`: the field encoding of the shared
+/// point.
+pub fn shared_secret<C: Curve, CS: ConstraintSystem<C::Base>>(
+    mut cs: CS,
+    sk: &[AllocatedBit],
+    pk: &CurvePoint<C>,
+) -> Result<AllocatedNum<C::Base>, SynthesisError> {
+    let point = shared_point(cs.namespace(|| "shared point"), sk, pk)?;
+    point_to_field(cs.namespace(|| "encode"), &point)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::{Circuit, SynthesisError},
+        dev::GadgetCheck,
+        fields::{Fp, Fq},
+        Basic, Ec1,
+    };
+
+    struct TestCircuit {
+        sk: Fq,
+        pk: Ec1,
+        secret: Fp,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let sk = alloc_scalar_bits::<Ec1, _>(cs.namespace(|| "sk"), Some(self.sk))?;
+            let pk = CurvePoint::witness(cs.namespace(|| "pk"), || Ok(self.pk))?;
+            let expected =
+                AllocatedNum::alloc_input(cs.namespace(|| "secret"), || Ok(self.secret))?;
+
+            let secret = shared_secret(cs.namespace(|| "ecdh"), &sk, &pk)?;
+            cs.enforce_zero(secret.lc() - &expected.lc());
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_shared_secret() {
+        let alice = Fq::from_u64(0x1234_5678_9abc);
+        let bob = Fq::from_u64(0xfedc_ba98_7654);
+        let alice_pk = Ec1::one() * alice;
+        let bob_pk = Ec1::one() * bob;
+
+        let secret = shared_secret_native(alice, bob_pk);
+        assert_eq!(secret, shared_secret_native(bob, alice_pk));
+        assert!(point_to_field_native(Ec1::zero()) != point_to_field_native(Ec1::one()));
+
+        let circuit = |sk| TestCircuit {
+            sk,
+            pk: bob_pk,
+            secret,
+        };
+        GadgetCheck::new(circuit(alice), &[secret])
+            .malformed("wrong secret key", circuit(bob), &[secret])
+            .budget(8174, 16603)
+            .check::<Basic>()
+            .unwrap();
+    }
+}
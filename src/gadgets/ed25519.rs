@@ -0,0 +1,749 @@
+//! Verification of Ed25519 signatures (RFC 8032) over the twisted Edwards
+//! curve `-x^2 + y^2 = 1 + d x^2 y^2`, whose base field is foreign to both
+//! fields of the cycle.
+//!
+//! The curve's addition law is complete, so unlike secp256k1 points no
+//! case needs to be excluded. Signatures are checked with the cofactored
+//! equation `[8][S]B = [8]R + [8][k]A`, which RFC 8032 permits, and `S` must
+//! be canonical. A verification costs about twelve million gates, most of
+//! them in the two scalar multiplications.
+//!
+//! Keys, signatures and messages are given as bytes, each as eight bits
+//! least significant first.
+
+use super::bignum::{words, ForeignElement, ForeignModulus};
+use super::sha512::sha512;
+use super::Boolean;
+use crate::circuits::{ConstraintSystem, SynthesisError};
+use crate::fields::Field;
+
+/// The base field of edwards25519, of order `2^255 - 19`.
+pub struct Curve25519Base;
+
+impl ForeignModulus for Curve25519Base {
+    type Words = [u64; 4];
+    const MODULUS: [u64; 4] = [
+        0xffff_ffff_ffff_ffed,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+        0x7fff_ffff_ffff_ffff,
+    ];
+}
+
+/// The scalar field of edwards25519, of order
+/// `2^252 + 27742317777372353535851937790883648493`.
+pub struct Ed25519Scalar;
+
+impl ForeignModulus for Ed25519Scalar {
+    type Words = [u64; 4];
+    const MODULUS: [u64; 4] = [
+        0x5812_631a_5cf5_d3ed,
+        0x14de_f9de_a2f7_9cd6,
+        0x0000_0000_0000_0000,
+        0x1000_0000_0000_0000,
+    ];
+}
+
+pub type Ed25519Element<F> = ForeignElement<F, Curve25519Base>;
+pub type Ed25519ScalarElement<F> = ForeignElement<F, Ed25519Scalar>;
+
+/// Affine coordinates, as little-endian 64-bit words.
+pub type AffinePoint = ([u64; 4], [u64; 4]);
+
+/// The number of bits of a canonical scalar.
+const SCALAR_BITS: usize = 253;
+
+/// Native arithmetic, for computing witnesses.
+mod native {
+    use super::*;
+
+    fn p() -> Vec<u64> {
+        Curve25519Base::MODULUS.to_vec()
+    }
+
+    pub fn add(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+        words::add_mod(a, b, &p())
+    }
+
+    pub fn sub(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+        words::sub_mod(a, b, &p())
+    }
+
+    pub fn mul(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+        words::mul_mod(a, b, &p())
+    }
+
+    pub fn invert(a: &[u64; 4]) -> [u64; 4] {
+        words::invert_mod(a, &p())
+    }
+
+    const ONE: [u64; 4] = [1, 0, 0, 0];
+
+    /// `-121665 / 121666`
+    pub fn d() -> [u64; 4] {
+        mul(
+            &sub(&[0; 4], &[121_665, 0, 0, 0]),
+            &invert(&[121_666, 0, 0, 0]),
+        )
+    }
+
+    fn sqrt(a: &[u64; 4]) -> Option<[u64; 4]> {
+        // p = 5 (mod 8), so a^((p + 3) / 8) is a root of a or of -a, and in
+        // the latter case multiplying by a root of -1 fixes it.
+        let exponent = words::divmod(&words::add(&p(), &[3]), &[8]).0;
+        let root = words::to_array(&words::pow_mod(a, &exponent, &p()));
+        if mul(&root, &root) == *a {
+            return Some(root);
+        }
+        let exponent = words::divmod(&words::sub(&p(), &[1]), &[4]).0;
+        let sqrt_m1 = words::to_array(&words::pow_mod(&[2], &exponent, &p()));
+        let root = mul(&root, &sqrt_m1);
+        if mul(&root, &root) == *a {
+            Some(root)
+        } else {
+            None
+        }
+    }
+
+    /// The point whose `y` coordinate is the low 255 bits of `encoding`,
+    /// reduced, and whose `x` coordinate has the parity of the top bit if it
+    /// can. This is the candidate a witness is taken from; whether the
+    /// encoding is canonical is left to the constraints.
+    pub fn recover(encoding: &[u64; 4]) -> Option<AffinePoint> {
+        let mut y = *encoding;
+        y[3] &= (1 << 63) - 1;
+        let y = words::reduce(&y, &p());
+        let sign = encoding[3] >> 63;
+
+        let y2 = mul(&y, &y);
+        let u = sub(&y2, &ONE);
+        let v = add(&mul(&d(), &y2), &ONE);
+        let mut x = sqrt(&mul(&u, &invert(&v)))?;
+        if x[0] & 1 != sign {
+            x = sub(&[0; 4], &x);
+        }
+        Some((x, y))
+    }
+
+    pub fn encode(point: &AffinePoint) -> [u64; 4] {
+        let mut encoding = point.1;
+        encoding[3] |= (point.0[0] & 1) << 63;
+        encoding
+    }
+
+    pub fn add_points(p: &AffinePoint, q: &AffinePoint) -> AffinePoint {
+        let x1x2 = mul(&p.0, &q.0);
+        let y1y2 = mul(&p.1, &q.1);
+        let dt = mul(&d(), &mul(&x1x2, &y1y2));
+        let x = mul(
+            &add(&mul(&p.0, &q.1), &mul(&p.1, &q.0)),
+            &invert(&add(&ONE, &dt)),
+        );
+        let y = mul(&add(&y1y2, &x1x2), &invert(&sub(&ONE, &dt)));
+        (x, y)
+    }
+}
+
+pub use native::add_points;
+
+/// The point whose encoding is `encoding`, read as a little-endian integer,
+/// if it is a canonical encoding of a point.
+pub fn decompress(encoding: &[u64; 4]) -> Option<AffinePoint> {
+    native::recover(encoding).filter(|point| native::encode(point) == *encoding)
+}
+
+/// The standard base point, with `y = 4/5` and `x` even.
+pub fn base_point() -> AffinePoint {
+    let y = native::mul(&[4, 0, 0, 0], &native::invert(&[5, 0, 0, 0]));
+    native::recover(&y).unwrap()
+}
+
+// The integer with little-endian bits `bits`, if they are all known.
+fn bits_value(bits: &[Boolean]) -> Option<[u64; 4]> {
+    assert!(bits.len() <= 256);
+    let mut value = [0; 4];
+    for (i, bit) in bits.iter().enumerate() {
+        if bit.get_value()? {
+            value[i / 64] |= 1 << (i % 64);
+        }
+    }
+    Some(value)
+}
+
+// Bytes with bits least significant first, as bytes with bits most
+// significant first, or vice versa.
+fn reverse_bits_in_bytes(bits: &[Boolean]) -> Vec<Boolean> {
+    assert_eq!(bits.len() % 8, 0);
+    bits.chunks(8)
+        .flat_map(|byte| byte.iter().rev().cloned())
+        .collect()
+}
+
+/// A point on edwards25519.
+pub struct EdwardsPoint<F: Field> {
+    x: Ed25519Element<F>,
+    y: Ed25519Element<F>,
+}
+
+impl<F: Field> Clone for EdwardsPoint<F> {
+    fn clone(&self) -> Self {
+        EdwardsPoint {
+            x: self.x.clone(),
+            y: self.y.clone(),
+        }
+    }
+}
+
+impl<F: Field> EdwardsPoint<F> {
+    pub fn constant(point: AffinePoint) -> Self {
+        EdwardsPoint {
+            x: Ed25519Element::constant(point.0),
+            y: Ed25519Element::constant(point.1),
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self::constant(([0; 4], [1, 0, 0, 0]))
+    }
+
+    /// Allocates `point`, constraining it to be on the curve.
+    pub fn alloc<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        point: Option<AffinePoint>,
+    ) -> Result<Self, SynthesisError> {
+        let x = Ed25519Element::alloc(cs.namespace(|| "x"), point.map(|p| p.0))?;
+        let y = Ed25519Element::alloc(cs.namespace(|| "y"), point.map(|p| p.1))?;
+
+        let x2 = x.mul(cs.namespace(|| "x^2"), &x)?;
+        let y2 = y.mul(cs.namespace(|| "y^2"), &y)?;
+        let lhs = y2.sub(cs.namespace(|| "y^2 - x^2"), &x2)?;
+        let x2y2 = x2.mul(cs.namespace(|| "x^2 y^2"), &y2)?;
+        let rhs = x2y2
+            .mul(
+                cs.namespace(|| "d x^2 y^2"),
+                &Ed25519Element::constant(native::d()),
+            )?
+            .add(
+                cs.namespace(|| "1 + d x^2 y^2"),
+                &Ed25519Element::constant([1, 0, 0, 0]),
+            )?;
+        Ed25519Element::enforce_equal(cs.namespace(|| "on curve"), &lhs, &rhs)?;
+
+        Ok(EdwardsPoint { x, y })
+    }
+
+    /// Allocates the point with the 256-bit encoding `bits`, constraining
+    /// the encoding to be canonical.
+    pub fn alloc_encoded<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        bits: &[Boolean],
+    ) -> Result<Self, SynthesisError> {
+        assert_eq!(bits.len(), 256);
+
+        let point = match bits_value(bits) {
+            Some(encoding) => {
+                Some(native::recover(&encoding).ok_or(SynthesisError::Unsatisfiable)?)
+            }
+            None => None,
+        };
+        let point = Self::alloc(cs.namespace(|| "point"), point)?;
+        let encoding = point.encode(cs.namespace(|| "encode"))?;
+        for (i, (a, b)) in encoding.iter().zip(bits.iter()).enumerate() {
+            Boolean::enforce_equal(cs.namespace(|| format!("bit {}", i)), a, b)?;
+        }
+
+        Ok(point)
+    }
+
+    /// The 256 bits of the canonical encoding of this point: the bits of
+    /// `y`, with the top bit replaced by the low bit of `x`.
+    pub fn encode<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+    ) -> Result<Vec<Boolean>, SynthesisError> {
+        let mut bits = self.y.to_bits(cs.namespace(|| "y"))?;
+        let x_bits = self.x.to_bits(cs.namespace(|| "x"))?;
+        // y is below 2^255, so the top bit is free
+        bits[255] = x_bits[0].clone();
+        Ok(bits)
+    }
+
+    pub fn get_value(&self) -> Option<AffinePoint> {
+        self.x
+            .get_value()
+            .and_then(|x| self.y.get_value().map(|y| (x, y)))
+    }
+
+    pub fn x(&self) -> &Ed25519Element<F> {
+        &self.x
+    }
+
+    pub fn y(&self) -> &Ed25519Element<F> {
+        &self.y
+    }
+
+    pub fn add<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let one = Ed25519Element::constant([1, 0, 0, 0]);
+
+        let x1y2 = self.x.mul(cs.namespace(|| "x1 y2"), &other.y)?;
+        let y1x2 = self.y.mul(cs.namespace(|| "y1 x2"), &other.x)?;
+        let x1x2 = self.x.mul(cs.namespace(|| "x1 x2"), &other.x)?;
+        let y1y2 = self.y.mul(cs.namespace(|| "y1 y2"), &other.y)?;
+        let dt = x1x2.mul(cs.namespace(|| "x1 x2 y1 y2"), &y1y2)?.mul(
+            cs.namespace(|| "d x1 x2 y1 y2"),
+            &Ed25519Element::constant(native::d()),
+        )?;
+
+        // The denominators are nonzero because d is not a square.
+        let value = self
+            .get_value()
+            .and_then(|p| other.get_value().map(|q| native::add_points(&p, &q)));
+        let x = Ed25519Element::alloc(cs.namespace(|| "x3"), value.map(|p| p.0))?;
+        let y = Ed25519Element::alloc(cs.namespace(|| "y3"), value.map(|p| p.1))?;
+
+        let numerator = x1y2.add(cs.namespace(|| "x numerator"), &y1x2)?;
+        let denominator = one.add(cs.namespace(|| "x denominator"), &dt)?;
+        let product = x.mul(cs.namespace(|| "x3 * x denominator"), &denominator)?;
+        Ed25519Element::enforce_equal(cs.namespace(|| "x3"), &product, &numerator)?;
+
+        let numerator = y1y2.add(cs.namespace(|| "y numerator"), &x1x2)?;
+        let denominator = one.sub(cs.namespace(|| "y denominator"), &dt)?;
+        let product = y.mul(cs.namespace(|| "y3 * y denominator"), &denominator)?;
+        Ed25519Element::enforce_equal(cs.namespace(|| "y3"), &product, &numerator)?;
+
+        Ok(EdwardsPoint { x, y })
+    }
+
+    pub fn double<CS: ConstraintSystem<F>>(&self, cs: CS) -> Result<Self, SynthesisError> {
+        self.add(cs, self)
+    }
+
+    /// `if_true` if `condition` holds, and `if_false` otherwise.
+    pub fn select<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        condition: &Boolean,
+        if_true: &Self,
+        if_false: &Self,
+    ) -> Result<Self, SynthesisError> {
+        Ok(EdwardsPoint {
+            x: Ed25519Element::select(cs.namespace(|| "x"), condition, &if_true.x, &if_false.x)?,
+            y: Ed25519Element::select(cs.namespace(|| "y"), condition, &if_true.y, &if_false.y)?,
+        })
+    }
+
+    /// This point multiplied by the scalar with little-endian bits `bits`.
+    pub fn mul<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+        bits: &[Boolean],
+    ) -> Result<Self, SynthesisError> {
+        let mut acc = Self::identity();
+        for (i, bit) in bits.iter().enumerate().rev() {
+            let mut cs = cs.namespace(|| format!("bit {}", i));
+            if i + 1 < bits.len() {
+                acc = acc.double(cs.namespace(|| "double"))?;
+                let sum = acc.add(cs.namespace(|| "add"), self)?;
+                acc = Self::select(cs.namespace(|| "select"), bit, &sum, &acc)?;
+            } else {
+                acc = Self::select(cs.namespace(|| "select"), bit, self, &acc)?;
+            }
+        }
+        Ok(acc)
+    }
+
+    /// The fixed point `base` multiplied by the scalar with little-endian
+    /// bits `bits`, which saves the doublings of `mul`.
+    pub fn mul_fixed<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        base: AffinePoint,
+        bits: &[Boolean],
+    ) -> Result<Self, SynthesisError> {
+        let identity = Self::identity();
+        let mut acc = identity.clone();
+        let mut power = base;
+        for (i, bit) in bits.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("bit {}", i));
+            let term = Self::select(
+                cs.namespace(|| "select"),
+                bit,
+                &Self::constant(power),
+                &identity,
+            )?;
+            acc = if i == 0 {
+                term
+            } else {
+                acc.add(cs.namespace(|| "add"), &term)?
+            };
+            power = native::add_points(&power, &power);
+        }
+        Ok(acc)
+    }
+
+    pub fn enforce_equal<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<(), SynthesisError> {
+        Ed25519Element::enforce_equal(cs.namespace(|| "x"), &a.x, &b.x)?;
+        Ed25519Element::enforce_equal(cs.namespace(|| "y"), &a.y, &b.y)
+    }
+
+    fn clear_cofactor<CS: ConstraintSystem<F>>(&self, mut cs: CS) -> Result<Self, SynthesisError> {
+        let mut acc = self.clone();
+        for i in 0..3 {
+            acc = acc.double(cs.namespace(|| format!("double {}", i)))?;
+        }
+        Ok(acc)
+    }
+}
+
+/// The challenge `k = SHA-512(R || A || M) mod l` of a signature with
+/// encoded first half `r`, as canonical little-endian bits, of which the
+/// top three are zero.
+pub fn challenge<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    r: &[Boolean],
+    public_key: &[Boolean],
+    message: &[Boolean],
+) -> Result<Vec<Boolean>, SynthesisError> {
+    let input: Vec<_> = r
+        .iter()
+        .chain(public_key.iter())
+        .chain(message.iter())
+        .cloned()
+        .collect();
+    let digest = sha512(cs.namespace(|| "hash"), &reverse_bits_in_bytes(&input))?;
+    let k = Ed25519ScalarElement::from_bits(
+        cs.namespace(|| "reduce"),
+        &reverse_bits_in_bytes(&digest),
+    )?;
+    k.to_bits(cs.namespace(|| "bits"))
+}
+
+/// Constrains `signature`, 64 bytes, to be a valid signature of `message`
+/// under `public_key`, 32 bytes.
+pub fn verify<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    public_key: &[Boolean],
+    signature: &[Boolean],
+    message: &[Boolean],
+) -> Result<(), SynthesisError> {
+    assert_eq!(public_key.len(), 256);
+    assert_eq!(signature.len(), 512);
+
+    let a = EdwardsPoint::alloc_encoded(cs.namespace(|| "A"), public_key)?;
+    let r = EdwardsPoint::alloc_encoded(cs.namespace(|| "R"), &signature[..256])?;
+    let s = &signature[256..];
+    Ed25519ScalarElement::enforce_below_modulus(cs.namespace(|| "S canonical"), s)?;
+
+    let k = challenge(cs.namespace(|| "k"), &signature[..256], public_key, message)?;
+
+    // Both scalars are canonical, so their top bits are zero.
+    let lhs = EdwardsPoint::mul_fixed(cs.namespace(|| "[S]B"), base_point(), &s[..SCALAR_BITS])?;
+    let ka = a.mul(cs.namespace(|| "[k]A"), &k[..SCALAR_BITS])?;
+    let rhs = r.add(cs.namespace(|| "R + [k]A"), &ka)?;
+
+    let lhs = lhs.clear_cofactor(cs.namespace(|| "[8][S]B"))?;
+    let rhs = rhs.clear_cofactor(cs.namespace(|| "[8](R + [k]A)"))?;
+    EdwardsPoint::enforce_equal(cs.namespace(|| "verify"), &lhs, &rhs)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::Circuit,
+        dev::{is_satisfied, GadgetCheck},
+        fields::Fp,
+        gadgets::AllocatedBit,
+        Basic,
+    };
+
+    fn to_words(bytes: &[u8; 32]) -> [u64; 4] {
+        let mut out = [0; 4];
+        for (i, byte) in bytes.iter().enumerate() {
+            out[i / 8] |= (*byte as u64) << (8 * (i % 8));
+        }
+        out
+    }
+
+    fn alloc_bytes<CS: ConstraintSystem<Fp>>(
+        cs: &mut CS,
+        name: &str,
+        bytes: &[u8],
+    ) -> Result<Vec<Boolean>, SynthesisError> {
+        let mut bits = vec![];
+        for (i, byte) in bytes.iter().enumerate() {
+            for j in 0..8 {
+                bits.push(Boolean::Is(AllocatedBit::alloc(
+                    cs.namespace(|| format!("{} byte {} bit {}", name, i, j)),
+                    || Ok((byte >> j) & 1 == 1),
+                )?));
+            }
+        }
+        Ok(bits)
+    }
+
+    // RFC 8032, section 7.1, test 1
+    const PUBLIC_KEY: [u8; 32] =
+        hex!("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+    const SIGNATURE: [u8; 64] = hex!(
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155
+         5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+    );
+
+    #[test]
+    fn test_native() {
+        let b = base_point();
+        assert_eq!(
+            native::encode(&b),
+            to_words(&hex!(
+                "5866666666666666666666666666666666666666666666666666666666666666"
+            ))
+        );
+        assert_eq!(decompress(&native::encode(&b)), Some(b));
+        let key = to_words(&PUBLIC_KEY);
+        assert_eq!(decompress(&key).map(|p| native::encode(&p)), Some(key));
+
+        // y = p + 1 is a non-canonical encoding of y = 1.
+        let mut non_canonical = Curve25519Base::MODULUS;
+        non_canonical[0] += 1;
+        assert_eq!(
+            native::recover(&non_canonical),
+            Some(([0; 4], [1, 0, 0, 0]))
+        );
+        assert_eq!(decompress(&non_canonical), None);
+    }
+
+    struct EncodedCircuit([u8; 32]);
+
+    impl Circuit<Fp> for EncodedCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let bits = alloc_bytes(cs, "encoding", &self.0)?;
+            EdwardsPoint::alloc_encoded(cs.namespace(|| "point"), &bits)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_encoding() {
+        let mut wrong_sign = PUBLIC_KEY;
+        wrong_sign[31] ^= 0x80;
+        let mut non_canonical = [0xff; 32];
+        non_canonical[0] = 0xee;
+        non_canonical[31] = 0x7f;
+        GadgetCheck::new(EncodedCircuit(PUBLIC_KEY), &[])
+            .malformed("non-canonical y", EncodedCircuit(non_canonical), &[])
+            .check::<Basic>()
+            .unwrap();
+        // Flipping the sign bit gives the negated point, which is also valid.
+        assert_eq!(
+            is_satisfied::<_, _, Basic>(&EncodedCircuit(wrong_sign), &[]),
+            Ok(true)
+        );
+    }
+
+    enum Op {
+        Add(AffinePoint),
+        Mul(u64, usize),
+        MulFixed(u64, usize),
+    }
+
+    struct OpCircuit {
+        point: AffinePoint,
+        op: Op,
+        expected: AffinePoint,
+    }
+
+    impl Circuit<Fp> for OpCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let point = EdwardsPoint::alloc(cs.namespace(|| "point"), Some(self.point))?;
+            let scalar_bits = |cs: &mut CS, scalar: u64, bits: usize| {
+                (0..bits)
+                    .map(|i| {
+                        Ok(Boolean::Is(AllocatedBit::alloc(
+                            cs.namespace(|| format!("scalar bit {}", i)),
+                            || Ok((scalar >> i) & 1 == 1),
+                        )?))
+                    })
+                    .collect::<Result<Vec<_>, SynthesisError>>()
+            };
+            let result = match self.op {
+                Op::Add(other) => {
+                    let other = EdwardsPoint::alloc(cs.namespace(|| "other"), Some(other))?;
+                    point.add(cs.namespace(|| "add"), &other)?
+                }
+                Op::Mul(scalar, bits) => {
+                    let bits = scalar_bits(cs, scalar, bits)?;
+                    point.mul(cs.namespace(|| "mul"), &bits)?
+                }
+                Op::MulFixed(scalar, bits) => {
+                    let bits = scalar_bits(cs, scalar, bits)?;
+                    EdwardsPoint::mul_fixed(cs.namespace(|| "mul"), self.point, &bits)?
+                }
+            };
+            EdwardsPoint::enforce_equal(
+                cs.namespace(|| "expected"),
+                &result,
+                &EdwardsPoint::constant(self.expected),
+            )
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let b = base_point();
+        let b2 = add_points(&b, &b);
+        let b3 = add_points(&b2, &b);
+        let b5 = add_points(&b3, &b2);
+        let identity = ([0; 4], [1, 0, 0, 0]);
+
+        GadgetCheck::new(
+            OpCircuit {
+                point: b,
+                op: Op::Add(b2),
+                expected: b3,
+            },
+            &[],
+        )
+        .malformed(
+            "wrong sum",
+            OpCircuit {
+                point: b,
+                op: Op::Add(b2),
+                expected: b2,
+            },
+            &[],
+        )
+        .malformed(
+            "off curve",
+            OpCircuit {
+                point: (b.0, b2.1),
+                op: Op::Add(b2),
+                expected: b3,
+            },
+            &[],
+        )
+        .check::<Basic>()
+        .unwrap();
+
+        for circuit in &[
+            // Addition is complete: doubling and the identity need no
+            // special cases.
+            OpCircuit {
+                point: b,
+                op: Op::Add(b),
+                expected: b2,
+            },
+            OpCircuit {
+                point: b,
+                op: Op::Add(identity),
+                expected: b,
+            },
+            OpCircuit {
+                point: b,
+                op: Op::Mul(5, 3),
+                expected: b5,
+            },
+            OpCircuit {
+                point: b,
+                op: Op::Mul(0, 2),
+                expected: identity,
+            },
+            OpCircuit {
+                point: b,
+                op: Op::MulFixed(5, 3),
+                expected: b5,
+            },
+        ] {
+            assert_eq!(is_satisfied::<_, _, Basic>(circuit, &[]), Ok(true));
+        }
+    }
+
+    struct ChallengeCircuit {
+        k: [u8; 32],
+    }
+
+    impl Circuit<Fp> for ChallengeCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let r = alloc_bytes(cs, "R", &SIGNATURE[..32])?;
+            let public_key = alloc_bytes(cs, "public key", &PUBLIC_KEY)?;
+            let k = challenge(cs.namespace(|| "k"), &r, &public_key, &[])?;
+            for (i, bit) in k.iter().enumerate() {
+                Boolean::enforce_equal(
+                    cs.namespace(|| format!("k bit {}", i)),
+                    bit,
+                    &Boolean::constant((self.k[i / 8] >> (i % 8)) & 1 == 1),
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_challenge() {
+        let k = hex!("86eabc8e4c96193d290504e7c600df6cf8d8256131ec2c138a3e7e162e525404");
+        let mut wrong = k;
+        wrong[0] ^= 1;
+        GadgetCheck::new(ChallengeCircuit { k }, &[])
+            .malformed("wrong challenge", ChallengeCircuit { k: wrong }, &[])
+            .check::<Basic>()
+            .unwrap();
+    }
+
+    struct VerifyCircuit {
+        public_key: [u8; 32],
+        signature: [u8; 64],
+        message: Vec<u8>,
+    }
+
+    impl Circuit<Fp> for VerifyCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let public_key = alloc_bytes(cs, "public key", &self.public_key)?;
+            let signature = alloc_bytes(cs, "signature", &self.signature)?;
+            let message = alloc_bytes(cs, "message", &self.message)?;
+            verify(cs.namespace(|| "verify"), &public_key, &signature, &message)
+        }
+    }
+
+    // Checking a whole verification circuit needs many gigabytes of memory;
+    // run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_verify() {
+        let mut wrong_s = SIGNATURE;
+        wrong_s[32] ^= 1;
+        GadgetCheck::new(
+            VerifyCircuit {
+                public_key: PUBLIC_KEY,
+                signature: SIGNATURE,
+                message: vec![],
+            },
+            &[],
+        )
+        .malformed(
+            "wrong message",
+            VerifyCircuit {
+                public_key: PUBLIC_KEY,
+                signature: SIGNATURE,
+                message: vec![0],
+            },
+            &[],
+        )
+        .malformed(
+            "wrong S",
+            VerifyCircuit {
+                public_key: PUBLIC_KEY,
+                signature: wrong_s,
+                message: vec![],
+            },
+            &[],
+        )
+        .check::<Basic>()
+        .unwrap();
+    }
+}
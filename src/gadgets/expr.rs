@@ -0,0 +1,313 @@
+//! Arithmetic expressions that lower to constraints.
+//!
+//! An [`Expr`] is built from allocated values with [`var`], constants with
+//! [`constant`], and the `+`, `-` and `*` operators, and is only turned into
+//! constraints by [`Expr::evaluate`] or [`enforce_equal`]. Lowering keeps
+//! sums and products by constants as linear combinations, so only products
+//! of two non-constant subexpressions cost a multiplication gate; constants
+//! are folded; and a subexpression that is shared, by cloning an `Expr`, is
+//! lowered once however often it is used. Each gate is placed in its own
+//! numbered namespace.
+//!
+//! ```ignore
+//! // y = x^3 + 5, with the shared x^2 costing a single gate
+//! let x = var(x);
+//! let x2 = x.clone() * x.clone();
+//! enforce_equal(cs, &(x2 * x + constant(F::from_u64(5))), &var(y))?;
+//! ```
+
+use super::{AllocatedBit, AllocatedNum, Combination, Num};
+use crate::circuits::{ConstraintSystem, SynthesisError};
+use crate::fields::Field;
+use std::collections::HashMap;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::rc::Rc;
+
+/// An arithmetic expression over the field `F`. Cloning an `Expr` shares it
+/// rather than copying it.
+#[derive(Clone, Debug)]
+pub struct Expr<F: Field>(Rc<Node<F>>);
+
+#[derive(Debug)]
+enum Node<F: Field> {
+    Constant(F),
+    Var(AllocatedNum<F>),
+    Add(Expr<F>, Expr<F>),
+    Sub(Expr<F>, Expr<F>),
+    Mul(Expr<F>, Expr<F>),
+    Neg(Expr<F>),
+}
+
+/// An allocated value.
+pub fn var<F: Field>(num: AllocatedNum<F>) -> Expr<F> {
+    Expr(Rc::new(Node::Var(num)))
+}
+
+/// A constant.
+pub fn constant<F: Field>(value: F) -> Expr<F> {
+    Expr(Rc::new(Node::Constant(value)))
+}
+
+impl<F: Field> From<AllocatedNum<F>> for Expr<F> {
+    fn from(num: AllocatedNum<F>) -> Self {
+        var(num)
+    }
+}
+
+impl<F: Field> From<AllocatedBit> for Expr<F> {
+    fn from(bit: AllocatedBit) -> Self {
+        var(bit.into())
+    }
+}
+
+impl<F: Field> Add for Expr<F> {
+    type Output = Expr<F>;
+
+    fn add(self, other: Expr<F>) -> Expr<F> {
+        Expr(Rc::new(Node::Add(self, other)))
+    }
+}
+
+impl<F: Field> Sub for Expr<F> {
+    type Output = Expr<F>;
+
+    fn sub(self, other: Expr<F>) -> Expr<F> {
+        Expr(Rc::new(Node::Sub(self, other)))
+    }
+}
+
+impl<F: Field> Mul for Expr<F> {
+    type Output = Expr<F>;
+
+    fn mul(self, other: Expr<F>) -> Expr<F> {
+        Expr(Rc::new(Node::Mul(self, other)))
+    }
+}
+
+impl<F: Field> Neg for Expr<F> {
+    type Output = Expr<F>;
+
+    fn neg(self) -> Expr<F> {
+        Expr(Rc::new(Node::Neg(self)))
+    }
+}
+
+/// A lowered subexpression: a constant, or a linear combination of
+/// allocated values.
+#[derive(Clone)]
+enum Lowered<F: Field> {
+    Constant(F),
+    Linear(Combination<F>),
+}
+
+impl<F: Field> Lowered<F> {
+    fn into_combination(self) -> Combination<F> {
+        match self {
+            Lowered::Constant(c) => Combination::from(Num::constant(c)),
+            Lowered::Linear(lc) => lc,
+        }
+    }
+}
+
+/// Lowers the expressions given to it, remembering the subexpressions it has
+/// lowered so that shared ones are only constrained once.
+struct Lowering<F: Field> {
+    lowered: HashMap<*const Node<F>, Lowered<F>>,
+    gates: usize,
+}
+
+impl<F: Field> Lowering<F> {
+    fn new() -> Self {
+        Lowering {
+            lowered: HashMap::new(),
+            gates: 0,
+        }
+    }
+
+    fn lower<CS: ConstraintSystem<F>>(
+        &mut self,
+        cs: &mut CS,
+        expr: &Expr<F>,
+    ) -> Result<Lowered<F>, SynthesisError> {
+        let key = Rc::as_ptr(&expr.0);
+        if let Some(lowered) = self.lowered.get(&key) {
+            return Ok(lowered.clone());
+        }
+
+        let lowered = match &*expr.0 {
+            Node::Constant(c) => Lowered::Constant(*c),
+            Node::Var(num) => Lowered::Linear(Combination::from(*num)),
+            Node::Add(a, b) => match (self.lower(cs, a)?, self.lower(cs, b)?) {
+                (Lowered::Constant(a), Lowered::Constant(b)) => Lowered::Constant(a + b),
+                (a, b) => Lowered::Linear(a.into_combination() + b.into_combination()),
+            },
+            Node::Sub(a, b) => match (self.lower(cs, a)?, self.lower(cs, b)?) {
+                (Lowered::Constant(a), Lowered::Constant(b)) => Lowered::Constant(a - b),
+                (a, b) => {
+                    Lowered::Linear(a.into_combination() + b.into_combination().scale(-F::one()))
+                }
+            },
+            Node::Neg(a) => match self.lower(cs, a)? {
+                Lowered::Constant(a) => Lowered::Constant(-a),
+                Lowered::Linear(a) => Lowered::Linear(a.scale(-F::one())),
+            },
+            Node::Mul(a, b) => match (self.lower(cs, a)?, self.lower(cs, b)?) {
+                (Lowered::Constant(a), Lowered::Constant(b)) => Lowered::Constant(a * b),
+                (Lowered::Constant(c), Lowered::Linear(lc))
+                | (Lowered::Linear(lc), Lowered::Constant(c)) => Lowered::Linear(lc.scale(c)),
+                (Lowered::Linear(a), Lowered::Linear(b)) => {
+                    self.gates += 1;
+                    let product = a.mul(cs.namespace(|| format!("mul {}", self.gates)), &b)?;
+                    Lowered::Linear(Combination::from(product))
+                }
+            },
+        };
+
+        self.lowered.insert(key, lowered.clone());
+        Ok(lowered)
+    }
+}
+
+impl<F: Field> Expr<F> {
+    /// The value of this expression, if the values of its variables are
+    /// known.
+    pub fn value(&self) -> Option<F> {
+        match &*self.0 {
+            Node::Constant(c) => Some(*c),
+            Node::Var(num) => num.get_value(),
+            Node::Add(a, b) => a.value().and_then(|a| b.value().map(|b| a + b)),
+            Node::Sub(a, b) => a.value().and_then(|a| b.value().map(|b| a - b)),
+            Node::Mul(a, b) => a.value().and_then(|a| b.value().map(|b| a * b)),
+            Node::Neg(a) => a.value().map(|a| -a),
+        }
+    }
+
+    /// Constrains this expression, returning its value as a constant if it
+    /// does not depend on any variable, or as an allocated value otherwise.
+    pub fn evaluate<CS: ConstraintSystem<F>>(&self, mut cs: CS) -> Result<Num<F>, SynthesisError> {
+        match Lowering::new().lower(&mut cs, self)? {
+            Lowered::Constant(c) => Ok(Num::constant(c)),
+            Lowered::Linear(lc) => lc.evaluate(cs),
+        }
+    }
+}
+
+/// Constrains each of `exprs` as `Expr::evaluate` does, lowering
+/// subexpressions shared between them once.
+pub fn evaluate_all<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    exprs: &[Expr<F>],
+) -> Result<Vec<Num<F>>, SynthesisError> {
+    let mut lowering = Lowering::new();
+    let mut nums = Vec::with_capacity(exprs.len());
+    for (i, expr) in exprs.iter().enumerate() {
+        nums.push(match lowering.lower(&mut cs, expr)? {
+            Lowered::Constant(c) => Num::constant(c),
+            Lowered::Linear(lc) => lc.evaluate(cs.namespace(|| format!("expr {}", i)))?,
+        });
+    }
+    Ok(nums)
+}
+
+/// Constrains `a` and `b` to be equal. Subexpressions shared between them are
+/// only lowered once. Returns `SynthesisError::Unsatisfiable` if both are
+/// constants that differ.
+pub fn enforce_equal<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    a: &Expr<F>,
+    b: &Expr<F>,
+) -> Result<(), SynthesisError> {
+    let mut lowering = Lowering::new();
+    let a = lowering.lower(&mut cs, a)?;
+    let b = lowering.lower(&mut cs, b)?;
+
+    match (a, b) {
+        (Lowered::Constant(a), Lowered::Constant(b)) => {
+            if a == b {
+                Ok(())
+            } else {
+                Err(SynthesisError::Unsatisfiable)
+            }
+        }
+        (a, b) => {
+            let lc = a.into_combination().lc(&mut cs) - &b.into_combination().lc(&mut cs);
+            cs.enforce_zero(lc);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::Circuit,
+        dev::{is_satisfied, GadgetCheck},
+        fields::Fp,
+        Basic,
+    };
+
+    struct CubicCircuit {
+        x: Fp,
+        y: Fp,
+    }
+
+    impl Circuit<Fp> for CubicCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = var(AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(self.x))?);
+            let y = var(AllocatedNum::alloc_input(cs.namespace(|| "y"), || {
+                Ok(self.y)
+            })?);
+
+            // y = 2x^3 - x^2 + 5, with x^2 lowered once and the constants
+            // folded into linear combinations
+            let x2 = x.clone() * x.clone();
+            let two = constant(Fp::one()) + constant(Fp::one());
+            let rhs = two * x2.clone() * x - x2 + constant(Fp::from(5));
+            enforce_equal(cs.namespace(|| "y"), &y, &rhs)
+        }
+    }
+
+    #[test]
+    fn test_expr() {
+        let circuit = |x, y| CubicCircuit {
+            x: Fp::from(x),
+            y: Fp::from(y),
+        };
+
+        GadgetCheck::new(circuit(3, 50), &[Fp::from(50)])
+            .malformed("wrong output", circuit(3, 51), &[Fp::from(51)])
+            .budget(4, 7)
+            .check::<Basic>()
+            .unwrap();
+    }
+
+    struct ConstantCircuit {
+        a: u64,
+        b: u64,
+    }
+
+    impl Circuit<Fp> for ConstantCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let a = constant(Fp::from(self.a)) * constant(Fp::from(self.a));
+            assert_eq!(a.value(), Some(Fp::from(self.a * self.a)));
+            assert!(a.evaluate(cs.namespace(|| "a"))?.is_constant());
+
+            enforce_equal(cs.namespace(|| "a == b"), &a, &constant(Fp::from(self.b)))
+        }
+    }
+
+    #[test]
+    fn test_expr_constants() {
+        assert_eq!(
+            is_satisfied::<_, _, Basic>(&ConstantCircuit { a: 3, b: 9 }, &[]),
+            Ok(true)
+        );
+        assert_eq!(
+            is_satisfied::<_, _, Basic>(&ConstantCircuit { a: 3, b: 8 }, &[]),
+            Err(crate::dev::SatisfactionError::Synthesis(
+                SynthesisError::Unsatisfiable
+            ))
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Inner products of witnessed vectors, and the vector folding step of an
+//! inner product argument, so that inner circuits can check small IPA-style
+//! arguments about their own committed vectors.
+
+use super::num::{AllocatedNum, Combination, Num};
+use crate::{circuits::ConstraintSystem, fields::Field, SynthesisError};
+
+pub use crate::util::compute_inner_product as inner_product_native;
+
+/// How the running sum of an inner product is accumulated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chunking {
+    /// Keep every product in a single linear combination, allocating only
+    /// the result.
+    Unbounded,
+    /// Allocate the running sum after every `n` products, bounding the size
+    /// of the linear combinations in the circuit at one extra constraint
+    /// per chunk.
+    Every(usize),
+}
+
+/// Computes `<a, b>` with one multiplication per term.
+pub fn inner_product<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    a: &[Num<F>],
+    b: &[Num<F>],
+    chunking: Chunking,
+) -> Result<Num<F>, SynthesisError> {
+    assert_eq!(a.len(), b.len());
+    let chunk = match chunking {
+        Chunking::Unbounded => a.len().max(1),
+        Chunking::Every(n) => {
+            assert!(n > 0);
+            n
+        }
+    };
+
+    let mut acc = Combination::zero();
+    for (i, (a_i, b_i)) in a.iter().zip(b.iter()).enumerate() {
+        let product = Combination::from(*a_i).mul(
+            cs.namespace(|| format!("a[{}] * b[{}]", i, i)),
+            &Combination::from(*b_i),
+        )?;
+        acc += product;
+
+        if (i + 1) % chunk == 0 && i + 1 != a.len() {
+            let sum = acc.evaluate(cs.namespace(|| format!("partial sum {}", i)))?;
+            acc = Combination::from(sum);
+        }
+    }
+
+    acc.evaluate(cs.namespace(|| "result"))
+}
+
+/// Enforces that `<a, b> = expected`.
+pub fn enforce_inner_product<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    a: &[Num<F>],
+    b: &[Num<F>],
+    expected: &AllocatedNum<F>,
+    chunking: Chunking,
+) -> Result<(), SynthesisError> {
+    let product = inner_product(cs.namespace(|| "inner product"), a, b, chunking)?;
+    let lc = product.lc(&mut cs);
+    cs.enforce_zero(lc - &expected.lc());
+
+    Ok(())
+}
+
+/// Halves `v` as one round of an inner product argument does, computing
+/// `v_lo * u + v_hi * u_inv`. `v` must have even length.
+pub fn fold_native<F: Field>(v: &[F], u: F, u_inv: F) -> Vec<F> {
+    assert_eq!(v.len() % 2, 0);
+    let (lo, hi) = v.split_at(v.len() / 2);
+    lo.iter()
+        .zip(hi.iter())
+        .map(|(lo, hi)| *lo * u + *hi * u_inv)
+        .collect()
+}
+
+/// Halves `v` in-circuit, computing `v_lo * u + v_hi * u_inv`.
+pub fn fold<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    v: &[Num<F>],
+    u: Num<F>,
+    u_inv: Num<F>,
+) -> Result<Vec<Num<F>>, SynthesisError> {
+    assert_eq!(v.len() % 2, 0);
+    let (lo, hi) = v.split_at(v.len() / 2);
+    let u = Combination::from(u);
+    let u_inv = Combination::from(u_inv);
+
+    lo.iter()
+        .zip(hi.iter())
+        .enumerate()
+        .map(|(i, (lo, hi))| {
+            let mut cs = cs.namespace(|| format!("fold {}", i));
+            let lo = Combination::from(*lo).mul(cs.namespace(|| "lo * u"), &u)?;
+            let hi = Combination::from(*hi).mul(cs.namespace(|| "hi * u_inv"), &u_inv)?;
+            (Combination::from(lo) + hi).evaluate(cs.namespace(|| "sum"))
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::{Circuit, SynthesisError},
+        dev::GadgetCheck,
+        fields::Fp,
+        Basic,
+    };
+
+    struct TestCircuit {
+        a: Vec<Fp>,
+        b: Vec<Fp>,
+        u: Fp,
+        expected: Fp,
+        chunking: Chunking,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut alloc = |name: &str, values: &[Fp]| -> Result<Vec<Num<Fp>>, SynthesisError> {
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        AllocatedNum::alloc(cs.namespace(|| format!("{}[{}]", name, i)), || Ok(*v))
+                            .map(Num::from)
+                    })
+                    .collect()
+            };
+            let a = alloc("a", &self.a)?;
+            let b = alloc("b", &self.b)?;
+            let u = AllocatedNum::alloc(cs.namespace(|| "u"), || Ok(self.u))?;
+            let u_inv = u.invert(cs.namespace(|| "u_inv"))?;
+            let expected =
+                AllocatedNum::alloc_input(cs.namespace(|| "expected"), || Ok(self.expected))?;
+
+            // Fold as one round of an inner product argument would, then check
+            // the inner product of the halved vectors.
+            let a = fold(cs.namespace(|| "fold a"), &a, u.into(), u_inv.into())?;
+            let b = fold(cs.namespace(|| "fold b"), &b, u_inv.into(), u.into())?;
+            enforce_inner_product(cs.namespace(|| "check"), &a, &b, &expected, self.chunking)
+        }
+    }
+
+    #[test]
+    fn test_inner_product() {
+        let a: Vec<_> = (1..=8).map(Fp::from).collect();
+        let b: Vec<_> = (11..=18).map(Fp::from).collect();
+        let u = Fp::from(3);
+        let u_inv = u.invert().unwrap();
+
+        let a_folded = fold_native(&a, u, u_inv);
+        let b_folded = fold_native(&b, u_inv, u);
+        let expected = inner_product_native(&a_folded, &b_folded);
+
+        let budgets = [
+            (Chunking::Unbounded, (36, 55)),
+            (Chunking::Every(1), (37, 58)),
+            (Chunking::Every(3), (36, 56)),
+        ];
+        for &(chunking, (mults, linear)) in &budgets {
+            let circuit = |expected| TestCircuit {
+                a: a.clone(),
+                b: b.clone(),
+                u,
+                expected,
+                chunking,
+            };
+            let wrong = expected + Fp::one();
+            GadgetCheck::new(circuit(expected), &[expected])
+                .malformed("wrong inner product", circuit(wrong), &[wrong])
+                .budget(mults, linear)
+                .check::<Basic>()
+                .unwrap();
+        }
+    }
+}
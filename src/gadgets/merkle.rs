@@ -0,0 +1,415 @@
+//! Append-only Merkle trees of field elements, hashed with Rescue, for
+//! applications that keep a set of commitments in a single payload root.
+//!
+//! [`IncrementalMerkleTree`] keeps only the frontier of a tree, the left
+//! siblings on the path of the next empty leaf, so that appending costs one
+//! hash per level however many leaves there are. Each append returns the
+//! [`MerklePath`] of the new leaf, and [`append`] checks the same append
+//! in-circuit: that the leaf was empty under the old root, and what the root
+//! is once it is not.
+//!
+//! A node at level `l` hashes a domain tagged with `l` and then its two
+//! children, so that nodes of different levels never collide. Empty leaves
+//! are zero, and an empty subtree hashes as a node of empty children.
+
+use super::num::{AllocatedNum, Num};
+use super::rescue::RescueGadget;
+use super::{AllocatedBit, Combination};
+use crate::{
+    circuits::{Coeff, ConstraintSystem, SynthesisError},
+    encoding::{invalid_data, read_scalar, read_u32, read_u64, write_scalar, write_u32, write_u64},
+    fields::Field,
+    rescue::Rescue,
+};
+use std::io::{self, Read, Write};
+
+/// The deepest tree supported, so that positions fit a `u64`.
+pub const MAX_DEPTH: usize = 63;
+
+/// Absorbed ahead of the children of a node, with the level of the node in
+/// its low byte.
+const NODE_DOMAIN: u64 = 0x6861_6c6f_5f6d_6b00;
+
+/// The hash of a node at `level`, whose children are `left` and `right`.
+pub fn hash_node<F: Field>(level: usize, left: F, right: F) -> F {
+    Rescue::hash_to_field(&[F::from_u64(NODE_DOMAIN | level as u64), left, right])
+}
+
+/// The roots of the empty subtrees of each height from 0 to `depth`.
+fn empty_roots<F: Field>(depth: usize) -> Vec<F> {
+    let mut roots = vec![F::zero()];
+    for level in 0..depth {
+        roots.push(hash_node(level, roots[level], roots[level]));
+    }
+    roots
+}
+
+/// The position of a leaf and the siblings on its path to the root, from the
+/// leaf up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerklePath<F: Field> {
+    pub position: u64,
+    pub siblings: Vec<F>,
+}
+
+impl<F: Field> MerklePath<F> {
+    /// The root of a tree with `leaf` at this path.
+    pub fn root(&self, leaf: F) -> F {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(leaf, |node, (level, sibling)| {
+                if (self.position >> level) & 1 == 0 {
+                    hash_node(level, node, *sibling)
+                } else {
+                    hash_node(level, *sibling, node)
+                }
+            })
+    }
+}
+
+/// An append-only Merkle tree of fixed depth, of which only the frontier is
+/// kept.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IncrementalMerkleTree<F: Field> {
+    depth: usize,
+    size: u64,
+    /// At each level, the last node that was a left child there.
+    frontier: Vec<F>,
+    /// Kept alongside the frontier, which does not determine the root of a
+    /// full tree.
+    root: F,
+}
+
+impl<F: Field> IncrementalMerkleTree<F> {
+    /// An empty tree with room for `2^depth` leaves.
+    pub fn new(depth: usize) -> Self {
+        assert!(depth <= MAX_DEPTH, "trees are at most {} deep", MAX_DEPTH);
+
+        IncrementalMerkleTree {
+            depth,
+            size: 0,
+            frontier: vec![F::zero(); depth],
+            root: empty_roots(depth)[depth],
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The number of leaves appended so far.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn root(&self) -> F {
+        self.root
+    }
+
+    /// The path of the next empty leaf, or `None` if the tree is full.
+    pub fn next_path(&self) -> Option<MerklePath<F>> {
+        if self.size >> self.depth != 0 {
+            return None;
+        }
+
+        let empty = empty_roots::<F>(self.depth);
+        let siblings = (0..self.depth)
+            .map(|level| {
+                if (self.size >> level) & 1 == 0 {
+                    empty[level]
+                } else {
+                    self.frontier[level]
+                }
+            })
+            .collect();
+
+        Some(MerklePath {
+            position: self.size,
+            siblings,
+        })
+    }
+
+    /// Appends `leaf`, returning its path, or `None` if the tree is full.
+    /// The path stays valid until the next append.
+    pub fn append(&mut self, leaf: F) -> Option<MerklePath<F>> {
+        let path = self.next_path()?;
+
+        let mut node = leaf;
+        for (level, sibling) in path.siblings.iter().enumerate() {
+            if (path.position >> level) & 1 == 0 {
+                self.frontier[level] = node;
+                node = hash_node(level, node, *sibling);
+            } else {
+                node = hash_node(level, *sibling, node);
+            }
+        }
+        self.size += 1;
+        self.root = node;
+
+        Some(path)
+    }
+
+    /// Writes the depth as a little-endian `u32`, the size as a
+    /// little-endian `u64`, the frontier and then the root.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_u32(writer, self.depth as u32)?;
+        write_u64(writer, self.size)?;
+        for node in &self.frontier {
+            write_scalar(writer, node)?;
+        }
+        write_scalar(writer, &self.root)
+    }
+
+    /// Reads a tree, and checks its root against its frontier unless it is
+    /// full.
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let depth = read_u32(reader)? as usize;
+        if depth > MAX_DEPTH {
+            return Err(invalid_data("Merkle tree too deep"));
+        }
+        let size = read_u64(reader)?;
+        if size > 1 << depth {
+            return Err(invalid_data(
+                "Merkle tree holds more leaves than it has room for",
+            ));
+        }
+        let frontier = (0..depth)
+            .map(|_| read_scalar(reader))
+            .collect::<io::Result<_>>()?;
+        let root = read_scalar(reader)?;
+
+        let tree = IncrementalMerkleTree {
+            depth,
+            size,
+            frontier,
+            root,
+        };
+        if let Some(path) = tree.next_path() {
+            if path.root(F::zero()) != root {
+                return Err(invalid_data("Merkle tree root does not match its frontier"));
+            }
+        }
+        Ok(tree)
+    }
+}
+
+/// Witnesses `path` for a tree of `depth`, as the little-endian bits of its
+/// position and its siblings.
+pub fn alloc_path<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    depth: usize,
+    path: Option<&MerklePath<F>>,
+) -> Result<(Vec<AllocatedBit>, Vec<Num<F>>), SynthesisError> {
+    let mut position = Vec::with_capacity(depth);
+    let mut siblings = Vec::with_capacity(depth);
+    for level in 0..depth {
+        let cs = &mut cs.namespace(|| format!("level {}", level));
+        position.push(AllocatedBit::alloc(cs.namespace(|| "position"), || {
+            path.map(|path| (path.position >> level) & 1 == 1)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?);
+        siblings.push(
+            AllocatedNum::alloc(cs.namespace(|| "sibling"), || {
+                path.map(|path| path.siblings[level])
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?
+            .into(),
+        );
+    }
+    Ok((position, siblings))
+}
+
+/// The root of a tree with `leaf` at the given position, matching
+/// `MerklePath::root`.
+pub fn root<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    leaf: Num<F>,
+    position: &[AllocatedBit],
+    siblings: &[Num<F>],
+) -> Result<Num<F>, SynthesisError> {
+    assert_eq!(position.len(), siblings.len());
+
+    let mut node = leaf;
+    for (level, (bit, sibling)) in position.iter().zip(siblings).enumerate() {
+        let cs = &mut cs.namespace(|| format!("level {}", level));
+
+        // Swaps the node and its sibling when the bit is set.
+        let swap = Combination::from(AllocatedNum::from(bit.clone())).mul(
+            cs.namespace(|| "swap"),
+            &(Combination::from(*sibling) + (Coeff::NegativeOne, node)),
+        )?;
+        let left = (Combination::from(node) + swap).evaluate(cs.namespace(|| "left"))?;
+        let right = (Combination::from(*sibling) - swap).evaluate(cs.namespace(|| "right"))?;
+
+        let domain = Num::constant(F::from_u64(NODE_DOMAIN | level as u64));
+        node = RescueGadget::hash_to_field(cs.namespace(|| "hash"), &[domain, left, right])?.into();
+    }
+
+    Ok(node)
+}
+
+/// Constrains the leaf at the given position to be empty under `old_root`,
+/// and returns the root once it is `leaf`.
+///
+/// This does not constrain the position to be the next empty one, as
+/// `IncrementalMerkleTree::append` chooses it; an application that needs the
+/// leaves in order binds the position bits to a count of its appends.
+pub fn append<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    old_root: &Num<F>,
+    position: &[AllocatedBit],
+    siblings: &[Num<F>],
+    leaf: Num<F>,
+) -> Result<Num<F>, SynthesisError> {
+    let empty = root(
+        cs.namespace(|| "old root"),
+        Num::constant(F::zero()),
+        position,
+        siblings,
+    )?;
+    let lc = empty.lc(&mut cs) - &old_root.lc(&mut cs);
+    cs.enforce_zero(lc);
+
+    root(cs.namespace(|| "new root"), leaf, position, siblings)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{circuits::Circuit, dev::GadgetCheck, fields::Fp, Basic};
+
+    const DEPTH: usize = 4;
+
+    /// The root of a tree of `DEPTH` holding `leaves`, computed level by
+    /// level.
+    fn naive_root(leaves: &[Fp]) -> Fp {
+        let mut layer = leaves.to_vec();
+        layer.resize(1 << DEPTH, Fp::zero());
+        for level in 0..DEPTH {
+            layer = layer
+                .chunks(2)
+                .map(|pair| hash_node(level, pair[0], pair[1]))
+                .collect();
+        }
+        layer[0]
+    }
+
+    #[test]
+    fn test_hash_node() {
+        let (left, right) = (Fp::from(3), Fp::from(5));
+        let node = hash_node(0, left, right);
+        for level in 1..4 {
+            assert!(hash_node(level, left, right) != node);
+        }
+        assert!(hash_node(0, Fp::from(4), right) != node);
+        assert!(hash_node(0, left, Fp::from(6)) != node);
+    }
+
+    #[test]
+    fn test_incremental_merkle_tree() {
+        let leaves: Vec<Fp> = (1..=(1 << DEPTH)).map(Fp::from_u64).collect();
+
+        let mut tree = IncrementalMerkleTree::new(DEPTH);
+        assert_eq!(tree.root(), naive_root(&[]));
+        for (i, leaf) in leaves.iter().enumerate() {
+            let path = tree.append(*leaf).unwrap();
+            assert_eq!(path.position, i as u64);
+            assert_eq!(tree.size(), i as u64 + 1);
+            assert_eq!(tree.root(), naive_root(&leaves[..=i]));
+            assert_eq!(path.root(*leaf), tree.root());
+            assert_eq!(path.root(Fp::zero()), naive_root(&leaves[..i]));
+
+            let mut bytes = vec![];
+            tree.write(&mut bytes).unwrap();
+            assert_eq!(IncrementalMerkleTree::read(&mut &bytes[..]).unwrap(), tree);
+        }
+        assert_eq!(tree.next_path(), None);
+        assert_eq!(tree.append(Fp::one()), None);
+
+        let mut bytes = vec![];
+        tree.write(&mut bytes).unwrap();
+        bytes[4] += 1;
+        assert!(IncrementalMerkleTree::<Fp>::read(&mut &bytes[..]).is_err());
+        bytes[4] -= 2;
+        assert!(IncrementalMerkleTree::<Fp>::read(&mut &bytes[..]).is_err());
+        bytes[0] = MAX_DEPTH as u8 + 1;
+        assert!(IncrementalMerkleTree::<Fp>::read(&mut &bytes[..]).is_err());
+    }
+
+    struct AppendCircuit {
+        old_root: Fp,
+        new_root: Fp,
+        path: MerklePath<Fp>,
+        leaf: Fp,
+    }
+
+    impl Circuit<Fp> for AppendCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let old_root =
+                AllocatedNum::alloc_input(cs.namespace(|| "old root"), || Ok(self.old_root))?;
+            let new_root =
+                AllocatedNum::alloc_input(cs.namespace(|| "new root"), || Ok(self.new_root))?;
+            let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(self.leaf))?;
+            let (position, siblings) =
+                alloc_path(cs.namespace(|| "path"), DEPTH, Some(&self.path))?;
+
+            let root = append(
+                cs.namespace(|| "append"),
+                &old_root.into(),
+                &position,
+                &siblings,
+                leaf.into(),
+            )?;
+            let lc = root.lc(&mut *cs);
+            cs.enforce_zero(lc - &new_root.lc());
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_append() {
+        let mut tree = IncrementalMerkleTree::new(DEPTH);
+        for i in 1..=5 {
+            tree.append(Fp::from_u64(i));
+        }
+        let old_root = tree.root();
+        let leaf = Fp::from_u64(6);
+        let path = tree.append(leaf).unwrap();
+        let new_root = tree.root();
+
+        let circuit = |old_root, new_root, path: &MerklePath<Fp>, leaf| AppendCircuit {
+            old_root,
+            new_root,
+            path: path.clone(),
+            leaf,
+        };
+        let occupied = MerklePath {
+            position: 4,
+            ..path.clone()
+        };
+        GadgetCheck::new(
+            circuit(old_root, new_root, &path, leaf),
+            &[old_root, new_root],
+        )
+        .malformed(
+            "wrong leaf",
+            circuit(old_root, new_root, &path, Fp::one()),
+            &[old_root, new_root],
+        )
+        .malformed(
+            "occupied position",
+            circuit(old_root, occupied.root(leaf), &occupied, leaf),
+            &[old_root, occupied.root(leaf)],
+        )
+        .malformed(
+            "wrong old root",
+            circuit(new_root, path.root(leaf), &path, leaf),
+            &[new_root, path.root(leaf)],
+        )
+        .budget(6200, 12397)
+        .check::<Basic>()
+        .unwrap();
+    }
+}
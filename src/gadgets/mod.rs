@@ -1,12 +1,39 @@
+//! Gadgets for writing circuits. The building blocks are `AllocatedBit`,
+//! `AllocatedNum`, the linear combinations `Num` and `Combination`, and
+//! `CurvePoint`, out of which the recursion's verification circuit is built.
+//! Each holds its value alongside its variable, which is `None` when the
+//! circuit is synthesized without a witness.
+
+pub mod beacon;
+pub mod bignum;
+pub mod bls12_381;
 mod boolean;
+mod bytes;
+mod challenge;
+mod commitment;
+pub mod ecdh;
+pub mod ed25519;
 mod ecc;
+pub mod expr;
+pub mod inner_product;
+pub mod merkle;
 mod num;
+pub mod poly;
+pub mod prf;
 mod rescue;
+pub mod rlp;
+pub mod secp256k1;
 pub mod sha256;
+pub mod sha512;
+pub mod ssz;
 mod uint32;
 mod uint64;
+pub mod varint;
+pub mod vrf;
 
 pub use boolean::*;
+pub use challenge::*;
+pub use commitment::*;
 pub use ecc::*;
 pub use num::*;
 pub use rescue::*;
@@ -70,6 +70,8 @@ where
     Ok((base_var, result_var))
 }
 
+/// A variable of the constraint system, together with its value when it is
+/// known.
 #[derive(Clone, Copy, Debug)]
 pub struct AllocatedNum<F: Field> {
     value: Option<F>,
@@ -77,6 +79,7 @@ pub struct AllocatedNum<F: Field> {
 }
 
 impl<F: Field> AllocatedNum<F> {
+    /// The constant 1, as the constraint system's `ONE` variable.
     pub fn one<CS>(cs: CS) -> AllocatedNum<F>
     where
         CS: ConstraintSystem<F>,
@@ -87,6 +90,7 @@ impl<F: Field> AllocatedNum<F> {
         }
     }
 
+    /// Allocates a private variable.
     pub fn alloc<CS, FF>(mut cs: CS, value: FF) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<F>,
@@ -101,6 +105,7 @@ impl<F: Field> AllocatedNum<F> {
         })
     }
 
+    /// Allocates a public input.
     pub fn alloc_input<CS, FF>(mut cs: CS, value: FF) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<F>,
@@ -115,6 +120,7 @@ impl<F: Field> AllocatedNum<F> {
         })
     }
 
+    /// Allocates a public input constrained to equal this variable.
     pub fn inputize<CS>(&self, mut cs: CS) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<F>,
@@ -132,6 +138,7 @@ impl<F: Field> AllocatedNum<F> {
         })
     }
 
+    /// Allocates the product of this variable and `other`.
     pub fn mul<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<F>,
@@ -160,10 +167,13 @@ impl<F: Field> AllocatedNum<F> {
         })
     }
 
+    /// Wraps a variable allocated directly on the constraint system, such as
+    /// one of the outputs of `ConstraintSystem::multiply`.
     pub fn from_raw_unchecked(value: Option<F>, var: Variable) -> Self {
         AllocatedNum { value, var }
     }
 
+    /// Allocates a variable and its square, using one multiplication gate.
     pub fn alloc_and_square<FF, CS>(mut cs: CS, value: FF) -> Result<(Self, Self), SynthesisError>
     where
         CS: ConstraintSystem<F>,
@@ -194,6 +204,7 @@ impl<F: Field> AllocatedNum<F> {
         ))
     }
 
+    /// Allocates `base^5`, the Rescue S-box.
     pub fn rescue_alpha<CS>(mut cs: CS, base: &Combination<F>) -> Result<Self, SynthesisError>
     where
         F: Field,
@@ -216,6 +227,7 @@ impl<F: Field> AllocatedNum<F> {
         })
     }
 
+    /// Allocates `base^(1/5)`, the inverse Rescue S-box.
     pub fn rescue_invalpha<CS>(mut cs: CS, base: &Combination<F>) -> Result<Self, SynthesisError>
     where
         F: Field,
@@ -238,6 +250,7 @@ impl<F: Field> AllocatedNum<F> {
         })
     }
 
+    /// The value of this variable, or `None` if there is no witness.
     pub fn get_value(&self) -> Option<F> {
         self.value
     }
@@ -246,10 +259,12 @@ impl<F: Field> AllocatedNum<F> {
         self.var
     }
 
+    /// This variable as a linear combination.
     pub fn lc(&self) -> LinearCombination<F> {
         LinearCombination::from(self.var)
     }
 
+    /// Allocates the inverse of this variable, which must be nonzero.
     pub fn invert<CS>(&self, mut cs: CS) -> Result<AllocatedNum<F>, SynthesisError>
     where
         CS: ConstraintSystem<F>,
@@ -287,6 +302,7 @@ impl<F: Field> AllocatedNum<F> {
         Ok(newnum)
     }
 
+    /// Allocates a square root of this variable, which must be square.
     pub fn sqrt<CS>(&self, mut cs: CS) -> Result<AllocatedNum<F>, SynthesisError>
     where
         CS: ConstraintSystem<F>,
@@ -335,6 +351,8 @@ impl<F: Field> From<AllocatedBit> for AllocatedNum<F> {
     }
 }
 
+/// A scaled variable or a constant, which costs no constraints to combine
+/// linearly with others.
 #[derive(Clone, Copy, Debug)]
 pub enum Num<F: Field> {
     Constant(Coeff<F>),
@@ -374,6 +392,7 @@ impl<F: Field> From<(Coeff<F>, Num<F>)> for Num<F> {
 }
 
 impl<F: Field> Num<F> {
+    /// Multiplies this by `val`.
     pub fn scale(self, val: F) -> Self {
         match self {
             Num::Constant(coeff) => Num::Constant(coeff * val),
@@ -381,6 +400,7 @@ impl<F: Field> Num<F> {
         }
     }
 
+    /// The constant `val`.
     pub fn constant(val: F) -> Self {
         Num::Constant(Coeff::from(val))
     }
@@ -392,6 +412,7 @@ impl<F: Field> Num<F> {
         }
     }
 
+    /// The value of this, or `None` if there is no witness.
     pub fn value(&self) -> Option<F> {
         match *self {
             Num::Constant(v) => Some(v.value()),
@@ -399,6 +420,7 @@ impl<F: Field> Num<F> {
         }
     }
 
+    /// This as a linear combination.
     pub fn lc<CS: ConstraintSystem<F>>(&self, mut _cs: CS) -> LinearCombination<F> {
         LinearCombination::zero()
             + match self {
@@ -408,6 +430,8 @@ impl<F: Field> Num<F> {
     }
 }
 
+/// A sum of `Num`s whose value is tracked, so that it can be used as an
+/// operand of a gate before being allocated.
 #[derive(Clone, Debug)]
 pub struct Combination<F: Field> {
     value: Option<F>,
@@ -542,6 +566,7 @@ impl<F: Field> Add<(Coeff<F>, Num<F>)> for Combination<F> {
 }
 
 impl<F: Field> Combination<F> {
+    /// The empty sum.
     pub fn zero() -> Self {
         Combination {
             value: Some(F::zero()),
@@ -549,6 +574,7 @@ impl<F: Field> Combination<F> {
         }
     }
 
+    /// Multiplies every term by `by`.
     pub fn scale(self, by: F) -> Self {
         let value = self.value.map(|v| v * by);
         let terms = self.terms.into_iter().map(|t| t.scale(by)).collect();
@@ -556,10 +582,23 @@ impl<F: Field> Combination<F> {
         Combination { value, terms }
     }
 
+    /// The value of the sum, or `None` if there is no witness.
     pub fn get_value(&self) -> Option<F> {
         self.value
     }
 
+    /// The value of the sum if all of its terms are constants, so that it
+    /// is known without a witness.
+    pub fn constant_value(&self) -> Option<F> {
+        self.terms
+            .iter()
+            .try_fold(F::zero(), |acc, term| match term {
+                Num::Constant(v) => Some(acc + v.value()),
+                Num::Allocated(..) => None,
+            })
+    }
+
+    /// The sum as a linear combination.
     pub fn lc<CS: ConstraintSystem<F>>(&self, mut cs: CS) -> LinearCombination<F> {
         let mut acc = LinearCombination::zero();
 
@@ -570,6 +609,7 @@ impl<F: Field> Combination<F> {
         acc
     }
 
+    /// Allocates the sum as a single variable, unless it is constant.
     pub fn evaluate<CS>(&self, mut cs: CS) -> Result<Num<F>, SynthesisError>
     where
         CS: ConstraintSystem<F>,
@@ -590,6 +630,8 @@ impl<F: Field> Combination<F> {
         }
     }
 
+    /// Allocates the product of this and `other`, using one multiplication
+    /// gate.
     pub fn mul<CS: ConstraintSystem<F>>(
         &self,
         mut cs: CS,
@@ -616,6 +658,7 @@ impl<F: Field> Combination<F> {
         Ok(AllocatedNum { value, var: o })
     }
 
+    /// Allocates the square of this, using one multiplication gate.
     pub fn square<CS: ConstraintSystem<F>>(
         &self,
         mut cs: CS,
@@ -639,6 +682,7 @@ impl<F: Field> Combination<F> {
         Ok(AllocatedNum { value, var: o })
     }
 
+    /// `AllocatedNum::rescue_alpha`, or a constant if this is constant.
     pub fn rescue_alpha<CS>(&self, cs: CS) -> Result<Num<F>, SynthesisError>
     where
         CS: ConstraintSystem<F>,
@@ -654,6 +698,7 @@ impl<F: Field> Combination<F> {
         }
     }
 
+    /// `AllocatedNum::rescue_invalpha`, or a constant if this is constant.
     pub fn rescue_invalpha<CS>(&self, cs: CS) -> Result<Num<F>, SynthesisError>
     where
         CS: ConstraintSystem<F>,
@@ -670,12 +715,12 @@ impl<F: Field> Combination<F> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod test {
     use super::AllocatedNum;
     use crate::{
         circuits::{Circuit, ConstraintSystem, SynthesisError},
-        dev::is_satisfied,
+        dev::{is_satisfied, GadgetCheck},
         fields::Fp,
         Basic,
     };
@@ -751,4 +796,52 @@ mod test {
             Ok(true)
         );
     }
+
+    struct ArithmeticCircuit {
+        a: Fp,
+        b: Fp,
+        product: Fp,
+    }
+
+    impl Circuit<Fp> for ArithmeticCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(self.a))?;
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(self.b))?;
+            let product =
+                AllocatedNum::alloc_input(cs.namespace(|| "product"), || Ok(self.product))?;
+
+            let ab = a.mul(cs.namespace(|| "a * b"), &b)?;
+            cs.enforce_zero(ab.lc() - &product.lc());
+
+            let a_inv = a.invert(cs.namespace(|| "a inverse"))?;
+            let one = a.mul(cs.namespace(|| "a * a inverse"), &a_inv)?;
+            cs.enforce_zero(one.lc() - CS::ONE);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_gadget_check() {
+        let circuit = |a, product| ArithmeticCircuit {
+            a,
+            b: Fp::from(10),
+            product,
+        };
+
+        GadgetCheck::new(circuit(Fp::from(12), Fp::from(120)), &[Fp::from(120)])
+            .malformed(
+                "wrong product",
+                circuit(Fp::from(12), Fp::from(121)),
+                &[Fp::from(121)],
+            )
+            .malformed(
+                "zero has no inverse",
+                circuit(Fp::zero(), Fp::zero()),
+                &[Fp::zero()],
+            )
+            .budget(6, 11)
+            .check::<Basic>()
+            .unwrap();
+    }
 }
@@ -0,0 +1,351 @@
+//! Evaluation and interpolation of univariate polynomials in-circuit, with
+//! native counterparts for computing the expected results.
+//!
+//! Coefficients are in increasing order of degree. The `b` polynomial of the
+//! inner product argument is a structured special case with its own gadget,
+//! `b_poly_eval`.
+
+use super::num::{AllocatedNum, Combination, Num};
+use crate::{circuits::ConstraintSystem, fields::Field, SynthesisError};
+
+/// Evaluates the polynomial with coefficients `coeffs` at `x`.
+pub fn evaluate_native<F: Field>(coeffs: &[F], x: F) -> F {
+    coeffs
+        .iter()
+        .rev()
+        .fold(F::zero(), |acc, coeff| acc * x + *coeff)
+}
+
+/// Evaluates the polynomial with coefficients `coeffs` at `x` by Horner's
+/// rule, using one multiplication per coefficient after the leading one.
+pub fn evaluate<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    coeffs: &[Num<F>],
+    x: Num<F>,
+) -> Result<Num<F>, SynthesisError> {
+    let mut coeffs = coeffs.iter().rev();
+    let mut acc = match coeffs.next() {
+        Some(leading) => Combination::from(*leading),
+        None => return Ok(Num::constant(F::zero())),
+    };
+
+    let x = Combination::from(x);
+    for (i, coeff) in coeffs.enumerate() {
+        let term = acc.mul(cs.namespace(|| format!("horner step {}", i)), &x)?;
+        acc = Combination::from(term) + *coeff;
+    }
+
+    acc.evaluate(cs.namespace(|| "result"))
+}
+
+/// The repeated squarings `[x, x^2, x^4, ..., x^(2^(count - 1))]`, computed
+/// with `count - 1` multiplications. Evaluations at the same point can share
+/// them.
+pub fn squarings<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    x: AllocatedNum<F>,
+    count: usize,
+) -> Result<Vec<AllocatedNum<F>>, SynthesisError> {
+    assert!(count > 0);
+
+    let mut powers = Vec::with_capacity(count);
+    powers.push(x);
+    for i in 1..count {
+        let prev = powers[i - 1];
+        powers.push(prev.mul(cs.namespace(|| format!("x^(2^{})", i)), &prev)?);
+    }
+    Ok(powers)
+}
+
+/// Evaluates the polynomial
+/// `b(X) = prod_i (challenges_inv[i] + challenges[i] X^(2^(k - 1 - i)))`
+/// of an inner product argument with `k` rounds, given the squarings of the
+/// point as computed by `squarings(cs, x, k)`. This is the in-circuit
+/// counterpart of `compute_b`.
+pub fn b_poly_eval<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    powers: &[AllocatedNum<F>],
+    challenges: &[AllocatedNum<F>],
+    challenges_inv: &[AllocatedNum<F>],
+) -> Result<Combination<F>, SynthesisError> {
+    assert!(!challenges.is_empty());
+    assert_eq!(challenges.len(), challenges_inv.len());
+    assert!(powers.len() >= challenges.len());
+
+    let k = challenges.len();
+    let mut acc: Option<Combination<F>> = None;
+    for (i, (challenge, challenge_inv)) in challenges.iter().zip(challenges_inv).enumerate() {
+        let mut cs = cs.namespace(|| format!("round {}", i));
+
+        let term = challenge.mul(cs.namespace(|| "challenge * x^(2^j)"), &powers[k - 1 - i])?;
+        let factor = Combination::from(*challenge_inv) + term;
+
+        acc = Some(match acc {
+            None => factor,
+            Some(acc) => Combination::from(acc.mul(cs.namespace(|| "accumulate"), &factor)?),
+        });
+    }
+
+    Ok(acc.unwrap())
+}
+
+/// The Lagrange basis polynomials of `domain` evaluated at `x`. The points
+/// of `domain` must be distinct.
+pub fn lagrange_coefficients_native<F: Field>(domain: &[F], x: F) -> Vec<F> {
+    denominators(domain)
+        .into_iter()
+        .enumerate()
+        .map(|(i, denominator)| {
+            domain
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .fold(denominator, |acc, (_, point)| acc * (x - *point))
+        })
+        .collect()
+}
+
+/// The inverse of `prod_{j != i} (domain[i] - domain[j])` for each `i`.
+fn denominators<F: Field>(domain: &[F]) -> Vec<F> {
+    domain
+        .iter()
+        .enumerate()
+        .map(|(i, point_i)| {
+            let denominator = domain
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .fold(F::one(), |acc, (_, point_j)| acc * (*point_i - *point_j));
+            let inverse = denominator.invert();
+            assert!(
+                bool::from(inverse.is_some()),
+                "domain points must be distinct"
+            );
+            inverse.unwrap()
+        })
+        .collect()
+}
+
+/// The coefficients of the unique polynomial of degree less than
+/// `domain.len()` taking `values[i]` at `domain[i]`.
+pub fn interpolate_native<F: Field>(domain: &[F], values: &[F]) -> Vec<F> {
+    assert_eq!(domain.len(), values.len());
+
+    let mut coeffs = vec![F::zero(); domain.len()];
+    for (i, (denominator, value)) in denominators(domain).into_iter().zip(values).enumerate() {
+        // Expand prod_{j != i} (X - domain[j]) one factor at a time.
+        let mut basis = vec![F::one()];
+        for (_, point) in domain.iter().enumerate().filter(|&(j, _)| j != i) {
+            let mut next = vec![F::zero(); basis.len() + 1];
+            for (k, coeff) in basis.iter().enumerate() {
+                next[k + 1] += *coeff;
+                next[k] -= *coeff * *point;
+            }
+            basis = next;
+        }
+
+        let scale = denominator * *value;
+        for (acc, coeff) in coeffs.iter_mut().zip(basis) {
+            *acc += coeff * scale;
+        }
+    }
+    coeffs
+}
+
+/// Evaluates at `x` the polynomial taking `values[i]` at the constant point
+/// `domain[i]`, without computing its coefficients. This costs roughly
+/// `domain.len()^2` multiplications, so is intended for small domains.
+pub fn interpolate<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    domain: &[F],
+    values: &[Num<F>],
+    x: Num<F>,
+) -> Result<Num<F>, SynthesisError> {
+    assert_eq!(domain.len(), values.len());
+
+    let x = Combination::from(x);
+    let mut acc = Combination::zero();
+    for (i, (denominator, value)) in denominators(domain).into_iter().zip(values).enumerate() {
+        let mut cs = cs.namespace(|| format!("basis {}", i));
+
+        let mut basis = Combination::from(Num::constant(denominator));
+        for (j, point) in domain.iter().enumerate().filter(|&(j, _)| j != i) {
+            let factor = x.clone() + Num::constant(-*point);
+            basis =
+                Combination::from(basis.mul(cs.namespace(|| format!("factor {}", j)), &factor)?);
+        }
+
+        let term: AllocatedNum<F> =
+            basis.mul(cs.namespace(|| "value"), &Combination::from(*value))?;
+        acc += term;
+    }
+
+    acc.evaluate(cs.namespace(|| "result"))
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::{Circuit, SynthesisError},
+        dev::GadgetCheck,
+        fields::Fp,
+        Basic,
+    };
+
+    struct TestCircuit {
+        domain: Vec<Fp>,
+        coeffs: Vec<Fp>,
+        x: Fp,
+        y: Fp,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut coeffs = vec![];
+            for (i, coeff) in self.coeffs.iter().enumerate() {
+                coeffs.push(
+                    AllocatedNum::alloc(cs.namespace(|| format!("coeff {}", i)), || Ok(*coeff))?
+                        .into(),
+                );
+            }
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(self.x))?;
+            let y = AllocatedNum::alloc_input(cs.namespace(|| "y"), || Ok(self.y))?;
+
+            let evaluation = evaluate(cs.namespace(|| "evaluate"), &coeffs, x.into())?;
+            let evaluation = evaluation.lc(&mut *cs);
+            cs.enforce_zero(evaluation - &y.lc());
+
+            // The interpolant through the evaluations on the domain agrees
+            // with the polynomial everywhere.
+            let mut values = vec![];
+            for (i, point) in self.domain.iter().enumerate() {
+                values.push(evaluate(
+                    cs.namespace(|| format!("value {}", i)),
+                    &coeffs,
+                    Num::constant(*point),
+                )?);
+            }
+            let interpolation = interpolate(
+                cs.namespace(|| "interpolate"),
+                &self.domain,
+                &values,
+                x.into(),
+            )?;
+            let interpolation = interpolation.lc(&mut *cs);
+            cs.enforce_zero(interpolation - &y.lc());
+
+            Ok(())
+        }
+    }
+
+    struct BCircuit {
+        x: Fp,
+        challenges: Vec<Fp>,
+        expected: Fp,
+    }
+
+    impl Circuit<Fp> for BCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(self.x))?;
+            let mut challenges = vec![];
+            let mut challenges_inv = vec![];
+            for (i, c) in self.challenges.iter().enumerate() {
+                let c = AllocatedNum::alloc(cs.namespace(|| format!("c {}", i)), || Ok(*c))?;
+                challenges_inv.push(c.invert(cs.namespace(|| format!("c_inv {}", i)))?);
+                challenges.push(c);
+            }
+            let expected =
+                AllocatedNum::alloc_input(cs.namespace(|| "expected"), || Ok(self.expected))?;
+
+            let powers = squarings(cs.namespace(|| "powers"), x, challenges.len())?;
+            let b = b_poly_eval(cs.namespace(|| "b"), &powers, &challenges, &challenges_inv)?;
+            let b = b.lc(&mut *cs);
+            cs.enforce_zero(b - &expected.lc());
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_b_poly_eval() {
+        let x = Fp::from(5);
+        let challenges: Vec<_> = (2..7).map(Fp::from).collect();
+        let challenges_inv: Vec<_> = challenges.iter().map(|c| c.invert().unwrap()).collect();
+        let expected = crate::util::compute_b(x, &challenges, &challenges_inv);
+
+        for k in 1..=challenges.len() {
+            let expected = crate::util::compute_b(x, &challenges[..k], &challenges_inv[..k]);
+            let circuit = BCircuit {
+                x,
+                challenges: challenges[..k].to_vec(),
+                expected,
+            };
+            GadgetCheck::new(circuit, &[expected])
+                .check::<Basic>()
+                .unwrap();
+        }
+
+        GadgetCheck::new(
+            BCircuit {
+                x,
+                challenges: challenges.clone(),
+                expected,
+            },
+            &[expected],
+        )
+        .malformed(
+            "wrong point",
+            BCircuit {
+                x: Fp::from(6),
+                challenges,
+                expected,
+            },
+            &[expected],
+        )
+        .budget(25, 44)
+        .check::<Basic>()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_native() {
+        let coeffs = vec![Fp::from(3), Fp::from(0), Fp::from(2)];
+        assert_eq!(evaluate_native(&coeffs, Fp::from(5)), Fp::from(53));
+
+        let domain = vec![Fp::from(1), Fp::from(2), Fp::from(4)];
+        let values: Vec<_> = domain
+            .iter()
+            .map(|x| evaluate_native(&coeffs, *x))
+            .collect();
+        assert_eq!(interpolate_native(&domain, &values), coeffs);
+
+        let x = Fp::from(9);
+        let lagrange = lagrange_coefficients_native(&domain, x);
+        let sum = lagrange
+            .iter()
+            .zip(values.iter())
+            .fold(Fp::zero(), |acc, (l, v)| acc + *l * *v);
+        assert_eq!(sum, evaluate_native(&coeffs, x));
+    }
+
+    #[test]
+    fn test_circuit() {
+        let coeffs = vec![Fp::from(7), Fp::from(1), Fp::from(5), Fp::from(2)];
+        let x = Fp::from(11);
+        let y = evaluate_native(&coeffs, x);
+        let circuit = |y| TestCircuit {
+            domain: vec![Fp::from(0), Fp::from(1), Fp::from(3), Fp::from(8)],
+            coeffs: coeffs.clone(),
+            x,
+            y,
+        };
+
+        let wrong = y + Fp::one();
+        GadgetCheck::new(circuit(y), &[y])
+            .malformed("wrong evaluation", circuit(wrong), &[wrong])
+            .budget(38, 72)
+            .check::<Basic>()
+            .unwrap();
+    }
+}
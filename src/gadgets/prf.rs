@@ -0,0 +1,180 @@
+//! Keyed pseudorandom functions built on Rescue, and the key derivation and
+//! nullifier schemes built from them.
+//!
+//! Every evaluation absorbs a prefix identifying the PRF, the caller's domain
+//! and the number of inputs before the key and inputs, so outputs under
+//! different domains or of different arities are independent. The sponge is
+//! at a reduced rate, so that every absorbed element affects the output.
+
+use super::num::{AllocatedNum, Num};
+use super::rescue::RescueGadget;
+use crate::{circuits::ConstraintSystem, fields::Field, rescue::Rescue, SynthesisError};
+
+const PRF_PREFIX: u64 = 0x6861_6c6f_5f70_7266;
+
+/// The domain under which `derive_key` evaluates the PRF.
+pub const DOMAIN_KEY_DERIVATION: u64 = 1;
+
+/// The domain under which `nullifier` evaluates the PRF.
+pub const DOMAIN_NULLIFIER: u64 = 2;
+
+/// Evaluates the PRF keyed by `key` on `input` under `domain`, outside of
+/// the circuit. Applications defining their own domains should avoid the
+/// `DOMAIN_*` constants of this module.
+pub fn prf_native<F: Field>(domain: u64, key: F, input: &[F]) -> F {
+    let mut rescue = Rescue::reduced_rate();
+    rescue.absorb(F::from_u64(PRF_PREFIX));
+    rescue.absorb(F::from_u64(domain));
+    rescue.absorb(F::from_u64(input.len() as u64));
+    rescue.absorb(key);
+    for value in input {
+        rescue.absorb(*value);
+    }
+    rescue.squeeze()
+}
+
+/// Evaluates the PRF keyed by `key` on `input` under `domain`.
+pub fn prf<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    domain: u64,
+    key: Num<F>,
+    input: &[Num<F>],
+) -> Result<AllocatedNum<F>, SynthesisError> {
+    let mut rescue = RescueGadget::reduced_rate(cs.namespace(|| "init Rescue"))?;
+    rescue.absorb(
+        cs.namespace(|| "absorb prefix"),
+        Num::constant(F::from_u64(PRF_PREFIX)),
+    )?;
+    rescue.absorb(
+        cs.namespace(|| "absorb domain"),
+        Num::constant(F::from_u64(domain)),
+    )?;
+    rescue.absorb(
+        cs.namespace(|| "absorb length"),
+        Num::constant(F::from_u64(input.len() as u64)),
+    )?;
+    rescue.absorb(cs.namespace(|| "absorb key"), key)?;
+    for (i, value) in input.iter().enumerate() {
+        rescue.absorb(cs.namespace(|| format!("absorb input {}", i)), *value)?;
+    }
+
+    rescue.squeeze(cs.namespace(|| "squeeze"))
+}
+
+/// Derives the child key at `index` from `parent`, outside of the circuit.
+pub fn derive_key_native<F: Field>(parent: F, index: F) -> F {
+    prf_native(DOMAIN_KEY_DERIVATION, parent, &[index])
+}
+
+/// Derives the child key at `index` from `parent`.
+pub fn derive_key<F: Field, CS: ConstraintSystem<F>>(
+    cs: CS,
+    parent: Num<F>,
+    index: Num<F>,
+) -> Result<AllocatedNum<F>, SynthesisError> {
+    prf(cs, DOMAIN_KEY_DERIVATION, parent, &[index])
+}
+
+/// The nullifier of the note with commitment `note` owned by `secret_key`,
+/// computed outside of the circuit.
+pub fn nullifier_native<F: Field>(secret_key: F, note: F) -> F {
+    prf_native(DOMAIN_NULLIFIER, secret_key, &[note])
+}
+
+/// The nullifier of the note with commitment `note` owned by `secret_key`.
+/// It is deterministic, so spending a note twice reveals the same
+/// nullifier, and can't be linked to the note without the key.
+pub fn nullifier<F: Field, CS: ConstraintSystem<F>>(
+    cs: CS,
+    secret_key: Num<F>,
+    note: Num<F>,
+) -> Result<AllocatedNum<F>, SynthesisError> {
+    prf(cs, DOMAIN_NULLIFIER, secret_key, &[note])
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::{Circuit, SynthesisError},
+        dev::GadgetCheck,
+        fields::Fp,
+        Basic,
+    };
+
+    struct TestCircuit {
+        key: Fp,
+        index: Fp,
+        note: Fp,
+        nullifier: Fp,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let key = AllocatedNum::alloc(cs.namespace(|| "key"), || Ok(self.key))?;
+            let index = AllocatedNum::alloc(cs.namespace(|| "index"), || Ok(self.index))?;
+            let note = AllocatedNum::alloc(cs.namespace(|| "note"), || Ok(self.note))?;
+            let expected =
+                AllocatedNum::alloc_input(cs.namespace(|| "nullifier"), || Ok(self.nullifier))?;
+
+            let child = derive_key(cs.namespace(|| "derive"), key.into(), index.into())?;
+            let nf = nullifier(cs.namespace(|| "nullifier"), child.into(), note.into())?;
+            cs.enforce_zero(nf.lc() - &expected.lc());
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_domain_separation() {
+        let key = Fp::from(7);
+        let input = Fp::from(11);
+
+        assert!(derive_key_native(key, input) != nullifier_native(key, input));
+        assert!(prf_native(3, key, &[input]) != prf_native(4, key, &[input]));
+        assert!(prf_native(3, key, &[]) != prf_native(3, key, &[Fp::zero()]));
+        assert!(nullifier_native(key, input) != nullifier_native(Fp::from(8), input));
+
+        // The prefix separates the PRF from other digests of the same
+        // elements.
+        let mut unprefixed = Rescue::reduced_rate();
+        for value in &[Fp::from(3), Fp::one(), key, input] {
+            unprefixed.absorb(*value);
+        }
+        assert!(prf_native(3, key, &[input]) != unprefixed.squeeze());
+    }
+
+    #[test]
+    fn test_every_input() {
+        let key = Fp::from(7);
+        let input: Vec<_> = (0..30).map(Fp::from_u64).collect();
+        let output = prf_native(5, key, &input);
+        for i in 0..input.len() {
+            let mut changed = input.clone();
+            changed[i] += Fp::one();
+            assert!(output != prf_native(5, key, &changed));
+        }
+    }
+
+    #[test]
+    fn test_nullifier_circuit() {
+        let key = Fp::from(7);
+        let index = Fp::from(2);
+        let note = Fp::from(123);
+        let expected = nullifier_native(derive_key_native(key, index), note);
+
+        let circuit = |key, note| TestCircuit {
+            key,
+            index,
+            note,
+            nullifier: expected,
+        };
+
+        GadgetCheck::new(circuit(key, note), &[expected])
+            .malformed("wrong key", circuit(Fp::from(8), note), &[expected])
+            .malformed("wrong note", circuit(key, Fp::from(124)), &[expected])
+            .budget(1535, 3067)
+            .check::<Basic>()
+            .unwrap();
+    }
+}
@@ -1,8 +1,10 @@
 use crate::{
     circuits::{Coeff, ConstraintSystem, SynthesisError},
     fields::Field,
+    gadgets::boolean::{unpack_fe, AllocatedBit},
     gadgets::num::{AllocatedNum, Combination, Num},
-    rescue::{generate_mds_matrix, RESCUE_M, RESCUE_ROUNDS, SPONGE_RATE},
+    replay,
+    rescue::{RescueConstants, RESCUE_M, RESCUE_ROUNDS, SPONGE_RATE},
 };
 use std::ops::AddAssign;
 
@@ -43,96 +45,6 @@ fn rescue_f<F: Field, CS: ConstraintSystem<F>>(
     Ok(())
 }
 
-/// Duplicates [`rescue_f`] in order to extract the key schedule.
-fn generate_key_schedule<F: Field, CS: ConstraintSystem<F>>(
-    mut cs: CS,
-    master_key: [Num<F>; RESCUE_M],
-    mds_matrix: &[[F; RESCUE_M]; RESCUE_M],
-) -> Result<[[Num<F>; RESCUE_M]; 2 * RESCUE_ROUNDS + 1], SynthesisError> {
-    // TODO: Generate correct constants
-    let constants = [[Num::constant(F::one()); RESCUE_M]; 2 * RESCUE_ROUNDS + 1];
-
-    let mut key_schedule = vec![];
-
-    let mut state: Vec<_> = master_key
-        .iter()
-        .zip(constants[0].iter())
-        .map(|(e, k)| Combination::from(*e) + *k)
-        .collect();
-    key_schedule.push(
-        state
-            .iter()
-            .enumerate()
-            .map(|(i, c)| c.evaluate(cs.namespace(|| format!("key [0, {}]", i))))
-            .collect::<Result<Vec<_>, _>>()?,
-    );
-
-    for r in 0..2 * RESCUE_ROUNDS {
-        let mut mid = vec![];
-        for entry in state.iter() {
-            if r % 2 == 0 {
-                mid.push(
-                    entry.rescue_invalpha(cs.namespace(|| format!("round {} s-box 1", r / 2)))?,
-                );
-            } else {
-                mid.push(entry.rescue_alpha(cs.namespace(|| format!("round {} s-box 2", r / 2)))?);
-            };
-        }
-
-        for (next_entry, (mds_row, constant)) in state
-            .iter_mut()
-            .zip(mds_matrix.iter().zip(constants[r + 1].iter()))
-        {
-            let mut sum = Combination::from(Num::constant(F::zero()));
-            for (coeff, entry) in mds_row.iter().zip(mid.iter()) {
-                sum = sum + (Coeff::Full(*coeff), *entry);
-            }
-            *next_entry = sum + *constant;
-        }
-
-        key_schedule.push(
-            state
-                .iter()
-                .enumerate()
-                .map(|(i, c)| c.evaluate(cs.namespace(|| format!("key [{}, {}]", r + 1, i))))
-                .collect::<Result<Vec<_>, _>>()?,
-        );
-    }
-
-    let key_schedule: Vec<_> = key_schedule
-        .into_iter()
-        .map(|k| {
-            [
-                k[0], k[1], k[2], k[3], k[4], k[5], k[6], k[7], k[8], k[9], k[10], k[11], k[12],
-            ]
-        })
-        .collect();
-
-    Ok([
-        key_schedule[0],
-        key_schedule[1],
-        key_schedule[2],
-        key_schedule[3],
-        key_schedule[4],
-        key_schedule[5],
-        key_schedule[6],
-        key_schedule[7],
-        key_schedule[8],
-        key_schedule[9],
-        key_schedule[10],
-        key_schedule[11],
-        key_schedule[12],
-        key_schedule[13],
-        key_schedule[14],
-        key_schedule[15],
-        key_schedule[16],
-        key_schedule[17],
-        key_schedule[18],
-        key_schedule[19],
-        key_schedule[20],
-    ])
-}
-
 fn pad<F: Field, CS: ConstraintSystem<F>>(
     mut cs: CS,
     input: &[Option<Num<F>>; SPONGE_RATE],
@@ -184,9 +96,22 @@ enum SpongeState<F: Field> {
 }
 
 impl<F: Field> SpongeState<F> {
-    fn absorb(val: Num<F>) -> Self {
+    fn empty(reduced_rate: bool) -> Self {
+        let mut input = [None; SPONGE_RATE];
+        if reduced_rate {
+            input[0] = Some(Num::constant(F::zero()));
+        }
+        SpongeState::Absorbing(input)
+    }
+
+    fn absorb(val: Num<F>, reduced_rate: bool) -> Self {
         let mut input = [None; SPONGE_RATE];
-        input[0] = Some(val);
+        if reduced_rate {
+            input[0] = Some(Num::constant(F::zero()));
+            input[1] = Some(val);
+        } else {
+            input[0] = Some(val);
+        }
         SpongeState::Absorbing(input)
     }
 }
@@ -196,10 +121,20 @@ pub struct RescueGadget<F: Field> {
     state: [Combination<F>; RESCUE_M],
     mds_matrix: [[F; RESCUE_M]; RESCUE_M],
     key_schedule: [[Num<F>; RESCUE_M]; 2 * RESCUE_ROUNDS + 1],
+    reduced_rate: bool,
 }
 
 impl<F: Field> RescueGadget<F> {
     pub fn new<CS: ConstraintSystem<F>>(cs: CS) -> Result<Self, SynthesisError> {
+        Self::with_constants(cs, &RescueConstants::standard())
+    }
+
+    /// Creates a sponge whose permutation uses `constants`, matching
+    /// `Rescue::with_constants`.
+    pub fn with_constants<CS: ConstraintSystem<F>>(
+        _cs: CS,
+        constants: &RescueConstants<F>,
+    ) -> Result<Self, SynthesisError> {
         let state = [
             Num::constant(F::zero()).into(),
             Num::constant(F::zero()).into(),
@@ -215,17 +150,29 @@ impl<F: Field> RescueGadget<F> {
             Num::constant(F::zero()).into(),
             Num::constant(F::zero()).into(),
         ];
-        let mds_matrix = generate_mds_matrix();
-
-        // To use Rescue as a permutation, fix the master key to zero
-        let key_schedule =
-            generate_key_schedule(cs, [Num::constant(F::zero()); RESCUE_M], &mds_matrix)?;
+        let mut key_schedule = [[Num::constant(F::zero()); RESCUE_M]; 2 * RESCUE_ROUNDS + 1];
+        for (round, keys) in key_schedule.iter_mut().zip(constants.key_schedule()) {
+            for (key, value) in round.iter_mut().zip(keys) {
+                *key = Num::constant(*value);
+            }
+        }
 
         Ok(RescueGadget {
-            sponge: SpongeState::Absorbing([None; SPONGE_RATE]),
+            sponge: SpongeState::empty(false),
             state,
-            mds_matrix,
+            mds_matrix: *constants.mds_matrix(),
             key_schedule,
+            reduced_rate: false,
+        })
+    }
+
+    /// Creates a sponge that leaves the first element of each block zero,
+    /// matching `Rescue::reduced_rate`.
+    pub fn reduced_rate<CS: ConstraintSystem<F>>(cs: CS) -> Result<Self, SynthesisError> {
+        Ok(RescueGadget {
+            sponge: SpongeState::empty(true),
+            reduced_rate: true,
+            ..Self::new(cs)?
         })
     }
 
@@ -234,6 +181,9 @@ impl<F: Field> RescueGadget<F> {
         cs: CS,
         val: Num<F>,
     ) -> Result<(), SynthesisError> {
+        if let Some(value) = val.value() {
+            replay::note_absorb(&value);
+        }
         match self.sponge {
             SpongeState::Absorbing(ref mut input) => {
                 for entry in input.iter_mut() {
@@ -251,11 +201,11 @@ impl<F: Field> RescueGadget<F> {
                     &self.mds_matrix,
                     &self.key_schedule,
                 )?;
-                self.sponge = SpongeState::absorb(val);
+                self.sponge = SpongeState::absorb(val, self.reduced_rate);
             }
             SpongeState::Squeezing(_) => {
                 // Drop the remaining output elements
-                self.sponge = SpongeState::absorb(val);
+                self.sponge = SpongeState::absorb(val, self.reduced_rate);
             }
         }
 
@@ -295,27 +245,86 @@ impl<F: Field> RescueGadget<F> {
                             // combinations.
                             *entry = out.into();
 
+                            if let Some(value) = out.get_value() {
+                                replay::note_squeeze(&value);
+                            }
                             return Ok(out);
                         }
                     }
 
                     // We've already squeezed out all available elements
-                    self.sponge = SpongeState::Absorbing([None; SPONGE_RATE]);
+                    self.sponge = SpongeState::empty(self.reduced_rate);
                 }
             }
         }
     }
+
+    /// Absorbs each of `inputs` in order into a fresh sponge at a reduced
+    /// rate and squeezes a single element, matching `Rescue::hash_to_field`.
+    pub fn hash_to_field<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        inputs: &[Num<F>],
+    ) -> Result<AllocatedNum<F>, SynthesisError> {
+        let mut rescue = Self::reduced_rate(cs.namespace(|| "init Rescue"))?;
+        for (i, input) in inputs.iter().enumerate() {
+            rescue.absorb(cs.namespace(|| format!("absorb {}", i)), *input)?;
+        }
+        rescue.squeeze(cs.namespace(|| "squeeze"))
+    }
+
+    /// Creates a sponge that has absorbed the 256 bits of `digest`, ordered
+    /// as payload bits are, matching `Rescue::from_digest`.
+    ///
+    /// The transcript of the verification circuit needs no such seed: its
+    /// first absorption is `k_commitment`, which already commits to the
+    /// payload. This is for applications whose own Fiat-Shamir transcript
+    /// must run across the steps of a chain.
+    pub fn from_digest<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        digest: &[AllocatedBit],
+    ) -> Result<Self, SynthesisError> {
+        assert_eq!(digest.len(), 256);
+
+        let mut rescue = Self::reduced_rate(cs.namespace(|| "init Rescue"))?;
+        for (i, half) in digest.chunks(128).enumerate() {
+            let mut packed = Combination::zero();
+            let mut coeff = F::one();
+            for bit in half {
+                packed = packed + (Coeff::Full(coeff), AllocatedNum::from(bit.clone()));
+                coeff = coeff + coeff;
+            }
+            let packed = packed.evaluate(cs.namespace(|| format!("pack half {}", i)))?;
+            rescue.absorb(cs.namespace(|| format!("absorb half {}", i)), packed)?;
+        }
+        Ok(rescue)
+    }
+
+    /// Squeezes a digest as 256 bits, the low 128 bits of each of the next
+    /// two elements, matching `Rescue::squeeze_digest`.
+    pub fn squeeze_digest<CS: ConstraintSystem<F>>(
+        &mut self,
+        mut cs: CS,
+    ) -> Result<Vec<AllocatedBit>, SynthesisError> {
+        let mut digest = Vec::with_capacity(256);
+        for i in 0..2 {
+            let num = self.squeeze(cs.namespace(|| format!("squeeze half {}", i)))?;
+            let mut bits = unpack_fe(cs.namespace(|| format!("unpack half {}", i)), &num.into())?;
+            bits.truncate(128);
+            digest.extend(bits);
+        }
+        Ok(digest)
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod test {
     use super::RescueGadget;
     use crate::{
         circuits::{Circuit, ConstraintSystem, SynthesisError},
-        dev::is_satisfied,
+        dev::{is_satisfied, GadgetCheck},
         fields::Fp,
-        gadgets::AllocatedNum,
-        rescue::{Rescue, SPONGE_RATE},
+        gadgets::{AllocatedBit, AllocatedNum, Num},
+        rescue::{Rescue, RescueConstants, SPONGE_RATE},
         Basic,
     };
 
@@ -385,6 +394,70 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_constants() {
+        // Crosses a block boundary in both directions.
+        struct TestCircuit {
+            constants: RescueConstants<Fp>,
+        }
+
+        impl Circuit<Fp> for TestCircuit {
+            fn synthesize<CS: ConstraintSystem<Fp>>(
+                &self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let mut g =
+                    RescueGadget::with_constants(cs.namespace(|| "init Rescue"), &self.constants)?;
+                for i in 0..(SPONGE_RATE + 2) {
+                    let n = AllocatedNum::alloc(cs.namespace(|| format!("n {}", i)), || {
+                        Ok(Fp::from(i as u64 + 1))
+                    })?;
+                    g.absorb(cs.namespace(|| format!("absorb {}", i)), n.into())?;
+                }
+                for i in 0..(SPONGE_RATE + 1) {
+                    let s = g.squeeze(cs.namespace(|| format!("squeeze {}", i)))?;
+                    let expected = AllocatedNum::alloc_input(
+                        cs.namespace(|| format!("expected {}", i)),
+                        || s.get_value().ok_or(SynthesisError::AssignmentMissing),
+                    )?;
+                    cs.enforce_zero(expected.lc() - &s.lc());
+                }
+
+                Ok(())
+            }
+        }
+
+        let seeded = RescueConstants::<Fp>::from_seed(b"test seed");
+        assert_eq!(seeded, RescueConstants::from_seed(b"test seed"));
+        assert_ne!(seeded, RescueConstants::from_seed(b"other seed"));
+        assert_ne!(seeded, RescueConstants::standard());
+        assert_eq!(RescueConstants::<Fp>::default(), RescueConstants::standard());
+
+        for constants in [RescueConstants::standard(), seeded.clone()] {
+            let mut r = Rescue::with_constants(constants.clone());
+            for i in 0..(SPONGE_RATE + 2) {
+                r.absorb(Fp::from(i as u64 + 1));
+            }
+            let expected: Vec<_> = (0..(SPONGE_RATE + 1)).map(|_| r.squeeze()).collect();
+
+            assert_eq!(
+                is_satisfied::<_, _, Basic>(&TestCircuit { constants }, &expected),
+                Ok(true)
+            );
+        }
+
+        // Unlike the standard matrix, the derived one mixes in every element.
+        let first = |constants: &RescueConstants<Fp>, x| {
+            let mut r = Rescue::with_constants(constants.clone());
+            r.absorb(x);
+            r.absorb(Fp::one());
+            r.squeeze()
+        };
+        let standard = RescueConstants::standard();
+        assert_eq!(first(&standard, Fp::one()), first(&standard, Fp::zero()));
+        assert_ne!(first(&seeded, Fp::one()), first(&seeded, Fp::zero()));
+    }
+
     #[test]
     fn saturate_sponge() {
         struct TestCircuit {
@@ -489,4 +562,189 @@ mod test {
             Ok(true)
         );
     }
+
+    struct HashCircuit {
+        a: Fp,
+        b: Fp,
+        expected: Fp,
+    }
+
+    impl Circuit<Fp> for HashCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(self.a))?;
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(self.b))?;
+            let expected =
+                AllocatedNum::alloc_input(cs.namespace(|| "expected"), || Ok(self.expected))?;
+
+            let mut g = RescueGadget::new(cs.namespace(|| "init Rescue"))?;
+            g.absorb(cs.namespace(|| "absorb a"), a.into())?;
+            g.absorb(cs.namespace(|| "absorb b"), b.into())?;
+            let s = g.squeeze(cs.namespace(|| "squeeze"))?;
+            cs.enforce_zero(s.lc() - &expected.lc());
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_gadget_check() {
+        let mut r = Rescue::new();
+        r.absorb(Fp::from(5));
+        r.absorb(Fp::from(7));
+        let expected = r.squeeze();
+
+        // Only the second element is varied: with the placeholder MDS matrix
+        // the first element of each absorbed block never reaches the output
+        // (see `generate_mds_matrix`).
+        GadgetCheck::new(
+            HashCircuit {
+                a: Fp::from(5),
+                b: Fp::from(7),
+                expected,
+            },
+            &[expected],
+        )
+        .malformed(
+            "wrong preimage",
+            HashCircuit {
+                a: Fp::from(5),
+                b: Fp::from(8),
+                expected,
+            },
+            &[expected],
+        )
+        .budget(780, 1559)
+        .check::<Basic>()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_hash_to_field() {
+        struct TestCircuit {
+            inputs: Vec<Fp>,
+            expected: Fp,
+        }
+
+        impl Circuit<Fp> for TestCircuit {
+            fn synthesize<CS: ConstraintSystem<Fp>>(
+                &self,
+                cs: &mut CS,
+            ) -> Result<(), SynthesisError> {
+                let mut inputs = vec![];
+                for (i, input) in self.inputs.iter().enumerate() {
+                    inputs.push(
+                        AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*input))?
+                            .into(),
+                    );
+                }
+                let expected =
+                    AllocatedNum::alloc_input(cs.namespace(|| "expected"), || Ok(self.expected))?;
+
+                let digest = RescueGadget::hash_to_field(cs.namespace(|| "hash"), &inputs)?;
+                cs.enforce_zero(digest.lc() - &expected.lc());
+
+                Ok(())
+            }
+        }
+
+        // Enough inputs to span more than one absorbed block.
+        let inputs: Vec<_> = (0..(SPONGE_RATE as u64 + 3)).map(Fp::from).collect();
+        let mut r = Rescue::reduced_rate();
+        for input in &inputs {
+            r.absorb(*input);
+        }
+        let expected = r.squeeze();
+        assert_eq!(Rescue::hash_to_field(&inputs), expected);
+
+        // Every input affects the result, whichever block it falls in.
+        for i in 0..inputs.len() {
+            let mut changed = inputs.clone();
+            changed[i] += Fp::one();
+            assert!(Rescue::hash_to_field(&changed) != expected);
+        }
+
+        assert_eq!(
+            is_satisfied::<_, _, Basic>(&TestCircuit { inputs, expected }, &[expected]),
+            Ok(true)
+        );
+    }
+
+    struct DigestCircuit {
+        digest: [u8; 32],
+        expected: [u8; 32],
+    }
+
+    impl Circuit<Fp> for DigestCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut alloc_bits = |name: &str, bytes: &[u8; 32]| {
+                (0..256)
+                    .map(|i| {
+                        AllocatedBit::alloc(cs.namespace(|| format!("{} bit {}", name, i)), || {
+                            Ok((bytes[i / 8] >> (i % 8)) & 1 == 1)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            let digest = alloc_bits("digest", &self.digest)?;
+            let expected = alloc_bits("expected", &self.expected)?;
+
+            let mut g = RescueGadget::from_digest(cs.namespace(|| "resume"), &digest)?;
+            g.absorb(cs.namespace(|| "absorb 7"), Num::constant(Fp::from(7)))?;
+            let squeezed = g.squeeze_digest(cs.namespace(|| "squeeze digest"))?;
+            for (a, b) in squeezed.iter().zip(expected.iter()) {
+                cs.enforce_zero(a.lc() - &b.lc());
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_digest() {
+        let mut digest = [0u8; 32];
+        for (i, byte) in digest.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let expected = |digest: &[u8; 32]| {
+            let mut r = Rescue::<Fp>::from_digest(digest);
+            r.absorb(Fp::from(7));
+            r.squeeze_digest()
+        };
+
+        // The sponge is at a reduced rate, so both halves of the digest
+        // reach the output.
+        let mut tampered = digest;
+        tampered[20] ^= 1;
+        assert!(expected(&tampered) != expected(&digest));
+        let mut tampered_low = digest;
+        tampered_low[0] ^= 1;
+        assert!(expected(&tampered_low) != expected(&digest));
+
+        GadgetCheck::new(
+            DigestCircuit {
+                digest,
+                expected: expected(&digest),
+            },
+            &[],
+        )
+        .malformed(
+            "wrong digest",
+            DigestCircuit {
+                digest: tampered,
+                expected: expected(&digest),
+            },
+            &[],
+        )
+        .malformed(
+            "wrong first half of digest",
+            DigestCircuit {
+                digest: tampered_low,
+                expected: expected(&digest),
+            },
+            &[],
+        )
+        .budget(1798, 3854)
+        .check::<Basic>()
+        .unwrap();
+    }
 }
@@ -0,0 +1,264 @@
+//! Headers of Ethereum RLP items.
+//!
+//! The first byte of an item determines its kind and length:
+//!
+//! - `0x00..=0x7f`: a single byte, which is the payload.
+//! - `0x80..=0xb7`: a string of up to 55 bytes.
+//! - `0xb8..=0xbf`: a string whose length follows in 1 to 8 big-endian bytes.
+//! - `0xc0..=0xf7` and `0xf8..=0xff`: the same for lists.
+//!
+//! Only canonical encodings are accepted: a byte below `0x80` cannot be
+//! encoded as a string of length one, and a long length cannot be used for
+//! fewer than 56 bytes or have leading zero bytes. Lengths of long items are
+//! limited to 4 bytes.
+
+use super::bytes::{boolean, byte, enforce_false, pack, select};
+use super::{Boolean, Combination};
+use crate::circuits::{Coeff, ConstraintSystem, LinearCombination, SynthesisError};
+use crate::fields::Field;
+
+/// The number of bytes a long item's length may occupy.
+pub const MAX_LENGTH_BYTES: usize = 4;
+
+/// The decoded header of an item.
+pub struct RlpHeader<F: Field> {
+    /// Whether the item is a list rather than a string.
+    pub list: Boolean,
+    /// The number of bytes before the payload: zero for a single byte.
+    pub header_len: Combination<F>,
+    /// The number of bytes of the payload.
+    pub payload_len: Combination<F>,
+}
+
+impl<F: Field> RlpHeader<F> {
+    /// Constrains the item, header and payload, to be `len` bytes long.
+    pub fn enforce_len<CS: ConstraintSystem<F>>(&self, mut cs: CS, len: usize) {
+        let lc = self.header_len.lc(&mut cs) + &self.payload_len.lc(&mut cs)
+            - (Coeff::Full(F::from_u64(len as u64)), CS::ONE);
+        cs.enforce_zero(lc);
+    }
+}
+
+fn or<F: Field, CS: ConstraintSystem<F>>(
+    cs: CS,
+    a: &Boolean,
+    b: &Boolean,
+) -> Result<Boolean, SynthesisError> {
+    Ok(Boolean::and(cs, &a.not(), &b.not())?.not())
+}
+
+/// Decodes the header of the item at the start of `bytes`.
+pub fn decode_header<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    bytes: &[Boolean],
+) -> Result<RlpHeader<F>, SynthesisError> {
+    let prefix = byte(bytes, 0);
+    let first = byte(bytes, 1);
+
+    let single = prefix[7].not();
+    let list = Boolean::and(cs.namespace(|| "list"), &prefix[7], &prefix[6])?;
+    let long = {
+        let mut cs = cs.namespace(|| "long");
+        let high = Boolean::and(cs.namespace(|| "5 and 4"), &prefix[5], &prefix[4])?;
+        let high = Boolean::and(cs.namespace(|| "5 and 4 and 3"), &high, &prefix[3])?;
+        Boolean::and(cs.namespace(|| "not single"), &high, &prefix[7])?
+    };
+    let short = Boolean::and(cs.namespace(|| "short"), &prefix[7], &long.not())?;
+
+    // The length of a long item occupies one more byte than the low three
+    // bits of the prefix give, so at most four when the third is clear.
+    let too_long = Boolean::and(cs.namespace(|| "long and bit 2"), &long, &prefix[2])?;
+    enforce_false(cs.namespace(|| "length fits"), &too_long)?;
+    let bit_0_or_1 = or(cs.namespace(|| "bit 0 or 1"), &prefix[0], &prefix[1])?;
+    let bit_0_and_1 = Boolean::and(cs.namespace(|| "bit 0 and 1"), &prefix[0], &prefix[1])?;
+    let has_length_byte: [Boolean; MAX_LENGTH_BYTES] = [
+        long.clone(),
+        Boolean::and(cs.namespace(|| "has length byte 1"), &long, &bit_0_or_1)?,
+        Boolean::and(cs.namespace(|| "has length byte 2"), &long, &prefix[1])?,
+        Boolean::and(cs.namespace(|| "has length byte 3"), &long, &bit_0_and_1)?,
+    ];
+
+    // Accumulate the big-endian length, leaving it unchanged once the
+    // length bytes have ended.
+    let mut long_len = Combination::zero();
+    for (i, has_byte) in has_length_byte.iter().enumerate() {
+        let shifted = long_len.clone().scale(F::from_u64(255)) + pack(&byte(bytes, i + 1));
+        long_len = long_len
+            + select(
+                cs.namespace(|| format!("length byte {}", i)),
+                has_byte,
+                &shifted,
+            )?;
+    }
+
+    let header_len = boolean(&prefix[7])
+        + boolean(&long)
+        + select(
+            cs.namespace(|| "length of length"),
+            &long,
+            &pack(&prefix[..3]),
+        )?;
+    let payload_len = boolean(&single)
+        + select(cs.namespace(|| "short length"), &short, &pack(&prefix[..6]))?
+        + long_len;
+
+    // A single byte below 0x80 must be encoded as itself.
+    {
+        let mut cs = cs.namespace(|| "canonical single byte");
+        let mut is_0x81 = prefix[0].clone();
+        for (i, bit) in prefix.iter().enumerate().skip(1) {
+            let bit = if i == 7 { bit.clone() } else { bit.not() };
+            is_0x81 = Boolean::and(cs.namespace(|| format!("bit {}", i)), &is_0x81, &bit)?;
+        }
+        let small = Boolean::and(cs.namespace(|| "small"), &is_0x81, &first[7].not())?;
+        enforce_false(
+            cs.namespace(|| "0x81 followed by a byte below 0x80"),
+            &small,
+        )?;
+    }
+
+    // A long length has no leading zero byte and is at least 56.
+    {
+        let mut cs = cs.namespace(|| "canonical long length");
+        let first_value = pack::<F>(&first);
+        let inverse = match (long.get_value(), first_value.get_value()) {
+            (Some(true), Some(v)) => Some(Option::from(v.invert()).unwrap_or_else(F::zero)),
+            (Some(false), _) => Some(F::zero()),
+            _ => None,
+        };
+        let (a, _, c) = cs.multiply(
+            || "first length byte * inverse = long",
+            || {
+                let first = first_value
+                    .get_value()
+                    .ok_or(SynthesisError::AssignmentMissing)?;
+                let inverse = inverse.ok_or(SynthesisError::AssignmentMissing)?;
+                Ok((first, inverse, first * inverse))
+            },
+        )?;
+        let lc = first_value.lc(&mut cs);
+        cs.enforce_zero(lc - a);
+        let lc = boolean::<F>(&long).lc(&mut cs);
+        cs.enforce_zero(LinearCombination::from(c) - &lc);
+
+        let at_least_56 = {
+            let high = Boolean::and(cs.namespace(|| "5 and 4"), &first[5], &first[4])?;
+            let high = Boolean::and(cs.namespace(|| "5 and 4 and 3"), &high, &first[3])?;
+            let high = or(cs.namespace(|| "or 6"), &high, &first[6])?;
+            or(cs.namespace(|| "or 7"), &high, &first[7])?
+        };
+        let one_byte = Boolean::and(
+            cs.namespace(|| "one length byte"),
+            &long,
+            &has_length_byte[1].not(),
+        )?;
+        let below_56 = Boolean::and(cs.namespace(|| "below 56"), &one_byte, &at_least_56.not())?;
+        enforce_false(cs.namespace(|| "one length byte below 56"), &below_56)?;
+    }
+
+    Ok(RlpHeader {
+        list,
+        header_len,
+        payload_len,
+    })
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::Circuit,
+        dev::{is_satisfied, GadgetCheck},
+        fields::Fp,
+        gadgets::bytes::alloc_bytes,
+        Basic,
+    };
+
+    struct HeaderCircuit {
+        bytes: Vec<u8>,
+        list: bool,
+        header_len: usize,
+        payload_len: usize,
+    }
+
+    impl Circuit<Fp> for HeaderCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let bytes = alloc_bytes(cs, &self.bytes)?;
+            let header = decode_header(cs.namespace(|| "header"), &bytes)?;
+
+            Boolean::enforce_equal(
+                cs.namespace(|| "list"),
+                &header.list,
+                &Boolean::constant(self.list),
+            )?;
+            let lc = header.header_len.lc(&mut *cs)
+                - (Coeff::Full(Fp::from(self.header_len as u64)), CS::ONE);
+            cs.enforce_zero(lc);
+            header.enforce_len(cs.namespace(|| "len"), self.header_len + self.payload_len);
+
+            Ok(())
+        }
+    }
+
+    fn header(bytes: &[u8], list: bool, header_len: usize, payload_len: usize) -> HeaderCircuit {
+        HeaderCircuit {
+            bytes: bytes.to_vec(),
+            list,
+            header_len,
+            payload_len,
+        }
+    }
+
+    #[test]
+    fn test_canonical_headers() {
+        for circuit in &[
+            header(&[0x05, 0x80], false, 0, 1),
+            header(&[0x80], false, 1, 0),
+            header(&[0x81, 0x80], false, 1, 1),
+            header(&[0x83, b'd', b'o', b'g'], false, 1, 3),
+            header(&[0xb7], false, 1, 55),
+            header(&[0xb8, 0x38], false, 2, 56),
+            header(&[0xb9, 0x01, 0x00], false, 3, 256),
+            header(&[0xc0], true, 1, 0),
+            header(&[0xf7], true, 1, 55),
+            header(&[0xf8, 0xff], true, 2, 255),
+            header(&[0xfb, 0x01, 0x02, 0x03, 0x04], true, 5, 0x0102_0304),
+        ] {
+            assert_eq!(
+                is_satisfied::<_, _, Basic>(circuit, &[]),
+                Ok(true),
+                "{:02x?}",
+                circuit.bytes
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejected_headers() {
+        GadgetCheck::new(header(&[0xb8, 0x38], false, 2, 56), &[])
+            .malformed("wrong length", header(&[0xb8, 0x38], false, 2, 57), &[])
+            .malformed("wrong kind", header(&[0xb8, 0x38], true, 2, 56), &[])
+            .malformed(
+                "long form below 56",
+                header(&[0xb8, 0x37], false, 2, 55),
+                &[],
+            )
+            .malformed(
+                "leading zero",
+                header(&[0xb9, 0x00, 0x40], false, 3, 64),
+                &[],
+            )
+            .malformed(
+                "length too long",
+                header(&[0xbc, 0, 0, 0, 0, 0x40], false, 6, 64),
+                &[],
+            )
+            .malformed(
+                "single byte as string",
+                header(&[0x81, 0x7f], false, 1, 1),
+                &[],
+            )
+            .check::<Basic>()
+            .unwrap();
+    }
+}
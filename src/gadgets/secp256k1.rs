@@ -0,0 +1,453 @@
+//! Arithmetic on the secp256k1 curve `y^2 = x^3 + 7`, whose base field is
+//! foreign to both fields of the cycle.
+//!
+//! Points are affine and the identity cannot be represented, so addition
+//! requires the points to have distinct `x` coordinates, and is constrained
+//! to. Scalar multiplication starts from a fixed offset point and removes it
+//! at the end, so that the accumulator is never the identity; it fails only
+//! if the scalar is zero or an intermediate sum is exceptional, which an
+//! honest prover hits with negligible probability. Each bit of a scalar
+//! costs about twenty-five thousand gates.
+
+use super::bignum::{words, ForeignElement, ForeignModulus};
+use super::Boolean;
+use crate::circuits::{ConstraintSystem, SynthesisError};
+use crate::fields::Field;
+
+/// The base field of secp256k1, of order `2^256 - 2^32 - 977`.
+pub struct Secp256k1Base;
+
+impl ForeignModulus for Secp256k1Base {
+    type Words = [u64; 4];
+    const MODULUS: [u64; 4] = [
+        0xffff_fffe_ffff_fc2f,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+    ];
+}
+
+pub type Secp256k1Element<F> = ForeignElement<F, Secp256k1Base>;
+
+/// Affine coordinates, as little-endian 64-bit words.
+pub type AffinePoint = ([u64; 4], [u64; 4]);
+
+/// The standard generator.
+pub const GENERATOR: AffinePoint = (
+    [
+        0x59f2_815b_16f8_1798,
+        0x029b_fcdb_2dce_28d9,
+        0x55a0_6295_ce87_0b07,
+        0x79be_667e_f9dc_bbac,
+    ],
+    [
+        0x9c47_d08f_fb10_d4b8,
+        0xfd17_b448_a685_5419,
+        0x5da4_fbfc_0e11_08a8,
+        0x483a_da77_26a3_c465,
+    ],
+);
+
+/// Native arithmetic, for computing witnesses.
+mod native {
+    use super::*;
+
+    fn p() -> Vec<u64> {
+        Secp256k1Base::MODULUS.to_vec()
+    }
+
+    pub fn add(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+        words::add_mod(a, b, &p())
+    }
+
+    pub fn sub(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+        words::sub_mod(a, b, &p())
+    }
+
+    pub fn mul(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+        words::mul_mod(a, b, &p())
+    }
+
+    pub fn invert(a: &[u64; 4]) -> [u64; 4] {
+        words::invert_mod(a, &p())
+    }
+
+    pub fn sqrt(a: &[u64; 4]) -> Option<[u64; 4]> {
+        // p = 3 (mod 4)
+        let exponent = words::divmod(&words::add(&p(), &[1]), &[4]).0;
+        let root = words::to_array(&words::pow_mod(a, &exponent, &p()));
+        if mul(&root, &root) == *a {
+            Some(root)
+        } else {
+            None
+        }
+    }
+
+    // The sum of points with distinct x coordinates, given the slope of
+    // the line through them.
+    fn chord(p: &AffinePoint, q: &AffinePoint, slope: [u64; 4]) -> AffinePoint {
+        let x = sub(&sub(&mul(&slope, &slope), &p.0), &q.0);
+        let y = sub(&mul(&slope, &sub(&p.0, &x)), &p.1);
+        (x, y)
+    }
+
+    pub fn add_slope(p: &AffinePoint, q: &AffinePoint) -> [u64; 4] {
+        mul(&sub(&q.1, &p.1), &invert(&sub(&q.0, &p.0)))
+    }
+
+    pub fn double_slope(p: &AffinePoint) -> [u64; 4] {
+        let x2 = mul(&p.0, &p.0);
+        mul(&add(&add(&x2, &x2), &x2), &invert(&add(&p.1, &p.1)))
+    }
+
+    pub fn add_points(p: &AffinePoint, q: &AffinePoint) -> AffinePoint {
+        chord(p, q, add_slope(p, q))
+    }
+
+    pub fn double(p: &AffinePoint) -> AffinePoint {
+        chord(p, p, double_slope(p))
+    }
+
+    pub fn negate(p: &AffinePoint) -> AffinePoint {
+        (p.0, sub(&[0; 4], &p.1))
+    }
+
+    /// The point with the least `x` coordinate of at least 2^255 that is on
+    /// the curve, which has no known relation to the generator.
+    pub fn offset() -> AffinePoint {
+        let mut x = [0, 0, 0, 1 << 63];
+        loop {
+            let rhs = add(&mul(&mul(&x, &x), &x), &[7, 0, 0, 0]);
+            if let Some(y) = sqrt(&rhs) {
+                return (x, y);
+            }
+            x = add(&x, &[1, 0, 0, 0]);
+        }
+    }
+}
+
+pub use native::{add_points, double};
+
+/// A point on secp256k1 other than the identity.
+pub struct Secp256k1Point<F: Field> {
+    x: Secp256k1Element<F>,
+    y: Secp256k1Element<F>,
+}
+
+impl<F: Field> Clone for Secp256k1Point<F> {
+    fn clone(&self) -> Self {
+        Secp256k1Point {
+            x: self.x.clone(),
+            y: self.y.clone(),
+        }
+    }
+}
+
+impl<F: Field> Secp256k1Point<F> {
+    pub fn constant(point: AffinePoint) -> Self {
+        Secp256k1Point {
+            x: Secp256k1Element::constant(point.0),
+            y: Secp256k1Element::constant(point.1),
+        }
+    }
+
+    /// Allocates `point`, constraining it to be on the curve.
+    pub fn alloc<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        point: Option<AffinePoint>,
+    ) -> Result<Self, SynthesisError> {
+        let x = Secp256k1Element::alloc(cs.namespace(|| "x"), point.map(|p| p.0))?;
+        let y = Secp256k1Element::alloc(cs.namespace(|| "y"), point.map(|p| p.1))?;
+
+        let y2 = y.mul(cs.namespace(|| "y^2"), &y)?;
+        let x2 = x.mul(cs.namespace(|| "x^2"), &x)?;
+        let x3 = x2.mul(cs.namespace(|| "x^3"), &x)?;
+        let rhs = x3.add(
+            cs.namespace(|| "x^3 + 7"),
+            &Secp256k1Element::constant([7, 0, 0, 0]),
+        )?;
+        Secp256k1Element::enforce_equal(cs.namespace(|| "on curve"), &y2, &rhs)?;
+
+        Ok(Secp256k1Point { x, y })
+    }
+
+    pub fn get_value(&self) -> Option<AffinePoint> {
+        self.x
+            .get_value()
+            .and_then(|x| self.y.get_value().map(|y| (x, y)))
+    }
+
+    pub fn x(&self) -> &Secp256k1Element<F> {
+        &self.x
+    }
+
+    pub fn y(&self) -> &Secp256k1Element<F> {
+        &self.y
+    }
+
+    // The third point on the line of the given slope through this point
+    // and `other`, negated.
+    fn chord<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        slope: &Secp256k1Element<F>,
+    ) -> Result<Self, SynthesisError> {
+        let slope2 = slope.mul(cs.namespace(|| "slope^2"), slope)?;
+        let x = slope2
+            .sub(cs.namespace(|| "slope^2 - x1"), &self.x)?
+            .sub(cs.namespace(|| "slope^2 - x1 - x2"), &other.x)?;
+        let dx = self.x.sub(cs.namespace(|| "x1 - x3"), &x)?;
+        let y = slope
+            .mul(cs.namespace(|| "slope * (x1 - x3)"), &dx)?
+            .sub(cs.namespace(|| "slope * (x1 - x3) - y1"), &self.y)?;
+        Ok(Secp256k1Point { x, y })
+    }
+
+    /// The sum of this point and `other`, which must have a different `x`
+    /// coordinate.
+    pub fn add<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let dx = other.x.sub(cs.namespace(|| "x2 - x1"), &self.x)?;
+        dx.enforce_nonzero(cs.namespace(|| "distinct x"))?;
+        let dy = other.y.sub(cs.namespace(|| "y2 - y1"), &self.y)?;
+
+        let slope_value = self
+            .get_value()
+            .and_then(|p| other.get_value().map(|q| native::add_slope(&p, &q)));
+        let slope = Secp256k1Element::alloc(cs.namespace(|| "slope"), slope_value)?;
+        let rise = slope.mul(cs.namespace(|| "slope * (x2 - x1)"), &dx)?;
+        Secp256k1Element::enforce_equal(cs.namespace(|| "slope"), &rise, &dy)?;
+
+        self.chord(cs.namespace(|| "sum"), other, &slope)
+    }
+
+    pub fn double<CS: ConstraintSystem<F>>(&self, mut cs: CS) -> Result<Self, SynthesisError> {
+        let slope_value = self.get_value().map(|p| native::double_slope(&p));
+        let slope = Secp256k1Element::alloc(cs.namespace(|| "slope"), slope_value)?;
+
+        // The curve has no points of order two, so y is nonzero.
+        let two_y = self.y.add(cs.namespace(|| "2y"), &self.y)?;
+        let rise = slope.mul(cs.namespace(|| "slope * 2y"), &two_y)?;
+        let x2 = self.x.mul(cs.namespace(|| "x^2"), &self.x)?;
+        let three_x2 = x2.scale(cs.namespace(|| "3x^2"), 3)?;
+        Secp256k1Element::enforce_equal(cs.namespace(|| "slope"), &rise, &three_x2)?;
+
+        self.chord(cs.namespace(|| "sum"), self, &slope)
+    }
+
+    /// `if_true` if `condition` holds, and `if_false` otherwise.
+    pub fn select<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        condition: &Boolean,
+        if_true: &Self,
+        if_false: &Self,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Secp256k1Point {
+            x: Secp256k1Element::select(cs.namespace(|| "x"), condition, &if_true.x, &if_false.x)?,
+            y: Secp256k1Element::select(cs.namespace(|| "y"), condition, &if_true.y, &if_false.y)?,
+        })
+    }
+
+    /// This point multiplied by the scalar with little-endian bits `bits`,
+    /// which must not be a multiple of the group order.
+    pub fn mul<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+        bits: &[Boolean],
+    ) -> Result<Self, SynthesisError> {
+        let offset = native::offset();
+        let mut acc = Self::constant(offset);
+        let mut shifted_offset = offset;
+        for (i, bit) in bits.iter().enumerate().rev() {
+            let mut cs = cs.namespace(|| format!("bit {}", i));
+            acc = acc.double(cs.namespace(|| "double"))?;
+            let sum = acc.add(cs.namespace(|| "add"), self)?;
+            acc = Self::select(cs.namespace(|| "select"), bit, &sum, &acc)?;
+            shifted_offset = native::double(&shifted_offset);
+        }
+
+        acc.add(
+            cs.namespace(|| "remove offset"),
+            &Self::constant(native::negate(&shifted_offset)),
+        )
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::Circuit,
+        dev::{is_satisfied, GadgetCheck},
+        fields::Fp,
+        gadgets::AllocatedBit,
+        Basic,
+    };
+
+    const DOUBLE_GENERATOR: AffinePoint = (
+        [
+            0xabac_09b9_5c70_9ee5,
+            0x5c77_8e4b_8cef_3ca7,
+            0x3045_406e_95c0_7cd8,
+            0xc604_7f94_41ed_7d6d,
+        ],
+        [
+            0x2364_31a9_50cf_e52a,
+            0xf7f6_3265_3266_d0e1,
+            0xa3c5_8419_466c_eaee,
+            0x1ae1_68fe_a63d_c339,
+        ],
+    );
+
+    const TRIPLE_GENERATOR: AffinePoint = (
+        [
+            0x8601_f113_bce0_36f9,
+            0xb531_c845_836f_99b0,
+            0x4934_4f85_f89d_5229,
+            0xf930_8a01_9258_c310,
+        ],
+        [
+            0x6cb9_fd75_84b8_e672,
+            0x6500_a999_34c2_231b,
+            0x0fe3_37e6_2a37_f356,
+            0x388f_7b0f_632d_e814,
+        ],
+    );
+
+    #[test]
+    fn test_native() {
+        assert_eq!(double(&GENERATOR), DOUBLE_GENERATOR);
+        assert_eq!(add_points(&GENERATOR, &DOUBLE_GENERATOR), TRIPLE_GENERATOR);
+    }
+
+    struct OnCurve(AffinePoint);
+
+    impl Circuit<Fp> for OnCurve {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            Secp256k1Point::alloc(cs.namespace(|| "point"), Some(self.0))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_on_curve() {
+        let mut off_curve = GENERATOR;
+        off_curve.1[0] ^= 1;
+        GadgetCheck::new(OnCurve(GENERATOR), &[])
+            .malformed("off curve", OnCurve(off_curve), &[])
+            .check::<Basic>()
+            .unwrap();
+    }
+
+    enum Op {
+        Add(AffinePoint),
+        Double,
+        Mul(u64, usize),
+    }
+
+    struct OpCircuit {
+        point: AffinePoint,
+        op: Op,
+        expected: AffinePoint,
+    }
+
+    impl Circuit<Fp> for OpCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let point = Secp256k1Point::alloc(cs.namespace(|| "point"), Some(self.point))?;
+            let result = match self.op {
+                Op::Add(other) => {
+                    let other = Secp256k1Point::alloc(cs.namespace(|| "other"), Some(other))?;
+                    point.add(cs.namespace(|| "add"), &other)?
+                }
+                Op::Double => point.double(cs.namespace(|| "double"))?,
+                Op::Mul(scalar, bits) => {
+                    let mut scalar_bits = vec![];
+                    for i in 0..bits {
+                        scalar_bits.push(Boolean::Is(AllocatedBit::alloc(
+                            cs.namespace(|| format!("scalar bit {}", i)),
+                            || Ok((scalar >> i) & 1 == 1),
+                        )?));
+                    }
+                    point.mul(cs.namespace(|| "mul"), &scalar_bits)?
+                }
+            };
+
+            let expected = Secp256k1Point::constant(self.expected);
+            Secp256k1Element::enforce_equal(cs.namespace(|| "x"), result.x(), expected.x())?;
+            Secp256k1Element::enforce_equal(cs.namespace(|| "y"), result.y(), expected.y())?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_add_and_double() {
+        GadgetCheck::new(
+            OpCircuit {
+                point: GENERATOR,
+                op: Op::Add(DOUBLE_GENERATOR),
+                expected: TRIPLE_GENERATOR,
+            },
+            &[],
+        )
+        .malformed(
+            "wrong sum",
+            OpCircuit {
+                point: GENERATOR,
+                op: Op::Add(DOUBLE_GENERATOR),
+                expected: DOUBLE_GENERATOR,
+            },
+            &[],
+        )
+        .malformed(
+            "equal x",
+            OpCircuit {
+                point: GENERATOR,
+                op: Op::Add(GENERATOR),
+                expected: DOUBLE_GENERATOR,
+            },
+            &[],
+        )
+        .check::<Basic>()
+        .unwrap();
+
+        assert_eq!(
+            is_satisfied::<_, _, Basic>(
+                &OpCircuit {
+                    point: GENERATOR,
+                    op: Op::Double,
+                    expected: DOUBLE_GENERATOR,
+                },
+                &[]
+            ),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_mul() {
+        let five = add_points(&DOUBLE_GENERATOR, &TRIPLE_GENERATOR);
+        GadgetCheck::new(
+            OpCircuit {
+                point: GENERATOR,
+                op: Op::Mul(5, 3),
+                expected: five,
+            },
+            &[],
+        )
+        .malformed(
+            "wrong product",
+            OpCircuit {
+                point: GENERATOR,
+                op: Op::Mul(3, 3),
+                expected: five,
+            },
+            &[],
+        )
+        .check::<Basic>()
+        .unwrap();
+    }
+}
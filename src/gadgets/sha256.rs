@@ -258,11 +258,15 @@ where
     Ok(vec![h0, h1, h2, h3, h4, h5, h6, h7])
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod test {
     use super::*;
     use crate::{
-        circuits::Circuit, dev::is_satisfied, fields::Fp, gadgets::boolean::AllocatedBit, Basic,
+        circuits::Circuit,
+        dev::{is_satisfied, GadgetCheck},
+        fields::Fp,
+        gadgets::boolean::AllocatedBit,
+        Basic,
     };
     use rand_core::{RngCore, SeedableRng};
     use rand_xorshift::XorShiftRng;
@@ -421,4 +425,64 @@ mod test {
             );
         }
     }
+
+    struct HashCircuit {
+        preimage: Vec<u8>,
+        digest: Vec<u8>,
+    }
+
+    impl Circuit<Fp> for HashCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut input = vec![];
+            for (j, byte) in self.preimage.iter().enumerate() {
+                for i in (0..8).rev() {
+                    input.push(Boolean::from(AllocatedBit::alloc(
+                        cs.namespace(|| format!("input bit {}", 8 * j + i)),
+                        || Ok((byte >> i) & 1 == 1),
+                    )?));
+                }
+            }
+
+            let out = sha256(cs.namespace(|| "sha256"), &input)?;
+
+            let expected = self
+                .digest
+                .iter()
+                .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1));
+            for (i, (a, b)) in out.iter().zip(expected).enumerate() {
+                Boolean::enforce_equal(
+                    cs.namespace(|| format!("output bit {}", i)),
+                    a,
+                    &Boolean::constant(b),
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_gadget_check() {
+        let digest =
+            hex!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").to_vec();
+
+        GadgetCheck::new(
+            HashCircuit {
+                preimage: b"abc".to_vec(),
+                digest: digest.clone(),
+            },
+            &[],
+        )
+        .malformed(
+            "wrong preimage",
+            HashCircuit {
+                preimage: b"abd".to_vec(),
+                digest,
+            },
+            &[],
+        )
+        .budget(32429, 65292)
+        .check::<Basic>()
+        .unwrap();
+    }
 }
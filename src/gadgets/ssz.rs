@@ -0,0 +1,211 @@
+//! SSZ (SimpleSerialize) basic values and the offsets of variable-size
+//! fields.
+//!
+//! Integers are little-endian. A container serializes its fixed-size fields
+//! in place and each variable-size field as a 4-byte offset into the
+//! container, with the variable-size fields following the fixed part in
+//! order. The offsets therefore start at the length of the fixed part and
+//! never decrease.
+
+use super::bytes::{byte, enforce_false, pack, range_check};
+use super::{Boolean, Combination, Num};
+use crate::circuits::{Coeff, ConstraintSystem, SynthesisError};
+use crate::fields::Field;
+
+/// The number of bytes of an offset.
+pub const BYTES_PER_LENGTH_OFFSET: usize = 4;
+
+/// The unsigned integer `bytes` encodes.
+pub fn decode_uint<F: Field>(bytes: &[Boolean]) -> Combination<F> {
+    assert_eq!(bytes.len() % 8, 0);
+    pack(bytes)
+}
+
+/// The boolean the byte `bytes` encodes, which must be 0 or 1.
+pub fn decode_bool<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    bytes: &[Boolean],
+) -> Result<Boolean, SynthesisError> {
+    assert_eq!(bytes.len(), 8);
+    for (i, bit) in bytes.iter().enumerate().skip(1) {
+        enforce_false(cs.namespace(|| format!("bit {}", i)), bit)?;
+    }
+    Ok(bytes[0].clone())
+}
+
+/// Where a variable-size field of a container lies.
+pub struct VariableField<F: Field> {
+    /// The offset of the field within the container.
+    pub offset: Combination<F>,
+    /// The number of bytes of the field.
+    pub len: Combination<F>,
+}
+
+/// Decodes the offsets of the variable-size fields of `container`, whose
+/// fixed part is `fixed_len` bytes long, from the byte positions
+/// `positions` within the fixed part. The offsets must start at `fixed_len`,
+/// never decrease and not exceed the length of the container, which is the
+/// length of `container`.
+pub fn decode_offsets<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    container: &[Boolean],
+    fixed_len: usize,
+    positions: &[usize],
+) -> Result<Vec<VariableField<F>>, SynthesisError> {
+    assert_eq!(container.len() % 8, 0);
+    let container_len = container.len() / 8;
+    assert!(fixed_len <= container_len);
+    if positions.is_empty() {
+        assert_eq!(fixed_len, container_len);
+        return Ok(vec![]);
+    }
+
+    let offsets: Vec<Combination<F>> = positions
+        .iter()
+        .map(|&position| {
+            assert!(position + BYTES_PER_LENGTH_OFFSET <= fixed_len);
+            let bits: Vec<Boolean> = (position..(position + BYTES_PER_LENGTH_OFFSET))
+                .flat_map(|i| byte(container, i))
+                .collect();
+            decode_uint(&bits)
+        })
+        .collect();
+
+    let lc = offsets[0].lc(&mut cs) - (Coeff::Full(F::from_u64(fixed_len as u64)), CS::ONE);
+    cs.enforce_zero(lc);
+
+    let mut fields = Vec::with_capacity(offsets.len());
+    for (i, offset) in offsets.iter().enumerate() {
+        let end = match offsets.get(i + 1) {
+            Some(next) => next.clone(),
+            None => Combination::from(Num::constant(F::from_u64(container_len as u64))),
+        };
+        let len = end + offset.clone().scale(-F::one());
+
+        // The length is only in range if the offsets are in order.
+        range_check(
+            cs.namespace(|| format!("field {} length", i)),
+            &len,
+            BYTES_PER_LENGTH_OFFSET * 8,
+        )?;
+
+        fields.push(VariableField {
+            offset: offset.clone(),
+            len,
+        });
+    }
+
+    Ok(fields)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::Circuit,
+        dev::{is_satisfied, GadgetCheck},
+        fields::Fp,
+        gadgets::bytes::alloc_bytes,
+        Basic,
+    };
+
+    // A container { a: uint16, b: List[uint8], c: boolean, d: List[uint8] }
+    struct ContainerCircuit {
+        bytes: Vec<u8>,
+        a: u64,
+        c: bool,
+        lens: [Fp; 2],
+    }
+
+    fn container(b: &[u8], d: &[u8]) -> Vec<u8> {
+        let fixed_len = 2 + 4 + 1 + 4;
+        let mut bytes = vec![0x34, 0x12];
+        bytes.extend_from_slice(&(fixed_len as u32).to_le_bytes());
+        bytes.push(1);
+        bytes.extend_from_slice(&((fixed_len + b.len()) as u32).to_le_bytes());
+        bytes.extend_from_slice(b);
+        bytes.extend_from_slice(d);
+        bytes
+    }
+
+    impl Circuit<Fp> for ContainerCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let bytes = alloc_bytes(cs, &self.bytes)?;
+
+            let a = decode_uint::<Fp>(&bytes[..16]);
+            let lc = a.lc(&mut *cs) - (Coeff::Full(Fp::from(self.a)), CS::ONE);
+            cs.enforce_zero(lc);
+
+            let c = decode_bool(cs.namespace(|| "c"), &bytes[48..56])?;
+            Boolean::enforce_equal(cs.namespace(|| "c value"), &c, &Boolean::constant(self.c))?;
+
+            let fields = decode_offsets(cs.namespace(|| "offsets"), &bytes, 11, &[2, 7])?;
+            for (field, len) in fields.iter().zip(self.lens.iter()) {
+                let lc = field.len.lc(&mut *cs) - (Coeff::Full(*len), CS::ONE);
+                cs.enforce_zero(lc);
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_container() {
+        let honest = ContainerCircuit {
+            bytes: container(&[1, 2, 3], &[4, 5]),
+            a: 0x1234,
+            c: true,
+            lens: [Fp::from(3), Fp::from(2)],
+        };
+        assert_eq!(is_satisfied::<_, _, Basic>(&honest, &[]), Ok(true));
+
+        let empty = ContainerCircuit {
+            bytes: container(&[], &[]),
+            a: 0x1234,
+            c: true,
+            lens: [Fp::zero(), Fp::zero()],
+        };
+        assert_eq!(is_satisfied::<_, _, Basic>(&empty, &[]), Ok(true));
+
+        let mut bad_bool = container(&[1, 2, 3], &[4, 5]);
+        bad_bool[6] = 2;
+        let mut bad_first_offset = container(&[1, 2, 3], &[4, 5]);
+        bad_first_offset[2] = 12;
+        // The second offset points before the first.
+        let mut decreasing = container(&[1, 2, 3], &[4, 5]);
+        decreasing[7] = 10;
+        // The second offset points past the end.
+        let mut overflowing = container(&[1, 2, 3], &[4, 5]);
+        overflowing[7] = 17;
+
+        let malformed = |bytes: Vec<u8>, lens: [Fp; 2]| ContainerCircuit {
+            bytes,
+            a: 0x1234,
+            c: true,
+            lens,
+        };
+        GadgetCheck::new(honest, &[])
+            .malformed(
+                "non-boolean byte",
+                malformed(bad_bool, [Fp::from(3), Fp::from(2)]),
+                &[],
+            )
+            .malformed(
+                "first offset",
+                malformed(bad_first_offset, [Fp::from(2), Fp::from(2)]),
+                &[],
+            )
+            .malformed(
+                "decreasing offsets",
+                malformed(decreasing, [-Fp::one(), Fp::from(6)]),
+                &[],
+            )
+            .malformed(
+                "offset past the end",
+                malformed(overflowing, [Fp::from(6), -Fp::one()]),
+                &[],
+            )
+            .check::<Basic>()
+            .unwrap();
+    }
+}
@@ -375,7 +375,7 @@ impl UInt32 {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod test {
     use super::UInt32;
     use crate::{
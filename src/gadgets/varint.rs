@@ -0,0 +1,137 @@
+//! Protobuf-style varints (unsigned LEB128): seven bits of the value per
+//! byte, least significant group first, with the high bit of each byte set
+//! if another byte follows.
+
+use super::bytes::{boolean, byte, enforce_false, pack, select};
+use super::{Boolean, Combination};
+use crate::circuits::{ConstraintSystem, SynthesisError};
+use crate::fields::Field;
+
+/// A decoded varint.
+pub struct Varint<F: Field> {
+    pub value: Combination<F>,
+    /// The number of bytes the varint occupies.
+    pub len: Combination<F>,
+}
+
+/// Decodes the varint at the start of `bytes`, which must end within its
+/// first `max_len` bytes. Bytes after the varint are ignored.
+pub fn decode<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    bytes: &[Boolean],
+    max_len: usize,
+) -> Result<Varint<F>, SynthesisError> {
+    assert!(max_len * 7 < F::CAPACITY as usize);
+
+    let mut value = Combination::zero();
+    let mut len = Combination::zero();
+    // Whether byte i is part of the varint
+    let mut active = Boolean::constant(true);
+    for i in 0..max_len {
+        let mut cs = cs.namespace(|| format!("byte {}", i));
+        let byte = byte(bytes, i);
+
+        let group = select(cs.namespace(|| "group"), &active, &pack(&byte[..7]))?;
+        value = value + group.scale(F::from_u64(2).pow(&[7 * i as u64, 0, 0, 0]));
+        len = len + boolean(&active);
+
+        active = Boolean::and(cs.namespace(|| "continues"), &active, &byte[7])?;
+    }
+    enforce_false(cs.namespace(|| "terminated"), &active)?;
+
+    Ok(Varint { value, len })
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::Circuit,
+        dev::{is_satisfied, GadgetCheck},
+        fields::Fp,
+        gadgets::{bytes::alloc_bytes, AllocatedNum},
+        Basic,
+    };
+
+    fn encode(mut value: u64) -> Vec<u8> {
+        let mut out = vec![];
+        loop {
+            let group = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(group);
+                return out;
+            }
+            out.push(group | 0x80);
+        }
+    }
+
+    struct VarintCircuit {
+        bytes: Vec<u8>,
+        value: u64,
+        len: u64,
+    }
+
+    impl Circuit<Fp> for VarintCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let bytes = alloc_bytes(cs, &self.bytes)?;
+            let varint = decode(cs.namespace(|| "varint"), &bytes, 10)?;
+
+            let value =
+                AllocatedNum::alloc_input(cs.namespace(|| "value"), || Ok(Fp::from(self.value)))?;
+            let lc = varint.value.lc(&mut *cs) - &value.lc();
+            cs.enforce_zero(lc);
+            let lc = varint.len.lc(&mut *cs) - (crate::Coeff::Full(Fp::from(self.len)), CS::ONE);
+            cs.enforce_zero(lc);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_varint() {
+        for &value in &[0, 1, 127, 128, 300, 1 << 35, u64::MAX] {
+            let mut bytes = encode(value);
+            let len = bytes.len() as u64;
+            bytes.extend_from_slice(&[0xff, 0x01]);
+
+            let circuit = VarintCircuit { bytes, value, len };
+            assert_eq!(
+                is_satisfied::<_, _, Basic>(&circuit, &[Fp::from(value)]),
+                Ok(true),
+                "{}",
+                value
+            );
+        }
+
+        // 300 = 0b10_0101100
+        GadgetCheck::new(
+            VarintCircuit {
+                bytes: vec![0xac, 0x02, 0x80],
+                value: 300,
+                len: 2,
+            },
+            &[Fp::from(300)],
+        )
+        .malformed(
+            "wrong value",
+            VarintCircuit {
+                bytes: vec![0xac, 0x02, 0x80],
+                value: 301,
+                len: 2,
+            },
+            &[Fp::from(301)],
+        )
+        .malformed(
+            "unterminated",
+            VarintCircuit {
+                bytes: vec![0x80; 10],
+                value: 0,
+                len: 10,
+            },
+            &[Fp::from(0)],
+        )
+        .check::<Basic>()
+        .unwrap();
+    }
+}
@@ -0,0 +1,386 @@
+//! An ECVRF-style verifiable random function over a curve whose base field
+//! is the field of the circuit, so that a step can draw randomness from an
+//! input with a key it is bound to, in a way its prover cannot bias.
+//!
+//! The proof for an input `alpha` under the key `pk = [sk] G` is the point
+//! `Gamma = [sk] H`, where `H` hashes `pk` and `alpha` to the curve, and a
+//! Chaum–Pedersen proof that `Gamma` and `pk` share a discrete logarithm.
+//! The output is a hash of the x-coordinate of `Gamma` alone: the circuit
+//! does not fix the sign of `H`, and negating `H` negates `Gamma`, so the
+//! output is unique all the same.
+//!
+//! `H` is found by try-and-increment, as the first of `x, x + 1, ...` that
+//! is the x-coordinate of a point, where `x` is a Rescue hash of `pk` and
+//! `alpha`. The circuit checks that each earlier candidate is not, with a
+//! square root of its curve equation times a non-residue, and so can only
+//! accept the first. It checks at most `HASH_TRIES` candidates; the inputs
+//! for which all of them fail, about one in `2^HASH_TRIES`, have no proof.
+
+use super::ecdh::alloc_scalar_bits;
+use super::num::{AllocatedNum, Num};
+use super::rescue::RescueGadget;
+use super::{unpack_fe, AllocatedBit, Combination, CurvePoint};
+use crate::{
+    circuits::{Coeff, ConstraintSystem, SynthesisError},
+    fields::{Field, LegendreSymbol},
+    rescue::Rescue,
+    Curve,
+};
+
+/// The number of candidates hashing to the curve tries.
+pub const HASH_TRIES: usize = 32;
+
+/// The number of bits of a challenge.
+const CHALLENGE_BITS: usize = 128;
+
+/// Absorbed ahead of the inputs of each hash, separating them from one
+/// another and from other Rescue digests.
+const HASH_DOMAIN: u64 = 0x6861_6c6f_5f76_6831;
+const CHALLENGE_DOMAIN: u64 = 0x6861_6c6f_5f76_6331;
+const NONCE_DOMAIN: u64 = 0x6861_6c6f_5f76_6e31;
+const OUTPUT_DOMAIN: u64 = 0x6861_6c6f_5f76_6f31;
+
+/// A proof that a VRF output was computed correctly.
+#[derive(Clone, Copy, Debug)]
+pub struct VrfProof<C: Curve> {
+    pub gamma: C,
+    /// The challenge, of which only the low 128 bits are used.
+    pub c: u128,
+    pub s: C::Scalar,
+}
+
+/// The coordinates of `point`, with the identity as (0, 0), as `CurvePoint`
+/// represents it.
+fn coordinates<C: Curve>(point: C) -> [C::Base; 2] {
+    let coords = point.get_xy();
+    if coords.is_some().into() {
+        let (x, y) = coords.unwrap();
+        [x, y]
+    } else {
+        [C::Base::zero(), C::Base::zero()]
+    }
+}
+
+/// The smallest quadratic non-residue of `F`.
+fn non_residue<F: Field>() -> F {
+    let mut n = F::one();
+    while n.legendre() != LegendreSymbol::QuadraticNonResidue {
+        n += F::one();
+    }
+    n
+}
+
+/// Hashes `pk` and `alpha` to the curve, outside of the circuit. Returns the
+/// point and the number of candidates before it, or `None` if none of the
+/// `HASH_TRIES` candidates is on the curve.
+pub fn hash_to_curve_native<C: Curve>(pk: C, alpha: C::Base) -> Option<(C, usize)> {
+    let [pkx, pky] = coordinates(pk);
+    let x = Rescue::hash_to_field(&[C::Base::from_u64(HASH_DOMAIN), pkx, pky, alpha]);
+
+    (0..HASH_TRIES).find_map(|i| {
+        let x = x + C::Base::from_u64(i as u64);
+        let y = (x.square() * x + C::b()).sqrt();
+        if y.is_some().into() {
+            Some((C::from_xy(x, y.unwrap()).unwrap(), i))
+        } else {
+            None
+        }
+    })
+}
+
+fn challenge_native<C: Curve>(points: [C; 5]) -> u128 {
+    let mut inputs = vec![C::Base::from_u64(CHALLENGE_DOMAIN)];
+    for point in points.iter() {
+        inputs.extend_from_slice(&coordinates(*point));
+    }
+    Rescue::hash_to_field(&inputs).get_lower_128()
+}
+
+/// The nonce of a proof, derived from the key and `H` so that proving needs
+/// no randomness.
+fn nonce<C: Curve>(sk: C::Scalar, h: C) -> C::Scalar {
+    let mut inputs = vec![C::Scalar::from_u64(NONCE_DOMAIN), sk];
+    for coordinate in coordinates(h).iter() {
+        for half in coordinate.to_bytes().chunks(16) {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(half);
+            inputs.push(C::Scalar::from_u128(u128::from_le_bytes(bytes)));
+        }
+    }
+    Rescue::hash_to_field(&inputs)
+}
+
+/// The output of a proof whose point is `gamma`, outside of the circuit.
+pub fn output_native<C: Curve>(gamma: C) -> C::Base {
+    let [x, _] = coordinates(gamma);
+    Rescue::hash_to_field(&[C::Base::from_u64(OUTPUT_DOMAIN), x])
+}
+
+/// Evaluates the VRF keyed by `sk` at `alpha`, returning the output and its
+/// proof, or `None` if `alpha` does not hash to the curve.
+pub fn prove<C: Curve>(sk: C::Scalar, alpha: C::Base) -> Option<(C::Base, VrfProof<C>)> {
+    let pk = C::one() * sk;
+    let (h, _) = hash_to_curve_native(pk, alpha)?;
+    let gamma = h * sk;
+
+    let k = nonce(sk, h);
+    let c = challenge_native([pk, h, gamma, C::one() * k, h * k]);
+    let s = k + C::Scalar::from_u128(c) * sk;
+
+    Some((output_native(gamma), VrfProof { gamma, c, s }))
+}
+
+/// The points `U = [s] G - [c] pk` and `V = [s] H - [c] Gamma` that the
+/// challenge of a proof commits to.
+fn commitments<C: Curve>(pk: C, h: C, proof: &VrfProof<C>) -> (C, C) {
+    let c = C::Scalar::from_u128(proof.c);
+    (C::one() * proof.s - pk * c, h * proof.s - proof.gamma * c)
+}
+
+/// Verifies `proof` for `alpha` under `pk` outside of the circuit, returning
+/// the output if it is valid.
+pub fn verify_native<C: Curve>(pk: C, alpha: C::Base, proof: &VrfProof<C>) -> Option<C::Base> {
+    let (h, _) = hash_to_curve_native(pk, alpha)?;
+    let (u, v) = commitments(pk, h, proof);
+
+    if challenge_native([pk, h, proof.gamma, u, v]) == proof.c {
+        Some(output_native(proof.gamma))
+    } else {
+        None
+    }
+}
+
+/// Constrains two points to be equal. No point other than the identity,
+/// which is represented as (0, 0), has a y-coordinate of zero, so comparing
+/// coordinates suffices.
+fn enforce_equal_points<C: Curve, CS: ConstraintSystem<C::Base>>(
+    mut cs: CS,
+    a: &CurvePoint<C>,
+    b: &CurvePoint<C>,
+) {
+    let (ax, ay) = a.get_xy();
+    let (bx, by) = b.get_xy();
+    let lc = ax.lc(&mut cs) - &bx.lc(&mut cs);
+    cs.enforce_zero(lc);
+    let lc = ay.lc(&mut cs) - &by.lc(&mut cs);
+    cs.enforce_zero(lc);
+}
+
+/// Hashes `pk` and `alpha` to the curve, matching `hash_to_curve_native`.
+fn hash_to_curve<C: Curve, CS: ConstraintSystem<C::Base>>(
+    mut cs: CS,
+    pk: &CurvePoint<C>,
+    alpha: &Num<C::Base>,
+    h: Option<(C, usize)>,
+) -> Result<CurvePoint<C>, SynthesisError> {
+    let (pkx, pky) = pk.get_xy();
+    let domain = Num::constant(C::Base::from_u64(HASH_DOMAIN));
+    let x = RescueGadget::hash_to_field(cs.namespace(|| "hash"), &[domain, pkx, pky, *alpha])?;
+    let n = non_residue::<C::Base>();
+
+    // `before[i]` is set if candidate `i` comes before `H`, in which case the
+    // candidate is proven not to be on the curve. The bits may only fall, and
+    // the last candidate is never skipped, so the number of set bits counts
+    // the candidates before `H`.
+    let mut before: Vec<AllocatedBit> = vec![];
+    let mut x_h = Combination::from(x);
+    for i in 0..(HASH_TRIES - 1) {
+        let cs = &mut cs.namespace(|| format!("candidate {}", i));
+        let bit = AllocatedBit::alloc(cs.namespace(|| "before"), || {
+            h.map(|(_, skipped)| i < skipped)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        if let Some(previous) = before.last() {
+            let rise = Combination::from(AllocatedNum::from(bit.clone())).mul(
+                cs.namespace(|| "only falls"),
+                &(Combination::from(Num::constant(C::Base::one()))
+                    + (Coeff::NegativeOne, AllocatedNum::from(previous.clone()))),
+            )?;
+            cs.enforce_zero(rise.lc());
+        }
+
+        let candidate = Combination::from(x) + Num::constant(C::Base::from_u64(i as u64));
+        let square = candidate.square(cs.namespace(|| "x^2"))?;
+        let cube = Combination::from(square).mul(cs.namespace(|| "x^3"), &candidate)?;
+        let rhs = Combination::from(cube) + Num::constant(C::b());
+        let skipped = Combination::from(AllocatedNum::from(bit.clone()))
+            .mul(cs.namespace(|| "rhs if before"), &rhs)?;
+
+        let root = AllocatedNum::alloc(cs.namespace(|| "root"), || {
+            let skipped = skipped
+                .get_value()
+                .ok_or(SynthesisError::AssignmentMissing)?;
+            Ok((skipped * n).sqrt().unwrap_or_else(C::Base::zero))
+        })?;
+        let root_squared = Combination::from(root).square(cs.namespace(|| "root^2"))?;
+        cs.enforce_zero(root_squared.lc() - (Coeff::Full(n), skipped.get_variable()));
+
+        x_h += AllocatedNum::from(bit.clone());
+        before.push(bit);
+    }
+
+    let point = CurvePoint::witness(cs.namespace(|| "H"), || {
+        h.map(|(h, _)| h).ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    let (x, _) = point.get_xy();
+    let lc = x.lc(&mut cs) - &x_h.lc(&mut cs);
+    cs.enforce_zero(lc);
+    let lc = point.is_identity().lc(CS::ONE, Coeff::One);
+    cs.enforce_zero(lc);
+
+    Ok(point)
+}
+
+/// Verifies `proof` for `alpha` under `pk`, and returns the output.
+pub fn verify<C: Curve, CS: ConstraintSystem<C::Base>>(
+    mut cs: CS,
+    pk: &CurvePoint<C>,
+    alpha: &Num<C::Base>,
+    proof: Option<&VrfProof<C>>,
+) -> Result<AllocatedNum<C::Base>, SynthesisError> {
+    let pk_value = pk.get_point().map(|pk| {
+        if pk.is_some().into() {
+            pk.unwrap()
+        } else {
+            C::zero()
+        }
+    });
+    let h_value = pk_value
+        .and_then(|pk| alpha.value().map(|alpha| (pk, alpha)))
+        .and_then(|(pk, alpha)| hash_to_curve_native(pk, alpha));
+    let uv_value = pk_value
+        .and_then(|pk| h_value.map(|(h, _)| (pk, h)))
+        .and_then(|(pk, h)| proof.map(|proof| commitments(pk, h, proof)));
+
+    let h = hash_to_curve(cs.namespace(|| "hash to curve"), pk, alpha, h_value)?;
+    let gamma = CurvePoint::witness(cs.namespace(|| "Gamma"), || {
+        proof
+            .map(|proof| proof.gamma)
+            .ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    let u = CurvePoint::witness(cs.namespace(|| "U"), || {
+        uv_value
+            .map(|(u, _)| u)
+            .ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    let v = CurvePoint::witness(cs.namespace(|| "V"), || {
+        uv_value
+            .map(|(_, v)| v)
+            .ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    let s = alloc_scalar_bits::<C, _>(cs.namespace(|| "s"), proof.map(|proof| proof.s))?;
+
+    let mut inputs = vec![Num::constant(C::Base::from_u64(CHALLENGE_DOMAIN))];
+    for point in [pk, &h, &gamma, &u, &v].iter() {
+        let (x, y) = point.get_xy();
+        inputs.push(x);
+        inputs.push(y);
+    }
+    let c = RescueGadget::hash_to_field(cs.namespace(|| "challenge"), &inputs)?;
+    let mut c = unpack_fe(cs.namespace(|| "unpack challenge"), &c.into())?;
+    c.truncate(CHALLENGE_BITS);
+
+    let (gx, gy) = C::one().get_xy().unwrap();
+    let g = CurvePoint::<C>::constant(gx, gy);
+    let sg = g.multiply(cs.namespace(|| "[s] G"), &s)?;
+    let cpk = pk.multiply(cs.namespace(|| "[c] pk"), &c)?;
+    let rhs = u.add(cs.namespace(|| "U + [c] pk"), &cpk)?;
+    enforce_equal_points(cs.namespace(|| "check U"), &sg, &rhs);
+
+    let sh = h.multiply(cs.namespace(|| "[s] H"), &s)?;
+    let cgamma = gamma.multiply(cs.namespace(|| "[c] Gamma"), &c)?;
+    let rhs = v.add(cs.namespace(|| "V + [c] Gamma"), &cgamma)?;
+    enforce_equal_points(cs.namespace(|| "check V"), &sh, &rhs);
+
+    let (gamma_x, _) = gamma.get_xy();
+    let domain = Num::constant(C::Base::from_u64(OUTPUT_DOMAIN));
+    RescueGadget::hash_to_field(cs.namespace(|| "output"), &[domain, gamma_x])
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{
+        circuits::Circuit,
+        dev::GadgetCheck,
+        fields::{Fp, Fq},
+        Basic, Ec1,
+    };
+
+    struct TestCircuit {
+        pk: Ec1,
+        alpha: Fp,
+        output: Fp,
+        proof: VrfProof<Ec1>,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let pk = CurvePoint::witness(cs.namespace(|| "pk"), || Ok(self.pk))?;
+            let alpha = AllocatedNum::alloc_input(cs.namespace(|| "alpha"), || Ok(self.alpha))?;
+            let expected =
+                AllocatedNum::alloc_input(cs.namespace(|| "output"), || Ok(self.output))?;
+
+            let output = verify(
+                cs.namespace(|| "vrf"),
+                &pk,
+                &alpha.into(),
+                Some(&self.proof),
+            )?;
+            cs.enforce_zero(output.lc() - &expected.lc());
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_vrf() {
+        let sk = Fq::from_u64(0x1234_5678_9abc);
+        let pk = Ec1::one() * sk;
+        // An input that skips a candidate, so that one is checked off the
+        // curve.
+        let alpha = (0..)
+            .map(Fp::from_u64)
+            .find(|alpha| hash_to_curve_native(pk, *alpha).unwrap().1 > 0)
+            .unwrap();
+        let (output, proof) = prove::<Ec1>(sk, alpha).unwrap();
+        assert_eq!(verify_native(pk, alpha, &proof), Some(output));
+
+        let other_alpha = alpha + Fp::one();
+        let (other_output, other_proof) = prove::<Ec1>(sk, other_alpha).unwrap();
+        assert!(output != other_output);
+        assert_eq!(verify_native(pk, other_alpha, &proof), None);
+        let forged = VrfProof {
+            gamma: proof.gamma.double(),
+            ..proof
+        };
+        assert_eq!(verify_native(pk, alpha, &forged), None);
+        assert_eq!(verify_native(Ec1::one(), alpha, &proof), None);
+
+        let circuit = |alpha, output, proof| TestCircuit {
+            pk,
+            alpha,
+            output,
+            proof,
+        };
+        GadgetCheck::new(circuit(alpha, output, proof), &[alpha, output])
+            .malformed(
+                "wrong output",
+                circuit(alpha, other_output, proof),
+                &[alpha, other_output],
+            )
+            .malformed(
+                "proof for another input",
+                circuit(alpha, other_output, other_proof),
+                &[alpha, other_output],
+            )
+            .malformed(
+                "wrong Gamma",
+                circuit(alpha, output_native(forged.gamma), forged),
+                &[alpha, output_native(forged.gamma)],
+            )
+            .budget(24541, 49879)
+            .check::<Basic>()
+            .unwrap();
+    }
+}
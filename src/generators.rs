@@ -0,0 +1,154 @@
+//! Hashes that `Params::from_seed_with` derives generators with.
+//!
+//! Each generator is the first valid point encoding among the hashes of the
+//! seed with successive counters, so the generators are fixed by `k`, the
+//! seed and the hash alone. Params files record the hash by its
+//! [`GeneratorHash::id`], so that readers derive the same generators as the
+//! writer did, and applications can match the derivation to a transcript
+//! hash they have already had audited.
+
+use crate::fields::{Field, Fp};
+use crate::rescue::{Rescue, RescueConstants};
+
+/// A hash from a seed and counter to a candidate point encoding.
+pub trait GeneratorHash {
+    /// Identifies the hash in params headers and `Params::digest`. The
+    /// hashes of this module take the ids up to `RescueGenerators::ID`;
+    /// applications supplying their own take ids above 255. `halo-cli` only
+    /// knows the hashes of this module, so it cannot read params files that
+    /// name any other.
+    fn id(&self) -> u32;
+
+    /// The `counter`th candidate encoding for `seed`.
+    fn hash(&self, seed: &[u8], counter: u64) -> [u8; 32];
+}
+
+/// SHA-256("halo generators" || seed || counter), with the counter as 64
+/// little-endian bits. This is the derivation of `Params::from_seed`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Generators;
+
+impl Sha256Generators {
+    pub const ID: u32 = 0;
+}
+
+impl GeneratorHash for Sha256Generators {
+    fn id(&self) -> u32 {
+        Self::ID
+    }
+
+    fn hash(&self, seed: &[u8], counter: u64) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.input(b"halo generators");
+        hasher.input(seed);
+        hasher.input(counter.to_le_bytes());
+
+        let mut attempt = [0u8; 32];
+        attempt.copy_from_slice(&hasher.result());
+        attempt
+    }
+}
+
+/// The first 32 bytes of SHA-512("halo generators" || seed || counter),
+/// with the counter as 64 little-endian bits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha512Generators;
+
+impl Sha512Generators {
+    pub const ID: u32 = 1;
+}
+
+impl GeneratorHash for Sha512Generators {
+    fn id(&self) -> u32 {
+        Self::ID
+    }
+
+    fn hash(&self, seed: &[u8], counter: u64) -> [u8; 32] {
+        use sha2::{Digest, Sha512};
+
+        let mut hasher = Sha512::new();
+        hasher.input(b"halo generators");
+        hasher.input(seed);
+        hasher.input(counter.to_le_bytes());
+
+        let mut attempt = [0u8; 32];
+        attempt.copy_from_slice(&hasher.result()[..32]);
+        attempt
+    }
+}
+
+/// A Rescue sponge over `Fp`, with the constants of
+/// `RescueConstants::from_seed("halo generators")`, that absorbs the length
+/// of the seed, the seed in 16-byte little-endian chunks (the last padded
+/// with zeroes) and the counter, and then squeezes a digest.
+#[derive(Clone, Debug)]
+pub struct RescueGenerators {
+    constants: RescueConstants<Fp>,
+}
+
+impl RescueGenerators {
+    pub const ID: u32 = 2;
+
+    pub fn new() -> Self {
+        RescueGenerators {
+            constants: RescueConstants::from_seed(b"halo generators"),
+        }
+    }
+}
+
+impl Default for RescueGenerators {
+    fn default() -> Self {
+        RescueGenerators::new()
+    }
+}
+
+impl GeneratorHash for RescueGenerators {
+    fn id(&self) -> u32 {
+        Self::ID
+    }
+
+    fn hash(&self, seed: &[u8], counter: u64) -> [u8; 32] {
+        let mut rescue = Rescue::with_constants(self.constants.clone());
+        rescue.absorb(Fp::from_u64(seed.len() as u64));
+        for chunk in seed.chunks(16) {
+            let mut bytes = [0u8; 16];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            rescue.absorb(Fp::from_u128(u128::from_le_bytes(bytes)));
+        }
+        rescue.absorb(Fp::from_u64(counter));
+        rescue.squeeze_digest()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hashes_differ() {
+        let hashes: [&dyn GeneratorHash; 3] = [
+            &Sha256Generators,
+            &Sha512Generators,
+            &RescueGenerators::new(),
+        ];
+        for (i, a) in hashes.iter().enumerate() {
+            assert_eq!(a.id() as usize, i);
+            assert_eq!(a.hash(b"seed", 3), a.hash(b"seed", 3));
+            assert_ne!(a.hash(b"seed", 3), a.hash(b"seed", 4));
+            assert_ne!(a.hash(b"seed", 3), a.hash(b"seeds", 3));
+            for b in &hashes[(i + 1)..] {
+                assert_ne!(a.hash(b"seed", 3), b.hash(b"seed", 3));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rescue_padding() {
+        // The length is absorbed, so trailing zero bytes change the hash.
+        let rescue = RescueGenerators::new();
+        assert_ne!(rescue.hash(b"seed", 0), rescue.hash(b"seed\0", 0));
+        assert_ne!(rescue.hash(&[], 0), rescue.hash(&[0; 16], 0));
+    }
+}
@@ -0,0 +1,698 @@
+//! A payload mode in which only a digest of the application state is public.
+//!
+//! The recursion layer binds every payload bit as a public input, so a
+//! kilobyte-scale state costs thousands of inputs and generators. Wrapping a
+//! circuit in `HashedPayload` makes the payload the 32-byte digest of the
+//! state instead, with the state itself supplied as a witness and rehashed
+//! in-circuit on every step. `MappedPayload` goes further for states that
+//! each step touches little of: it binds the state channel by channel, so
+//! that a step allocates and rehashes only the channels it asks for.
+//!
+//! The digest is chosen by a `PayloadHash`, independently of the Rescue
+//! sponge of the proof transcripts, and is SHA-256 unless the adapters are
+//! given another. Consecutive proofs of a chain are over different fields, so
+//! a payload digest must be recomputable in both; a Rescue digest over one
+//! field cannot be checked by the circuit over the other without non-native
+//! arithmetic.
+
+use std::marker::PhantomData;
+
+use crate::circuits::*;
+use crate::fields::*;
+use crate::gadgets::sha256::sha256;
+use crate::gadgets::sha512::sha512;
+use crate::gadgets::{AllocatedBit, AllocatedNum, Boolean};
+use crate::payload::PayloadLayout;
+
+/// The hash that binds a state to the payload of `HashedPayload` and
+/// `MappedPayload`. It must be computable by circuits over either field of
+/// the cycle. Preimages and digests are ordered as payload bits are: byte by
+/// byte, least significant bit first.
+pub trait PayloadHash {
+    /// The length of a digest in bytes.
+    const DIGEST_LEN: usize;
+
+    fn hash(preimage: &[u8]) -> Vec<u8>;
+
+    fn hash_gadget<F: Field, CS: ConstraintSystem<F>>(
+        cs: CS,
+        preimage: &[Boolean],
+    ) -> Result<Vec<Boolean>, SynthesisError>;
+}
+
+/// SHA-256, the default payload hash.
+pub struct Sha256Payload;
+
+impl PayloadHash for Sha256Payload {
+    const DIGEST_LEN: usize = 32;
+
+    fn hash(preimage: &[u8]) -> Vec<u8> {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.input(preimage);
+        hasher.result().to_vec()
+    }
+
+    fn hash_gadget<F: Field, CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        preimage: &[Boolean],
+    ) -> Result<Vec<Boolean>, SynthesisError> {
+        Ok(msb_first(&sha256(
+            cs.namespace(|| "sha256"),
+            &msb_first(preimage),
+        )?))
+    }
+}
+
+/// SHA-512, for applications that already commit to their state with it. Its
+/// digest takes twice the public inputs of `Sha256Payload`'s.
+pub struct Sha512Payload;
+
+impl PayloadHash for Sha512Payload {
+    const DIGEST_LEN: usize = 64;
+
+    fn hash(preimage: &[u8]) -> Vec<u8> {
+        use sha2::{Digest, Sha512};
+
+        let mut hasher = Sha512::new();
+        hasher.input(preimage);
+        hasher.result().to_vec()
+    }
+
+    fn hash_gadget<F: Field, CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        preimage: &[Boolean],
+    ) -> Result<Vec<Boolean>, SynthesisError> {
+        Ok(msb_first(&sha512(
+            cs.namespace(|| "sha512"),
+            &msb_first(preimage),
+        )?))
+    }
+}
+
+// The SHA-2 gadgets consume and produce bits most significant first, so the
+// bits of each byte are reversed on the way in and out.
+fn msb_first(bits: &[Boolean]) -> Vec<Boolean> {
+    bits.chunks(8)
+        .flat_map(|byte| byte.iter().rev().cloned())
+        .collect()
+}
+
+/// The payload that binds `preimage` under the default payload hash.
+pub fn payload_digest(preimage: &[u8]) -> Vec<u8> {
+    Sha256Payload::hash(preimage)
+}
+
+/// An inner circuit whose state is bound by digest rather than directly.
+/// The preimage bits are ordered as payload bits are: byte by byte, least
+/// significant bit first.
+pub trait HashedPayloadCircuit<F: Field> {
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_preimage: &[AllocatedBit],
+        new_preimage: &[AllocatedBit],
+    ) -> Result<(), SynthesisError>;
+}
+
+/// Adapts a `HashedPayloadCircuit` into a `RecursiveCircuit` whose payload is
+/// the `H` digest of a fixed-length preimage.
+pub struct HashedPayload<C, H = Sha256Payload> {
+    circuit: C,
+    base_preimage: Vec<u8>,
+    old_preimage: Option<Vec<u8>>,
+    new_preimage: Option<Vec<u8>>,
+    _hash: PhantomData<H>,
+}
+
+impl<C> HashedPayload<C> {
+    /// Preimages have the length of `base_preimage`, the state of the base
+    /// case.
+    pub fn new(circuit: C, base_preimage: Vec<u8>) -> Self {
+        Self::with_hash(circuit, base_preimage)
+    }
+}
+
+impl<C, H: PayloadHash> HashedPayload<C, H> {
+    /// Like `new`, but binds the state with `H` rather than SHA-256.
+    pub fn with_hash(circuit: C, base_preimage: Vec<u8>) -> Self {
+        HashedPayload {
+            circuit,
+            base_preimage,
+            old_preimage: None,
+            new_preimage: None,
+            _hash: PhantomData,
+        }
+    }
+
+    /// Supplies the prover with the states before and after this step. The
+    /// payload to prove is `H::hash(&new)`.
+    pub fn with_preimages(mut self, old: Vec<u8>, new: Vec<u8>) -> Self {
+        assert_eq!(old.len(), self.base_preimage.len());
+        assert_eq!(new.len(), self.base_preimage.len());
+        self.old_preimage = Some(old);
+        self.new_preimage = Some(new);
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.circuit
+    }
+}
+
+fn alloc_preimage<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    len: usize,
+    value: Option<&[u8]>,
+) -> Result<Vec<AllocatedBit>, SynthesisError> {
+    let mut bits = Vec::with_capacity(len * 8);
+    for j in 0..len {
+        for i in 0..8 {
+            bits.push(AllocatedBit::alloc(
+                cs.namespace(|| format!("bit {}", 8 * j + i)),
+                || {
+                    let byte = value.ok_or(SynthesisError::AssignmentMissing)?[j];
+                    Ok((byte >> i) & 1 == 1)
+                },
+            )?);
+        }
+    }
+    Ok(bits)
+}
+
+fn enforce_equal<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    a: &[Boolean],
+    b: &[Boolean],
+) -> Result<(), SynthesisError> {
+    if a.len() != b.len() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    for (i, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+        Boolean::enforce_equal(cs.namespace(|| format!("bit {}", i)), a, b)?;
+    }
+
+    Ok(())
+}
+
+fn booleans(bits: &[AllocatedBit]) -> Vec<Boolean> {
+    bits.iter().cloned().map(Boolean::from).collect()
+}
+
+fn enforce_digest<F: Field, CS: ConstraintSystem<F>, H: PayloadHash>(
+    mut cs: CS,
+    preimage: &[AllocatedBit],
+    payload: &[AllocatedBit],
+) -> Result<(), SynthesisError> {
+    let digest = H::hash_gadget(cs.namespace(|| "digest"), &booleans(preimage))?;
+    enforce_equal(cs.namespace(|| "payload"), &digest, &booleans(payload))
+}
+
+impl<F: Field, C: HashedPayloadCircuit<F>, H: PayloadHash> RecursiveCircuit<F>
+    for HashedPayload<C, H>
+{
+    fn base_payload(&self) -> Vec<bool> {
+        let mut bits = vec![];
+        for byte in H::hash(&self.base_preimage) {
+            for i in 0..8 {
+                bits.push((byte >> i) & 1 == 1);
+            }
+        }
+        bits
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        _challenges: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        let len = self.base_preimage.len();
+        let old_preimage = alloc_preimage(
+            cs.namespace(|| "old preimage"),
+            len,
+            self.old_preimage.as_ref().map(|v| &v[..]),
+        )?;
+        let new_preimage = alloc_preimage(
+            cs.namespace(|| "new preimage"),
+            len,
+            self.new_preimage.as_ref().map(|v| &v[..]),
+        )?;
+
+        enforce_digest::<_, _, H>(cs.namespace(|| "old digest"), &old_preimage, old_payload)?;
+        enforce_digest::<_, _, H>(cs.namespace(|| "new digest"), &new_preimage, new_payload)?;
+
+        self.circuit.synthesize(cs, &old_preimage, &new_preimage)
+    }
+}
+
+/// The payload that binds `state` in a `MappedPayload` laid out by `layout`:
+/// the digest of the concatenated digests of its channels.
+pub fn mapped_payload_digest(layout: &PayloadLayout, state: &[u8]) -> Vec<u8> {
+    mapped_payload_digest_with::<Sha256Payload>(layout, state)
+}
+
+/// `mapped_payload_digest` under the payload hash `H`.
+pub fn mapped_payload_digest_with<H: PayloadHash>(layout: &PayloadLayout, state: &[u8]) -> Vec<u8> {
+    let mut digests = vec![];
+    for name in layout.names() {
+        digests.extend(H::hash(layout.get(state, name)));
+    }
+    H::hash(&digests)
+}
+
+/// The channels of a `MappedPayload` state that a step allocated. Bits are
+/// ordered as payload bits are.
+pub struct MappedChannels<'a> {
+    layout: &'a PayloadLayout,
+    bits: Vec<Option<Vec<AllocatedBit>>>,
+}
+
+impl<'a> MappedChannels<'a> {
+    /// The bits of channel `name`, which the step must have requested.
+    pub fn get(&self, name: &str) -> &[AllocatedBit] {
+        let index = self
+            .layout
+            .names()
+            .position(|n| n == name)
+            .unwrap_or_else(|| panic!("unknown payload channel {}", name));
+        self.bits[index]
+            .as_ref()
+            .unwrap_or_else(|| panic!("payload channel {} was not requested", name))
+    }
+}
+
+/// An inner circuit whose state is split into channels and bound by digest,
+/// like a `HashedPayloadCircuit`'s, but of which each step allocates only
+/// the channels it reads or writes.
+pub trait MappedPayloadCircuit<F: Field> {
+    fn layout(&self) -> &PayloadLayout;
+
+    /// The channels of the old state the step reads.
+    fn reads(&self) -> Vec<&str>;
+
+    /// The channels of the new state the step writes. The others are carried
+    /// over from the old state unchanged.
+    fn writes(&self) -> Vec<&str>;
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_state: MappedChannels,
+        new_state: MappedChannels,
+    ) -> Result<(), SynthesisError>;
+}
+
+/// Adapts a `MappedPayloadCircuit` into a `RecursiveCircuit` whose payload is
+/// `mapped_payload_digest_with::<H>` of its state.
+///
+/// Each channel is bound by its own digest, and the payload by the digest of
+/// those, so a step witnesses the digest of every channel but the bits of
+/// only those it requests. A channel it does not read or write costs one
+/// digest however long it is, at the price of hashing the channel digests
+/// on top of the channels a step does touch. This pays off when a state is
+/// large and each step touches little of it.
+pub struct MappedPayload<C, H = Sha256Payload> {
+    circuit: C,
+    base_state: Vec<u8>,
+    old_state: Option<Vec<u8>>,
+    new_state: Option<Vec<u8>>,
+    _hash: PhantomData<H>,
+}
+
+impl<C> MappedPayload<C> {
+    /// States are laid out by the circuit's layout, with `base_state` the
+    /// state of the base case.
+    pub fn new(circuit: C, base_state: Vec<u8>) -> Self {
+        Self::with_hash(circuit, base_state)
+    }
+}
+
+impl<C, H: PayloadHash> MappedPayload<C, H> {
+    /// Like `new`, but binds the state with `H` rather than SHA-256.
+    pub fn with_hash(circuit: C, base_state: Vec<u8>) -> Self {
+        MappedPayload {
+            circuit,
+            base_state,
+            old_state: None,
+            new_state: None,
+            _hash: PhantomData,
+        }
+    }
+
+    /// Supplies the prover with the states before and after this step. The
+    /// payload to prove is `mapped_payload_digest_with::<H>` of `new`.
+    pub fn with_states(mut self, old: Vec<u8>, new: Vec<u8>) -> Self {
+        assert_eq!(old.len(), self.base_state.len());
+        assert_eq!(new.len(), self.base_state.len());
+        self.old_state = Some(old);
+        self.new_state = Some(new);
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.circuit
+    }
+}
+
+impl<F: Field, C: MappedPayloadCircuit<F>, H: PayloadHash> RecursiveCircuit<F>
+    for MappedPayload<C, H>
+{
+    fn base_payload(&self) -> Vec<bool> {
+        let layout = self.circuit.layout();
+        assert_eq!(self.base_state.len(), layout.len());
+
+        let mut bits = vec![];
+        for byte in mapped_payload_digest_with::<H>(layout, &self.base_state) {
+            for i in 0..8 {
+                bits.push((byte >> i) & 1 == 1);
+            }
+        }
+        bits
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        _challenges: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        let layout = self.circuit.layout();
+        let reads = self.circuit.reads();
+        let writes = self.circuit.writes();
+
+        let mut old_digests = vec![];
+        let mut new_digests = vec![];
+        let mut old_channels = vec![];
+        let mut new_channels = vec![];
+        for name in layout.names() {
+            let cs = &mut cs.namespace(|| format!("channel {}", name));
+            let channel = |state: &Option<Vec<u8>>| {
+                state.as_ref().map(|state| layout.get(state, name).to_vec())
+            };
+            let old = channel(&self.old_state);
+            let len = layout.get(&self.base_state, name).len();
+
+            let old_digest = old.as_ref().map(|old| H::hash(old));
+            let old_digest = alloc_preimage(
+                cs.namespace(|| "old digest"),
+                H::DIGEST_LEN,
+                old_digest.as_deref(),
+            )?;
+            let old_digest = booleans(&old_digest);
+
+            let old_bits = if reads.contains(&name) {
+                let bits = alloc_preimage(cs.namespace(|| "old"), len, old.as_deref())?;
+                let digest = H::hash_gadget(cs.namespace(|| "hash old"), &booleans(&bits))?;
+                enforce_equal(cs.namespace(|| "bind old"), &digest, &old_digest)?;
+                Some(bits)
+            } else {
+                None
+            };
+
+            let (new_bits, new_digest) = if writes.contains(&name) {
+                let new = channel(&self.new_state);
+                let bits = alloc_preimage(cs.namespace(|| "new"), len, new.as_deref())?;
+                let digest = H::hash_gadget(cs.namespace(|| "hash new"), &booleans(&bits))?;
+                (Some(bits), digest)
+            } else {
+                (None, old_digest.clone())
+            };
+
+            old_digests.extend(old_digest);
+            new_digests.extend(new_digest);
+            old_channels.push(old_bits);
+            new_channels.push(new_bits);
+        }
+
+        let old_digest = H::hash_gadget(cs.namespace(|| "old digest"), &old_digests)?;
+        enforce_equal(
+            cs.namespace(|| "old payload"),
+            &old_digest,
+            &booleans(old_payload),
+        )?;
+        let new_digest = H::hash_gadget(cs.namespace(|| "new digest"), &new_digests)?;
+        enforce_equal(
+            cs.namespace(|| "new payload"),
+            &new_digest,
+            &booleans(new_payload),
+        )?;
+
+        self.circuit.synthesize(
+            cs,
+            MappedChannels {
+                layout,
+                bits: old_channels,
+            },
+            MappedChannels {
+                layout,
+                bits: new_channels,
+            },
+        )
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{dev::is_satisfied, Basic, Fp};
+
+    // The first byte of the state counts steps; the rest is carried over.
+    struct CountingCircuit;
+
+    impl<F: Field> HashedPayloadCircuit<F> for CountingCircuit {
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            cs: &mut CS,
+            old_preimage: &[AllocatedBit],
+            new_preimage: &[AllocatedBit],
+        ) -> Result<(), SynthesisError> {
+            let mut old = LinearCombination::zero();
+            let mut new = LinearCombination::zero();
+            let mut coeff = F::one();
+            for (a, b) in old_preimage[0..8].iter().zip(new_preimage[0..8].iter()) {
+                old = old + (Coeff::Full(coeff), a.get_variable());
+                new = new + (Coeff::Full(coeff), b.get_variable());
+                coeff = coeff + coeff;
+            }
+            cs.enforce_zero(new - &old - CS::ONE);
+
+            for (a, b) in old_preimage[8..].iter().zip(new_preimage[8..].iter()) {
+                cs.enforce_zero(LinearCombination::from(a.get_variable()) - b.get_variable());
+            }
+
+            Ok(())
+        }
+    }
+
+    struct TestCircuit<H = Sha256Payload> {
+        circuit: HashedPayload<CountingCircuit, H>,
+        old_payload: Vec<u8>,
+        new_payload: Vec<u8>,
+    }
+
+    impl<H: PayloadHash> Circuit<Fp> for TestCircuit<H> {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut alloc = |name: &str, bytes: &[u8]| {
+                let mut bits = vec![];
+                for (j, byte) in bytes.iter().enumerate() {
+                    for i in 0..8 {
+                        bits.push(AllocatedBit::alloc(
+                            cs.namespace(|| format!("{} bit {}", name, 8 * j + i)),
+                            || Ok((byte >> i) & 1 == 1),
+                        )?);
+                    }
+                }
+                Ok(bits)
+            };
+            let old = alloc("old", &self.old_payload)?;
+            let new = alloc("new", &self.new_payload)?;
+
+            RecursiveCircuit::synthesize(&self.circuit, cs, &old, &new, &[])
+        }
+    }
+
+    #[test]
+    fn test_hashed_payload() {
+        let base = vec![0u8; 40];
+        let circuit = HashedPayload::new(CountingCircuit, base.clone());
+        let base_payload = RecursiveCircuit::<Fp>::base_payload(&circuit);
+        assert_eq!(base_payload.len(), 256);
+        assert_eq!(base_payload[0], payload_digest(&base)[0] & 1 == 1);
+
+        let satisfied = |old: Vec<u8>, new: Vec<u8>, new_payload: Vec<u8>| {
+            is_satisfied::<_, _, Basic>(
+                &TestCircuit {
+                    circuit: HashedPayload::new(CountingCircuit, base.clone())
+                        .with_preimages(old.clone(), new),
+                    old_payload: payload_digest(&old),
+                    new_payload,
+                },
+                &[],
+            )
+            .is_ok()
+        };
+
+        let mut old = vec![7u8; 40];
+        old[0] = 3;
+        let mut new = old.clone();
+        new[0] = 4;
+        assert!(satisfied(old.clone(), new.clone(), payload_digest(&new)));
+
+        // The payload must be the digest of the new state.
+        assert!(!satisfied(old.clone(), new.clone(), payload_digest(&old)));
+
+        // The inner circuit still constrains the transition.
+        let mut skipped = old.clone();
+        skipped[0] = 5;
+        assert!(!satisfied(old, skipped.clone(), payload_digest(&skipped)));
+    }
+
+    #[test]
+    fn test_payload_hash() {
+        let base = vec![0u8; 40];
+        let circuit = HashedPayload::<_, Sha512Payload>::with_hash(CountingCircuit, base.clone());
+        let base_payload = RecursiveCircuit::<Fp>::base_payload(&circuit);
+        assert_eq!(base_payload.len(), 512);
+        assert_eq!(base_payload[0], Sha512Payload::hash(&base)[0] & 1 == 1);
+
+        let satisfied = |old: Vec<u8>, new: Vec<u8>, new_payload: Vec<u8>| {
+            is_satisfied::<_, _, Basic>(
+                &TestCircuit {
+                    circuit: HashedPayload::<_, Sha512Payload>::with_hash(
+                        CountingCircuit,
+                        base.clone(),
+                    )
+                    .with_preimages(old.clone(), new),
+                    old_payload: Sha512Payload::hash(&old),
+                    new_payload,
+                },
+                &[],
+            )
+            .is_ok()
+        };
+
+        let old = vec![3u8; 40];
+        let mut new = old.clone();
+        new[0] = 4;
+        assert!(satisfied(
+            old.clone(),
+            new.clone(),
+            Sha512Payload::hash(&new)
+        ));
+
+        // The state is bound by the configured hash, not the default one.
+        let mut sha256 = payload_digest(&new);
+        sha256.resize(64, 0);
+        assert!(!satisfied(old, new, sha256));
+    }
+
+    // Reads and writes the counter, and carries over the rest of the state
+    // without allocating it.
+    struct MappedCountingCircuit {
+        layout: PayloadLayout,
+    }
+
+    impl MappedCountingCircuit {
+        fn new() -> Self {
+            MappedCountingCircuit {
+                layout: PayloadLayout::new()
+                    .channel("counter", 1)
+                    .channel("log", 40),
+            }
+        }
+    }
+
+    impl<F: Field> MappedPayloadCircuit<F> for MappedCountingCircuit {
+        fn layout(&self) -> &PayloadLayout {
+            &self.layout
+        }
+
+        fn reads(&self) -> Vec<&str> {
+            vec!["counter"]
+        }
+
+        fn writes(&self) -> Vec<&str> {
+            vec!["counter"]
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            cs: &mut CS,
+            old_state: MappedChannels,
+            new_state: MappedChannels,
+        ) -> Result<(), SynthesisError> {
+            CountingCircuit.synthesize(cs, old_state.get("counter"), new_state.get("counter"))
+        }
+    }
+
+    struct MappedTestCircuit {
+        circuit: MappedPayload<MappedCountingCircuit>,
+        old_payload: Vec<u8>,
+        new_payload: Vec<u8>,
+    }
+
+    impl Circuit<Fp> for MappedTestCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let old = alloc_preimage(cs.namespace(|| "old"), 32, Some(&self.old_payload))?;
+            let new = alloc_preimage(cs.namespace(|| "new"), 32, Some(&self.new_payload))?;
+
+            RecursiveCircuit::synthesize(&self.circuit, cs, &old, &new, &[])
+        }
+    }
+
+    #[test]
+    fn test_mapped_payload() {
+        let layout = MappedCountingCircuit::new().layout;
+        let base = vec![0u8; 41];
+        let circuit = MappedPayload::new(MappedCountingCircuit::new(), base.clone());
+        let base_payload = RecursiveCircuit::<Fp>::base_payload(&circuit);
+        assert_eq!(base_payload.len(), 256);
+        assert_eq!(
+            base_payload[0],
+            mapped_payload_digest(&layout, &base)[0] & 1 == 1
+        );
+
+        let satisfied = |old: Vec<u8>, new: Vec<u8>, new_payload: Vec<u8>| {
+            is_satisfied::<_, _, Basic>(
+                &MappedTestCircuit {
+                    circuit: MappedPayload::new(MappedCountingCircuit::new(), base.clone())
+                        .with_states(old.clone(), new),
+                    old_payload: mapped_payload_digest(&layout, &old),
+                    new_payload,
+                },
+                &[],
+            )
+            .is_ok()
+        };
+
+        let mut old = vec![7u8; 41];
+        old[0] = 3;
+        let mut new = old.clone();
+        new[0] = 4;
+        let payload = |state: &[u8]| mapped_payload_digest(&layout, state);
+        assert!(satisfied(old.clone(), new.clone(), payload(&new)));
+
+        // The payload must be the digest of the new state.
+        assert!(!satisfied(old.clone(), new.clone(), payload(&old)));
+        assert!(!satisfied(old.clone(), new.clone(), payload_digest(&new)));
+
+        // Channels the step does not write are carried over.
+        let mut rewritten = new.clone();
+        rewritten[1] = 8;
+        assert!(!satisfied(
+            old.clone(),
+            rewritten.clone(),
+            payload(&rewritten)
+        ));
+
+        // The inner circuit still constrains the transition.
+        let mut skipped = old.clone();
+        skipped[0] = 5;
+        assert!(!satisfied(old, skipped.clone(), payload(&skipped)));
+    }
+}
@@ -0,0 +1,222 @@
+//! Retention of intermediate states of a chain, so that an old state can be
+//! audited or the computation forked from it.
+
+use crate::circuits::*;
+use crate::curves::*;
+use crate::ivc::IvcState;
+use crate::proofs::*;
+use std::collections::BTreeMap;
+
+/// A retained state. The chain alternates between the two curves of the
+/// cycle, so states at even steps have their proof over `E1` and those at
+/// odd steps over `E2`.
+#[derive(Clone)]
+pub enum Checkpoint<E1: Curve, E2: Curve> {
+    Even(IvcState<E1, E2>),
+    Odd(IvcState<E2, E1>),
+}
+
+impl<E1, E2> Checkpoint<E1, E2>
+where
+    E1: Curve<Base = <E2 as Curve>::Scalar>,
+    E2: Curve<Base = <E1 as Curve>::Scalar>,
+{
+    pub fn step(&self) -> u64 {
+        match self {
+            Checkpoint::Even(state) => state.step(),
+            Checkpoint::Odd(state) => state.step(),
+        }
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        match self {
+            Checkpoint::Even(state) => state.payload(),
+            Checkpoint::Odd(state) => state.payload(),
+        }
+    }
+
+    /// Fully verifies the retained proof. `e1params` and `e2params` are the
+    /// parameters of step zero of the chain.
+    pub fn verify<CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>>(
+        &self,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+        circuit: &CS,
+    ) -> Result<bool, SynthesisError> {
+        match self {
+            Checkpoint::Even(state) => state.proof().verify(e1params, e2params, circuit),
+            Checkpoint::Odd(state) => state.proof().verify(e2params, e1params, circuit),
+        }
+    }
+}
+
+/// Keeps the state of every `interval`th step of a chain, optionally
+/// bounded to the most recent `max_checkpoints` of them.
+#[derive(Clone)]
+pub struct ProofHistory<E1: Curve, E2: Curve> {
+    interval: u64,
+    max_checkpoints: Option<usize>,
+    checkpoints: BTreeMap<u64, Checkpoint<E1, E2>>,
+}
+
+impl<E1, E2> ProofHistory<E1, E2>
+where
+    E1: Curve<Base = <E2 as Curve>::Scalar>,
+    E2: Curve<Base = <E1 as Curve>::Scalar>,
+{
+    pub fn new(interval: u64) -> Self {
+        assert!(interval > 0);
+
+        ProofHistory {
+            interval,
+            max_checkpoints: None,
+            checkpoints: BTreeMap::new(),
+        }
+    }
+
+    /// Discards the oldest checkpoints once more than `max_checkpoints` are
+    /// retained.
+    pub fn with_max_checkpoints(mut self, max_checkpoints: usize) -> Self {
+        assert!(max_checkpoints > 0);
+        self.max_checkpoints = Some(max_checkpoints);
+        self.prune();
+        self
+    }
+
+    fn prune(&mut self) {
+        if let Some(max) = self.max_checkpoints {
+            while self.checkpoints.len() > max {
+                let oldest = *self.checkpoints.keys().next().unwrap();
+                self.checkpoints.remove(&oldest);
+            }
+        }
+    }
+
+    fn record(&mut self, checkpoint: Checkpoint<E1, E2>) -> bool {
+        let step = checkpoint.step();
+        if !step.is_multiple_of(self.interval) {
+            return false;
+        }
+
+        self.checkpoints.insert(step, checkpoint);
+        self.prune();
+        true
+    }
+
+    /// Offers the state of an even step to the history, which keeps it if
+    /// the step falls on the interval. Returns whether it was kept.
+    pub fn record_even(&mut self, state: &IvcState<E1, E2>) -> bool {
+        assert_eq!(state.step() % 2, 0);
+        self.record(Checkpoint::Even(state.clone()))
+    }
+
+    /// Offers the state of an odd step to the history.
+    pub fn record_odd(&mut self, state: &IvcState<E2, E1>) -> bool {
+        assert_eq!(state.step() % 2, 1);
+        self.record(Checkpoint::Odd(state.clone()))
+    }
+
+    pub fn get(&self, step: u64) -> Option<&Checkpoint<E1, E2>> {
+        self.checkpoints.get(&step)
+    }
+
+    /// The most recent checkpoint at or before `step`.
+    pub fn latest_at_or_before(&self, step: u64) -> Option<&Checkpoint<E1, E2>> {
+        self.checkpoints
+            .range(..=step)
+            .next_back()
+            .map(|(_, checkpoint)| checkpoint)
+    }
+
+    /// The steps for which a checkpoint is retained, in increasing order.
+    pub fn steps(&self) -> Vec<u64> {
+        self.checkpoints.keys().cloned().collect()
+    }
+
+    /// Forks the chain at `step`: checkpoints after it are discarded and the
+    /// state at `step` is returned so the computation can continue from it
+    /// along a different path.
+    pub fn branch(&mut self, step: u64) -> Option<Checkpoint<E1, E2>> {
+        let checkpoint = self.checkpoints.get(&step)?.clone();
+        self.checkpoints.split_off(&(step + 1));
+        Some(checkpoint)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::fixtures::{phony_state, TrivialCircuit};
+
+    #[test]
+    fn test_retention() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+        let mut history = ProofHistory::new(3).with_max_checkpoints(3);
+
+        for step in 0..12 {
+            let kept = if step % 2 == 0 {
+                history.record_even(&phony_state(&e1params, &e2params, step))
+            } else {
+                history.record_odd(&phony_state(&e2params, &e1params, step))
+            };
+            assert_eq!(kept, step % 3 == 0);
+        }
+
+        assert_eq!(history.steps(), vec![3, 6, 9]);
+        assert!(history.get(0).is_none());
+        assert_eq!(history.latest_at_or_before(8).unwrap().step(), 6);
+        assert!(history.latest_at_or_before(2).is_none());
+
+        match history.get(9).unwrap() {
+            Checkpoint::Odd(state) => assert_eq!(state.payload(), &[9]),
+            Checkpoint::Even(_) => panic!("step 9 is odd"),
+        }
+    }
+
+    #[test]
+    fn test_branch() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+        let mut history = ProofHistory::new(2);
+
+        for step in (0..8).step_by(2) {
+            history.record_even(&phony_state(&e1params, &e2params, step));
+        }
+
+        let fork = history.branch(4).unwrap();
+        assert_eq!(fork.step(), 4);
+        assert_eq!(fork.payload(), &[4]);
+        assert_eq!(history.steps(), vec![0, 2, 4]);
+        assert!(history.branch(5).is_none());
+    }
+
+    // Recursive proofs of even a trivial circuit need k = 22; run with
+    // `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_checkpoint_verify() {
+        let e1params: Params<Ec1> = Params::new(22);
+        let e2params: Params<Ec0> = Params::new(22);
+        let circuit = TrivialCircuit;
+
+        let even = IvcState::start(&e1params, &e2params, &circuit, &[0; 4]).unwrap();
+        let odd = even
+            .advance(&e1params, &e2params, &circuit, &[1; 4])
+            .unwrap();
+        let mut history = ProofHistory::new(1);
+        history.record_even(&even);
+        history.record_odd(&odd);
+
+        // Both are verified with the params of step zero.
+        for step in 0..2 {
+            let checkpoint = history.get(step).unwrap();
+            assert!(checkpoint.verify(&e1params, &e2params, &circuit).unwrap());
+        }
+
+        let mut proof = odd.proof().clone();
+        proof.payload[0] ^= 1;
+        let mismatched = Checkpoint::Odd(IvcState::new(proof, 1, &e2params, &e1params));
+        assert!(!mismatched.verify(&e1params, &e2params, &circuit).unwrap());
+    }
+}
@@ -0,0 +1,242 @@
+//! Persistence of an incremental computation, so that a long-running chain
+//! of recursive proofs can be resumed after a crash without replaying every
+//! step.
+
+#[cfg(feature = "prover")]
+use crate::circuits::*;
+use crate::curves::*;
+use crate::encoding::*;
+use crate::proofs::*;
+use crate::recursion::RecursiveProof;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"halo-ivc";
+const VERSION: u32 = 1;
+
+/// The latest proof of a chain together with how many steps it covers and
+/// the parameters it was created with.
+#[derive(Clone)]
+pub struct IvcState<E1: Curve, E2: Curve> {
+    proof: RecursiveProof<E1, E2>,
+    step: u64,
+    e1params_digest: [u8; 32],
+    e2params_digest: [u8; 32],
+}
+
+impl<E1, E2> IvcState<E1, E2>
+where
+    E1: Curve<Base = <E2 as Curve>::Scalar>,
+    E2: Curve<Base = <E1 as Curve>::Scalar>,
+{
+    /// Wraps `proof`, which is the `step`th proof of its chain.
+    pub fn new(
+        proof: RecursiveProof<E1, E2>,
+        step: u64,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+    ) -> Self {
+        IvcState {
+            proof,
+            step,
+            e1params_digest: e1params.digest(),
+            e2params_digest: e2params.digest(),
+        }
+    }
+
+    /// Creates the base case of a chain.
+    #[cfg(feature = "prover")]
+    pub fn start<CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>>(
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+        circuit: &CS,
+        new_payload: &[u8],
+    ) -> Result<Self, SynthesisError> {
+        let proof = RecursiveProof::create_proof(e1params, e2params, None, circuit, new_payload)?;
+
+        Ok(IvcState::new(proof, 0, e1params, e2params))
+    }
+
+    /// Proves the next step of the chain. The next proof is over the other
+    /// curve of the cycle, so the parameters swap places.
+    #[cfg(feature = "prover")]
+    pub fn advance<CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>>(
+        &self,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+        circuit: &CS,
+        new_payload: &[u8],
+    ) -> Result<IvcState<E2, E1>, SynthesisError> {
+        let proof = RecursiveProof::create_proof(
+            e2params,
+            e1params,
+            Some(&self.proof),
+            circuit,
+            new_payload,
+        )?;
+
+        Ok(IvcState {
+            proof,
+            step: self.step + 1,
+            e1params_digest: self.e2params_digest,
+            e2params_digest: self.e1params_digest,
+        })
+    }
+
+    pub fn proof(&self) -> &RecursiveProof<E1, E2> {
+        &self.proof
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        self.proof.payload()
+    }
+
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        write_u32(writer, VERSION)?;
+        write_u64(writer, self.step)?;
+        writer.write_all(&self.e1params_digest)?;
+        writer.write_all(&self.e2params_digest)?;
+        self.proof.write(writer)
+    }
+
+    /// Reads a state and checks that it was created with `e1params` and
+    /// `e2params` and that the proof is shaped for them.
+    pub fn read<R: Read>(
+        reader: &mut R,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+    ) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an IVC state"));
+        }
+        if read_u32(reader)? != VERSION {
+            return Err(invalid_data("unsupported IVC state version"));
+        }
+        let step = read_u64(reader)?;
+        let mut e1params_digest = [0u8; 32];
+        reader.read_exact(&mut e1params_digest)?;
+        let mut e2params_digest = [0u8; 32];
+        reader.read_exact(&mut e2params_digest)?;
+        let proof = RecursiveProof::read(reader)?;
+
+        let mut trailing = [0u8; 1];
+        if reader.read(&mut trailing)? != 0 {
+            return Err(invalid_data("trailing bytes after IVC state"));
+        }
+
+        if e1params_digest != e1params.digest() || e2params_digest != e2params.digest() {
+            return Err(invalid_data("IVC state was created with different params"));
+        }
+
+        if let Err(e) = proof.validate_shape(e1params, e2params) {
+            return Err(invalid_data(&format!(
+                "IVC state proof does not match params: {}",
+                e
+            )));
+        }
+
+        Ok(IvcState {
+            proof,
+            step,
+            e1params_digest,
+            e2params_digest,
+        })
+    }
+
+    /// Writes the state to `path`. The state is first written to a
+    /// temporary file alongside it and then renamed into place, so a crash
+    /// midway leaves any previous state intact.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        let mut bytes = vec![];
+        self.write(&mut bytes)?;
+        {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+    ) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        IvcState::read(&mut &bytes[..], e1params, e2params)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::fixtures::phony_state;
+
+    #[test]
+    fn test_save_load() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+        let state = phony_state(&e1params, &e2params, 7);
+
+        let path = std::env::temp_dir().join(format!("halo-ivc-{}", std::process::id()));
+        state.save(&path).unwrap();
+
+        let loaded = IvcState::load(&path, &e1params, &e2params).unwrap();
+        assert_eq!(loaded.step(), 7);
+        assert_eq!(loaded.payload(), &[7]);
+
+        let mut expected = vec![];
+        state.write(&mut expected).unwrap();
+        let mut actual = vec![];
+        loaded.write(&mut actual).unwrap();
+        assert_eq!(expected, actual);
+
+        // Loading with other params fails.
+        let other: Params<Ec0> = Params::new(4);
+        assert!(IvcState::load(&path, &e1params, &other).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_rejects_corruption() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+        let state = phony_state(&e1params, &e2params, 7);
+
+        let mut bytes = vec![];
+        state.write(&mut bytes).unwrap();
+        assert!(IvcState::read(&mut &bytes[..], &e1params, &e2params).is_ok());
+
+        let mut truncated = bytes.clone();
+        truncated.pop();
+        assert!(IvcState::read(&mut &truncated[..], &e1params, &e2params).is_err());
+
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(IvcState::read(&mut &extended[..], &e1params, &e2params).is_err());
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] ^= 1;
+        assert!(IvcState::read(&mut &bad_magic[..], &e1params, &e2params).is_err());
+
+        // A proof that decodes but is not shaped for the params.
+        let mut narrow = phony_state(&e1params, &e2params, 7);
+        narrow.proof.proof.inner_product.rounds[0].l.pop();
+        let mut bytes = vec![];
+        narrow.write(&mut bytes).unwrap();
+        assert!(IvcState::read(&mut &bytes[..], &e1params, &e2params).is_err());
+    }
+}
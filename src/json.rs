@@ -0,0 +1,232 @@
+//! Human-readable JSON renderings of proofs and the data that accompanies
+//! them, for diagnosing recursion failures.
+//!
+//! Fields are named as in the structs they render. Scalars are strings of
+//! big-endian hex, as `Debug` prints them, and points are objects of their
+//! affine `x` and `y`, or `null` for the identity. Byte strings are
+//! unprefixed hex. The output is meant to be read rather than parsed back;
+//! use the binary encoding to store proofs.
+
+use crate::curves::*;
+use crate::fields::*;
+use crate::proofs::*;
+use crate::recursion::RecursiveProof;
+
+/// A JSON object under construction, from values that are already rendered.
+pub(crate) struct Object(Vec<(&'static str, String)>);
+
+impl Object {
+    pub(crate) fn new() -> Self {
+        Object(vec![])
+    }
+
+    pub(crate) fn field(mut self, name: &'static str, value: String) -> Self {
+        self.0.push((name, value));
+        self
+    }
+
+    pub(crate) fn finish(self) -> String {
+        let fields: Vec<String> = self
+            .0
+            .into_iter()
+            .map(|(name, value)| format!("\"{}\": {}", name, value))
+            .collect();
+        enclose('{', fields, '}')
+    }
+}
+
+// Lays out `items` one per line, indenting any lines they span.
+pub(crate) fn enclose(open: char, items: Vec<String>, close: char) -> String {
+    if items.is_empty() {
+        return format!("{}{}", open, close);
+    }
+
+    let items: Vec<String> = items
+        .into_iter()
+        .map(|item| format!("  {}", item.replace('\n', "\n  ")))
+        .collect();
+    format!("{}\n{}\n{}", open, items.join(",\n"), close)
+}
+
+pub(crate) fn scalar<F: Field>(v: &F) -> String {
+    format!("\"{:?}\"", v)
+}
+
+fn scalars<F: Field>(v: &[F]) -> String {
+    enclose('[', v.iter().map(scalar).collect(), ']')
+}
+
+fn point<C: Curve>(p: &C) -> String {
+    let xy = p.get_xy();
+    if bool::from(xy.is_none()) {
+        return "null".to_string();
+    }
+
+    let (x, y) = xy.unwrap();
+    Object::new()
+        .field("x", scalar(&x))
+        .field("y", scalar(&y))
+        .finish()
+}
+
+fn points<C: Curve>(p: &[C]) -> String {
+    enclose('[', p.iter().map(point).collect(), ']')
+}
+
+pub(crate) fn bytes(v: &[u8]) -> String {
+    let hex: String = v.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("\"{}\"", hex)
+}
+
+impl<C: Curve> Leftovers<C> {
+    pub fn to_json(&self) -> String {
+        Object::new()
+            .field("s_new_commitment", point(&self.s_new_commitment))
+            .field("y_new", scalar(&self.y_new))
+            .field("g_new", point(&self.g_new))
+            .field(
+                "challenges_sq_packed_new",
+                scalars(&self.challenges_sq_packed_new),
+            )
+            .finish()
+    }
+}
+
+impl<F: Field> Deferred<F> {
+    pub fn to_json(&self) -> String {
+        Object::new()
+            .field("x", scalar(&self.x))
+            .field("y_old", scalar(&self.y_old))
+            .field("y_cur", scalar(&self.y_cur))
+            .field("y_new", scalar(&self.y_new))
+            .field("ky_opening", scalar(&self.ky_opening))
+            .field("tx_positive_opening", scalar(&self.tx_positive_opening))
+            .field("tx_negative_opening", scalar(&self.tx_negative_opening))
+            .field("sx_cur_opening", scalar(&self.sx_cur_opening))
+            .field("rx_opening", scalar(&self.rx_opening))
+            .field("rxy_opening", scalar(&self.rxy_opening))
+            .field(
+                "challenges_sq_packed_old",
+                scalars(&self.challenges_sq_packed_old),
+            )
+            .field("gx_old_opening", scalar(&self.gx_old_opening))
+            .field(
+                "challenges_sq_packed_new",
+                scalars(&self.challenges_sq_packed_new),
+            )
+            .field("b_x", scalar(&self.b_x))
+            .field("b_xy", scalar(&self.b_xy))
+            .field("b_y_old", scalar(&self.b_y_old))
+            .field("b_y_cur", scalar(&self.b_y_cur))
+            .field("b_y_new", scalar(&self.b_y_new))
+            .finish()
+    }
+}
+
+impl<C: Curve> MultiPolynomialOpening<C> {
+    pub fn to_json(&self) -> String {
+        let rounds = self
+            .rounds
+            .iter()
+            .map(|round| {
+                Object::new()
+                    .field("L", points(&round.L))
+                    .field("R", points(&round.R))
+                    .field("l", scalars(&round.l))
+                    .field("r", scalars(&round.r))
+                    .finish()
+            })
+            .collect();
+
+        Object::new()
+            .field("rounds", enclose('[', rounds, ']'))
+            .field("a", scalars(&self.a))
+            .field("g", point(&self.g))
+            .finish()
+    }
+}
+
+impl<C: Curve> Proof<C> {
+    pub fn to_json(&self) -> String {
+        Object::new()
+            .field("r_commitment", point(&self.r_commitment))
+            .field("s_cur_commitment", point(&self.s_cur_commitment))
+            .field("t_positive_commitment", point(&self.t_positive_commitment))
+            .field("t_negative_commitment", point(&self.t_negative_commitment))
+            .field("c_commitment", point(&self.c_commitment))
+            .field("s_new_commitment", point(&self.s_new_commitment))
+            .field("rx_opening", scalar(&self.rx_opening))
+            .field("rxy_opening", scalar(&self.rxy_opening))
+            .field("sx_old_opening", scalar(&self.sx_old_opening))
+            .field("sx_cur_opening", scalar(&self.sx_cur_opening))
+            .field("tx_positive_opening", scalar(&self.tx_positive_opening))
+            .field("tx_negative_opening", scalar(&self.tx_negative_opening))
+            .field("sx_new_opening", scalar(&self.sx_new_opening))
+            .field("inner_product", self.inner_product.to_json())
+            .finish()
+    }
+}
+
+impl<E1: Curve, E2: Curve> RecursiveProof<E1, E2> {
+    pub fn to_json(&self) -> String {
+        Object::new()
+            .field("proof", self.proof.to_json())
+            .field("oldproof1", self.oldproof1.to_json())
+            .field("oldproof2", self.oldproof2.to_json())
+            .field("deferred", self.deferred.to_json())
+            .field("payload", bytes(&self.payload))
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::fixtures::phony_proof;
+
+    #[test]
+    fn test_leftovers_json() {
+        let params: Params<Ec1> = Params::new(4);
+        let mut leftovers = Leftovers::dummy(&params);
+        leftovers.y_new = Fq::from_u64(0x1234);
+        leftovers.challenges_sq_packed_new.truncate(2);
+
+        let (x, y) = leftovers.g_new.get_xy().unwrap();
+        let expected = format!(
+            "{{
+  \"s_new_commitment\": null,
+  \"y_new\": \"0x{}1234\",
+  \"g_new\": {{
+    \"x\": \"{:?}\",
+    \"y\": \"{:?}\"
+  }},
+  \"challenges_sq_packed_new\": [
+    \"0x{}0c\",
+    \"0x{}0c\"
+  ]
+}}",
+            "0".repeat(60),
+            x,
+            y,
+            "0".repeat(62),
+            "0".repeat(62)
+        );
+        assert_eq!(leftovers.to_json(), expected);
+    }
+
+    #[test]
+    fn test_recursive_proof_json() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+        let mut proof = phony_proof(&e1params, &e2params, vec![0xab, 0x01]);
+        proof.proof.inner_product.rounds.truncate(1);
+
+        let json = proof.to_json();
+        assert!(json.ends_with("\n  \"payload\": \"ab01\"\n}"));
+        assert!(json.contains("\n      \"rounds\": [\n        {\n          \"L\": [\n"));
+        assert!(json.contains(&format!(
+            "\n    \"y_old\": {},\n",
+            scalar(&proof.deferred.y_old)
+        )));
+    }
+}
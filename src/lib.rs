@@ -1,25 +1,139 @@
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 #[macro_use]
 extern crate hex_literal;
 
 #[macro_use]
 mod util;
 
+#[cfg(feature = "proofs")]
+mod accumulation;
+#[cfg(feature = "proofs")]
+mod challenge;
+#[cfg(feature = "verify")]
+mod chain;
+#[cfg(feature = "proofs")]
 mod circuits;
+#[cfg(feature = "proofs")]
+mod commitment;
+#[cfg(feature = "proofs")]
+mod compose;
+#[cfg(feature = "proofs")]
+mod cost;
+#[cfg(all(feature = "proofs", any(test, feature = "test-utils")))]
+mod corrupt;
+#[cfg(feature = "proofs")]
 mod curves;
+#[cfg(feature = "prover")]
 pub mod dev;
+#[cfg(feature = "prover")]
+pub mod distributed;
+#[cfg(feature = "proofs")]
+mod encoding;
+#[cfg(feature = "verify")]
+pub mod ffi;
+#[cfg(feature = "prover")]
+pub mod fft;
+#[cfg(all(test, feature = "prover"))]
+mod fixtures;
 mod fields;
+#[cfg(feature = "proofs")]
 mod gadgets;
+#[cfg(feature = "verify")]
+mod generators;
+#[cfg(feature = "verify")]
+mod hashed_payload;
+#[cfg(feature = "verify")]
+mod history;
+#[cfg(feature = "verify")]
+mod ivc;
+#[cfg(feature = "proofs")]
+mod json;
+#[cfg(feature = "proofs")]
+mod metrics;
+#[cfg(feature = "proofs")]
+mod payload;
+#[cfg(feature = "proofs")]
+pub mod plain;
+#[cfg(feature = "proofs")]
+pub mod plonkish;
+#[cfg(feature = "proofs")]
 mod proofs;
+#[cfg(feature = "proofs")]
 mod recursion;
+#[cfg(feature = "verify")]
+mod registry;
+#[cfg(feature = "proofs")]
+mod replay;
+#[cfg(feature = "proofs")]
 pub mod rescue;
+#[cfg(feature = "prover")]
+mod scratch;
+#[cfg(feature = "proofs")]
+mod security;
+#[cfg(feature = "proofs")]
+mod shards;
+#[cfg(feature = "proofs")]
 mod synthesis;
+#[cfg(feature = "proofs")]
+pub mod trace;
+#[cfg(feature = "prover")]
+mod tune;
+#[cfg(feature = "verify")]
+mod verifier_cache;
 
+#[cfg(feature = "proofs")]
+pub use accumulation::*;
+#[cfg(feature = "verify")]
+pub use chain::*;
+#[cfg(feature = "proofs")]
+pub use challenge::*;
+#[cfg(feature = "proofs")]
 pub use circuits::*;
+#[cfg(feature = "proofs")]
+pub use commitment::*;
+#[cfg(feature = "proofs")]
+pub use compose::*;
+#[cfg(feature = "proofs")]
+pub use cost::*;
+#[cfg(all(feature = "proofs", any(test, feature = "test-utils")))]
+pub use corrupt::*;
+#[cfg(feature = "proofs")]
 pub use curves::*;
+#[cfg(feature = "proofs")]
+pub use encoding::DecodeLimits;
 pub use fields::*;
+#[cfg(feature = "proofs")]
 pub use gadgets::*;
+#[cfg(feature = "verify")]
+pub use generators::*;
+#[cfg(feature = "verify")]
+pub use hashed_payload::*;
+#[cfg(feature = "verify")]
+pub use history::*;
+#[cfg(feature = "verify")]
+pub use ivc::*;
+#[cfg(feature = "proofs")]
+pub use metrics::{clear_recorder, set_recorder, Metrics, TimedStep};
+#[cfg(feature = "proofs")]
+pub use payload::*;
+#[cfg(feature = "proofs")]
 pub use proofs::*;
+#[cfg(feature = "proofs")]
 pub use recursion::*;
+#[cfg(feature = "verify")]
+pub use registry::*;
+#[cfg(feature = "proofs")]
+pub use replay::*;
+#[cfg(feature = "prover")]
+pub use scratch::{Scratch, ScratchConfig};
+#[cfg(feature = "proofs")]
+pub use security::*;
+#[cfg(feature = "proofs")]
+pub use shards::*;
+#[cfg(feature = "proofs")]
 pub use synthesis::*;
+#[cfg(feature = "prover")]
+pub use tune::AutoTune;
 pub use util::*;
+#[cfg(feature = "verify")]
+pub use verifier_cache::*;
@@ -0,0 +1,147 @@
+//! Hooks for monitoring provers and verifiers.
+//!
+//! Nothing is recorded until a [`Metrics`] recorder is installed with
+//! [`set_recorder`]. From then on, the crate reports to it from whichever
+//! thread does the work, so a recorder typically forwards to counters and
+//! histograms of a metrics library.
+
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+static RECORDER: RwLock<Option<Arc<dyn Metrics>>> = RwLock::new(None);
+
+/// A step of recursive proving or verification that is timed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimedStep {
+    /// `RecursiveProof::create_proof`
+    CreateProof,
+    /// `RecursiveProof::verify` and `RecursiveProof::verify_cached`
+    Verify,
+}
+
+/// Receives measurements from the crate. Every method does nothing by
+/// default, so a recorder implements only those it needs.
+pub trait Metrics: Send + Sync {
+    /// `step` took `duration`, whether or not it succeeded.
+    fn step_duration(&self, _step: TimedStep, _duration: Duration) {}
+
+    /// A recursive proof was created that encodes to `bytes` bytes.
+    fn proof_size(&self, _bytes: usize) {}
+
+    /// A witness was synthesized for a proof, with this many multiplication
+    /// gates and linear constraints.
+    fn constraint_count(&self, _multiplications: usize, _linear: usize) {}
+
+    /// A multiexponentiation of `size` bases was computed.
+    fn msm_size(&self, _size: usize) {}
+
+    /// A `VerifierCache` was consulted, and either held the circuit or not.
+    fn cache_lookup(&self, _hit: bool) {}
+}
+
+/// Installs `recorder`, replacing any that was installed before.
+pub fn set_recorder(recorder: Arc<dyn Metrics>) {
+    *RECORDER.write().unwrap() = Some(recorder);
+}
+
+/// Removes the installed recorder, if any.
+pub fn clear_recorder() {
+    *RECORDER.write().unwrap() = None;
+}
+
+/// Calls `f` with the installed recorder, if there is one.
+pub(crate) fn record<F: FnOnce(&dyn Metrics)>(f: F) {
+    if let Some(recorder) = RECORDER.read().unwrap().as_ref() {
+        f(&**recorder);
+    }
+}
+
+/// Reports the time from its creation to when it is dropped as the duration
+/// of a step, so that steps which fail part way are timed too.
+pub(crate) struct StepTimer {
+    step: TimedStep,
+    start: Instant,
+}
+
+impl StepTimer {
+    pub(crate) fn start(step: TimedStep) -> Self {
+        StepTimer {
+            step,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for StepTimer {
+    fn drop(&mut self) {
+        let duration = self.start.elapsed();
+        record(|m| m.step_duration(self.step, duration));
+    }
+}
+
+/// Whether a recorder is installed, for measurements that cost something to
+/// take.
+#[cfg(feature = "prover")]
+pub(crate) fn is_recording() -> bool {
+    RECORDER.read().unwrap().is_some()
+}
+
+/// Held by tests that install a recorder, so that none replaces another's
+/// while it records.
+#[cfg(all(test, feature = "prover"))]
+pub(crate) static TEST_RECORDER: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::circuits::*;
+    use crate::curves::*;
+    use crate::fields::*;
+    use crate::proofs::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        constraints: Mutex<Vec<(usize, usize)>>,
+        msms: Mutex<Vec<usize>>,
+    }
+
+    impl Metrics for Recorder {
+        fn constraint_count(&self, multiplications: usize, linear: usize) {
+            self.constraints
+                .lock()
+                .unwrap()
+                .push((multiplications, linear));
+        }
+
+        fn msm_size(&self, size: usize) {
+            self.msms.lock().unwrap().push(size);
+        }
+    }
+
+    struct CountedCircuit;
+
+    impl<F: Field> Circuit<F> for CountedCircuit {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let (a, b, _) = cs.multiply(|| "one", || Ok((F::one(), F::one(), F::one())))?;
+            cs.enforce_zero(LinearCombination::from(a) - b);
+            cs.alloc_input(|| "one", || Ok(F::one()))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_recorder() {
+        // Other tests may run concurrently and report to the recorder too, so
+        // only look for this test's measurements among them.
+        let _lock = TEST_RECORDER.lock().unwrap();
+        let recorder = Arc::new(Recorder::default());
+        set_recorder(recorder.clone());
+        let params: Params<Ec1> = Params::new(4);
+        crate::plain::prove(&params, &CountedCircuit).unwrap();
+        clear_recorder();
+
+        assert!(recorder.constraints.lock().unwrap().contains(&(2, 3)));
+        assert!(recorder.msms.lock().unwrap().contains(&params.n));
+    }
+}
@@ -0,0 +1,587 @@
+//! Named channels within a payload, so that an inner circuit can address
+//! e.g. its `state_root` and `fee_accumulator` separately rather than slicing
+//! one opaque byte string by hand.
+
+use crate::circuits::*;
+use crate::fields::*;
+use crate::gadgets::{AllocatedBit, AllocatedNum};
+use std::ops::Range;
+
+/// The byte layout of a payload: an ordered list of named, fixed-length
+/// channels.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PayloadLayout {
+    channels: Vec<(String, usize)>,
+}
+
+impl PayloadLayout {
+    pub fn new() -> Self {
+        PayloadLayout::default()
+    }
+
+    /// Appends a channel of `len` bytes.
+    pub fn channel<N: Into<String>>(mut self, name: N, len: usize) -> Self {
+        let name = name.into();
+        assert!(
+            self.channels.iter().all(|(n, _)| *n != name),
+            "duplicate payload channel {}",
+            name
+        );
+        self.channels.push((name, len));
+        self
+    }
+
+    /// The total length of the payload in bytes.
+    pub fn len(&self) -> usize {
+        self.channels.iter().map(|(_, len)| len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.channels.iter().map(|(name, _)| &name[..])
+    }
+
+    /// The byte range occupied by the channel `name`.
+    pub fn range(&self, name: &str) -> Option<Range<usize>> {
+        let mut start = 0;
+        for (n, len) in &self.channels {
+            if n == name {
+                return Some(start..(start + len));
+            }
+            start += len;
+        }
+        None
+    }
+
+    fn expect_range(&self, name: &str) -> Range<usize> {
+        self.range(name)
+            .unwrap_or_else(|| panic!("unknown payload channel {}", name))
+    }
+
+    /// Concatenates the values of every channel, given in layout order.
+    pub fn encode(&self, values: &[&[u8]]) -> Vec<u8> {
+        assert_eq!(values.len(), self.channels.len());
+
+        let mut payload = Vec::with_capacity(self.len());
+        for ((name, len), value) in self.channels.iter().zip(values.iter()) {
+            assert_eq!(value.len(), *len, "wrong length for channel {}", name);
+            payload.extend_from_slice(value);
+        }
+        payload
+    }
+
+    /// The value of channel `name` within `payload`.
+    pub fn get<'a>(&self, payload: &'a [u8], name: &str) -> &'a [u8] {
+        assert_eq!(payload.len(), self.len());
+        &payload[self.expect_range(name)]
+    }
+
+    /// Overwrites the value of channel `name` within `payload`.
+    pub fn set(&self, payload: &mut [u8], name: &str, value: &[u8]) {
+        assert_eq!(payload.len(), self.len());
+        payload[self.expect_range(name)].copy_from_slice(value);
+    }
+}
+
+/// A value that occupies a payload channel of a fixed number of bytes.
+pub trait PayloadField: Sized {
+    /// The length of the encoding in bytes.
+    const LEN: usize;
+
+    /// Appends the `LEN` bytes encoding this value to `out`.
+    fn encode_into(&self, out: &mut Vec<u8>);
+
+    /// Decodes a value from exactly `LEN` bytes, or returns None if they are
+    /// not a valid encoding.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_payload_field_uint {
+    ($($t:ty),*) => {
+        $(
+            /// Little-endian.
+            impl PayloadField for $t {
+                const LEN: usize = std::mem::size_of::<$t>();
+
+                fn encode_into(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    let mut repr = [0; std::mem::size_of::<$t>()];
+                    if bytes.len() != repr.len() {
+                        return None;
+                    }
+                    repr.copy_from_slice(bytes);
+                    Some(<$t>::from_le_bytes(repr))
+                }
+            }
+        )*
+    };
+}
+
+impl_payload_field_uint!(u8, u16, u32, u64);
+
+impl<const N: usize> PayloadField for [u8; N] {
+    const LEN: usize = N;
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut value = [0; N];
+        if bytes.len() != N {
+            return None;
+        }
+        value.copy_from_slice(bytes);
+        Some(value)
+    }
+}
+
+macro_rules! impl_payload_field_fe {
+    ($($t:ty),*) => {
+        $(
+            /// The canonical 32-byte encoding; non-canonical encodings are
+            /// rejected.
+            impl PayloadField for $t {
+                const LEN: usize = 32;
+
+                fn encode_into(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_bytes());
+                }
+
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    let mut repr = [0; 32];
+                    if bytes.len() != 32 {
+                        return None;
+                    }
+                    repr.copy_from_slice(bytes);
+                    <$t>::from_bytes(&repr).into()
+                }
+            }
+        )*
+    };
+}
+
+impl_payload_field_fe!(Fp, Fq);
+
+/// A struct stored in a payload, with one channel per field named after it.
+/// This is usually derived with `#[derive(Payload)]` from the `halo-derive`
+/// crate, for structs whose fields are all `PayloadField`s.
+pub trait Payload: Sized {
+    fn layout() -> PayloadLayout;
+
+    fn encode(&self) -> Vec<u8>;
+
+    /// Decodes a payload laid out according to `layout`, or returns None if
+    /// its length is wrong or any field is not validly encoded.
+    fn decode(payload: &[u8]) -> Option<Self>;
+
+    /// The value whose encoding is all zeroes, which is every field's zero
+    /// for the `PayloadField`s of this crate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the all-zero payload does not decode.
+    fn base() -> Self {
+        Self::decode(&vec![0; Self::layout().len()]).expect("the zero payload does not decode")
+    }
+
+    /// Allocates the bits of the encoding of `value`, in the order the
+    /// recursion layer allocates payload bits, so that they can be addressed
+    /// with `PayloadChannels`. `value` is None when there is no witness.
+    fn alloc<F: Field, CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        value: Option<&Self>,
+    ) -> Result<Vec<AllocatedBit>, SynthesisError> {
+        let len = Self::layout().len();
+        let bytes = value.map(|v| v.encode());
+        if let Some(bytes) = &bytes {
+            assert_eq!(bytes.len(), len);
+        }
+
+        let mut bits = Vec::with_capacity(len * 8);
+        for i in 0..(len * 8) {
+            bits.push(AllocatedBit::alloc(
+                cs.namespace(|| format!("bit {}", i)),
+                || {
+                    let bytes = bytes.as_ref().ok_or(SynthesisError::AssignmentMissing)?;
+                    Ok((bytes[i / 8] >> (i % 8)) & 1 == 1)
+                },
+            )?);
+        }
+        Ok(bits)
+    }
+}
+
+/// The allocated bits of a payload, addressable by channel. Bits are in the
+/// order the recursion layer allocates them: byte by byte, least significant
+/// bit first.
+#[derive(Clone, Copy)]
+pub struct PayloadChannels<'a> {
+    layout: &'a PayloadLayout,
+    bits: &'a [AllocatedBit],
+}
+
+impl<'a> PayloadChannels<'a> {
+    pub fn new(layout: &'a PayloadLayout, bits: &'a [AllocatedBit]) -> Self {
+        assert_eq!(bits.len(), layout.len() * 8);
+        PayloadChannels { layout, bits }
+    }
+
+    /// The bits of channel `name`.
+    pub fn get(&self, name: &str) -> &'a [AllocatedBit] {
+        let range = self.layout.expect_range(name);
+        &self.bits[(range.start * 8)..(range.end * 8)]
+    }
+
+    /// Every bit of the payload.
+    pub fn bits(&self) -> &'a [AllocatedBit] {
+        self.bits
+    }
+}
+
+/// An inner circuit whose payload is split into named channels.
+pub trait ChanneledCircuit<F: Field> {
+    fn layout(&self) -> &PayloadLayout;
+
+    /// The payload of the base case, laid out according to `layout`.
+    fn base_payload(&self) -> Vec<u8>;
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_payload: PayloadChannels,
+        new_payload: PayloadChannels,
+    ) -> Result<(), SynthesisError>;
+}
+
+/// The bits of `payload` in the order the recursion layer allocates them,
+/// as `RecursiveCircuit::base_payload` returns them.
+pub fn payload_bits(payload: &[u8]) -> Vec<bool> {
+    let mut bits = Vec::with_capacity(payload.len() * 8);
+    for byte in payload {
+        for i in 0..8 {
+            bits.push((byte >> i) & 1 == 1);
+        }
+    }
+    bits
+}
+
+/// Adapts a `ChanneledCircuit` into a `RecursiveCircuit`.
+pub struct Channeled<C>(pub C);
+
+impl<F: Field, C: ChanneledCircuit<F>> RecursiveCircuit<F> for Channeled<C> {
+    fn base_payload(&self) -> Vec<bool> {
+        let payload = self.0.base_payload();
+        assert_eq!(payload.len(), self.0.layout().len());
+
+        payload_bits(&payload)
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        _challenges: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        let layout = self.0.layout();
+        if old_payload.len() != layout.len() * 8 || new_payload.len() != layout.len() * 8 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        self.0.synthesize(
+            cs,
+            PayloadChannels::new(layout, old_payload),
+            PayloadChannels::new(layout, new_payload),
+        )
+    }
+}
+
+/// An inner circuit whose payload is the encoding of a `Payload` struct,
+/// from which the base payload is derived.
+pub trait StateCircuit<F: Field> {
+    type State: Payload;
+
+    /// The state of the base case. Defaults to `Payload::base`.
+    fn base_state(&self) -> Self::State {
+        Self::State::base()
+    }
+
+    /// How many challenges `synthesize` is given, as in `RecursiveCircuit`.
+    fn num_challenges(&self) -> usize {
+        0
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_state: PayloadChannels,
+        new_state: PayloadChannels,
+        challenges: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError>;
+}
+
+/// Adapts a `StateCircuit` into a `RecursiveCircuit`.
+pub struct Stateful<C>(pub C);
+
+impl<F: Field, C: StateCircuit<F>> RecursiveCircuit<F> for Stateful<C> {
+    fn base_payload(&self) -> Vec<bool> {
+        payload_bits(&self.0.base_state().encode())
+    }
+
+    fn num_challenges(&self) -> usize {
+        self.0.num_challenges()
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        challenges: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        let layout = C::State::layout();
+        if old_payload.len() != layout.len() * 8 || new_payload.len() != layout.len() * 8 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        self.0.synthesize(
+            cs,
+            PayloadChannels::new(&layout, old_payload),
+            PayloadChannels::new(&layout, new_payload),
+            challenges,
+        )
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{dev::is_satisfied, Basic, Fp};
+
+    // The counter channel increments by one each step, and the tag channel
+    // is carried over unchanged.
+    struct CounterCircuit {
+        layout: PayloadLayout,
+    }
+
+    impl CounterCircuit {
+        fn new() -> Self {
+            CounterCircuit {
+                layout: PayloadLayout::new().channel("counter", 1).channel("tag", 2),
+            }
+        }
+    }
+
+    impl<F: Field> ChanneledCircuit<F> for CounterCircuit {
+        fn layout(&self) -> &PayloadLayout {
+            &self.layout
+        }
+
+        fn base_payload(&self) -> Vec<u8> {
+            self.layout.encode(&[&[0], &[0xca, 0xfe]])
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            cs: &mut CS,
+            old_payload: PayloadChannels,
+            new_payload: PayloadChannels,
+        ) -> Result<(), SynthesisError> {
+            let pack = |bits: &[AllocatedBit]| {
+                let mut lc = LinearCombination::zero();
+                let mut coeff = F::one();
+                for bit in bits {
+                    lc = lc + (Coeff::Full(coeff), bit.get_variable());
+                    coeff = coeff + coeff;
+                }
+                lc
+            };
+
+            let old = pack(old_payload.get("counter"));
+            let new = pack(new_payload.get("counter"));
+            cs.enforce_zero(new - &old - CS::ONE);
+
+            for (a, b) in old_payload.get("tag").iter().zip(new_payload.get("tag")) {
+                cs.enforce_zero(LinearCombination::from(a.get_variable()) - b.get_variable());
+            }
+
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct CounterState {
+        counter: u8,
+        tag: u16,
+    }
+
+    impl Payload for CounterState {
+        fn layout() -> PayloadLayout {
+            CounterCircuit::new().layout
+        }
+
+        fn encode(&self) -> Vec<u8> {
+            let mut out = vec![];
+            self.counter.encode_into(&mut out);
+            self.tag.encode_into(&mut out);
+            out
+        }
+
+        fn decode(payload: &[u8]) -> Option<Self> {
+            let layout = Self::layout();
+            if payload.len() != layout.len() {
+                return None;
+            }
+            Some(CounterState {
+                counter: PayloadField::decode(layout.get(payload, "counter"))?,
+                tag: PayloadField::decode(layout.get(payload, "tag"))?,
+            })
+        }
+    }
+
+    // The same circuit as `CounterCircuit`, with the base payload derived
+    // from its state.
+    struct CounterStateCircuit;
+
+    impl<F: Field> StateCircuit<F> for CounterStateCircuit {
+        type State = CounterState;
+
+        fn base_state(&self) -> CounterState {
+            CounterState {
+                counter: 0,
+                tag: 0xfeca,
+            }
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            cs: &mut CS,
+            old_state: PayloadChannels,
+            new_state: PayloadChannels,
+            _challenges: &[AllocatedNum<F>],
+        ) -> Result<(), SynthesisError> {
+            ChanneledCircuit::synthesize(&CounterCircuit::new(), cs, old_state, new_state)
+        }
+    }
+
+    struct TestCircuit {
+        old: Vec<u8>,
+        new: Vec<u8>,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut alloc = |bytes: &[u8]| {
+                let mut bits = vec![];
+                for byte in bytes {
+                    for i in 0..8 {
+                        bits.push(AllocatedBit::alloc(&mut *cs, || Ok((byte >> i) & 1 == 1))?);
+                    }
+                }
+                Ok(bits)
+            };
+            let old = alloc(&self.old)?;
+            let new = alloc(&self.new)?;
+
+            RecursiveCircuit::synthesize(&Channeled(CounterCircuit::new()), cs, &old, &new, &[])
+        }
+    }
+
+    struct StatefulTestCircuit {
+        old: CounterState,
+        new: CounterState,
+    }
+
+    impl Circuit<Fp> for StatefulTestCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let old = CounterState::alloc(cs.namespace(|| "old"), Some(&self.old))?;
+            let new = CounterState::alloc(cs.namespace(|| "new"), Some(&self.new))?;
+
+            RecursiveCircuit::synthesize(&Stateful(CounterStateCircuit), cs, &old, &new, &[])
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let layout = PayloadLayout::new().channel("a", 3).channel("b", 1);
+        assert_eq!(layout.len(), 4);
+        assert_eq!(layout.range("b"), Some(3..4));
+        assert_eq!(layout.range("c"), None);
+
+        let mut payload = layout.encode(&[&[1, 2, 3], &[4]]);
+        assert_eq!(layout.get(&payload, "a"), &[1, 2, 3]);
+        layout.set(&mut payload, "b", &[9]);
+        assert_eq!(payload, vec![1, 2, 3, 9]);
+    }
+
+    #[test]
+    fn test_payload_bits() {
+        // Least significant bit first, whatever the platform's byte order.
+        let bits = payload_bits(&[0x01, 0x80]);
+        let mut expected = vec![false; 16];
+        expected[0] = true;
+        expected[15] = true;
+        assert_eq!(bits, expected);
+
+        let mut bytes = vec![];
+        0x0102_0304u32.encode_into(&mut bytes);
+        assert_eq!(bytes, vec![4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_channeled_circuit() {
+        let circuit = Channeled(CounterCircuit::new());
+        let base = RecursiveCircuit::<Fp>::base_payload(&circuit);
+        assert_eq!(base.len(), 24);
+        assert!(!base[0]);
+        assert!(base[9]);
+
+        let satisfied = |old: &[u8], new: &[u8]| {
+            is_satisfied::<_, _, Basic>(
+                &TestCircuit {
+                    old: old.to_vec(),
+                    new: new.to_vec(),
+                },
+                &[],
+            )
+            .is_ok()
+        };
+        assert!(satisfied(&[4, 0xca, 0xfe], &[5, 0xca, 0xfe]));
+        assert!(!satisfied(&[4, 0xca, 0xfe], &[6, 0xca, 0xfe]));
+        assert!(!satisfied(&[4, 0xca, 0xfe], &[5, 0xca, 0xff]));
+    }
+    #[test]
+    fn test_state_circuit() {
+        assert_eq!(CounterState::base(), CounterState { counter: 0, tag: 0 });
+
+        let circuit = Stateful(CounterStateCircuit);
+        assert_eq!(
+            RecursiveCircuit::<Fp>::base_payload(&circuit),
+            RecursiveCircuit::<Fp>::base_payload(&Channeled(CounterCircuit::new()))
+        );
+
+        let satisfied = |old: (u8, u16), new: (u8, u16)| {
+            let circuit = StatefulTestCircuit {
+                old: CounterState {
+                    counter: old.0,
+                    tag: old.1,
+                },
+                new: CounterState {
+                    counter: new.0,
+                    tag: new.1,
+                },
+            };
+            is_satisfied::<_, _, Basic>(&circuit, &[]).is_ok()
+        };
+        assert!(satisfied((4, 7), (5, 7)));
+        assert!(!satisfied((4, 7), (6, 7)));
+        assert!(!satisfied((4, 7), (5, 8)));
+    }
+}
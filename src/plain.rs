@@ -0,0 +1,706 @@
+//! One-shot proofs of a single `Circuit`, for when no recursion is needed.
+//!
+//! These wrap `Proof::new` and the [`Halo`] accumulation scheme,
+//! bootstrapping from `Leftovers::dummy` and deciding the accumulator right
+//! away rather than handing it to a later proof.
+//!
+//! A plain proof over one curve of the cycle can also be verified inside a
+//! circuit over the other with `verify_in_circuit`, so that an application
+//! circuit can attest to many independent proofs at once. The checks that
+//! cannot be made in-circuit are left to the verifier of the enclosing proof,
+//! which completes them with `discharge`. A [`SubProof`] applies this to
+//! many instances of one circuit, proven together as a [`Repeated`] circuit,
+//! so that checking them costs the enclosing circuit the same however many
+//! there are.
+
+use crate::accumulation::*;
+use crate::circuits::*;
+use crate::curves::*;
+use crate::fields::Field;
+use crate::gadgets::*;
+use crate::proofs::*;
+use crate::recursion::ProofVerifier;
+use crate::synthesis::Basic;
+
+/// Creates a proof that `circuit` is satisfied.
+#[cfg(feature = "prover")]
+pub fn prove<C: Curve, CS: Circuit<C::Scalar>>(
+    params: &Params<C>,
+    circuit: &CS,
+) -> Result<Proof<C>, SynthesisError> {
+    let leftovers = Leftovers::dummy(params);
+    let (proof, _) = Proof::new::<_, Basic>(params, circuit, &leftovers)?;
+
+    Ok(proof)
+}
+
+/// Creates a proof that `circuit` is satisfied whose inner product argument
+/// stops once its vectors have length `2^tail`, as `Proof::new_with_tail`
+/// describes. `verify` accepts it like any other proof, but the recursion
+/// does not.
+#[cfg(feature = "prover")]
+pub fn prove_with_tail<C: Curve, CS: Circuit<C::Scalar>>(
+    params: &Params<C>,
+    circuit: &CS,
+    tail: usize,
+) -> Result<Proof<C>, SynthesisError> {
+    let leftovers = Leftovers::dummy(params);
+    let (proof, _) = Proof::new_with_tail::<_, Basic>(params, circuit, &leftovers, tail)?;
+
+    Ok(proof)
+}
+
+/// Fully verifies a proof created by `prove` or `prove_with_tail` against the circuit's public
+/// `inputs`. The circuit is only synthesized for its shape, so it need not
+/// carry a witness.
+pub fn verify<C: Curve, CS: Circuit<C::Scalar>>(
+    params: &Params<C>,
+    circuit: &CS,
+    proof: &Proof<C>,
+    inputs: &[C::Scalar],
+) -> Result<bool, SynthesisError> {
+    let scheme = Halo::new(params, circuit)?;
+    match scheme.accumulate(&[(proof.clone(), inputs.to_vec())], &scheme.init()) {
+        Ok(acc) => Ok(scheme.decide(&acc)),
+        Err(SynthesisError::Unsatisfiable) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Performs the checks of a proof's verification that `verify_in_circuit`
+/// leaves to the verifier, given the deferred values and leftovers that the
+/// proof was tied to in-circuit.
+pub fn discharge<C: Curve, CS: Circuit<C::Scalar>>(
+    params: &Params<C>,
+    circuit: &CS,
+    deferred: &Deferred<C::Scalar>,
+    leftovers: &Leftovers<C>,
+) -> Result<bool, SynthesisError> {
+    Ok(deferred.verify(params.k) && leftovers.verify::<_, Basic>(params, circuit)?)
+}
+
+/// A proof created by `prove`, along with the values its partial
+/// verification produces, as witnessed by `verify_in_circuit`.
+#[derive(Clone)]
+pub struct Witness<C: Curve> {
+    proof: Proof<C>,
+    leftovers: Leftovers<C>,
+    deferred: Deferred<C::Scalar>,
+    forkvalues: Vec<u8>,
+}
+
+impl<C: Curve> Witness<C> {
+    /// Partially verifies `proof` against the circuit's public `inputs`.
+    ///
+    /// Returns `SynthesisError::Unsatisfiable` if the proof does not verify.
+    pub fn new<CS: Circuit<C::Scalar>>(
+        params: &Params<C>,
+        circuit: &CS,
+        proof: &Proof<C>,
+        inputs: &[C::Scalar],
+    ) -> Result<Self, SynthesisError> {
+        let leftovers = Leftovers::dummy(params);
+        let (worked, leftovers, deferred, forkvalues) =
+            proof.verify::<_, Basic>(&leftovers, params, circuit, inputs, None)?;
+        if !worked {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        Ok(Witness {
+            proof: proof.clone(),
+            leftovers,
+            deferred,
+            forkvalues,
+        })
+    }
+
+    /// The deferred values the proof is tied to.
+    pub fn deferred(&self) -> &Deferred<C::Scalar> {
+        &self.deferred
+    }
+
+    /// The leftovers the proof is tied to.
+    pub fn leftovers(&self) -> &Leftovers<C> {
+        &self.leftovers
+    }
+}
+
+/// Verifies a proof created by `prove` inside a circuit over `C::Base`,
+/// given the little-endian bits of each of `circuit`'s public inputs.
+///
+/// Only the group and transcript checks are made in-circuit. The proof is
+/// tied to `deferred` and `leftovers`, the bits of the `to_bytes` encodings
+/// of the `Deferred` and `Leftovers` its verification produces, which the
+/// caller should expose as public inputs so that the verifier of the
+/// enclosing proof can `discharge` them. `witness` is only needed to fill in
+/// the witness.
+pub fn verify_in_circuit<C, CS, Inner>(
+    mut cs: CS,
+    params: &Params<C>,
+    circuit: &Inner,
+    inputs: &[&[AllocatedBit]],
+    deferred: &[AllocatedBit],
+    leftovers: &[AllocatedBit],
+    witness: Option<&Witness<C>>,
+) -> Result<(), SynthesisError>
+where
+    C: Curve,
+    CS: ConstraintSystem<C::Base>,
+    Inner: Circuit<C::Scalar>,
+{
+    let k = params.k;
+    let input_map = compute_input_map::<_, _, Basic>(circuit)?;
+    assert_eq!(inputs.len(), input_map.len() - 1);
+    assert_eq!(deferred.len(), 12 * 256 + (4 + 2 * k) * 128);
+    let layout = LeftoversLayout::new(k);
+    assert_eq!(leftovers.len(), layout.len());
+
+    let generator = |index: usize| {
+        let (x, y) = params.generators[index].get_xy().unwrap();
+        CurvePoint::<C>::constant(x, y)
+    };
+
+    let mut k_commitment = generator(input_map[0]);
+    {
+        let mut cs = cs.namespace(|| "k_commitment");
+        for (i, (index, bits)) in input_map[1..].iter().zip(inputs.iter()).enumerate() {
+            let term = generator(*index).multiply(cs.namespace(|| format!("input {}", i)), bits)?;
+            k_commitment = k_commitment.add(cs.namespace(|| format!("add input {}", i)), &term)?;
+        }
+    }
+
+    // A plain proof is never a base case.
+    let base_case = AllocatedBit::alloc(cs.namespace(|| "is base case"), || Ok(false))?;
+    cs.enforce_zero(LinearCombination::from(base_case.get_variable()));
+
+    // The proof was created from the dummy leftovers, which are fixed.
+    let dummy = Leftovers::dummy(params);
+    let mut old_leftovers = vec![];
+    {
+        let mut cs = cs.namespace(|| "old_leftovers");
+        for (i, bit) in layout.bits(Some(&dummy)).into_iter().enumerate() {
+            let allocated = AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), || Ok(bit))?;
+            if bit {
+                cs.enforce_zero(LinearCombination::from(allocated.get_variable()) - CS::ONE);
+            } else {
+                cs.enforce_zero(LinearCombination::from(allocated.get_variable()));
+            }
+            old_leftovers.push(allocated);
+        }
+    }
+
+    let verifier = ProofVerifier::new(
+        params,
+        witness.map(|witness| &witness.proof),
+        Some(&dummy),
+        witness.map(|witness| &witness.forkvalues[..]),
+    );
+    verifier.verify_proof(
+        cs.namespace(|| "verify proof"),
+        base_case.clone(),
+        &k_commitment,
+        &old_leftovers,
+        deferred,
+        leftovers,
+    )?;
+
+    verifier.equal_unless_base_case(
+        cs.namespace(|| "deferred[challenges] == old_leftovers[challenges]"),
+        base_case.clone(),
+        &deferred[256 * 8..256 * 8 + 128 * k],
+        &old_leftovers[layout.challenges()],
+    )?;
+    verifier.equal_unless_base_case(
+        cs.namespace(|| "deferred[y_old] == old_leftovers[y_old]"),
+        base_case,
+        &deferred[128..128 * 2],
+        &old_leftovers[layout.y_new()],
+    )
+}
+
+/// Many instances of one circuit, synthesized one after another, so that
+/// they can be proven together as a [`SubProof`].
+pub struct Repeated<C>(pub Vec<C>);
+
+impl<F: Field, C: Circuit<F>> Circuit<F> for Repeated<C> {
+    fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        for (i, instance) in self.0.iter().enumerate() {
+            instance.synthesize(&mut cs.namespace(|| format!("instance {}", i)))?;
+        }
+        Ok(())
+    }
+}
+
+/// A proof of a [`Repeated`] circuit, for a circuit over the other curve to
+/// check all of the instances at once by verifying it with
+/// `SubProof::verify`.
+///
+/// The enclosing circuit then costs the same however many instances there
+/// are, apart from the bits of their public inputs. Verifying the proof
+/// costs about `150,988 + 14,221 k` multiplication gates, though, so this
+/// only pays off once the instances cost more than that together. The
+/// verifier of the enclosing proof must also `discharge` the sub-proof, and
+/// that takes time linear in the size of the `Repeated` circuit.
+#[derive(Clone)]
+pub struct SubProof<C: Curve> {
+    witness: Witness<C>,
+    inputs: Vec<C::Scalar>,
+}
+
+impl<C: Curve> SubProof<C> {
+    /// Proves `circuit`, whose public inputs are read back from it, and
+    /// partially verifies the proof against `shape`, the same instances
+    /// without their witnesses.
+    #[cfg(feature = "prover")]
+    pub fn create<Inner: Circuit<C::Scalar>>(
+        params: &Params<C>,
+        circuit: &Repeated<Inner>,
+        shape: &Repeated<Inner>,
+    ) -> Result<Self, SynthesisError> {
+        let proof = prove(params, circuit)?;
+        let inputs = compute_public_inputs::<_, _, Basic>(circuit)?;
+        let witness = Witness::new(params, shape, &proof, &inputs)?;
+
+        Ok(SubProof { witness, inputs })
+    }
+
+    /// The public inputs of the instances, in order.
+    pub fn inputs(&self) -> &[C::Scalar] {
+        &self.inputs
+    }
+
+    /// The proof's partial verification, whose deferred values and
+    /// leftovers the verifier of the enclosing proof discharges.
+    pub fn witness(&self) -> &Witness<C> {
+        &self.witness
+    }
+
+    /// Verifies a sub-proof of `shape` inside a circuit over `C::Base`, as
+    /// `verify_in_circuit` does, given the little-endian bits of each of its
+    /// public inputs.
+    ///
+    /// Returns the bits of the deferred values and leftovers the proof is
+    /// tied to, as `discharged_bits` computes them. The caller must make them
+    /// public, as public inputs or in the payload, so that the verifier of
+    /// the enclosing proof can check them against those it discharges.
+    pub fn verify<CS, Inner>(
+        mut cs: CS,
+        params: &Params<C>,
+        shape: &Repeated<Inner>,
+        inputs: &[&[AllocatedBit]],
+        subproof: Option<&Self>,
+    ) -> Result<Vec<AllocatedBit>, SynthesisError>
+    where
+        CS: ConstraintSystem<C::Base>,
+        Inner: Circuit<C::Scalar>,
+    {
+        let num_deferred = 12 * 256 + (4 + 2 * params.k) * 128;
+        let num_leftovers = LeftoversLayout::new(params.k).len();
+        let values = subproof.map(|subproof| {
+            discharged_bits(subproof.witness.deferred(), subproof.witness.leftovers())
+        });
+
+        let mut bits = Vec::with_capacity(num_deferred + num_leftovers);
+        {
+            let mut cs = cs.namespace(|| "discharged");
+            for i in 0..num_deferred + num_leftovers {
+                bits.push(AllocatedBit::alloc(
+                    cs.namespace(|| format!("bit {}", i)),
+                    || {
+                        values
+                            .as_ref()
+                            .map(|values| values[i])
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    },
+                )?);
+            }
+        }
+
+        verify_in_circuit(
+            cs.namespace(|| "verify sub-proof"),
+            params,
+            shape,
+            inputs,
+            &bits[..num_deferred],
+            &bits[num_deferred..],
+            subproof.map(|subproof| &subproof.witness),
+        )?;
+
+        Ok(bits)
+    }
+}
+
+/// The bits of the `to_bytes` encodings of `deferred` followed by those of
+/// `leftovers`, little-endian within each byte, as `SubProof::verify` returns
+/// them.
+pub fn discharged_bits<C: Curve>(
+    deferred: &Deferred<C::Scalar>,
+    leftovers: &Leftovers<C>,
+) -> Vec<bool> {
+    let mut bytes = deferred.to_bytes();
+    bytes.extend(leftovers.to_bytes());
+
+    let mut bits = Vec::with_capacity(bytes.len() * 8);
+    for byte in bytes {
+        for i in 0..8 {
+            bits.push((byte >> i) & 1 == 1);
+        }
+    }
+    bits
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::dev::is_satisfied;
+    use crate::fields::*;
+    use crate::recursion::RecursiveProof;
+    use crate::security::SecurityLevel;
+
+    struct SquareCircuit<F: Field> {
+        x: Option<F>,
+    }
+
+    impl<F: Field> Circuit<F> for SquareCircuit<F> {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let (a, b, c) = cs.multiply(
+                || "x^2",
+                || {
+                    let x = self.x.ok_or(SynthesisError::AssignmentMissing)?;
+
+                    Ok((x, x, x.square()))
+                },
+            )?;
+            cs.enforce_zero(LinearCombination::from(a) - b);
+
+            let x2 = cs.alloc_input(
+                || "x2",
+                || Ok(self.x.ok_or(SynthesisError::AssignmentMissing)?.square()),
+            )?;
+            cs.enforce_zero(LinearCombination::from(x2) - c);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_plain_proof() {
+        let params: Params<Ec1> = Params::new(4);
+
+        let proof = prove(
+            &params,
+            &SquareCircuit {
+                x: Some(Fq::from(7)),
+            },
+        )
+        .unwrap();
+
+        let verifier_circuit = SquareCircuit { x: None };
+        assert!(verify(&params, &verifier_circuit, &proof, &[Fq::from(49)]).unwrap());
+        assert!(!verify(&params, &verifier_circuit, &proof, &[Fq::from(50)]).unwrap());
+    }
+
+    /// Verifies a proof of `SquareCircuit` over `Ec1` that `input` is a
+    /// square, exposing the values to discharge as public inputs.
+    struct AggregateCircuit<'a> {
+        params: &'a Params<Ec1>,
+        witness: &'a Witness<Ec1>,
+        input: Fq,
+    }
+
+    impl<'a> AggregateCircuit<'a> {
+        fn public_inputs(&self) -> Vec<Fp> {
+            let mut bytes = self.witness.deferred().to_bytes();
+            bytes.extend(self.witness.leftovers().to_bytes());
+
+            let mut inputs = vec![];
+            for byte in bytes {
+                for i in 0..8 {
+                    inputs.push(Fp::from(((byte >> i) & 1) as u64));
+                }
+            }
+            inputs
+        }
+    }
+
+    impl<'a> Circuit<Fp> for AggregateCircuit<'a> {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut alloc_inputs = |name: &str, bytes: Vec<u8>| {
+                let mut bits = vec![];
+                for (j, byte) in bytes.into_iter().enumerate() {
+                    for i in 0..8 {
+                        let bit = AllocatedBit::alloc_input_unchecked(
+                            cs.namespace(|| format!("{} bit {}", name, 8 * j + i)),
+                            || Ok((byte >> i) & 1 == 1),
+                        )?;
+                        bit.check(cs.namespace(|| format!("check {} bit {}", name, 8 * j + i)))?;
+                        bits.push(bit);
+                    }
+                }
+                Ok(bits)
+            };
+            let deferred = alloc_inputs("deferred", self.witness.deferred().to_bytes())?;
+            let leftovers = alloc_inputs("leftovers", self.witness.leftovers().to_bytes())?;
+
+            let mut input = vec![];
+            for (i, bit) in self.input.to_le_bits().into_iter().enumerate() {
+                input.push(AllocatedBit::alloc(
+                    cs.namespace(|| format!("input bit {}", i)),
+                    || Ok(bit),
+                )?);
+            }
+
+            verify_in_circuit(
+                cs.namespace(|| "verify square proof"),
+                self.params,
+                &SquareCircuit::<Fq> { x: None },
+                &[&input],
+                &deferred,
+                &leftovers,
+                Some(self.witness),
+            )
+        }
+    }
+
+    #[test]
+    fn test_prove_with_tail() {
+        let params: Params<Ec1> = Params::new(4);
+        let circuit = SquareCircuit {
+            x: Some(Fq::from(7)),
+        };
+        let verifier_circuit = SquareCircuit { x: None };
+
+        for tail in 0..params.k {
+            let proof = prove_with_tail(&params, &circuit, tail).unwrap();
+            assert_eq!(proof.inner_product.rounds.len(), params.k - tail);
+            assert_eq!(proof.inner_product.a.len(), 5 << tail);
+
+            assert!(verify(&params, &verifier_circuit, &proof, &[Fq::from(49)]).unwrap());
+            assert!(!verify(&params, &verifier_circuit, &proof, &[Fq::from(50)]).unwrap());
+
+            // The vectors sent in the clear are checked in full.
+            let mut tampered = proof.clone();
+            let last = tampered.inner_product.a.len() - 1;
+            tampered.inner_product.a[last] += Fq::one();
+            assert!(!verify(&params, &verifier_circuit, &tampered, &[Fq::from(49)]).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_security_level() {
+        let params: Params<Ec1> = Params::new(4).with_security_level(SecurityLevel::Bits100);
+        let proof = prove(
+            &params,
+            &SquareCircuit {
+                x: Some(Fq::from(3)),
+            },
+        )
+        .unwrap();
+
+        let verifier_circuit = SquareCircuit { x: None };
+        assert!(verify(&params, &verifier_circuit, &proof, &[Fq::from(9)]).unwrap());
+
+        let witness = Witness::new(&params, &verifier_circuit, &proof, &[Fq::from(9)]).unwrap();
+        for challenge in witness.leftovers().challenges() {
+            assert!(SecurityLevel::Bits100.is_packed_challenge(challenge));
+        }
+
+        // The in-circuit verifier derives its challenges at the same level.
+        let circuit = AggregateCircuit {
+            params: &params,
+            witness: &witness,
+            input: Fq::from(9),
+        };
+        assert_eq!(
+            is_satisfied::<_, _, Basic>(&circuit, &circuit.public_inputs()),
+            Ok(true)
+        );
+
+        // Verifying at another level derives different challenges.
+        let params = params.with_security_level(SecurityLevel::Bits128);
+        assert!(!verify(&params, &verifier_circuit, &proof, &[Fq::from(9)]).unwrap());
+    }
+
+    #[test]
+    fn test_deferred_checks() {
+        let params: Params<Ec1> = Params::new(4);
+        let proof = prove(
+            &params,
+            &SquareCircuit {
+                x: Some(Fq::from(5)),
+            },
+        )
+        .unwrap();
+        let verifier_circuit = SquareCircuit { x: None };
+        let witness = Witness::new(&params, &verifier_circuit, &proof, &[Fq::from(25)]).unwrap();
+
+        let checks = witness.deferred().checks(params.k).unwrap();
+        assert!(checks.verify());
+        assert_eq!(checks.iter().count(), 7);
+
+        let mut deferred = witness.deferred().clone();
+        deferred.b_xy += Fq::one();
+        let checks = deferred.checks(params.k).unwrap();
+        assert!(!checks.verify());
+        let failed: Vec<_> = checks
+            .iter()
+            .filter(|(_, check)| !check.holds())
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(failed, vec!["b_xy"]);
+        assert!(deferred.checks(params.k - 1).is_none());
+
+        // The deferred values of the base case hold trivially.
+        let e2params: Params<Ec0> = Params::new(4);
+        let recursive = RecursiveProof::<Ec1, Ec0> {
+            proof,
+            oldproof1: Leftovers::dummy(&params),
+            oldproof2: Leftovers::dummy(&e2params),
+            deferred: Deferred::dummy(e2params.k),
+            payload: vec![],
+        };
+        assert!(recursive.deferred_checks(&e2params).unwrap().verify());
+    }
+
+    #[test]
+    fn test_verify_in_circuit() {
+        let params: Params<Ec1> = Params::new(4);
+        let proof = prove(
+            &params,
+            &SquareCircuit {
+                x: Some(Fq::from(7)),
+            },
+        )
+        .unwrap();
+
+        let verifier_circuit = SquareCircuit { x: None };
+        assert_eq!(
+            Witness::new(&params, &verifier_circuit, &proof, &[Fq::from(50)]).err(),
+            Some(SynthesisError::Unsatisfiable)
+        );
+        let witness = Witness::new(&params, &verifier_circuit, &proof, &[Fq::from(49)]).unwrap();
+        assert!(discharge(
+            &params,
+            &verifier_circuit,
+            witness.deferred(),
+            witness.leftovers()
+        )
+        .unwrap());
+
+        let honest = AggregateCircuit {
+            params: &params,
+            witness: &witness,
+            input: Fq::from(49),
+        };
+        assert_eq!(
+            is_satisfied::<_, _, Basic>(&honest, &honest.public_inputs()),
+            Ok(true)
+        );
+
+        // Claiming the proof is of a different input changes its transcript.
+        let dishonest = AggregateCircuit {
+            input: Fq::from(50),
+            ..honest
+        };
+        assert_ne!(
+            is_satisfied::<_, _, Basic>(&dishonest, &dishonest.public_inputs()),
+            Ok(true)
+        );
+    }
+
+    /// Checks every instance of a `Repeated` square circuit by verifying a
+    /// sub-proof of them, exposing the values to discharge as public inputs.
+    struct SubProofCircuit<'a> {
+        params: &'a Params<Ec1>,
+        subproof: Option<&'a SubProof<Ec1>>,
+        squares: Vec<Fq>,
+    }
+
+    impl<'a> Circuit<Fp> for SubProofCircuit<'a> {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut inputs = vec![];
+            for (j, square) in self.squares.iter().enumerate() {
+                let mut bits = vec![];
+                for (i, bit) in square.to_le_bits().into_iter().enumerate() {
+                    bits.push(AllocatedBit::alloc(
+                        cs.namespace(|| format!("square {} bit {}", j, i)),
+                        || Ok(bit),
+                    )?);
+                }
+                inputs.push(bits);
+            }
+            let inputs: Vec<&[AllocatedBit]> = inputs.iter().map(|bits| &bits[..]).collect();
+
+            let shape = Repeated(
+                self.squares
+                    .iter()
+                    .map(|_| SquareCircuit::<Fq> { x: None })
+                    .collect(),
+            );
+            let discharged = SubProof::verify(
+                cs.namespace(|| "verify squares"),
+                self.params,
+                &shape,
+                &inputs,
+                self.subproof,
+            )?;
+            for (i, bit) in discharged.iter().enumerate() {
+                let input = cs.alloc_input(
+                    || format!("discharged bit {}", i),
+                    || {
+                        bit.get_value()
+                            .map(|bit| if bit { Fp::one() } else { Fp::zero() })
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    },
+                )?;
+                cs.enforce_zero(LinearCombination::from(input) - bit.get_variable());
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_subproof() {
+        let params: Params<Ec1> = Params::new(5);
+        let circuit = Repeated(
+            (2..5)
+                .map(|x| SquareCircuit {
+                    x: Some(Fq::from(x)),
+                })
+                .collect(),
+        );
+        let shape = Repeated((0..3).map(|_| SquareCircuit { x: None }).collect());
+        let squares = vec![Fq::from(4), Fq::from(9), Fq::from(16)];
+
+        let subproof = SubProof::create(&params, &circuit, &shape).unwrap();
+        assert_eq!(subproof.inputs(), &squares[..]);
+        let deferred = subproof.witness().deferred();
+        let leftovers = subproof.witness().leftovers();
+        assert!(discharge(&params, &shape, deferred, leftovers).unwrap());
+
+        let public_inputs: Vec<Fp> = discharged_bits(deferred, leftovers)
+            .into_iter()
+            .map(|bit| if bit { Fp::one() } else { Fp::zero() })
+            .collect();
+        let honest = SubProofCircuit {
+            params: &params,
+            subproof: Some(&subproof),
+            squares,
+        };
+        assert_eq!(
+            is_satisfied::<_, _, Basic>(&honest, &public_inputs),
+            Ok(true)
+        );
+
+        // Claiming the proof is of different squares changes its transcript.
+        let dishonest = SubProofCircuit {
+            squares: vec![Fq::from(4), Fq::from(9), Fq::from(25)],
+            ..honest
+        };
+        assert_ne!(
+            is_satisfied::<_, _, Basic>(&dishonest, &public_inputs),
+            Ok(true)
+        );
+    }
+}
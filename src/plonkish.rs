@@ -0,0 +1,372 @@
+//! Export of synthesized circuits as PLONKish tables, for moving inner
+//! circuits to provers with that arithmetization and for comparing constraint
+//! systems across provers.
+//!
+//! The table has three advice columns `a`, `b` and `c`, with a row for each
+//! multiplication gate, and a single gate `q_mul * (a * b - c) = 0` whose
+//! selector `q_mul` is a fixed column enabled on every row. The linear
+//! constraints of the circuit become copy constraints where they equate two
+//! cells, copy constraints to the instance column where they bind a public
+//! input, and remain linear constraints over cells otherwise. The first row
+//! is the gate that allocates the constant one, as in every circuit.
+
+use crate::circuits::{Circuit, Coeff, SynthesisError, Variable};
+use crate::fields::Field;
+use crate::json::{enclose, scalar, Object};
+use crate::synthesis::{Backend, SynthesisDriver};
+use std::collections::BTreeMap;
+
+/// A column of the table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Column {
+    A,
+    B,
+    C,
+}
+
+impl Column {
+    fn name(&self) -> &'static str {
+        match self {
+            Column::A => "a",
+            Column::B => "b",
+            Column::C => "c",
+        }
+    }
+}
+
+/// An advice cell, by column and row counting from zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cell {
+    pub column: Column,
+    pub row: usize,
+}
+
+impl Cell {
+    fn from_variable(var: Variable) -> Self {
+        match var {
+            Variable::A(index) => Cell {
+                column: Column::A,
+                row: index - 1,
+            },
+            Variable::B(index) => Cell {
+                column: Column::B,
+                row: index - 1,
+            },
+            Variable::C(index) => Cell {
+                column: Column::C,
+                row: index - 1,
+            },
+        }
+    }
+
+    fn json(&self) -> String {
+        format!("[\"{}\", {}]", self.column.name(), self.row)
+    }
+}
+
+/// A circuit laid out as a PLONKish table. See the module documentation for
+/// the layout.
+#[derive(Clone, Debug)]
+pub struct PlonkishTable<F: Field> {
+    /// The number of rows, one per multiplication gate.
+    pub rows: usize,
+    /// The advice values of each row as `[a, b, c]`, if the circuit was
+    /// synthesized with a witness.
+    pub advice: Option<Vec<[F; 3]>>,
+    /// The cell each row of the instance column is copied to.
+    pub instance: Vec<Cell>,
+    /// Pairs of cells constrained to be equal.
+    pub copies: Vec<(Cell, Cell)>,
+    /// The other linear constraints, each a sum of terms equal to zero.
+    pub linear: Vec<Vec<(F, Cell)>>,
+}
+
+impl<F: Field> PlonkishTable<F> {
+    /// Checks that the advice values satisfy the table with `instance` as
+    /// the instance column, leaving out the constant one in its first row.
+    /// Returns `false` if there are no advice values.
+    pub fn is_satisfied(&self, instance: &[F]) -> bool {
+        let advice = match &self.advice {
+            Some(advice) => advice,
+            None => return false,
+        };
+        let value = |cell: &Cell| advice[cell.row][cell.column as usize];
+
+        let mut expected = vec![F::one()];
+        expected.extend_from_slice(instance);
+
+        advice.iter().all(|row| row[0] * row[1] == row[2])
+            && self.instance.len() == expected.len()
+            && self
+                .instance
+                .iter()
+                .zip(expected.iter())
+                .all(|(cell, v)| value(cell) == *v)
+            && self.copies.iter().all(|(l, r)| value(l) == value(r))
+            && self.linear.iter().all(|terms| {
+                terms
+                    .iter()
+                    .fold(F::zero(), |acc, (coeff, cell)| acc + *coeff * value(cell))
+                    == F::zero()
+            })
+    }
+
+    /// Renders the table as JSON, with field elements as in `Debug` and
+    /// cells as `[column, row]`.
+    pub fn to_json(&self) -> String {
+        let cells = |cells: Vec<String>| enclose('[', cells, ']');
+        let mut object = Object::new()
+            .field("rows", self.rows.to_string())
+            .field("advice", "[\"a\", \"b\", \"c\"]".to_string())
+            .field("fixed", "[\"q_mul\"]".to_string())
+            .field("gates", "[\"q_mul * (a * b - c)\"]".to_string())
+            .field(
+                "instance",
+                cells(self.instance.iter().map(Cell::json).collect()),
+            )
+            .field(
+                "copies",
+                cells(
+                    self.copies
+                        .iter()
+                        .map(|(l, r)| format!("[{}, {}]", l.json(), r.json()))
+                        .collect(),
+                ),
+            )
+            .field(
+                "linear",
+                cells(
+                    self.linear
+                        .iter()
+                        .map(|terms| {
+                            cells(
+                                terms
+                                    .iter()
+                                    .map(|(coeff, cell)| {
+                                        format!("[{}, {}]", scalar(coeff), cell.json())
+                                    })
+                                    .collect(),
+                            )
+                        })
+                        .collect(),
+                ),
+            );
+        if let Some(advice) = &self.advice {
+            object = object.field(
+                "assignment",
+                cells(
+                    advice
+                        .iter()
+                        .map(|row| {
+                            format!(
+                                "[{}]",
+                                row.iter().map(scalar).collect::<Vec<_>>().join(", ")
+                            )
+                        })
+                        .collect(),
+                ),
+            );
+        }
+        object.finish()
+    }
+}
+
+/// Lays out `circuit` as a PLONKish table. The advice values are included
+/// if the circuit has a complete witness.
+pub fn export<F: Field, C: Circuit<F>, S: SynthesisDriver>(
+    circuit: &C,
+) -> Result<PlonkishTable<F>, SynthesisError> {
+    struct Recorder<F: Field> {
+        values: Vec<[F; 3]>,
+        missing: bool,
+        constraints: Vec<BTreeMap<Cell, F>>,
+        inputs: Vec<usize>,
+    }
+
+    impl<F: Field> Backend<F> for &mut Recorder<F> {
+        type LinearConstraintIndex = usize;
+
+        fn get_var(&self, var: Variable) -> Option<F> {
+            let cell = Cell::from_variable(var);
+            Some(self.values[cell.row][cell.column as usize])
+        }
+
+        fn set_var<FF, A, AR>(
+            &mut self,
+            _annotation: Option<A>,
+            var: Variable,
+            value: FF,
+        ) -> Result<(), SynthesisError>
+        where
+            FF: FnOnce() -> Result<F, SynthesisError>,
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            let cell = Cell::from_variable(var);
+            match value() {
+                Ok(value) => self.values[cell.row][cell.column as usize] = value,
+                Err(_) => self.missing = true,
+            }
+
+            Ok(())
+        }
+
+        fn new_multiplication_gate<A, AR>(&mut self, _annotation: Option<A>)
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            // Cells left unassigned are zero, as in the prover.
+            self.values.push([F::zero(); 3]);
+        }
+
+        fn new_linear_constraint<A, AR>(&mut self, _annotation: A) -> Self::LinearConstraintIndex
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.constraints.push(BTreeMap::new());
+            self.constraints.len()
+        }
+
+        fn insert_coefficient(&mut self, var: Variable, coeff: Coeff<F>, y: &usize) {
+            *self.constraints[y - 1]
+                .entry(Cell::from_variable(var))
+                .or_insert_with(F::zero) += coeff.value();
+        }
+
+        fn get_for_q(&self, q: usize) -> Self::LinearConstraintIndex {
+            q
+        }
+
+        fn new_k_power(&mut self, index: usize, _value: Option<F>) -> Result<(), SynthesisError> {
+            self.inputs.push(index);
+
+            Ok(())
+        }
+    }
+
+    let mut recorder = Recorder {
+        values: vec![],
+        missing: false,
+        constraints: vec![],
+        inputs: vec![],
+    };
+    S::synthesize(&mut recorder, circuit)?;
+
+    let mut table = PlonkishTable {
+        rows: recorder.values.len(),
+        advice: if recorder.missing {
+            None
+        } else {
+            Some(recorder.values)
+        },
+        instance: vec![],
+        copies: vec![],
+        linear: vec![],
+    };
+
+    let mut inputs = recorder.inputs.into_iter().peekable();
+    for (q, terms) in recorder.constraints.into_iter().enumerate() {
+        let terms: Vec<(F, Cell)> = terms
+            .into_iter()
+            .filter(|(_, coeff)| *coeff != F::zero())
+            .map(|(cell, coeff)| (coeff, cell))
+            .collect();
+
+        // A public input is bound by a constraint on its variable alone,
+        // whose value k(Y) supplies.
+        if inputs.peek() == Some(&(q + 1)) {
+            inputs.next();
+            match terms[..] {
+                [(coeff, cell)] if coeff == F::one() => table.instance.push(cell),
+                _ => return Err(SynthesisError::Unsatisfiable),
+            }
+            continue;
+        }
+
+        match terms[..] {
+            [] => {}
+            [(a, l), (b, r)] if a == -b => table.copies.push((l, r)),
+            _ => table.linear.push(terms),
+        }
+    }
+
+    Ok(table)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::circuits::ConstraintSystem;
+    use crate::dev::is_satisfied;
+    use crate::gadgets::AllocatedNum;
+    use crate::{Basic, Fp};
+
+    // x^3 + 5 = y for public y
+    struct CubicCircuit {
+        x: Option<Fp>,
+    }
+
+    impl Circuit<Fp> for CubicCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+                self.x.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let x2 = x.mul(cs.namespace(|| "x^2"), &x)?;
+            let x3 = x2.mul(cs.namespace(|| "x^3"), &x)?;
+            let y = AllocatedNum::alloc_input(cs.namespace(|| "y"), || {
+                Ok(self
+                    .x
+                    .ok_or(SynthesisError::AssignmentMissing)?
+                    .pow(&[3, 0, 0, 0])
+                    + Fp::from(5))
+            })?;
+            cs.enforce_zero(x3.lc() + (Coeff::Full(Fp::from(5)), CS::ONE) - y.get_variable());
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        let circuit = CubicCircuit {
+            x: Some(Fp::from(3)),
+        };
+        let table = export::<_, _, Basic>(&circuit).unwrap();
+        assert_eq!(table.instance.len(), 2);
+        assert_eq!(
+            table.instance[0],
+            Cell {
+                column: Column::A,
+                row: 0
+            }
+        );
+        assert!(!table.copies.is_empty());
+        assert!(!table.linear.is_empty());
+
+        // The table agrees with the circuit on which witnesses satisfy it.
+        let y = Fp::from(32);
+        assert_eq!(is_satisfied::<_, _, Basic>(&circuit, &[y]), Ok(true));
+        assert!(table.is_satisfied(&[y]));
+        assert!(!table.is_satisfied(&[y + Fp::one()]));
+
+        let mut tampered = table.clone();
+        tampered.advice.as_mut().unwrap()[1][2] += Fp::one();
+        assert!(!tampered.is_satisfied(&[y]));
+
+        // Without a witness only the structure is exported.
+        let structure = export::<_, _, Basic>(&CubicCircuit { x: None }).unwrap();
+        assert!(structure.advice.is_none());
+        assert!(!structure.is_satisfied(&[y]));
+        assert_eq!(structure.rows, table.rows);
+        assert_eq!(structure.instance, table.instance);
+        assert_eq!(structure.copies, table.copies);
+        assert_eq!(structure.linear, table.linear);
+
+        let json = table.to_json();
+        assert!(json.contains("\"gates\": [\"q_mul * (a * b - c)\"]"));
+        assert!(json.contains("\"assignment\""));
+        assert!(!structure.to_json().contains("\"assignment\""));
+    }
+}
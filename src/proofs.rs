@@ -1,5 +1,9 @@
+#[cfg(feature = "prover")]
+use crate::distributed::{Channel, Coordinator, Local, Offload, ProvingError};
 use crate::rescue::Rescue;
 use crate::*;
+use std::ops::Range;
+use std::sync::OnceLock;
 
 /// Packed challenge that happens to end up being valid on both curves
 const MAGIC: u64 = 12;
@@ -16,6 +20,66 @@ pub struct Leftovers<C: Curve> {
     pub challenges_sq_packed_new: Vec<C::Scalar>,
 }
 
+/// The positions of the values of `Leftovers` in the little-endian bits of
+/// its `to_bytes` encoding, which is how verification circuits take them as
+/// inputs. Point coordinates occupy 256 bits each, and `y_new` and the
+/// packed challenges 128 bits each, their high bits being zero.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LeftoversLayout {
+    k: usize,
+}
+
+impl LeftoversLayout {
+    /// The layout for leftovers with `k` challenges.
+    pub(crate) fn new(k: usize) -> Self {
+        LeftoversLayout { k }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        256 * 4 + 128 * (self.k + 1)
+    }
+
+    /// The x and y coordinates of `s_new_commitment`.
+    pub(crate) fn s_new_commitment(&self) -> (Range<usize>, Range<usize>) {
+        (0..256, 256..512)
+    }
+
+    pub(crate) fn y_new(&self) -> Range<usize> {
+        512..512 + 128
+    }
+
+    /// The x and y coordinates of `g_new`.
+    pub(crate) fn g_new(&self) -> (Range<usize>, Range<usize>) {
+        (256 * 2 + 128..256 * 3 + 128, 256 * 3 + 128..256 * 4 + 128)
+    }
+
+    /// All of the packed challenges.
+    pub(crate) fn challenges(&self) -> Range<usize> {
+        256 * 4 + 128..self.len()
+    }
+
+    pub(crate) fn challenge(&self, i: usize) -> Range<usize> {
+        assert!(i < self.k);
+        let start = 256 * 4 + 128 * (i + 1);
+        start..start + 128
+    }
+
+    /// The bits of `leftovers`, or all zero bits in their place when there
+    /// are none, such as before the first proof.
+    pub(crate) fn bits<C: Curve>(&self, leftovers: Option<&Leftovers<C>>) -> Vec<bool> {
+        let bits: Vec<bool> = match leftovers {
+            Some(leftovers) => leftovers
+                .to_bytes()
+                .into_iter()
+                .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+                .collect(),
+            None => vec![false; self.len()],
+        };
+        assert_eq!(bits.len(), self.len());
+        bits
+    }
+}
+
 impl<C: Curve> Leftovers<C> {
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut ret = vec![];
@@ -62,14 +126,18 @@ impl<C: Curve> Leftovers<C> {
             .iter()
             .map(|v| get_challenge_scalar(*v))
             .collect();
-        let challenges_new: Vec<C::Scalar> = challenges_sq_new
-            .iter()
-            .map(|v| v.sqrt().unwrap())
-            .collect(); // TODO: DUMMY IN OTHER FUNCTION
-        let mut challenges_inv_new = challenges_new;
-        let allinv = Field::batch_invert(&mut challenges_inv_new);
+        // Every base case needs this multiexp over all generators, and it
+        // only depends on the params, so it is computed once per params.
+        let g_new = *params.dummy_g_new.get_or_init(|| {
+            let challenges_new: Vec<C::Scalar> = challenges_sq_new
+                .iter()
+                .map(|v| v.sqrt().unwrap())
+                .collect(); // TODO: DUMMY IN OTHER FUNCTION
+            let mut challenges_inv_new = challenges_new;
+            let allinv = Field::batch_invert(&mut challenges_inv_new);
 
-        let g_new = compute_g_for_inner_product(&params.generators, &challenges_sq_new, allinv);
+            compute_g_for_inner_product(&params.generators, &challenges_sq_new, allinv)
+        });
 
         Leftovers {
             s_new_commitment,
@@ -86,8 +154,42 @@ impl<C: Curve> Leftovers<C> {
         circuit: &CS,
     ) -> Result<bool, SynthesisError> {
         let sx = params.compute_sx::<_, S>(circuit, self.y_new)?;
-        let s_new_commitment = params.commit(&sx, false);
-        assert_eq!(self.challenges_sq_packed_new.len(), params.k);
+        Ok(self.verify_with_sx(params, &sx))
+    }
+
+    /// Fully verifies the proof cycle, given the circuit's s(X, Y) as
+    /// recorded by `Params::compute_s`.
+    pub(crate) fn verify_with_s(&self, params: &Params<C>, s: &SparseS<C::Scalar>) -> bool {
+        self.verify_with_sx(params, &s.sx(self.y_new, params.n))
+    }
+
+    /// The distance between the generators that g(X) is committed to with,
+    /// which is the length of the vectors the inner product argument
+    /// producing these leftovers stopped at.
+    pub(crate) fn stride(&self, k: usize) -> usize {
+        assert!(self.challenges_sq_packed_new.len() <= k);
+        1 << (k - self.challenges_sq_packed_new.len())
+    }
+
+    /// The coefficients of g(X), given those of the polynomial whose
+    /// evaluation at `X^stride` it is.
+    #[cfg(feature = "prover")]
+    fn g_coeffs(&self, k: usize, coeffs: Vec<C::Scalar>) -> Vec<C::Scalar> {
+        let stride = self.stride(k);
+        if stride == 1 {
+            return coeffs;
+        }
+
+        let mut spread = vec![C::Scalar::zero(); coeffs.len() * stride];
+        for (i, coeff) in coeffs.into_iter().enumerate() {
+            spread[i * stride] = coeff;
+        }
+        spread
+    }
+
+    fn verify_with_sx(&self, params: &Params<C>, sx: &[C::Scalar]) -> bool {
+        let s_new_commitment = params.commit(sx, false);
+        let stride = self.stride(params.k);
 
         let challenges_sq_new: Vec<C::Scalar> = self
             .challenges_sq_packed_new
@@ -100,15 +202,137 @@ impl<C: Curve> Leftovers<C> {
             allinv = allinv * &(c.sqrt().unwrap()); // TODO
         }
         allinv = allinv.invert().unwrap();
-        let g_new = compute_g_for_inner_product(&params.generators, &challenges_sq_new, allinv);
+        let g_new = if stride == 1 {
+            compute_g_for_inner_product(&params.generators, &challenges_sq_new, allinv)
+        } else {
+            let generators: Vec<_> = params.generators.iter().step_by(stride).cloned().collect();
+            compute_g_for_inner_product(&generators, &challenges_sq_new, allinv)
+        };
+
+        (g_new == self.g_new) && (s_new_commitment == self.s_new_commitment)
+    }
+
+    /// The commitment to s(X, y_new).
+    pub fn s_new_commitment(&self) -> C {
+        self.s_new_commitment
+    }
+
+    /// The commitment to g(X) for the new inner product challenges.
+    pub fn g_new(&self) -> C {
+        self.g_new
+    }
+
+    pub fn y_new(&self) -> C::Scalar {
+        self.y_new
+    }
+
+    /// The packed squares of the new inner product challenges.
+    pub fn challenges(&self) -> &[C::Scalar] {
+        &self.challenges_sq_packed_new
+    }
+
+    /// Checks that these are the leftovers of the proof whose partial
+    /// verification produced `deferred`, as the verification circuit enforces
+    /// on the 128-bit challenges, reporting the first value that differs.
+    pub fn consistent_with(&self, deferred: &Deferred<C::Scalar>) -> Result<(), LeftoversMismatch> {
+        fn packed_eq<F: Field>(a: &F, b: &F) -> bool {
+            a.to_bytes()[..16] == b.to_bytes()[..16]
+        }
+
+        if !packed_eq(&self.y_new, &deferred.y_new) {
+            return Err(LeftoversMismatch::YNew);
+        }
+        if self.challenges_sq_packed_new.len() != deferred.challenges_sq_packed_new.len() {
+            return Err(LeftoversMismatch::ChallengeCount(
+                self.challenges_sq_packed_new.len(),
+                deferred.challenges_sq_packed_new.len(),
+            ));
+        }
+        for (i, (a, b)) in self
+            .challenges_sq_packed_new
+            .iter()
+            .zip(deferred.challenges_sq_packed_new.iter())
+            .enumerate()
+        {
+            if !packed_eq(a, b) {
+                return Err(LeftoversMismatch::Challenge(i));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Fully verifies the leftovers of both curves of a cycle, as every
+/// recursive proof verification must. The circuits are synthesized on the
+/// calling thread, after which the two final multiexps are computed
+/// concurrently.
+pub fn decide_pair<C1, C2, CS1, CS2, S>(
+    first: (&Leftovers<C1>, &Params<C1>, &CS1),
+    second: (&Leftovers<C2>, &Params<C2>, &CS2),
+) -> Result<bool, SynthesisError>
+where
+    C1: Curve,
+    C2: Curve,
+    CS1: Circuit<C1::Scalar>,
+    CS2: Circuit<C2::Scalar>,
+    S: SynthesisDriver,
+{
+    let (leftovers1, params1, circuit1) = first;
+    let (leftovers2, params2, circuit2) = second;
+    let sx1 = params1.compute_sx::<_, S>(circuit1, leftovers1.y_new)?;
+    let sx2 = params2.compute_sx::<_, S>(circuit2, leftovers2.y_new)?;
+
+    #[cfg(feature = "prover")]
+    {
+        use crossbeam_utils::thread;
 
-        Ok((g_new == self.g_new) && (s_new_commitment == self.s_new_commitment))
+        let (worked1, worked2) = thread::scope(|scope| {
+            let handle = scope.spawn(|_| leftovers2.verify_with_sx(params2, &sx2));
+            let worked1 = leftovers1.verify_with_sx(params1, &sx1);
+            (worked1, handle.join().unwrap())
+        })
+        .unwrap();
+
+        Ok(worked1 & worked2)
     }
+
+    #[cfg(not(feature = "prover"))]
+    Ok(leftovers1.verify_with_sx(params1, &sx1) & leftovers2.verify_with_sx(params2, &sx2))
+}
+
+/// Which value of some `Leftovers` disagrees with a `Deferred`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeftoversMismatch {
+    /// y_new differs.
+    YNew,
+    /// The leftovers and deferred have this many challenges, respectively.
+    ChallengeCount(usize, usize),
+    /// The new inner product challenge at this index differs.
+    Challenge(usize),
 }
 
+impl std::fmt::Display for LeftoversMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LeftoversMismatch::YNew => write!(f, "leftovers and deferred y_new differ"),
+            LeftoversMismatch::ChallengeCount(a, b) => write!(
+                f,
+                "leftovers have {} challenges but deferred has {}",
+                a, b
+            ),
+            LeftoversMismatch::Challenge(i) => {
+                write!(f, "leftovers and deferred challenge {} differ", i)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LeftoversMismatch {}
+
 // 4 * 128 + 6 * 256 + k * 128 + 256 + k * 128 + 5 * 256
 // = 12 * 256 + (4 + 2k) * 128
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Deferred<F: Field> {
     // comes from circuit
     pub x: F,
@@ -264,6 +488,16 @@ impl<F: Field> Deferred<F> {
     }
 
     pub fn verify(&self, k: usize) -> bool {
+        self.checks(k).is_some_and(|checks| checks.verify())
+    }
+
+    /// The scalar equations whose checking was deferred to the next proof
+    /// in the cycle, for params with the given `k`, or `None` if there are
+    /// more old challenges than `k` allows.
+    pub fn checks(&self, k: usize) -> Option<DeferredChecks<F>> {
+        if self.challenges_sq_packed_old.len() > k {
+            return None;
+        }
         let (lhs, rhs) = self.compute(k);
 
         let correct_gx_old_opening = {
@@ -280,7 +514,12 @@ impl<F: Field> Deferred<F> {
 
             let mut challenges_inv = challenges.clone();
             F::batch_invert(&mut challenges_inv);
-            compute_b(self.x, &challenges, &challenges_inv)
+            let stride = 1 << (k - self.challenges_sq_packed_old.len());
+            compute_b(
+                self.x.pow(&[stride as u64, 0, 0, 0]),
+                &challenges,
+                &challenges_inv,
+            )
         };
 
         // TODO: prover could have put a zero here
@@ -296,19 +535,147 @@ impl<F: Field> Deferred<F> {
         }
         let mut challenges_inv = challenges.clone();
         F::batch_invert(&mut challenges_inv);
-        let b_x = compute_b(self.x, &challenges, &challenges_inv);
-        let b_xy = compute_b(self.x * self.y_cur, &challenges, &challenges_inv);
-        let b_y_old = compute_b(self.y_old, &challenges, &challenges_inv);
-        let b_y_cur = compute_b(self.y_cur, &challenges, &challenges_inv);
-        let b_y_new = compute_b(self.y_new, &challenges, &challenges_inv);
-
-        lhs == rhs
-            && correct_gx_old_opening == self.gx_old_opening
-            && self.b_x == b_x
-            && self.b_xy == b_xy
-            && self.b_y_old == b_y_old
-            && self.b_y_cur == b_y_cur
-            && self.b_y_new == b_y_new
+        let b = |point: F| compute_b(point, &challenges, &challenges_inv);
+
+        Some(DeferredChecks {
+            circuit: DeferredCheck {
+                claimed: lhs,
+                expected: rhs,
+            },
+            gx_old_opening: DeferredCheck {
+                claimed: self.gx_old_opening,
+                expected: correct_gx_old_opening,
+            },
+            b_x: DeferredCheck {
+                claimed: self.b_x,
+                expected: b(self.x),
+            },
+            b_xy: DeferredCheck {
+                claimed: self.b_xy,
+                expected: b(self.x * self.y_cur),
+            },
+            b_y_old: DeferredCheck {
+                claimed: self.b_y_old,
+                expected: b(self.y_old),
+            },
+            b_y_cur: DeferredCheck {
+                claimed: self.b_y_cur,
+                expected: b(self.y_cur),
+            },
+            b_y_new: DeferredCheck {
+                claimed: self.b_y_new,
+                expected: b(self.y_new),
+            },
+        })
+    }
+}
+
+/// One of the equations in `DeferredChecks`, which holds when both sides
+/// are equal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeferredCheck<F: Field> {
+    pub claimed: F,
+    pub expected: F,
+}
+
+impl<F: Field> DeferredCheck<F> {
+    pub fn holds(&self) -> bool {
+        self.claimed == self.expected
+    }
+}
+
+/// The scalar equations a proof defers to the next proof in the cycle,
+/// which can check them more cheaply in its own scalar field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeferredChecks<F: Field> {
+    /// That t(X, Y) is consistent with the openings of r(X, Y), s(X, Y) and
+    /// k(Y), i.e. that the circuit was satisfied. `claimed` is the side
+    /// computed from the openings of r, s and k, and `expected` the side
+    /// computed from the openings of t.
+    pub circuit: DeferredCheck<F>,
+    /// The opening at x of g(X) for the old challenges.
+    pub gx_old_opening: DeferredCheck<F>,
+    /// The openings of b(X) for the new challenges at x, xy, y_old, y_cur
+    /// and y_new, which the inner product argument was verified with.
+    pub b_x: DeferredCheck<F>,
+    pub b_xy: DeferredCheck<F>,
+    pub b_y_old: DeferredCheck<F>,
+    pub b_y_cur: DeferredCheck<F>,
+    pub b_y_new: DeferredCheck<F>,
+}
+
+impl<F: Field> DeferredChecks<F> {
+    /// Each check together with its name.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &DeferredCheck<F>)> {
+        vec![
+            ("circuit", &self.circuit),
+            ("gx_old_opening", &self.gx_old_opening),
+            ("b_x", &self.b_x),
+            ("b_xy", &self.b_xy),
+            ("b_y_old", &self.b_y_old),
+            ("b_y_cur", &self.b_y_cur),
+            ("b_y_new", &self.b_y_new),
+        ]
+        .into_iter()
+    }
+
+    /// Whether every check holds.
+    pub fn verify(&self) -> bool {
+        self.iter().all(|(_, check)| check.holds())
+    }
+}
+
+/// The prover's work that depends only on the circuit structure and the
+/// old leftovers, which `Proof::create_batch` shares between its proofs.
+#[cfg(feature = "prover")]
+struct SharedProving<'a, C: Curve> {
+    old_leftovers: &'a Leftovers<C>,
+    s: SparseS<C::Scalar>,
+    sx_old: Vec<C::Scalar>,
+    challenges_old: Vec<C::Scalar>,
+    challenges_old_inv: Vec<C::Scalar>,
+    gx_old: Vec<C::Scalar>,
+}
+
+#[cfg(feature = "prover")]
+impl<'a, C: Curve> SharedProving<'a, C> {
+    fn new<CS: Circuit<C::Scalar>, S: SynthesisDriver>(
+        params: &Params<C>,
+        circuit: &CS,
+        old_leftovers: &'a Leftovers<C>,
+    ) -> Result<Self, SynthesisError> {
+        // Record s(X, Y) once; each of its evaluations reuses it.
+        let s = params.compute_s::<_, S>(circuit)?;
+
+        // Compute s(X, y_old)
+        let sx_old = s.sx(old_leftovers.y_new, params.n);
+
+        // Compute the coefficients for G_old
+        let challenges_sq_old: Vec<C::Scalar> = old_leftovers
+            .challenges_sq_packed_new
+            .iter()
+            .map(|v| get_challenge_scalar(*v))
+            .collect();
+
+        let challenges_old: Vec<C::Scalar> = challenges_sq_old
+            .iter()
+            .map(|a| a.sqrt().unwrap())
+            .collect();
+        let mut challenges_old_inv = challenges_old.clone();
+        let allinv_old = Field::batch_invert(&mut challenges_old_inv);
+        let gx_old = old_leftovers.g_coeffs(
+            params.k,
+            compute_g_coeffs_for_inner_product(&challenges_sq_old, allinv_old),
+        );
+
+        Ok(SharedProving {
+            old_leftovers,
+            s,
+            sx_old,
+            challenges_old,
+            challenges_old_inv,
+            gx_old,
+        })
     }
 }
 
@@ -335,18 +702,99 @@ pub struct Proof<C: Curve> {
     pub inner_product: MultiPolynomialOpening<C>,
 }
 
+// Nothing can fail to reach a worker when there are none.
+#[cfg(feature = "prover")]
+fn local_error(e: ProvingError) -> SynthesisError {
+    match e {
+        ProvingError::Synthesis(e) => e,
+        ProvingError::Io(e) => unreachable!("local proving failed: {}", e),
+    }
+}
+
 impl<C: Curve> Proof<C> {
+    #[cfg(feature = "prover")]
     pub fn new<CS: Circuit<C::Scalar>, S: SynthesisDriver>(
         params: &Params<C>,
         circuit: &CS,
         old_leftovers: &Leftovers<C>,
     ) -> Result<(Proof<C>, Leftovers<C>), SynthesisError> {
+        Self::new_with_tail::<_, S>(params, circuit, old_leftovers, 0)
+    }
+
+    /// Creates a proof whose inner product argument stops once its vectors
+    /// have length `2^tail` and sends them in the clear, trading `tail` fewer
+    /// rounds for `5 * (2^tail - 1)` more scalars in the proof. Verifying the
+    /// proof then computes the final generators itself, in time linear in
+    /// `params.n`. The proof cannot be verified by a recursive circuit, which
+    /// checks all `params.k` rounds and defers a single folded generator
+    /// rather than the `2^tail` a shorter argument leaves.
+    #[cfg(feature = "prover")]
+    pub fn new_with_tail<CS: Circuit<C::Scalar>, S: SynthesisDriver>(
+        params: &Params<C>,
+        circuit: &CS,
+        old_leftovers: &Leftovers<C>,
+        tail: usize,
+    ) -> Result<(Proof<C>, Leftovers<C>), SynthesisError> {
+        let shared = SharedProving::new::<_, S>(params, circuit, old_leftovers)?;
+        Self::prove::<_, S, _>(params, &shared, circuit, tail, &mut Local).map_err(local_error)
+    }
+
+    /// Creates the same proof as `new`, with the commitments and the t(X)
+    /// product of the proof computed by `coordinator`'s workers, which must
+    /// be serving `C`. The s(X, y_old) and G_old(X) work that `new` shares
+    /// with `create_batch` stays on the calling thread.
+    #[cfg(feature = "prover")]
+    pub fn new_distributed<CS: Circuit<C::Scalar>, S: SynthesisDriver, Ch: Channel>(
+        params: &Params<C>,
+        circuit: &CS,
+        old_leftovers: &Leftovers<C>,
+        coordinator: &mut Coordinator<Ch>,
+    ) -> Result<(Proof<C>, Leftovers<C>), ProvingError> {
+        let shared = SharedProving::new::<_, S>(params, circuit, old_leftovers)?;
+        Self::prove::<_, S, _>(params, &shared, circuit, 0, coordinator)
+    }
+
+    /// Creates one proof for each of `circuits`, which must all have the
+    /// same structure and differ only in their witnesses, as when proving
+    /// many leaf statements that are later aggregated. Each proof builds on
+    /// `old_leftovers`, and the work that does not depend on the witness or
+    /// the transcript, i.e. s(X, Y), s(X, y_old) and G_old(X), is done once
+    /// for the whole batch. The proofs are identical to those of `new`.
+    #[cfg(feature = "prover")]
+    #[allow(clippy::type_complexity)]
+    pub fn create_batch<CS: Circuit<C::Scalar>, S: SynthesisDriver>(
+        params: &Params<C>,
+        circuits: &[CS],
+        old_leftovers: &Leftovers<C>,
+    ) -> Result<Vec<(Proof<C>, Leftovers<C>)>, SynthesisError> {
+        let first = match circuits.first() {
+            Some(circuit) => circuit,
+            None => return Ok(vec![]),
+        };
+        let shared = SharedProving::new::<_, S>(params, first, old_leftovers)?;
+
+        circuits
+            .iter()
+            .map(|circuit| {
+                Self::prove::<_, S, _>(params, &shared, circuit, 0, &mut Local).map_err(local_error)
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "prover")]
+    fn prove<CS: Circuit<C::Scalar>, S: SynthesisDriver, O: Offload<C>>(
+        params: &Params<C>,
+        shared: &SharedProving<'_, C>,
+        circuit: &CS,
+        tail: usize,
+        offload: &mut O,
+    ) -> Result<(Proof<C>, Leftovers<C>), ProvingError> {
         struct Assignment<F: Field> {
             n: usize,
             q: usize,
-            a: Vec<F>,
-            b: Vec<F>,
-            c: Vec<F>,
+            a: Scratch<F>,
+            b: Scratch<F>,
+            c: Scratch<F>,
             inputs: Vec<(usize, F)>,
         }
 
@@ -394,10 +842,10 @@ impl<C: Curve> Proof<C> {
                 A: FnOnce() -> AR,
                 AR: Into<String>,
             {
+                // The witness is preallocated, with room for every gate a
+                // proof can have.
+                assert!(self.n < self.a.len(), "too many multiplication gates");
                 self.n += 1;
-                self.a.push(F::zero());
-                self.b.push(F::zero());
-                self.c.push(F::zero());
             }
 
             fn new_linear_constraint<A, AR>(
@@ -443,9 +891,9 @@ impl<C: Curve> Proof<C> {
         let mut assignment = Assignment::<C::Scalar> {
             n: 0,
             q: 0,
-            a: vec![],
-            b: vec![],
-            c: vec![],
+            a: Scratch::zeroed(params.n),
+            b: Scratch::zeroed(params.n),
+            c: Scratch::zeroed(params.n),
             inputs: vec![],
         };
 
@@ -455,36 +903,18 @@ impl<C: Curve> Proof<C> {
 
         assert!(assignment.n < params.n);
         assert!(assignment.q < params.d);
-
-        assignment.a.resize(params.n, C::Scalar::zero());
-        assignment.b.resize(params.n, C::Scalar::zero());
-        assignment.c.resize(params.n, C::Scalar::zero());
+        metrics::record(|m| m.constraint_count(assignment.n, assignment.q));
 
         let mut transcript = Rescue::<C::Base>::new();
 
-        // Compute s(X, y_old)
+        let s = &shared.s;
+        let old_leftovers = shared.old_leftovers;
         let y_old = old_leftovers.y_new;
-        let sx_old = params.compute_sx::<_, S>(circuit, y_old)?;
-
-        // Get s(X, y_old)
+        let sx_old = &shared.sx_old;
         let s_old_commitment = old_leftovers.s_new_commitment;
-
-        // Compute the coefficients for G_old
-        let challenges_sq_old: Vec<C::Scalar> = old_leftovers
-            .challenges_sq_packed_new
-            .iter()
-            .map(|v| get_challenge_scalar(*v))
-            .collect();
-
-        let challenges_old: Vec<C::Scalar> = challenges_sq_old
-            .iter()
-            .map(|a| a.sqrt().unwrap())
-            .collect();
-        let mut challenges_old_inv = challenges_old.clone();
-        let allinv_old = Field::batch_invert(&mut challenges_old_inv);
-        let gx_old = compute_g_coeffs_for_inner_product(&challenges_sq_old, allinv_old);
-
-        // Get G_old
+        let challenges_old = &shared.challenges_old;
+        let challenges_old_inv = &shared.challenges_old_inv;
+        let gx_old = &shared.gx_old;
         let g_old_commitment = old_leftovers.g_new;
 
         // Compute k(Y)
@@ -496,34 +926,42 @@ impl<C: Curve> Proof<C> {
         }
 
         // Commit to k(Y)
-        let k_commitment = params.commit(&ky, false);
+        let k_commitment = offload.commit(params, &ky, false)?;
         append_point::<C>(&mut transcript, &k_commitment);
 
         // Compute r(X, Y)
-        let mut rx = Vec::with_capacity(3 * params.n + 1);
-        rx.extend(assignment.c.into_iter().rev());
-        rx.extend(assignment.b.into_iter().rev());
-        rx.push(C::Scalar::zero());
-        rx.extend(assignment.a.into_iter());
-        assert_eq!(rx.len(), 3 * params.n + 1);
+        let mut rx = Scratch::zeroed(3 * params.n + 1);
+        for (r, c) in rx[..params.n].iter_mut().zip(assignment.c.iter().rev()) {
+            *r = *c;
+        }
+        for (r, b) in rx[params.n..2 * params.n]
+            .iter_mut()
+            .zip(assignment.b.iter().rev())
+        {
+            *r = *b;
+        }
+        rx[2 * params.n + 1..].copy_from_slice(&assignment.a);
+        drop(assignment.a);
+        drop(assignment.b);
+        drop(assignment.c);
 
         // Commit to r(X, Y)
-        let r_commitment = params.commit(&rx, true);
+        let r_commitment = offload.commit(params, &rx, true)?;
         append_point::<C>(&mut transcript, &r_commitment);
 
         // Obtain the challenge y_cur
-        let y_cur = get_challenge::<_, C::Scalar>(&mut transcript);
+        let y_cur = get_challenge::<_, C::Scalar>(&mut transcript, params.level);
         let y_cur_inv = y_cur.invert().unwrap();
 
         // Compute s(X, y_cur)
-        let sx_cur = params.compute_sx::<_, S>(circuit, y_cur)?;
+        let sx_cur = s.sx(y_cur, params.n);
 
         // Commit to s(X, y_cur)
-        let s_cur_commitment = params.commit(&sx_cur, false);
+        let s_cur_commitment = offload.commit(params, &sx_cur, false)?;
         append_point::<C>(&mut transcript, &s_cur_commitment);
 
         // Compute r(X, y_cur)
-        let mut rxy = rx.clone();
+        let mut rxy = rx.to_vec();
         {
             let mut cur = y_cur.pow(&[params.n as u64, 0, 0, 0]);
             for coefficient in rxy.iter_mut().rev() {
@@ -559,27 +997,27 @@ impl<C: Curve> Proof<C> {
             *a += b;
         }
 
-        let mut tx = util::multiply_polynomials(rx.clone(), r_primex);
+        let tx = offload.multiply_polynomials(rx.to_vec(), r_primex)?;
         assert_eq!(tx.len(), 7 * params.n + 1);
         //assert_eq!(tx[4 * params.n], params.compute_opening(&ky, y_cur, false) * &y_cur.pow(&[params.n as u64, 0, 0, 0]));
         //tx[4 * params.n] = C::Scalar::zero(); // -k(y)
 
         // Commit to t^+(X, y)
         let tx_positive = &tx[4 * params.n + 1..];
-        let t_positive_commitment = params.commit(tx_positive, false);
+        let t_positive_commitment = offload.commit(params, tx_positive, false)?;
         append_point::<C>(&mut transcript, &t_positive_commitment);
 
         // Commit to t^-(X, y)
         let tx_negative = &tx[0..(4 * params.n)];
-        let t_negative_commitment = params.commit(tx_negative, false);
+        let t_negative_commitment = offload.commit(params, tx_negative, false)?;
         assert_eq!(params.generators.len(), 4 * params.n);
         append_point::<C>(&mut transcript, &t_negative_commitment);
 
         // Obtain the challenge x
-        let x = get_challenge::<_, C::Scalar>(&mut transcript);
+        let x = get_challenge::<_, C::Scalar>(&mut transcript, params.level);
 
         // Compute s(x, Y)
-        let mut sy = params.compute_sy::<_, S>(circuit, x, params.n, assignment.q)?;
+        let mut sy = s.sy(x, params.n);
         {
             // We have to scale s(x, Y) by x^n to correspond with the
             // other commitments.
@@ -590,17 +1028,17 @@ impl<C: Curve> Proof<C> {
         }
 
         // Commit to s(x, Y)
-        let c_commitment = params.commit(&sy, false);
+        let c_commitment = offload.commit(params, &sy, false)?;
         append_point::<C>(&mut transcript, &c_commitment);
 
         // Obtain the challenge y_new
-        let y_new = get_challenge::<_, C::Scalar>(&mut transcript);
+        let y_new = get_challenge::<_, C::Scalar>(&mut transcript, params.level);
 
         // Compute s(X, y_new)
-        let sx_new = params.compute_sx::<_, S>(circuit, y_new)?;
+        let sx_new = s.sx(y_new, params.n);
 
         // Commit to s(X, y_new)
-        let s_new_commitment = params.commit(&sx_new, false);
+        let s_new_commitment = offload.commit(params, &sx_new, false)?;
         append_point::<C>(&mut transcript, &s_new_commitment);
 
         // Send openings
@@ -615,7 +1053,7 @@ impl<C: Curve> Proof<C> {
         append_scalar::<C>(&mut transcript, &rx_opening);
         let rxy_opening = params.compute_opening(&rx, x * &y_cur, true);
         append_scalar::<C>(&mut transcript, &rxy_opening);
-        let sx_old_opening = params.compute_opening(&sx_old, x, false);
+        let sx_old_opening = params.compute_opening(sx_old, x, false);
         append_scalar::<C>(&mut transcript, &sx_old_opening);
         let sx_cur_opening = params.compute_opening(&sx_cur, x, false);
         append_scalar::<C>(&mut transcript, &sx_cur_opening);
@@ -626,16 +1064,20 @@ impl<C: Curve> Proof<C> {
         let sx_new_opening = params.compute_opening(&sx_new, x, false);
         append_scalar::<C>(&mut transcript, &sx_new_opening);
 
-        let gx_old_opening = params.compute_opening(&gx_old, x, false);
+        let gx_old_opening = params.compute_opening(gx_old, x, false);
         assert_eq!(
             gx_old_opening,
-            compute_b(x, &challenges_old, &challenges_old_inv)
+            compute_b(
+                x.pow(&[old_leftovers.stride(params.k) as u64, 0, 0, 0]),
+                challenges_old,
+                challenges_old_inv
+            )
         );
         append_scalar::<C>(&mut transcript, &gx_old_opening);
 
         // Obtain the challenge z
 
-        let mut z = get_challenge::<_, C::Scalar>(&mut transcript);
+        let mut z = get_challenge::<_, C::Scalar>(&mut transcript, params.level);
         z = get_challenge_scalar(z);
 
         // Compute P, the commitment to p(x), and p, the value it
@@ -647,6 +1089,7 @@ impl<C: Curve> Proof<C> {
         let p_commitment = p_commitment * &z + t_negative_commitment;
         let p_commitment = p_commitment * &z + s_new_commitment;
         let p_commitment = p_commitment * &z + g_old_commitment;
+        replay::note_point("p_commitment", || p_commitment);
 
         let p_opening = rx_opening;
         let p_opening = p_opening * &z + &sx_old_opening;
@@ -656,7 +1099,7 @@ impl<C: Curve> Proof<C> {
         let p_opening = p_opening * &z + &sx_new_opening;
         let p_opening = p_opening * &z + &gx_old_opening;
 
-        let mut px = vec![C::Scalar::zero(); params.d];
+        let mut px = Scratch::zeroed(params.d);
         px[(params.d - rx.len())..].copy_from_slice(&rx);
         {
             fn add_to_px<F: Field>(px: &mut [F], poly: &[F]) {
@@ -670,8 +1113,7 @@ impl<C: Curve> Proof<C> {
                 }
             }
             mul_px(&mut px, &z);
-            add_to_px(&mut px, &sx_old);
-            drop(sx_old);
+            add_to_px(&mut px, sx_old);
             mul_px(&mut px, &z);
             add_to_px(&mut px, &sx_cur);
             drop(sx_cur);
@@ -685,11 +1127,11 @@ impl<C: Curve> Proof<C> {
             add_to_px(&mut px, &sx_new);
             drop(sx_new);
             mul_px(&mut px, &z);
-            add_to_px(&mut px, &gx_old);
-            drop(gx_old);
+            add_to_px(&mut px, gx_old);
         }
 
         let q_commitment = c_commitment + (k_commitment * &z);
+        replay::note_point("q_commitment", || q_commitment);
         let qy_opening = sx_cur_opening + &(ky_opening * &z);
 
         let mut qy = sy.clone();
@@ -749,7 +1191,10 @@ impl<C: Curve> Proof<C> {
             ],
             &params.generators,
             params.k,
+            tail,
+            params.level,
         );
+        replay::note_point("g_new", || g_new);
 
         let metadata = Leftovers {
             s_new_commitment,
@@ -789,45 +1234,28 @@ impl<C: Curve> Proof<C> {
         inputs: &[C::Scalar],
         k_commitment: Option<C>,
     ) -> Result<(bool, Leftovers<C>, Deferred<C::Scalar>, Vec<u8>), SynthesisError> {
-        struct InputMap {
-            inputs: Vec<usize>,
-        }
-
-        impl<'a, F: Field> Backend<F> for &'a mut InputMap {
-            type LinearConstraintIndex = ();
-
-            fn get_for_q(&self, _q: usize) -> Self::LinearConstraintIndex {
-                ()
-            }
-
-            fn new_k_power(&mut self, index: usize, _: Option<F>) -> Result<(), SynthesisError> {
-                self.inputs.push(index);
-                Ok(())
-            }
-
-            fn new_linear_constraint<A, AR>(
-                &mut self,
-                _annotation: A,
-            ) -> Self::LinearConstraintIndex
-            where
-                A: FnOnce() -> AR,
-                AR: Into<String>,
-            {
-                ()
-            }
-        }
+        let input_map = compute_input_map::<_, _, S>(circuit)?;
+        Ok(self.verify_with_input_map(leftovers, params, &input_map, inputs, k_commitment))
+    }
 
-        let mut inputmap = InputMap { inputs: vec![] };
-        S::synthesize(&mut inputmap, circuit)?;
-        assert_eq!(inputs.len(), inputmap.inputs.len() - 1);
+    /// Partially verifies the proof, given the positions of the circuit's
+    /// public inputs in k(Y) as computed by `compute_input_map`.
+    pub(crate) fn verify_with_input_map(
+        &self,
+        leftovers: &Leftovers<C>,
+        params: &Params<C>,
+        input_map: &[usize],
+        inputs: &[C::Scalar],
+        k_commitment: Option<C>,
+    ) -> (bool, Leftovers<C>, Deferred<C::Scalar>, Vec<u8>) {
+        assert_eq!(inputs.len(), input_map.len() - 1);
 
         let mut transcript = Rescue::<C::Base>::new();
 
         // Commitments
         let mut ky = vec![];
         ky.push(C::Scalar::zero());
-        for (index, value) in inputmap
-            .inputs
+        for (index, value) in input_map
             .iter()
             .zip(Some(C::Scalar::one()).iter().chain(inputs.iter()))
         {
@@ -848,14 +1276,14 @@ impl<C: Curve> Proof<C> {
         //);
         append_point::<C>(&mut transcript, &k_commitment);
         append_point::<C>(&mut transcript, &self.r_commitment);
-        let y_cur = get_challenge::<_, C::Scalar>(&mut transcript);
+        let y_cur = get_challenge::<_, C::Scalar>(&mut transcript, params.level);
         //println!("VERIFIER: y_cur in the verifier: {:?}", y_cur);
         append_point::<C>(&mut transcript, &self.s_cur_commitment);
         append_point::<C>(&mut transcript, &self.t_positive_commitment);
         append_point::<C>(&mut transcript, &self.t_negative_commitment);
-        let x = get_challenge::<_, C::Scalar>(&mut transcript);
+        let x = get_challenge::<_, C::Scalar>(&mut transcript, params.level);
         append_point::<C>(&mut transcript, &self.c_commitment);
-        let y_new = get_challenge::<_, C::Scalar>(&mut transcript);
+        let y_new = get_challenge::<_, C::Scalar>(&mut transcript, params.level);
         append_point::<C>(&mut transcript, &self.s_new_commitment);
 
         // Openings
@@ -882,10 +1310,11 @@ impl<C: Curve> Proof<C> {
 
         let mut challenges_old_inv = challenges_old.clone();
         Field::batch_invert(&mut challenges_old_inv);
-        let gx_old_opening = compute_b(x, &challenges_old, &challenges_old_inv);
+        let x_old = x.pow(&[leftovers.stride(params.k) as u64, 0, 0, 0]);
+        let gx_old_opening = compute_b(x_old, &challenges_old, &challenges_old_inv);
         append_scalar::<C>(&mut transcript, &gx_old_opening);
 
-        let mut z = get_challenge::<_, C::Scalar>(&mut transcript);
+        let mut z = get_challenge::<_, C::Scalar>(&mut transcript, params.level);
         z = get_challenge_scalar(z);
         //println!("VERIFIER: z in the verifier: {:?}", z);
 
@@ -896,6 +1325,7 @@ impl<C: Curve> Proof<C> {
         let p_commitment = p_commitment * &z + self.t_negative_commitment;
         let p_commitment = p_commitment * &z + self.s_new_commitment;
         let p_commitment = p_commitment * &z + leftovers.g_new;
+        replay::note_point("p_commitment", || p_commitment);
 
         let p_opening = self.rx_opening;
         let p_opening = p_opening * &z + &self.sx_old_opening;
@@ -906,6 +1336,7 @@ impl<C: Curve> Proof<C> {
         let p_opening = p_opening * &z + &gx_old_opening;
 
         let q_commitment = self.c_commitment + (k_commitment * &z);
+        replay::note_point("q_commitment", || q_commitment);
         let qy_opening = self.sx_cur_opening + &(ky_opening * &z);
 
         let mut transcript = transcript;
@@ -944,8 +1375,11 @@ impl<C: Curve> Proof<C> {
                         right_edge: false,
                     },
                 ],
+                &params.generators,
                 params.k,
+                params.level,
             );
+        replay::note_point("g_new", || g_new);
 
         let metadata = Leftovers {
             s_new_commitment: self.s_new_commitment,
@@ -990,10 +1424,11 @@ impl<C: Curve> Proof<C> {
             b_y_new: compute_b(y_new, &challenges_new, &challenges_new_inv),
         };
 
-        Ok((inner_product_satisfied, metadata, deferred, forkvalues))
+        (inner_product_satisfied, metadata, deferred, forkvalues)
     }
 }
 
+#[cfg(feature = "prover")]
 #[test]
 fn my_test_circuit() {
     struct CubingCircuit<F: Field> {
@@ -1107,19 +1542,368 @@ fn my_test_circuit() {
         .verify::<_, Basic>(&params, &verifier_circuit)
         .unwrap());
     assert_eq!(prover_new_leftovers, verifier_new_leftovers);
+
+    // the new leftovers are linked to the deferred checks of the same proof
+    assert_eq!(verifier_new_leftovers.consistent_with(&deferred), Ok(()));
+    let mut bad_deferred = deferred.clone();
+    bad_deferred.y_new += Fq::one();
+    assert_eq!(
+        verifier_new_leftovers.consistent_with(&bad_deferred),
+        Err(LeftoversMismatch::YNew)
+    );
+    let mut bad_deferred = deferred.clone();
+    bad_deferred.challenges_sq_packed_new[1] = Fq::zero();
+    assert_eq!(
+        verifier_new_leftovers.consistent_with(&bad_deferred),
+        Err(LeftoversMismatch::Challenge(1))
+    );
+    bad_deferred.challenges_sq_packed_new.pop();
+    assert_eq!(
+        verifier_new_leftovers.consistent_with(&bad_deferred),
+        Err(LeftoversMismatch::ChallengeCount(params.k, params.k - 1))
+    );
 }
 
+#[cfg(feature = "prover")]
+#[test]
+fn test_public_outputs() {
+    struct CubeOutputCircuit<F: Field> {
+        x: Option<F>,
+    }
+
+    impl<F: Field> Circuit<F> for CubeOutputCircuit<F> {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+                self.x.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let x2 = x.mul(cs.namespace(|| "x^2"), &x)?;
+            let x3 = x2.mul(cs.namespace(|| "x^3"), &x)?;
+            x3.inputize(cs.namespace(|| "output"))?;
+
+            Ok(())
+        }
+    }
+
+    let params: Params<Ec1> = Params::new(5);
+    let prover_circuit = CubeOutputCircuit {
+        x: Some(Fq::from(10)),
+    };
+    let verifier_circuit: CubeOutputCircuit<Fq> = CubeOutputCircuit { x: None };
+
+    let inputs = compute_public_inputs::<_, _, Basic>(&prover_circuit).unwrap();
+    assert_eq!(inputs, vec![Fq::from(1000)]);
+    assert_eq!(
+        compute_public_inputs::<_, _, Basic>(&verifier_circuit),
+        Err(SynthesisError::AssignmentMissing)
+    );
+
+    let leftovers = Leftovers::dummy(&params);
+    let (proof, _) = Proof::new::<_, Basic>(&params, &prover_circuit, &leftovers).unwrap();
+    let (valid_proof, _, _, _) = proof
+        .verify::<_, Basic>(&leftovers, &params, &verifier_circuit, &inputs, None)
+        .unwrap();
+    assert!(valid_proof);
+}
+
+#[cfg(feature = "prover")]
+#[test]
+fn test_create_batch() {
+    struct SquareCircuit<F: Field> {
+        x: Option<F>,
+    }
+
+    impl<F: Field> Circuit<F> for SquareCircuit<F> {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+                self.x.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let x2 = x.mul(cs.namespace(|| "x^2"), &x)?;
+            x2.inputize(cs.namespace(|| "output"))?;
+
+            Ok(())
+        }
+    }
+
+    let params: Params<Ec1> = Params::new(5);
+    let circuits: Vec<_> = (2..5)
+        .map(|x| SquareCircuit {
+            x: Some(Fq::from(x)),
+        })
+        .collect();
+    let verifier_circuit: SquareCircuit<Fq> = SquareCircuit { x: None };
+
+    let leftovers = Leftovers::dummy(&params);
+    let batch = Proof::create_batch::<_, Basic>(&params, &circuits, &leftovers).unwrap();
+    assert_eq!(batch.len(), circuits.len());
+    for (circuit, (proof, new_leftovers)) in circuits.iter().zip(batch.iter()) {
+        let inputs = compute_public_inputs::<_, _, Basic>(circuit).unwrap();
+        let (valid_proof, verifier_leftovers, _, _) = proof
+            .verify::<_, Basic>(&leftovers, &params, &verifier_circuit, &inputs, None)
+            .unwrap();
+        assert!(valid_proof);
+        assert_eq!(&verifier_leftovers, new_leftovers);
+
+        let (single, single_leftovers) =
+            Proof::new::<_, Basic>(&params, circuit, &leftovers).unwrap();
+        assert_eq!(single.r_commitment, proof.r_commitment);
+        assert_eq!(single.inner_product.g, proof.inner_product.g);
+        assert_eq!(&single_leftovers, new_leftovers);
+    }
+
+    let empty: [SquareCircuit<Fq>; 0] = [];
+    assert!(Proof::create_batch::<_, Basic>(&params, &empty, &leftovers)
+        .unwrap()
+        .is_empty());
+}
+
+#[cfg(feature = "prover")]
+#[test]
+fn test_decide_pair() {
+    struct SquareCircuit<F: Field> {
+        x: Option<F>,
+    }
+
+    impl<F: Field> Circuit<F> for SquareCircuit<F> {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+                self.x.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let x2 = x.mul(cs.namespace(|| "x^2"), &x)?;
+            x2.inputize(cs.namespace(|| "output"))?;
+
+            Ok(())
+        }
+    }
+
+    let params0: Params<Ec0> = Params::new(5);
+    let params1: Params<Ec1> = Params::new(5);
+    let circuit0 = SquareCircuit {
+        x: Some(Fp::from(3)),
+    };
+    let circuit1 = SquareCircuit {
+        x: Some(Fq::from(4)),
+    };
+    let (_, leftovers0) =
+        Proof::new::<_, Basic>(&params0, &circuit0, &Leftovers::dummy(&params0)).unwrap();
+    let (_, leftovers1) =
+        Proof::new::<_, Basic>(&params1, &circuit1, &Leftovers::dummy(&params1)).unwrap();
+
+    let verifier0: SquareCircuit<Fp> = SquareCircuit { x: None };
+    let verifier1: SquareCircuit<Fq> = SquareCircuit { x: None };
+    assert!(leftovers0.verify::<_, Basic>(&params0, &verifier0).unwrap());
+    assert!(leftovers1.verify::<_, Basic>(&params1, &verifier1).unwrap());
+    assert!(decide_pair::<_, _, _, _, Basic>(
+        (&leftovers0, &params0, &verifier0),
+        (&leftovers1, &params1, &verifier1),
+    )
+    .unwrap());
+
+    // Either side failing fails the pair.
+    let mut bad0 = leftovers0.clone();
+    bad0.g_new = bad0.g_new.double();
+    assert!(!decide_pair::<_, _, _, _, Basic>(
+        (&bad0, &params0, &verifier0),
+        (&leftovers1, &params1, &verifier1),
+    )
+    .unwrap());
+    let mut bad1 = leftovers1.clone();
+    bad1.s_new_commitment = bad1.s_new_commitment.double();
+    assert!(!decide_pair::<_, _, _, _, Basic>(
+        (&leftovers0, &params0, &verifier0),
+        (&bad1, &params1, &verifier1),
+    )
+    .unwrap());
+}
+
+#[cfg(feature = "prover")]
+#[test]
+fn test_scratch_proof() {
+    struct CubeCircuit {
+        x: Fp,
+    }
+
+    impl Circuit<Fp> for CubeCircuit {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(self.x))?;
+            let x2 = x.mul(cs.namespace(|| "x^2"), &x)?;
+            let x3 = x2.mul(cs.namespace(|| "x^3"), &x)?;
+            x3.inputize(cs.namespace(|| "output"))?;
+
+            Ok(())
+        }
+    }
+
+    let params: Params<Ec0> = Params::new(5);
+    let circuit = CubeCircuit { x: Fp::from(3) };
+    let prove = || {
+        let (proof, leftovers) =
+            Proof::new::<_, Basic>(&params, &circuit, &Leftovers::dummy(&params)).unwrap();
+        let mut bytes = vec![];
+        proof.write(&mut bytes).unwrap();
+        (bytes, leftovers.g_new)
+    };
+
+    // Backing every buffer with a file gives the same proof.
+    let in_memory = prove();
+    ScratchConfig::with_threshold(0).install();
+    let mapped = prove();
+    ScratchConfig::default().install();
+    assert!(in_memory == mapped);
+}
+
+#[cfg(feature = "prover")]
+#[test]
+fn test_labels_not_evaluated_when_proving() {
+    struct LabelCircuit<F: Field> {
+        x: Option<F>,
+    }
+
+    impl<F: Field> Circuit<F> for LabelCircuit<F> {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let label = || -> String { panic!("label evaluated") };
+            let x = AllocatedNum::alloc(cs.namespace(label), || {
+                self.x.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let x2 = x.mul(cs.namespace(label), &x)?;
+            cs.enforce_zero(x2.lc() - (Coeff::Full(F::from_u64(9)), CS::ONE));
+
+            Ok(())
+        }
+    }
+
+    let params: Params<Ec1> = Params::new(5);
+    let prover_circuit = LabelCircuit {
+        x: Some(Fq::from(3)),
+    };
+    let verifier_circuit: LabelCircuit<Fq> = LabelCircuit { x: None };
+
+    let leftovers = Leftovers::dummy(&params);
+    let (proof, _) = Proof::new::<_, Basic>(&params, &prover_circuit, &leftovers).unwrap();
+    let (valid_proof, _, _, _) = proof
+        .verify::<_, Basic>(&leftovers, &params, &verifier_circuit, &[], None)
+        .unwrap();
+    assert!(valid_proof);
+}
+
+#[cfg(feature = "prover")]
+#[test]
+fn test_dummy_leftovers_cached() {
+    let params: Params<Ec1> = Params::new(4);
+    let fresh = params.clone();
+    assert!(params.dummy_g_new.get().is_none());
+
+    let first = Leftovers::dummy(&params);
+    assert_eq!(params.dummy_g_new.get(), Some(&first.g_new));
+    assert_eq!(Leftovers::dummy(&params), first);
+    assert_eq!(Leftovers::dummy(&fresh), first);
+}
+
+#[cfg(feature = "prover")]
+#[test]
+fn test_from_seed_with() {
+    let params: Params<Ec1> = Params::from_seed(4, b"seed");
+    let sha256 = Params::<Ec1>::from_seed_with(4, b"seed", &Sha256Generators);
+    assert_eq!(sha256.generators, params.generators);
+
+    let sha512 = Params::<Ec1>::from_seed_with(4, b"seed", &Sha512Generators);
+    let rescue = Params::<Ec1>::from_seed_with(4, b"seed", &RescueGenerators::new());
+    assert_eq!(sha512.generators.len(), params.d);
+    assert_eq!(rescue.generators.len(), params.d);
+    assert_ne!(sha512.digest(), params.digest());
+    assert_ne!(rescue.digest(), params.digest());
+    assert_ne!(rescue.digest(), sha512.digest());
+
+    // The digest binds the recorded hash, not only the generators.
+    assert_eq!(params.generator_hash(), Some(Sha256Generators::ID));
+    assert_eq!(rescue.generator_hash(), Some(RescueGenerators::ID));
+    let mut relabeled = sha512.clone();
+    relabeled.generator_hash = Some(256);
+    assert_ne!(relabeled.digest(), sha512.digest());
+    // Recording SHA-256 leaves the digest as it was before hashes were
+    // recorded.
+    relabeled.generator_hash = Some(Sha256Generators::ID);
+    let sha256_digest = relabeled.digest();
+    relabeled.generator_hash = None;
+    assert_eq!(relabeled.digest(), sha256_digest);
+}
+
+#[cfg(feature = "prover")]
+#[test]
+fn test_sparse_s() {
+    struct TestCircuit;
+
+    impl<F: Field> Circuit<F> for TestCircuit {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = cs.alloc(|| "x", || Ok(F::from_u64(3)))?;
+            let (a, b, c) = cs.multiply(
+                || "x^2",
+                || Ok((F::from_u64(3), F::from_u64(3), F::from_u64(9))),
+            )?;
+            cs.enforce_zero(LinearCombination::from(x) - a);
+            cs.enforce_zero(LinearCombination::from(x) - b);
+            let x2 = cs.alloc_input(|| "x2", || Ok(F::from_u64(9)))?;
+            cs.enforce_zero(
+                LinearCombination::from(x2) + (Coeff::Full(F::from_u64(7)), c)
+                    - (Coeff::Full(F::from_u64(8)), c),
+            );
+
+            Ok(())
+        }
+    }
+
+    let params: Params<Ec1> = Params::new(4);
+    let s = params.compute_s::<_, Basic>(&TestCircuit).unwrap();
+    assert!(!s.is_empty());
+
+    let y = Fq::from_u64(11);
+    let x = Fq::from_u64(13);
+    assert_eq!(
+        s.sx(y, params.n),
+        params.compute_sx::<_, Basic>(&TestCircuit, y).unwrap()
+    );
+    assert_eq!(
+        s.sy(x, params.n),
+        params
+            .compute_sy::<_, Basic>(&TestCircuit, x, params.n, 0)
+            .unwrap()
+    );
+}
+
+/// Public parameters: `d = 2^k` generators. They commit to polynomials of
+/// degree below `d`, so a circuit is limited to `n = d / 4` multiplication
+/// gates and fewer than `d` linear constraints.
+///
+/// Public inputs use no generators beyond these limits. The commitment to
+/// k(Y) uses the generator at each input's linear constraint, and that
+/// constraint already counts towards `d`. The number of generators cannot
+/// be raised without raising `k`, because every commitment is opened by one
+/// inner product argument over all `d` of them. Extra generators would not
+/// help a larger payload anyway. Each payload bit costs the verification
+/// circuit 3.5 multiplication gates, which is 14 of the `d` slots at four
+/// per gate, but only 9 linear constraints. A growing payload therefore hits
+/// the gate limit first.
+///
+/// The fields are read through accessors, as the caches below are derived
+/// from them and would go stale if they could be changed.
 #[derive(Clone)]
 pub struct Params<C: Curve> {
-    pub g: C,
-    pub d: usize,
-    pub n: usize,
-    pub k: usize,
-    pub generators: Vec<C>,
-    pub generators_xy: Vec<(C::Base, C::Base)>,
+    pub(crate) g: C,
+    pub(crate) d: usize,
+    pub(crate) n: usize,
+    pub(crate) k: usize,
+    pub(crate) generators: Vec<C::Affine>,
+    pub(crate) level: SecurityLevel,
+    /// The `GeneratorHash::id` the generators were derived with, if they
+    /// were derived from a seed.
+    pub(crate) generator_hash: Option<u32>,
+    /// The `g_new` of `Leftovers::dummy`, filled in on first use.
+    pub(crate) dummy_g_new: OnceLock<C>,
+    /// The sums of generators behind `k_commitment`, filled in on first use.
+    pub(crate) k_commitment_table: OnceLock<crate::recursion::KCommitmentPrecompute<C>>,
 }
 
 impl<C: Curve> Params<C> {
+    #[cfg(feature = "prover")]
     pub fn new(k: usize) -> Self {
         use crossbeam_utils::thread;
 
@@ -1127,8 +1911,7 @@ impl<C: Curve> Params<C> {
         let d = 1 << k;
         let n = d / 4;
 
-        let mut generators = vec![C::zero(); d];
-        let mut generators_xy = vec![(C::Base::zero(), C::Base::zero()); d];
+        let mut generators = vec![C::Affine::zero(); d];
         // TODO: use public source of randomness
         let num_cpus = num_cpus::get();
         let mut chunk = d / num_cpus;
@@ -1137,24 +1920,21 @@ impl<C: Curve> Params<C> {
         }
 
         thread::scope(|scope| {
-            for (gen, gen_xy) in generators
-                .chunks_mut(chunk)
-                .zip(generators_xy.chunks_mut(chunk))
-            {
+            for gen in generators.chunks_mut(chunk) {
                 scope.spawn(move |_| {
                     use rand_core::{OsRng, RngCore};
                     let mut attempt = [0u8; 32];
 
-                    'outer: for (gen, gen_xy) in gen.iter_mut().zip(gen_xy.iter_mut()) {
+                    'outer: for gen in gen.iter_mut() {
                         loop {
                             OsRng.fill_bytes(&mut attempt);
-                            let attempt = C::from_bytes(&attempt);
+                            let attempt = C::Affine::from_bytes(&attempt).and_then(|p| {
+                                subtle::CtOption::new(p, p.is_torsion_free())
+                            });
                             if bool::from(attempt.is_some()) {
                                 let attempt = attempt.unwrap();
-                                let (x, y, z) = attempt.get_xyz();
-                                assert!(z == C::Base::one());
+                                assert!(!bool::from(attempt.is_zero()));
                                 *gen = attempt;
-                                *gen_xy = (x, y);
                                 continue 'outer;
                             }
                         }
@@ -1170,16 +1950,139 @@ impl<C: Curve> Params<C> {
             d,
             n,
             generators,
-            generators_xy,
+            level: SecurityLevel::default(),
+            generator_hash: None,
+            dummy_g_new: OnceLock::new(),
+            k_commitment_table: OnceLock::new(),
         }
     }
 
+    /// Derives parameters deterministically from `seed`, so that independent
+    /// implementations can reproduce them. Each generator is the first valid
+    /// point encoding among SHA-256("halo generators" || seed || counter)
+    /// for successive little-endian 64-bit counters.
+    #[cfg(feature = "verify")]
+    pub fn from_seed(k: usize, seed: &[u8]) -> Self {
+        Self::from_seed_with(k, seed, &Sha256Generators)
+    }
+
+    /// Derives parameters deterministically from `seed` as `from_seed` does,
+    /// but with the candidate encodings produced by `hash`.
+    #[cfg(feature = "verify")]
+    pub fn from_seed_with<H: GeneratorHash>(k: usize, seed: &[u8], hash: &H) -> Self {
+        assert!(k > 3);
+        let d = 1 << k;
+        let n = d / 4;
+
+        let mut generators = Vec::with_capacity(d);
+        let mut counter = 0u64;
+        while generators.len() < d {
+            let attempt = hash.hash(seed, counter);
+            counter += 1;
+
+            let point = C::Affine::from_bytes(&attempt)
+                .and_then(|p| subtle::CtOption::new(p, p.is_torsion_free()));
+            if bool::from(point.is_some()) {
+                let point = point.unwrap();
+                assert!(!bool::from(point.is_zero()));
+                generators.push(point);
+            }
+        }
+
+        Params {
+            g: C::one(),
+            k,
+            d,
+            n,
+            generators,
+            level: SecurityLevel::default(),
+            generator_hash: Some(hash.id()),
+            dummy_g_new: OnceLock::new(),
+            k_commitment_table: OnceLock::new(),
+        }
+    }
+
+    /// The group generator, `C::one()`.
+    pub fn g(&self) -> C {
+        self.g
+    }
+
+    /// The number of generators, `2^k`.
+    pub fn d(&self) -> usize {
+        self.d
+    }
+
+    /// The most multiplication gates a circuit may have, `d / 4`.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// The base-2 logarithm of `d`.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The generators that vectors are committed to.
+    pub fn generators(&self) -> &[C::Affine] {
+        &self.generators
+    }
+
+    /// The security level of the challenges in proofs over these params.
+    pub fn level(&self) -> SecurityLevel {
+        self.level
+    }
+
+    /// The `GeneratorHash::id` of the hash `from_seed_with` derived the
+    /// generators with, or `None` if they were not derived from a seed.
+    pub fn generator_hash(&self) -> Option<u32> {
+        self.generator_hash
+    }
+
+    /// Targets `level` instead of the default `SecurityLevel`. Proofs are
+    /// only valid under parameters at the level they were created at.
+    pub fn with_security_level(mut self, level: SecurityLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// A SHA-256 digest of the parameters, used to check that persisted data
+    /// is used with the parameters it was created under.
+    #[cfg(feature = "verify")]
+    pub fn digest(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.input(b"halo params");
+        hasher.input((self.k as u32).to_le_bytes());
+        hasher.input(self.g.to_bytes());
+        for gen in &self.generators {
+            hasher.input(gen.to_bytes());
+        }
+        // Left out at the default level, so that digests recorded before
+        // levels existed remain valid.
+        if self.level != SecurityLevel::default() {
+            hasher.input((self.level.challenge_bits() as u32).to_le_bytes());
+        }
+        // Likewise left out for SHA-256, the hash of `from_seed`.
+        match self.generator_hash {
+            Some(id) if id != Sha256Generators::ID => {
+                hasher.input(b"generator hash");
+                hasher.input(id.to_le_bytes());
+            }
+            _ => {}
+        }
+
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&hasher.result());
+        digest
+    }
+
     pub fn commit(&self, v: &[C::Scalar], right_edge: bool) -> C {
         assert!(self.generators.len() >= v.len());
         if right_edge {
-            util::multiexp(&v, &self.generators[(self.generators.len() - v.len())..])
+            util::multiexp_affine(&v, &self.generators[(self.generators.len() - v.len())..])
         } else {
-            util::multiexp(&v, &self.generators[0..v.len()])
+            util::multiexp_affine(&v, &self.generators[0..v.len()])
         }
     }
 
@@ -1216,6 +2119,17 @@ impl<C: Curve> Params<C> {
         Ok(sy.poly())
     }
 
+    /// Records the nonzero terms of s(X, Y) for `circuit`, from which
+    /// s(X, y) and s(x, Y) can be computed without resynthesizing it.
+    pub fn compute_s<CS: Circuit<C::Scalar>, S: SynthesisDriver>(
+        &self,
+        circuit: &CS,
+    ) -> Result<SparseS<C::Scalar>, SynthesisError> {
+        let mut s = SparseS::new();
+        S::synthesize(&mut s, circuit)?;
+        Ok(s)
+    }
+
     pub fn compute_opening<F: Field>(&self, v: &[F], point: F, right_edge: bool) -> F {
         let mut acc = F::zero();
         let mut cur = F::one();
@@ -1237,9 +2151,24 @@ pub struct PolynomialOpening<C: Curve> {
     commitment: C,
     opening: C::Scalar,
     point: C::Scalar,
+    #[cfg_attr(not(feature = "prover"), allow(dead_code))]
     right_edge: bool,
 }
 
+impl<C: Curve> PolynomialOpening<C> {
+    /// The claim that `commitment` opens to `opening` at `point`. If
+    /// `right_edge`, the committed vector ends at the last generator, as in
+    /// `Params::commit`.
+    pub fn new(commitment: C, opening: C::Scalar, point: C::Scalar, right_edge: bool) -> Self {
+        PolynomialOpening {
+            commitment,
+            opening,
+            point,
+            right_edge,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct MultiPolynomialOpening<C: Curve> {
     pub rounds: Vec<InnerProductRound<C>>,
@@ -1276,13 +2205,25 @@ impl<C: Curve> MultiPolynomialOpening<C> {
         }
     }
 
+    /// Verifies the openings of `instances`. If the argument stopped early,
+    /// its final generators are computed from `generators` here, and the
+    /// returned g is the first of them.
     pub fn verify_proof(
         &self,
         transcript: &mut Rescue<C::Base>,
         instances: &[PolynomialOpening<C>],
+        generators: &[C::Affine],
         k: usize,
+        level: SecurityLevel,
     ) -> (bool, Vec<C::Scalar>, C, Vec<u8>) {
         // TODO: verify lengths of stuff before we proceed
+        if self.rounds.is_empty()
+            || self.rounds.len() > k
+            || self.a.len() != instances.len() << (k - self.rounds.len())
+        {
+            return (false, vec![], self.g, vec![]);
+        }
+        let stride = 1 << (k - self.rounds.len());
 
         let mut p = vec![];
         let mut v = vec![];
@@ -1296,7 +2237,6 @@ impl<C: Curve> MultiPolynomialOpening<C> {
         let mut challenges_inv = vec![];
         let mut challenges_sq_packed = vec![];
         let mut forkvalues = vec![];
-        assert_eq!(self.rounds.len(), k);
 
         for round in &self.rounds {
             for j in 0..instances.len() {
@@ -1310,7 +2250,7 @@ impl<C: Curve> MultiPolynomialOpening<C> {
             let (challenge, challenge_sq, challenge_sq_packed) = loop {
                 let mut transcript = transcript.clone();
                 transcript.absorb(forkvalue);
-                let challenge_sq_packed = get_challenge::<_, C::Scalar>(&mut transcript);
+                let challenge_sq_packed = get_challenge::<_, C::Scalar>(&mut transcript, level);
                 let challenge_sq: C::Scalar = get_challenge_scalar(challenge_sq_packed);
                 match challenge_sq.sqrt().to_option() {
                     Some(challenge) => {
@@ -1325,7 +2265,7 @@ impl<C: Curve> MultiPolynomialOpening<C> {
             forkvalues.push(forkvalue_u8);
             transcript.absorb(forkvalue);
             assert_eq!(
-                get_challenge::<_, C::Scalar>(transcript),
+                get_challenge::<_, C::Scalar>(transcript, level),
                 challenge_sq_packed
             );
             let challenge_inv = challenge.invert().unwrap();
@@ -1343,6 +2283,47 @@ impl<C: Curve> MultiPolynomialOpening<C> {
             }
         }
 
+        for p in &p {
+            replay::note_point("folded commitment", || *p);
+        }
+
+        if stride > 1 {
+            // The argument stopped early, so check the vectors it sent
+            // against the final generators, each the fold of every
+            // `stride`th generator.
+            let challenges_sq: Vec<C::Scalar> = challenges.iter().map(|c| c.square()).collect();
+            let allinv = challenges_inv
+                .iter()
+                .fold(C::Scalar::one(), |acc, c| acc * c);
+            let s = compute_g_coeffs_for_inner_product(&challenges_sq, allinv);
+            let folded: Vec<C> = (0..stride)
+                .map(|i| {
+                    let generators: Vec<_> =
+                        generators.iter().skip(i).step_by(stride).cloned().collect();
+                    multiexp_affine(&s, &generators)
+                })
+                .collect();
+
+            let mut worked = true;
+            for (j, a) in self.a.chunks(stride).enumerate() {
+                let point = instances[j].point;
+                let mut cur = compute_b(
+                    point.pow(&[stride as u64, 0, 0, 0]),
+                    &challenges,
+                    &challenges_inv,
+                );
+                let mut opening = C::Scalar::zero();
+                for a in a {
+                    opening += *a * cur;
+                    cur *= point;
+                }
+
+                worked &= p[j] == util::multiexp(a, &folded) && v[j] == opening;
+            }
+
+            return (worked, challenges_sq_packed, folded[0], forkvalues);
+        }
+
         for j in 0..instances.len() {
             let b = compute_b(instances[j].point, &challenges, &challenges_inv);
 
@@ -1358,16 +2339,22 @@ impl<C: Curve> MultiPolynomialOpening<C> {
         return (true, challenges_sq_packed, self.g, forkvalues);
     }
 
+    /// Proves the openings of `instances`, stopping once the vectors have
+    /// length `2^tail` and sending what remains of them in the clear.
+    #[cfg(feature = "prover")]
     pub fn new_proof<'a>(
         transcript: &mut Rescue<C::Base>,
         instances: &'a [(PolynomialOpening<C>, &'a [C::Scalar])],
-        generators: &[C],
+        generators: &[C::Affine],
         k: usize,
+        tail: usize,
+        level: SecurityLevel,
     ) -> (MultiPolynomialOpening<C>, Vec<C::Scalar>, C) {
+        assert!(tail < k);
         let mut rounds = vec![];
         let mut a = vec![];
         let mut b = vec![];
-        let mut generators = generators.to_vec();
+        let mut generators: Vec<C> = generators.iter().map(|g| g.to_projective()).collect();
 
         for instance in instances {
             let mut v;
@@ -1392,7 +2379,7 @@ impl<C: Curve> MultiPolynomialOpening<C> {
         {
             let mut k = k;
             #[allow(non_snake_case)]
-            while k > 0 {
+            while k > tail {
                 let l = 1 << (k - 1);
                 let mut round_L = vec![];
                 let mut round_R = vec![];
@@ -1417,7 +2404,7 @@ impl<C: Curve> MultiPolynomialOpening<C> {
                 let (challenge, challenge_sq, challenge_sq_packed) = loop {
                     let mut transcript = transcript.clone();
                     transcript.absorb(forkvalue);
-                    let challenge_sq_packed = get_challenge::<_, C::Scalar>(&mut transcript);
+                    let challenge_sq_packed = get_challenge::<_, C::Scalar>(&mut transcript, level);
                     let challenge_sq: C::Scalar = get_challenge_scalar(challenge_sq_packed);
                     match challenge_sq.sqrt().to_option() {
                         Some(challenge) => {
@@ -1430,7 +2417,7 @@ impl<C: Curve> MultiPolynomialOpening<C> {
                 };
                 transcript.absorb(forkvalue);
                 assert_eq!(
-                    get_challenge::<_, C::Scalar>(transcript),
+                    get_challenge::<_, C::Scalar>(transcript, level),
                     challenge_sq_packed
                 );
                 let challenge_inv = challenge.invert().unwrap();
@@ -1463,11 +2450,12 @@ impl<C: Curve> MultiPolynomialOpening<C> {
 
         let mut final_a = vec![];
         for j in 0..instances.len() {
-            assert_eq!(a[j].len(), 1);
-            final_a.push(a[j][0]);
+            assert_eq!(a[j].len(), 1 << tail);
+            replay::note_point("folded commitment", || util::multiexp(&a[j], &generators));
+            final_a.extend(a[j].iter().cloned());
         }
 
-        assert_eq!(generators.len(), 1);
+        assert_eq!(generators.len(), 1 << tail);
 
         (
             MultiPolynomialOpening {
@@ -1481,7 +2469,7 @@ impl<C: Curve> MultiPolynomialOpening<C> {
     }
 }
 
-fn append_point<C: Curve>(transcript: &mut Rescue<C::Base>, p: &C) {
+pub(crate) fn append_point<C: Curve>(transcript: &mut Rescue<C::Base>, p: &C) {
     let xy = p.get_xy();
     if bool::from(xy.is_some()) {
         let (x, y) = xy.unwrap();
@@ -1493,17 +2481,19 @@ fn append_point<C: Curve>(transcript: &mut Rescue<C::Base>, p: &C) {
     }
 }
 
-fn append_scalar<C: Curve>(transcript: &mut Rescue<C::Base>, scalar: &C::Scalar) {
+pub(crate) fn append_scalar<C: Curve>(transcript: &mut Rescue<C::Base>, scalar: &C::Scalar) {
     append_point(transcript, &(C::one() * scalar))
 }
 
-fn get_challenge<F1: Field, F2: Field>(transcript: &mut Rescue<F1>) -> F2 {
+pub(crate) fn get_challenge<F1: Field, F2: Field>(
+    transcript: &mut Rescue<F1>,
+    level: SecurityLevel,
+) -> F2 {
     let challenge = transcript.squeeze();
-    let challenge = challenge.get_lower_128();
+    let challenge: Challenge<F1> =
+        Challenge::from_u128(level.pack_challenge(challenge.get_lower_128()));
 
-    let challenge = challenge | (1 << 127);
-
-    F2::from_u128(challenge)
+    challenge.cast::<F2>().packed()
 }
 
 /*
@@ -1718,3 +2708,219 @@ impl<'a, F: Field> Backend<F> for &'a mut SyEval<F> {
         };
     }
 }
+
+/// The nonzero terms of s(X, Y), recorded by synthesizing the circuit once
+/// so that the prover can evaluate s(X, y) and s(x, Y) at several points
+/// without resynthesizing. Most terms come from wiring and have coefficient
+/// one or minus one, which the evaluations apply without a multiplication.
+#[derive(Clone, Debug)]
+pub struct SparseS<F: Field> {
+    // Number of multiplication gates
+    n: usize,
+
+    // Number of linear constraints, i.e. the degree of s(X, Y) in Y
+    q: usize,
+
+    // (variable, q, coefficient) for each term Y^{q} coeff X^{var}
+    terms: Vec<(Variable, usize, Coeff<F>)>,
+}
+
+impl<F: Field> SparseS<F> {
+    fn new() -> Self {
+        SparseS {
+            n: 0,
+            q: 0,
+            terms: vec![],
+        }
+    }
+
+    /// The number of nonzero terms.
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Computes s(X, y) as `Params::compute_sx` does for params with the
+    /// given `n`.
+    pub fn sx(&self, y: F, n: usize) -> Vec<F> {
+        let mut ypowers = Vec::with_capacity(self.q + 1);
+        let mut cur = F::one();
+        for _ in 0..=self.q {
+            ypowers.push(cur);
+            cur *= y;
+        }
+
+        // u, v and w as in `SxEval`
+        let mut u = vec![F::zero(); self.n];
+        let mut v = vec![F::zero(); self.n];
+        let mut w = vec![F::zero(); self.n];
+        for (var, q, coeff) in &self.terms {
+            let acc = match var {
+                Variable::A(index) => &mut u[index - 1],
+                Variable::B(index) => &mut v[index - 1],
+                Variable::C(index) => &mut w[index - 1],
+            };
+            apply_coeff(acc, coeff, ypowers[*q]);
+        }
+
+        // Like `compute_sx`, only the first `n` gates are kept.
+        u.resize(n, F::zero());
+        v.resize(n, F::zero());
+        w.resize(n, F::zero());
+        let mut sx = Vec::with_capacity(3 * n + 1);
+        sx.extend(u.into_iter().rev());
+        sx.push(F::zero());
+        sx.extend(v);
+        sx.extend(w);
+
+        sx
+    }
+
+    /// Computes s(x, Y) as `Params::compute_sy` does for params with the
+    /// given `n`.
+    pub fn sy(&self, x: F, n: usize) -> Vec<F> {
+        let xinv = x.invert().unwrap();
+
+        // x^{-1}, ..., x^{-N}, x^1, ..., x^{2N}, laid out as in `SyEval`
+        let mut xpowers = Vec::with_capacity(3 * n);
+        let mut tmp = F::one();
+        for _ in 0..n {
+            tmp *= xinv;
+            xpowers.push(tmp);
+        }
+        let mut tmp = F::one();
+        for _ in 0..(2 * n) {
+            tmp *= x;
+            xpowers.push(tmp);
+        }
+
+        let mut sy = vec![F::zero(); self.q + 1];
+        for (var, q, coeff) in &self.terms {
+            let xpower = match var {
+                Variable::A(index) => xpowers[index - 1],
+                Variable::B(index) => xpowers[n + index - 1],
+                Variable::C(index) => xpowers[2 * n + index - 1],
+            };
+            apply_coeff(&mut sy[*q], coeff, xpower);
+        }
+
+        sy
+    }
+}
+
+// acc += coeff * power
+fn apply_coeff<F: Field>(acc: &mut F, coeff: &Coeff<F>, power: F) {
+    match coeff {
+        Coeff::Zero => {}
+        Coeff::One => *acc += power,
+        Coeff::NegativeOne => *acc -= power,
+        Coeff::Full(val) => *acc += power * val,
+    }
+}
+
+impl<'a, F: Field> Backend<F> for &'a mut SparseS<F> {
+    type LinearConstraintIndex = usize;
+
+    fn new_multiplication_gate<A, AR>(&mut self, _annotation: Option<A>)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.n += 1;
+    }
+
+    fn new_linear_constraint<A, AR>(&mut self, _annotation: A) -> Self::LinearConstraintIndex
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.q += 1;
+        self.q
+    }
+
+    fn get_for_q(&self, q: usize) -> Self::LinearConstraintIndex {
+        q
+    }
+
+    fn insert_coefficient(&mut self, var: Variable, coeff: Coeff<F>, q: &usize) {
+        if coeff != Coeff::Zero {
+            self.terms.push((var, *q, coeff));
+        }
+    }
+}
+
+/// The positions in k(Y) of the public inputs of `circuit`, starting with
+/// the implicit input fixed to one.
+pub(crate) fn compute_input_map<F: Field, CS: Circuit<F>, S: SynthesisDriver>(
+    circuit: &CS,
+) -> Result<Vec<usize>, SynthesisError> {
+    struct InputMap {
+        inputs: Vec<usize>,
+    }
+
+    impl<'a, F: Field> Backend<F> for &'a mut InputMap {
+        type LinearConstraintIndex = ();
+
+        fn get_for_q(&self, _q: usize) -> Self::LinearConstraintIndex {
+            ()
+        }
+
+        fn new_k_power(&mut self, index: usize, _: Option<F>) -> Result<(), SynthesisError> {
+            self.inputs.push(index);
+            Ok(())
+        }
+
+        fn new_linear_constraint<A, AR>(&mut self, _annotation: A) -> Self::LinearConstraintIndex
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            ()
+        }
+    }
+
+    let mut inputmap = InputMap { inputs: vec![] };
+    S::synthesize(&mut inputmap, circuit)?;
+    Ok(inputmap.inputs)
+}
+
+/// The values `circuit` assigns to its public inputs, without the implicit
+/// input fixed to one, as `Proof::verify` takes them.
+///
+/// A circuit can compute outputs and expose them as public inputs, for
+/// example with `AllocatedNum::inputize`, so that the prover reads them back
+/// here rather than computing them natively before synthesis.
+pub fn compute_public_inputs<F: Field, CS: Circuit<F>, S: SynthesisDriver>(
+    circuit: &CS,
+) -> Result<Vec<F>, SynthesisError> {
+    struct Inputs<F: Field> {
+        values: Vec<F>,
+    }
+
+    impl<F: Field> Backend<F> for &mut Inputs<F> {
+        type LinearConstraintIndex = ();
+
+        fn get_for_q(&self, _q: usize) -> Self::LinearConstraintIndex {}
+
+        fn new_k_power(&mut self, _index: usize, value: Option<F>) -> Result<(), SynthesisError> {
+            self.values
+                .push(value.ok_or(SynthesisError::AssignmentMissing)?);
+            Ok(())
+        }
+
+        fn new_linear_constraint<A, AR>(&mut self, _annotation: A) -> Self::LinearConstraintIndex
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+        }
+    }
+
+    let mut inputs = Inputs { values: vec![] };
+    S::synthesize(&mut inputs, circuit)?;
+    inputs.values.remove(0);
+    Ok(inputs.values)
+}
@@ -1,24 +1,246 @@
 use super::circuits::*;
+use super::gadgets::poly::{b_poly_eval, squarings};
 use super::gadgets::*;
+use super::metrics::*;
 use super::proofs::*;
 use super::synthesis::Basic;
-use super::{Curve, Field};
+use super::{Curve, CurveAffine, Field};
 use std::marker::PhantomData;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// The window of the scalar multiplications in the final check of the inner
+/// product argument. At 2 bits `multiply_windowed` takes about a fifth fewer
+/// gates than `multiply`, which saves 22,530 multiplication gates per
+/// recursion step, about 4% of the verification circuit at k = 17. Wider
+/// windows cost more in selections than they save in additions.
+const MULTIPLY_WINDOW: usize = 2;
+
+/// Budgets in multiplication gates for the main components of the
+/// verification circuit, as a fixed cost and a cost per round of the inner
+/// product argument, at the default security level. They are the costs when
+/// they were set, so a gadget change that grows the circuit fails synthesis
+/// under the counting backends of `dev` until its budget is deliberately
+/// raised.
+const VERIFY_DEFERRED_BUDGET: (usize, usize) = (13, 155);
+const VERIFY_PROOF_BUDGET: (usize, usize) = (150_996, 14_221);
+const INNER_PRODUCT_BUDGET: (usize, usize) = (86_297, 14_219);
+
+/// Absorbed ahead of the payloads in the transcript of the inner circuit's
+/// challenges, so that they can't be confused with other Rescue digests.
+const INNER_CHALLENGE_DOMAIN: u64 = 0x6861_6c6f_6368_616c;
+
+fn budget((fixed, per_round): (usize, usize), k: usize) -> usize {
+    fixed + per_round * k
+}
+
+/// Sums of the generators that commit to the public input bits of a
+/// verification circuit, in every combination within each nibble of the
+/// inputs. With them, committing to k(Y) takes one addition per nibble
+/// instead of one per set bit. The sums of a nibble are computed the first
+/// time it is used and kept in the params, so every step shares them.
+#[derive(Clone)]
+pub(crate) struct KCommitmentPrecompute<C: Curve> {
+    nibbles: Vec<OnceLock<Vec<C::Affine>>>,
+}
+
+impl<C: Curve> KCommitmentPrecompute<C> {
+    fn new(input_generators: usize) -> Self {
+        KCommitmentPrecompute {
+            nibbles: (0..input_generators.div_ceil(4))
+                .map(|_| OnceLock::new())
+                .collect(),
+        }
+    }
+
+    /// The 16 sums of the generators of the input bits 4j to 4j + 3, indexed
+    /// by the bits they include.
+    fn nibble(&self, input_generators: &[C::Affine], j: usize) -> &[C::Affine] {
+        self.nibbles[j].get_or_init(|| {
+            let generators = &input_generators[4 * j..];
+            let mut sums = vec![C::zero(); 16];
+            for v in 1..16usize {
+                // Past the last generator there are no input bits.
+                let lowest = v.trailing_zeros() as usize;
+                sums[v] = match generators.get(lowest) {
+                    Some(generator) => sums[v & (v - 1)] + generator,
+                    None => sums[v & (v - 1)],
+                };
+            }
+            let mut affine = vec![C::Affine::zero(); 16];
+            C::batch_normalize(&sums, &mut affine);
+            affine
+        })
+    }
+}
+
+impl<C: Curve> Params<C> {
+    /// The generator that commits to the public input of a verification
+    /// circuit that is fixed to one.
+    pub(crate) fn k_commitment_base(&self) -> &C::Affine {
+        &self.generators[1]
+    }
+
+    /// The generators that commit to the public input bits of a
+    /// verification circuit, one per bit.
+    pub(crate) fn k_commitment_generators(&self) -> &[C::Affine] {
+        &self.generators[2..]
+    }
+
+    /// The commitment to k(Y) of a verification circuit whose public input
+    /// bits are the little-endian bits of `inputs`.
+    pub(crate) fn k_commitment(&self, inputs: &[u8]) -> C {
+        let generators = self.k_commitment_generators();
+        assert!(inputs.len() * 8 <= generators.len());
+        let table = self
+            .k_commitment_table
+            .get_or_init(|| KCommitmentPrecompute::new(generators.len()));
+
+        let mut k_commitment = self.k_commitment_base().to_projective();
+        for (i, byte) in inputs.iter().enumerate() {
+            for (j, nibble) in [byte & 0xf, byte >> 4].iter().enumerate() {
+                if *nibble != 0 {
+                    k_commitment += table.nibble(generators, 2 * i + j)[*nibble as usize];
+                }
+            }
+        }
+        k_commitment
+    }
+}
 
+/// A proof over `E1` of a step of a chain, which verifies the proof of the
+/// step before it.
+///
+/// A proof cannot be migrated to a newer circuit. It carries the leftovers
+/// of every proof before it in the chain, and they are decided against the
+/// s(X, Y) of the one circuit the chain is made with, so no step of another
+/// circuit can verify it in-circuit. A chain of an older circuit ends at its
+/// tip, which still verifies under that circuit, and an upgraded circuit
+/// starts a new chain from a base payload derived from the old tip's.
 #[derive(Clone)]
 pub struct RecursiveProof<E1: Curve, E2: Curve> {
-    proof: Proof<E1>,
-    oldproof1: Leftovers<E1>,
-    oldproof2: Leftovers<E2>,
-    deferred: Deferred<E2::Scalar>,
-    payload: Vec<u8>,
+    pub(crate) proof: Proof<E1>,
+    pub(crate) oldproof1: Leftovers<E1>,
+    pub(crate) oldproof2: Leftovers<E2>,
+    pub(crate) deferred: Deferred<E2::Scalar>,
+    pub(crate) payload: Vec<u8>,
+}
+
+/// Why `RecursiveProof::validate` rejected a proof before any of its
+/// cryptographic checks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MalformedProof {
+    /// The payload has this many bytes, but the circuit's has that many.
+    PayloadLength(usize, usize),
+    /// The inner product argument has this many rounds, but the params need
+    /// that many.
+    RoundCount(usize, usize),
+    /// The inner product round at this index does not open five polynomials.
+    RoundWidth(usize),
+    /// The final inner product values do not open five polynomials.
+    FinalWidth,
+    /// The leftovers verifying this proof have this many challenges, but the
+    /// params need that many.
+    OldLeftovers(usize, usize),
+    /// The leftovers for the next proof have this many challenges, but the
+    /// params need that many.
+    NewLeftovers(usize, usize),
+    /// The deferred checks have this many old and new challenges, but the
+    /// params need that many of each.
+    Deferred(usize, usize, usize),
 }
 
+impl std::fmt::Display for MalformedProof {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MalformedProof::PayloadLength(a, b) => {
+                write!(f, "payload has {} bytes but the circuit's has {}", a, b)
+            }
+            MalformedProof::RoundCount(a, b) => write!(
+                f,
+                "inner product argument has {} rounds but params need {}",
+                a, b
+            ),
+            MalformedProof::RoundWidth(i) => {
+                write!(f, "inner product round {} has the wrong width", i)
+            }
+            MalformedProof::FinalWidth => {
+                write!(f, "final inner product values have the wrong width")
+            }
+            MalformedProof::OldLeftovers(a, b) => write!(
+                f,
+                "old leftovers have {} challenges but params need {}",
+                a, b
+            ),
+            MalformedProof::NewLeftovers(a, b) => write!(
+                f,
+                "new leftovers have {} challenges but params need {}",
+                a, b
+            ),
+            MalformedProof::Deferred(a, b, c) => write!(
+                f,
+                "deferred checks have {} old and {} new challenges but params need {}",
+                a, b, c
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MalformedProof {}
+
+impl<E1: Curve, E2: Curve> RecursiveProof<E1, E2> {
+    /// The payload this proof attests to, followed by any auxiliary
+    /// commitments it binds.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// The auxiliary commitments this proof binds, as a proof of `circuit`,
+    /// which are the last 32 bytes each of `payload`.
+    pub fn aux_commitments<CS: RecursiveCircuit<E1::Scalar>>(&self, circuit: &CS) -> Vec<[u8; 32]> {
+        let len = 32 * circuit.num_aux_commitments();
+        let start = self.payload.len().saturating_sub(len);
+        self.payload[start..]
+            .chunks_exact(32)
+            .map(|commitment| {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(commitment);
+                bytes
+            })
+            .collect()
+    }
+
+    /// The checks this proof's verification circuit deferred when verifying
+    /// the previous proof, which the next proof in the cycle performs, and
+    /// `verify` performs natively. `e2params` are the params of the previous
+    /// proof. Returns `None` if the deferred values are malformed for them.
+    pub fn deferred_checks(&self, e2params: &Params<E2>) -> Option<DeferredChecks<E2::Scalar>> {
+        self.deferred.checks(e2params.k)
+    }
+}
+
+/// What partially verifying a proof over `E1` yields: whether it worked,
+/// the deferred checks, the leftovers of the proof and those it carries, and
+/// the fork values.
+#[cfg(feature = "prover")]
+pub(crate) type PartialVerification<E1, E2> = (
+    bool,
+    Deferred<<E1 as Curve>::Scalar>,
+    Leftovers<E1>,
+    Leftovers<E2>,
+    Vec<u8>,
+);
+
 impl<E1, E2> RecursiveProof<E1, E2>
 where
     E1: Curve<Base = <E2 as Curve>::Scalar>,
     E2: Curve<Base = <E1 as Curve>::Scalar>,
 {
+    /// Creates a proof over `E1` that verifies `old_proof`, which is over
+    /// `E2`. The two params may have different `k`, so that proofs of a small
+    /// circuit can be verified by a larger one, as long as each has enough
+    /// generators to commit to the public inputs of its proofs.
+    #[cfg(feature = "prover")]
     pub fn create_proof<CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>>(
         e1params: &Params<E1>,
         e2params: &Params<E2>,
@@ -26,6 +248,36 @@ where
         circuit: &CS,
         new_payload: &[u8],
     ) -> Result<Self, SynthesisError> {
+        Self::create_proof_with_aux(e1params, e2params, old_proof, circuit, new_payload, &[])
+    }
+
+    /// Creates a proof as `create_proof` does, which also binds `aux`, one
+    /// auxiliary commitment for each of the circuit's `num_aux_commitments`.
+    /// They are public inputs that follow the payload, so the verification
+    /// circuit of the next proof folds them into its commitment to k(Y)
+    /// along with it.
+    #[cfg(feature = "prover")]
+    pub fn create_proof_with_aux<
+        CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>,
+    >(
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+        old_proof: Option<&RecursiveProof<E2, E1>>,
+        circuit: &CS,
+        new_payload: &[u8],
+        aux: &[[u8; 32]],
+    ) -> Result<Self, SynthesisError> {
+        let _timer = StepTimer::start(TimedStep::CreateProof);
+
+        if aux.len() != RecursiveCircuit::<E1::Scalar>::num_aux_commitments(circuit) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let mut payload = new_payload.to_vec();
+        for commitment in aux {
+            payload.extend_from_slice(commitment);
+        }
+        let new_payload = &payload[..];
+
         let (newdeferred, new_leftovers, old_leftovers, forkvalues) = match old_proof {
             Some(old_proof) => {
                 let (_, newdeferred, l1, l2, forkvalues) =
@@ -33,6 +285,10 @@ where
 
                 (newdeferred, l1, l2, forkvalues)
             }
+            // The verification circuit has the same shape in the base case,
+            // so that one verification key covers every step; its checks are
+            // only switched off by the base case bit. What can be saved is
+            // the multiexps behind the dummy leftovers, which the params cache.
             None => (
                 Deferred::dummy(e2params.k),
                 Leftovers::dummy(e2params),
@@ -41,79 +297,96 @@ where
             ),
         };
 
-        let mut circuit = VerificationCircuit::<E1, E2, _> {
-            _marker: PhantomData,
-            params: e2params,
-            base_case: None,
-            proof: None,
-            inner_circuit: circuit,
+        let circuit = VerificationCircuit::with_witness(
+            e1params.k,
+            e2params,
+            circuit,
             new_payload,
-            forkvalues: Some(&forkvalues[..]),
-            old_leftovers: Some(old_leftovers.clone()),
-            new_leftovers: Some(new_leftovers.clone()),
-            deferred: Some(newdeferred.clone()),
-        };
-
-        if old_proof.is_some() {
-            circuit.base_case = Some(false);
-            circuit.proof = old_proof;
-        } else {
-            circuit.base_case = Some(true);
-        }
+            VerificationWitness {
+                proof: old_proof,
+                forkvalues: &forkvalues,
+                old_leftovers: old_leftovers.clone(),
+                new_leftovers: new_leftovers.clone(),
+                deferred: newdeferred.clone(),
+            },
+        );
 
         // Now make the proof...
         let (proof, _) = Proof::new::<_, Basic>(e1params, &circuit, &old_leftovers)?;
 
-        Ok(RecursiveProof {
+        let proof = RecursiveProof {
             proof,
             oldproof1: old_leftovers,
             oldproof2: new_leftovers,
             deferred: newdeferred,
             payload: new_payload.to_vec(),
-        })
+        };
+
+        if is_recording() {
+            let mut bytes = vec![];
+            proof.write(&mut bytes).unwrap();
+            record(|m| m.proof_size(bytes.len()));
+        }
+
+        Ok(proof)
     }
 
+    /// The verification circuits of this proof, over `E1`, and of the proof
+    /// it verified, over `E2`, as seen by the verifier.
+    pub(crate) fn verifier_circuits<'a, CS>(
+        &'a self,
+        e1params: &'a Params<E1>,
+        e2params: &'a Params<E2>,
+        circuit: &'a CS,
+    ) -> (
+        VerifierVerificationCircuit<'a, E1, E2, CS>,
+        VerifierVerificationCircuit<'a, E2, E1, CS>,
+    )
+    where
+        CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>,
+    {
+        let circuit1 = VerificationCircuit::new(e1params.k, e2params, circuit, &self.payload);
+        let circuit2 = VerificationCircuit::new(e2params.k, e1params, circuit, &self.payload);
+
+        (circuit1, circuit2)
+    }
+
+    /// Partially verifies this proof, as the prover of the next step does.
+    /// Neither leftovers is decided, as the next step accumulates them, and
+    /// `verify_crypto` decides them together.
+    #[cfg(feature = "prover")]
     pub(crate) fn verify_inner<CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>>(
         &self,
         e1params: &Params<E1>,
         e2params: &Params<E2>,
         circuit: &CS,
-    ) -> Result<
-        (
-            bool,
-            Deferred<E1::Scalar>,
-            Leftovers<E1>,
-            Leftovers<E2>,
-            Vec<u8>,
-        ),
-        SynthesisError,
-    > {
-        let circuit1 = VerificationCircuit::<E1, E2, _> {
-            _marker: PhantomData,
-            params: e2params,
-            base_case: None,
-            proof: None,
-            inner_circuit: circuit,
-            new_payload: &self.payload,
-            forkvalues: None,
-            old_leftovers: None,
-            new_leftovers: None,
-            deferred: None,
-        };
+    ) -> Result<PartialVerification<E1, E2>, SynthesisError> {
+        let circuit1 = VerifierVerificationCircuit::<E1, E2, _>::new(
+            e1params.k,
+            e2params,
+            circuit,
+            &self.payload,
+        );
 
-        let circuit2 = VerificationCircuit::<E2, E1, _> {
-            _marker: PhantomData,
-            params: e1params,
-            base_case: None,
-            proof: None,
-            inner_circuit: circuit,
-            new_payload: &self.payload,
-            forkvalues: None,
-            old_leftovers: None,
-            new_leftovers: None,
-            deferred: None,
-        };
+        let input_map = compute_input_map::<_, _, Basic>(&circuit1)?;
+        let (worked, deferred, leftovers, forkvalues) = self.verify_proof(e1params, &input_map);
 
+        Ok((
+            worked,
+            deferred,
+            leftovers,
+            self.oldproof2.clone(),
+            forkvalues,
+        ))
+    }
+
+    /// Partially verifies `self.proof`, given the positions of the public
+    /// inputs of its verification circuit.
+    pub(crate) fn verify_proof(
+        &self,
+        e1params: &Params<E1>,
+        input_map: &[usize],
+    ) -> (bool, Deferred<E1::Scalar>, Leftovers<E1>, Vec<u8>) {
         // The public inputs for the proof consists of
         // 1. The (new) payload.
         // 2. The leftovers that should be used to verify this proof.
@@ -128,103 +401,287 @@ where
         inputs.extend(self.oldproof2.to_bytes());
         inputs.extend(self.deferred.to_bytes());
 
-        let mut k_commitment = e1params.generators[1];
-        let mut iter_gens = e1params.generators[2..].iter();
+        let k_commitment = e1params.k_commitment(&inputs);
         let mut bitinputs = vec![];
         for byte in inputs {
             for i in 0..8 {
                 let b = ((byte >> i) & 1) == 1;
                 if b {
                     bitinputs.push(E1::Scalar::one());
-                    k_commitment = k_commitment + iter_gens.next().unwrap();
                 } else {
-                    iter_gens.next();
                     bitinputs.push(E1::Scalar::zero());
                 }
             }
         }
 
-        let (worked, leftovers, deferred, forkvalues) = self.proof.verify::<_, Basic>(
+        let (worked, leftovers, deferred, forkvalues) = self.proof.verify_with_input_map(
             &self.oldproof1,
             e1params,
-            &circuit1,
+            input_map,
             &bitinputs,
             Some(k_commitment),
-        )?;
+        );
 
-        let worked = worked & self.oldproof2.verify::<_, Basic>(e2params, &circuit2)?;
+        // The verification circuit checks every round of the inner product
+        // argument, so proofs that stopped it early cannot be recursed on.
+        let worked = worked && self.proof.inner_product.rounds.len() == e1params.k;
 
-        Ok((
-            worked,
-            deferred,
-            leftovers,
-            self.oldproof2.clone(),
-            forkvalues,
-        ))
+        (worked, deferred, leftovers, forkvalues)
     }
 
+    /// Fully verifies this proof: `validate`, then `verify_crypto`.
     pub fn verify<CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>>(
         &self,
         e1params: &Params<E1>,
         e2params: &Params<E2>,
         circuit: &CS,
     ) -> Result<bool, SynthesisError> {
-        let circuit1 = VerificationCircuit::<E1, E2, _> {
-            _marker: PhantomData,
-            params: e2params,
-            base_case: None,
-            proof: None,
-            inner_circuit: circuit,
-            new_payload: &self.payload,
-            forkvalues: None,
-            old_leftovers: None,
-            new_leftovers: None,
-            deferred: None,
-        };
+        if self.validate(e1params, e2params, circuit).is_err() {
+            return Ok(false);
+        }
 
-        let circuit2 = VerificationCircuit::<E2, E1, _> {
-            _marker: PhantomData,
-            params: e1params,
-            base_case: None,
-            proof: None,
-            inner_circuit: circuit,
-            new_payload: &self.payload,
-            forkvalues: None,
-            old_leftovers: None,
-            new_leftovers: None,
-            deferred: None,
-        };
+        self.verify_crypto(e1params, e2params, circuit)
+    }
 
-        let (worked, deferred, a, b, _) = self.verify_inner(e1params, e2params, circuit)?;
+    /// Checks that this proof has the shape of a proof of `circuit` under
+    /// these params, in time independent of their size and without any
+    /// group operations, so that malformed proofs can be turned away before
+    /// `verify_crypto`. Points and scalars are on the curve and canonical by
+    /// construction, as `read` rejects any that are not.
+    pub fn validate<CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>>(
+        &self,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+        circuit: &CS,
+    ) -> Result<(), MalformedProof> {
+        let payload_len = RecursiveCircuit::<E1::Scalar>::base_payload(circuit)
+            .len()
+            .div_ceil(8)
+            + 32 * RecursiveCircuit::<E1::Scalar>::num_aux_commitments(circuit);
+        if self.payload.len() != payload_len {
+            return Err(MalformedProof::PayloadLength(
+                self.payload.len(),
+                payload_len,
+            ));
+        }
 
+        self.validate_shape(e1params, e2params)
+    }
+
+    /// The checks of `validate` that depend only on the params, which a
+    /// proof read without its circuit at hand can still be held to.
+    pub(crate) fn validate_shape(
+        &self,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+    ) -> Result<(), MalformedProof> {
+        let inner_product = &self.proof.inner_product;
+        if inner_product.rounds.len() != e1params.k {
+            return Err(MalformedProof::RoundCount(
+                inner_product.rounds.len(),
+                e1params.k,
+            ));
+        }
+        for (i, round) in inner_product.rounds.iter().enumerate() {
+            if [round.L.len(), round.R.len(), round.l.len(), round.r.len()] != [5; 4] {
+                return Err(MalformedProof::RoundWidth(i));
+            }
+        }
+        if inner_product.a.len() != 5 {
+            return Err(MalformedProof::FinalWidth);
+        }
+
+        let old = self.oldproof1.challenges_sq_packed_new.len();
+        if old != e1params.k {
+            return Err(MalformedProof::OldLeftovers(old, e1params.k));
+        }
+        let new = self.oldproof2.challenges_sq_packed_new.len();
+        if new != e2params.k {
+            return Err(MalformedProof::NewLeftovers(new, e2params.k));
+        }
+        let deferred = (
+            self.deferred.challenges_sq_packed_old.len(),
+            self.deferred.challenges_sq_packed_new.len(),
+        );
+        if deferred != (e2params.k, e2params.k) {
+            return Err(MalformedProof::Deferred(deferred.0, deferred.1, e2params.k));
+        }
+
+        Ok(())
+    }
+
+    /// The cryptographic checks of `verify`, for a proof that has passed
+    /// `validate`. Malformed proofs may panic rather than fail.
+    pub fn verify_crypto<CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>>(
+        &self,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+        circuit: &CS,
+    ) -> Result<bool, SynthesisError> {
+        let _timer = StepTimer::start(TimedStep::Verify);
+
+        let (circuit1, circuit2) = self.verifier_circuits(e1params, e2params, circuit);
+
+        let input_map = compute_input_map::<_, _, Basic>(&circuit1)?;
+        let (worked, deferred, a, _) = self.verify_proof(e1params, &input_map);
+
+        // Both leftovers are decided here, and only here, so that their
+        // multiexps are scheduled together.
         Ok(worked
             & self.deferred.verify(e2params.k)
             & deferred.verify(e1params.k)
-            & a.verify::<_, Basic>(e1params, &circuit1)?
-            & b.verify::<_, Basic>(e2params, &circuit2)?)
+            & decide_pair::<_, _, _, _, Basic>(
+                (&a, e1params, &circuit1),
+                (&self.oldproof2, e2params, &circuit2),
+            )?)
     }
 }
 
-pub(crate) struct VerificationCircuit<'a, C1: Curve, C2: Curve, CS: RecursiveCircuit<C1::Scalar>> {
-    pub(crate) _marker: PhantomData<(C1, C2)>,
-    pub(crate) params: &'a Params<C2>,
-    pub(crate) base_case: Option<bool>,
-    pub(crate) inner_circuit: &'a CS,
+/// The circuit a recursive proof over `C1` is a proof of, which verifies
+/// the previous proof, over `C2`, and runs the inner circuit.
+///
+/// Its role `R` is either [`Verifying`], for the verifier and for anything
+/// that only needs the circuit's shape, or a [`VerificationWitness`] the
+/// prover fills the witness from, so that the witness is either wholly
+/// present or wholly absent.
+pub(crate) struct VerificationCircuit<
+    'a,
+    C1: Curve,
+    C2: Curve,
+    CS: RecursiveCircuit<C1::Scalar>,
+    R: Role<'a, C1, C2> = Verifying,
+> {
+    _marker: PhantomData<(C1, C2)>,
+    // The k of the params this circuit is proven with, which sizes our own
+    // leftovers and deferred checks. It may differ from `params.k`.
+    k: usize,
+    // The params of the proof this circuit verifies
+    params: &'a Params<C2>,
+    inner_circuit: &'a CS,
+    new_payload: &'a [u8],
+    role: R,
+}
+
+/// A `VerificationCircuit` as the prover synthesizes it.
+#[cfg(feature = "prover")]
+pub(crate) type ProverVerificationCircuit<'a, C1, C2, CS> =
+    VerificationCircuit<'a, C1, C2, CS, VerificationWitness<'a, C1, C2>>;
+
+/// A `VerificationCircuit` as the verifier synthesizes it.
+pub(crate) type VerifierVerificationCircuit<'a, C1, C2, CS> =
+    VerificationCircuit<'a, C1, C2, CS, Verifying>;
+
+/// What the prover knows about the step a `VerificationCircuit` proves.
+pub(crate) struct VerificationWitness<'a, C1: Curve, C2: Curve> {
+    /// The proof verified, or None in the base case.
     pub(crate) proof: Option<&'a RecursiveProof<C2, C1>>,
-    pub(crate) new_payload: &'a [u8],
-    pub(crate) forkvalues: Option<&'a [u8]>,
-    pub(crate) old_leftovers: Option<Leftovers<C1>>,
-    pub(crate) new_leftovers: Option<Leftovers<C2>>,
-    pub(crate) deferred: Option<Deferred<C2::Scalar>>,
+    /// The fork values of the native verification of `proof`.
+    pub(crate) forkvalues: &'a [u8],
+    pub(crate) old_leftovers: Leftovers<C1>,
+    pub(crate) new_leftovers: Leftovers<C2>,
+    pub(crate) deferred: Deferred<C2::Scalar>,
+}
+
+/// The role of a `VerificationCircuit` when it is synthesized without a
+/// witness.
+pub(crate) struct Verifying;
+
+/// Whether a `VerificationCircuit` is synthesized with a witness.
+pub(crate) trait Role<'a, C1: Curve, C2: Curve> {
+    fn witness(&self) -> Option<&VerificationWitness<'a, C1, C2>>;
+}
+
+impl<'a, C1: Curve, C2: Curve> Role<'a, C1, C2> for Verifying {
+    fn witness(&self) -> Option<&VerificationWitness<'a, C1, C2>> {
+        None
+    }
 }
 
-impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Scalar>>
-    VerificationCircuit<'a, E1, E2, Inner>
+impl<'a, C1: Curve, C2: Curve> Role<'a, C1, C2> for VerificationWitness<'a, C1, C2> {
+    fn witness(&self) -> Option<&VerificationWitness<'a, C1, C2>> {
+        Some(self)
+    }
+}
+
+impl<'a, C1: Curve, C2: Curve, CS: RecursiveCircuit<C1::Scalar>>
+    VerifierVerificationCircuit<'a, C1, C2, CS>
 {
+    /// The circuit proven with params of size `k`, verifying proofs made
+    /// with `params`, for proofs of `new_payload`.
+    pub(crate) fn new(
+        k: usize,
+        params: &'a Params<C2>,
+        inner_circuit: &'a CS,
+        new_payload: &'a [u8],
+    ) -> Self {
+        VerificationCircuit {
+            _marker: PhantomData,
+            k,
+            params,
+            inner_circuit,
+            new_payload,
+            role: Verifying,
+        }
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<'a, C1: Curve, C2: Curve, CS: RecursiveCircuit<C1::Scalar>>
+    ProverVerificationCircuit<'a, C1, C2, CS>
+{
+    /// The circuit `new` describes, synthesized with `witness`.
+    pub(crate) fn with_witness(
+        k: usize,
+        params: &'a Params<C2>,
+        inner_circuit: &'a CS,
+        new_payload: &'a [u8],
+        witness: VerificationWitness<'a, C1, C2>,
+    ) -> Self {
+        VerificationCircuit {
+            _marker: PhantomData,
+            k,
+            params,
+            inner_circuit,
+            new_payload,
+            role: witness,
+        }
+    }
+}
+
+impl<
+        'a,
+        E1: Curve,
+        E2: Curve<Base = E1::Scalar>,
+        Inner: RecursiveCircuit<E1::Scalar>,
+        R: Role<'a, E1, E2>,
+    > VerificationCircuit<'a, E1, E2, Inner, R>
+{
+    /// The proof this circuit verifies, if it is synthesized with a witness
+    /// outside the base case.
+    fn proof(&self) -> Option<&'a RecursiveProof<E2, E1>> {
+        self.role.witness().and_then(|witness| witness.proof)
+    }
+
+    fn verifier(&self) -> ProofVerifier<'a, E2> {
+        let proof = self.proof();
+        ProofVerifier::new(
+            self.params,
+            proof.map(|proof| &proof.proof),
+            proof.map(|proof| &proof.oldproof1),
+            self.role.witness().map(|witness| witness.forkvalues),
+        )
+    }
+
+    /// Checks the deferred scalar arithmetic of the previous proof.
+    ///
+    /// `batch_challenge` are the bits of a transcript challenge derived after
+    /// `deferred` was committed to, which is used to batch the checks of the
+    /// `b` evaluations into one.
     fn verify_deferred<CS: ConstraintSystem<E1::Scalar>>(
         &self,
         mut cs: CS,
         mut deferred: &[AllocatedBit],
+        batch_challenge: &[AllocatedBit],
     ) -> Result<(), SynthesisError> {
         // Unpack all of the deferred data
         let x = self.obtain_scalar_from_bits(cs.namespace(|| "pack x"), &deferred[0..128])?;
@@ -261,22 +718,22 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             self.obtain_scalar_from_bits(cs.namespace(|| "pack rxy_opening"), &deferred[0..256])?;
         deferred = &deferred[256..];
         let mut challenges_sq_old = vec![];
-        for i in 0..self.params.k {
-            challenges_sq_old.push(self.get_challenge_scalar(
-                cs.namespace(|| format!("pack old challenge {}", i)),
-                &deferred[0..128],
-            )?);
+        for i in 0..self.k {
+            challenges_sq_old.push(
+                AllocatedChallenge::from_bits(&deferred[0..128])
+                    .scalar(cs.namespace(|| format!("pack old challenge {}", i)))?,
+            );
             deferred = &deferred[128..];
         }
         let gx_old_opening = self
             .obtain_scalar_from_bits(cs.namespace(|| "pack gx_old_opening"), &deferred[0..256])?;
         deferred = &deferred[256..];
         let mut challenges_sq_new = vec![];
-        for i in 0..self.params.k {
-            challenges_sq_new.push(self.get_challenge_scalar(
-                cs.namespace(|| format!("pack new challenge {}", i)),
-                &deferred[0..128],
-            )?);
+        for i in 0..self.k {
+            challenges_sq_new.push(
+                AllocatedChallenge::from_bits(&deferred[0..128])
+                    .scalar(cs.namespace(|| format!("pack new challenge {}", i)))?,
+            );
             deferred = &deferred[128..];
         }
         let b_x = self.obtain_scalar_from_bits(cs.namespace(|| "pack b_x"), &deferred[0..256])?;
@@ -304,7 +761,7 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         let xy = x.mul(cs.namespace(|| "xy"), &y_cur)?;
         let x_invy = x.mul(cs.namespace(|| "x_invy"), &yinv)?;
 
-        let nk = self.params.k - 2;
+        let nk = self.k - 2;
 
         // let xinvn = xinv.pow(&[n as u64, 0, 0, 0]);
         let mut xinvn = xinv.clone();
@@ -322,22 +779,16 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
                 &xinvd,
             )?;
         }
+        // The squarings of x, y_cur and xy are shared between the powers
+        // below and the evaluations of b.
+        let x_powers = squarings(cs.namespace(|| "x^(2^i)"), x, self.k)?;
+        let y_cur_powers = squarings(cs.namespace(|| "y_cur^(2^i)"), y_cur, self.k)?;
+        let xy_powers = squarings(cs.namespace(|| "xy^(2^i)"), xy, self.k)?;
+
         // let yn = self.y_cur.pow(&[n as u64, 0, 0, 0]);
-        let mut yn = y_cur.clone();
-        for i in 0..nk {
-            yn = yn.mul(
-                cs.namespace(|| format!("y^{}", 2u32.pow(i as u32 + 1))),
-                &yn,
-            )?;
-        }
+        let yn = y_cur_powers[nk];
         // let xn = self.x.pow(&[n as u64, 0, 0, 0]);
-        let mut xn = x.clone();
-        for i in 0..nk {
-            xn = xn.mul(
-                cs.namespace(|| format!("x^{}", 2u32.pow(i as u32 + 1))),
-                &xn,
-            )?;
-        }
+        let xn = x_powers[nk];
         // let xyinvn31 = xyinv.pow(&[(3 * n - 1) as u64, 0, 0, 0]);
         let mut xyinvn31 = xyinv.clone();
         for i in 0..nk {
@@ -366,28 +817,27 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         let lhs = sx_cur_opening.mul(cs.namespace(|| "sx_cur_opening * xinvn"), &xinvn)?;
         let lhs = lhs.mul(cs.namespace(|| "sx_cur_opening * xinvn * yn"), &yn)?;
 
-        // Computes x + x^2 + x^3 + ... + x^n
+        // Computes x + x^2 + x^3 + ... + x^n, given the squarings of x
         fn compute_thing<F: Field, CS: ConstraintSystem<F>>(
             mut cs: CS,
-            x: AllocatedNum<F>,
+            powers: &[AllocatedNum<F>],
             k: usize,
         ) -> Result<Combination<F>, SynthesisError> {
-            let mut acc = Combination::from(x);
-            let mut cur = x.clone();
-            for _ in 0..k {
+            let mut acc = Combination::from(powers[0]);
+            for cur in &powers[0..k] {
                 let tmp = acc.mul(
-                    cs.namespace(|| format!("extend polynomial")),
-                    &Combination::from(cur),
+                    cs.namespace(|| "extend polynomial"),
+                    &Combination::from(*cur),
                 )?;
-                cur = cur.mul(cs.namespace(|| "square cur"), &cur)?;
 
                 acc = acc + tmp;
             }
             Ok(acc)
         }
 
-        let thing = compute_thing(cs.namespace(|| "poly(xy, nk)"), xy, nk)?;
-        let thing = thing + compute_thing(cs.namespace(|| "poly(x_invy, nk)"), x_invy, nk)?;
+        let x_invy_powers = squarings(cs.namespace(|| "x_invy^(2^i)"), x_invy, nk.max(1))?;
+        let thing = compute_thing(cs.namespace(|| "poly(xy, nk)"), &xy_powers, nk)?;
+        let thing = thing + compute_thing(cs.namespace(|| "poly(x_invy, nk)"), &x_invy_powers, nk)?;
         let thing = thing.mul(
             cs.namespace(|| "(poly(xy, nk) + poly(x_invy, nk)) * xn"),
             &Combination::from(xn),
@@ -426,9 +876,9 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             for (i, c) in challenges_old_inv.iter_mut().enumerate() {
                 *c = c.invert(cs.namespace(|| format!("invert old challenge {}", i)))?;
             }
-            let expected_gx_old_opening = self.compute_b(
+            let expected_gx_old_opening = b_poly_eval(
                 cs.namespace(|| "b_old(x)"),
-                x,
+                &x_powers,
                 &challenges_old,
                 &challenges_old_inv,
             )?;
@@ -446,105 +896,216 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         for (i, c) in challenges_new_inv.iter_mut().enumerate() {
             *c = c.invert(cs.namespace(|| format!("invert new challenge {}", i)))?;
         }
-        let expected_b_x = self.compute_b(
+        let expected_b_x = b_poly_eval(
             cs.namespace(|| "b_new(x)"),
-            x,
+            &x_powers,
             &challenges_new,
             &challenges_new_inv,
         )?;
-        let expected_b_xy = self.compute_b(
+        let expected_b_xy = b_poly_eval(
             cs.namespace(|| "b_new(xy)"),
-            xy,
+            &xy_powers,
             &challenges_new,
             &challenges_new_inv,
         )?;
-        let expected_b_y_old = self.compute_b(
+        let y_old_powers = squarings(cs.namespace(|| "y_old^(2^i)"), y_old, self.k)?;
+        let y_new_powers = squarings(cs.namespace(|| "y_new^(2^i)"), y_new, self.k)?;
+        let expected_b_y_old = b_poly_eval(
             cs.namespace(|| "b_new(y_old)"),
-            y_old,
+            &y_old_powers,
             &challenges_new,
             &challenges_new_inv,
         )?;
-        let expected_b_y_cur = self.compute_b(
+        let expected_b_y_cur = b_poly_eval(
             cs.namespace(|| "b_new(y_cur)"),
-            y_cur,
+            &y_cur_powers,
             &challenges_new,
             &challenges_new_inv,
         )?;
-        let expected_b_y_new = self.compute_b(
+        let expected_b_y_new = b_poly_eval(
             cs.namespace(|| "b_new(y_new)"),
-            y_new,
+            &y_new_powers,
             &challenges_new,
             &challenges_new_inv,
         )?;
 
-        let lc = expected_b_x.lc(&mut cs);
-        cs.enforce_zero(lc - b_x.get_variable());
+        // Rather than checking each opening of b separately, check a random
+        // linear combination of them. The challenge was squeezed from a
+        // transcript that absorbed the claimed openings (via the k(Y)
+        // commitment), so a prover cannot choose them to cancel out.
+        let r =
+            self.obtain_scalar_from_bits(cs.namespace(|| "pack batch challenge"), batch_challenge)?;
+        let r = Combination::from(r);
+        let differences = vec![
+            expected_b_x - b_x,
+            expected_b_xy - b_xy,
+            expected_b_y_old - b_y_old,
+            expected_b_y_cur - b_y_cur,
+            expected_b_y_new - b_y_new,
+        ];
+        let mut differences = differences.into_iter().rev();
+        let mut batched = differences.next().unwrap();
+        for (i, difference) in differences.enumerate() {
+            let tmp = batched.mul(cs.namespace(|| format!("batch b openings {}", i)), &r)?;
+            batched = difference + tmp;
+        }
 
-        let lc = expected_b_xy.lc(&mut cs);
-        cs.enforce_zero(lc - b_xy.get_variable());
+        let lc = batched.lc(&mut cs);
+        cs.enforce_zero(lc);
 
-        let lc = expected_b_y_old.lc(&mut cs);
-        cs.enforce_zero(lc - b_y_old.get_variable());
+        Ok(())
+    }
 
-        let lc = expected_b_y_cur.lc(&mut cs);
-        cs.enforce_zero(lc - b_y_cur.get_variable());
+    fn obtain_scalar_from_bits<CS: ConstraintSystem<E1::Scalar>>(
+        &self,
+        mut cs: CS,
+        bits: &[AllocatedBit],
+    ) -> Result<AllocatedNum<E1::Scalar>, SynthesisError> {
+        let mut value = Some(E1::Scalar::zero());
+        let mut cur = E1::Scalar::one();
+        let mut lc = LinearCombination::zero();
+        for bit in bits {
+            if let Some(bit) = bit.get_value() {
+                if bit {
+                    value = value.map(|value| value + &cur);
+                }
+            }
+            lc = lc + (Coeff::Full(cur), bit.get_variable());
+            cur = cur + &cur;
+        }
 
-        let lc = expected_b_y_new.lc(&mut cs);
-        cs.enforce_zero(lc - b_y_new.get_variable());
+        let newnum = AllocatedNum::alloc(cs.namespace(|| "scalar"), || {
+            value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
 
-        Ok(())
+        cs.enforce_zero(lc - newnum.get_variable());
+
+        Ok(newnum)
     }
 
-    fn compute_b<CS: ConstraintSystem<E1::Scalar>>(
+    /// Squeezes the challenges the inner circuit asks for from a transcript
+    /// over a domain separator and the old and new payloads, packed
+    /// `CAPACITY` bits at a time.
+    fn inner_challenges<CS: ConstraintSystem<E1::Scalar>>(
         &self,
         mut cs: CS,
-        x: AllocatedNum<E1::Scalar>,
-        challenges: &[AllocatedNum<E1::Scalar>],
-        challenges_inv: &[AllocatedNum<E1::Scalar>],
-    ) -> Result<Combination<E1::Scalar>, SynthesisError> {
-        assert!(challenges.len() >= 1);
-        assert_eq!(challenges.len(), challenges_inv.len());
-        Ok(if challenges.len() == 1 {
-            // return *challenges_inv.last().unwrap() + *challenges.last().unwrap() * x;
-            let tmp = x.mul(
-                cs.namespace(|| "x * challenges[-1]"),
-                challenges.last().unwrap(),
-            )?;
-            Combination::from(*challenges_inv.last().unwrap()) + tmp
-        } else {
-            // return (*challenges_inv.last().unwrap() + *challenges.last().unwrap() * x)
-            //     * compute_b(
-            //         x.square(),
-            //         &challenges[0..(challenges.len() - 1)],
-            //         &challenges_inv[0..(challenges.len() - 1)],
-            //     );
-
-            let tmp = x.mul(
-                cs.namespace(|| "x * challenges[-1]"),
-                challenges.last().unwrap(),
-            )?;
-            let tmp = Combination::from(*challenges_inv.last().unwrap()) + tmp;
-            let x2 = x.mul(cs.namespace(|| "x^2"), &x)?;
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+    ) -> Result<Vec<AllocatedNum<E1::Scalar>>, SynthesisError> {
+        let count = self.inner_circuit.num_challenges();
+        if count == 0 {
+            return Ok(vec![]);
+        }
 
-            Combination::from(
-                self.compute_b(
-                    cs.namespace(|| format!("b layer {}", challenges.len() - 1)),
-                    x2,
-                    &challenges[0..(challenges.len() - 1)],
-                    &challenges_inv[0..(challenges.len() - 1)],
-                )?
-                .mul(cs, &tmp)?,
-            )
-        })
+        let mut transcript = RescueGadget::new(cs.namespace(|| "transcript"))?;
+        transcript.absorb(
+            cs.namespace(|| "absorb domain"),
+            Num::constant(E1::Scalar::from_u64(INNER_CHALLENGE_DOMAIN)),
+        )?;
+        let payloads: Vec<AllocatedBit> = old_payload.iter().chain(new_payload).cloned().collect();
+        for (i, bits) in payloads.chunks(E1::Scalar::CAPACITY as usize).enumerate() {
+            let packed =
+                self.obtain_scalar_from_bits(cs.namespace(|| format!("pack {}", i)), bits)?;
+            transcript.absorb(cs.namespace(|| format!("absorb {}", i)), packed.into())?;
+        }
+
+        (0..count)
+            .map(|i| transcript.squeeze(cs.namespace(|| format!("challenge {}", i))))
+            .collect()
     }
+}
 
-    fn num_equal_unless_base_case<CS: ConstraintSystem<E1::Scalar>>(
+/// Enforces that `base_case` is what `rule` derives from `old_payload`, if
+/// anything. `base_payload` must satisfy the rule.
+pub(crate) fn derive_base_case<F: Field, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    rule: BaseCaseRule,
+    base_payload: &[bool],
+    old_payload: &[AllocatedBit],
+    base_case: &AllocatedBit,
+) -> Result<(), SynthesisError> {
+    let derived = match rule {
+        BaseCaseRule::Prover => return Ok(()),
+        BaseCaseRule::Counter(range) => {
+            assert!(
+                base_payload[range.clone()].iter().all(|b| !b),
+                "base payload must have a zero step counter"
+            );
+            let counter = old_payload
+                .get(range)
+                .ok_or(SynthesisError::Unsatisfiable)?;
+            let (first, rest) = counter.split_first().ok_or(SynthesisError::Unsatisfiable)?;
+            let mut is_zero = AllocatedBit::nor(cs.namespace(|| "bit 0"), first, first)?;
+            for (i, bit) in rest.iter().enumerate() {
+                is_zero = AllocatedBit::and_not(
+                    cs.namespace(|| format!("bit {}", i + 1)),
+                    &is_zero,
+                    bit,
+                )?;
+            }
+            is_zero
+        }
+    };
+    cs.enforce_zero(LinearCombination::from(derived.get_variable()) - base_case.get_variable());
+
+    Ok(())
+}
+
+/// Verifies a Halo proof over `C` inside a circuit over `C::Base`, the scalar
+/// field of the other curve of the cycle.
+///
+/// The group and transcript checks of the proof are made in-circuit. Its
+/// scalar-field checks and its new leftovers cannot be, and are instead tied
+/// to bits supplied by the caller, which must hand them to someone who can
+/// discharge them: a proof over `C` that checks them as deferred values, or a
+/// native verifier. The same gadget verifies the previous proof in every
+/// recursion step, and can be used by an inner circuit to verify further
+/// independent proofs of the same cycle.
+pub struct ProofVerifier<'a, C: Curve> {
+    params: &'a Params<C>,
+    proof: Option<&'a Proof<C>>,
+    old_leftovers: Option<&'a Leftovers<C>>,
+    forkvalues: Option<&'a [u8]>,
+}
+
+impl<'a, C: Curve> ProofVerifier<'a, C> {
+    /// Creates a verifier for a proof made with `params`. The proof, the
+    /// leftovers it was created from, and the fork values produced by its
+    /// native verification are only needed to fill in the witness.
+    pub fn new(
+        params: &'a Params<C>,
+        proof: Option<&'a Proof<C>>,
+        old_leftovers: Option<&'a Leftovers<C>>,
+        forkvalues: Option<&'a [u8]>,
+    ) -> Self {
+        ProofVerifier {
+            params,
+            proof,
+            old_leftovers,
+            forkvalues,
+        }
+    }
+
+    /// Enforces `lhs == rhs` unless `base_case` is set, in which case
+    /// nothing is enforced. The check is still made, so that the circuit has
+    /// the same shape in the base case. Only when both sides are constants,
+    /// which does not depend on the witness, is the gate left out.
+    fn num_equal_unless_base_case<CS: ConstraintSystem<C::Base>>(
         &self,
         mut cs: CS,
         base_case: AllocatedBit,
-        lhs: &Combination<E1::Scalar>,
-        rhs: &Combination<E1::Scalar>,
+        lhs: &Combination<C::Base>,
+        rhs: &Combination<C::Base>,
     ) -> Result<(), SynthesisError> {
+        // Constants are compared now: equal ones need no gate, and unequal
+        // ones can only pass in the base case.
+        if let (Some(lhs), Some(rhs)) = (lhs.constant_value(), rhs.constant_value()) {
+            if lhs != rhs {
+                cs.enforce_zero(LinearCombination::zero() + CS::ONE - base_case.get_variable());
+            }
+            return Ok(());
+        }
+
         let not_basecase = base_case.get_value().map(|v| (!v).into());
 
         // lhs - rhs * (1 - base_case) = 0
@@ -569,114 +1130,145 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         Ok(())
     }
 
-    fn equal_unless_base_case<CS: ConstraintSystem<E1::Scalar>>(
+    /// Enforces that each of `lhs` equals the corresponding one of `rhs`
+    /// unless `base_case` is set, as `num_equal_unless_base_case` does.
+    pub(crate) fn nums_equal_unless_base_case<CS: ConstraintSystem<C::Base>>(
         &self,
         mut cs: CS,
         base_case: AllocatedBit,
-        mut lhs: &[AllocatedBit],
-        mut rhs: &[AllocatedBit],
+        lhs: &[Combination<C::Base>],
+        rhs: &[Combination<C::Base>],
     ) -> Result<(), SynthesisError> {
         assert_eq!(lhs.len(), rhs.len());
 
-        let mut i = 0;
-        while lhs.len() > 0 {
-            i += 1;
-            let mut coeff = E1::Scalar::one();
-            let mut lhs_lc = Combination::zero();
-            let mut rhs_lc = Combination::zero();
-
-            let mut truncate_by = 0;
-            for (i, (lhs, rhs)) in lhs.iter().zip(rhs.iter()).take(250).enumerate() {
-                lhs_lc = lhs_lc + (Coeff::Full(coeff), Num::from(AllocatedNum::from(lhs.clone())));
-                rhs_lc = rhs_lc + (Coeff::Full(coeff), Num::from(AllocatedNum::from(rhs.clone())));
-
-                coeff = coeff + &coeff;
-                truncate_by = i + 1;
-            }
-
+        for (i, (lhs, rhs)) in lhs.iter().zip(rhs.iter()).enumerate() {
             self.num_equal_unless_base_case(
                 cs.namespace(|| format!("check {}", i)),
                 base_case.clone(),
-                &lhs_lc,
-                &rhs_lc
+                lhs,
+                rhs,
             )?;
-
-            lhs = &lhs[truncate_by..];
-            rhs = &rhs[truncate_by..];
         }
 
-        // let not_basecase = base_case.get_value().map(|v| (!v).into());
-
-        // for (lhs, rhs) in lhs.iter().zip(rhs.iter()) {
-        //     // lhs - rhs * (1 - base_case) = 0
-        //     // if base_case is true, then 1 - base_case will be zero
-        //     // if base_case is false, then lhs - rhs must be zero, and therefore they are equal
-        //     let (a, b, c) = cs.multiply(
-        //         || "equal_unless_base_case",
-        //         || {
-        //             let lhs = lhs.get_value().ok_or(SynthesisError::AssignmentMissing)?;
-        //             let rhs = rhs.get_value().ok_or(SynthesisError::AssignmentMissing)?;
-        //             let not_basecase = not_basecase.ok_or(SynthesisError::AssignmentMissing)?;
-
-        //             let lhs: E1::Scalar = lhs.into();
-        //             let rhs: E1::Scalar = rhs.into();
-
-        //             Ok((lhs - &rhs, not_basecase, Field::zero()))
-        //         },
-        //     )?;
-        //     cs.enforce_zero(LinearCombination::from(a) - lhs.get_variable() + rhs.get_variable());
-        //     cs.enforce_zero(LinearCombination::from(b) - CS::ONE + base_case.get_variable());
-        //     cs.enforce_zero(LinearCombination::from(c))
-        // }
-
         Ok(())
     }
 
-    fn obtain_scalar_from_bits<CS: ConstraintSystem<E1::Scalar>>(
+    /// Enforces that two equally long bit strings are equal unless
+    /// `base_case` is set, packing up to 250 bits into each check.
+    pub(crate) fn equal_unless_base_case<CS: ConstraintSystem<C::Base>>(
+        &self,
+        cs: CS,
+        base_case: AllocatedBit,
+        lhs: &[impl Clone + Into<Boolean>],
+        rhs: &[impl Clone + Into<Boolean>],
+    ) -> Result<(), SynthesisError> {
+        assert_eq!(lhs.len(), rhs.len());
+
+        let pack = |bits: Vec<Boolean>| -> Vec<Combination<C::Base>> {
+            bits.chunks(250)
+                .map(|chunk| {
+                    let mut coeff = C::Base::one();
+                    let mut packed = Combination::zero();
+                    for bit in chunk {
+                        packed = match bit {
+                            Boolean::Is(bit) => {
+                                let bit = Num::from(AllocatedNum::from(bit.clone()));
+                                packed + (Coeff::Full(coeff), bit)
+                            }
+                            Boolean::Not(bit) => {
+                                let bit = Num::from(AllocatedNum::from(bit.clone()));
+                                packed
+                                    + (Coeff::Full(coeff), Num::constant(C::Base::one()))
+                                    + (Coeff::Full(-coeff), bit)
+                            }
+                            Boolean::Constant(true) => {
+                                packed + (Coeff::Full(coeff), Num::constant(C::Base::one()))
+                            }
+                            Boolean::Constant(false) => packed,
+                        };
+                        coeff = coeff + &coeff;
+                    }
+                    packed
+                })
+                .collect()
+        };
+        let lhs = pack(lhs.iter().cloned().map(Into::into).collect());
+        let rhs = pack(rhs.iter().cloned().map(Into::into).collect());
+
+        self.nums_equal_unless_base_case(cs, base_case, &lhs, &rhs)
+    }
+
+    /// Enforces that `point` is not the identity unless `base_case` is set.
+    ///
+    /// The commitments of an honest proof and the generators its inner
+    /// product argument folds to are never the identity, but it is what they
+    /// are witnessed as when there is no proof. Outside the base case this
+    /// rules them out, rather than relying on the identity failing the
+    /// checks it takes part in.
+    fn not_identity_unless_base_case<CS: ConstraintSystem<C::Base>>(
         &self,
         mut cs: CS,
-        bits: &[AllocatedBit],
-    ) -> Result<AllocatedNum<E1::Scalar>, SynthesisError> {
-        let mut value = Some(E1::Scalar::zero());
-        let mut cur = E1::Scalar::one();
-        let mut lc = LinearCombination::zero();
-        for bit in bits {
-            if let Some(bit) = bit.get_value() {
-                if bit {
-                    value = value.map(|value| value + &cur);
-                }
-            }
-            lc = lc + (Coeff::Full(cur), bit.get_variable());
-            cur = cur + &cur;
-        }
+        base_case: &AllocatedBit,
+        point: &CurvePoint<C>,
+    ) -> Result<(), SynthesisError> {
+        let is_identity = point.is_identity();
 
-        let newnum = AllocatedNum::alloc(cs.namespace(|| "scalar"), || {
-            value.ok_or(SynthesisError::AssignmentMissing)
-        })?;
+        // is_identity * (1 - base_case) = 0
+        let (a, b, c) = cs.multiply(
+            || "is_identity * (1 - base_case)",
+            || {
+                let is_identity = is_identity
+                    .get_value()
+                    .ok_or(SynthesisError::AssignmentMissing)?;
+                let base_case = base_case
+                    .get_value()
+                    .ok_or(SynthesisError::AssignmentMissing)?;
+
+                Ok((
+                    is_identity.into(),
+                    (!base_case).into(),
+                    (is_identity && !base_case).into(),
+                ))
+            },
+        )?;
+        cs.enforce_zero(LinearCombination::from(a) - &is_identity.lc(CS::ONE, Coeff::One));
+        cs.enforce_zero(LinearCombination::from(b) - CS::ONE + base_case.get_variable());
+        cs.enforce_zero(LinearCombination::from(c));
 
-        cs.enforce_zero(lc - newnum.get_variable());
+        Ok(())
+    }
 
-        Ok(newnum)
+    /// Checks that the coordinates of `point` are encoded by the bits in
+    /// `leftovers` at the positions `coordinates` of a `LeftoversLayout`,
+    /// unless `base_case` is set.
+    fn point_equal_unless_base_case<CS: ConstraintSystem<C::Base>>(
+        &self,
+        mut cs: CS,
+        base_case: AllocatedBit,
+        point: &CurvePoint<C>,
+        leftovers: &[AllocatedBit],
+        coordinates: (Range<usize>, Range<usize>),
+    ) -> Result<(), SynthesisError> {
+        let (x, y) = point.get_xy();
+        let x = unpack_fe(cs.namespace(|| "unpack x"), &x)?;
+        let y = unpack_fe(cs.namespace(|| "unpack y"), &y)?;
+        self.equal_unless_base_case(
+            cs.namespace(|| "x"),
+            base_case.clone(),
+            &x,
+            &leftovers[coordinates.0],
+        )?;
+        self.equal_unless_base_case(cs.namespace(|| "y"), base_case, &y, &leftovers[coordinates.1])
     }
 
-    fn witness_bits_from_fe<F: Field, CS: ConstraintSystem<E1::Scalar>>(
+    fn witness_bits_from_fe<F: Field, CS: ConstraintSystem<C::Base>>(
         &self,
         mut cs: CS,
         value: F,
     ) -> Result<Vec<AllocatedBit>, SynthesisError> {
-        let mut tmp = Vec::with_capacity(256);
-        let bytes = value.to_bytes();
-
-        for byte in &bytes[0..] {
-            for i in 0..8 {
-                let bit = ((*byte >> i) & 1) == 1;
-                tmp.push(bit);
-            }
-        }
-
         let mut res = Vec::with_capacity(256);
 
-        for (i, b) in tmp.into_iter().enumerate() {
+        for (i, b) in value.to_le_bits().into_iter().enumerate() {
             res.push(AllocatedBit::alloc(
                 cs.namespace(|| format!("bit {}", i)),
                 || Ok(b),
@@ -686,15 +1278,26 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         Ok(res)
     }
 
-    fn verify_proof<CS: ConstraintSystem<E1::Scalar>>(
+    /// Verifies the proof against `k_commitment`, its commitment to k(Y),
+    /// unless `base_case` is set.
+    ///
+    /// `old_leftovers` are the bits of the `Leftovers` the proof was created
+    /// from, and `new_deferred` and `new_leftovers` the bits of the
+    /// `Deferred` and `Leftovers` its verification produces, each in the
+    /// little-endian bit order of their `to_bytes` encoding.
+    pub fn verify_proof<CS: ConstraintSystem<C::Base>>(
         &self,
         mut cs: CS,
         base_case: AllocatedBit,
-        k_commitment: &CurvePoint<E2>,
+        k_commitment: &CurvePoint<C>,
         old_leftovers: &[AllocatedBit],
         new_deferred: &[AllocatedBit],
         new_leftovers: &[AllocatedBit],
     ) -> Result<(), SynthesisError> {
+        let layout = LeftoversLayout::new(self.params.k);
+        assert_eq!(old_leftovers.len(), layout.len());
+        assert_eq!(new_leftovers.len(), layout.len());
+
         let mut transcript = RescueGadget::new(cs.namespace(|| "init Rescue"))?;
         let transcript = &mut transcript;
 
@@ -709,9 +1312,14 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         let r_commitment = CurvePoint::witness(cs.namespace(|| "witness r_commitment"), || {
             Ok(self
                 .proof
-                .map(|proof| proof.proof.r_commitment)
-                .unwrap_or(E2::zero()))
+                .map(|proof| proof.r_commitment)
+                .unwrap_or(C::zero()))
         })?;
+        self.not_identity_unless_base_case(
+            cs.namespace(|| "r_commitment is not the identity"),
+            &base_case,
+            &r_commitment,
+        )?;
         self.commit_point(
             cs.namespace(|| "commit r_commitment"),
             transcript,
@@ -724,9 +1332,14 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             CurvePoint::witness(cs.namespace(|| "witness s_cur_commitment"), || {
                 Ok(self
                     .proof
-                    .map(|proof| proof.proof.s_cur_commitment)
-                    .unwrap_or(E2::zero()))
+                    .map(|proof| proof.s_cur_commitment)
+                    .unwrap_or(C::zero()))
             })?;
+        self.not_identity_unless_base_case(
+            cs.namespace(|| "s_cur_commitment is not the identity"),
+            &base_case,
+            &s_cur_commitment,
+        )?;
         self.commit_point(
             cs.namespace(|| "commit s_cur_commitment"),
             transcript,
@@ -737,9 +1350,14 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             CurvePoint::witness(cs.namespace(|| "witness t_positive_commitment"), || {
                 Ok(self
                     .proof
-                    .map(|proof| proof.proof.t_positive_commitment)
-                    .unwrap_or(E2::zero()))
+                    .map(|proof| proof.t_positive_commitment)
+                    .unwrap_or(C::zero()))
             })?;
+        self.not_identity_unless_base_case(
+            cs.namespace(|| "t_positive_commitment is not the identity"),
+            &base_case,
+            &t_positive_commitment,
+        )?;
         self.commit_point(
             cs.namespace(|| "commit t_positive_commitment"),
             transcript,
@@ -750,9 +1368,14 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             CurvePoint::witness(cs.namespace(|| "witness t_negative_commitment"), || {
                 Ok(self
                     .proof
-                    .map(|proof| proof.proof.t_negative_commitment)
-                    .unwrap_or(E2::zero()))
+                    .map(|proof| proof.t_negative_commitment)
+                    .unwrap_or(C::zero()))
             })?;
+        self.not_identity_unless_base_case(
+            cs.namespace(|| "t_negative_commitment is not the identity"),
+            &base_case,
+            &t_negative_commitment,
+        )?;
         self.commit_point(
             cs.namespace(|| "commit t_negative_commitment"),
             transcript,
@@ -764,9 +1387,14 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         let c_commitment = CurvePoint::witness(cs.namespace(|| "witness c_commitment"), || {
             Ok(self
                 .proof
-                .map(|proof| proof.proof.c_commitment)
-                .unwrap_or(E2::zero()))
+                .map(|proof| proof.c_commitment)
+                .unwrap_or(C::zero()))
         })?;
+        self.not_identity_unless_base_case(
+            cs.namespace(|| "c_commitment is not the identity"),
+            &base_case,
+            &c_commitment,
+        )?;
         self.commit_point(
             cs.namespace(|| "commit c_commitment"),
             transcript,
@@ -779,9 +1407,14 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             CurvePoint::witness(cs.namespace(|| "witness s_new_commitment"), || {
                 Ok(self
                     .proof
-                    .map(|proof| proof.proof.s_new_commitment)
-                    .unwrap_or(E2::zero()))
+                    .map(|proof| proof.s_new_commitment)
+                    .unwrap_or(C::zero()))
             })?;
+        self.not_identity_unless_base_case(
+            cs.namespace(|| "s_new_commitment is not the identity"),
+            &base_case,
+            &s_new_commitment,
+        )?;
         self.commit_point(
             cs.namespace(|| "commit s_new_commitment"),
             transcript,
@@ -791,8 +1424,8 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         // // Openings
 
         let g = {
-            let (x, y) = E2::one().get_xy().unwrap();
-            CurvePoint::<E2>::constant(x, y)
+            let (x, y) = C::one().get_xy().unwrap();
+            CurvePoint::<C>::constant(x, y)
         };
 
         let ky_opening_pt = g.multiply(
@@ -829,8 +1462,8 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             CurvePoint::witness(cs.namespace(|| "witness sx_old_opening_pt"), || {
                 Ok(self
                     .proof
-                    .map(|proof| E2::one() * &proof.proof.sx_old_opening)
-                    .unwrap_or(E2::zero()))
+                    .map(|proof| C::one() * &proof.sx_old_opening)
+                    .unwrap_or(C::zero()))
             })?;
         self.commit_point(
             cs.namespace(|| "commit sx_old_opening_pt"),
@@ -872,8 +1505,8 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             CurvePoint::witness(cs.namespace(|| "witness sx_new_opening_pt"), || {
                 Ok(self
                     .proof
-                    .map(|proof| E2::one() * &proof.proof.sx_new_opening)
-                    .unwrap_or(E2::zero()))
+                    .map(|proof| C::one() * &proof.sx_new_opening)
+                    .unwrap_or(C::zero()))
             })?;
         self.commit_point(
             cs.namespace(|| "commit sx_new_opening_pt"),
@@ -896,54 +1529,35 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         // old_leftovers
         let s_old_commitment =
             CurvePoint::witness(cs.namespace(|| "witness s_old_commitment"), || {
-                Ok(self
-                    .proof
-                    .map(|proof| proof.oldproof1.s_new_commitment)
-                    .unwrap_or(E2::zero()))
+                Ok(self.old_leftovers.map(|leftovers| leftovers.s_new_commitment)
+                    .unwrap_or(C::zero()))
             })?;
-        {
-            let mut cs = cs.namespace(|| format!("s_old_commitment"));
-            let (x, y) = s_old_commitment.get_xy();
-            let x = unpack_fe(cs.namespace(|| "unpack x"), &x)?;
-            let y = unpack_fe(cs.namespace(|| "unpack y"), &y)?;
-            self.equal_unless_base_case(
-                cs.namespace(|| "x"),
-                base_case.clone(),
-                &x,
-                &old_leftovers[0..256],
-            )?;
-            self.equal_unless_base_case(
-                cs.namespace(|| "y"),
-                base_case.clone(),
-                &y,
-                &old_leftovers[256..512],
-            )?;
-        }
+        // Unlike the others, s_old_commitment may be the identity, as it is
+        // in `Leftovers::dummy`.
+        self.point_equal_unless_base_case(
+            cs.namespace(|| "s_old_commitment"),
+            base_case.clone(),
+            &s_old_commitment,
+            old_leftovers,
+            layout.s_new_commitment(),
+        )?;
 
         let g_old = CurvePoint::witness(cs.namespace(|| "witness g_old"), || {
-            Ok(self
-                .proof
-                .map(|proof| proof.oldproof1.g_new)
-                .unwrap_or(E2::zero()))
+            Ok(self.old_leftovers.map(|leftovers| leftovers.g_new)
+                .unwrap_or(C::zero()))
         })?;
-        {
-            let mut cs = cs.namespace(|| format!("g_old"));
-            let (x, y) = g_old.get_xy();
-            let x = unpack_fe(cs.namespace(|| "unpack x"), &x)?;
-            let y = unpack_fe(cs.namespace(|| "unpack y"), &y)?;
-            self.equal_unless_base_case(
-                cs.namespace(|| "x"),
-                base_case.clone(),
-                &x,
-                &old_leftovers[256 * 2 + 128..256 * 3 + 128],
-            )?;
-            self.equal_unless_base_case(
-                cs.namespace(|| "y"),
-                base_case.clone(),
-                &y,
-                &old_leftovers[256 * 3 + 128..256 * 4 + 128],
-            )?;
-        }
+        self.not_identity_unless_base_case(
+            cs.namespace(|| "g_old is not the identity"),
+            &base_case,
+            &g_old,
+        )?;
+        self.point_equal_unless_base_case(
+            cs.namespace(|| "g_old"),
+            base_case.clone(),
+            &g_old,
+            old_leftovers,
+            layout.g_new(),
+        )?;
 
         let p_commitment = {
             let mut cs = cs.namespace(|| "p_commitment");
@@ -957,26 +1571,26 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             let p_commitment = p_commitment * &z + leftovers.g_new;
             */
             let p_commitment = r_commitment.clone();
-            let p_commitment = p_commitment.multiply_endo(cs.namespace(|| "mul z 1"), &z)?;
+            let p_commitment = z.multiply(cs.namespace(|| "mul z 1"), &p_commitment)?;
             let p_commitment =
                 p_commitment.add(cs.namespace(|| "add s_old_commitment"), &s_old_commitment)?;
-            let p_commitment = p_commitment.multiply_endo(cs.namespace(|| "mul z 2"), &z)?;
+            let p_commitment = z.multiply(cs.namespace(|| "mul z 2"), &p_commitment)?;
             let p_commitment =
                 p_commitment.add(cs.namespace(|| "add s_cur_commitment"), &s_cur_commitment)?;
-            let p_commitment = p_commitment.multiply_endo(cs.namespace(|| "mul z 3"), &z)?;
+            let p_commitment = z.multiply(cs.namespace(|| "mul z 3"), &p_commitment)?;
             let p_commitment = p_commitment.add(
                 cs.namespace(|| "add t_positive_commitment"),
                 &t_positive_commitment,
             )?;
-            let p_commitment = p_commitment.multiply_endo(cs.namespace(|| "mul z 4"), &z)?;
+            let p_commitment = z.multiply(cs.namespace(|| "mul z 4"), &p_commitment)?;
             let p_commitment = p_commitment.add(
                 cs.namespace(|| "add t_negative_commitment"),
                 &t_negative_commitment,
             )?;
-            let p_commitment = p_commitment.multiply_endo(cs.namespace(|| "mul z 5"), &z)?;
+            let p_commitment = z.multiply(cs.namespace(|| "mul z 5"), &p_commitment)?;
             let p_commitment =
                 p_commitment.add(cs.namespace(|| "add s_new_commitment"), &s_new_commitment)?;
-            let p_commitment = p_commitment.multiply_endo(cs.namespace(|| "mul z 6"), &z)?;
+            let p_commitment = z.multiply(cs.namespace(|| "mul z 6"), &p_commitment)?;
             p_commitment.add(cs.namespace(|| "add g_old"), &g_old)?
         };
 
@@ -992,26 +1606,26 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             let p_opening = p_opening * &z + &gx_old_opening;
             */
             let p_opening = rx_opening_pt;
-            let p_opening = p_opening.multiply_endo(cs.namespace(|| "mul z 1"), &z)?;
+            let p_opening = z.multiply(cs.namespace(|| "mul z 1"), &p_opening)?;
             let p_opening =
                 p_opening.add(cs.namespace(|| "add sx_old_opening_pt"), &sx_old_opening_pt)?;
-            let p_opening = p_opening.multiply_endo(cs.namespace(|| "mul z 2"), &z)?;
+            let p_opening = z.multiply(cs.namespace(|| "mul z 2"), &p_opening)?;
             let p_opening =
                 p_opening.add(cs.namespace(|| "add sx_cur_opening_pt"), &sx_cur_opening_pt)?;
-            let p_opening = p_opening.multiply_endo(cs.namespace(|| "mul z 3"), &z)?;
+            let p_opening = z.multiply(cs.namespace(|| "mul z 3"), &p_opening)?;
             let p_opening = p_opening.add(
                 cs.namespace(|| "add tx_positive_opening_pt"),
                 &tx_positive_opening_pt,
             )?;
-            let p_opening = p_opening.multiply_endo(cs.namespace(|| "mul z 4"), &z)?;
+            let p_opening = z.multiply(cs.namespace(|| "mul z 4"), &p_opening)?;
             let p_opening = p_opening.add(
                 cs.namespace(|| "add tx_negative_opening_pt"),
                 &tx_negative_opening_pt,
             )?;
-            let p_opening = p_opening.multiply_endo(cs.namespace(|| "mul z 5"), &z)?;
+            let p_opening = z.multiply(cs.namespace(|| "mul z 5"), &p_opening)?;
             let p_opening =
                 p_opening.add(cs.namespace(|| "add sx_new_opening_pt"), &sx_new_opening_pt)?;
-            let p_opening = p_opening.multiply_endo(cs.namespace(|| "mul z 6"), &z)?;
+            let p_opening = z.multiply(cs.namespace(|| "mul z 6"), &p_opening)?;
             p_opening.add(cs.namespace(|| "add gx_old_opening_pt"), &gx_old_opening_pt)?
         };
 
@@ -1022,13 +1636,13 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             let qy_opening = self.sx_cur_opening + &(ky_opening * &z);
             */
 
-            let q_commitment = k_commitment.multiply_endo(cs.namespace(|| "mul z 1"), &z)?;
+            let q_commitment = z.multiply(cs.namespace(|| "mul z 1"), &k_commitment)?;
             q_commitment.add(cs.namespace(|| "add c_commitment"), &c_commitment)?
         };
 
         let qy_opening = {
             let mut cs = cs.namespace(|| "qy_opening");
-            let qy_opening = ky_opening_pt.multiply_endo(cs.namespace(|| "mul z 2"), &z)?;
+            let qy_opening = z.multiply(cs.namespace(|| "mul z 2"), &ky_opening_pt)?;
             qy_opening.add(cs.namespace(|| "add sx_cur_opening_pt"), &sx_cur_opening_pt)?
         };
 
@@ -1066,68 +1680,47 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             ],
             b,
         )?;
+        cs.assert_budget("inner product", budget(INNER_PRODUCT_BUDGET, self.params.k))?;
 
         // new_leftovers
-        {
-            let mut cs = cs.namespace(|| format!("s_new_commitment"));
-            let (x, y) = s_new_commitment.get_xy();
-            let x = unpack_fe(cs.namespace(|| "unpack x"), &x)?;
-            let y = unpack_fe(cs.namespace(|| "unpack y"), &y)?;
-            self.equal_unless_base_case(
-                cs.namespace(|| "x"),
-                base_case.clone(),
-                &x,
-                &new_leftovers[0..256],
-            )?;
-            self.equal_unless_base_case(
-                cs.namespace(|| "y"),
-                base_case.clone(),
-                &y,
-                &new_leftovers[256..512],
-            )?;
-        }
+        self.point_equal_unless_base_case(
+            cs.namespace(|| "s_new_commitment"),
+            base_case.clone(),
+            &s_new_commitment,
+            new_leftovers,
+            layout.s_new_commitment(),
+        )?;
 
         {
             self.equal_unless_base_case(
                 cs.namespace(|| "y_new in new_leftovers"),
                 base_case.clone(),
-                &y_new,
-                &new_leftovers[512..512 + 128],
+                y_new.bits(),
+                &new_leftovers[layout.y_new()],
             )?;
         }
 
-        {
-            let mut cs = cs.namespace(|| format!("g_new"));
-            let (x, y) = g_new.get_xy();
-            let x = unpack_fe(cs.namespace(|| "unpack x"), &x)?;
-            let y = unpack_fe(cs.namespace(|| "unpack y"), &y)?;
-            self.equal_unless_base_case(
-                cs.namespace(|| "x"),
-                base_case.clone(),
-                &x,
-                &new_leftovers[256 * 2 + 128..256 * 3 + 128],
-            )?;
-            self.equal_unless_base_case(
-                cs.namespace(|| "y"),
-                base_case.clone(),
-                &y,
-                &new_leftovers[256 * 3 + 128..256 * 4 + 128],
-            )?;
-        }
+        self.point_equal_unless_base_case(
+            cs.namespace(|| "g_new"),
+            base_case.clone(),
+            &g_new,
+            new_leftovers,
+            layout.g_new(),
+        )?;
 
         for (i, challenge_sq_packed) in challenges_sq_packed_new.into_iter().enumerate() {
             self.equal_unless_base_case(
                 cs.namespace(|| format!("challenge {} in new_leftovers", i)),
                 base_case.clone(),
-                &challenge_sq_packed,
-                &new_leftovers[256 * 4 + 128 + 128 * i..256 * 4 + 128 + 128 * i + 128],
+                challenge_sq_packed.bits(),
+                &new_leftovers[layout.challenge(i)],
             )?;
 
             // 4 * 128 + 6 * 256 + k * 128 + 256 is the start
             self.equal_unless_base_case(
                 cs.namespace(|| format!("challenge {} in new_deferred", i)),
                 base_case.clone(),
-                &challenge_sq_packed,
+                challenge_sq_packed.bits(),
                 &new_deferred[(4 * 128 + 6 * 256 + self.params.k * 128 + 256) + i * 128
                     ..(4 * 128 + 6 * 256 + self.params.k * 128 + 256) + i * 128 + 128],
             )?;
@@ -1138,7 +1731,7 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             self.equal_unless_base_case(
                 cs.namespace(|| "challenge x in new_deferred"),
                 base_case.clone(),
-                &x,
+                x.bits(),
                 &new_deferred[0..128],
             )?;
         }
@@ -1148,7 +1741,7 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             self.equal_unless_base_case(
                 cs.namespace(|| "challenge y_cur in new_deferred"),
                 base_case.clone(),
-                &y_cur,
+                y_cur.bits(),
                 &new_deferred[128 * 2..128 * 2 + 128],
             )?;
         }
@@ -1158,7 +1751,7 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             self.equal_unless_base_case(
                 cs.namespace(|| "challenge y_new in new_deferred"),
                 base_case.clone(),
-                &y_new,
+                y_new.bits(),
                 &new_deferred[128 * 3..128 * 3 + 128],
             )?;
         }
@@ -1166,15 +1759,19 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         Ok(())
     }
 
-    fn verify_inner_product<CS: ConstraintSystem<E1::Scalar>>(
+    /// Verifies the inner product argument opening `commitments` to
+    /// `openings`, each opening committed to as a multiple of the generator,
+    /// at the points whose `b` values are given by the bits in `b`. Returns
+    /// the final generator and the packed squared challenges of the rounds.
+    pub fn verify_inner_product<CS: ConstraintSystem<C::Base>>(
         &self,
         mut cs: CS,
         base_case: &AllocatedBit,
-        transcript: &mut RescueGadget<E1::Scalar>,
-        commitments: &[CurvePoint<E2>],
-        openings: &[CurvePoint<E2>],
+        transcript: &mut RescueGadget<C::Base>,
+        commitments: &[CurvePoint<C>],
+        openings: &[CurvePoint<C>],
         b: &[&[AllocatedBit]],
-    ) -> Result<(CurvePoint<E2>, Vec<Vec<AllocatedBit>>), SynthesisError> {
+    ) -> Result<(CurvePoint<C>, Vec<AllocatedChallenge>), SynthesisError> {
         assert_eq!(commitments.len(), openings.len());
         let mut challenges_sq_packed = vec![];
 
@@ -1189,26 +1786,26 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
                 let L = CurvePoint::witness(cs.namespace(|| format!("witness L_{}", j)), || {
                     Ok(self
                         .proof
-                        .map(|proof| proof.proof.inner_product.rounds[i].L[j])
-                        .unwrap_or(E2::zero()))
+                        .map(|proof| proof.inner_product.rounds[i].L[j])
+                        .unwrap_or(C::zero()))
                 })?;
                 let R = CurvePoint::witness(cs.namespace(|| format!("witness R_{}", j)), || {
                     Ok(self
                         .proof
-                        .map(|proof| proof.proof.inner_product.rounds[i].R[j])
-                        .unwrap_or(E2::zero()))
+                        .map(|proof| proof.inner_product.rounds[i].R[j])
+                        .unwrap_or(C::zero()))
                 })?;
                 let l = CurvePoint::witness(cs.namespace(|| format!("witness l_{}", j)), || {
                     Ok(self
                         .proof
-                        .map(|proof| E2::one() * &proof.proof.inner_product.rounds[i].l[j])
-                        .unwrap_or(E2::zero()))
+                        .map(|proof| C::one() * &proof.inner_product.rounds[i].l[j])
+                        .unwrap_or(C::zero()))
                 })?;
                 let r = CurvePoint::witness(cs.namespace(|| format!("witness r_{}", j)), || {
                     Ok(self
                         .proof
-                        .map(|proof| E2::one() * &proof.proof.inner_product.rounds[i].r[j])
-                        .unwrap_or(E2::zero()))
+                        .map(|proof| C::one() * &proof.inner_product.rounds[i].r[j])
+                        .unwrap_or(C::zero()))
                 })?;
 
                 self.commit_point(cs.namespace(|| format!("commit L_{}", j)), transcript, &L)?;
@@ -1239,22 +1836,29 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             )?;
             challenges_sq_packed.push(challenge_sq_packed.clone());
 
+            // These four multiplications by the round challenge take about
+            // 2,080 of the round's gates per commitment, 516 for each
+            // `multiply_endo` and a few more for each inverse. Only the
+            // challenge bits are common to them: each bit pair doubles and
+            // adds into an accumulator, and conditionally negates and applies
+            // the endomorphism to a point, so every gate depends on the point
+            // being multiplied and none can be shared between points.
             for (j, tmp) in tmp.into_iter().enumerate() {
-                let L = tmp.0.multiply_endo(
+                let L = challenge_sq_packed.multiply(
                     cs.namespace(|| format!("[challenge^2] L_{}", j)),
-                    &challenge_sq_packed,
+                    &tmp.0,
                 )?;
-                let R = tmp.1.multiply_inv_endo(
+                let R = challenge_sq_packed.multiply_inv(
                     cs.namespace(|| format!("[challenge^-2] R_{}", j)),
-                    &challenge_sq_packed,
+                    &tmp.1,
                 )?;
-                let l = tmp.2.multiply_endo(
+                let l = challenge_sq_packed.multiply(
                     cs.namespace(|| format!("[challenge^2] l_{}", j)),
-                    &challenge_sq_packed,
+                    &tmp.2,
                 )?;
-                let r = tmp.3.multiply_inv_endo(
+                let r = challenge_sq_packed.multiply_inv(
                     cs.namespace(|| format!("[challenge^-2] r_{}", j)),
-                    &challenge_sq_packed,
+                    &tmp.3,
                 )?;
 
                 p[j] = p[j].add(cs.namespace(|| format!("p_{} + L_{}", j, j)), &L)?;
@@ -1267,53 +1871,77 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         let g_new = CurvePoint::witness(cs.namespace(|| "witness G"), || {
             Ok(self
                 .proof
-                .map(|proof| proof.proof.inner_product.g)
-                .unwrap_or(E2::zero()))
+                .map(|proof| proof.inner_product.g)
+                .unwrap_or(C::zero()))
         })?;
+        self.not_identity_unless_base_case(
+            cs.namespace(|| "G is not the identity"),
+            base_case,
+            &g_new,
+        )?;
 
         let g = {
-            let (x, y) = E2::one().get_xy().unwrap();
-            CurvePoint::<E2>::constant(x, y)
+            let (x, y) = C::one().get_xy().unwrap();
+            CurvePoint::<C>::constant(x, y)
         };
 
         for j in 0..commitments.len() {
             let a = self.witness_bits_from_fe(
                 cs.namespace(|| format!("witness a_{}", j)),
                 self.proof
-                    .map(|proof| proof.proof.inner_product.a[j])
+                    .map(|proof| proof.inner_product.a[j])
                     .unwrap_or(Field::zero()),
             )?;
 
             let (x1, y1) = p[j].get_xy();
             let (x2, y2) = g_new
-                .multiply(cs.namespace(|| format!("[a_{}] g_new", j)), &a)?
+                .multiply_windowed(
+                    cs.namespace(|| format!("[a_{}] g_new", j)),
+                    &a,
+                    MULTIPLY_WINDOW,
+                )?
                 .get_xy();
-            {
-                let mut cs = cs.namespace(|| format!("p_{} == [a_{}] g_new", j, j));
-                self.num_equal_unless_base_case(cs.namespace(|| "x"), base_case.clone(), &Combination::from(x1), &Combination::from(x2))?;
-                self.num_equal_unless_base_case(cs.namespace(|| "y"), base_case.clone(), &Combination::from(y1), &Combination::from(y2))?;
-            }
+            self.nums_equal_unless_base_case(
+                cs.namespace(|| format!("p_{} == [a_{}] g_new", j, j)),
+                base_case.clone(),
+                &[x1.into(), y1.into()],
+                &[x2.into(), y2.into()],
+            )?;
 
             let (x1, y1) = v[j].get_xy();
             let (x2, y2) = g
-                .multiply(cs.namespace(|| "[a_{}] g"), &a)?
-                .multiply(cs.namespace(|| format!("[a b_{}] g", j)), b[j])?
+                .multiply_windowed(cs.namespace(|| "[a_{}] g"), &a, MULTIPLY_WINDOW)?
+                .multiply_windowed(
+                    cs.namespace(|| format!("[a b_{}] g", j)),
+                    b[j],
+                    MULTIPLY_WINDOW,
+                )?
                 .get_xy();
-            {
-                let mut cs = cs.namespace(|| format!("v_{} == [a_{} b_{}] g", j, j, j));
-                self.num_equal_unless_base_case(cs.namespace(|| "x"), base_case.clone(), &Combination::from(x1), &Combination::from(x2))?;
-                self.num_equal_unless_base_case(cs.namespace(|| "y"), base_case.clone(), &Combination::from(y1), &Combination::from(y2))?;
-            }
+            self.nums_equal_unless_base_case(
+                cs.namespace(|| format!("v_{} == [a_{} b_{}] g", j, j, j)),
+                base_case.clone(),
+                &[x1.into(), y1.into()],
+                &[x2.into(), y2.into()],
+            )?;
         }
 
         Ok((g_new, challenges_sq_packed))
     }
 
-    fn commit_point<CS: ConstraintSystem<E1::Scalar>>(
+    /// Absorbs both coordinates of `point`, matching `append_point`.
+    ///
+    /// Absorbing `x` and the parity of `y` instead would save an absorption
+    /// per point, but absorptions are cheap: a duplex of the sponge costs 781
+    /// gates for `SPONGE_RATE` (12) elements, about 65 gates each. Taking the
+    /// parity of `y` needs its full bit decomposition, which costs 256 gates
+    /// through `unpack_fe` and more again to check it is canonical, since
+    /// `y + p` fits in 256 bits and has the other parity. Compression would
+    /// make every committed point at least four times as expensive.
+    fn commit_point<CS: ConstraintSystem<C::Base>>(
         &self,
         mut cs: CS,
-        transcript: &mut RescueGadget<E1::Scalar>,
-        point: &CurvePoint<E2>,
+        transcript: &mut RescueGadget<C::Base>,
+        point: &CurvePoint<C>,
     ) -> Result<(), SynthesisError> {
         let (x, y) = point.get_xy();
         transcript.absorb(cs.namespace(|| "absorb x"), x)?;
@@ -1322,228 +1950,77 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
         Ok(())
     }
 
-    fn get_challenge_scalar<CS: ConstraintSystem<E1::Scalar>>(
-        &self,
-        mut cs: CS,
-        bits: &[AllocatedBit],
-    ) -> Result<AllocatedNum<E1::Scalar>, SynthesisError> {
-        assert_eq!(bits.len(), 128);
-        let mut acc = Combination::from(Num::constant(E1::Scalar::one() + &E1::Scalar::one() + &E1::Scalar::one()));
-
-        for i in 1..64 {
-            let should_negate = &bits[i * 2];
-            let should_endo = &bits[i * 2 + 1];
-
-            // acc = acc + acc
-            acc = acc.scale(E1::Scalar::from_u128(2));
-            // tmp = 1 - 2b
-            // acc = acc + tmp
-            acc = acc + Combination::from(AllocatedNum::one(&mut cs));
-            acc = acc
-                + (Combination::from(AllocatedNum::from(should_negate.clone()))
-                    .scale(-E1::Scalar::from_u128(2)));
-            // acc = (1 - b') acc + b' acc * beta
-            //     = acc - b' * acc + b' * acc * beta
-            //     = (b' * beta - b') * (acc) + acc
-            let mut outval = None;
-            let (a, b, c) = cs.multiply(
-                || format!("should_endo round {}", i),
-                || {
-                    let acc = acc.get_value().ok_or(SynthesisError::AssignmentMissing)?;
-                    let should_endo = should_endo
-                        .get_value()
-                        .ok_or(SynthesisError::AssignmentMissing)?;
-                    let should_endo = if should_endo {
-                        E1::Scalar::one()
-                    } else {
-                        E1::Scalar::zero()
-                    };
-                    let beta = E1::Scalar::BETA;
-
-                    let lhs = should_endo * &beta - &should_endo;
-                    let rhs = acc;
-                    let out = lhs * &rhs;
-                    outval = Some(out);
-
-                    Ok((lhs, rhs, out))
-                },
-            )?;
-            cs.enforce_zero(
-                LinearCombination::from(a) + should_endo.get_variable()
-                    - (Coeff::Full(E1::Scalar::BETA), should_endo.get_variable()),
-            );
-            let acclc = acc.lc(&mut cs);
-            cs.enforce_zero(LinearCombination::from(b) - &acclc);
-
-            acc = acc + Combination::from(Num::from(AllocatedNum::from_raw_unchecked(outval, c)));
-        }
-
-        let newacc = AllocatedNum::alloc(cs.namespace(|| "final acc value"), || {
-            acc.get_value().ok_or(SynthesisError::AssignmentMissing)
-        })?;
-
-        let acclc = acc.lc(&mut cs);
-        cs.enforce_zero(LinearCombination::from(newacc.get_variable()) - &acclc);
-
-        Ok(newacc)
-    }
-
-    fn get_challenge<CS: ConstraintSystem<E1::Scalar>>(
+    fn get_challenge<CS: ConstraintSystem<C::Base>>(
         &self,
-        mut cs: CS,
-        transcript: &mut RescueGadget<E1::Scalar>,
-    ) -> Result<Vec<AllocatedBit>, SynthesisError> {
-        let num = transcript.squeeze(cs.namespace(|| "squeeze"))?;
-        let mut bits = unpack_fe(cs.namespace(|| "unpack"), &num.into())?;
-        bits.truncate(127);
-        bits.push(AllocatedBit::one(cs));
-
-        Ok(bits)
+        cs: CS,
+        transcript: &mut RescueGadget<C::Base>,
+    ) -> Result<AllocatedChallenge, SynthesisError> {
+        AllocatedChallenge::squeeze(cs, transcript, self.params.level)
     }
 }
-
-impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Scalar>>
-    Circuit<E1::Scalar> for VerificationCircuit<'a, E1, E2, Inner>
+impl<
+        'a,
+        E1: Curve,
+        E2: Curve<Base = E1::Scalar>,
+        Inner: RecursiveCircuit<E1::Scalar>,
+        R: Role<'a, E1, E2>,
+    > Circuit<E1::Scalar> for VerificationCircuit<'a, E1, E2, Inner, R>
 {
     fn synthesize<CS: ConstraintSystem<E1::Scalar>>(
         &self,
         cs: &mut CS,
     ) -> Result<(), SynthesisError> {
-        let mut payload_bits = vec![];
-        {
-            let mut cs = cs.namespace(|| "new_payload");
-            for (j, byte) in self.new_payload.into_iter().enumerate() {
-                for i in 0..8 {
-                    let bit = (*byte >> i) & 1 == 1;
-                    payload_bits.push(AllocatedBit::alloc_input_unchecked(
-                        cs.namespace(|| format!("bit {}", 8 * j + i)),
-                        || Ok(bit),
-                    )?);
-                }
-            }
-        }
-
-        let mut leftovers1 = vec![];
-        {
-            let mut cs = cs.namespace(|| "old_leftovers");
-            if let Some(l) = &self.old_leftovers {
-                let bytes = l.to_bytes();
-                for (j, byte) in bytes.into_iter().enumerate() {
-                    for i in 0..8 {
-                        let bit = (byte >> i) & 1 == 1;
-                        leftovers1.push(AllocatedBit::alloc_input_unchecked(
-                            cs.namespace(|| format!("bit {}", 8 * j + i)),
-                            || Ok(bit),
-                        )?);
-                    }
-                }
-            } else {
-                // (256 * 2) + 128 + (256 * 2) + (128 * k)
-                // = 256 * 4 + 128 * (k + 1)
-                let num_bits = 256 * 4 + 128 * (self.params.k + 1);
-                for i in 0..num_bits {
-                    leftovers1.push(AllocatedBit::alloc_input_unchecked(
-                        cs.namespace(|| format!("bit {}", i)),
-                        || Ok(false),
-                    )?);
-                }
-            }
-        }
-
-        let mut leftovers2 = vec![];
-        {
-            let mut cs = cs.namespace(|| "new_leftovers");
-            if let Some(l) = &self.new_leftovers {
-                let bytes = l.to_bytes();
-                for (j, byte) in bytes.into_iter().enumerate() {
-                    for i in 0..8 {
-                        let bit = (byte >> i) & 1 == 1;
-                        leftovers2.push(AllocatedBit::alloc_input_unchecked(
-                            cs.namespace(|| format!("bit {}", 8 * j + i)),
-                            || Ok(bit),
-                        )?);
-                    }
-                }
-            } else {
-                // (256 * 2) + 128 + (256 * 2) + (128 * k)
-                // = 256 * 4 + 128 * (k + 1)
-                let num_bits = 256 * 4 + 128 * (self.params.k + 1);
-                for i in 0..num_bits {
-                    leftovers2.push(AllocatedBit::alloc_input_unchecked(
-                        cs.namespace(|| format!("bit {}", i)),
-                        || Ok(false),
-                    )?);
-                }
-            }
-        }
-
-        let mut deferred = vec![];
-        {
-            let mut cs = cs.namespace(|| "deferred");
-            if let Some(l) = &self.deferred {
-                let bytes = l.to_bytes();
-                for (j, byte) in bytes.into_iter().enumerate() {
-                    for i in 0..8 {
-                        let bit = (byte >> i) & 1 == 1;
-                        deferred.push(AllocatedBit::alloc_input_unchecked(
-                            cs.namespace(|| format!("bit {}", 8 * j + i)),
-                            || Ok(bit),
-                        )?);
-                    }
-                }
-            } else {
+        // The inputs are checked to be bits only once all of them have been
+        // allocated.
+        let witness = self.role.witness();
+        let mut inputs = InputBits::new();
+        let mut payload_bits = inputs.alloc_bytes(&mut *cs, "new_payload", self.new_payload)?;
+        let leftovers1 = inputs.alloc(
+            &mut *cs,
+            "old_leftovers",
+            LeftoversLayout::new(self.k).bits(witness.map(|witness| &witness.old_leftovers)),
+        )?;
+        let leftovers2 = inputs.alloc(
+            &mut *cs,
+            "new_leftovers",
+            LeftoversLayout::new(self.params.k).bits(witness.map(|witness| &witness.new_leftovers)),
+        )?;
+        let deferred = match witness.map(|witness| &witness.deferred) {
+            Some(deferred) => inputs.alloc_bytes(&mut *cs, "deferred", &deferred.to_bytes())?,
+            None => {
                 // 12 * 256 + (4 + 2k) * 128
                 let num_bits = 12 * 256 + (4 + 2 * self.params.k) * 128;
-                for i in 0..num_bits {
-                    deferred.push(AllocatedBit::alloc_input_unchecked(
-                        cs.namespace(|| format!("deferred bit {}", i)),
-                        || Ok(false),
-                    )?);
-                }
-            }
-        }
-
-        // Check that all the inputs are booleans now that we've allocated
-        // all of our public inputs.
-        {
-            let mut cs = cs.namespace(|| "constrain new_payload");
-            for (i, b) in payload_bits.iter().enumerate() {
-                b.check(cs.namespace(|| format!("bit {}", i)))?;
-            }
-        }
-        {
-            let mut cs = cs.namespace(|| "constrain old_leftovers");
-            for (i, b) in leftovers1.iter().enumerate() {
-                b.check(cs.namespace(|| format!("bit {}", i)))?;
-            }
-        }
-        {
-            let mut cs = cs.namespace(|| "constrain new_leftovers");
-            for (i, b) in leftovers2.iter().enumerate() {
-                b.check(cs.namespace(|| format!("bit {}", i)))?;
-            }
-        }
-        {
-            let mut cs = cs.namespace(|| "constrain deferred");
-            for (i, b) in deferred.iter().enumerate() {
-                b.check(cs.namespace(|| format!("bit {}", i)))?;
+                inputs.alloc(&mut *cs, "deferred", vec![false; num_bits])?
             }
-        }
+        };
+        inputs.finish(&mut *cs)?;
+
+        // The auxiliary commitments are the last bits of the payload.
+        let aux_len = 256 * self.inner_circuit.num_aux_commitments();
+        let aux_bits = payload_bits.split_off(
+            payload_bits
+                .len()
+                .checked_sub(aux_len)
+                .ok_or(SynthesisError::Unsatisfiable)?,
+        );
 
-        // Is this the base case?
+        // Is this the base case? If so, the checks of the old proof below
+        // do not have to hold, and the old payload must be the base payload.
         let base_case = AllocatedBit::alloc(cs.namespace(|| "is base case"), || {
-            self.base_case.ok_or(SynthesisError::AssignmentMissing)
+            witness
+                .map(|witness| witness.proof.is_none())
+                .ok_or(SynthesisError::AssignmentMissing)
         })?;
 
         // Compute k(Y) commitment
-        let mut k_commitment = CurvePoint::<E2>::constant(
-            self.params.generators_xy[1].0,
-            self.params.generators_xy[1].1,
-        );
+        let mut k_commitment = {
+            let (x, y) = self.params.k_commitment_base().get_xy().unwrap();
+            CurvePoint::<E2>::constant(x, y)
+        };
 
         // Attach payload for old proof
         let mut old_payload = vec![];
-        if let Some(proof) = &self.proof {
+        if let Some(proof) = self.proof() {
             let mut cs = cs.namespace(|| "old_payload");
             for (j, byte) in proof.payload.iter().enumerate() {
                 for i in 0..8 {
@@ -1556,7 +2033,14 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             }
         } else {
             let mut cs = cs.namespace(|| "base_payload");
-            for (i, bit) in self.inner_circuit.base_payload().into_iter().enumerate() {
+            let base_payload = self.inner_circuit.base_payload();
+            assert!(
+                aux_len == 0 || base_payload.len() % 8 == 0,
+                "auxiliary commitments must follow a whole number of payload bytes"
+            );
+            // There are no auxiliary commitments before the first proof.
+            let bits = base_payload.into_iter().chain(vec![false; aux_len]);
+            for (i, bit) in bits.enumerate() {
                 old_payload.push(AllocatedBit::alloc(
                     cs.namespace(|| format!("bit {}", i)),
                     || Ok(bit),
@@ -1601,38 +2085,32 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             }
         }
 
+        // Tie the base case to the old payload, if the inner circuit asks.
+        derive_base_case(
+            cs.namespace(|| "derive base case"),
+            self.inner_circuit.base_case_rule(),
+            &self.inner_circuit.base_payload(),
+            &old_payload,
+            &base_case,
+        )?;
+
+        let old_leftovers_layout = LeftoversLayout::new(self.params.k);
         let mut old_leftovers1 = vec![];
         {
             let mut cs = cs.namespace(|| "old_proof");
-            if let Some(l) = &self.proof {
-                let l = &l.oldproof1;
-                let bytes = l.to_bytes();
-                for (j, byte) in bytes.into_iter().enumerate() {
-                    for i in 0..8 {
-                        let bit = (byte >> i) & 1 == 1;
-                        old_leftovers1.push(AllocatedBit::alloc(
-                            cs.namespace(|| format!("bit {}", 8 * j + i)),
-                            || Ok(bit),
-                        )?);
-                    }
-                }
-            } else {
-                // (256 * 2) + 128 + (256 * 2) + (128 * k)
-                // = 256 * 4 + 128 * (k + 1)
-                let num_bits = 256 * 4 + 128 * (self.params.k + 1);
-                for i in 0..num_bits {
-                    old_leftovers1.push(AllocatedBit::alloc(
-                        cs.namespace(|| format!("bit {}", i)),
-                        || Ok(false),
-                    )?);
-                }
+            let bits = old_leftovers_layout.bits(self.proof().map(|proof| &proof.oldproof1));
+            for (i, bit) in bits.into_iter().enumerate() {
+                old_leftovers1.push(AllocatedBit::alloc(
+                    cs.namespace(|| format!("bit {}", i)),
+                    || Ok(bit),
+                )?);
             }
         }
 
         let mut old_deferred = vec![];
         {
             let mut cs = cs.namespace(|| "old_deferred");
-            if let Some(l) = &self.proof {
+            if let Some(l) = self.proof() {
                 let l = &l.deferred;
                 let bytes = l.to_bytes();
                 for (j, byte) in bytes.into_iter().enumerate() {
@@ -1645,7 +2123,7 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
                     }
                 }
             } else {
-                let dummy_deferred = Deferred::<E1::Scalar>::dummy(self.params.k);
+                let dummy_deferred = Deferred::<E1::Scalar>::dummy(self.k);
                 let bytes = dummy_deferred.to_bytes();
                 for (_, byte) in bytes.into_iter().enumerate() {
                     for i in 0..8 {
@@ -1659,8 +2137,6 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             }
         }
 
-        assert_eq!(old_deferred.len(), deferred.len());
-
         let mut bits_for_k_commitment = vec![];
         bits_for_k_commitment.extend(old_payload.clone());
         bits_for_k_commitment.extend(old_leftovers1.clone());
@@ -1671,10 +2147,11 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             let mut cs = cs.namespace(|| "k_commitment");
             for (i, (bit, gen)) in bits_for_k_commitment
                 .into_iter()
-                .zip(self.params.generators_xy[2..].iter())
+                .zip(self.params.k_commitment_generators().iter())
                 .enumerate()
             {
-                let gen = CurvePoint::constant(gen.0, gen.1);
+                let (x, y) = gen.get_xy().unwrap();
+                let gen = CurvePoint::constant(x, y);
                 k_commitment = k_commitment.add_conditionally_incomplete(
                     cs.namespace(|| format!("bit {}", i)),
                     &gen,
@@ -1685,8 +2162,13 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
 
         // println!("k inside circuit: {:?}", k_commitment);
 
-        self.verify_deferred(cs.namespace(|| "verify deferred"), &old_deferred)?;
-        self.verify_proof(
+        self.verify_deferred(
+            cs.namespace(|| "verify deferred"),
+            &old_deferred,
+            &deferred[128 * 2..128 * 3],
+        )?;
+        cs.assert_budget("verify deferred", budget(VERIFY_DEFERRED_BUDGET, self.k))?;
+        self.verifier().verify_proof(
             cs.namespace(|| "verify proof"),
             base_case.clone(),
             &k_commitment,
@@ -1694,27 +2176,283 @@ impl<'a, E1: Curve, E2: Curve<Base = E1::Scalar>, Inner: RecursiveCircuit<E1::Sc
             &deferred,
             &leftovers2,
         )?;
+        cs.assert_budget("verify proof", budget(VERIFY_PROOF_BUDGET, self.params.k))?;
 
         // deferred old challenges should be the same
-        self.equal_unless_base_case(
+        self.verifier().equal_unless_base_case(
             cs.namespace(|| "deferred[challeges] == old_leftovers1[challenges]"),
             base_case.clone(),
             &deferred[256 * 8..256 * 8 + 128 * self.params.k],
-            &old_leftovers1[256 * 4 + 128..],
+            &old_leftovers1[old_leftovers_layout.challenges()],
         )?;
 
         // deferred y_old should be the same
-        self.equal_unless_base_case(
+        self.verifier().equal_unless_base_case(
             cs.namespace(|| "deferred[y_old] == old_leftovers1[y_old]"),
             base_case.clone(),
             &deferred[128 * 1..128 * 2],
-            &old_leftovers1[256 * 2..256 * 2 + 128],
+            &old_leftovers1[old_leftovers_layout.y_new()],
         )?;
 
+        // The inner circuit sees the payloads without the auxiliary
+        // commitments, which it checks separately.
+        let old_payload = &old_payload[..old_payload.len() - aux_len];
+        if aux_len > 0 {
+            let commitments: Vec<_> = aux_bits.chunks(256).collect();
+            self.inner_circuit.synthesize_aux(
+                &mut cs.namespace(|| "auxiliary commitments"),
+                &payload_bits,
+                &commitments,
+            )?;
+        }
+
+        let challenges = self.inner_challenges(
+            cs.namespace(|| "inner circuit challenges"),
+            old_payload,
+            &payload_bits,
+        )?;
         self.inner_circuit.synthesize(
             &mut cs.namespace(|| "inner circuit"),
-            &old_payload,
+            old_payload,
             &payload_bits,
+            &challenges,
         )
     }
 }
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::dev::{is_satisfied, GadgetCheck};
+    use crate::{Ec0, Ec1, Fp, Fq};
+
+    struct SquareCircuit {
+        x: Option<Fq>,
+    }
+
+    impl Circuit<Fq> for SquareCircuit {
+        fn synthesize<CS: ConstraintSystem<Fq>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+                self.x.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let x2 = x.mul(cs.namespace(|| "x^2"), &x)?;
+            x2.inputize(cs.namespace(|| "x^2 input"))?;
+
+            Ok(())
+        }
+    }
+
+    struct ThreeBytes;
+
+    impl<F: Field> RecursiveCircuit<F> for ThreeBytes {
+        fn base_payload(&self) -> Vec<bool> {
+            vec![false; 24]
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            _: &mut CS,
+            _: &[AllocatedBit],
+            _: &[AllocatedBit],
+            _: &[AllocatedNum<F>],
+        ) -> Result<(), SynthesisError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(5);
+        let proof = RecursiveProof::<Ec1, Ec0> {
+            proof: crate::plain::prove(
+                &e1params,
+                &SquareCircuit {
+                    x: Some(Fq::from(3)),
+                },
+            )
+            .unwrap(),
+            oldproof1: Leftovers::dummy(&e1params),
+            oldproof2: Leftovers::dummy(&e2params),
+            deferred: Deferred::dummy(e2params.k),
+            payload: vec![1, 2, 3],
+        };
+        let validate =
+            |proof: &RecursiveProof<Ec1, Ec0>| proof.validate(&e1params, &e2params, &ThreeBytes);
+        assert_eq!(validate(&proof), Ok(()));
+
+        let mut malformed = proof.clone();
+        malformed.payload.push(4);
+        assert_eq!(
+            validate(&malformed),
+            Err(MalformedProof::PayloadLength(4, 3))
+        );
+        // Rejected without reaching the cryptographic checks.
+        assert!(!malformed.verify(&e1params, &e2params, &ThreeBytes).unwrap());
+
+        let mut malformed = proof.clone();
+        malformed.proof.inner_product.rounds.pop();
+        assert_eq!(validate(&malformed), Err(MalformedProof::RoundCount(3, 4)));
+
+        let mut malformed = proof.clone();
+        malformed.proof.inner_product.rounds[2].r.pop();
+        assert_eq!(validate(&malformed), Err(MalformedProof::RoundWidth(2)));
+
+        let mut malformed = proof.clone();
+        malformed.proof.inner_product.a.clear();
+        assert_eq!(validate(&malformed), Err(MalformedProof::FinalWidth));
+
+        // The leftovers and deferred checks are sized by their own params.
+        let mut malformed = proof.clone();
+        malformed.oldproof1 = Leftovers::dummy(&Params::<Ec1>::new(5));
+        assert_eq!(
+            validate(&malformed),
+            Err(MalformedProof::OldLeftovers(5, 4))
+        );
+
+        let mut malformed = proof.clone();
+        malformed.oldproof2 = Leftovers::dummy(&Params::<Ec0>::new(4));
+        assert_eq!(
+            validate(&malformed),
+            Err(MalformedProof::NewLeftovers(4, 5))
+        );
+
+        let mut malformed = proof;
+        malformed.deferred = Deferred::dummy(4);
+        assert_eq!(validate(&malformed), Err(MalformedProof::Deferred(4, 4, 5)));
+    }
+
+    struct IdentityCheck<'a> {
+        params: &'a Params<Ec1>,
+        point: Ec1,
+        base_case: bool,
+    }
+
+    impl<'a> Circuit<Fp> for IdentityCheck<'a> {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let base_case =
+                AllocatedBit::alloc(cs.namespace(|| "base case"), || Ok(self.base_case))?;
+            let point = CurvePoint::witness(cs.namespace(|| "point"), || Ok(self.point))?;
+            ProofVerifier::new(self.params, None, None, None).not_identity_unless_base_case(
+                cs.namespace(|| "check"),
+                &base_case,
+                &point,
+            )
+        }
+    }
+
+    #[test]
+    fn test_not_identity_unless_base_case() {
+        let params: Params<Ec1> = Params::new(4);
+        let check = |point, base_case| {
+            is_satisfied::<_, _, Basic>(
+                &IdentityCheck {
+                    params: &params,
+                    point,
+                    base_case,
+                },
+                &[],
+            )
+            .is_ok()
+        };
+
+        assert!(check(Ec1::one(), false));
+        assert!(check(Ec1::one(), true));
+        assert!(check(Ec1::zero(), true));
+        assert!(!check(Ec1::zero(), false));
+    }
+
+    struct NumsEqual<'a> {
+        params: &'a Params<Ec1>,
+        base_case: bool,
+        constants: Vec<(Fp, Fp)>,
+        witnessed: Vec<(Fp, Fp)>,
+    }
+
+    impl<'a> Circuit<Fp> for NumsEqual<'a> {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let base_case =
+                AllocatedBit::alloc(cs.namespace(|| "base case"), || Ok(self.base_case))?;
+            let mut lhs = vec![];
+            let mut rhs = vec![];
+            for (a, b) in &self.constants {
+                lhs.push(Num::constant(*a).into());
+                rhs.push(Num::constant(*b).into());
+            }
+            for (i, (a, b)) in self.witnessed.iter().enumerate() {
+                let a = AllocatedNum::alloc(cs.namespace(|| format!("lhs {}", i)), || Ok(*a))?;
+                let b = AllocatedNum::alloc(cs.namespace(|| format!("rhs {}", i)), || Ok(*b))?;
+                lhs.push(a.into());
+                rhs.push(b.into());
+            }
+            ProofVerifier::new(self.params, None, None, None).nums_equal_unless_base_case(
+                cs.namespace(|| "check"),
+                base_case,
+                &lhs,
+                &rhs,
+            )
+        }
+    }
+
+    #[test]
+    fn test_nums_equal_unless_base_case() {
+        let params: Params<Ec1> = Params::new(4);
+        let circuit = |base_case, constants: &[(u64, u64)], witnessed: &[(u64, u64)]| {
+            let pairs = |pairs: &[(u64, u64)]| {
+                pairs
+                    .iter()
+                    .map(|(a, b)| (Fp::from_u64(*a), Fp::from_u64(*b)))
+                    .collect()
+            };
+            NumsEqual {
+                params: &params,
+                base_case,
+                constants: pairs(constants),
+                witnessed: pairs(witnessed),
+            }
+        };
+
+        // Equal constants take no gates; only the witnessed pair does.
+        GadgetCheck::new(circuit(false, &[(0, 0), (3, 3)], &[(5, 5)]), &[])
+            .malformed("unequal", circuit(false, &[(0, 0)], &[(5, 6)]), &[])
+            .malformed("constants", circuit(false, &[(1, 2)], &[(5, 5)]), &[])
+            .budget(4, 6)
+            .check::<Basic>()
+            .unwrap();
+
+        // Anything goes in the base case.
+        assert_eq!(
+            is_satisfied::<_, _, Basic>(&circuit(true, &[(0, 1)], &[(5, 6)]), &[]),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_k_commitment() {
+        let params: Params<Ec1> = Params::new(6);
+        let naive = |inputs: &[u8]| {
+            let mut k_commitment = params.generators[1].to_projective();
+            for (i, byte) in inputs.iter().enumerate() {
+                for j in 0..8 {
+                    if (byte >> j) & 1 == 1 {
+                        k_commitment += params.generators[2 + 8 * i + j];
+                    }
+                }
+            }
+            k_commitment
+        };
+
+        let inputs = [0x5a, 0xc3, 0x00, 0xff, 0x81, 0x3c, 0x7e];
+        assert_eq!(params.k_commitment(&[]), naive(&[]));
+        assert_eq!(params.k_commitment(&inputs), naive(&inputs));
+
+        // Only the nibbles with bits set have been summed, and those are
+        // reused.
+        let table = params.k_commitment_table.get().unwrap();
+        assert!(table.nibbles[..4].iter().all(|n| n.get().is_some()));
+        assert!(table.nibbles[4..6].iter().all(|n| n.get().is_none()));
+        assert!(table.nibbles[14..].iter().all(|n| n.get().is_none()));
+        let longer: Vec<u8> = (1..=7).collect();
+        assert_eq!(params.k_commitment(&longer), naive(&longer));
+    }
+}
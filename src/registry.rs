@@ -0,0 +1,547 @@
+//! Digests of circuit structure, and registries of the circuits a verifier
+//! accepts.
+//!
+//! A [`RegistryCircuit`] selects among a list of step circuits in-circuit,
+//! by the index in the first [`INDEX_BYTES`] bytes of the new payload, which
+//! the verification circuit around it takes as a public input. The
+//! [`CircuitRegistry`] of the step circuits' digests commits to the list, and
+//! a verifier that holds its root checks a proof with
+//! [`RegistryCircuit::verify`].
+//!
+//! Every proof that a chain accumulates is decided against one s(X, Y), that
+//! of the circuit at the tip, so the selection is among the step circuits
+//! within one verification circuit rather than among verification circuits.
+//! Registering new step logic appends a circuit and so changes the
+//! verification circuit: new chains can take steps of both the old and the
+//! new logic, and the tips of chains of the earlier registry still verify
+//! under it, but an old chain is not extended by the new circuit.
+
+use crate::circuits::*;
+use crate::compose::{known_values, Gated};
+use crate::curves::Curve;
+use crate::fields::Field;
+use crate::gadgets::{AllocatedBit, AllocatedNum};
+use crate::proofs::Params;
+use crate::recursion::{RecursiveProof, VerifierVerificationCircuit};
+use crate::synthesis::{Backend, Basic, SynthesisDriver};
+
+/// Computes a digest of the constraint structure of a circuit.
+///
+/// The digest commits to the number of multiplication gates, the positions of
+/// the public inputs in k(Y), and every term of every linear constraint (the
+/// variable it refers to and its coefficient). These are exactly the things
+/// that determine the fixed polynomial s(X, Y), so any change to synthesis that
+/// would invalidate existing proofs changes the digest. Witness values are not
+/// included, so the prover and verifier views of a circuit must agree.
+pub fn circuit_shape_digest<F: Field, C: Circuit<F>, S: SynthesisDriver>(
+    circuit: &C,
+) -> Result<[u8; 32], SynthesisError> {
+    use sha2::{Digest, Sha256};
+
+    struct Shape {
+        hasher: Sha256,
+        n: usize,
+        q: usize,
+    }
+
+    impl<F: Field> Backend<F> for &mut Shape {
+        type LinearConstraintIndex = usize;
+
+        fn new_multiplication_gate<A, AR>(&mut self, _annotation: Option<A>)
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.n += 1;
+        }
+
+        fn new_linear_constraint<A, AR>(&mut self, _annotation: A) -> Self::LinearConstraintIndex
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.q += 1;
+            self.hasher.input(b"Q");
+            self.hasher.input((self.q as u64).to_le_bytes());
+            self.q
+        }
+
+        fn insert_coefficient(
+            &mut self,
+            var: Variable,
+            coeff: Coeff<F>,
+            y: &Self::LinearConstraintIndex,
+        ) {
+            // Terms are only ever inserted into the most recent constraint.
+            assert_eq!(*y, self.q);
+
+            let (tag, index) = match var {
+                Variable::A(index) => (b"A", index),
+                Variable::B(index) => (b"B", index),
+                Variable::C(index) => (b"C", index),
+            };
+            self.hasher.input(tag);
+            self.hasher.input((index as u64).to_le_bytes());
+            self.hasher.input(&coeff.value().to_bytes()[..]);
+        }
+
+        fn get_for_q(&self, q: usize) -> Self::LinearConstraintIndex {
+            q
+        }
+
+        fn new_k_power(&mut self, index: usize, _: Option<F>) -> Result<(), SynthesisError> {
+            self.hasher.input(b"K");
+            self.hasher.input((index as u64).to_le_bytes());
+
+            Ok(())
+        }
+    }
+
+    let mut shape = Shape {
+        hasher: Sha256::new(),
+        n: 0,
+        q: 0,
+    };
+
+    S::synthesize(&mut shape, circuit)?;
+
+    shape.hasher.input(b"N");
+    shape.hasher.input((shape.n as u64).to_le_bytes());
+
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&shape.hasher.result());
+
+    Ok(digest)
+}
+
+/// Computes [`circuit_shape_digest`] for the verification circuit that wraps
+/// a recursive circuit, as seen by the verifier.
+pub fn recursive_circuit_shape_digest<
+    E1,
+    E2,
+    C: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>,
+    S: SynthesisDriver,
+>(
+    e1params: &Params<E1>,
+    e2params: &Params<E2>,
+    circuit: &C,
+    new_payload: &[u8],
+) -> Result<[u8; 32], SynthesisError>
+where
+    E1: Curve<Base = <E2 as Curve>::Scalar>,
+    E2: Curve<Base = <E1 as Curve>::Scalar>,
+{
+    let circuit =
+        VerifierVerificationCircuit::<E1, E2, _>::new(e1params.k, e2params, circuit, new_payload);
+
+    circuit_shape_digest::<_, _, S>(&circuit)
+}
+
+/// Computes [`circuit_shape_digest`] of the transition a recursive circuit
+/// enforces, over payloads as long as its base payload, apart from the
+/// verification circuit around it.
+pub fn step_shape_digest<F: Field, C: RecursiveCircuit<F>, S: SynthesisDriver>(
+    circuit: &C,
+) -> Result<[u8; 32], SynthesisError> {
+    struct Step<'a, C>(&'a C);
+
+    impl<'a, F: Field, C: RecursiveCircuit<F>> Circuit<F> for Step<'a, C> {
+        fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let len = self.0.base_payload().len();
+            let mut alloc = |name: &str| {
+                (0..len)
+                    .map(|i| {
+                        AllocatedBit::alloc(cs.namespace(|| format!("{} {}", name, i)), || {
+                            Err(SynthesisError::AssignmentMissing)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            let old_payload = alloc("old")?;
+            let new_payload = alloc("new")?;
+            let challenges = (0..self.0.num_challenges())
+                .map(|i| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("challenge {}", i)), || {
+                        Err(SynthesisError::AssignmentMissing)
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            self.0
+                .synthesize(cs, &old_payload, &new_payload, &challenges)
+        }
+    }
+
+    circuit_shape_digest::<_, _, S>(&Step(circuit))
+}
+
+/// The number of leading payload bytes that hold the little-endian index of
+/// the registered circuit a proof was made with.
+pub const INDEX_BYTES: usize = 4;
+
+/// The registry index selected by `payload`, or None if it is too short to
+/// hold one.
+pub fn payload_index(payload: &[u8]) -> Option<u32> {
+    if payload.len() < INDEX_BYTES {
+        return None;
+    }
+
+    let mut index = [0u8; INDEX_BYTES];
+    index.copy_from_slice(&payload[..INDEX_BYTES]);
+    Some(u32::from_le_bytes(index))
+}
+
+/// An ordered list of the digests, as computed by [`step_shape_digest`], of
+/// the step circuits a [`RegistryCircuit`] selects among.
+///
+/// The digests are over the scalar field of the curve the proofs are over.
+/// Proofs at odd steps of a chain are over the opposite curve, and are made
+/// with the registry of the digests over the other field.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CircuitRegistry {
+    digests: Vec<[u8; 32]>,
+}
+
+impl CircuitRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `digest`, returning the index under which proofs select it.
+    pub fn register(&mut self, digest: [u8; 32]) -> u32 {
+        self.digests.push(digest);
+        (self.digests.len() - 1) as u32
+    }
+
+    pub fn get(&self, index: u32) -> Option<&[u8; 32]> {
+        self.digests.get(index as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.digests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.digests.is_empty()
+    }
+
+    /// A commitment to the registered digests and their order:
+    /// SHA-256("halo circuit registry" || count || digests), with the count
+    /// as a little-endian `u64`.
+    pub fn root(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.input(b"halo circuit registry");
+        hasher.input((self.digests.len() as u64).to_le_bytes());
+        for digest in &self.digests {
+            hasher.input(&digest[..]);
+        }
+
+        let mut root = [0u8; 32];
+        root.copy_from_slice(&hasher.result());
+        root
+    }
+}
+
+/// Runs the circuit at the index in the first [`INDEX_BYTES`] bytes of the
+/// new payload, over the rest of it.
+///
+/// The circuits see the payload without its index, and must have base
+/// payloads of the same length; the first circuit's is the base case's, under
+/// index zero. The selection is enforced by one bit per circuit, of which
+/// exactly one is set and whose index is the payload's, so an index with no
+/// circuit is unsatisfiable. The constraints of the circuits not selected are
+/// switched off as in `Conditional`, at a multiplication gate per linear
+/// constraint of every circuit.
+pub struct RegistryCircuit<C> {
+    circuits: Vec<C>,
+}
+
+impl<C> RegistryCircuit<C> {
+    pub fn new(circuits: Vec<C>) -> Self {
+        assert!(!circuits.is_empty());
+        RegistryCircuit { circuits }
+    }
+
+    /// Appends `circuit`, returning the index under which proofs select it.
+    pub fn register(&mut self, circuit: C) -> u32 {
+        self.circuits.push(circuit);
+        (self.circuits.len() - 1) as u32
+    }
+
+    /// The registry of the digests of the circuits over `F`, whose root a
+    /// verifier holds to pin the circuits it accepts.
+    pub fn registry<F: Field, S: SynthesisDriver>(&self) -> Result<CircuitRegistry, SynthesisError>
+    where
+        C: RecursiveCircuit<F>,
+    {
+        let mut registry = CircuitRegistry::new();
+        for circuit in &self.circuits {
+            registry.register(step_shape_digest::<F, _, S>(circuit)?);
+        }
+        Ok(registry)
+    }
+
+    /// Fully verifies `proof`, after checking that the registry of these
+    /// circuits over the scalar field of `E1` has the root `root`.
+    pub fn verify<E1, E2>(
+        &self,
+        proof: &RecursiveProof<E1, E2>,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+        root: &[u8; 32],
+    ) -> Result<bool, SynthesisError>
+    where
+        E1: Curve<Base = <E2 as Curve>::Scalar>,
+        E2: Curve<Base = <E1 as Curve>::Scalar>,
+        C: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>,
+    {
+        if &self.registry::<E1::Scalar, Basic>()?.root() != root {
+            return Ok(false);
+        }
+
+        proof.verify(e1params, e2params, self)
+    }
+}
+
+impl<F: Field, C: RecursiveCircuit<F>> RecursiveCircuit<F> for RegistryCircuit<C> {
+    fn base_payload(&self) -> Vec<bool> {
+        let mut payload = vec![false; 8 * INDEX_BYTES];
+        payload.extend(self.circuits[0].base_payload());
+        payload
+    }
+
+    fn num_challenges(&self) -> usize {
+        self.circuits
+            .iter()
+            .map(|circuit| circuit.num_challenges())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        old_payload: &[AllocatedBit],
+        new_payload: &[AllocatedBit],
+        challenges: &[AllocatedNum<F>],
+    ) -> Result<(), SynthesisError> {
+        let split = 8 * INDEX_BYTES;
+        let len = split + self.circuits[0].base_payload().len();
+        if old_payload.len() != len || new_payload.len() != len {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        if self
+            .circuits
+            .iter()
+            .any(|circuit| split + circuit.base_payload().len() != len)
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut index = LinearCombination::zero();
+        let mut index_value = Some(0u64);
+        let mut coeff = F::one();
+        for (i, bit) in new_payload[..split].iter().enumerate() {
+            index = index + (Coeff::Full(coeff), bit.get_variable());
+            coeff = coeff + coeff;
+            index_value =
+                index_value.and_then(|acc| bit.get_value().map(|b| acc | (b as u64) << i));
+        }
+
+        // sum(selected) = 1, and sum(i * selected_i) = index
+        let mut selected = vec![];
+        let mut count = LinearCombination::zero();
+        let mut selected_index = LinearCombination::zero();
+        for i in 0..self.circuits.len() {
+            let bit = AllocatedBit::alloc(cs.namespace(|| format!("select {}", i)), || {
+                index_value
+                    .map(|index| index == i as u64)
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            count = count + bit.get_variable();
+            selected_index =
+                selected_index + (Coeff::Full(F::from_u64(i as u64)), bit.get_variable());
+            selected.push(bit);
+        }
+        cs.enforce_zero(count - CS::ONE);
+        cs.enforce_zero(selected_index - &index);
+
+        let known = known_values::<F, CS>(old_payload, new_payload, challenges);
+        for (i, (circuit, bit)) in self.circuits.iter().zip(selected.iter()).enumerate() {
+            let mut gated = Gated::new(
+                cs.namespace(|| format!("circuit {}", i)),
+                LinearCombination::from(bit.get_variable()),
+                bit.get_value()
+                    .map(|b| if b { F::one() } else { F::zero() }),
+                known.clone(),
+            );
+            circuit.synthesize(
+                &mut gated,
+                &old_payload[split..],
+                &new_payload[split..],
+                &challenges[..circuit.num_challenges()],
+            )?;
+            gated.finish()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::curves::{Ec0, Ec1};
+    use crate::dev::is_satisfied;
+    use crate::fields::{Fp, Fq};
+    use crate::fixtures::phony_proof;
+
+    // Adds a constant to the byte that follows the index.
+    struct Add(u64);
+
+    impl<F: Field> RecursiveCircuit<F> for Add {
+        fn base_payload(&self) -> Vec<bool> {
+            vec![false; 8]
+        }
+
+        fn synthesize<CS: ConstraintSystem<F>>(
+            &self,
+            cs: &mut CS,
+            old_payload: &[AllocatedBit],
+            new_payload: &[AllocatedBit],
+            _challenges: &[AllocatedNum<F>],
+        ) -> Result<(), SynthesisError> {
+            let pack = |bits: &[AllocatedBit]| {
+                let mut lc = LinearCombination::zero();
+                let mut coeff = F::one();
+                for bit in bits {
+                    lc = lc + (Coeff::Full(coeff), bit.get_variable());
+                    coeff = coeff + coeff;
+                }
+                lc
+            };
+
+            let old = pack(old_payload);
+            let new = pack(new_payload);
+            cs.enforce_zero(new - &old - (Coeff::Full(F::from_u64(self.0)), CS::ONE));
+
+            Ok(())
+        }
+    }
+
+    struct TestCircuit<'a> {
+        circuit: &'a RegistryCircuit<Add>,
+        old: &'a [u8],
+        new: &'a [u8],
+    }
+
+    impl<'a> Circuit<Fp> for TestCircuit<'a> {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut alloc = |bytes: &[u8]| {
+                let mut bits = vec![];
+                for byte in bytes {
+                    for i in 0..8 {
+                        bits.push(AllocatedBit::alloc(&mut *cs, || Ok((byte >> i) & 1 == 1))?);
+                    }
+                }
+                Ok(bits)
+            };
+            let old = alloc(self.old)?;
+            let new = alloc(self.new)?;
+
+            self.circuit.synthesize(cs, &old, &new, &[])
+        }
+    }
+
+    fn satisfied(circuit: &RegistryCircuit<Add>, old: &[u8], new: &[u8]) -> bool {
+        is_satisfied::<_, _, Basic>(&TestCircuit { circuit, old, new }, &[]) == Ok(true)
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = CircuitRegistry::new();
+        assert!(registry.is_empty());
+        let empty = registry.root();
+
+        assert_eq!(registry.register([1; 32]), 0);
+        assert_eq!(registry.register([2; 32]), 1);
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get(1), Some(&[2; 32]));
+        assert_eq!(registry.get(2), None);
+
+        // The root commits to the order of the digests.
+        let mut swapped = CircuitRegistry::new();
+        swapped.register([2; 32]);
+        swapped.register([1; 32]);
+        assert!(registry.root() != swapped.root());
+        assert!(registry.root() != empty);
+
+        assert_eq!(payload_index(&[1, 2, 0, 0, 9]), Some(0x201));
+        assert_eq!(payload_index(&[1, 2, 0]), None);
+    }
+
+    #[test]
+    fn test_registry_circuit_selects_by_index() {
+        let mut circuit = RegistryCircuit::new(vec![Add(1)]);
+        assert_eq!(
+            RecursiveCircuit::<Fp>::base_payload(&circuit),
+            vec![false; 40]
+        );
+
+        assert!(satisfied(&circuit, &[0, 0, 0, 0, 5], &[0, 0, 0, 0, 6]));
+        assert!(!satisfied(&circuit, &[0, 0, 0, 0, 5], &[0, 0, 0, 0, 7]));
+        // There is no circuit at index 1 yet.
+        assert!(!satisfied(&circuit, &[0, 0, 0, 0, 5], &[1, 0, 0, 0, 7]));
+
+        // Registering new step logic leaves the old selectable, and the index
+        // of the old payload does not matter.
+        assert_eq!(circuit.register(Add(2)), 1);
+        assert!(satisfied(&circuit, &[0, 0, 0, 0, 5], &[1, 0, 0, 0, 7]));
+        assert!(satisfied(&circuit, &[1, 0, 0, 0, 7], &[0, 0, 0, 0, 8]));
+        assert!(!satisfied(&circuit, &[0, 0, 0, 0, 5], &[1, 0, 0, 0, 6]));
+        assert!(!satisfied(&circuit, &[0, 0, 0, 0, 5], &[0, 0, 0, 1, 7]));
+    }
+
+    #[test]
+    fn test_registry_circuit_digests() {
+        let circuit = RegistryCircuit::new(vec![Add(1), Add(2)]);
+        let registry = circuit.registry::<Fp, Basic>().unwrap();
+        assert_eq!(registry.len(), 2);
+        assert_eq!(
+            registry.get(1),
+            Some(&step_shape_digest::<Fp, _, Basic>(&Add(2)).unwrap())
+        );
+        assert!(registry.get(0) != registry.get(1));
+
+        let swapped = RegistryCircuit::new(vec![Add(2), Add(1)]);
+        assert!(swapped.registry::<Fp, Basic>().unwrap().root() != registry.root());
+
+        // The circuits are all part of the verification circuit, so
+        // registering one changes it.
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+        let digest = |circuit: &RegistryCircuit<Add>| {
+            recursive_circuit_shape_digest::<Ec1, Ec0, _, Basic>(
+                &e1params, &e2params, circuit, &[0; 5],
+            )
+            .unwrap()
+        };
+        assert!(digest(&circuit) != digest(&RegistryCircuit::new(vec![Add(1)])));
+    }
+
+    #[test]
+    fn test_verify_checks_root() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+
+        let circuit = RegistryCircuit::new(vec![Add(1)]);
+        let other = RegistryCircuit::new(vec![Add(2)]);
+        let root = other.registry::<Fq, Basic>().unwrap().root();
+
+        // A phony proof, rejected for the root before it is examined.
+        let proof = phony_proof(&e1params, &e2params, vec![0; 5]);
+        assert!(!circuit.verify(&proof, &e1params, &e2params, &root).unwrap());
+    }
+}
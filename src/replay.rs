@@ -0,0 +1,252 @@
+//! Recordings of transcripts, for finding out why a proof does not verify.
+//!
+//! A proof that fails to verify only tells the verifier `false`. Replaying
+//! the verification inside [`record_transcript`] records, in order, every
+//! element absorbed into and squeezed from a transcript and the results of
+//! the multiexps the proof is checked with. Recording the prover, or a run
+//! of a verification circuit over a witness, the same way gives a log to
+//! compare it with: [`TranscriptLog::first_divergence`] finds the first
+//! event at which the two differ, and [`TranscriptLog::side_by_side`] lays
+//! them out next to each other.
+//!
+//! Recording applies to the calling thread only, and costs nothing while
+//! nothing is being recorded.
+
+use crate::curves::Curve;
+use crate::fields::Field;
+use std::cell::RefCell;
+use std::fmt::{self, Write};
+
+thread_local! {
+    static LOG: RefCell<Option<Vec<TranscriptEvent>>> = const { RefCell::new(None) };
+}
+
+/// Something that happened to a transcript, with field elements and curve
+/// points in their 32-byte encodings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptEvent {
+    /// An element was absorbed.
+    Absorb([u8; 32]),
+    /// An element was squeezed.
+    Squeeze([u8; 32]),
+    /// A multiexp, or a combination of commitments, computed this point.
+    Point(&'static str, [u8; 32]),
+}
+
+impl fmt::Display for TranscriptEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TranscriptEvent::Absorb(bytes) => write!(f, "absorb  {}", Hex(bytes)),
+            TranscriptEvent::Squeeze(bytes) => write!(f, "squeeze {}", Hex(bytes)),
+            TranscriptEvent::Point(label, bytes) => write!(f, "{} {}", label, Hex(bytes)),
+        }
+    }
+}
+
+struct Hex<'a>(&'a [u8; 32]);
+
+impl<'a> fmt::Display for Hex<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// The events recorded by `record_transcript`, in the order they happened.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TranscriptLog {
+    pub events: Vec<TranscriptEvent>,
+}
+
+impl TranscriptLog {
+    /// The index of the first event at which this log and `other` differ,
+    /// or at which one of them ends before the other. None if they are the
+    /// same.
+    pub fn first_divergence(&self, other: &TranscriptLog) -> Option<usize> {
+        let common = self
+            .events
+            .iter()
+            .zip(other.events.iter())
+            .position(|(a, b)| a != b);
+        match common {
+            Some(i) => Some(i),
+            None if self.events.len() != other.events.len() => {
+                Some(std::cmp::min(self.events.len(), other.events.len()))
+            }
+            None => None,
+        }
+    }
+
+    /// Lists the events of this log and `other` next to each other, under
+    /// the headings `names`, with the first divergence marked by `>>` and
+    /// the events from then on by `!` where they differ.
+    pub fn side_by_side(&self, other: &TranscriptLog, names: (&str, &str)) -> String {
+        let divergence = self.first_divergence(other);
+        let len = std::cmp::max(self.events.len(), other.events.len());
+        let cell = |log: &TranscriptLog, i: usize| {
+            log.events
+                .get(i)
+                .map_or_else(|| "-".to_string(), |e| e.to_string())
+        };
+
+        let mut out = String::new();
+        writeln!(out, "      {:<90} {}", names.0, names.1).unwrap();
+        for i in 0..len {
+            let marker = match divergence {
+                Some(d) if d == i => ">>",
+                Some(d) if d < i && self.events.get(i) != other.events.get(i) => " !",
+                _ => "  ",
+            };
+            let (left, right) = (cell(self, i), cell(other, i));
+            writeln!(out, "{} {:>3} {:<90} {}", marker, i, left, right).unwrap();
+        }
+        out
+    }
+}
+
+/// Runs `f`, recording the transcript events of the calling thread, and
+/// returns its result with the log. Recordings do not nest: an inner one
+/// takes the events until it finishes.
+pub fn record_transcript<R, F: FnOnce() -> R>(f: F) -> (R, TranscriptLog) {
+    let outer = LOG.with(|log| log.borrow_mut().replace(vec![]));
+    let result = f();
+    let events = LOG.with(|log| std::mem::replace(&mut *log.borrow_mut(), outer));
+
+    (
+        result,
+        TranscriptLog {
+            events: events.unwrap_or_default(),
+        },
+    )
+}
+
+/// Records the event `event` makes, if the thread is recording.
+fn note<E: FnOnce() -> TranscriptEvent>(event: E) {
+    LOG.with(|log| {
+        if let Some(events) = log.borrow_mut().as_mut() {
+            events.push(event());
+        }
+    });
+}
+
+pub(crate) fn note_absorb<F: Field>(value: &F) {
+    note(|| TranscriptEvent::Absorb(value.to_bytes()));
+}
+
+pub(crate) fn note_squeeze<F: Field>(value: &F) {
+    note(|| TranscriptEvent::Squeeze(value.to_bytes()));
+}
+
+/// Records the point `point` computes under `label`, computing it only if
+/// the thread is recording.
+pub(crate) fn note_point<C: Curve, P: FnOnce() -> C>(label: &'static str, point: P) {
+    note(|| TranscriptEvent::Point(label, point().to_bytes()));
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::circuits::*;
+    use crate::dev::is_satisfied;
+    use crate::fixtures::TrivialCircuit;
+    use crate::gadgets::{AllocatedNum, RescueGadget};
+    use crate::proofs::*;
+    use crate::rescue::Rescue;
+    use crate::{Basic, Ec0, Fp};
+
+    struct Sponge(Vec<Fp>);
+
+    impl Circuit<Fp> for Sponge {
+        fn synthesize<CS: ConstraintSystem<Fp>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let mut rescue = RescueGadget::new(cs.namespace(|| "rescue"))?;
+            for (i, value) in self.0.iter().enumerate() {
+                let value =
+                    AllocatedNum::alloc(cs.namespace(|| format!("value {}", i)), || Ok(*value))?;
+                rescue.absorb(cs.namespace(|| format!("absorb {}", i)), value.into())?;
+            }
+            rescue.squeeze(cs.namespace(|| "squeeze"))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_record_sponge() {
+        let values: Vec<_> = (1..15).map(Fp::from_u64).collect();
+        let (_, native) = record_transcript(|| {
+            let mut rescue = Rescue::new();
+            for value in &values {
+                rescue.absorb(*value);
+            }
+            rescue.squeeze()
+        });
+        assert_eq!(native.events.len(), 15);
+        assert_eq!(
+            native.events[0],
+            TranscriptEvent::Absorb(Fp::one().to_bytes())
+        );
+
+        // The circuit absorbs and squeezes the same elements.
+        let (satisfied, circuit) =
+            record_transcript(|| is_satisfied::<_, _, Basic>(&Sponge(values.clone()), &[]));
+        assert_eq!(satisfied, Ok(true));
+        assert_eq!(native.first_divergence(&circuit), None);
+
+        let mut other = values.clone();
+        other[3] = Fp::zero();
+        let (_, diverged) = record_transcript(|| is_satisfied::<_, _, Basic>(&Sponge(other), &[]));
+        assert_eq!(native.first_divergence(&diverged), Some(3));
+        let table = native.side_by_side(&diverged, ("native", "circuit"));
+        assert!(table.lines().nth(4).unwrap().starts_with(">>   3 absorb"));
+
+        let mut short = native.clone();
+        short.events.pop();
+        assert_eq!(native.first_divergence(&short), Some(14));
+
+        // Nothing is recorded outside `record_transcript`.
+        Rescue::new().absorb(Fp::one());
+        assert_eq!(record_transcript(|| ()).1, TranscriptLog::default());
+    }
+
+    #[test]
+    fn test_replay_proof() {
+        let params = Params::<Ec0>::new(4);
+        let leftovers = Leftovers::dummy(&params);
+        let inputs = [Fp::one()];
+
+        let (proof, prover) = record_transcript(|| {
+            Proof::new::<_, Basic>(&params, &TrivialCircuit, &leftovers)
+                .unwrap()
+                .0
+        });
+        let (result, verifier) = record_transcript(|| {
+            proof
+                .verify::<_, Basic>(&leftovers, &params, &TrivialCircuit, &inputs, None)
+                .unwrap()
+                .0
+        });
+        assert!(result);
+        assert_eq!(prover.first_divergence(&verifier), None);
+        assert!(verifier
+            .events
+            .iter()
+            .any(|e| matches!(e, TranscriptEvent::Point("p_commitment", _))));
+
+        // A wrong opening is where the verifier first parts from the prover.
+        let mut proof = proof;
+        proof.sx_cur_opening += Fp::one();
+        let (result, replay) = record_transcript(|| {
+            proof
+                .verify::<_, Basic>(&leftovers, &params, &TrivialCircuit, &inputs, None)
+                .unwrap()
+                .0
+        });
+        assert!(!result);
+        let at = prover.first_divergence(&replay).unwrap();
+        assert!(matches!(replay.events[at], TranscriptEvent::Absorb(_)));
+        assert!(prover
+            .side_by_side(&replay, ("prover", "verifier"))
+            .contains(">>"));
+    }
+}
@@ -1,7 +1,15 @@
 //! Implementation of a duplex sponge construction based on the Rescue algebraic
 //! permutation.
+//!
+//! [`Rescue`] computes outside of the circuit exactly the digests that
+//! `RescueGadget` computes inside it, given the same sequence of absorbed
+//! elements, so applications can build Merkle trees, transcripts and payload
+//! digests natively and have their circuits recompute them. Both are built
+//! from a [`RescueConstants`], the one place the MDS matrix and key schedule
+//! are computed, so that they cannot drift apart.
 
 use crate::fields::Field;
+use crate::replay;
 
 pub(crate) const RESCUE_ROUNDS: usize = 10;
 pub(crate) const RESCUE_M: usize = 13;
@@ -9,7 +17,7 @@ pub(crate) const RESCUE_M: usize = 13;
 // Set sponge capacity to 1
 pub(crate) const SPONGE_RATE: usize = RESCUE_M - 1;
 
-pub(crate) fn generate_mds_matrix<F: Field>() -> [[F; RESCUE_M]; RESCUE_M] {
+fn generate_mds_matrix<F: Field>() -> [[F; RESCUE_M]; RESCUE_M] {
     // TODO: Correct MDS generation; this causes horribly-biased output
     let mut mds_matrix = [[F::zero(); RESCUE_M]; RESCUE_M];
     for i in (0..RESCUE_M).rev() {
@@ -62,10 +70,8 @@ fn rescue_f<F: Field>(
 fn generate_key_schedule<F: Field>(
     master_key: [F; RESCUE_M],
     mds_matrix: &[[F; RESCUE_M]; RESCUE_M],
+    constants: &[[F; RESCUE_M]; 2 * RESCUE_ROUNDS + 1],
 ) -> [[F; RESCUE_M]; 2 * RESCUE_ROUNDS + 1] {
-    // TODO: Generate correct constants
-    let constants = [[F::one(); RESCUE_M]; 2 * RESCUE_ROUNDS + 1];
-
     let mut key_schedule = vec![];
     let mut state = master_key;
 
@@ -115,6 +121,105 @@ fn generate_key_schedule<F: Field>(
     ]
 }
 
+/// The MDS matrix and key schedule of a Rescue permutation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RescueConstants<F: Field> {
+    mds_matrix: [[F; RESCUE_M]; RESCUE_M],
+    key_schedule: [[F; RESCUE_M]; 2 * RESCUE_ROUNDS + 1],
+}
+
+impl<F: Field> Default for RescueConstants<F> {
+    fn default() -> Self {
+        RescueConstants::standard()
+    }
+}
+
+impl<F: Field> RescueConstants<F> {
+    /// The constants of `Rescue::new` and `RescueGadget::new`, which every
+    /// transcript and digest of the crate uses. They are placeholders: the
+    /// round constants are all one, and the MDS matrix has a zero first
+    /// column, so the first element of each block of absorbed elements does
+    /// not affect the output. `Rescue::reduced_rate` avoids absorbing there.
+    pub fn standard() -> Self {
+        Self::from_parts(
+            generate_mds_matrix(),
+            [[F::one(); RESCUE_M]; 2 * RESCUE_ROUNDS + 1],
+        )
+    }
+
+    /// Derives constants from `seed`. The round constants are successive
+    /// elements, and the MDS matrix is the Cauchy matrix 1 / (x_i - y_j) of
+    /// the following 2 * `RESCUE_M` distinct elements, where each element is
+    /// the first canonical encoding among SHA-256("halo rescue" || seed ||
+    /// counter) for successive little-endian 64-bit counters.
+    #[cfg(feature = "verify")]
+    pub fn from_seed(seed: &[u8]) -> Self {
+        use sha2::{Digest, Sha256};
+
+        let mut counter = 0u64;
+        let mut next = || loop {
+            let mut hasher = Sha256::new();
+            hasher.input(b"halo rescue");
+            hasher.input(seed);
+            hasher.input(counter.to_le_bytes());
+            counter += 1;
+
+            let mut bytes = [0u8; 32];
+            bytes.copy_from_slice(&hasher.result());
+            let element = F::from_bytes(&bytes);
+            if bool::from(element.is_some()) {
+                return element.unwrap();
+            }
+        };
+
+        let mut constants = [[F::zero(); RESCUE_M]; 2 * RESCUE_ROUNDS + 1];
+        for round in constants.iter_mut() {
+            for constant in round.iter_mut() {
+                *constant = next();
+            }
+        }
+
+        let mut points: Vec<F> = vec![];
+        while points.len() < 2 * RESCUE_M {
+            let point = next();
+            if !points.contains(&point) {
+                points.push(point);
+            }
+        }
+        let (xs, ys) = points.split_at(RESCUE_M);
+        let mut mds_matrix = [[F::zero(); RESCUE_M]; RESCUE_M];
+        for (row, x) in mds_matrix.iter_mut().zip(xs.iter()) {
+            for (entry, y) in row.iter_mut().zip(ys.iter()) {
+                *entry = (*x - y).invert().unwrap();
+            }
+        }
+
+        Self::from_parts(mds_matrix, constants)
+    }
+
+    fn from_parts(
+        mds_matrix: [[F; RESCUE_M]; RESCUE_M],
+        constants: [[F; RESCUE_M]; 2 * RESCUE_ROUNDS + 1],
+    ) -> Self {
+        // To use Rescue as a permutation, fix the master key to zero
+        let key_schedule = generate_key_schedule([F::zero(); RESCUE_M], &mds_matrix, &constants);
+
+        RescueConstants {
+            mds_matrix,
+            key_schedule,
+        }
+    }
+
+    pub fn mds_matrix(&self) -> &[[F; RESCUE_M]; RESCUE_M] {
+        &self.mds_matrix
+    }
+
+    /// The round keys, one before the first round and one after each.
+    pub fn key_schedule(&self) -> &[[F; RESCUE_M]; 2 * RESCUE_ROUNDS + 1] {
+        &self.key_schedule
+    }
+}
+
 fn pad<F: Field>(input: &[Option<F>; SPONGE_RATE]) -> [F; SPONGE_RATE] {
     let mut padded = [F::one(); SPONGE_RATE];
     for i in 0..SPONGE_RATE {
@@ -155,19 +260,36 @@ enum SpongeState<F: Field> {
 }
 
 impl<F: Field> SpongeState<F> {
-    fn absorb(val: F) -> Self {
+    /// A block with nothing absorbed yet. At a reduced rate, the first
+    /// element of the block is fixed to zero.
+    fn empty(reduced_rate: bool) -> Self {
+        let mut input = [None; SPONGE_RATE];
+        if reduced_rate {
+            input[0] = Some(F::zero());
+        }
+        SpongeState::Absorbing(input)
+    }
+
+    fn absorb(val: F, reduced_rate: bool) -> Self {
         let mut input = [None; SPONGE_RATE];
-        input[0] = Some(val);
+        if reduced_rate {
+            input[0] = Some(F::zero());
+            input[1] = Some(val);
+        } else {
+            input[0] = Some(val);
+        }
         SpongeState::Absorbing(input)
     }
 }
 
+/// A Rescue sponge over `F`, absorbing and squeezing one field element at a
+/// time.
 #[derive(Clone)]
 pub struct Rescue<F: Field> {
     sponge: SpongeState<F>,
     state: [F; RESCUE_M],
-    mds_matrix: [[F; RESCUE_M]; RESCUE_M],
-    key_schedule: [[F; RESCUE_M]; 2 * RESCUE_ROUNDS + 1],
+    constants: RescueConstants<F>,
+    reduced_rate: bool,
 }
 
 impl<F: Field> Default for Rescue<F> {
@@ -177,21 +299,40 @@ impl<F: Field> Default for Rescue<F> {
 }
 
 impl<F: Field> Rescue<F> {
+    /// Creates a sponge with an all-zero initial state.
     pub fn new() -> Self {
-        let mds_matrix = generate_mds_matrix();
-
-        // To use Rescue as a permutation, fix the master key to zero
-        let key_schedule = generate_key_schedule([F::zero(); RESCUE_M], &mds_matrix);
+        Self::with_constants(RescueConstants::standard())
+    }
 
+    /// Creates a sponge with an all-zero initial state whose permutation
+    /// uses `constants`, matching `RescueGadget::with_constants`.
+    pub fn with_constants(constants: RescueConstants<F>) -> Self {
         Rescue {
-            sponge: SpongeState::Absorbing([None; SPONGE_RATE]),
+            sponge: SpongeState::empty(false),
             state: [F::zero(); RESCUE_M],
-            mds_matrix,
-            key_schedule,
+            constants,
+            reduced_rate: false,
+        }
+    }
+
+    /// Creates a sponge like `new` that absorbs `SPONGE_RATE - 1` elements
+    /// per block, leaving the first element of each block zero, so that
+    /// every absorbed element reaches the output despite the zero first
+    /// column of the standard MDS matrix. Commitments, PRFs and hashes that
+    /// must bind all of their inputs use it. `RescueGadget::reduced_rate`
+    /// matches it in-circuit.
+    pub fn reduced_rate() -> Self {
+        Rescue {
+            sponge: SpongeState::empty(true),
+            reduced_rate: true,
+            ..Self::new()
         }
     }
 
+    /// Absorbs `val`, permuting the state once every `SPONGE_RATE` elements.
+    /// Absorbing after squeezing discards any remaining output.
     pub fn absorb(&mut self, val: F) {
+        replay::note_absorb(&val);
         match self.sponge {
             SpongeState::Absorbing(ref mut input) => {
                 for entry in input.iter_mut() {
@@ -202,16 +343,23 @@ impl<F: Field> Rescue<F> {
                 }
 
                 // We've already absorbed as many elements as we can
-                let _ = rescue_duplex(&mut self.state, input, &self.mds_matrix, &self.key_schedule);
-                self.sponge = SpongeState::absorb(val);
+                let _ = rescue_duplex(
+                    &mut self.state,
+                    input,
+                    &self.constants.mds_matrix,
+                    &self.constants.key_schedule,
+                );
+                self.sponge = SpongeState::absorb(val, self.reduced_rate);
             }
             SpongeState::Squeezing(_) => {
                 // Drop the remaining output elements
-                self.sponge = SpongeState::absorb(val);
+                self.sponge = SpongeState::absorb(val, self.reduced_rate);
             }
         }
     }
 
+    /// Squeezes the next output element, permuting the state first if
+    /// anything was absorbed since the last permutation.
     pub fn squeeze(&mut self) -> F {
         loop {
             match self.sponge {
@@ -219,21 +367,61 @@ impl<F: Field> Rescue<F> {
                     self.sponge = SpongeState::Squeezing(rescue_duplex(
                         &mut self.state,
                         &input,
-                        &self.mds_matrix,
-                        &self.key_schedule,
+                        &self.constants.mds_matrix,
+                        &self.constants.key_schedule,
                     ));
                 }
                 SpongeState::Squeezing(ref mut output) => {
                     for entry in output.iter_mut() {
                         if let Some(e) = entry.take() {
+                            replay::note_squeeze(&e);
                             return e;
                         }
                     }
 
                     // We've already squeezed out all available elements
-                    self.sponge = SpongeState::Absorbing([None; SPONGE_RATE]);
+                    self.sponge = SpongeState::empty(self.reduced_rate);
                 }
             }
         }
     }
+
+    /// Absorbs each of `inputs` in order into a fresh sponge at a reduced
+    /// rate, so that every input affects the result, and squeezes a single
+    /// element. `RescueGadget::hash_to_field` computes the same value
+    /// in-circuit.
+    pub fn hash_to_field(inputs: &[F]) -> F {
+        let mut rescue = Rescue::reduced_rate();
+        for input in inputs {
+            rescue.absorb(*input);
+        }
+        rescue.squeeze()
+    }
+
+    /// Creates a sponge that has absorbed `digest`, typically one squeezed
+    /// by `squeeze_digest` in an earlier step of a recursion and carried in
+    /// its payload. The digest is absorbed as two 128-bit halves, which fit
+    /// either field of the cycle, so a transcript can run across the whole
+    /// chain. The sponge is at a reduced rate, so that both halves and
+    /// everything absorbed afterwards reach the output.
+    /// `RescueGadget::from_digest` resumes it in-circuit.
+    pub fn from_digest(digest: &[u8; 32]) -> Self {
+        let mut rescue = Rescue::reduced_rate();
+        for half in digest.chunks(16) {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(half);
+            rescue.absorb(F::from_u128(u128::from_le_bytes(bytes)));
+        }
+        rescue
+    }
+
+    /// Squeezes a 32-byte digest from the low 128 bits of each of the next
+    /// two elements, for `from_digest` to resume.
+    pub fn squeeze_digest(&mut self) -> [u8; 32] {
+        let mut digest = [0u8; 32];
+        for half in digest.chunks_mut(16) {
+            half.copy_from_slice(&self.squeeze().get_lower_128().to_le_bytes());
+        }
+        digest
+    }
 }
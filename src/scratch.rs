@@ -0,0 +1,266 @@
+//! Scratch buffers for the prover that can live on disk.
+//!
+//! The witness and the largest polynomials of a proof grow with the circuit,
+//! and for large `k` they may not fit in memory. Once a [`ScratchConfig`]
+//! with a threshold is installed, buffers at least that large are backed by
+//! a memory-mapped temporary file instead, so the operating system can page
+//! them out. Proving is slower but completes.
+
+use crate::fields::Field;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+static CONFIG: RwLock<Option<ScratchConfig>> = RwLock::new(None);
+
+/// When and where scratch buffers are backed by temporary files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScratchConfig {
+    /// Buffers of at least this many bytes are backed by a temporary file.
+    /// If None, every buffer is kept in memory.
+    pub threshold: Option<usize>,
+    /// The directory temporary files are created in.
+    pub dir: PathBuf,
+}
+
+impl Default for ScratchConfig {
+    /// Keeps every buffer in memory.
+    fn default() -> Self {
+        ScratchConfig {
+            threshold: None,
+            dir: std::env::temp_dir(),
+        }
+    }
+}
+
+impl ScratchConfig {
+    /// Backs buffers of at least `threshold` bytes with temporary files in
+    /// the default temporary directory.
+    pub fn with_threshold(threshold: usize) -> Self {
+        ScratchConfig {
+            threshold: Some(threshold),
+            ..ScratchConfig::default()
+        }
+    }
+
+    /// The installed configuration, or the default one.
+    pub fn current() -> Self {
+        CONFIG.read().unwrap().clone().unwrap_or_default()
+    }
+
+    /// Makes the prover use this configuration, replacing any installed
+    /// before.
+    pub fn install(&self) {
+        *CONFIG.write().unwrap() = Some(self.clone());
+    }
+}
+
+/// A fixed-length buffer of field elements, in memory or in a memory-mapped
+/// temporary file depending on its size and the `ScratchConfig`.
+pub struct Scratch<F: Field> {
+    inner: Inner<F>,
+}
+
+enum Inner<F: Field> {
+    Heap(Vec<F>),
+    #[cfg(unix)]
+    Mapped(mapped::Mapping<F>),
+}
+
+impl<F: Field> Scratch<F> {
+    /// A buffer of `len` zeroes, placed according to the installed
+    /// `ScratchConfig`.
+    pub fn zeroed(len: usize) -> Self {
+        Self::zeroed_with(len, &ScratchConfig::current())
+    }
+
+    /// A buffer of `len` zeroes, placed according to `config`. If the
+    /// temporary file cannot be created or mapped, the buffer is kept in
+    /// memory.
+    pub fn zeroed_with(len: usize, config: &ScratchConfig) -> Self {
+        let bytes = len.saturating_mul(std::mem::size_of::<F>());
+        #[cfg(unix)]
+        {
+            if config.threshold.is_some_and(|t| bytes >= t) && bytes > 0 {
+                if let Ok(mapping) = mapped::Mapping::zeroed(len, &config.dir) {
+                    return Scratch {
+                        inner: Inner::Mapped(mapping),
+                    };
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (bytes, config);
+
+        Scratch {
+            inner: Inner::Heap(vec![F::zero(); len]),
+        }
+    }
+
+    /// Whether the buffer is backed by a temporary file.
+    pub fn is_mapped(&self) -> bool {
+        match self.inner {
+            Inner::Heap(_) => false,
+            #[cfg(unix)]
+            Inner::Mapped(_) => true,
+        }
+    }
+}
+
+impl<F: Field> Deref for Scratch<F> {
+    type Target = [F];
+
+    fn deref(&self) -> &[F] {
+        match &self.inner {
+            Inner::Heap(v) => v,
+            #[cfg(unix)]
+            Inner::Mapped(m) => m,
+        }
+    }
+}
+
+impl<F: Field> DerefMut for Scratch<F> {
+    fn deref_mut(&mut self) -> &mut [F] {
+        match &mut self.inner {
+            Inner::Heap(v) => v,
+            #[cfg(unix)]
+            Inner::Mapped(m) => m,
+        }
+    }
+}
+
+#[cfg(unix)]
+mod mapped {
+    use crate::fields::Field;
+    use std::fs::OpenOptions;
+    use std::io;
+    use std::ops::{Deref, DerefMut};
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+    /// `len` field elements in a shared mapping of a temporary file, which
+    /// is unlinked as soon as it is mapped.
+    pub(super) struct Mapping<F: Field> {
+        ptr: *mut F,
+        len: usize,
+    }
+
+    // The mapping is owned like the allocation of a `Vec`.
+    unsafe impl<F: Field + Send> Send for Mapping<F> {}
+    unsafe impl<F: Field + Sync> Sync for Mapping<F> {}
+
+    impl<F: Field> Mapping<F> {
+        pub(super) fn zeroed(len: usize, dir: &Path) -> io::Result<Self> {
+            let bytes = len
+                .checked_mul(std::mem::size_of::<F>())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "scratch too large"))?;
+            let path = dir.join(format!(
+                "halo-scratch-{}-{}",
+                std::process::id(),
+                NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+            ));
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+            let mapped = file.set_len(bytes as u64).and_then(|()| {
+                // Safety: the file is `bytes` long and nothing else maps it.
+                let ptr = unsafe {
+                    libc::mmap(
+                        std::ptr::null_mut(),
+                        bytes,
+                        libc::PROT_READ | libc::PROT_WRITE,
+                        libc::MAP_SHARED,
+                        file.as_raw_fd(),
+                        0,
+                    )
+                };
+                if ptr == libc::MAP_FAILED {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(ptr as *mut F)
+                }
+            });
+            // The mapping keeps the file's pages alive after it is removed.
+            let removed = std::fs::remove_file(&path);
+            let mapping = Mapping { ptr: mapped?, len };
+            removed?;
+
+            // A zeroed file is not necessarily zeroed field elements.
+            for i in 0..len {
+                // Safety: `i` is within the mapping, which is page aligned.
+                unsafe { mapping.ptr.add(i).write(F::zero()) };
+            }
+
+            Ok(mapping)
+        }
+    }
+
+    impl<F: Field> Deref for Mapping<F> {
+        type Target = [F];
+
+        fn deref(&self) -> &[F] {
+            // Safety: the mapping holds `len` initialized elements.
+            unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+        }
+    }
+
+    impl<F: Field> DerefMut for Mapping<F> {
+        fn deref_mut(&mut self) -> &mut [F] {
+            // Safety: as for `deref`, and the mapping is borrowed uniquely.
+            unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+        }
+    }
+
+    impl<F: Field> Drop for Mapping<F> {
+        fn drop(&mut self) {
+            // Safety: this is the mapping made in `zeroed`, of this size.
+            unsafe {
+                libc::munmap(
+                    self.ptr as *mut libc::c_void,
+                    self.len * std::mem::size_of::<F>(),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Fp;
+
+    #[test]
+    fn test_scratch() {
+        let dir = std::env::temp_dir().join(format!("halo-scratch-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = ScratchConfig {
+            threshold: Some(1024),
+            dir: dir.clone(),
+        };
+
+        let mut small = Scratch::<Fp>::zeroed_with(4, &config);
+        assert!(!small.is_mapped());
+        small[1] = Fp::one();
+        assert_eq!(&small[..], &[Fp::zero(), Fp::one(), Fp::zero(), Fp::zero()]);
+
+        let mut large = Scratch::<Fp>::zeroed_with(1000, &config);
+        assert_eq!(large.is_mapped(), cfg!(unix));
+        assert!(large.iter().all(|v| *v == Fp::zero()));
+        for (i, v) in large.iter_mut().enumerate() {
+            *v = Fp::from_u64(i as u64);
+        }
+        assert_eq!(large[999], Fp::from_u64(999));
+
+        // The temporary file is gone once mapped.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(&dir).unwrap();
+
+        assert!(!Scratch::<Fp>::zeroed_with(1000, &ScratchConfig::default()).is_mapped());
+        assert!(!Scratch::<Fp>::zeroed_with(0, &config).is_mapped());
+    }
+}
@@ -0,0 +1,80 @@
+//! Security levels, which fix how many bits of each Fiat-Shamir challenge
+//! the prover and verifier take from the transcript.
+
+use crate::fields::Field;
+
+/// The soundness level targeted by a set of `Params`.
+///
+/// Challenges are packed into 128-bit values with the top bit set, so that
+/// they are never zero. The level determines how many of the bits below it
+/// are taken from the transcript; the rest are zero. This changes neither
+/// the encoding of proofs nor the layout of the recursion's public inputs,
+/// only the values allowed in them, so both sides of a proof must agree on
+/// the level for it to verify.
+///
+/// The bits a level leaves out are constants in the recursive circuit, so
+/// a lower level spends fewer gates on the scalar multiplications by
+/// challenges. Nothing else is scaled by the level. The inner product
+/// argument is run once, and repeating it would multiply the cost of
+/// verifying it in the circuit rather than trade soundness for a smaller
+/// one; the field sizes are those of the Tweedle cycle, which the
+/// recursion is built on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SecurityLevel {
+    /// Challenges with 127 bits from the transcript.
+    #[default]
+    Bits128,
+    /// Challenges with 99 bits from the transcript.
+    Bits100,
+}
+
+impl SecurityLevel {
+    /// The number of bits of each challenge taken from the transcript.
+    pub fn challenge_bits(&self) -> usize {
+        match self {
+            SecurityLevel::Bits128 => 127,
+            SecurityLevel::Bits100 => 99,
+        }
+    }
+
+    fn mask(&self) -> u128 {
+        (1 << self.challenge_bits()) - 1
+    }
+
+    /// Packs the low bits of a value squeezed from the transcript into a
+    /// challenge.
+    pub(crate) fn pack_challenge(&self, squeezed: u128) -> u128 {
+        (squeezed & self.mask()) | (1 << 127)
+    }
+
+    /// Checks that `packed` is a challenge at this level, as produced by
+    /// `pack_challenge`.
+    pub fn is_packed_challenge<F: Field>(&self, packed: &F) -> bool {
+        if packed.to_bytes()[16..].iter().any(|b| *b != 0) {
+            return false;
+        }
+        let packed = packed.get_lower_128();
+        packed >> 127 == 1 && packed & !(1 << 127) & !self.mask() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fields::Fp;
+
+    #[test]
+    fn test_pack_challenge() {
+        let squeezed = u128::MAX - 5;
+        for level in &[SecurityLevel::Bits128, SecurityLevel::Bits100] {
+            let packed = level.pack_challenge(squeezed);
+            assert_eq!(packed.count_ones() as usize, level.challenge_bits() - 1);
+            assert!(level.is_packed_challenge(&Fp::from_u128(packed)));
+        }
+
+        let packed = Fp::from_u128(SecurityLevel::Bits128.pack_challenge(squeezed));
+        assert!(!SecurityLevel::Bits100.is_packed_challenge(&packed));
+        assert!(!SecurityLevel::Bits128.is_packed_challenge(&Fp::from_u128(1)));
+        assert!(!SecurityLevel::Bits128.is_packed_challenge(&-Fp::one()));
+    }
+}
@@ -0,0 +1,361 @@
+//! Storage of `Params` generators in shards, so that very large parameter
+//! sets can live in a key/value store (or a directory of files) and only the
+//! ranges of generators needed by a particular commitment are loaded.
+
+use crate::curves::*;
+use crate::proofs::Params;
+use crate::security::SecurityLevel;
+use crate::util;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+const HEADER_KEY: &str = "header";
+
+fn shard_key(shard: usize) -> String {
+    format!("generators/{}", shard)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A simple key/value store that params shards are written to and read from.
+pub trait ShardStore {
+    fn put(&mut self, key: &str, value: &[u8]) -> io::Result<()>;
+    fn get(&self, key: &str) -> io::Result<Vec<u8>>;
+}
+
+/// An in-memory store, mostly useful for testing.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+}
+
+impl ShardStore for MemoryStore {
+    fn put(&mut self, key: &str, value: &[u8]) -> io::Result<()> {
+        self.entries.insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        self.entries
+            .get(key)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, key.to_string()))
+    }
+}
+
+/// A store that keeps one file per key underneath a root directory.
+#[derive(Clone, Debug)]
+pub struct DirectoryStore {
+    root: PathBuf,
+}
+
+impl DirectoryStore {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DirectoryStore { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let mut path = self.root.clone();
+        for component in key.split('/') {
+            path.push(component);
+        }
+        path
+    }
+}
+
+impl ShardStore for DirectoryStore {
+    fn put(&mut self, key: &str, value: &[u8]) -> io::Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, value)
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        std::fs::read(self.path(key))
+    }
+}
+
+impl<C: Curve> Params<C> {
+    /// Writes the generators to `store` in shards of `shard_size` points
+    /// each, along with a header describing the layout and the hash the
+    /// generators were derived with, if any.
+    pub fn write_shards<S: ShardStore>(&self, store: &mut S, shard_size: usize) -> io::Result<()> {
+        assert!(shard_size > 0);
+
+        let mut header = Vec::with_capacity(16);
+        header.extend_from_slice(&(self.k as u32).to_le_bytes());
+        header.extend_from_slice(&(shard_size as u64).to_le_bytes());
+        // Headers without a hash, as all were before hashes were recorded,
+        // end here.
+        if let Some(id) = self.generator_hash {
+            header.extend_from_slice(&id.to_le_bytes());
+        }
+        store.put(HEADER_KEY, &header)?;
+
+        for (i, shard) in self.generators.chunks(shard_size).enumerate() {
+            let mut bytes = Vec::with_capacity(shard.len() * 32);
+            for gen in shard {
+                bytes.extend_from_slice(&gen.to_bytes());
+            }
+            store.put(&shard_key(i), &bytes)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A view of `Params` whose generators remain in a `ShardStore`. Shards are
+/// loaded on demand, so committing to a short vector only reads the shards
+/// that cover it.
+#[derive(Clone, Debug)]
+pub struct ShardedParams<C: Curve, S: ShardStore> {
+    pub g: C,
+    pub d: usize,
+    pub n: usize,
+    pub k: usize,
+    pub shard_size: usize,
+    /// The `GeneratorHash::id` the generators were derived with, if the
+    /// header records one.
+    pub generator_hash: Option<u32>,
+    store: S,
+}
+
+impl<C: Curve, S: ShardStore> ShardedParams<C, S> {
+    /// Reads the header from `store`. No generators are loaded.
+    pub fn open(store: S) -> io::Result<Self> {
+        let header = store.get(HEADER_KEY)?;
+        let generator_hash = match header.len() {
+            12 => None,
+            16 => {
+                let mut id = [0u8; 4];
+                id.copy_from_slice(&header[12..16]);
+                Some(u32::from_le_bytes(id))
+            }
+            _ => return Err(invalid_data("malformed params header")),
+        };
+        let mut k = [0u8; 4];
+        k.copy_from_slice(&header[0..4]);
+        let k = u32::from_le_bytes(k) as usize;
+        let mut shard_size = [0u8; 8];
+        shard_size.copy_from_slice(&header[4..12]);
+        let shard_size = usize::try_from(u64::from_le_bytes(shard_size))
+            .map_err(|_| invalid_data("malformed params header"))?;
+
+        // 2^k generators must be addressable on this platform.
+        if k <= 3 || k >= usize::BITS as usize || shard_size == 0 {
+            return Err(invalid_data("malformed params header"));
+        }
+
+        let d = 1 << k;
+
+        Ok(ShardedParams {
+            g: C::one(),
+            d,
+            n: d / 4,
+            k,
+            shard_size,
+            generator_hash,
+            store,
+        })
+    }
+
+    fn shard_len(&self, shard: usize) -> usize {
+        std::cmp::min(self.shard_size, self.d - shard * self.shard_size)
+    }
+
+    /// Loads and decodes a single shard.
+    pub fn load_shard(&self, shard: usize) -> io::Result<Vec<C::Affine>> {
+        assert!(shard * self.shard_size < self.d);
+
+        let bytes = self.store.get(&shard_key(shard))?;
+        if bytes.len() != self.shard_len(shard) * 32 {
+            return Err(invalid_data("params shard has the wrong length"));
+        }
+
+        let mut points = Vec::with_capacity(bytes.len() / 32);
+        for chunk in bytes.chunks(32) {
+            let mut repr = [0u8; 32];
+            repr.copy_from_slice(chunk);
+            let point = C::Affine::decode(&repr)
+                .map_err(|e| invalid_data(&format!("invalid point in params shard: {}", e)))?;
+            points.push(point);
+        }
+
+        Ok(points)
+    }
+
+    /// Loads the generators with indices in `start..(start + len)`, reading
+    /// only the shards that overlap that range.
+    pub fn load_range(&self, start: usize, len: usize) -> io::Result<Vec<C::Affine>> {
+        assert!(start + len <= self.d);
+
+        let mut generators = Vec::with_capacity(len);
+        if len == 0 {
+            return Ok(generators);
+        }
+
+        let end = start + len;
+        for shard in (start / self.shard_size)..=((end - 1) / self.shard_size) {
+            let offset = shard * self.shard_size;
+            let points = self.load_shard(shard)?;
+            let lo = std::cmp::max(start, offset) - offset;
+            let hi = std::cmp::min(end, offset + points.len()) - offset;
+            generators.extend_from_slice(&points[lo..hi]);
+        }
+
+        Ok(generators)
+    }
+
+    /// Equivalent to `Params::commit`, but performs the multiexp shard by
+    /// shard so that at most one shard of generators is held in memory.
+    pub fn commit(&self, v: &[C::Scalar], right_edge: bool) -> io::Result<C> {
+        assert!(self.d >= v.len());
+
+        let start = if right_edge { self.d - v.len() } else { 0 };
+        let end = start + v.len();
+
+        let mut acc = C::zero();
+        let mut cur = start;
+        while cur < end {
+            let shard = cur / self.shard_size;
+            let offset = shard * self.shard_size;
+            let points = self.load_shard(shard)?;
+            let hi = std::cmp::min(end, offset + points.len());
+            acc += util::multiexp_affine(
+                &v[(cur - start)..(hi - start)],
+                &points[(cur - offset)..(hi - offset)],
+            );
+            cur = hi;
+        }
+
+        Ok(acc)
+    }
+
+    /// Loads every shard and reassembles the full `Params`, at the default
+    /// security level since the header does not record one.
+    pub fn to_params(&self) -> io::Result<Params<C>> {
+        let generators = self.load_range(0, self.d)?;
+
+        Ok(Params {
+            g: self.g,
+            d: self.d,
+            n: self.n,
+            k: self.k,
+            generators,
+            level: SecurityLevel::default(),
+            generator_hash: self.generator_hash,
+            dummy_g_new: OnceLock::new(),
+            k_commitment_table: OnceLock::new(),
+        })
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::fields::*;
+    use crate::fixtures::seeded_params;
+
+    #[test]
+    fn test_sharded_commit() {
+        let params = seeded_params::<Ec1>(4);
+        let mut store = MemoryStore::new();
+        params.write_shards(&mut store, 3).unwrap();
+        let sharded = ShardedParams::<Ec1, _>::open(store).unwrap();
+
+        assert_eq!(sharded.k, params.k);
+        assert_eq!(sharded.d, params.d);
+        assert_eq!(sharded.n, params.n);
+        assert_eq!(sharded.load_range(2, 7).unwrap(), &params.generators[2..9]);
+
+        let v: Vec<Fq> = (1..12).map(|i| Fq::from_u64(i * 7 + 1)).collect();
+        for &right_edge in &[false, true] {
+            assert_eq!(
+                sharded.commit(&v, right_edge).unwrap(),
+                params.commit(&v, right_edge)
+            );
+        }
+        assert_eq!(sharded.commit(&[], true).unwrap(), Ec1::zero());
+
+        let reassembled = sharded.to_params().unwrap();
+        assert_eq!(reassembled.generators, params.generators);
+        assert_eq!(sharded.generator_hash, None);
+    }
+
+    #[test]
+    fn test_generator_hash_header() {
+        let params = Params::<Ec1>::from_seed_with(4, b"seed", &crate::Sha512Generators);
+        let mut store = MemoryStore::new();
+        params.write_shards(&mut store, 8).unwrap();
+        assert_eq!(store.get(HEADER_KEY).unwrap().len(), 16);
+
+        let sharded = ShardedParams::<Ec1, _>::open(store).unwrap();
+        assert_eq!(sharded.generator_hash, Some(crate::Sha512Generators::ID));
+        assert_eq!(sharded.to_params().unwrap().digest(), params.digest());
+    }
+
+    #[test]
+    fn test_directory_store() {
+        let params = seeded_params::<Ec0>(4);
+        let root = std::env::temp_dir().join(format!("halo-shards-{}", std::process::id()));
+        let mut store = DirectoryStore::new(&root);
+        params.write_shards(&mut store, 5).unwrap();
+
+        let sharded = ShardedParams::<Ec0, _>::open(store).unwrap();
+        assert_eq!(sharded.to_params().unwrap().generators, params.generators);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_shard() {
+        let params = seeded_params::<Ec1>(4);
+        let mut store = MemoryStore::new();
+        params.write_shards(&mut store, 4).unwrap();
+        store.put(&shard_key(1), &[0xff; 32 * 4]).unwrap();
+
+        let sharded = ShardedParams::<Ec1, _>::open(store).unwrap();
+        assert!(sharded.load_shard(0).is_ok());
+        assert!(sharded.load_shard(1).is_err());
+        assert!(sharded.commit(&[Fq::one(); 5], false).is_err());
+        assert!(sharded.commit(&[Fq::one(); 4], false).is_ok());
+    }
+
+    #[test]
+    fn test_malformed_header() {
+        let open = |k: u32, shard_size: u64| {
+            let mut header = k.to_le_bytes().to_vec();
+            header.extend_from_slice(&shard_size.to_le_bytes());
+            let mut store = MemoryStore::new();
+            store.put(HEADER_KEY, &header).unwrap();
+            ShardedParams::<Ec1, _>::open(store).map(|sharded| sharded.d)
+        };
+
+        assert_eq!(open(10, 4).unwrap(), 1 << 10);
+        assert!(open(3, 4).is_err());
+        assert!(open(10, 0).is_err());
+        // Neither the 12 bytes of the layout nor 16 with a generator hash.
+        let mut store = MemoryStore::new();
+        store.put(HEADER_KEY, &[0; 13]).unwrap();
+        assert!(ShardedParams::<Ec1, _>::open(store).is_err());
+        // Too many generators to address on this platform.
+        assert!(open(usize::BITS, 4).is_err());
+    }
+}
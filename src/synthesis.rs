@@ -79,6 +79,12 @@ pub trait Backend<FF: Field> {
 
     /// Exit out of the existing namespace.
     fn pop_namespace(&mut self, _gadget_name: Option<String>) {}
+
+    /// Check the budget of a namespace just left, for backends that count
+    /// constraints.
+    fn assert_budget(&mut self, _name: &str, _max_mults: usize) -> Result<(), SynthesisError> {
+        Ok(())
+    }
 }
 
 /// This is an abstraction which synthesizes circuits.
@@ -248,6 +254,14 @@ impl SynthesisDriver for Basic {
             fn get_root(&mut self) -> &mut Self::Root {
                 self
             }
+
+            fn assert_budget(
+                &mut self,
+                name: &str,
+                max_mults: usize,
+            ) -> Result<(), SynthesisError> {
+                self.backend.assert_budget(name, max_mults)
+            }
         }
 
         let mut tmp: Synthesizer<F, B> = Synthesizer {
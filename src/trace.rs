@@ -0,0 +1,208 @@
+//! Steps written once as native functions, from which both the witness and
+//! the constraints are derived.
+//!
+//! A [`TraceStep`] maps a state of field elements to the next state using
+//! only the arithmetic of [`TraceValue`]. Running it over field elements with
+//! [`run`] executes it natively; running it over [`Expr`]s with
+//! [`synthesize`] records the same computation as an expression, which is
+//! then lowered to constraints whose assignment comes from the values of the
+//! state. As both are the same code, the circuit cannot diverge from the
+//! native execution.
+//!
+//! ```ignore
+//! // (a, b) -> (b, a + b)
+//! struct Fibonacci;
+//!
+//! impl<F: Field> TraceStep<F> for Fibonacci {
+//!     fn state_len(&self) -> usize {
+//!         2
+//!     }
+//!
+//!     fn step<V: TraceValue<F>>(&self, state: &[V]) -> Vec<V> {
+//!         vec![state[1].clone(), state[0].clone() + state[1].clone()]
+//!     }
+//! }
+//! ```
+
+use crate::circuits::{Circuit, ConstraintSystem, SynthesisError};
+use crate::fields::Field;
+use crate::gadgets::expr::{self, Expr};
+use crate::gadgets::{AllocatedNum, Num};
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// The values a step computes with: field elements when it is run natively,
+/// and expressions when it is synthesized.
+pub trait TraceValue<F: Field>:
+    Clone + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self>
+{
+    fn constant(value: F) -> Self;
+
+    fn square(&self) -> Self {
+        self.clone() * self.clone()
+    }
+}
+
+impl<F: Field> TraceValue<F> for F {
+    fn constant(value: F) -> Self {
+        value
+    }
+}
+
+impl<F: Field> TraceValue<F> for Expr<F> {
+    fn constant(value: F) -> Self {
+        expr::constant(value)
+    }
+}
+
+/// A deterministic transition from one state of `state_len` field elements
+/// to the next.
+pub trait TraceStep<F: Field> {
+    fn state_len(&self) -> usize;
+
+    fn step<V: TraceValue<F>>(&self, state: &[V]) -> Vec<V>;
+}
+
+fn step_checked<F: Field, S: TraceStep<F>, V: TraceValue<F>>(step: &S, state: &[V]) -> Vec<V> {
+    assert_eq!(state.len(), step.state_len());
+    let next = step.step(state);
+    assert_eq!(next.len(), step.state_len());
+    next
+}
+
+/// Executes `step` natively.
+pub fn run<F: Field, S: TraceStep<F>>(step: &S, state: &[F]) -> Vec<F> {
+    step_checked(step, state)
+}
+
+/// Constrains the next state to be `step` applied to `state`, returning it.
+/// Its values are known whenever those of `state` are.
+pub fn synthesize<F: Field, CS: ConstraintSystem<F>, S: TraceStep<F>>(
+    cs: CS,
+    step: &S,
+    state: &[AllocatedNum<F>],
+) -> Result<Vec<Num<F>>, SynthesisError> {
+    let state: Vec<Expr<F>> = state.iter().map(|num| expr::var(*num)).collect();
+    expr::evaluate_all(cs, &step_checked(step, &state))
+}
+
+/// A circuit proving that applying `step` to a private state gives the
+/// public one returned by `public_inputs`.
+pub struct TraceCircuit<F: Field, S: TraceStep<F>> {
+    step: S,
+    state: Vec<F>,
+    next: Vec<F>,
+}
+
+impl<F: Field, S: TraceStep<F>> TraceCircuit<F, S> {
+    pub fn new(step: S, state: Vec<F>) -> Self {
+        let next = run(&step, &state);
+        TraceCircuit { step, state, next }
+    }
+
+    /// The next state.
+    pub fn public_inputs(&self) -> Vec<F> {
+        self.next.clone()
+    }
+}
+
+impl<F: Field, S: TraceStep<F>> Circuit<F> for TraceCircuit<F, S> {
+    fn synthesize<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut state = Vec::with_capacity(self.state.len());
+        for (i, value) in self.state.iter().enumerate() {
+            state.push(AllocatedNum::alloc(
+                cs.namespace(|| format!("state {}", i)),
+                || Ok(*value),
+            )?);
+        }
+
+        let next = synthesize(cs.namespace(|| "step"), &self.step, &state)?;
+
+        for (i, (num, value)) in next.into_iter().zip(self.next.iter()).enumerate() {
+            let input =
+                AllocatedNum::alloc_input(cs.namespace(|| format!("next {}", i)), || Ok(*value))?;
+            let lc = num.lc(&mut *cs) - &input.lc();
+            cs.enforce_zero(lc);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::{dev::GadgetCheck, fields::Fp, Basic};
+
+    // (a, b) -> (b, a + b)
+    struct Fibonacci;
+
+    impl<F: Field> TraceStep<F> for Fibonacci {
+        fn state_len(&self) -> usize {
+            2
+        }
+
+        fn step<V: TraceValue<F>>(&self, state: &[V]) -> Vec<V> {
+            vec![state[1].clone(), state[0].clone() + state[1].clone()]
+        }
+    }
+
+    // (x, y) -> (x^3 - y + 5, x^2 y)
+    struct Cubic;
+
+    impl<F: Field> TraceStep<F> for Cubic {
+        fn state_len(&self) -> usize {
+            2
+        }
+
+        fn step<V: TraceValue<F>>(&self, state: &[V]) -> Vec<V> {
+            let x2 = state[0].square();
+            vec![
+                x2.clone() * state[0].clone() - state[1].clone() + V::constant(F::from_u64(5)),
+                x2 * state[1].clone(),
+            ]
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let mut state = vec![Fp::zero(), Fp::one()];
+        for _ in 0..10 {
+            state = run(&Fibonacci, &state);
+        }
+        assert_eq!(state, vec![Fp::from(55), Fp::from(89)]);
+
+        assert_eq!(
+            run(&Cubic, &[Fp::from(3), Fp::from(2)]),
+            vec![Fp::from(30), Fp::from(18)]
+        );
+    }
+
+    #[test]
+    fn test_trace_circuit() {
+        let honest = TraceCircuit::new(Fibonacci, vec![Fp::from(5), Fp::from(8)]);
+        assert_eq!(honest.public_inputs(), vec![Fp::from(8), Fp::from(13)]);
+        let inputs = honest.public_inputs();
+
+        GadgetCheck::new(honest, &inputs)
+            .malformed(
+                "wrong next state",
+                TraceCircuit {
+                    step: Fibonacci,
+                    state: vec![Fp::from(5), Fp::from(8)],
+                    next: vec![Fp::from(8), Fp::from(14)],
+                },
+                &[Fp::from(8), Fp::from(14)],
+            )
+            .budget(4, 7)
+            .check::<Basic>()
+            .unwrap();
+
+        // The shared x^2 is constrained once.
+        let honest = TraceCircuit::new(Cubic, vec![Fp::from(3), Fp::from(2)]);
+        let inputs = honest.public_inputs();
+        GadgetCheck::new(honest, &inputs)
+            .budget(7, 13)
+            .check::<Basic>()
+            .unwrap();
+    }
+}
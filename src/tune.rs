@@ -0,0 +1,345 @@
+//! Tuning of the prover to the machine it runs on.
+//!
+//! How finely the prover splits multiexps and polynomial arithmetic across
+//! threads, and which bucket window its multiexps use, depend on how many
+//! processors there are and how fast they are. An [`AutoTune`] profile
+//! records those choices. `AutoTune::load_or_measure` measures the machine
+//! once and keeps the profile on disk, and `AutoTune::install` makes the
+//! prover use it; until then the prover uses `AutoTune::default`.
+
+use crate::curves::Curve;
+use crate::encoding::*;
+use crate::fields::Field;
+use crate::util;
+use crossbeam_utils::thread;
+use std::convert::TryFrom;
+use std::fs;
+use std::hint::black_box;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 8] = b"halotune";
+const VERSION: u32 = 1;
+
+/// The number of bases `AutoTune::measure` times multiexps over, as a
+/// power of two.
+const MEASURE_LOG_MSM_SIZE: u32 = 12;
+
+static PROFILE: RwLock<Option<AutoTune>> = RwLock::new(None);
+
+/// How the prover splits its work across threads and sizes its multiexp
+/// windows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoTune {
+    /// The number of processors when the profile was made.
+    pub cpus: usize,
+    /// How many threads work is split across.
+    pub threads: usize,
+    /// The fewest field elements worth handing to a thread of their own.
+    pub min_chunk: usize,
+    /// The fewest multiexp bases worth handing to a thread of their own.
+    pub min_msm_chunk: usize,
+    /// Added to the default bucket window of a multiexp, in bits.
+    pub msm_window_bias: i32,
+    /// Measured field multiplications per second on one thread, or zero if
+    /// the machine was not measured.
+    pub field_muls_per_sec: u64,
+    /// Measured multiexp bases per second on one thread, or zero if the
+    /// machine was not measured.
+    pub msm_bases_per_sec: u64,
+}
+
+impl Default for AutoTune {
+    /// Splits work evenly across one thread per processor, without having
+    /// measured them.
+    fn default() -> Self {
+        let cpus = num_cpus::get();
+        AutoTune {
+            cpus,
+            threads: cpus,
+            min_chunk: 1,
+            min_msm_chunk: 1,
+            msm_window_bias: 0,
+            field_muls_per_sec: 0,
+            msm_bases_per_sec: 0,
+        }
+    }
+}
+
+impl AutoTune {
+    /// The installed profile, or the default one.
+    pub fn current() -> Self {
+        PROFILE.read().unwrap().unwrap_or_default()
+    }
+
+    /// Makes the prover use this profile, replacing any installed before.
+    pub fn install(&self) {
+        *PROFILE.write().unwrap() = Some(*self);
+    }
+
+    /// Measures this machine, which takes on the order of a second.
+    pub fn measure() -> Self {
+        Self::measure_with(MEASURE_LOG_MSM_SIZE)
+    }
+
+    fn measure_with(log_msm_size: u32) -> Self {
+        let mut tune = AutoTune::default();
+
+        // Field multiplications, chained so none can be skipped.
+        let muls = 1 << 16;
+        let mut acc = crate::Fp::from_u64(7);
+        let elapsed = time(|| {
+            for _ in 0..muls {
+                acc = black_box(acc * acc);
+            }
+        });
+        tune.field_muls_per_sec = per_sec(muls, elapsed);
+
+        // Multiexps, over each window near the default one.
+        let n = 1 << log_msm_size;
+        let mut bases = Vec::with_capacity(n);
+        let mut coeffs = Vec::with_capacity(n);
+        let mut base = crate::Ec1::one();
+        let mut coeff = crate::Fq::from_u64(7);
+        for _ in 0..n {
+            base = base.double() + crate::Ec1::one();
+            coeff = coeff.square() + crate::Fq::one();
+            bases.push(base);
+            coeffs.push(coeff);
+        }
+        let mut best = None;
+        for bias in -1..=1 {
+            let window = (util::msm_window(n) as i32 + bias) as usize;
+            let elapsed = time(|| {
+                let mut acc = crate::Ec1::zero();
+                util::multiexp_serial(&coeffs, &bases, &mut acc, window);
+                black_box(acc);
+            });
+            if best.is_none_or(|(_, best)| elapsed < best) {
+                best = Some((bias, elapsed));
+            }
+        }
+        let (bias, elapsed) = best.unwrap();
+        tune.msm_window_bias = bias;
+        tune.msm_bases_per_sec = per_sec(n, elapsed);
+
+        // A thread only pays off once its work outlasts the cost of
+        // spawning it.
+        let rounds = 16;
+        let spawn = time(|| {
+            for _ in 0..rounds {
+                thread::scope(|scope| {
+                    for _ in 0..tune.threads {
+                        scope.spawn(|_| ());
+                    }
+                })
+                .unwrap();
+            }
+        }) / (rounds * tune.threads as u32);
+        tune.min_chunk = work_during(spawn, tune.field_muls_per_sec);
+        tune.min_msm_chunk = work_during(spawn, tune.msm_bases_per_sec);
+
+        tune
+    }
+
+    /// Loads the profile at `path`, or measures this machine and saves the
+    /// profile there if there is none or it was made with a different
+    /// number of processors.
+    pub fn load_or_measure<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        match Self::load(path) {
+            Ok(tune) if tune.cpus == num_cpus::get() => return Ok(tune),
+            Ok(_) => {}
+            Err(e)
+                if e.kind() == io::ErrorKind::NotFound
+                    || e.kind() == io::ErrorKind::InvalidData
+                    || e.kind() == io::ErrorKind::UnexpectedEof => {}
+            Err(e) => return Err(e),
+        }
+
+        let tune = Self::measure();
+        tune.save(path)?;
+        Ok(tune)
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        write_u32(writer, VERSION)?;
+        write_u64(writer, self.cpus as u64)?;
+        write_u64(writer, self.threads as u64)?;
+        write_u64(writer, self.min_chunk as u64)?;
+        write_u64(writer, self.min_msm_chunk as u64)?;
+        write_u32(writer, self.msm_window_bias as u32)?;
+        write_u64(writer, self.field_muls_per_sec)?;
+        write_u64(writer, self.msm_bases_per_sec)
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a tuning profile"));
+        }
+        if read_u32(reader)? != VERSION {
+            return Err(invalid_data("unsupported tuning profile version"));
+        }
+        let read_usize = |reader: &mut R| {
+            usize::try_from(read_u64(reader)?).map_err(|_| invalid_data("malformed tuning profile"))
+        };
+        let tune = AutoTune {
+            cpus: read_usize(reader)?,
+            threads: read_usize(reader)?,
+            min_chunk: read_usize(reader)?,
+            min_msm_chunk: read_usize(reader)?,
+            msm_window_bias: read_u32(reader)? as i32,
+            field_muls_per_sec: read_u64(reader)?,
+            msm_bases_per_sec: read_u64(reader)?,
+        };
+
+        let mut trailing = [0u8; 1];
+        if reader.read(&mut trailing)? != 0 {
+            return Err(invalid_data("trailing bytes after tuning profile"));
+        }
+        if tune.threads == 0
+            || tune.min_chunk == 0
+            || tune.min_msm_chunk == 0
+            || tune.msm_window_bias.abs() > 4
+        {
+            return Err(invalid_data("malformed tuning profile"));
+        }
+
+        Ok(tune)
+    }
+
+    /// Writes the profile to `path`, by way of a temporary file so that a
+    /// crash midway leaves any previous profile intact.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        let mut bytes = vec![];
+        self.write(&mut bytes)?;
+        fs::write(&tmp, &bytes)?;
+        fs::rename(&tmp, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        AutoTune::read(&mut &bytes[..])
+    }
+
+    /// How many field elements of `len` each thread should handle, or None
+    /// if they are better handled on the calling thread.
+    pub(crate) fn chunk_size(&self, len: usize) -> Option<usize> {
+        split(len, self.threads, self.min_chunk)
+    }
+
+    /// How many of `len` multiexp bases each thread should handle, or None
+    /// if they are better handled on the calling thread.
+    pub(crate) fn msm_chunk_size(&self, len: usize) -> Option<usize> {
+        split(len, self.threads, self.min_msm_chunk)
+    }
+
+    /// The bucket window, in bits, for a multiexp of `len` bases on one
+    /// thread.
+    pub(crate) fn msm_window(&self, len: usize) -> usize {
+        (util::msm_window(len) as i32 + self.msm_window_bias).clamp(1, 32) as usize
+    }
+}
+
+fn split(len: usize, threads: usize, min_chunk: usize) -> Option<usize> {
+    let chunk = std::cmp::max(len.div_ceil(threads), min_chunk);
+    if threads > 1 && len > threads && chunk < len {
+        Some(chunk)
+    } else {
+        None
+    }
+}
+
+fn time<F: FnOnce()>(f: F) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+fn per_sec(count: usize, elapsed: Duration) -> u64 {
+    (count as f64 / elapsed.as_secs_f64().max(1e-9)) as u64
+}
+
+/// How many operations at `per_sec` fit in `elapsed`, and at least one.
+fn work_during(elapsed: Duration, per_sec: u64) -> usize {
+    std::cmp::max(1, (elapsed.as_secs_f64() * per_sec as f64).ceil() as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let tune = AutoTune {
+            threads: 4,
+            min_chunk: 10,
+            min_msm_chunk: 100,
+            ..AutoTune::default()
+        };
+        assert_eq!(tune.chunk_size(4), None);
+        assert_eq!(tune.chunk_size(15), Some(10));
+        assert_eq!(tune.chunk_size(1000), Some(250));
+        assert_eq!(tune.msm_chunk_size(150), Some(100));
+        assert_eq!(tune.msm_chunk_size(100), None);
+
+        let single = AutoTune { threads: 1, ..tune };
+        assert_eq!(single.chunk_size(1000), None);
+    }
+
+    #[test]
+    fn test_measure_save_load() {
+        let tune = AutoTune::measure_with(6);
+        assert!(tune.threads > 0);
+        assert!(tune.min_chunk > 0 && tune.min_msm_chunk > 0);
+        assert!(tune.field_muls_per_sec > 0 && tune.msm_bases_per_sec > 0);
+
+        let path = std::env::temp_dir().join(format!("halo-tune-{}", std::process::id()));
+        tune.save(&path).unwrap();
+        assert_eq!(AutoTune::load(&path).unwrap(), tune);
+        assert_eq!(AutoTune::load_or_measure(&path).unwrap(), tune);
+
+        // A profile made with another processor count is measured again.
+        let other = AutoTune {
+            cpus: tune.cpus + 1,
+            ..tune
+        };
+        other.save(&path).unwrap();
+        assert_eq!(AutoTune::load_or_measure(&path).unwrap().cpus, tune.cpus);
+
+        let mut bytes = vec![];
+        tune.write(&mut bytes).unwrap();
+        bytes.push(0);
+        assert!(AutoTune::read(&mut &bytes[..]).is_err());
+        assert!(AutoTune::read(&mut &bytes[1..]).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tuned_multiexp() {
+        // Every window gives the same multiexp.
+        let bases: Vec<_> = (1..50)
+            .map(|i| crate::Ec1::one() * crate::Fq::from_u64(i))
+            .collect();
+        let coeffs: Vec<_> = (1..50).map(|i| crate::Fq::from_u64(i * i + 3)).collect();
+        let mut expected = crate::Ec1::zero();
+        for (base, coeff) in bases.iter().zip(coeffs.iter()) {
+            expected += *base * *coeff;
+        }
+        for window in 1..10 {
+            let mut acc = crate::Ec1::zero();
+            util::multiexp_serial(&coeffs, &bases, &mut acc, window);
+            assert_eq!(acc, expected);
+        }
+    }
+}
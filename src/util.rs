@@ -1,7 +1,13 @@
-use crate::{Curve, Field};
+#[cfg(feature = "proofs")]
+use crate::{Curve, CurveAffine, Field};
+#[cfg(feature = "proofs")]
+use std::ops::AddAssign;
+#[cfg(feature = "prover")]
 use crossbeam_utils::thread;
-use num_cpus;
+#[cfg(feature = "prover")]
+use crate::tune::AutoTune;
 
+#[cfg(feature = "prover")]
 pub fn parallel_generator_collapse<C: Curve>(
     g: &mut [C],
     challenge: C::Scalar,
@@ -11,11 +17,7 @@ pub fn parallel_generator_collapse<C: Curve>(
 
     let (g_lo, g_hi) = g.split_at_mut(l);
 
-    let num_cpus = num_cpus::get();
-    let mut chunk = l / num_cpus;
-    if chunk < num_cpus {
-        chunk = l;
-    }
+    let chunk = AutoTune::current().chunk_size(l).unwrap_or(l).max(1);
 
     thread::scope(|scope| {
         for (lo, hi) in g_lo.chunks_mut(chunk).zip(g_hi.chunks(chunk)) {
@@ -29,6 +31,7 @@ pub fn parallel_generator_collapse<C: Curve>(
     .unwrap();
 }
 
+#[cfg(feature = "proofs")]
 pub fn compute_inner_product<F: Field>(a: &[F], b: &[F]) -> F {
     assert_eq!(a.len(), b.len());
     let mut acc = F::zero();
@@ -41,96 +44,52 @@ pub fn compute_inner_product<F: Field>(a: &[F], b: &[F]) -> F {
 }
 
 /// TODO: Naive multiexp for now.
+#[cfg(feature = "proofs")]
 pub fn multiexp<F: Field, C: Curve<Scalar = F>>(coeffs: &[C::Scalar], bases: &[C]) -> C {
+    crate::metrics::record(|m| m.msm_size(coeffs.len()));
+    multiexp_generic(coeffs, bases)
+}
+
+/// Computes the same multiexp as `multiexp` over affine bases, which lets
+/// the bucket accumulation use mixed additions.
+#[cfg(feature = "proofs")]
+pub fn multiexp_affine<A: CurveAffine>(coeffs: &[A::Scalar], bases: &[A]) -> A::Projective {
+    crate::metrics::record(|m| m.msm_size(coeffs.len()));
+    multiexp_generic(coeffs, bases)
+}
+
+#[cfg(feature = "prover")]
+fn multiexp_generic<C, B>(coeffs: &[C::Scalar], bases: &[B]) -> C
+where
+    C: Curve + for<'a> AddAssign<&'a B>,
+    B: Copy + Send + Sync + Into<C>,
+{
     assert_eq!(coeffs.len(), bases.len());
 
-    let num_cpus = num_cpus::get();
-    if coeffs.len() > num_cpus {
-        let chunk = coeffs.len() / num_cpus;
+    let tune = AutoTune::current();
+    if let Some(chunk) = tune.msm_chunk_size(coeffs.len()) {
+        let window = tune.msm_window(chunk);
         let num_chunks = coeffs.chunks(chunk).len();
         let mut results = vec![C::zero(); num_chunks];
         thread::scope(|scope| {
-            let chunk = coeffs.len() / num_cpus;
-
             for ((coeffs, bases), acc) in coeffs
                 .chunks(chunk)
                 .zip(bases.chunks(chunk))
                 .zip(results.iter_mut())
             {
-                scope.spawn(move |_| {
-                    let coeffs: Vec<[u8; 32]> = coeffs.iter().map(|a| a.to_bytes()).collect();
-
-                    let c = if bases.len() < 32 {
-                        3
-                    } else {
-                        (f64::from(bases.len() as u32)).ln().ceil() as usize
-                    };
-
-                    fn get_at(segment: usize, c: usize, bytes: &[u8; 32]) -> usize {
-                        let skip_bits = segment * c;
-                        let skip_bytes = skip_bits / 8;
-
-                        if skip_bytes >= 32 {
-                            return 0;
-                        }
-
-                        let mut v = [0; 8];
-                        for (v, o) in v.iter_mut().zip(bytes[skip_bytes..].iter()) {
-                            *v = *o;
-                        }
-
-                        let mut tmp = u64::from_le_bytes(v);
-                        tmp >>= skip_bits - (skip_bytes * 8);
-                        tmp = tmp % (1 << c);
-
-                        tmp as usize
-                    }
-
-                    let segments = (256 / c) + 1;
-
-                    for current_segment in (0..segments).rev() {
-                        for _ in 0..c {
-                            *acc = acc.double();
-                        }
-
-                        let mut buckets = vec![C::zero(); (1 << c) - 1];
-
-                        for (coeff, base) in coeffs.iter().zip(bases.iter()) {
-                            let coeff = get_at(current_segment, c, coeff);
-                            if coeff != 0 {
-                                buckets[coeff - 1] += base;
-                            }
-                        }
-
-                        // Summation by parts
-                        // e.g. 3a + 2b + 1c = a +
-                        //                    (a) + b +
-                        //                    ((a) + b) + c
-                        let mut running_sum = C::zero();
-                        for exp in buckets.into_iter().rev() {
-                            running_sum.add_assign(&exp);
-                            acc.add_assign(&running_sum);
-                        }
-                    }
-
-                    /*
-                    for (coeff, base) in coeffs.iter().zip(bases.iter()) {
-                        let coeff: F = *coeff;
-                        let base: C = *base;
-                        let product = base * coeff;
-                        *acc = (*acc) + product;
-                    }
-                    */
-                });
+                scope.spawn(move |_| multiexp_serial(coeffs, bases, acc, window));
             }
         })
         .unwrap();
         results.iter().fold(C::zero(), |a, b| a + *b)
+    } else if coeffs.len() > tune.threads {
+        let mut acc = C::zero();
+        multiexp_serial(coeffs, bases, &mut acc, tune.msm_window(coeffs.len()));
+        acc
     } else {
         let mut acc = C::zero();
         for (coeff, base) in coeffs.iter().zip(bases.iter()) {
-            let coeff: F = *coeff;
-            let base: C = *base;
+            let base: C = (*base).into();
             let product = base * coeff;
             acc += product;
         }
@@ -138,216 +97,94 @@ pub fn multiexp<F: Field, C: Curve<Scalar = F>>(coeffs: &[C::Scalar], bases: &[C
     }
 }
 
-pub fn multiply_polynomials<F: Field>(mut a: Vec<F>, mut b: Vec<F>) -> Vec<F> {
-    let degree_of_result = (a.len() - 1) + (b.len() - 1);
-    let coeffs_of_result = degree_of_result + 1;
-
-    // Compute the size of our evaluation domain
-    let mut m = 1;
-    let mut exp = 0;
-    while m < coeffs_of_result {
-        m *= 2;
-        exp += 1;
-
-        // The pairing-friendly curve may not be able to support
-        // large enough (radix2) evaluation domains.
-        if exp >= F::S {
-            panic!("polynomial too large");
-        }
-    }
-
-    // Compute alpha, the 2^exp primitive root of unity
-    let mut alpha = F::ALPHA;
-    for _ in exp..F::S {
-        alpha = alpha.square();
-    }
-
-    // Extend the vectors with zeroes
-    a.resize(m, F::zero());
-    b.resize(m, F::zero());
-
-    best_fft(&mut a, alpha, exp);
-    best_fft(&mut b, alpha, exp);
-
-    // Multiply pairwise
-    let num_cpus = num_cpus::get();
-    if a.len() > num_cpus {
-        thread::scope(|scope| {
-            let chunk = a.len() / num_cpus::get();
-
-            for (a, b) in a.chunks_mut(chunk).zip(b.chunks(chunk)) {
-                scope.spawn(move |_| {
-                    for (a, b) in a.iter_mut().zip(b.iter()) {
-                        *a *= *b;
-                    }
-                });
-            }
-        })
-        .unwrap();
-    } else {
-        for (a, b) in a.iter_mut().zip(b.iter()) {
-            *a *= *b;
-        }
-    }
-
-    // Inverse FFT
-    let alpha_inv = alpha.invert().unwrap();
-    best_fft(&mut a, alpha_inv, exp);
-
-    // Divide all elements by m = a.len()
-    let minv = F::from_u64(m as u64).invert().unwrap();
-    if a.len() > num_cpus {
-        thread::scope(|scope| {
-            let chunk = a.len() / num_cpus::get();
-
-            for a in a.chunks_mut(chunk) {
-                scope.spawn(move |_| {
-                    for a in a.iter_mut() {
-                        *a *= minv;
-                    }
-                });
-            }
-        })
-        .unwrap();
-    } else {
-        for a in a.iter_mut() {
-            *a *= minv;
-        }
-    }
-
-    a.truncate(coeffs_of_result);
-
-    a
-}
-
-fn log2_floor(num: usize) -> u32 {
-    assert!(num > 0);
-
-    let mut pow = 0;
-
-    while (1 << (pow + 1)) <= num {
-        pow += 1;
-    }
+/// Without the `prover` feature there is no thread pool, so the multiexp is
+/// performed on the calling thread.
+#[cfg(all(feature = "proofs", not(feature = "prover")))]
+fn multiexp_generic<C, B>(coeffs: &[C::Scalar], bases: &[B]) -> C
+where
+    C: Curve + for<'a> AddAssign<&'a B>,
+    B: Copy + Send + Sync + Into<C>,
+{
+    assert_eq!(coeffs.len(), bases.len());
 
-    pow
+    let mut acc = C::zero();
+    multiexp_serial(coeffs, bases, &mut acc, msm_window(bases.len()));
+    acc
 }
 
-fn best_fft<F: Field>(a: &mut [F], omega: F, log_n: u32) {
-    let cpus = num_cpus::get();
-    let log_cpus = log2_floor(cpus);
-
-    if log_n <= log_cpus {
-        serial_fft(a, omega, log_n);
+/// The bucket window, in bits, for a multiexp of `len` bases when the
+/// machine has not been measured.
+#[cfg(feature = "proofs")]
+pub(crate) fn msm_window(len: usize) -> usize {
+    if len < 32 {
+        3
     } else {
-        parallel_fft(a, omega, log_n, log_cpus);
+        (len as f64).ln().ceil() as usize
     }
 }
 
-fn serial_fft<F: Field>(a: &mut [F], omega: F, log_n: u32) {
-    fn bitreverse(mut n: u32, l: u32) -> u32 {
-        let mut r = 0;
-        for _ in 0..l {
-            r = (r << 1) | (n & 1);
-            n >>= 1;
+/// Adds the multiexp of `coeffs` and `bases` to `acc`, using buckets for
+/// windows of `c` bits.
+#[cfg(feature = "proofs")]
+pub(crate) fn multiexp_serial<C, B>(coeffs: &[C::Scalar], bases: &[B], acc: &mut C, c: usize)
+where
+    C: Curve + for<'a> AddAssign<&'a B>,
+{
+    assert!(c > 0 && c < 64);
+    let coeffs: Vec<[u8; 32]> = coeffs.iter().map(|a| a.to_bytes()).collect();
+
+    fn get_at(segment: usize, c: usize, bytes: &[u8; 32]) -> usize {
+        let skip_bits = segment * c;
+        let skip_bytes = skip_bits / 8;
+
+        if skip_bytes >= 32 {
+            return 0;
         }
-        r
-    }
 
-    let n = a.len() as u32;
-    assert_eq!(n, 1 << log_n);
-
-    for k in 0..n {
-        let rk = bitreverse(k, log_n);
-        if k < rk {
-            a.swap(rk as usize, k as usize);
+        let mut v = [0; 8];
+        for (v, o) in v.iter_mut().zip(bytes[skip_bytes..].iter()) {
+            *v = *o;
         }
-    }
 
-    let mut m = 1;
-    for _ in 0..log_n {
-        let w_m = omega.pow(&[u64::from(n / (2 * m)), 0, 0, 0]);
-
-        let mut k = 0;
-        while k < n {
-            let mut w = F::one();
-            for j in 0..m {
-                let mut t = a[(k + j + m) as usize];
-                t *= w;
-                a[(k + j + m) as usize] = a[(k + j) as usize] - t;
-                a[(k + j) as usize] += t;
-                w *= w_m;
-            }
-
-            k += 2 * m;
-        }
+        let mut tmp = u64::from_le_bytes(v);
+        tmp >>= skip_bits - (skip_bytes * 8);
+        tmp = tmp % (1 << c);
 
-        m *= 2;
+        tmp as usize
     }
-}
 
-fn parallel_fft<F: Field>(a: &mut [F], omega: F, log_n: u32, log_cpus: u32) {
-    assert!(log_n >= log_cpus);
+    let segments = (256 / c) + 1;
 
-    let num_cpus = 1 << log_cpus;
-    let log_new_n = log_n - log_cpus;
-    let mut tmp = vec![vec![F::zero(); 1 << log_new_n]; num_cpus];
-    let new_omega = omega.pow(&[num_cpus as u64, 0, 0, 0]);
-
-    thread::scope(|scope| {
-        let a = &*a;
+    for current_segment in (0..segments).rev() {
+        for _ in 0..c {
+            *acc = acc.double();
+        }
 
-        for (j, tmp) in tmp.iter_mut().enumerate() {
-            scope.spawn(move |_| {
-                // Shuffle into a sub-FFT
-                let omega_j = omega.pow(&[j as u64, 0, 0, 0]);
-                let omega_step = omega.pow(&[(j as u64) << log_new_n, 0, 0, 0]);
-
-                let mut elt = F::one();
-                for i in 0..(1 << log_new_n) {
-                    for s in 0..num_cpus {
-                        let idx = (i + (s << log_new_n)) % (1 << log_n);
-                        let mut t = a[idx];
-                        t *= elt;
-                        tmp[i] += t;
-                        elt *= omega_step;
-                    }
-                    elt *= omega_j;
-                }
+        let mut buckets = vec![C::zero(); (1 << c) - 1];
 
-                // Perform sub-FFT
-                serial_fft(tmp, new_omega, log_new_n);
-            });
+        for (coeff, base) in coeffs.iter().zip(bases.iter()) {
+            let coeff = get_at(current_segment, c, coeff);
+            if coeff != 0 {
+                buckets[coeff - 1] += base;
+            }
         }
-    })
-    .unwrap();
-
-    // Unshuffle
-    let mask = (1 << log_cpus) - 1;
-    for (idx, a) in a.iter_mut().enumerate() {
-        *a = tmp[idx & mask][idx >> log_cpus];
-    }
-}
 
-#[test]
-fn test_fft() {
-    use crate::fields::Fp;
-
-    let a = (0..1000).map(Fp::from_u64).collect::<Vec<_>>();
-    let b = (0..1000)
-        .map(|i| Fp::from_u64(i + 1000))
-        .collect::<Vec<_>>();
-
-    let mut naive_product = vec![Fp::zero(); (a.len() + b.len()) - 1];
-    for (i, a) in a.iter().enumerate() {
-        for (j, b) in b.iter().enumerate() {
-            naive_product[i + j] += (*a) * (*b);
+        // Summation by parts
+        // e.g. 3a + 2b + 1c = a +
+        //                    (a) + b +
+        //                    ((a) + b) + c
+        let mut running_sum = C::zero();
+        for exp in buckets.into_iter().rev() {
+            running_sum.add_assign(&exp);
+            acc.add_assign(&running_sum);
         }
     }
-    let valid_product = multiply_polynomials(a, b);
-
-    assert_eq!(valid_product, naive_product);
 }
 
+#[cfg(feature = "prover")]
+pub use crate::fft::multiply_polynomials;
+
+#[cfg(feature = "proofs")]
 pub fn get_challenge_scalar<F1: Field, F2: Field>(challenge: F1) -> F2 {
     let challenge = challenge.get_lower_128();
 
@@ -374,6 +211,7 @@ pub fn get_challenge_scalar<F1: Field, F2: Field>(challenge: F1) -> F2 {
     acc
 }
 
+#[cfg(feature = "proofs")]
 pub fn compute_b<F: Field>(x: F, challenges: &[F], challenges_inv: &[F]) -> F {
     assert!(!challenges.is_empty());
     assert_eq!(challenges.len(), challenges_inv.len());
@@ -389,6 +227,7 @@ pub fn compute_b<F: Field>(x: F, challenges: &[F], challenges_inv: &[F]) -> F {
     }
 }
 
+#[cfg(feature = "proofs")]
 pub fn compute_g_coeffs_for_inner_product<F: Field>(challenges_sq: &[F], allinv: F) -> Vec<F> {
     let lg_n = challenges_sq.len();
     let n = 1 << lg_n;
@@ -396,7 +235,7 @@ pub fn compute_g_coeffs_for_inner_product<F: Field>(challenges_sq: &[F], allinv:
     let mut s = Vec::with_capacity(n);
     s.push(allinv);
     for i in 1..n {
-        let lg_i = (32 - 1 - (i as u32).leading_zeros()) as usize;
+        let lg_i = (usize::BITS - 1 - i.leading_zeros()) as usize;
         let k = 1 << lg_i;
         let u_lg_i_sq = challenges_sq[(lg_n - 1) - lg_i];
         s.push(s[i - k] * u_lg_i_sq);
@@ -405,16 +244,18 @@ pub fn compute_g_coeffs_for_inner_product<F: Field>(challenges_sq: &[F], allinv:
     s
 }
 
-pub fn compute_g_for_inner_product<F: Field, C: Curve<Scalar = F>>(
-    generators: &[C],
+#[cfg(feature = "proofs")]
+pub fn compute_g_for_inner_product<F: Field, A: CurveAffine<Scalar = F>>(
+    generators: &[A],
     challenges_sq: &[F],
     allinv: F,
-) -> C {
+) -> A::Projective {
     let s = compute_g_coeffs_for_inner_product::<F>(challenges_sq, allinv);
 
-    multiexp(&s, &generators)
+    multiexp_affine(&s, &generators)
 }
 
+#[cfg(feature = "proofs")]
 #[test]
 fn test_compute_b() {
     use crate::Fp;
@@ -0,0 +1,228 @@
+//! Reuse of the circuit-dependent parts of recursive proof verification.
+//!
+//! Verifying a [`RecursiveProof`] synthesizes its verification circuits on
+//! both curves of the cycle, to compute s(X, Y) at the verifier's challenges
+//! and the positions of the public inputs. Neither depends on the proof, so
+//! a [`VerifierCache`] records them once per circuit and pair of params and
+//! shares them across every proof verified through it.
+
+use crate::circuits::*;
+use crate::curves::*;
+use crate::metrics::*;
+use crate::proofs::*;
+use crate::recursion::RecursiveProof;
+use crate::registry::recursive_circuit_shape_digest;
+use crate::synthesis::Basic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// What verification needs to know about the verification circuits of a
+/// `RecursiveProof<E1, E2>`.
+struct CachedCircuit<E1: Curve, E2: Curve> {
+    // Positions in k(Y) of the public inputs of the circuit over E1
+    input_map: Vec<usize>,
+
+    s1: SparseS<E1::Scalar>,
+    s2: SparseS<E2::Scalar>,
+}
+
+/// The digests of `e1params`, of `e2params` and of the shape of the
+/// verification circuit over `E1`.
+type CacheKey = ([u8; 32], [u8; 32], [u8; 32]);
+
+type CacheEntries<E1, E2> = HashMap<CacheKey, Arc<CachedCircuit<E1, E2>>>;
+
+/// A cache of verification circuits keyed by the digests of both params and
+/// the shape digest of the verification circuit, which commits to the
+/// recursive circuit, `k` and the payload length.
+///
+/// The digests are computed on every verification, so a hit saves the
+/// synthesis behind s(X, Y) on both curves and the input positions, but not
+/// that behind the shape digest.
+///
+/// Proofs at odd steps of a chain are over the opposite curve, and need a
+/// `VerifierCache<E2, E1>`.
+pub struct VerifierCache<E1: Curve, E2: Curve> {
+    entries: Mutex<CacheEntries<E1, E2>>,
+}
+
+impl<E1: Curve, E2: Curve> Default for VerifierCache<E1, E2> {
+    fn default() -> Self {
+        VerifierCache {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<E1: Curve, E2: Curve> VerifierCache<E1, E2> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of cached circuits.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+impl<E1, E2> VerifierCache<E1, E2>
+where
+    E1: Curve<Base = <E2 as Curve>::Scalar>,
+    E2: Curve<Base = <E1 as Curve>::Scalar>,
+{
+    // The entry for the circuits of `proof`, built on a miss. The lock is not
+    // held while synthesizing, so concurrent misses on the same key may each
+    // build the entry; all but one are discarded.
+    fn get_or_insert<CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>>(
+        &self,
+        proof: &RecursiveProof<E1, E2>,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+        circuit: &CS,
+    ) -> Result<Arc<CachedCircuit<E1, E2>>, SynthesisError> {
+        let key = (
+            e1params.digest(),
+            e2params.digest(),
+            recursive_circuit_shape_digest::<_, _, _, Basic>(
+                e1params,
+                e2params,
+                circuit,
+                proof.payload(),
+            )?,
+        );
+        if let Some(entry) = self.entries.lock().unwrap().get(&key) {
+            record(|m| m.cache_lookup(true));
+            return Ok(entry.clone());
+        }
+        record(|m| m.cache_lookup(false));
+
+        let (circuit1, circuit2) = proof.verifier_circuits(e1params, e2params, circuit);
+        let entry = Arc::new(CachedCircuit {
+            input_map: compute_input_map::<_, _, Basic>(&circuit1)?,
+            s1: e1params.compute_s::<_, Basic>(&circuit1)?,
+            s2: e2params.compute_s::<_, Basic>(&circuit2)?,
+        });
+
+        Ok(self
+            .entries
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert(entry)
+            .clone())
+    }
+}
+
+impl<E1, E2> RecursiveProof<E1, E2>
+where
+    E1: Curve<Base = <E2 as Curve>::Scalar>,
+    E2: Curve<Base = <E1 as Curve>::Scalar>,
+{
+    /// Verifies this proof as `verify` does, reusing the verification
+    /// circuits recorded in `cache` for `circuit` and these params instead of
+    /// synthesizing them.
+    pub fn verify_cached<CS: RecursiveCircuit<E1::Scalar> + RecursiveCircuit<E2::Scalar>>(
+        &self,
+        e1params: &Params<E1>,
+        e2params: &Params<E2>,
+        circuit: &CS,
+        cache: &VerifierCache<E1, E2>,
+    ) -> Result<bool, SynthesisError> {
+        if self.validate(e1params, e2params, circuit).is_err() {
+            return Ok(false);
+        }
+
+        let _timer = StepTimer::start(TimedStep::Verify);
+
+        let cached = cache.get_or_insert(self, e1params, e2params, circuit)?;
+        let (worked, deferred, a, _) = self.verify_proof(e1params, &cached.input_map);
+
+        Ok(worked
+            & self.deferred.verify(e2params.k)
+            & deferred.verify(e1params.k)
+            & a.verify_with_s(e1params, &cached.s1)
+            & self.oldproof2.verify_with_s(e2params, &cached.s2))
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod test {
+    use super::*;
+    use crate::fields::*;
+    use crate::fixtures::{phony_proof, TrivialCircuit};
+    use crate::security::SecurityLevel;
+
+    #[test]
+    fn test_cached_circuits() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+        let proof = phony_proof(&e1params, &e2params, vec![0xab; 4]);
+        let cache = VerifierCache::new();
+        assert!(cache.is_empty());
+
+        let cached = cache
+            .get_or_insert(&proof, &e1params, &e2params, &TrivialCircuit)
+            .unwrap();
+        let again = cache
+            .get_or_insert(&proof, &e1params, &e2params, &TrivialCircuit)
+            .unwrap();
+        assert!(Arc::ptr_eq(&cached, &again));
+        assert_eq!(cache.len(), 1);
+
+        // The cached circuits are those that `verify` synthesizes.
+        let (circuit1, circuit2) = proof.verifier_circuits(&e1params, &e2params, &TrivialCircuit);
+        assert_eq!(
+            cached.input_map,
+            compute_input_map::<_, _, Basic>(&circuit1).unwrap()
+        );
+        let y = Fq::from_u64(5);
+        assert_eq!(
+            cached.s1.sx(y, e1params.n),
+            e1params.compute_sx::<_, Basic>(&circuit1, y).unwrap()
+        );
+        let y = Fp::from_u64(5);
+        assert_eq!(
+            cached.s2.sx(y, e2params.n),
+            e2params.compute_sx::<_, Basic>(&circuit2, y).unwrap()
+        );
+
+        // Other generators and security levels have their own entries.
+        let other: Params<Ec0> = Params::new(4);
+        cache
+            .get_or_insert(&proof, &e1params, &other, &TrivialCircuit)
+            .unwrap();
+        assert_eq!(cache.len(), 2);
+        let other = e1params.clone().with_security_level(SecurityLevel::Bits100);
+        cache
+            .get_or_insert(&proof, &other, &e2params, &TrivialCircuit)
+            .unwrap();
+        assert_eq!(cache.len(), 3);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_proof() {
+        let e1params: Params<Ec1> = Params::new(4);
+        let e2params: Params<Ec0> = Params::new(4);
+        let mut proof = phony_proof(&e1params, &e2params, vec![0xab; 4]);
+        let cache = VerifierCache::new();
+
+        // Proofs that fail `validate` are rejected before anything is
+        // cached.
+        proof.payload.push(0);
+        assert!(!proof
+            .verify_cached(&e1params, &e2params, &TrivialCircuit, &cache)
+            .unwrap());
+        assert!(cache.is_empty());
+    }
+}